@@ -0,0 +1,203 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    make_stop_set, LowerCaseFilter, StandardTokenizer, StopFilter, TokenStream, WhitespaceTokenizer,
+};
+use core::analysis::{DEFAULT_MAX_TOKEN_LENGTH, ENGLISH_STOP_WORDS};
+
+use error::Result;
+
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+
+/// An Analyzer builds `TokenStream`s, which analyze text. It thus represents a
+/// policy for extracting index terms from text.
+///
+/// A typical implementation first builds a tokenizer, which breaks the
+/// stream of characters from the reader into raw tokens, then one or more
+/// token filters may be applied to the output of the tokenizer:
+///
+/// ```rust,ignore
+/// impl Analyzer for MyAnalyzer {
+///     fn token_stream(&self, _field: &str, reader: Box<dyn Read>) -> Result<Box<dyn TokenStream>> {
+///         let tokenizer = Box::new(StandardTokenizer::new(reader));
+///         Ok(Box::new(LowerCaseFilter::new(tokenizer)))
+///     }
+/// }
+/// ```
+///
+/// `IndexWriter` uses the analyzer of its `IndexWriterConfig` for the
+/// tokenized fields that don't provide their own `TokenStream`.
+pub trait Analyzer: Send + Sync {
+    /// Returns a `TokenStream` suitable for `field_name`, tokenizing the
+    /// contents of `reader`.
+    fn token_stream(&self, field_name: &str, reader: Box<dyn Read>)
+        -> Result<Box<dyn TokenStream>>;
+
+    /// Returns a `TokenStream` suitable for `field_name`, tokenizing the
+    /// contents of `text`.
+    fn token_stream_from_str(&self, field_name: &str, text: &str) -> Result<Box<dyn TokenStream>> {
+        self.token_stream(field_name, Box::new(Cursor::new(text.as_bytes().to_vec())))
+    }
+
+    /// Invoked before indexing a `Fieldable` instance if terms have already
+    /// been added to that field. This allows custom analyzers to place an
+    /// automatic position increment gap between `Fieldable` instances using
+    /// the same field name. The default value position increment gap is 0.
+    fn position_increment_gap(&self, _field_name: &str) -> u32 {
+        0
+    }
+
+    /// Just like `position_increment_gap`, except for token offsets instead.
+    /// By default this returns 1. This method is only called if the field
+    /// produced at least one token for indexing.
+    fn offset_gap(&self, _field_name: &str) -> usize {
+        1
+    }
+}
+
+/// Splits text at whitespace with `WhitespaceTokenizer`, the tokens are not
+/// normalized.
+#[derive(Debug, Default)]
+pub struct WhitespaceAnalyzer;
+
+impl Analyzer for WhitespaceAnalyzer {
+    fn token_stream(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        Ok(Box::new(WhitespaceTokenizer::new(reader)))
+    }
+}
+
+/// Filters `StandardTokenizer` with `LowerCaseFilter` and `StopFilter`,
+/// using a list of English stop words by default.
+pub struct StandardAnalyzer {
+    stop_words: Arc<HashSet<String>>,
+    max_token_length: usize,
+}
+
+impl Default for StandardAnalyzer {
+    fn default() -> Self {
+        StandardAnalyzer::new(make_stop_set(&ENGLISH_STOP_WORDS))
+    }
+}
+
+impl StandardAnalyzer {
+    /// Builds an analyzer with the given stop words, which must be lower
+    /// cased.
+    pub fn new(stop_words: HashSet<String>) -> StandardAnalyzer {
+        StandardAnalyzer {
+            stop_words: Arc::new(stop_words),
+            max_token_length: DEFAULT_MAX_TOKEN_LENGTH,
+        }
+    }
+
+    /// Set the max allowed token length, tokens longer than this are split
+    /// by the `StandardTokenizer`.
+    pub fn set_max_token_length(&mut self, length: usize) {
+        self.max_token_length = length;
+    }
+
+    pub fn max_token_length(&self) -> usize {
+        self.max_token_length
+    }
+}
+
+impl Analyzer for StandardAnalyzer {
+    fn token_stream(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let mut tokenizer = StandardTokenizer::new(reader);
+        tokenizer.set_max_token_length(self.max_token_length)?;
+        let stream = LowerCaseFilter::new(Box::new(tokenizer));
+        Ok(Box::new(StopFilter::new(
+            Box::new(stream),
+            Arc::clone(&self.stop_words),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::{ASCIIFoldingFilter, LengthFilter};
+
+    // (term, position increment, start offset, end offset)
+    fn consume(mut stream: Box<dyn TokenStream>) -> (Vec<(String, u32, usize, usize)>, u32) {
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.increment_token().unwrap() {
+            let bytes = stream.term_bytes_attribute().get_bytes_ref();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                stream.position_attribute_mut().get_position(),
+                stream.offset_attribute().start_offset(),
+                stream.offset_attribute().end_offset(),
+            ));
+        }
+        stream.end().unwrap();
+        (tokens, stream.position_attribute_mut().get_position())
+    }
+
+    #[test]
+    fn test_standard_analyzer() {
+        let analyzer = StandardAnalyzer::default();
+        let stream = analyzer
+            .token_stream_from_str("body", "The Quick fox is in the HOUSE of it")
+            .unwrap();
+        let (tokens, final_increment) = consume(stream);
+        assert_eq!(
+            tokens,
+            vec![
+                ("quick".to_string(), 2, 4, 9),
+                ("fox".to_string(), 1, 10, 13),
+                ("house".to_string(), 4, 24, 29),
+            ]
+        );
+        // "of it" are removed at the end of the stream
+        assert_eq!(final_increment, 2);
+    }
+
+    struct FoldingAnalyzer;
+
+    impl Analyzer for FoldingAnalyzer {
+        fn token_stream(
+            &self,
+            _field_name: &str,
+            reader: Box<dyn Read>,
+        ) -> Result<Box<dyn TokenStream>> {
+            let stream = Box::new(StandardTokenizer::new(reader));
+            let stream = Box::new(LowerCaseFilter::new(stream));
+            let stream = Box::new(ASCIIFoldingFilter::new(stream, false));
+            Ok(Box::new(LengthFilter::new(stream, 2, 5)?))
+        }
+    }
+
+    #[test]
+    fn test_filter_chain() {
+        let stream = FoldingAnalyzer
+            .token_stream_from_str("body", "Ça a été Übermäßig CAFÉ")
+            .unwrap();
+        let (tokens, _) = consume(stream);
+        let terms: Vec<_> = tokens.iter().map(|t| t.0.as_str()).collect();
+        assert_eq!(terms, vec!["ca", "ete", "cafe"]);
+        assert_eq!(tokens[1].1, 2);
+        assert_eq!(tokens[2].1, 2);
+    }
+}
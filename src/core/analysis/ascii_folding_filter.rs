@@ -0,0 +1,839 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::str;
+
+/// This class converts alphabetic, numeric, and symbolic Unicode characters
+/// which are not in the first 127 ASCII characters (the "Basic Latin" Unicode
+/// block) into their ASCII equivalents, if one exists.
+///
+/// Characters from the following Unicode blocks are converted:
+///
+/// * Latin-1 Supplement, Latin Extended-A, Latin Extended-B
+/// * Latin Extended Additional, Latin Extended-C, Latin Extended-D
+/// * Alphabetic Presentation Forms (ligatures)
+/// * General Punctuation, Superscripts and Subscripts
+/// * Halfwidth and Fullwidth Forms (the ASCII variants)
+///
+/// If `preserve_original` is true, the original token is also emitted, at the
+/// same position, after the folded one.
+#[derive(Debug)]
+pub struct ASCIIFoldingFilter {
+    input: Box<dyn TokenStream>,
+    term_attr: CharTermAttribute,
+    preserve_original: bool,
+    // the original token, to emit on the next call
+    pending_original: Option<Vec<u8>>,
+}
+
+impl ASCIIFoldingFilter {
+    pub fn new(input: Box<dyn TokenStream>, preserve_original: bool) -> Self {
+        ASCIIFoldingFilter {
+            input,
+            term_attr: CharTermAttribute::new(),
+            preserve_original,
+            pending_original: None,
+        }
+    }
+
+    pub fn preserve_original(&self) -> bool {
+        self.preserve_original
+    }
+}
+
+impl TokenStream for ASCIIFoldingFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if let Some(original) = self.pending_original.take() {
+            self.term_attr.copy_buffer(&original);
+            self.input.position_attribute_mut().set_position(0);
+            return Ok(true);
+        }
+
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let term = bytes.bytes();
+        self.term_attr.set_empty();
+        if term.is_ascii() {
+            self.term_attr.copy_buffer(term);
+        } else {
+            let mut folded = String::with_capacity(term.len());
+            fold_to_ascii(str::from_utf8(term)?, &mut folded);
+            self.term_attr.append(&folded);
+            if self.preserve_original && folded.as_bytes() != term {
+                self.pending_original = Some(term.to_vec());
+            }
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.term_attr.set_empty();
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.pending_original = None;
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+/// Converts the characters of `input` into their ASCII equivalents where
+/// possible and appends the result to `output`, chars without an equivalent
+/// are copied unchanged.
+pub fn fold_to_ascii(input: &str, output: &mut String) {
+    for c in input.chars() {
+        if c.is_ascii() {
+            output.push(c);
+            continue;
+        }
+        match c as u32 {
+            // fullwidth ASCII variants
+            0xFF01..=0xFF5E => output.push((c as u32 - 0xFEE0) as u8 as char),
+            _ => match FOLDING.binary_search_by_key(&c, |&(from, _)| from) {
+                Ok(idx) => output.push_str(FOLDING[idx].1),
+                Err(_) => output.push(c),
+            },
+        }
+    }
+}
+
+// sorted mapping of the non ASCII chars to their ASCII equivalent
+const FOLDING: &[(char, &str)] = &[
+    ('\u{a0}', " "),
+    ('«', "\""),
+    ('²', "2"),
+    ('³', "3"),
+    ('·', "."),
+    ('¹', "1"),
+    ('»', "\""),
+    ('À', "A"),
+    ('Á', "A"),
+    ('Â', "A"),
+    ('Ã', "A"),
+    ('Ä', "A"),
+    ('Å', "A"),
+    ('Æ', "AE"),
+    ('Ç', "C"),
+    ('È', "E"),
+    ('É', "E"),
+    ('Ê', "E"),
+    ('Ë', "E"),
+    ('Ì', "I"),
+    ('Í', "I"),
+    ('Î', "I"),
+    ('Ï', "I"),
+    ('Ð', "D"),
+    ('Ñ', "N"),
+    ('Ò', "O"),
+    ('Ó', "O"),
+    ('Ô', "O"),
+    ('Õ', "O"),
+    ('Ö', "O"),
+    ('×', "x"),
+    ('Ø', "O"),
+    ('Ù', "U"),
+    ('Ú', "U"),
+    ('Û', "U"),
+    ('Ü', "U"),
+    ('Ý', "Y"),
+    ('Þ', "TH"),
+    ('ß', "ss"),
+    ('à', "a"),
+    ('á', "a"),
+    ('â', "a"),
+    ('ã', "a"),
+    ('ä', "a"),
+    ('å', "a"),
+    ('æ', "ae"),
+    ('ç', "c"),
+    ('è', "e"),
+    ('é', "e"),
+    ('ê', "e"),
+    ('ë', "e"),
+    ('ì', "i"),
+    ('í', "i"),
+    ('î', "i"),
+    ('ï', "i"),
+    ('ð', "d"),
+    ('ñ', "n"),
+    ('ò', "o"),
+    ('ó', "o"),
+    ('ô', "o"),
+    ('õ', "o"),
+    ('ö', "o"),
+    ('÷', "/"),
+    ('ø', "o"),
+    ('ù', "u"),
+    ('ú', "u"),
+    ('û', "u"),
+    ('ü', "u"),
+    ('ý', "y"),
+    ('þ', "th"),
+    ('ÿ', "y"),
+    ('Ā', "A"),
+    ('ā', "a"),
+    ('Ă', "A"),
+    ('ă', "a"),
+    ('Ą', "A"),
+    ('ą', "a"),
+    ('Ć', "C"),
+    ('ć', "c"),
+    ('Ĉ', "C"),
+    ('ĉ', "c"),
+    ('Ċ', "C"),
+    ('ċ', "c"),
+    ('Č', "C"),
+    ('č', "c"),
+    ('Ď', "D"),
+    ('ď', "d"),
+    ('Đ', "D"),
+    ('đ', "d"),
+    ('Ē', "E"),
+    ('ē', "e"),
+    ('Ĕ', "E"),
+    ('ĕ', "e"),
+    ('Ė', "E"),
+    ('ė', "e"),
+    ('Ę', "E"),
+    ('ę', "e"),
+    ('Ě', "E"),
+    ('ě', "e"),
+    ('Ĝ', "G"),
+    ('ĝ', "g"),
+    ('Ğ', "G"),
+    ('ğ', "g"),
+    ('Ġ', "G"),
+    ('ġ', "g"),
+    ('Ģ', "G"),
+    ('ģ', "g"),
+    ('Ĥ', "H"),
+    ('ĥ', "h"),
+    ('Ħ', "H"),
+    ('ħ', "h"),
+    ('Ĩ', "I"),
+    ('ĩ', "i"),
+    ('Ī', "I"),
+    ('ī', "i"),
+    ('Ĭ', "I"),
+    ('ĭ', "i"),
+    ('Į', "I"),
+    ('į', "i"),
+    ('İ', "I"),
+    ('ı', "i"),
+    ('Ĳ', "IJ"),
+    ('ĳ', "ij"),
+    ('Ĵ', "J"),
+    ('ĵ', "j"),
+    ('Ķ', "K"),
+    ('ķ', "k"),
+    ('ĸ', "q"),
+    ('Ĺ', "L"),
+    ('ĺ', "l"),
+    ('Ļ', "L"),
+    ('ļ', "l"),
+    ('Ľ', "L"),
+    ('ľ', "l"),
+    ('Ł', "L"),
+    ('ł', "l"),
+    ('Ń', "N"),
+    ('ń', "n"),
+    ('Ņ', "N"),
+    ('ņ', "n"),
+    ('Ň', "N"),
+    ('ň', "n"),
+    ('Ŋ', "N"),
+    ('ŋ', "n"),
+    ('Ō', "O"),
+    ('ō', "o"),
+    ('Ŏ', "O"),
+    ('ŏ', "o"),
+    ('Ő', "O"),
+    ('ő', "o"),
+    ('Œ', "OE"),
+    ('œ', "oe"),
+    ('Ŕ', "R"),
+    ('ŕ', "r"),
+    ('Ŗ', "R"),
+    ('ŗ', "r"),
+    ('Ř', "R"),
+    ('ř', "r"),
+    ('Ś', "S"),
+    ('ś', "s"),
+    ('Ŝ', "S"),
+    ('ŝ', "s"),
+    ('Ş', "S"),
+    ('ş', "s"),
+    ('Š', "S"),
+    ('š', "s"),
+    ('Ţ', "T"),
+    ('ţ', "t"),
+    ('Ť', "T"),
+    ('ť', "t"),
+    ('Ŧ', "T"),
+    ('ŧ', "t"),
+    ('Ũ', "U"),
+    ('ũ', "u"),
+    ('Ū', "U"),
+    ('ū', "u"),
+    ('Ŭ', "U"),
+    ('ŭ', "u"),
+    ('Ů', "U"),
+    ('ů', "u"),
+    ('Ű', "U"),
+    ('ű', "u"),
+    ('Ų', "U"),
+    ('ų', "u"),
+    ('Ŵ', "W"),
+    ('ŵ', "w"),
+    ('Ŷ', "Y"),
+    ('ŷ', "y"),
+    ('Ÿ', "Y"),
+    ('Ź', "Z"),
+    ('ź', "z"),
+    ('Ż', "Z"),
+    ('ż', "z"),
+    ('Ž', "Z"),
+    ('ž', "z"),
+    ('ſ', "s"),
+    ('ƀ', "b"),
+    ('Ɓ', "B"),
+    ('Ƈ', "C"),
+    ('ƈ', "c"),
+    ('Ɗ', "D"),
+    ('Ǝ', "E"),
+    ('Ɛ', "E"),
+    ('Ƒ', "F"),
+    ('ƒ', "f"),
+    ('Ɠ', "G"),
+    ('Ɩ', "I"),
+    ('Ɨ', "I"),
+    ('Ƙ', "K"),
+    ('ƙ', "k"),
+    ('Ɲ', "N"),
+    ('Ơ', "O"),
+    ('ơ', "o"),
+    ('Ƥ', "P"),
+    ('ƥ', "p"),
+    ('Ƭ', "T"),
+    ('ƭ', "t"),
+    ('Ʈ', "T"),
+    ('Ư', "U"),
+    ('ư', "u"),
+    ('Ʋ', "V"),
+    ('Ƴ', "Y"),
+    ('ƴ', "y"),
+    ('Ƶ', "Z"),
+    ('ƶ', "z"),
+    ('Ǆ', "DZ"),
+    ('ǅ', "Dz"),
+    ('ǆ', "dz"),
+    ('Ǉ', "LJ"),
+    ('ǈ', "Lj"),
+    ('ǉ', "lj"),
+    ('Ǌ', "NJ"),
+    ('ǋ', "Nj"),
+    ('ǌ', "nj"),
+    ('Ǎ', "A"),
+    ('ǎ', "a"),
+    ('Ǐ', "I"),
+    ('ǐ', "i"),
+    ('Ǒ', "O"),
+    ('ǒ', "o"),
+    ('Ǔ', "U"),
+    ('ǔ', "u"),
+    ('Ǖ', "U"),
+    ('ǖ', "u"),
+    ('Ǘ', "U"),
+    ('ǘ', "u"),
+    ('Ǚ', "U"),
+    ('ǚ', "u"),
+    ('Ǜ', "U"),
+    ('ǜ', "u"),
+    ('ǝ', "e"),
+    ('Ǟ', "A"),
+    ('ǟ', "a"),
+    ('Ǡ', "A"),
+    ('ǡ', "a"),
+    ('Ǣ', "AE"),
+    ('ǣ', "ae"),
+    ('Ǧ', "G"),
+    ('ǧ', "g"),
+    ('Ǩ', "K"),
+    ('ǩ', "k"),
+    ('Ǫ', "O"),
+    ('ǫ', "o"),
+    ('Ǭ', "O"),
+    ('ǭ', "o"),
+    ('ǰ', "j"),
+    ('Ǳ', "DZ"),
+    ('ǲ', "Dz"),
+    ('ǳ', "dz"),
+    ('Ǵ', "G"),
+    ('ǵ', "g"),
+    ('Ǹ', "N"),
+    ('ǹ', "n"),
+    ('Ǻ', "A"),
+    ('ǻ', "a"),
+    ('Ǽ', "AE"),
+    ('ǽ', "ae"),
+    ('Ǿ', "O"),
+    ('ǿ', "o"),
+    ('Ȁ', "A"),
+    ('ȁ', "a"),
+    ('Ȃ', "A"),
+    ('ȃ', "a"),
+    ('Ȅ', "E"),
+    ('ȅ', "e"),
+    ('Ȇ', "E"),
+    ('ȇ', "e"),
+    ('Ȉ', "I"),
+    ('ȉ', "i"),
+    ('Ȋ', "I"),
+    ('ȋ', "i"),
+    ('Ȍ', "O"),
+    ('ȍ', "o"),
+    ('Ȏ', "O"),
+    ('ȏ', "o"),
+    ('Ȑ', "R"),
+    ('ȑ', "r"),
+    ('Ȓ', "R"),
+    ('ȓ', "r"),
+    ('Ȕ', "U"),
+    ('ȕ', "u"),
+    ('Ȗ', "U"),
+    ('ȗ', "u"),
+    ('Ș', "S"),
+    ('ș', "s"),
+    ('Ț', "T"),
+    ('ț', "t"),
+    ('Ȟ', "H"),
+    ('ȟ', "h"),
+    ('Ȥ', "Z"),
+    ('ȥ', "z"),
+    ('Ȧ', "A"),
+    ('ȧ', "a"),
+    ('Ȩ', "E"),
+    ('ȩ', "e"),
+    ('Ȫ', "O"),
+    ('ȫ', "o"),
+    ('Ȭ', "O"),
+    ('ȭ', "o"),
+    ('Ȯ', "O"),
+    ('ȯ', "o"),
+    ('Ȱ', "O"),
+    ('ȱ', "o"),
+    ('Ȳ', "Y"),
+    ('ȳ', "y"),
+    ('Ȼ', "C"),
+    ('ȼ', "c"),
+    ('Ƀ', "B"),
+    ('Ɇ', "E"),
+    ('ɇ', "e"),
+    ('Ɉ', "J"),
+    ('ɉ', "j"),
+    ('Ɍ', "R"),
+    ('ɍ', "r"),
+    ('Ɏ', "Y"),
+    ('ɏ', "y"),
+    ('ɓ', "b"),
+    ('ɗ', "d"),
+    ('ɛ', "e"),
+    ('ɠ', "g"),
+    ('ɨ', "i"),
+    ('ɲ', "n"),
+    ('ʈ', "t"),
+    ('ʋ', "v"),
+    ('Ḁ', "A"),
+    ('ḁ', "a"),
+    ('Ḃ', "B"),
+    ('ḃ', "b"),
+    ('Ḅ', "B"),
+    ('ḅ', "b"),
+    ('Ḇ', "B"),
+    ('ḇ', "b"),
+    ('Ḉ', "C"),
+    ('ḉ', "c"),
+    ('Ḋ', "D"),
+    ('ḋ', "d"),
+    ('Ḍ', "D"),
+    ('ḍ', "d"),
+    ('Ḏ', "D"),
+    ('ḏ', "d"),
+    ('Ḑ', "D"),
+    ('ḑ', "d"),
+    ('Ḓ', "D"),
+    ('ḓ', "d"),
+    ('Ḕ', "E"),
+    ('ḕ', "e"),
+    ('Ḗ', "E"),
+    ('ḗ', "e"),
+    ('Ḙ', "E"),
+    ('ḙ', "e"),
+    ('Ḛ', "E"),
+    ('ḛ', "e"),
+    ('Ḝ', "E"),
+    ('ḝ', "e"),
+    ('Ḟ', "F"),
+    ('ḟ', "f"),
+    ('Ḡ', "G"),
+    ('ḡ', "g"),
+    ('Ḣ', "H"),
+    ('ḣ', "h"),
+    ('Ḥ', "H"),
+    ('ḥ', "h"),
+    ('Ḧ', "H"),
+    ('ḧ', "h"),
+    ('Ḩ', "H"),
+    ('ḩ', "h"),
+    ('Ḫ', "H"),
+    ('ḫ', "h"),
+    ('Ḭ', "I"),
+    ('ḭ', "i"),
+    ('Ḯ', "I"),
+    ('ḯ', "i"),
+    ('Ḱ', "K"),
+    ('ḱ', "k"),
+    ('Ḳ', "K"),
+    ('ḳ', "k"),
+    ('Ḵ', "K"),
+    ('ḵ', "k"),
+    ('Ḷ', "L"),
+    ('ḷ', "l"),
+    ('Ḹ', "L"),
+    ('ḹ', "l"),
+    ('Ḻ', "L"),
+    ('ḻ', "l"),
+    ('Ḽ', "L"),
+    ('ḽ', "l"),
+    ('Ḿ', "M"),
+    ('ḿ', "m"),
+    ('Ṁ', "M"),
+    ('ṁ', "m"),
+    ('Ṃ', "M"),
+    ('ṃ', "m"),
+    ('Ṅ', "N"),
+    ('ṅ', "n"),
+    ('Ṇ', "N"),
+    ('ṇ', "n"),
+    ('Ṉ', "N"),
+    ('ṉ', "n"),
+    ('Ṋ', "N"),
+    ('ṋ', "n"),
+    ('Ṍ', "O"),
+    ('ṍ', "o"),
+    ('Ṏ', "O"),
+    ('ṏ', "o"),
+    ('Ṑ', "O"),
+    ('ṑ', "o"),
+    ('Ṓ', "O"),
+    ('ṓ', "o"),
+    ('Ṕ', "P"),
+    ('ṕ', "p"),
+    ('Ṗ', "P"),
+    ('ṗ', "p"),
+    ('Ṙ', "R"),
+    ('ṙ', "r"),
+    ('Ṛ', "R"),
+    ('ṛ', "r"),
+    ('Ṝ', "R"),
+    ('ṝ', "r"),
+    ('Ṟ', "R"),
+    ('ṟ', "r"),
+    ('Ṡ', "S"),
+    ('ṡ', "s"),
+    ('Ṣ', "S"),
+    ('ṣ', "s"),
+    ('Ṥ', "S"),
+    ('ṥ', "s"),
+    ('Ṧ', "S"),
+    ('ṧ', "s"),
+    ('Ṩ', "S"),
+    ('ṩ', "s"),
+    ('Ṫ', "T"),
+    ('ṫ', "t"),
+    ('Ṭ', "T"),
+    ('ṭ', "t"),
+    ('Ṯ', "T"),
+    ('ṯ', "t"),
+    ('Ṱ', "T"),
+    ('ṱ', "t"),
+    ('Ṳ', "U"),
+    ('ṳ', "u"),
+    ('Ṵ', "U"),
+    ('ṵ', "u"),
+    ('Ṷ', "U"),
+    ('ṷ', "u"),
+    ('Ṹ', "U"),
+    ('ṹ', "u"),
+    ('Ṻ', "U"),
+    ('ṻ', "u"),
+    ('Ṽ', "V"),
+    ('ṽ', "v"),
+    ('Ṿ', "V"),
+    ('ṿ', "v"),
+    ('Ẁ', "W"),
+    ('ẁ', "w"),
+    ('Ẃ', "W"),
+    ('ẃ', "w"),
+    ('Ẅ', "W"),
+    ('ẅ', "w"),
+    ('Ẇ', "W"),
+    ('ẇ', "w"),
+    ('Ẉ', "W"),
+    ('ẉ', "w"),
+    ('Ẋ', "X"),
+    ('ẋ', "x"),
+    ('Ẍ', "X"),
+    ('ẍ', "x"),
+    ('Ẏ', "Y"),
+    ('ẏ', "y"),
+    ('Ẑ', "Z"),
+    ('ẑ', "z"),
+    ('Ẓ', "Z"),
+    ('ẓ', "z"),
+    ('Ẕ', "Z"),
+    ('ẕ', "z"),
+    ('ẖ', "h"),
+    ('ẗ', "t"),
+    ('ẘ', "w"),
+    ('ẙ', "y"),
+    ('ẛ', "s"),
+    ('ẞ', "SS"),
+    ('Ạ', "A"),
+    ('ạ', "a"),
+    ('Ả', "A"),
+    ('ả', "a"),
+    ('Ấ', "A"),
+    ('ấ', "a"),
+    ('Ầ', "A"),
+    ('ầ', "a"),
+    ('Ẩ', "A"),
+    ('ẩ', "a"),
+    ('Ẫ', "A"),
+    ('ẫ', "a"),
+    ('Ậ', "A"),
+    ('ậ', "a"),
+    ('Ắ', "A"),
+    ('ắ', "a"),
+    ('Ằ', "A"),
+    ('ằ', "a"),
+    ('Ẳ', "A"),
+    ('ẳ', "a"),
+    ('Ẵ', "A"),
+    ('ẵ', "a"),
+    ('Ặ', "A"),
+    ('ặ', "a"),
+    ('Ẹ', "E"),
+    ('ẹ', "e"),
+    ('Ẻ', "E"),
+    ('ẻ', "e"),
+    ('Ẽ', "E"),
+    ('ẽ', "e"),
+    ('Ế', "E"),
+    ('ế', "e"),
+    ('Ề', "E"),
+    ('ề', "e"),
+    ('Ể', "E"),
+    ('ể', "e"),
+    ('Ễ', "E"),
+    ('ễ', "e"),
+    ('Ệ', "E"),
+    ('ệ', "e"),
+    ('Ỉ', "I"),
+    ('ỉ', "i"),
+    ('Ị', "I"),
+    ('ị', "i"),
+    ('Ọ', "O"),
+    ('ọ', "o"),
+    ('Ỏ', "O"),
+    ('ỏ', "o"),
+    ('Ố', "O"),
+    ('ố', "o"),
+    ('Ồ', "O"),
+    ('ồ', "o"),
+    ('Ổ', "O"),
+    ('ổ', "o"),
+    ('Ỗ', "O"),
+    ('ỗ', "o"),
+    ('Ộ', "O"),
+    ('ộ', "o"),
+    ('Ớ', "O"),
+    ('ớ', "o"),
+    ('Ờ', "O"),
+    ('ờ', "o"),
+    ('Ở', "O"),
+    ('ở', "o"),
+    ('Ỡ', "O"),
+    ('ỡ', "o"),
+    ('Ợ', "O"),
+    ('ợ', "o"),
+    ('Ụ', "U"),
+    ('ụ', "u"),
+    ('Ủ', "U"),
+    ('ủ', "u"),
+    ('Ứ', "U"),
+    ('ứ', "u"),
+    ('Ừ', "U"),
+    ('ừ', "u"),
+    ('Ử', "U"),
+    ('ử', "u"),
+    ('Ữ', "U"),
+    ('ữ', "u"),
+    ('Ự', "U"),
+    ('ự', "u"),
+    ('Ỳ', "Y"),
+    ('ỳ', "y"),
+    ('Ỵ', "Y"),
+    ('ỵ', "y"),
+    ('Ỷ', "Y"),
+    ('ỷ', "y"),
+    ('Ỹ', "Y"),
+    ('ỹ', "y"),
+    ('‐', "-"),
+    ('‑', "-"),
+    ('‒', "-"),
+    ('–', "-"),
+    ('—', "-"),
+    ('―', "-"),
+    ('‘', "'"),
+    ('’', "'"),
+    ('‚', "'"),
+    ('‛', "'"),
+    ('“', "\""),
+    ('”', "\""),
+    ('„', "\""),
+    ('‟', "\""),
+    ('•', "*"),
+    ('…', "..."),
+    ('′', "'"),
+    ('″', "\""),
+    ('‹', "<"),
+    ('›', ">"),
+    ('⁰', "0"),
+    ('⁴', "4"),
+    ('⁵', "5"),
+    ('⁶', "6"),
+    ('⁷', "7"),
+    ('⁸', "8"),
+    ('⁹', "9"),
+    ('₀', "0"),
+    ('₁', "1"),
+    ('₂', "2"),
+    ('₃', "3"),
+    ('₄', "4"),
+    ('₅', "5"),
+    ('₆', "6"),
+    ('₇', "7"),
+    ('₈', "8"),
+    ('₉', "9"),
+    ('−', "-"),
+    ('ⱼ', "j"),
+    ('ⱽ', "V"),
+    ('ꟲ', "C"),
+    ('ꟳ', "F"),
+    ('ꟴ', "Q"),
+    ('ﬀ', "ff"),
+    ('ﬁ', "fi"),
+    ('ﬂ', "fl"),
+    ('ﬃ', "ffi"),
+    ('ﬄ', "ffl"),
+    ('ﬅ', "st"),
+    ('ﬆ', "st"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn fold(text: &str) -> String {
+        let mut output = String::new();
+        fold_to_ascii(text, &mut output);
+        output
+    }
+
+    #[test]
+    fn test_fold_to_ascii() {
+        assert!(FOLDING.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(
+            fold("Ŝtŕáñĝé Æsir straße œuvre"),
+            "Strange AEsir strasse oeuvre"
+        );
+        assert_eq!(fold("Ｒｕｃｅｎｅ １２３"), "Rucene 123");
+        assert_eq!(fold("“quoted” – ﬁne"), "\"quoted\" - fine");
+        assert_eq!(fold("中文 ok"), "中文 ok");
+    }
+
+    #[test]
+    fn test_preserve_original() {
+        let input = Box::new(WhitespaceTokenizer::new(Box::new(Cursor::new(
+            "déjà vu".to_string(),
+        ))));
+        let mut filter = ASCIIFoldingFilter::new(input, true);
+        filter.reset().unwrap();
+        let mut tokens = vec![];
+        while filter.increment_token().unwrap() {
+            let bytes = filter.term_bytes_attribute().get_bytes_ref();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                filter.position_attribute_mut().get_position(),
+            ));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                ("deja".to_string(), 1),
+                ("déjà".to_string(), 0),
+                ("vu".to_string(), 1),
+            ]
+        );
+    }
+}
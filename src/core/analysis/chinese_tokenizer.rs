@@ -0,0 +1,344 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    is_cjk, Analyzer, CharTermAttribute, LowerCaseFilter, OffsetAttribute, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream, WordDictionary,
+};
+
+use error::Result;
+
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+/// How `ChineseTokenizer` emits the segmented words.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SegmentMode {
+    /// Only emits the most probable segmentation.
+    Default,
+    /// Also emits the dictionary words of at least two chars found inside
+    /// each segmented word, at the same position, for a better recall.
+    Search,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Segment {
+    start: usize,
+    end: usize,
+    position: u32,
+}
+
+/// A dictionary based Chinese word segmenter.
+///
+/// The input is split into runs of letters and digits, the other chars are
+/// dropped. In each run, every dictionary word starting at each char is
+/// looked up to build a directed acyclic graph of the possible words, and
+/// the segmentation with the maximum probability, the product of the word
+/// frequencies over the total frequency, is found by dynamic programming.
+/// Chars that are not part of any dictionary word become single char words,
+/// except consecutive non CJK chars like Latin letters and digits, which
+/// are joined back into a word.
+///
+/// Offsets are counted in chars.
+pub struct ChineseTokenizer {
+    reader: Option<Box<dyn Read>>,
+    dictionary: Arc<WordDictionary>,
+    mode: SegmentMode,
+    input: Vec<char>,
+    segments: Vec<Segment>,
+    upto: usize,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+}
+
+impl fmt::Debug for ChineseTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChineseTokenizer")
+            .field("mode", &self.mode)
+            .field("upto", &self.upto)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .finish()
+    }
+}
+
+impl ChineseTokenizer {
+    pub fn new(reader: Box<dyn Read>, dictionary: Arc<WordDictionary>, mode: SegmentMode) -> Self {
+        ChineseTokenizer {
+            reader: Some(reader),
+            dictionary,
+            mode,
+            input: Vec::with_capacity(0),
+            segments: Vec::with_capacity(0),
+            upto: 0,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+        }
+    }
+
+    fn segment(&mut self) -> Result<()> {
+        self.segments.clear();
+        let mut start = 0;
+        while start < self.input.len() {
+            if !self.input[start].is_alphanumeric() {
+                start += 1;
+                continue;
+            }
+            let mut end = start + 1;
+            while end < self.input.len() && self.input[end].is_alphanumeric() {
+                end += 1;
+            }
+            self.segment_run(start, end)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    fn segment_run(&mut self, start: usize, end: usize) -> Result<()> {
+        let dag = self.build_dag(start, end)?;
+        let route = best_route(&dag, self.dictionary.total_frequency());
+
+        let mut i = 0;
+        while i < dag.len() {
+            let mut j = route[i];
+            // joins the single non CJK chars, e.g. Latin words
+            if j == i + 1 && !is_cjk(self.input[start + i]) {
+                while j < dag.len() && route[j] == j + 1 && !is_cjk(self.input[start + j]) {
+                    j += 1;
+                }
+            }
+            self.segments.push(Segment {
+                start: start + i,
+                end: start + j,
+                position: 1,
+            });
+            if self.mode == SegmentMode::Search {
+                for (k, words) in dag.iter().enumerate().take(j).skip(i) {
+                    for &(sub_end, _) in words {
+                        if sub_end <= j && sub_end - k >= 2 && (k, sub_end) != (i, j) {
+                            self.segments.push(Segment {
+                                start: start + k,
+                                end: start + sub_end,
+                                position: 0,
+                            });
+                        }
+                    }
+                }
+            }
+            i = j;
+        }
+        Ok(())
+    }
+
+    // For each char of `input[start..end]`, the ends and frequencies of the
+    // dictionary words starting there, relative to `start`. A char that
+    // doesn't start any word is a word by itself.
+    fn build_dag(&self, start: usize, end: usize) -> Result<Vec<Vec<(usize, u64)>>> {
+        let mut dag = Vec::with_capacity(end - start);
+        for i in 0..end - start {
+            let mut words = vec![];
+            self.dictionary
+                .prefix_words(&self.input[start + i..end], |len, frequency| {
+                    words.push((i + len, frequency))
+                })?;
+            if words.is_empty() {
+                words.push((i + 1, 0));
+            }
+            dag.push(words);
+        }
+        Ok(dag)
+    }
+}
+
+// Viterbi over the DAG from the last char: `route[i]` is the end of the word
+// starting at `i` in the most probable segmentation of the chars from `i`.
+// Unknown words count as frequency 1, and ties pick the longest word.
+fn best_route(dag: &[Vec<(usize, u64)>], total_frequency: u64) -> Vec<usize> {
+    let log_total = (total_frequency.max(1) as f64).ln();
+    let mut scores = vec![0f64; dag.len() + 1];
+    let mut route = vec![0; dag.len()];
+    for i in (0..dag.len()).rev() {
+        let mut best = ::std::f64::NEG_INFINITY;
+        for &(end, frequency) in &dag[i] {
+            let score = (frequency.max(1) as f64).ln() - log_total + scores[end];
+            if score >= best {
+                best = score;
+                route[i] = end;
+            }
+        }
+        scores[i] = best;
+    }
+    route
+}
+
+impl TokenStream for ChineseTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.clear_attributes();
+        if self.upto >= self.segments.len() {
+            return Ok(false);
+        }
+        let segment = self.segments[self.upto];
+        self.upto += 1;
+        for c in &self.input[segment.start..segment.end] {
+            self.term_attr.push_char(*c);
+        }
+        self.offset_attr.set_offset(segment.start, segment.end)?;
+        self.position_attr.set_position(segment.position);
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        let final_offset = self.input.len();
+        self.offset_attr.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        if let Some(mut reader) = self.reader.take() {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            self.input = text.chars().collect();
+            self.segment()?;
+        }
+        self.upto = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+/// Filters `ChineseTokenizer` with `LowerCaseFilter`.
+pub struct ChineseAnalyzer {
+    dictionary: Arc<WordDictionary>,
+    mode: SegmentMode,
+}
+
+impl ChineseAnalyzer {
+    pub fn new(dictionary: Arc<WordDictionary>, mode: SegmentMode) -> ChineseAnalyzer {
+        ChineseAnalyzer { dictionary, mode }
+    }
+}
+
+impl Analyzer for ChineseAnalyzer {
+    fn token_stream(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = ChineseTokenizer::new(reader, Arc::clone(&self.dictionary), self.mode);
+        Ok(Box::new(LowerCaseFilter::new(Box::new(tokenizer))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DICTIONARY: &str = "
+        中国 5000 ns
+        中国人 800 n
+        国人 100 n
+        人民 3000 n
+        共和 300 v
+        共和国 1000 n
+        人民共和国 100 nt
+        华人 500 n
+        中华 2000 nz
+        中华人民共和国 50 ns
+        万岁 400 v
+        语言 3000 n
+        编程 1000 v
+        编程语言 500 n
+    ";
+
+    // (term, position increment, start offset, end offset)
+    fn segment(text: &str, mode: SegmentMode) -> Vec<(String, u32, usize, usize)> {
+        let dictionary = WordDictionary::from_reader(DICTIONARY.as_bytes()).unwrap();
+        let analyzer = ChineseAnalyzer::new(Arc::new(dictionary), mode);
+        let mut stream = analyzer.token_stream_from_str("body", text).unwrap();
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.increment_token().unwrap() {
+            let bytes = stream.term_bytes_attribute().get_bytes_ref();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                stream.position_attribute_mut().get_position(),
+                stream.offset_attribute().start_offset(),
+                stream.offset_attribute().end_offset(),
+            ));
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    #[test]
+    fn test_segment() {
+        let terms: Vec<_> = segment("中华人民共和国万岁！Rust编程语言2019", SegmentMode::Default)
+            .into_iter()
+            .map(|t| t.0)
+            .collect();
+        assert_eq!(
+            terms,
+            vec!["中华人民共和国", "万岁", "rust", "编程语言", "2019"]
+        );
+
+        // unknown chars are single words
+        let tokens = segment("我是中国人", SegmentMode::Default);
+        assert_eq!(
+            tokens,
+            vec![
+                ("我".to_string(), 1, 0, 1),
+                ("是".to_string(), 1, 1, 2),
+                ("中国人".to_string(), 1, 2, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_mode() {
+        let tokens = segment("中华人民共和国", SegmentMode::Search);
+        assert_eq!(
+            tokens,
+            vec![
+                ("中华人民共和国".to_string(), 1, 0, 7),
+                ("中华".to_string(), 0, 0, 2),
+                ("华人".to_string(), 0, 1, 3),
+                ("人民".to_string(), 0, 2, 4),
+                ("人民共和国".to_string(), 0, 2, 7),
+                ("共和".to_string(), 0, 4, 6),
+                ("共和国".to_string(), 0, 4, 7),
+            ]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::collections::VecDeque;
+use std::str;
+
+/// bigram flag for Han Ideographs
+pub const CJK_HAN: u32 = 1;
+/// bigram flag for Hiragana
+pub const CJK_HIRAGANA: u32 = 2;
+/// bigram flag for Katakana
+pub const CJK_KATAKANA: u32 = 4;
+/// bigram flag for Hangul
+pub const CJK_HANGUL: u32 = 8;
+/// bigram flag for all scripts
+pub const CJK_ALL: u32 = 0xff;
+
+fn cjk_script(c: char) -> u32 {
+    match c as u32 {
+        0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xF900..=0xFAFF
+        | 0x3005
+        | 0x3007
+        | 0x20000..=0x2FA1F => CJK_HAN,
+        0x3041..=0x309F | 0x1B001 => CJK_HIRAGANA,
+        0x30A0..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F | 0x1B000 => CJK_KATAKANA,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xA960..=0xA97F | 0xAC00..=0xD7FF => CJK_HANGUL,
+        _ => 0,
+    }
+}
+
+/// Returns whether `c` is a Han, Hiragana, Katakana or Hangul char.
+pub(crate) fn is_cjk(c: char) -> bool {
+    cjk_script(c) != 0
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    term: String,
+    position: u32,
+    start_offset: usize,
+    end_offset: usize,
+}
+
+/// Forms bigrams of CJK terms that are generated from `StandardTokenizer`.
+///
+/// The chars of adjacent CJK tokens, in the scripts selected by the flags,
+/// are joined into overlapping bigrams: "中文分词" gives "中文", "文分" and
+/// "分词" at consecutive positions, each one with the offsets of its two
+/// chars. Tokens longer than one char, like the Katakana or Hangul words of
+/// `StandardTokenizer`, are split into chars first. A CJK char without any
+/// adjacent CJK char is emitted as a unigram, and all the other tokens pass
+/// through unchanged.
+///
+/// With `output_unigrams`, every char is also emitted as a unigram, and each
+/// bigram is put at the position of its first char.
+#[derive(Debug)]
+pub struct CJKBigramFilter {
+    input: Box<dyn TokenStream>,
+    flags: u32,
+    output_unigrams: bool,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    // chars of the current run of adjacent CJK tokens
+    run: Vec<Token>,
+    pending: VecDeque<Token>,
+    exhausted: bool,
+}
+
+impl CJKBigramFilter {
+    /// Bigrams all the CJK scripts, without unigrams.
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        Self::with_flags(input, CJK_ALL, false)
+    }
+
+    /// Only bigrams the scripts set in `flags`, a combination of `CJK_HAN`,
+    /// `CJK_HIRAGANA`, `CJK_KATAKANA` and `CJK_HANGUL`.
+    pub fn with_flags(input: Box<dyn TokenStream>, flags: u32, output_unigrams: bool) -> Self {
+        CJKBigramFilter {
+            input,
+            flags,
+            output_unigrams,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            run: vec![],
+            pending: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    fn is_bigram_char(&self, c: char) -> bool {
+        cjk_script(c) & self.flags != 0
+    }
+
+    fn input_token(&mut self) -> Result<Token> {
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let term = str::from_utf8(bytes.bytes())?.to_string();
+        let offset_attr = self.input.offset_attribute();
+        let (start_offset, end_offset) = (offset_attr.start_offset(), offset_attr.end_offset());
+        Ok(Token {
+            term,
+            position: self.input.position_attribute_mut().get_position(),
+            start_offset,
+            end_offset,
+        })
+    }
+
+    // splits a CJK token into chars, when the offsets don't match the chars
+    // (e.g. the token was modified by a previous filter) all the chars get
+    // the offsets of the token
+    fn push_run(&mut self, token: Token) {
+        let len = token.term.chars().count();
+        let exact = token.end_offset - token.start_offset == len;
+        for (i, c) in token.term.chars().enumerate() {
+            let (start_offset, end_offset) = if exact {
+                (token.start_offset + i, token.start_offset + i + 1)
+            } else {
+                (token.start_offset, token.end_offset)
+            };
+            self.run.push(Token {
+                term: c.to_string(),
+                position: if i == 0 { token.position } else { 1 },
+                start_offset,
+                end_offset,
+            });
+        }
+    }
+
+    // turns the current run into unigrams and bigrams
+    fn flush_run(&mut self) {
+        let run = &self.run;
+        if run.len() == 1 {
+            self.pending.push_back(run[0].clone());
+        } else {
+            for (i, token) in run.iter().enumerate() {
+                if self.output_unigrams {
+                    self.pending.push_back(token.clone());
+                }
+                if let Some(next) = run.get(i + 1) {
+                    self.pending.push_back(Token {
+                        term: format!("{}{}", token.term, next.term),
+                        position: if self.output_unigrams {
+                            0
+                        } else {
+                            token.position
+                        },
+                        start_offset: token.start_offset,
+                        end_offset: next.end_offset,
+                    });
+                }
+            }
+        }
+        self.run.clear();
+    }
+
+    // reads input tokens until the end of the current run, which fills
+    // `pending`
+    fn refill(&mut self) -> Result<()> {
+        while !self.exhausted && self.pending.is_empty() {
+            if !self.input.increment_token()? {
+                self.exhausted = true;
+                if !self.run.is_empty() {
+                    self.flush_run();
+                }
+                break;
+            }
+            let token = self.input_token()?;
+            let is_cjk =
+                !token.term.is_empty() && token.term.chars().all(|c| self.is_bigram_char(c));
+            if is_cjk {
+                // a removed token or a gap between the tokens breaks the run
+                let adjacent = self.run.last().map_or(false, |last| {
+                    token.position == 1 && last.end_offset == token.start_offset
+                });
+                if !adjacent && !self.run.is_empty() {
+                    self.flush_run();
+                }
+                self.push_run(token);
+            } else {
+                if !self.run.is_empty() {
+                    self.flush_run();
+                }
+                self.pending.push_back(token);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TokenStream for CJKBigramFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.refill()?;
+        if let Some(token) = self.pending.pop_front() {
+            self.clear_attributes();
+            self.term_attr.append(&token.term);
+            self.position_attr.set_position(token.position);
+            self.offset_attr
+                .set_offset(token.start_offset, token.end_offset)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let final_offset = self.input.offset_attribute().end_offset();
+        self.offset_attr.set_offset(final_offset, final_offset)?;
+        let position = self.input.position_attribute_mut().get_position();
+        self.position_attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.run.clear();
+        self.pending.clear();
+        self.exhausted = false;
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::StandardTokenizer;
+    use std::io::Cursor;
+
+    // (term, position increment, start offset, end offset)
+    fn consume(stream: &mut dyn TokenStream) -> Vec<(String, u32, usize, usize)> {
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.increment_token().unwrap() {
+            let bytes = stream.term_bytes_attribute().get_bytes_ref();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                stream.position_attribute_mut().get_position(),
+                stream.offset_attribute().start_offset(),
+                stream.offset_attribute().end_offset(),
+            ));
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    fn filter(text: &str, flags: u32, output_unigrams: bool) -> CJKBigramFilter {
+        let reader = Box::new(Cursor::new(text.to_string()));
+        let tokenizer = Box::new(StandardTokenizer::new(reader));
+        CJKBigramFilter::with_flags(tokenizer, flags, output_unigrams)
+    }
+
+    #[test]
+    fn test_bigrams() {
+        let mut stream = filter("中文分词 in Rust，字 한국어", CJK_ALL, false);
+        assert_eq!(
+            consume(&mut stream),
+            vec![
+                ("中文".to_string(), 1, 0, 2),
+                ("文分".to_string(), 1, 1, 3),
+                ("分词".to_string(), 1, 2, 4),
+                ("in".to_string(), 1, 5, 7),
+                ("Rust".to_string(), 1, 8, 12),
+                ("字".to_string(), 1, 13, 14),
+                ("한국".to_string(), 1, 15, 17),
+                ("국어".to_string(), 1, 16, 18),
+            ]
+        );
+        assert_eq!(stream.offset_attribute().end_offset(), 18);
+
+        // Hangul is not bigrammed
+        let mut stream = filter("한국어", CJK_HAN, false);
+        assert_eq!(consume(&mut stream), vec![("한국어".to_string(), 1, 0, 3)]);
+    }
+
+    #[test]
+    fn test_unigrams() {
+        let mut stream = filter("东京タワー", CJK_ALL, true);
+        let tokens = consume(&mut stream);
+        let terms: Vec<_> = tokens.iter().map(|t| (t.0.as_str(), t.1)).collect();
+        assert_eq!(
+            terms,
+            vec![
+                ("东", 1),
+                ("东京", 0),
+                ("京", 1),
+                ("京タ", 0),
+                ("タ", 1),
+                ("タワ", 0),
+                ("ワ", 1),
+                ("ワー", 0),
+                ("ー", 1),
+            ]
+        );
+        assert_eq!((tokens[3].2, tokens[3].3), (1, 3));
+    }
+}
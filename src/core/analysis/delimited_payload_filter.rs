@@ -0,0 +1,155 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PayloadEncoder, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::str;
+
+/// The default delimiter between a token and its payload.
+pub const DEFAULT_PAYLOAD_DELIMITER: char = '|';
+
+/// Splits each token at the first `delimiter`: the text before it is kept as
+/// the term, and the text after it is converted into the payload of the
+/// token by the `PayloadEncoder`, e.g. `word|0.8` with a `FloatEncoder`.
+///
+/// Tokens without the delimiter are left unchanged and carry no payload.
+#[derive(Debug)]
+pub struct DelimitedPayloadTokenFilter {
+    input: Box<dyn TokenStream>,
+    delimiter: char,
+    encoder: Box<dyn PayloadEncoder>,
+    term_attr: CharTermAttribute,
+    payload_attr: PayloadAttribute,
+}
+
+impl DelimitedPayloadTokenFilter {
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        delimiter: char,
+        encoder: Box<dyn PayloadEncoder>,
+    ) -> Self {
+        DelimitedPayloadTokenFilter {
+            input,
+            delimiter,
+            encoder,
+            term_attr: CharTermAttribute::new(),
+            payload_attr: PayloadAttribute::new(Vec::with_capacity(0)),
+        }
+    }
+}
+
+impl TokenStream for DelimitedPayloadTokenFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let term = str::from_utf8(bytes.bytes())?;
+        self.term_attr.set_empty();
+        if let Some(idx) = term.find(self.delimiter) {
+            self.term_attr.append(&term[..idx]);
+            let payload = self
+                .encoder
+                .encode(&term[idx + self.delimiter.len_utf8()..])?;
+            self.payload_attr.set_payload(payload);
+        } else {
+            self.term_attr.append(term);
+            self.payload_attr.clear();
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.term_attr.set_empty();
+        self.payload_attr.end();
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.payload_attr.clear();
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        Some(&mut self.payload_attr)
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        Some(&self.payload_attr)
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::{decode_float, FloatEncoder, WhitespaceTokenizer};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_float_payloads() {
+        let input = Box::new(WhitespaceTokenizer::new(Box::new(Cursor::new(
+            "quick|0.8 brown fox|2".to_string(),
+        ))));
+        let mut filter = DelimitedPayloadTokenFilter::new(
+            input,
+            DEFAULT_PAYLOAD_DELIMITER,
+            Box::new(FloatEncoder),
+        );
+        filter.reset().unwrap();
+        let mut tokens = vec![];
+        while filter.increment_token().unwrap() {
+            let bytes = filter.term_bytes_attribute().get_bytes_ref();
+            let term = String::from_utf8(bytes.bytes().to_vec()).unwrap();
+            let payload = filter.payload_attribute().unwrap().get_payload();
+            let weight = if payload.is_empty() {
+                None
+            } else {
+                Some(decode_float(payload).unwrap())
+            };
+            tokens.push((term, weight));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                ("quick".to_string(), Some(0.8)),
+                ("brown".to_string(), None),
+                ("fox".to_string(), Some(2.0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::str;
+
+/// Tokenizes the input tokens into n-grams anchored at their start, e.g.
+/// "apple" with `min_gram` 1 and `max_gram` 3 gives "a", "ap" and "app".
+///
+/// All the grams of a token keep the offsets of the token, the first one
+/// takes the position increment of the token and the others 0. Tokens
+/// shorter than `min_gram` are removed unless `preserve_original` is set,
+/// in which case tokens shorter than `min_gram` or longer than `max_gram`
+/// are also emitted as is.
+#[derive(Debug)]
+pub struct EdgeNGramTokenFilter {
+    input: Box<dyn TokenStream>,
+    min_gram: usize,
+    max_gram: usize,
+    preserve_original: bool,
+    term_attr: CharTermAttribute,
+    // chars of the current token, and the size of the next gram
+    cur_term: Vec<char>,
+    cur_gram_size: usize,
+    emit_original: bool,
+    skipped_positions: u32,
+}
+
+impl EdgeNGramTokenFilter {
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        min_gram: usize,
+        max_gram: usize,
+        preserve_original: bool,
+    ) -> Result<Self> {
+        if min_gram < 1 {
+            bail!(IllegalArgument("min_gram must be greater than zero".into()));
+        }
+        if min_gram > max_gram {
+            bail!(IllegalArgument(
+                "min_gram must not be greater than max_gram".into()
+            ));
+        }
+        Ok(EdgeNGramTokenFilter {
+            input,
+            min_gram,
+            max_gram,
+            preserve_original,
+            term_attr: CharTermAttribute::new(),
+            cur_term: Vec::new(),
+            cur_gram_size: 0,
+            emit_original: false,
+            skipped_positions: 0,
+        })
+    }
+
+    fn max_gram_size(&self) -> usize {
+        self.max_gram.min(self.cur_term.len())
+    }
+
+    fn set_term(&mut self, len: usize) {
+        self.term_attr.set_empty();
+        for c in &self.cur_term[..len] {
+            self.term_attr.push_char(*c);
+        }
+    }
+}
+
+impl TokenStream for EdgeNGramTokenFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        loop {
+            if self.cur_gram_size > 0 && self.cur_gram_size <= self.max_gram_size() {
+                let len = self.cur_gram_size;
+                self.set_term(len);
+                self.input.position_attribute_mut().set_position(0);
+                self.cur_gram_size += 1;
+                return Ok(true);
+            }
+            if self.emit_original {
+                let len = self.cur_term.len();
+                self.set_term(len);
+                self.input.position_attribute_mut().set_position(0);
+                self.emit_original = false;
+                return Ok(true);
+            }
+
+            if !self.input.increment_token()? {
+                return Ok(false);
+            }
+            self.cur_term.clear();
+            {
+                let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+                self.cur_term.extend(str::from_utf8(bytes.bytes())?.chars());
+            }
+            let len = self.cur_term.len();
+            let position_attr = self.input.position_attribute_mut();
+            if len < self.min_gram && !self.preserve_original {
+                self.skipped_positions += position_attr.get_position();
+                self.cur_gram_size = 0;
+                continue;
+            }
+            let position = position_attr.get_position() + self.skipped_positions;
+            position_attr.set_position(position);
+            self.skipped_positions = 0;
+
+            // the first token of the current input token, either the
+            // shortest gram or the original token
+            if len < self.min_gram {
+                self.cur_gram_size = 0;
+                self.set_term(len);
+            } else {
+                self.cur_gram_size = self.min_gram + 1;
+                self.emit_original = self.preserve_original && len > self.max_gram;
+                let first = self.min_gram;
+                self.set_term(first);
+            }
+            return Ok(true);
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.term_attr.set_empty();
+        self.input.end()?;
+        let position_attr = self.input.position_attribute_mut();
+        let position = position_attr.get_position() + self.skipped_positions;
+        position_attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.cur_term.clear();
+        self.cur_gram_size = 0;
+        self.emit_original = false;
+        self.skipped_positions = 0;
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    // (term, position increment, start offset, end offset)
+    fn consume(stream: &mut dyn TokenStream) -> Vec<(String, u32, usize, usize)> {
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.increment_token().unwrap() {
+            let bytes = stream.term_bytes_attribute().get_bytes_ref();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                stream.position_attribute_mut().get_position(),
+                stream.offset_attribute().start_offset(),
+                stream.offset_attribute().end_offset(),
+            ));
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    fn filter(text: &str, preserve_original: bool) -> EdgeNGramTokenFilter {
+        let reader = Box::new(Cursor::new(text.to_string()));
+        let tokenizer = Box::new(WhitespaceTokenizer::new(reader));
+        EdgeNGramTokenFilter::new(tokenizer, 2, 3, preserve_original).unwrap()
+    }
+
+    #[test]
+    fn test_edge_ngrams() {
+        let mut stream = filter("a apple 中文", false);
+        assert_eq!(
+            consume(&mut stream),
+            vec![
+                ("ap".to_string(), 2, 2, 7),
+                ("app".to_string(), 0, 2, 7),
+                ("中文".to_string(), 1, 8, 10),
+            ]
+        );
+
+        let mut stream = filter("a apple", true);
+        assert_eq!(
+            consume(&mut stream),
+            vec![
+                ("a".to_string(), 1, 0, 1),
+                ("ap".to_string(), 1, 2, 7),
+                ("app".to_string(), 0, 2, 7),
+                ("apple".to_string(), 0, 2, 7),
+            ]
+        );
+    }
+}
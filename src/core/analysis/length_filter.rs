@@ -0,0 +1,116 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    OffsetAttribute, PayloadAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::str;
+
+/// Removes words that are too long or too short from the stream.
+///
+/// The length is counted in chars, tokens whose length is in
+/// `[min, max]` are kept. Like `StopFilter`, the position increments of the
+/// removed tokens are added to the next kept token.
+#[derive(Debug)]
+pub struct LengthFilter {
+    input: Box<dyn TokenStream>,
+    min: usize,
+    max: usize,
+    skipped_positions: u32,
+}
+
+impl LengthFilter {
+    pub fn new(input: Box<dyn TokenStream>, min: usize, max: usize) -> Result<Self> {
+        if min > max {
+            bail!(IllegalArgument(format!(
+                "maximum length must not be less than minimum length, got min: {}, max: {}",
+                min, max
+            )));
+        }
+        Ok(LengthFilter {
+            input,
+            min,
+            max,
+            skipped_positions: 0,
+        })
+    }
+
+    fn accept(&self) -> Result<bool> {
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let len = str::from_utf8(bytes.bytes())?.chars().count();
+        Ok(len >= self.min && len <= self.max)
+    }
+}
+
+impl TokenStream for LengthFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.skipped_positions = 0;
+        while self.input.increment_token()? {
+            let accept = self.accept()?;
+            let position_attr = self.input.position_attribute_mut();
+            if accept {
+                if self.skipped_positions != 0 {
+                    let position = position_attr.get_position() + self.skipped_positions;
+                    position_attr.set_position(position);
+                }
+                return Ok(true);
+            }
+            self.skipped_positions += position_attr.get_position();
+        }
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        let position_attr = self.input.position_attribute_mut();
+        let position = position_attr.get_position() + self.skipped_positions;
+        position_attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.skipped_positions = 0;
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        self.input.term_bytes_attribute_mut()
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        self.input.term_bytes_attribute()
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::str;
+
+/// Normalizes token text to lower case.
+#[derive(Debug)]
+pub struct LowerCaseFilter {
+    input: Box<dyn TokenStream>,
+    term_attr: CharTermAttribute,
+}
+
+impl LowerCaseFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        LowerCaseFilter {
+            input,
+            term_attr: CharTermAttribute::new(),
+        }
+    }
+}
+
+impl TokenStream for LowerCaseFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let term = str::from_utf8(bytes.bytes())?;
+        self.term_attr.set_empty();
+        for c in term.chars() {
+            for lower in c.to_lowercase() {
+                self.term_attr.push_char(lower);
+            }
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.term_attr.set_empty();
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
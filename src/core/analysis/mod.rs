@@ -26,3 +26,71 @@ pub use self::whitespace_tokenizer::*;
 mod token_attributes;
 
 pub use self::token_attributes::*;
+
+mod standard_tokenizer;
+
+pub use self::standard_tokenizer::*;
+
+mod lower_case_filter;
+
+pub use self::lower_case_filter::*;
+
+mod stop_filter;
+
+pub use self::stop_filter::*;
+
+mod ascii_folding_filter;
+
+pub use self::ascii_folding_filter::*;
+
+mod length_filter;
+
+pub use self::length_filter::*;
+
+mod ngram_tokenizer;
+
+pub use self::ngram_tokenizer::*;
+
+mod edge_ngram_filter;
+
+pub use self::edge_ngram_filter::*;
+
+mod cjk_bigram_filter;
+
+pub use self::cjk_bigram_filter::*;
+
+mod word_dictionary;
+
+pub use self::word_dictionary::*;
+
+mod chinese_tokenizer;
+
+pub use self::chinese_tokenizer::*;
+
+mod porter_stemmer;
+
+pub use self::porter_stemmer::*;
+
+mod snowball_filter;
+
+pub use self::snowball_filter::*;
+
+mod synonym_map;
+
+pub use self::synonym_map::*;
+
+mod synonym_graph_filter;
+
+pub use self::synonym_graph_filter::*;
+
+mod payload_encoder;
+
+pub use self::payload_encoder::*;
+
+mod delimited_payload_filter;
+
+pub use self::delimited_payload_filter::*;
+
+mod analyzer;
+
+pub use self::analyzer::*;
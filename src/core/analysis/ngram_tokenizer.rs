@@ -0,0 +1,254 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::fmt;
+use std::io::Read;
+
+pub const DEFAULT_MIN_NGRAM_SIZE: usize = 1;
+pub const DEFAULT_MAX_NGRAM_SIZE: usize = 2;
+
+/// Tokenizes the input into n-grams of the given size(s).
+///
+/// For each start offset, the n-grams are emitted by increasing size: the
+/// first one has a position increment of 1 and the longer ones 0, so the
+/// position of a gram is the index of its first char and phrase queries of
+/// grams work. For example "abcde" with `min_gram` 1 and `max_gram` 3 gives:
+///
+/// | term   | a | ab | abc | b | bc | bcd | c | cd | cde | d | de | e |
+/// |--------|---|----|-----|---|----|-----|---|----|-----|---|----|---|
+/// | pos    | 0 | 0  | 0   | 1 | 1  | 1   | 2 | 2  | 2   | 3 | 3  | 4 |
+/// | offset |0-1|0-2 | 0-3 |1-2|1-3 | 1-4 |2-3|2-4 | 2-5 |3-4|3-5 |4-5|
+///
+/// Grams never span chars rejected by the token char predicate, by default
+/// every char is a token char. Offsets are counted in chars.
+pub struct NGramTokenizer {
+    reader: Option<Box<dyn Read>>,
+    is_token_char: fn(char) -> bool,
+    input: Vec<char>,
+    min_gram: usize,
+    max_gram: usize,
+    // start of the current gram, and its size
+    pos: usize,
+    gram_size: usize,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+}
+
+impl fmt::Debug for NGramTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NGramTokenizer")
+            .field("min_gram", &self.min_gram)
+            .field("max_gram", &self.max_gram)
+            .field("pos", &self.pos)
+            .field("gram_size", &self.gram_size)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .finish()
+    }
+}
+
+impl NGramTokenizer {
+    /// Creates an `NGramTokenizer` with given min and max n-grams.
+    pub fn new(reader: Box<dyn Read>, min_gram: usize, max_gram: usize) -> Result<Self> {
+        Self::with_token_chars(reader, min_gram, max_gram, |_| true)
+    }
+
+    /// Creates an `NGramTokenizer` whose grams only contain the chars
+    /// accepted by `is_token_char`, e.g. `|c| !c.is_whitespace()`.
+    pub fn with_token_chars(
+        reader: Box<dyn Read>,
+        min_gram: usize,
+        max_gram: usize,
+        is_token_char: fn(char) -> bool,
+    ) -> Result<Self> {
+        if min_gram < 1 {
+            bail!(IllegalArgument("min_gram must be greater than zero".into()));
+        }
+        if min_gram > max_gram {
+            bail!(IllegalArgument(
+                "min_gram must not be greater than max_gram".into()
+            ));
+        }
+        Ok(NGramTokenizer {
+            reader: Some(reader),
+            is_token_char,
+            input: Vec::with_capacity(0),
+            min_gram,
+            max_gram,
+            pos: 0,
+            gram_size: 0,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+        })
+    }
+
+    // number of token chars from `start`, at most `max_gram`
+    fn run_length(&self, start: usize) -> usize {
+        self.input[start..]
+            .iter()
+            .take(self.max_gram)
+            .take_while(|c| (self.is_token_char)(**c))
+            .count()
+    }
+}
+
+impl TokenStream for NGramTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.clear_attributes();
+
+        // the next size at the current start
+        if self.gram_size > 0
+            && self.gram_size < self.max_gram
+            && self.run_length(self.pos) > self.gram_size
+        {
+            self.gram_size += 1;
+            self.position_attr.set_position(0);
+        } else {
+            if self.gram_size > 0 {
+                self.pos += 1;
+            }
+            // the next start with enough token chars for a gram, the
+            // position increments over the skipped non token chars
+            let mut increment = 1;
+            while self.pos < self.input.len() && self.run_length(self.pos) < self.min_gram {
+                if (self.is_token_char)(self.input[self.pos]) {
+                    increment += 1;
+                }
+                self.pos += 1;
+            }
+            if self.pos >= self.input.len() {
+                return Ok(false);
+            }
+            self.gram_size = self.min_gram;
+            self.position_attr.set_position(increment);
+        }
+
+        for c in &self.input[self.pos..self.pos + self.gram_size] {
+            self.term_attr.push_char(*c);
+        }
+        self.offset_attr
+            .set_offset(self.pos, self.pos + self.gram_size)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        let final_offset = self.input.len();
+        self.offset_attr.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        if let Some(mut reader) = self.reader.take() {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            self.input = text.chars().collect();
+        }
+        self.pos = 0;
+        self.gram_size = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // (term, position increment, start offset, end offset)
+    fn consume(tokenizer: &mut NGramTokenizer) -> Vec<(String, u32, usize, usize)> {
+        let mut tokens = vec![];
+        tokenizer.reset().unwrap();
+        while tokenizer.increment_token().unwrap() {
+            let bytes = tokenizer.term_bytes_attribute().get_bytes_ref();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                tokenizer.position_attribute_mut().get_position(),
+                tokenizer.offset_attribute().start_offset(),
+                tokenizer.offset_attribute().end_offset(),
+            ));
+        }
+        tokenizer.end().unwrap();
+        tokens
+    }
+
+    fn reader(text: &str) -> Box<dyn Read> {
+        Box::new(Cursor::new(text.to_string()))
+    }
+
+    #[test]
+    fn test_ngrams() {
+        let mut tokenizer = NGramTokenizer::new(reader("中文分词"), 1, 2).unwrap();
+        assert_eq!(
+            consume(&mut tokenizer),
+            vec![
+                ("中".to_string(), 1, 0, 1),
+                ("中文".to_string(), 0, 0, 2),
+                ("文".to_string(), 1, 1, 2),
+                ("文分".to_string(), 0, 1, 3),
+                ("分".to_string(), 1, 2, 3),
+                ("分词".to_string(), 0, 2, 4),
+                ("词".to_string(), 1, 3, 4),
+            ]
+        );
+        assert_eq!(tokenizer.offset_attribute().end_offset(), 4);
+
+        let mut tokenizer = NGramTokenizer::new(reader("ab"), 3, 4).unwrap();
+        assert!(consume(&mut tokenizer).is_empty());
+        assert!(NGramTokenizer::new(reader("ab"), 0, 1).is_err());
+        assert!(NGramTokenizer::new(reader("ab"), 3, 2).is_err());
+    }
+
+    #[test]
+    fn test_token_chars() {
+        let mut tokenizer =
+            NGramTokenizer::with_token_chars(reader("ab c  def"), 2, 2, |c| !c.is_whitespace())
+                .unwrap();
+        assert_eq!(
+            consume(&mut tokenizer),
+            vec![
+                ("ab".to_string(), 1, 0, 2),
+                // "b" and "c" can't start a bigram
+                ("de".to_string(), 3, 6, 8),
+                ("ef".to_string(), 1, 7, 9),
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::{ErrorKind, Result};
+
+use std::fmt::Debug;
+
+/// Converts the text of a token into a payload, see `DelimitedPayloadTokenFilter`.
+pub trait PayloadEncoder: Debug + Send + Sync {
+    fn encode(&self, text: &str) -> Result<Vec<u8>>;
+}
+
+/// Encodes the text as a 4 bytes float payload, decoded by `decode_float`.
+#[derive(Debug, Default)]
+pub struct FloatEncoder;
+
+impl PayloadEncoder for FloatEncoder {
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let value: f32 = text.trim().parse()?;
+        Ok(encode_float(value))
+    }
+}
+
+/// Encodes the text as a 4 bytes int payload, decoded by `decode_int`.
+#[derive(Debug, Default)]
+pub struct IntegerEncoder;
+
+impl PayloadEncoder for IntegerEncoder {
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let value: i32 = text.trim().parse()?;
+        Ok(encode_int(value))
+    }
+}
+
+/// Stores the UTF-8 bytes of the text as is.
+#[derive(Debug, Default)]
+pub struct IdentityEncoder;
+
+impl PayloadEncoder for IdentityEncoder {
+    fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        Ok(text.as_bytes().to_vec())
+    }
+}
+
+/// Encodes a float as the big endian bytes of its bits.
+pub fn encode_float(value: f32) -> Vec<u8> {
+    encode_int(value.to_bits() as i32)
+}
+
+/// Decodes a payload written by `encode_float`.
+pub fn decode_float(payload: &[u8]) -> Result<f32> {
+    Ok(f32::from_bits(decode_int(payload)? as u32))
+}
+
+/// Encodes an int as 4 big endian bytes.
+pub fn encode_int(value: i32) -> Vec<u8> {
+    vec![
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ]
+}
+
+/// Decodes a payload written by `encode_int`.
+pub fn decode_int(payload: &[u8]) -> Result<i32> {
+    if payload.len() != 4 {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "invalid payload length {}, expected 4",
+            payload.len()
+        )));
+    }
+    Ok((i32::from(payload[0]) << 24)
+        | (i32::from(payload[1]) << 16)
+        | (i32::from(payload[2]) << 8)
+        | i32::from(payload[3]))
+}
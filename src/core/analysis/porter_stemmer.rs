@@ -0,0 +1,405 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::str;
+
+/// Transforms the token stream as per the Porter stemming algorithm.
+///
+/// The input must already be lower cased, so it is usually put after a
+/// `LowerCaseFilter`. See `SnowballFilter` for the revised English stemmer
+/// and the other Snowball stemmers.
+#[derive(Debug)]
+pub struct PorterStemFilter {
+    input: Box<dyn TokenStream>,
+    stemmer: PorterStemmer,
+    term_attr: CharTermAttribute,
+}
+
+impl PorterStemFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        PorterStemFilter {
+            input,
+            stemmer: PorterStemmer::default(),
+            term_attr: CharTermAttribute::new(),
+        }
+    }
+}
+
+impl TokenStream for PorterStemFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let stem = self.stemmer.stem(str::from_utf8(bytes.bytes())?);
+        self.term_attr.set_empty();
+        self.term_attr.append(&stem);
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.term_attr.set_empty();
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+/// The Porter stemming algorithm, a port of the reference implementation by
+/// Martin Porter, see "An algorithm for suffix stripping", Program 14(3),
+/// 1980.
+///
+/// `b[..=k]` is the word being stemmed, `j` is the end of the stem when a
+/// suffix was matched by `ends`, it may be -1 when the whole word matched.
+#[derive(Debug, Default)]
+pub struct PorterStemmer {
+    b: Vec<char>,
+    k: isize,
+    j: isize,
+}
+
+impl PorterStemmer {
+    /// Stems `word`, which must be lower cased.
+    pub fn stem(&mut self, word: &str) -> String {
+        self.b.clear();
+        self.b.extend(word.chars());
+        self.k = self.b.len() as isize - 1;
+        if self.k > 1 {
+            self.step1ab();
+            if self.k > 0 {
+                self.step1c();
+                self.step2();
+                self.step3();
+                self.step4();
+                self.step5();
+            }
+        }
+        self.b[..(self.k + 1) as usize].iter().collect()
+    }
+
+    fn char_at(&self, i: isize) -> char {
+        self.b[i as usize]
+    }
+
+    // whether b[i] is a consonant
+    fn cons(&self, i: isize) -> bool {
+        match self.char_at(i) {
+            'a' | 'e' | 'i' | 'o' | 'u' => false,
+            'y' => i == 0 || !self.cons(i - 1),
+            _ => true,
+        }
+    }
+
+    // the number of consonant sequences between 0 and j: if c is a consonant
+    // sequence and v a vowel sequence, <c>(vc){m}<v> gives m
+    fn m(&self) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+        loop {
+            if i > self.j {
+                return n;
+            }
+            if !self.cons(i) {
+                break;
+            }
+            i += 1;
+        }
+        i += 1;
+        loop {
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if self.cons(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            n += 1;
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if !self.cons(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+
+    // whether 0..=j contains a vowel
+    fn vowel_in_stem(&self) -> bool {
+        (0..=self.j).any(|i| !self.cons(i))
+    }
+
+    // whether j-1, j contain a double consonant
+    fn double_c(&self, j: isize) -> bool {
+        j >= 1 && self.char_at(j) == self.char_at(j - 1) && self.cons(j)
+    }
+
+    // whether i-2, i-1, i has the form consonant - vowel - consonant and
+    // the second c is not w, x or y, e.g. hop but not snow
+    fn cvc(&self, i: isize) -> bool {
+        if i < 2 || !self.cons(i) || self.cons(i - 1) || !self.cons(i - 2) {
+            return false;
+        }
+        match self.char_at(i) {
+            'w' | 'x' | 'y' => false,
+            _ => true,
+        }
+    }
+
+    // whether 0..=k ends with `s`, sets j to the end of the stem if so
+    fn ends(&mut self, s: &str) -> bool {
+        let len = s.chars().count() as isize;
+        if len > self.k + 1 {
+            return false;
+        }
+        let start = (self.k + 1 - len) as usize;
+        if !self.b[start..=self.k as usize]
+            .iter()
+            .cloned()
+            .eq(s.chars())
+        {
+            return false;
+        }
+        self.j = self.k - len;
+        true
+    }
+
+    // sets j+1..=k to `s`, readjusting k
+    fn set_to(&mut self, s: &str) {
+        self.b.truncate((self.j + 1) as usize);
+        self.b.extend(s.chars());
+        self.k = self.b.len() as isize - 1;
+    }
+
+    fn r(&mut self, s: &str) {
+        if self.m() > 0 {
+            self.set_to(s);
+        }
+    }
+
+    // gets rid of plurals and -ed or -ing, e.g.
+    //
+    // caresses -> caress, ponies -> poni, cats -> cat, feed -> feed,
+    // agreed -> agree, plastered -> plaster, motoring -> motor,
+    // hopping -> hop, filing -> file
+    fn step1ab(&mut self) {
+        if self.char_at(self.k) == 's' {
+            if self.ends("sses") {
+                self.k -= 2;
+            } else if self.ends("ies") {
+                self.set_to("i");
+            } else if self.char_at(self.k - 1) != 's' {
+                self.k -= 1;
+            }
+        }
+        if self.ends("eed") {
+            if self.m() > 0 {
+                self.k -= 1;
+            }
+        } else if (self.ends("ed") || self.ends("ing")) && self.vowel_in_stem() {
+            self.k = self.j;
+            if self.ends("at") {
+                self.set_to("ate");
+            } else if self.ends("bl") {
+                self.set_to("ble");
+            } else if self.ends("iz") {
+                self.set_to("ize");
+            } else if self.double_c(self.k) {
+                self.k -= 1;
+                match self.char_at(self.k) {
+                    'l' | 's' | 'z' => self.k += 1,
+                    _ => {}
+                }
+            } else if self.m() == 1 && self.cvc(self.k) {
+                self.set_to("e");
+            }
+        }
+        self.b.truncate((self.k + 1) as usize);
+    }
+
+    // turns terminal y to i when there is another vowel in the stem
+    fn step1c(&mut self) {
+        if self.ends("y") && self.vowel_in_stem() {
+            let k = self.k as usize;
+            self.b[k] = 'i';
+        }
+    }
+
+    // maps double suffices to single ones, e.g. -ization to -ize, when the
+    // stem has m() > 0. Only the first matching suffix is considered, like
+    // the switch on the penultimate char of the reference implementation.
+    fn step2(&mut self) {
+        const RULES: [(&str, &str); 21] = [
+            ("ational", "ate"),
+            ("tional", "tion"),
+            ("enci", "ence"),
+            ("anci", "ance"),
+            ("izer", "ize"),
+            ("bli", "ble"),
+            ("alli", "al"),
+            ("entli", "ent"),
+            ("eli", "e"),
+            ("ousli", "ous"),
+            ("ization", "ize"),
+            ("ation", "ate"),
+            ("ator", "ate"),
+            ("alism", "al"),
+            ("iveness", "ive"),
+            ("fulness", "ful"),
+            ("ousness", "ous"),
+            ("aliti", "al"),
+            ("iviti", "ive"),
+            ("biliti", "ble"),
+            ("logi", "log"),
+        ];
+        for &(suffix, replacement) in RULES.iter() {
+            if self.ends(suffix) {
+                self.r(replacement);
+                return;
+            }
+        }
+    }
+
+    // deals with -ic-, -full, -ness etc.
+    fn step3(&mut self) {
+        const RULES: [(&str, &str); 7] = [
+            ("icate", "ic"),
+            ("ative", ""),
+            ("alize", "al"),
+            ("iciti", "ic"),
+            ("ical", "ic"),
+            ("ful", ""),
+            ("ness", ""),
+        ];
+        for &(suffix, replacement) in RULES.iter() {
+            if self.ends(suffix) {
+                self.r(replacement);
+                return;
+            }
+        }
+    }
+
+    // takes off -ant, -ence etc., in context <c>vcvc<v>
+    fn step4(&mut self) {
+        const SUFFIXES: [&str; 19] = [
+            "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ion",
+            "ou", "ism", "ate", "iti", "ous", "ive", "ize",
+        ];
+        for suffix in SUFFIXES.iter() {
+            if !self.ends(suffix) {
+                continue;
+            }
+            // -ion needs a preceding s or t
+            if *suffix == "ion" && (self.j < 0 || !"st".contains(self.char_at(self.j))) {
+                continue;
+            }
+            if self.m() > 1 {
+                self.k = self.j;
+                self.b.truncate((self.k + 1) as usize);
+            }
+            return;
+        }
+    }
+
+    // removes a final -e if m() > 1, and changes -ll to -l if m() > 1
+    fn step5(&mut self) {
+        self.j = self.k;
+        if self.char_at(self.k) == 'e' {
+            let a = self.m();
+            if a > 1 || (a == 1 && !self.cvc(self.k - 1)) {
+                self.k -= 1;
+            }
+        }
+        if self.char_at(self.k) == 'l' && self.double_c(self.k) && self.m() > 1 {
+            self.k -= 1;
+        }
+        self.b.truncate((self.k + 1) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porter_stemmer() {
+        let mut stemmer = PorterStemmer::default();
+        let cases = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("ties", "ti"),
+            ("cats", "cat"),
+            ("feed", "feed"),
+            ("agreed", "agre"),
+            ("plastered", "plaster"),
+            ("motoring", "motor"),
+            ("sing", "sing"),
+            ("hopping", "hop"),
+            ("falling", "fall"),
+            ("filing", "file"),
+            ("happy", "happi"),
+            ("sky", "sky"),
+            ("relational", "relat"),
+            ("generalizations", "gener"),
+            ("oscillators", "oscil"),
+            ("adoption", "adopt"),
+            ("is", "is"),
+        ];
+        for &(word, stem) in cases.iter() {
+            assert_eq!(stemmer.stem(word), stem, "{}", word);
+        }
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PayloadAttribute, PorterStemmer, PositionAttribute,
+    TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::str;
+
+/// The stemmers of `SnowballFilter`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SnowballLanguage {
+    /// The revised Porter stemmer for English, also known as Porter2.
+    English,
+    /// The original Porter stemmer, see `PorterStemFilter`.
+    Porter,
+}
+
+/// A filter that stems words using a Snowball stemmer.
+///
+/// The input must already be lower cased, so it is usually put after a
+/// `LowerCaseFilter`.
+#[derive(Debug)]
+pub struct SnowballFilter {
+    input: Box<dyn TokenStream>,
+    language: SnowballLanguage,
+    porter: PorterStemmer,
+    term_attr: CharTermAttribute,
+}
+
+impl SnowballFilter {
+    pub fn new(input: Box<dyn TokenStream>, language: SnowballLanguage) -> Self {
+        SnowballFilter {
+            input,
+            language,
+            porter: PorterStemmer::default(),
+            term_attr: CharTermAttribute::new(),
+        }
+    }
+}
+
+impl TokenStream for SnowballFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if !self.input.increment_token()? {
+            return Ok(false);
+        }
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        let term = str::from_utf8(bytes.bytes())?;
+        let stem = match self.language {
+            SnowballLanguage::English => english_stem(term),
+            SnowballLanguage::Porter => self.porter.stem(term),
+        };
+        self.term_attr.set_empty();
+        self.term_attr.append(&stem);
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.term_attr.set_empty();
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+const EXCEPTIONS: [(&str, &str); 18] = [
+    ("skis", "ski"),
+    ("skies", "sky"),
+    ("dying", "die"),
+    ("lying", "lie"),
+    ("tying", "tie"),
+    ("idly", "idl"),
+    ("gently", "gentl"),
+    ("ugly", "ugli"),
+    ("early", "earli"),
+    ("only", "onli"),
+    ("singly", "singl"),
+    ("sky", "sky"),
+    ("news", "news"),
+    ("howe", "howe"),
+    ("atlas", "atlas"),
+    ("cosmos", "cosmos"),
+    ("bias", "bias"),
+    ("andes", "andes"),
+];
+
+// words left as is after step 1a
+const EXCEPTIONS_1A: [&str; 8] = [
+    "inning", "outing", "canning", "herring", "earring", "proceed", "exceed", "succeed",
+];
+
+// a 'Y' is a consonant 'y'
+fn is_vowel(c: char) -> bool {
+    match c {
+        'a' | 'e' | 'i' | 'o' | 'u' | 'y' => true,
+        _ => false,
+    }
+}
+
+fn is_double(w: &[char]) -> bool {
+    let n = w.len();
+    n >= 2
+        && w[n - 1] == w[n - 2]
+        && match w[n - 1] {
+            'b' | 'd' | 'f' | 'g' | 'm' | 'n' | 'p' | 'r' | 't' => true,
+            _ => false,
+        }
+}
+
+fn is_li_ending(c: char) -> bool {
+    match c {
+        'c' | 'd' | 'e' | 'g' | 'h' | 'k' | 'm' | 'n' | 'r' | 't' => true,
+        _ => false,
+    }
+}
+
+// whether `w` ends with a short syllable: a vowel followed by a non-vowel
+// other than w, x or Y and preceded by a non-vowel, or a vowel at the
+// beginning of the word followed by a non-vowel
+fn ends_with_short_syllable(w: &[char]) -> bool {
+    let n = w.len();
+    if n == 2 {
+        is_vowel(w[0]) && !is_vowel(w[1])
+    } else if n >= 3 {
+        !is_vowel(w[n - 3])
+            && is_vowel(w[n - 2])
+            && !is_vowel(w[n - 1])
+            && w[n - 1] != 'w'
+            && w[n - 1] != 'x'
+            && w[n - 1] != 'Y'
+    } else {
+        false
+    }
+}
+
+// the region after the first non-vowel following a vowel in `w[from..]`
+fn region_after(w: &[char], from: usize) -> usize {
+    (from + 1..w.len())
+        .find(|&i| !is_vowel(w[i]) && is_vowel(w[i - 1]))
+        .map_or(w.len(), |i| i + 1)
+}
+
+fn ends_with(w: &[char], suffix: &str) -> bool {
+    let len = suffix.chars().count();
+    len <= w.len() && w[w.len() - len..].iter().cloned().eq(suffix.chars())
+}
+
+// the longest of `suffixes` that ends `w`
+fn longest_suffix<'a>(w: &[char], suffixes: &[&'a str]) -> Option<&'a str> {
+    suffixes
+        .iter()
+        .filter(|s| ends_with(w, s))
+        .max_by_key(|s| s.len())
+        .cloned()
+}
+
+fn replace_suffix(w: &mut Vec<char>, len: usize, replacement: &str) {
+    let new_len = w.len() - len;
+    w.truncate(new_len);
+    w.extend(replacement.chars());
+}
+
+/// The English (Porter2) Snowball stemmer, see
+/// <https://snowballstem.org/algorithms/english/stemmer.html>.
+///
+/// `word` must be lower cased.
+pub fn english_stem(word: &str) -> String {
+    if word.chars().count() <= 2 {
+        return word.to_string();
+    }
+    for &(exception, stem) in EXCEPTIONS.iter() {
+        if word == exception {
+            return stem.to_string();
+        }
+    }
+
+    let mut w: Vec<char> = word.chars().collect();
+    if w[0] == '\'' {
+        w.remove(0);
+    }
+    // consonant y's are marked as 'Y'
+    for i in 0..w.len() {
+        if w[i] == 'y' && (i == 0 || is_vowel(w[i - 1])) {
+            w[i] = 'Y';
+        }
+    }
+
+    let r1 = ["gener", "commun", "arsen"]
+        .iter()
+        .find(|prefix| w.iter().cloned().take(prefix.len()).eq(prefix.chars()))
+        .map_or_else(|| region_after(&w, 0), |prefix| prefix.len());
+    let r2 = region_after(&w, r1);
+
+    step0(&mut w);
+    step1a(&mut w);
+    let stemmed: String = w.iter().collect();
+    if EXCEPTIONS_1A.contains(&stemmed.as_str()) {
+        return stemmed;
+    }
+    step1b(&mut w, r1);
+    step1c(&mut w);
+    step2(&mut w, r1);
+    step3(&mut w, r1, r2);
+    step4(&mut w, r2);
+    step5(&mut w, r1, r2);
+
+    w.iter().map(|&c| if c == 'Y' { 'y' } else { c }).collect()
+}
+
+fn step0(w: &mut Vec<char>) {
+    if let Some(suffix) = longest_suffix(w, &["'", "'s", "'s'"]) {
+        let len = suffix.len();
+        replace_suffix(w, len, "");
+    }
+}
+
+fn step1a(w: &mut Vec<char>) {
+    match longest_suffix(w, &["sses", "ied", "ies", "s", "us", "ss"]) {
+        Some("sses") => replace_suffix(w, 4, "ss"),
+        Some("ied") | Some("ies") => {
+            let replacement = if w.len() > 4 { "i" } else { "ie" };
+            replace_suffix(w, 3, replacement);
+        }
+        Some("s") => {
+            let n = w.len();
+            if w[..n - 2].iter().any(|&c| is_vowel(c)) {
+                w.truncate(n - 1);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn step1b(w: &mut Vec<char>, r1: usize) {
+    let suffix = match longest_suffix(w, &["eed", "eedly", "ed", "edly", "ing", "ingly"]) {
+        Some(suffix) => suffix,
+        None => return,
+    };
+    let stem_len = w.len() - suffix.len();
+    if suffix == "eed" || suffix == "eedly" {
+        if stem_len >= r1 {
+            replace_suffix(w, suffix.len(), "ee");
+        }
+        return;
+    }
+    if !w[..stem_len].iter().any(|&c| is_vowel(c)) {
+        return;
+    }
+    w.truncate(stem_len);
+    if ends_with(w, "at") || ends_with(w, "bl") || ends_with(w, "iz") {
+        w.push('e');
+    } else if is_double(w) {
+        w.pop();
+    } else if ends_with_short_syllable(w) && r1 >= w.len() {
+        w.push('e');
+    }
+}
+
+fn step1c(w: &mut Vec<char>) {
+    let n = w.len();
+    if n > 2 && (w[n - 1] == 'y' || w[n - 1] == 'Y') && !is_vowel(w[n - 2]) {
+        w[n - 1] = 'i';
+    }
+}
+
+fn step2(w: &mut Vec<char>, r1: usize) {
+    const RULES: [(&str, &str); 24] = [
+        ("tional", "tion"),
+        ("enci", "ence"),
+        ("anci", "ance"),
+        ("abli", "able"),
+        ("entli", "ent"),
+        ("izer", "ize"),
+        ("ization", "ize"),
+        ("ational", "ate"),
+        ("ation", "ate"),
+        ("ator", "ate"),
+        ("alism", "al"),
+        ("aliti", "al"),
+        ("alli", "al"),
+        ("fulness", "ful"),
+        ("ousli", "ous"),
+        ("ousness", "ous"),
+        ("iveness", "ive"),
+        ("iviti", "ive"),
+        ("biliti", "ble"),
+        ("bli", "ble"),
+        ("ogi", "og"),
+        ("fulli", "ful"),
+        ("lessli", "less"),
+        ("li", ""),
+    ];
+    let suffixes: Vec<&str> = RULES.iter().map(|r| r.0).collect();
+    let suffix = match longest_suffix(w, &suffixes) {
+        Some(suffix) => suffix,
+        None => return,
+    };
+    let stem_len = w.len() - suffix.len();
+    if stem_len < r1 {
+        return;
+    }
+    let applies = match suffix {
+        "ogi" => stem_len > 0 && w[stem_len - 1] == 'l',
+        "li" => stem_len > 0 && is_li_ending(w[stem_len - 1]),
+        _ => true,
+    };
+    if applies {
+        let replacement = RULES.iter().find(|r| r.0 == suffix).unwrap().1;
+        replace_suffix(w, suffix.len(), replacement);
+    }
+}
+
+fn step3(w: &mut Vec<char>, r1: usize, r2: usize) {
+    const RULES: [(&str, &str); 9] = [
+        ("tional", "tion"),
+        ("ational", "ate"),
+        ("alize", "al"),
+        ("icate", "ic"),
+        ("iciti", "ic"),
+        ("ical", "ic"),
+        ("ful", ""),
+        ("ness", ""),
+        ("ative", ""),
+    ];
+    let suffixes: Vec<&str> = RULES.iter().map(|r| r.0).collect();
+    let suffix = match longest_suffix(w, &suffixes) {
+        Some(suffix) => suffix,
+        None => return,
+    };
+    let stem_len = w.len() - suffix.len();
+    if stem_len < r1 || (suffix == "ative" && stem_len < r2) {
+        return;
+    }
+    let replacement = RULES.iter().find(|r| r.0 == suffix).unwrap().1;
+    replace_suffix(w, suffix.len(), replacement);
+}
+
+fn step4(w: &mut Vec<char>, r2: usize) {
+    const SUFFIXES: [&str; 19] = [
+        "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ism",
+        "ate", "iti", "ous", "ive", "ize", "sion", "tion",
+    ];
+    let suffix = match longest_suffix(w, &SUFFIXES) {
+        Some(suffix) => suffix,
+        None => return,
+    };
+    // -ion is removed when preceded by s or t, which stay
+    let len = if suffix == "sion" || suffix == "tion" {
+        3
+    } else {
+        suffix.len()
+    };
+    if w.len() - len >= r2 {
+        let new_len = w.len() - len;
+        w.truncate(new_len);
+    }
+}
+
+fn step5(w: &mut Vec<char>, r1: usize, r2: usize) {
+    let n = w.len();
+    match w.last() {
+        Some('e') => {
+            if n > r2 || (n > r1 && !ends_with_short_syllable(&w[..n - 1])) {
+                w.pop();
+            }
+        }
+        Some('l') => {
+            if n > r2 && n >= 2 && w[n - 2] == 'l' {
+                w.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_stem() {
+        let cases = [
+            ("consign", "consign"),
+            ("consigned", "consign"),
+            ("consigning", "consign"),
+            ("consignment", "consign"),
+            ("consistency", "consist"),
+            ("consolation", "consol"),
+            ("consolatory", "consolatori"),
+            ("conspicuously", "conspicu"),
+            ("conspiracy", "conspiraci"),
+            ("constable", "constabl"),
+            ("knightly", "knight"),
+            ("knitted", "knit"),
+            ("generously", "generous"),
+            ("generate", "generat"),
+            ("skies", "sky"),
+            ("cried", "cri"),
+            ("ties", "tie"),
+            ("gas", "gas"),
+            ("gaps", "gap"),
+            ("kiwis", "kiwi"),
+            ("running", "run"),
+            ("hoping", "hope"),
+            ("succeeding", "succeed"),
+            ("boy's", "boy"),
+        ];
+        for &(word, stem) in cases.iter() {
+            assert_eq!(english_stem(word), stem, "{}", word);
+        }
+    }
+}
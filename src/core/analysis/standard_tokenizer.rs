@@ -0,0 +1,397 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+use std::fmt;
+use std::io::Read;
+
+/// Default maximum allowed token length, in chars.
+pub const DEFAULT_MAX_TOKEN_LENGTH: usize = 255;
+
+/// Absolute maximum sized token
+pub const MAX_TOKEN_LENGTH_LIMIT: usize = 1024 * 1024;
+
+/// Word break property of a char, as defined by the Unicode Text
+/// Segmentation algorithm (UAX#29), restricted to the classes that matter
+/// for finding words.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum WordBreak {
+    ALetter,
+    Numeric,
+    Katakana,
+    Hiragana,
+    Ideographic,
+    ExtendNumLet,
+    MidLetter,
+    MidNum,
+    MidNumLet,
+    // combining marks and format chars, they never break from the
+    // preceding char
+    Extend,
+    Other,
+}
+
+impl WordBreak {
+    fn of(c: char) -> WordBreak {
+        match c as u32 {
+            0x3005 | 0x3007 | 0x3021..=0x3029 | 0x3038..=0x303B => WordBreak::Ideographic,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FFFF => {
+                WordBreak::Ideographic
+            }
+            0x0300..=0x036F
+            | 0x0483..=0x0489
+            | 0x0591..=0x05BD
+            | 0x0610..=0x061A
+            | 0x064B..=0x065F
+            | 0x1AB0..=0x1AFF
+            | 0x1DC0..=0x1DFF
+            | 0x200C..=0x200D
+            | 0x20D0..=0x20FF
+            | 0x3099..=0x309A
+            | 0xFE00..=0xFE0F
+            | 0xFE20..=0xFE2F
+            | 0xFF9E..=0xFF9F => WordBreak::Extend,
+            0x3041..=0x309F => WordBreak::Hiragana,
+            0x30A0..=0x30FF | 0x31F0..=0x31FF | 0x32D0..=0x32FE | 0xFF66..=0xFF9D => {
+                WordBreak::Katakana
+            }
+            0x005F | 0x203F | 0x2040 | 0x2054 | 0xFE33 | 0xFE34 | 0xFE4D..=0xFE4F | 0xFF3F => {
+                WordBreak::ExtendNumLet
+            }
+            0x003A | 0x00B7 | 0x0387 | 0x05F4 | 0x2027 | 0xFE13 | 0xFE55 | 0xFF1A => {
+                WordBreak::MidLetter
+            }
+            0x002C | 0x003B | 0x037E | 0x0589 | 0x060C | 0x060D | 0x066C | 0x07F8 | 0x2044
+            | 0xFE10 | 0xFE14 | 0xFE50 | 0xFE54 | 0xFF0C | 0xFF1B => WordBreak::MidNum,
+            0x0027 | 0x002E | 0x2018 | 0x2019 | 0x2024 | 0xFE52 | 0xFF07 | 0xFF0E => {
+                WordBreak::MidNumLet
+            }
+            _ if c.is_numeric() => WordBreak::Numeric,
+            _ if c.is_alphabetic() => WordBreak::ALetter,
+            _ => WordBreak::Other,
+        }
+    }
+
+    /// Whether a word can start with a char of this class.
+    fn starts_word(self) -> bool {
+        match self {
+            WordBreak::ALetter
+            | WordBreak::Numeric
+            | WordBreak::Katakana
+            | WordBreak::Hiragana
+            | WordBreak::Ideographic => true,
+            _ => false,
+        }
+    }
+
+    /// Rules WB5 to WB13b: no break between two adjacent chars of these
+    /// classes.
+    fn joins(self, next: WordBreak) -> bool {
+        use self::WordBreak::*;
+        match (self, next) {
+            (ALetter, ALetter) | (ALetter, Numeric) | (Numeric, ALetter) | (Numeric, Numeric) => {
+                true
+            }
+            (Katakana, Katakana) => true,
+            (ALetter, ExtendNumLet)
+            | (Numeric, ExtendNumLet)
+            | (Katakana, ExtendNumLet)
+            | (ExtendNumLet, ExtendNumLet) => true,
+            (ExtendNumLet, ALetter) | (ExtendNumLet, Numeric) | (ExtendNumLet, Katakana) => true,
+            _ => false,
+        }
+    }
+
+    /// Rules WB6/WB7 and WB11/WB12: a single punctuation char between two
+    /// letters or two digits doesn't break the word.
+    fn joins_over(self, mid: WordBreak, next: WordBreak) -> bool {
+        match (self, mid, next) {
+            (WordBreak::ALetter, WordBreak::MidLetter, WordBreak::ALetter)
+            | (WordBreak::ALetter, WordBreak::MidNumLet, WordBreak::ALetter)
+            | (WordBreak::Numeric, WordBreak::MidNum, WordBreak::Numeric)
+            | (WordBreak::Numeric, WordBreak::MidNumLet, WordBreak::Numeric) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A grammar-based tokenizer that implements the word break rules from the
+/// Unicode Text Segmentation algorithm, as specified in
+/// [Unicode Standard Annex #29](http://unicode.org/reports/tr29/).
+///
+/// Words made of letters and digits are kept whole, including inner
+/// apostrophes and periods ("O'Reilly", "U.S.A"), as are numbers with inner
+/// separators ("1,000.5"). Each Han ideograph and each Hiragana char is
+/// emitted as a single token, runs of Katakana as one token. Punctuation,
+/// whitespace and symbols are dropped.
+///
+/// Tokens longer than the max token length are split at that length. Like
+/// `WhitespaceTokenizer`, offsets are counted in chars.
+pub struct StandardTokenizer {
+    reader: Option<Box<dyn Read>>,
+    input: Vec<char>,
+    pos: usize,
+    max_token_length: usize,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+}
+
+impl fmt::Debug for StandardTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StandardTokenizer")
+            .field("pos", &self.pos)
+            .field("max_token_length", &self.max_token_length)
+            .field("term_attr", &self.term_attr)
+            .field("offset_attr", &self.offset_attr)
+            .field("position_attr", &self.position_attr)
+            .finish()
+    }
+}
+
+impl StandardTokenizer {
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        StandardTokenizer {
+            reader: Some(reader),
+            input: Vec::with_capacity(0),
+            pos: 0,
+            max_token_length: DEFAULT_MAX_TOKEN_LENGTH,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+        }
+    }
+
+    /// Set the max allowed token length, longer tokens are split.
+    pub fn set_max_token_length(&mut self, length: usize) -> Result<()> {
+        if length == 0 || length > MAX_TOKEN_LENGTH_LIMIT {
+            bail!(IllegalArgument(format!(
+                "max_token_length must be in [1, {}], got {}",
+                MAX_TOKEN_LENGTH_LIMIT, length
+            )));
+        }
+        self.max_token_length = length;
+        Ok(())
+    }
+
+    pub fn max_token_length(&self) -> usize {
+        self.max_token_length
+    }
+
+    // the class of the char at `idx` and the index after its trailing
+    // `Extend` chars
+    fn class_at(&self, idx: usize) -> Option<(WordBreak, usize)> {
+        if idx >= self.input.len() {
+            return None;
+        }
+        let class = WordBreak::of(self.input[idx]);
+        Some((class, self.skip_extend(idx + 1)))
+    }
+
+    fn skip_extend(&self, mut idx: usize) -> usize {
+        while idx < self.input.len() && WordBreak::of(self.input[idx]) == WordBreak::Extend {
+            idx += 1;
+        }
+        idx
+    }
+
+    // find the end of the word whose first char is of class `class`, with
+    // `next` the index after that char
+    fn word_end(&self, mut class: WordBreak, mut next: usize) -> usize {
+        if class == WordBreak::Ideographic || class == WordBreak::Hiragana {
+            return next;
+        }
+        while let Some((next_class, after)) = self.class_at(next) {
+            if class.joins(next_class) {
+                class = next_class;
+                next = after;
+                continue;
+            }
+            if let Some((third_class, end)) = self.class_at(after) {
+                if class.joins_over(next_class, third_class) {
+                    class = third_class;
+                    next = end;
+                    continue;
+                }
+            }
+            break;
+        }
+        next
+    }
+
+    // a word may start with `ExtendNumLet` chars only if they're followed
+    // by a letter, digit or katakana
+    fn leading_connector(&self, mut idx: usize) -> bool {
+        while let Some((class, next)) = self.class_at(idx) {
+            match class {
+                WordBreak::ExtendNumLet => idx = next,
+                WordBreak::ALetter | WordBreak::Numeric | WordBreak::Katakana => return true,
+                _ => return false,
+            }
+        }
+        false
+    }
+}
+
+impl TokenStream for StandardTokenizer {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.clear_attributes();
+
+        while let Some((class, next)) = self.class_at(self.pos) {
+            let starts_word = class.starts_word()
+                || (class == WordBreak::ExtendNumLet && self.leading_connector(next));
+            if !starts_word {
+                self.pos = next;
+                continue;
+            }
+
+            let start = self.pos;
+            let mut end = self.word_end(class, next);
+            if end - start > self.max_token_length {
+                end = start + self.max_token_length;
+            }
+            for c in &self.input[start..end] {
+                self.term_attr.push_char(*c);
+            }
+            self.offset_attr.set_offset(start, end)?;
+            self.pos = end;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_attributes();
+        let final_offset = self.input.len();
+        self.offset_attr.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        if let Some(mut reader) = self.reader.take() {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            self.input = text.chars().collect();
+        }
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn tokens(text: &str, max_token_length: usize) -> Vec<(String, usize, usize)> {
+        let mut tokenizer = StandardTokenizer::new(Box::new(Cursor::new(text.to_string())));
+        tokenizer.set_max_token_length(max_token_length).unwrap();
+        tokenizer.reset().unwrap();
+        let mut result = vec![];
+        while tokenizer.increment_token().unwrap() {
+            let bytes = tokenizer.term_bytes_attribute().get_bytes_ref();
+            result.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                tokenizer.offset_attribute().start_offset(),
+                tokenizer.offset_attribute().end_offset(),
+            ));
+        }
+        tokenizer.end().unwrap();
+        assert_eq!(
+            tokenizer.offset_attribute().end_offset(),
+            text.chars().count()
+        );
+        result
+    }
+
+    fn terms(text: &str) -> Vec<String> {
+        tokens(text, DEFAULT_MAX_TOKEN_LENGTH)
+            .into_iter()
+            .map(|t| t.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            tokens("The quick-brown fox.", 255),
+            vec![
+                ("The".to_string(), 0, 3),
+                ("quick".to_string(), 4, 9),
+                ("brown".to_string(), 10, 15),
+                ("fox".to_string(), 16, 19),
+            ]
+        );
+        assert_eq!(
+            terms("O'Reilly's U.S.A. e-mail: foo_bar@example.com"),
+            vec!["O'Reilly's", "U.S.A", "e", "mail", "foo_bar", "example.com"]
+        );
+        assert_eq!(
+            terms("1,000.50 $ 3.14, v2 _ __init__"),
+            vec!["1,000.50", "3.14", "v2", "__init__"]
+        );
+        assert_eq!(terms("café naïve"), vec!["café", "naïve"]);
+        assert_eq!(terms("...  !!"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_cjk() {
+        assert_eq!(
+            tokens("我爱lucene", 255),
+            vec![
+                ("我".to_string(), 0, 1),
+                ("爱".to_string(), 1, 2),
+                ("lucene".to_string(), 2, 8),
+            ]
+        );
+        assert_eq!(terms("カタカナとひら"), vec!["カタカナ", "と", "ひ", "ら"]);
+        assert_eq!(terms("한국어 텍스트"), vec!["한국어", "텍스트"]);
+    }
+
+    #[test]
+    fn test_max_token_length() {
+        assert_eq!(
+            tokens("abcdefg hi", 3),
+            vec![
+                ("abc".to_string(), 0, 3),
+                ("def".to_string(), 3, 6),
+                ("g".to_string(), 6, 7),
+                ("hi".to_string(), 8, 10),
+            ]
+        );
+    }
+}
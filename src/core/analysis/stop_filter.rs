@@ -0,0 +1,123 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    OffsetAttribute, PayloadAttribute, PositionAttribute, TermToBytesRefAttribute, TokenStream,
+};
+
+use error::Result;
+
+use std::collections::HashSet;
+use std::str;
+use std::sync::Arc;
+
+/// An unmodifiable set containing some common English words that are not
+/// usually useful for searching.
+pub const ENGLISH_STOP_WORDS: [&str; 33] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// Builds a stop word set from an array of words.
+pub fn make_stop_set(stop_words: &[&str]) -> HashSet<String> {
+    stop_words.iter().map(|w| (*w).to_string()).collect()
+}
+
+/// Removes stop words from a token stream.
+///
+/// The match is exact, so the filter is usually put after a
+/// `LowerCaseFilter` with a lower cased stop word set. The position
+/// increment of the removed tokens is added to the next kept token, so
+/// phrase queries don't match across removed stop words.
+#[derive(Debug)]
+pub struct StopFilter {
+    input: Box<dyn TokenStream>,
+    stop_words: Arc<HashSet<String>>,
+    skipped_positions: u32,
+}
+
+impl StopFilter {
+    pub fn new(input: Box<dyn TokenStream>, stop_words: Arc<HashSet<String>>) -> Self {
+        StopFilter {
+            input,
+            stop_words,
+            skipped_positions: 0,
+        }
+    }
+
+    fn accept(&self) -> Result<bool> {
+        let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+        Ok(!self.stop_words.contains(str::from_utf8(bytes.bytes())?))
+    }
+}
+
+impl TokenStream for StopFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        self.skipped_positions = 0;
+        while self.input.increment_token()? {
+            let accept = self.accept()?;
+            let position_attr = self.input.position_attribute_mut();
+            if accept {
+                if self.skipped_positions != 0 {
+                    let position = position_attr.get_position() + self.skipped_positions;
+                    position_attr.set_position(position);
+                }
+                return Ok(true);
+            }
+            self.skipped_positions += position_attr.get_position();
+        }
+        Ok(false)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        let position_attr = self.input.position_attribute_mut();
+        let position = position_attr.get_position() + self.skipped_positions;
+        position_attr.set_position(position);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.skipped_positions = 0;
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        self.input.offset_attribute_mut()
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        self.input.offset_attribute()
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        self.input.position_attribute_mut()
+    }
+
+    fn payload_attribute_mut(&mut self) -> Option<&mut PayloadAttribute> {
+        self.input.payload_attribute_mut()
+    }
+
+    fn payload_attribute(&self) -> Option<&PayloadAttribute> {
+        self.input.payload_attribute()
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        self.input.term_bytes_attribute_mut()
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        self.input.term_bytes_attribute()
+    }
+}
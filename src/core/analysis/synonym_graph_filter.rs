@@ -0,0 +1,311 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    CharTermAttribute, OffsetAttribute, PositionAttribute, SynonymMap, TermToBytesRefAttribute,
+    TokenStream,
+};
+
+use error::Result;
+
+use std::collections::VecDeque;
+use std::str;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+struct Token {
+    term: String,
+    position: u32,
+    position_length: u32,
+    start_offset: usize,
+    end_offset: usize,
+}
+
+/// Applies the rules of a `SynonymMap` to a token stream, producing a token
+/// graph.
+///
+/// The longest input phrase of the map matching the next tokens is replaced
+/// by the output phrases, and by the matched tokens themselves if the rule
+/// keeps them. Each phrase is a path of the graph from the position of the
+/// first matched token to the position following the match, so a multi-word
+/// path gets its own intermediate positions, and the tokens spanning several
+/// positions, e.g. "wtf" for the path "what the fudge", have the matching
+/// position length. A query parser that understands position lengths can
+/// then build phrase queries for each path.
+///
+/// The matched tokens keep their offsets and the synonyms get the offsets of
+/// the whole match, except that start offsets never go backwards since the
+/// `IndexWriter` rejects it. The words of an input phrase must be at
+/// consecutive positions.
+#[derive(Debug)]
+pub struct SynonymGraphFilter {
+    input: Box<dyn TokenStream>,
+    map: Arc<SynonymMap>,
+    term_attr: CharTermAttribute,
+    offset_attr: OffsetAttribute,
+    position_attr: PositionAttribute,
+    lookahead: VecDeque<Token>,
+    pending: VecDeque<Token>,
+    // positions to add to the next token, when the last path of a match
+    // ended before its end node
+    gap: u32,
+    exhausted: bool,
+}
+
+impl SynonymGraphFilter {
+    pub fn new(input: Box<dyn TokenStream>, map: Arc<SynonymMap>) -> Self {
+        SynonymGraphFilter {
+            input,
+            map,
+            term_attr: CharTermAttribute::new(),
+            offset_attr: OffsetAttribute::new(),
+            position_attr: PositionAttribute::new(),
+            lookahead: VecDeque::new(),
+            pending: VecDeque::new(),
+            gap: 0,
+            exhausted: false,
+        }
+    }
+
+    fn fill_lookahead(&mut self) -> Result<()> {
+        let max_words = self.map.max_input_words().max(1);
+        while !self.exhausted && self.lookahead.len() < max_words {
+            if !self.input.increment_token()? {
+                self.exhausted = true;
+                break;
+            }
+            let bytes = self.input.term_bytes_attribute().get_bytes_ref();
+            let term = str::from_utf8(bytes.bytes())?.to_string();
+            let offset_attr = self.input.offset_attribute();
+            let (start_offset, end_offset) = (offset_attr.start_offset(), offset_attr.end_offset());
+            self.lookahead.push_back(Token {
+                term,
+                position: self.input.position_attribute_mut().get_position(),
+                position_length: 1,
+                start_offset,
+                end_offset,
+            });
+        }
+        Ok(())
+    }
+
+    // matches the head of the lookahead, and fills `pending` with either the
+    // head token or the graph of the matched rule
+    fn process_head(&mut self) -> Result<()> {
+        // a removed token between two words breaks the phrase
+        let words: Vec<&str> = self
+            .lookahead
+            .iter()
+            .enumerate()
+            .take_while(|(i, token)| *i == 0 || token.position == 1)
+            .map(|(_, token)| token.term.as_str())
+            .collect();
+        let (len, matched) = match self.map.longest_match(&words)? {
+            Some(m) => m,
+            None => {
+                let mut token = self.lookahead.pop_front().unwrap();
+                token.position += self.gap;
+                self.gap = 0;
+                self.pending.push_back(token);
+                return Ok(());
+            }
+        };
+
+        let matched_tokens: Vec<Token> = self.lookahead.drain(..len).collect();
+        let first = &matched_tokens[0];
+        let last = &matched_tokens[len - 1];
+
+        // (from node, to node, token), the end node is unknown until all the
+        // intermediate nodes are numbered, `None` stands for it
+        let mut edges: Vec<(u32, Option<u32>, Token)> = vec![];
+        let mut next_node = 1u32;
+        let mut add_path = |edges: &mut Vec<_>, tokens: Vec<Token>| {
+            let n = tokens.len();
+            let mut from = 0;
+            for (i, token) in tokens.into_iter().enumerate() {
+                let to = if i + 1 == n {
+                    None
+                } else {
+                    next_node += 1;
+                    Some(next_node - 1)
+                };
+                edges.push((from, to, token));
+                from = next_node - 1;
+            }
+        };
+        if matched.keep_orig {
+            add_path(&mut edges, matched_tokens.clone());
+        }
+        for &ord in &matched.ords {
+            let tokens = self
+                .map
+                .output(ord)
+                .iter()
+                .map(|word| Token {
+                    term: word.clone(),
+                    position: 1,
+                    position_length: 1,
+                    start_offset: first.start_offset,
+                    end_offset: last.end_offset,
+                })
+                .collect();
+            add_path(&mut edges, tokens);
+        }
+        let end_node = next_node;
+        // stable, so the tokens leaving a node keep the order of the paths
+        edges.sort_by_key(|edge| edge.0);
+
+        let mut last_node = 0;
+        let mut last_start_offset = first.start_offset;
+        for (i, (from, to, mut token)) in edges.into_iter().enumerate() {
+            token.position = if i == 0 {
+                first.position + self.gap
+            } else {
+                from - last_node
+            };
+            token.position_length = to.unwrap_or(end_node) - from;
+            token.start_offset = token.start_offset.max(last_start_offset);
+            token.end_offset = token.end_offset.max(token.start_offset);
+            last_start_offset = token.start_offset;
+            last_node = from;
+            self.pending.push_back(token);
+        }
+        self.gap = end_node - last_node - 1;
+        Ok(())
+    }
+}
+
+impl TokenStream for SynonymGraphFilter {
+    fn increment_token(&mut self) -> Result<bool> {
+        if self.pending.is_empty() {
+            self.fill_lookahead()?;
+            if self.lookahead.is_empty() {
+                return Ok(false);
+            }
+            self.process_head()?;
+        }
+        let token = self.pending.pop_front().unwrap();
+        self.clear_attributes();
+        self.term_attr.append(&token.term);
+        self.position_attr.set_position(token.position);
+        self.position_attr
+            .set_position_length(token.position_length);
+        self.offset_attr
+            .set_offset(token.start_offset, token.end_offset)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.end_attributes();
+        let final_offset = self.input.offset_attribute().end_offset();
+        self.offset_attr.set_offset(final_offset, final_offset)?;
+        let position = self.input.position_attribute_mut().get_position();
+        self.position_attr.set_position(position + self.gap);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.lookahead.clear();
+        self.pending.clear();
+        self.gap = 0;
+        self.exhausted = false;
+        self.input.reset()
+    }
+
+    fn offset_attribute_mut(&mut self) -> &mut OffsetAttribute {
+        &mut self.offset_attr
+    }
+
+    fn offset_attribute(&self) -> &OffsetAttribute {
+        &self.offset_attr
+    }
+
+    fn position_attribute_mut(&mut self) -> &mut PositionAttribute {
+        &mut self.position_attr
+    }
+
+    fn term_bytes_attribute_mut(&mut self) -> &mut dyn TermToBytesRefAttribute {
+        &mut self.term_attr
+    }
+
+    fn term_bytes_attribute(&self) -> &dyn TermToBytesRefAttribute {
+        &self.term_attr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    // (term, position increment, position length, start offset, end offset)
+    fn synonyms(rules: &str, text: &str) -> Vec<(String, u32, u32, usize, usize)> {
+        let map = SynonymMap::parse_solr(rules.as_bytes(), true, true).unwrap();
+        let reader = Box::new(Cursor::new(text.to_string()));
+        let mut stream =
+            SynonymGraphFilter::new(Box::new(WhitespaceTokenizer::new(reader)), Arc::new(map));
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.increment_token().unwrap() {
+            let bytes = stream.term_bytes_attribute().get_bytes_ref();
+            let position_attr = *stream.position_attribute_mut();
+            tokens.push((
+                String::from_utf8(bytes.bytes().to_vec()).unwrap(),
+                position_attr.get_position(),
+                position_attr.get_position_length(),
+                stream.offset_attribute().start_offset(),
+                stream.offset_attribute().end_offset(),
+            ));
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    #[test]
+    fn test_multi_word_synonyms() {
+        let t = |term: &str, pos, len, start, end| (term.to_string(), pos, len, start, end);
+
+        // the single word input spans the three positions of its synonym
+        assert_eq!(
+            synonyms("wtf, what the fudge", "say wtf now"),
+            vec![
+                t("say", 1, 1, 0, 3),
+                t("wtf", 1, 3, 4, 7),
+                t("what", 0, 1, 4, 7),
+                t("the", 1, 1, 4, 7),
+                t("fudge", 1, 1, 4, 7),
+                t("now", 1, 1, 8, 11),
+            ]
+        );
+
+        // the multi word input is replaced, the next token follows the
+        // longest path
+        assert_eq!(
+            synonyms("what the fudge => wtf", "what the fudge now"),
+            vec![t("wtf", 1, 1, 0, 14), t("now", 1, 1, 15, 18)]
+        );
+
+        // the synonym follows the kept input
+        assert_eq!(
+            synonyms("usa, united states", "the usa"),
+            vec![
+                t("the", 1, 1, 0, 3),
+                t("usa", 1, 2, 4, 7),
+                t("united", 0, 1, 4, 7),
+                t("states", 1, 1, 4, 7),
+            ]
+        );
+    }
+}
@@ -0,0 +1,373 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::fst::{ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, FST};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{
+    ErrorKind::{CorruptIndex, IllegalArgument},
+    Result,
+};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+
+/// Separates the words of a multi-word synonym in the FST inputs.
+pub const WORD_SEPARATOR: u8 = 0;
+
+/// The outputs of the rules matching an input.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SynonymMatch {
+    /// Whether the matched input must be kept along with the synonyms.
+    pub keep_orig: bool,
+    /// The ordinals of the output phrases, see `SynonymMap::output`.
+    pub ords: Vec<usize>,
+}
+
+/// A map of synonym rules, from phrases to phrases.
+///
+/// The input phrases are compiled into an FST whose outputs encode the
+/// `keep_orig` flag and the ordinals of their output phrases. Build it with
+/// a `SynonymMapBuilder`, or from a Solr synonyms file with `parse_solr`.
+pub struct SynonymMap {
+    fst: Option<FST<ByteSequenceOutputFactory>>,
+    outputs: Vec<Vec<String>>,
+    max_input_words: usize,
+    ignore_case: bool,
+}
+
+impl fmt::Debug for SynonymMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SynonymMap")
+            .field("outputs", &self.outputs.len())
+            .field("max_input_words", &self.max_input_words)
+            .field("ignore_case", &self.ignore_case)
+            .finish()
+    }
+}
+
+impl SynonymMap {
+    /// Parses synonyms in the Solr format, one rule per line:
+    ///
+    /// * `i-pod, i pod => ipod`, explicit mapping: the inputs on the left are replaced by all the
+    ///   outputs on the right.
+    /// * `ipod, i-pod, i pod`, equivalent synonyms: with `expand` each one is mapped to all the
+    ///   others and kept, otherwise they are all replaced by the first one.
+    ///
+    /// Empty lines and lines starting with `#` are ignored, and `\` escapes
+    /// the next char, e.g. a `,` in a synonym. The words of multi-word
+    /// synonyms are separated by whitespace.
+    pub fn parse_solr<R: Read>(mut reader: R, expand: bool, ignore_case: bool) -> Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut builder = SynonymMapBuilder::new(ignore_case);
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let sides = split(line, "=>", false);
+            if sides.len() > 2 {
+                bail!(IllegalArgument(format!(
+                    "more than one explicit mapping specified on line {}: '{}'",
+                    i + 1,
+                    line
+                )));
+            }
+            if sides.len() == 2 {
+                let inputs = parse_phrases(&sides[0]);
+                let outputs = parse_phrases(&sides[1]);
+                for input in &inputs {
+                    for output in &outputs {
+                        builder.add(input, output, false)?;
+                    }
+                }
+            } else {
+                let inputs = parse_phrases(line);
+                if expand {
+                    for (j, input) in inputs.iter().enumerate() {
+                        for (k, output) in inputs.iter().enumerate() {
+                            if j != k {
+                                builder.add(input, output, true)?;
+                            }
+                        }
+                    }
+                } else if let Some(first) = inputs.first() {
+                    for input in &inputs {
+                        builder.add(input, first, false)?;
+                    }
+                }
+            }
+        }
+        builder.build()
+    }
+
+    /// The maximum number of words of an input phrase.
+    pub fn max_input_words(&self) -> usize {
+        self.max_input_words
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// The words of the output phrase `ord`.
+    pub fn output(&self, ord: usize) -> &[String] {
+        &self.outputs[ord]
+    }
+
+    /// Finds the longest input phrase that is a prefix of `words`, returns
+    /// its number of words and its outputs.
+    pub fn longest_match(&self, words: &[&str]) -> Result<Option<(usize, SynonymMatch)>> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(None),
+        };
+        let mut reader = fst.bytes_reader();
+        let mut arc = fst.root_arc();
+        let mut output = vec![];
+        let mut best = None;
+        let mut lower = String::new();
+        'words: for (i, word) in words.iter().enumerate() {
+            let word = if self.ignore_case {
+                lower.clear();
+                lower.extend(word.chars().flat_map(char::to_lowercase));
+                lower.as_str()
+            } else {
+                *word
+            };
+            let separator = if i > 0 { Some(WORD_SEPARATOR) } else { None };
+            for b in separator.into_iter().chain(word.bytes()) {
+                match fst.find_target_arc(i32::from(b), &arc, &mut reader)? {
+                    Some(next) => arc = next,
+                    None => break 'words,
+                }
+                if let Some(ref out) = arc.output {
+                    output.extend_from_slice(out.inner());
+                }
+            }
+            if arc.is_final() {
+                let mut bytes = output.clone();
+                if let Some(ref out) = arc.next_final_output {
+                    bytes.extend_from_slice(out.inner());
+                }
+                best = Some((i + 1, bytes));
+            }
+        }
+        match best {
+            Some((len, bytes)) => Ok(Some((len, Self::decode(&bytes)?))),
+            None => Ok(None),
+        }
+    }
+
+    // a u32 header `count << 1 | keep_orig`, then `count` u32 ordinals, all
+    // big endian
+    fn decode(bytes: &[u8]) -> Result<SynonymMatch> {
+        let read = |i: usize| -> u32 {
+            bytes[i * 4..i * 4 + 4]
+                .iter()
+                .fold(0u32, |v, b| v << 8 | u32::from(*b))
+        };
+        if bytes.len() < 4 {
+            bail!(CorruptIndex("invalid synonym output".into()));
+        }
+        let header = read(0);
+        let count = (header >> 1) as usize;
+        if bytes.len() != (count + 1) * 4 {
+            bail!(CorruptIndex("invalid synonym output".into()));
+        }
+        Ok(SynonymMatch {
+            keep_orig: header & 1 != 0,
+            ords: (1..=count).map(|i| read(i) as usize).collect(),
+        })
+    }
+}
+
+// splits `s` on the unescaped occurrences of `separator`, removing the
+// escapes if `unescape`
+fn split(s: &str, separator: &str, unescape: bool) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            if !unescape {
+                current.push(c);
+            }
+            if let Some((_, escaped)) = chars.next() {
+                current.push(escaped);
+            }
+        } else if s[i..].starts_with(separator) {
+            parts.push(current);
+            current = String::new();
+            for _ in 1..separator.chars().count() {
+                chars.next();
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+// the comma separated phrases of one side of a rule, as words
+fn parse_phrases(s: &str) -> Vec<Vec<String>> {
+    split(s, ",", true)
+        .iter()
+        .map(|phrase| {
+            phrase
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+#[derive(Default)]
+struct MapEntry {
+    keep_orig: bool,
+    ords: Vec<u32>,
+}
+
+/// Builds a `SynonymMap`.
+pub struct SynonymMapBuilder {
+    // inputs are keyed by their words joined by `WORD_SEPARATOR`
+    entries: BTreeMap<Vec<u8>, MapEntry>,
+    outputs: Vec<Vec<String>>,
+    output_ords: HashMap<Vec<String>, u32>,
+    max_input_words: usize,
+    ignore_case: bool,
+}
+
+impl SynonymMapBuilder {
+    /// With `ignore_case` the rules are lower cased, and so are the tokens
+    /// when matching them.
+    pub fn new(ignore_case: bool) -> Self {
+        SynonymMapBuilder {
+            entries: BTreeMap::new(),
+            outputs: vec![],
+            output_ords: HashMap::new(),
+            max_input_words: 0,
+            ignore_case,
+        }
+    }
+
+    /// Adds a rule replacing the `input` words by the `output` words, the
+    /// input is also kept if `keep_orig` is true for any rule of that input.
+    pub fn add<S: AsRef<str>>(&mut self, input: &[S], output: &[S], keep_orig: bool) -> Result<()> {
+        if input.is_empty() || output.is_empty() {
+            bail!(IllegalArgument(
+                "synonym input and output must not be empty".into()
+            ));
+        }
+        let normalize = |w: &S| -> String {
+            if self.ignore_case {
+                w.as_ref().to_lowercase()
+            } else {
+                w.as_ref().to_string()
+            }
+        };
+        let input: Vec<String> = input.iter().map(normalize).collect();
+        let output: Vec<String> = output.iter().map(normalize).collect();
+        if input
+            .iter()
+            .chain(output.iter())
+            .any(|w| w.is_empty() || w.contains('\u{0}'))
+        {
+            bail!(IllegalArgument("synonym words must not be empty".into()));
+        }
+
+        let ord = match self.output_ords.get(&output) {
+            Some(ord) => *ord,
+            None => {
+                let ord = self.outputs.len() as u32;
+                self.outputs.push(output.clone());
+                self.output_ords.insert(output, ord);
+                ord
+            }
+        };
+        self.max_input_words = self.max_input_words.max(input.len());
+        let key = input.join("\u{0}").into_bytes();
+        let entry = self.entries.entry(key).or_insert_with(MapEntry::default);
+        entry.keep_orig |= keep_orig;
+        if !entry.ords.contains(&ord) {
+            entry.ords.push(ord);
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<SynonymMap> {
+        let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory::new());
+        builder.init();
+        let mut scratch = IntsRefBuilder::new();
+        for (input, entry) in &self.entries {
+            let header = (entry.ords.len() as u32) << 1 | entry.keep_orig as u32;
+            let mut bytes = Vec::with_capacity((entry.ords.len() + 1) * 4);
+            bytes.extend_from_slice(&header.to_be_bytes());
+            for ord in &entry.ords {
+                bytes.extend_from_slice(&ord.to_be_bytes());
+            }
+            builder.add(
+                to_ints_ref(input, &mut scratch),
+                ByteSequenceOutput::new(bytes),
+            )?;
+        }
+        Ok(SynonymMap {
+            fst: builder.finish()?,
+            outputs: self.outputs,
+            max_input_words: self.max_input_words,
+            ignore_case: self.ignore_case,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_solr() {
+        let rules = "
+            # comment
+            i-pod, i pod => ipod
+            tv, television
+            a\\,b => c
+        ";
+        let map = SynonymMap::parse_solr(rules.as_bytes(), true, true).unwrap();
+        assert_eq!(map.max_input_words(), 2);
+
+        let (len, m) = map.longest_match(&["I", "Pod", "x"]).unwrap().unwrap();
+        assert_eq!(len, 2);
+        assert!(!m.keep_orig);
+        assert_eq!(map.output(m.ords[0]), &["ipod".to_string()]);
+
+        let (len, m) = map.longest_match(&["tv"]).unwrap().unwrap();
+        assert_eq!(len, 1);
+        assert!(m.keep_orig);
+        assert_eq!(map.output(m.ords[0]), &["television".to_string()]);
+
+        let (_, m) = map.longest_match(&["a,b"]).unwrap().unwrap();
+        assert_eq!(map.output(m.ords[0]), &["c".to_string()]);
+        assert!(map.longest_match(&["i", "phone"]).unwrap().is_none());
+
+        let map = SynonymMap::parse_solr("tv, television".as_bytes(), false, false).unwrap();
+        let (_, m) = map.longest_match(&["television"]).unwrap().unwrap();
+        assert!(!m.keep_orig);
+        assert_eq!(map.output(m.ords[0]), &["tv".to_string()]);
+        assert!(SynonymMap::parse_solr("a => b => c".as_bytes(), false, false).is_err());
+    }
+}
@@ -141,9 +141,15 @@ impl OffsetAttribute {
 /// also sets the increment to the number of stop words removed before each
 /// non-stop word.  Then exact phrase queries will only match when the terms
 /// occur with no intervening stop words.
+///
+/// The position length is the number of positions spanned by the token, it
+/// is greater than one for tokens of a token graph, like the multi-word
+/// synonyms of `SynonymGraphFilter`, that span several positions of another
+/// path. The default value is one, it is ignored when indexing.
 #[derive(Debug, Clone, Copy)]
 pub struct PositionAttribute {
     position: u32,
+    position_length: u32,
 }
 
 impl Default for PositionAttribute {
@@ -155,7 +161,10 @@ impl Default for PositionAttribute {
 impl PositionAttribute {
     #[inline]
     pub fn new() -> PositionAttribute {
-        PositionAttribute { position: 1 }
+        PositionAttribute {
+            position: 1,
+            position_length: 1,
+        }
     }
 
     pub fn set_position(&mut self, position: u32) {
@@ -166,12 +175,22 @@ impl PositionAttribute {
         self.position
     }
 
+    pub fn set_position_length(&mut self, position_length: u32) {
+        self.position_length = position_length;
+    }
+
+    pub fn get_position_length(self) -> u32 {
+        self.position_length
+    }
+
     pub fn clear(&mut self) {
-        self.position = 1
+        self.position = 1;
+        self.position_length = 1;
     }
 
     pub fn end(&mut self) {
-        self.position = 0
+        self.position = 0;
+        self.position_length = 1;
     }
 }
 
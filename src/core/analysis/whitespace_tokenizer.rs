@@ -86,6 +86,7 @@ impl WhitespaceTokenizer {
     fn clear_attributes(&mut self) {
         self.term_attr.clear();
         self.offset_attr.clear();
+        self.position_attr.clear();
     }
 
     fn correct_offset(&self, offset: usize) -> usize {
@@ -0,0 +1,189 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::store::io::{DataInput, DataOutput};
+use core::util::fst::{ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, FST};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{
+    ErrorKind::{CorruptIndex, IllegalArgument},
+    Result,
+};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+
+// frequencies are stored as big endian u64 outputs
+const FREQUENCY_BYTES: usize = 8;
+
+/// A dictionary of words and their frequencies, used by `ChineseTokenizer`
+/// to segment text.
+///
+/// The words are stored in an FST keyed by their UTF-8 bytes, the outputs
+/// being the frequencies. A dictionary is usually built once from a text
+/// file with `from_reader`, then written with `save` and later opened with
+/// `load`, e.g. from an `IndexInput` of a memory mapped directory.
+pub struct WordDictionary {
+    fst: Option<FST<ByteSequenceOutputFactory>>,
+    total_frequency: u64,
+}
+
+impl fmt::Debug for WordDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WordDictionary")
+            .field("total_frequency", &self.total_frequency)
+            .finish()
+    }
+}
+
+impl WordDictionary {
+    /// Reads a text dictionary with one word per line, in the format
+    /// `word frequency [tag]`; the optional tag is ignored, as are empty
+    /// lines. When a word is repeated the last frequency is used.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut words = BTreeMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            let word = match parts.next() {
+                Some(word) => word,
+                None => continue,
+            };
+            let frequency = match parts.next().map(str::parse::<u64>) {
+                Some(Ok(frequency)) => frequency,
+                _ => bail!(IllegalArgument(format!(
+                    "invalid dictionary line {}: '{}'",
+                    i + 1,
+                    line
+                ))),
+            };
+            words.insert(word.to_string(), frequency);
+        }
+        Self::build(&words)
+    }
+
+    /// Builds a dictionary from `(word, frequency)` pairs.
+    pub fn from_words<I: IntoIterator<Item = (String, u64)>>(words: I) -> Result<Self> {
+        let words: BTreeMap<String, u64> = words
+            .into_iter()
+            .filter(|(word, _)| !word.is_empty())
+            .collect();
+        Self::build(&words)
+    }
+
+    // `String` sorts by UTF-8 bytes, which is the order the FST needs
+    fn build(words: &BTreeMap<String, u64>) -> Result<Self> {
+        let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory::new());
+        builder.init();
+        let mut scratch = IntsRefBuilder::new();
+        let mut total_frequency = 0u64;
+        for (word, frequency) in words {
+            total_frequency += *frequency;
+            let output = ByteSequenceOutput::new(frequency.to_be_bytes().to_vec());
+            builder.add(to_ints_ref(word.as_bytes(), &mut scratch), output)?;
+        }
+        Ok(WordDictionary {
+            fst: builder.finish()?,
+            total_frequency,
+        })
+    }
+
+    /// Writes this dictionary, it can be read back with `load`.
+    pub fn save(&self, out: &mut impl DataOutput) -> Result<()> {
+        out.write_vlong(self.total_frequency as i64)?;
+        match self.fst {
+            Some(ref fst) => {
+                out.write_byte(1)?;
+                fst.save(out)
+            }
+            None => out.write_byte(0),
+        }
+    }
+
+    /// Reads a dictionary written by `save`.
+    pub fn load<I: DataInput + ?Sized>(input: &mut I) -> Result<Self> {
+        let total_frequency = input.read_vlong()? as u64;
+        let fst = if input.read_byte()? == 1 {
+            Some(FST::from_input(input, ByteSequenceOutputFactory::new())?)
+        } else {
+            None
+        };
+        Ok(WordDictionary {
+            fst,
+            total_frequency,
+        })
+    }
+
+    /// The sum of the frequencies of all the words.
+    pub fn total_frequency(&self) -> u64 {
+        self.total_frequency
+    }
+
+    /// Returns the frequency of `word`, or `None` if it isn't in the
+    /// dictionary.
+    pub fn frequency(&self, word: &str) -> Result<Option<u64>> {
+        match self.fst {
+            Some(ref fst) => match fst.get(word.as_bytes())? {
+                Some(output) => Ok(Some(Self::decode_frequency(output.inner())?)),
+                None => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Calls `f` with the length in chars and the frequency of each word of
+    /// the dictionary that is a prefix of `text`, by increasing length.
+    pub fn prefix_words<F: FnMut(usize, u64)>(&self, text: &[char], mut f: F) -> Result<()> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(()),
+        };
+        let mut reader = fst.bytes_reader();
+        let mut arc = fst.root_arc();
+        let mut output = Vec::with_capacity(FREQUENCY_BYTES);
+        let mut buf = [0u8; 4];
+        for (i, c) in text.iter().enumerate() {
+            for b in c.encode_utf8(&mut buf).bytes() {
+                match fst.find_target_arc(i32::from(b), &arc, &mut reader)? {
+                    Some(next) => arc = next,
+                    None => return Ok(()),
+                }
+                if let Some(ref out) = arc.output {
+                    output.extend_from_slice(out.inner());
+                }
+            }
+            if arc.is_final() {
+                let len = output.len();
+                if let Some(ref out) = arc.next_final_output {
+                    output.extend_from_slice(out.inner());
+                }
+                f(i + 1, Self::decode_frequency(&output)?);
+                output.truncate(len);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_frequency(bytes: &[u8]) -> Result<u64> {
+        if bytes.len() != FREQUENCY_BYTES {
+            bail!(CorruptIndex(format!(
+                "invalid word frequency length: {}",
+                bytes.len()
+            )));
+        }
+        Ok(bytes.iter().fold(0u64, |v, b| v << 8 | u64::from(*b)))
+    }
+}
@@ -260,6 +260,20 @@ impl Default for Lucene62Codec {
     }
 }
 
+impl Lucene62Codec {
+    /// Creates a codec whose postings record the block impacts if
+    /// `write_block_impacts` is true, see
+    /// `Lucene50PostingsFormat::with_block_impacts`.
+    ///
+    /// NOTE: the segments written this way can't be read by Lucene 6.2.1.
+    pub fn with_block_impacts(write_block_impacts: bool) -> Lucene62Codec {
+        Lucene62Codec {
+            postings_format: PerFieldPostingsFormat::new(write_block_impacts),
+            ..Default::default()
+        }
+    }
+}
+
 impl Codec for Lucene62Codec {
     type FieldsProducer = Arc<PerFieldFieldsReader>;
     type PostingFmt = PerFieldPostingsFormat;
@@ -77,6 +77,57 @@ pub trait PostingIterator: DocIterator {
     /// (neither members of the returned BytesRef nor bytes
     /// in the bytes). */
     fn payload(&self) -> Result<Payload>;
+
+    /// Shallow-advances to the block of postings that contains `target` and
+    /// returns the last document of that block, the documents up to which
+    /// `impact()` holds. Unlike `advance()`, this never moves the iterator,
+    /// `target` may even be behind the current document.
+    ///
+    /// Returns `NO_MORE_DOCS` if the postings have no block metadata.
+    fn advance_shallow(&mut self, _target: DocId) -> Result<DocId> {
+        Ok(NO_MORE_DOCS)
+    }
+
+    /// Returns the impact of the block reached by the last call to
+    /// `advance_shallow()`, or `None` if it is unknown.
+    fn impact(&self) -> Option<Impact> {
+        None
+    }
+}
+
+/// The highest term frequency and the most competitive norm of a block of
+/// postings, which bound the scores of its documents.
+///
+/// Norms are compared as unsigned values and the largest one is the most
+/// competitive: `BM25Similarity` and `ClassicSimilarity` encode
+/// `boost / sqrt(length)` in the norms, so this is the norm of the shortest
+/// document. This only holds for the similarities that declare
+/// `Similarity::monotonic_norms` at index time, the postings of the others
+/// record a norm of -1, the largest unsigned value, so that only the freq
+/// bounds the scores.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Impact {
+    pub freq: i32,
+    pub norm: i64,
+}
+
+impl Impact {
+    pub fn new(freq: i32, norm: i64) -> Impact {
+        Impact { freq, norm }
+    }
+
+    /// Merges `other` into this impact, so that it bounds the scores of both.
+    pub fn merge(&mut self, other: &Impact) {
+        self.freq = self.freq.max(other.freq);
+        if other.norm as u64 > self.norm as u64 {
+            self.norm = other.norm;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.freq = 0;
+        self.norm = 0;
+    }
 }
 
 /// a `PostingIterator` that no matching docs are available.
@@ -22,7 +22,9 @@ use std::string::ToString;
 use std::sync::Arc;
 
 use core::codec::field_infos::{FieldInfo, FieldInfoRef};
-use core::codec::postings::blocktree::{BlockTermState, SegmentTermsIterFrame, MAX_LONGS_SIZE};
+use core::codec::postings::blocktree::{
+    BlockTermState, IntersectTermIterator, SegmentTermsIterFrame, MAX_LONGS_SIZE,
+};
 use core::codec::postings::{
     FieldsProducer, Lucene50PostingIterator, Lucene50PostingsReader, Lucene50PostingsReaderRef,
 };
@@ -32,13 +34,14 @@ use core::codec::{Fields, SeekStatus, TermIterator, Terms};
 use core::doc::IndexOptions;
 use core::store::directory::Directory;
 use core::store::io::{ByteArrayDataInput, DataInput, IndexInput};
+use core::util::automaton::{AutomatonTermIterEnum, AutomatonType, CompiledAutomaton};
 use core::util::fst::{
     Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, DirectionalBytesReader,
     FSTBytesReader, OutputFactory, FST,
 };
 use core::util::UnsignedShift;
 use error::{
-    ErrorKind::{CorruptIndex, IllegalState, UnsupportedOperation},
+    ErrorKind::{CorruptIndex, IllegalArgument, IllegalState, UnsupportedOperation},
     Result,
 };
 
@@ -394,7 +397,7 @@ pub struct FieldReader {
     sum_doc_freq: i64,
     doc_count: i32,
     _index_start_fp: i64,
-    _root_block_fp: i64,
+    root_block_fp: i64,
     root_code: Vec<u8>,
     min_term: Vec<u8>,
     max_term: Vec<u8>,
@@ -447,7 +450,7 @@ impl FieldReader {
             sum_doc_freq,
             doc_count,
             _index_start_fp: index_start_fp,
-            _root_block_fp: root_block_fp,
+            root_block_fp,
             min_term,
             max_term,
             longs_size,
@@ -476,6 +479,11 @@ impl FieldReader {
         self.longs_size
     }
 
+    #[inline]
+    pub fn root_block_fp(&self) -> i64 {
+        self.root_block_fp
+    }
+
     #[inline]
     pub fn parent(&self) -> &BlockTreeTermsReader {
         &self.parent
@@ -499,6 +507,32 @@ impl<'a> Terms for FieldReader {
         ))
     }
 
+    fn intersect(
+        &self,
+        compiled: &CompiledAutomaton,
+        start_term: Option<&[u8]>,
+    ) -> Result<AutomatonTermIterEnum<Self::Iterator>> {
+        if compiled.automaton_type != AutomatonType::Normal {
+            bail!(IllegalArgument(
+                "please use CompiledAutomaton::term_iterator instead".into()
+            ));
+        }
+        if self.index.is_none() {
+            bail!(IllegalState("terms index was not loaded".into()));
+        }
+        let iter = IntersectTermIterator::new(
+            self,
+            (*self.terms_in).clone()?,
+            self.postings_reader.clone(),
+            self.field_info.clone(),
+            Arc::clone(compiled.run_automaton.as_ref().unwrap()),
+            start_term,
+        )?;
+        Ok(AutomatonTermIterEnum::Intersect(SegmentTermIterator {
+            iter: SegmentTermIterEnum::Intersect(Box::new(iter)),
+        }))
+    }
+
     fn size(&self) -> Result<i64> {
         Ok(self.num_terms)
     }
@@ -905,7 +939,13 @@ impl ToString for Stats {
 ///
 /// This implementation skips any auto-prefix terms it encounters.
 pub struct SegmentTermIterator {
-    iter: Box<SegmentTermIteratorInner>,
+    iter: SegmentTermIterEnum,
+}
+
+// `Intersect` is only created by `FieldReader::intersect` and can't seek.
+enum SegmentTermIterEnum {
+    Seekable(Box<SegmentTermIteratorInner>),
+    Intersect(Box<IntersectTermIterator>),
 }
 
 impl SegmentTermIterator {
@@ -921,7 +961,9 @@ impl SegmentTermIterator {
             postings_reader,
             field_info,
         ));
-        Self { iter }
+        Self {
+            iter: SegmentTermIterEnum::Seekable(iter),
+        }
     }
 }
 
@@ -931,67 +973,106 @@ impl TermIterator for SegmentTermIterator {
 
     #[inline]
     fn next(&mut self) -> Result<Option<Vec<u8>>> {
-        self.iter.next()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.next(),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.next(),
+        }
     }
 
     #[inline]
     fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
-        self.iter.seek_exact(text)
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.seek_exact(text),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.seek_exact(text),
+        }
     }
 
     #[inline]
     fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
-        self.iter.seek_ceil(text)
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.seek_ceil(text),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.seek_ceil(text),
+        }
     }
 
     #[inline]
     fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
-        self.iter.seek_exact_ord(ord)
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.seek_exact_ord(ord),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.seek_exact_ord(ord),
+        }
     }
 
     #[inline]
     fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
-        self.iter.seek_exact_state(text, state)
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.seek_exact_state(text, state),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.seek_exact_state(text, state),
+        }
     }
 
     #[inline]
     fn term(&self) -> Result<&[u8]> {
-        Ok(self.iter.term())
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref i) => Ok(i.term()),
+            SegmentTermIterEnum::Intersect(ref i) => i.term(),
+        }
     }
 
     #[inline]
     fn ord(&self) -> Result<i64> {
-        self.iter.ord()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref i) => i.ord(),
+            SegmentTermIterEnum::Intersect(ref i) => i.ord(),
+        }
     }
 
     #[inline]
     fn doc_freq(&mut self) -> Result<i32> {
-        self.iter.doc_freq()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.doc_freq(),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.doc_freq(),
+        }
     }
 
     #[inline]
     fn total_term_freq(&mut self) -> Result<i64> {
-        self.iter.total_term_freq()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.total_term_freq(),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.total_term_freq(),
+        }
     }
 
     #[inline]
     fn postings(&mut self) -> Result<Self::Postings> {
-        self.iter.postings()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.postings(),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.postings(),
+        }
     }
 
     #[inline]
     fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
-        self.iter.postings_with_flags(flags)
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.postings_with_flags(flags),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.postings_with_flags(flags),
+        }
     }
 
     #[inline]
     fn term_state(&mut self) -> Result<Self::TermState> {
-        self.iter.term_state()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref mut i) => i.term_state(),
+            SegmentTermIterEnum::Intersect(ref mut i) => i.term_state(),
+        }
     }
 
     #[inline]
     fn is_empty(&self) -> bool {
-        self.iter.is_empty()
+        match self.iter {
+            SegmentTermIterEnum::Seekable(ref i) => i.is_empty(),
+            SegmentTermIterEnum::Intersect(ref i) => i.is_empty(),
+        }
     }
 }
 
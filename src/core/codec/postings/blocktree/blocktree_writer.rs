@@ -12,7 +12,9 @@
 // limitations under the License.
 
 use core::codec::codec_util::{write_footer, write_index_header};
+use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::{FieldInfo, FieldInfos};
+use core::codec::norms::NormsProducer;
 use core::codec::postings::blocktree::*;
 use core::codec::postings::{FieldsConsumer, PostingsWriterBase};
 use core::codec::segment_infos::{segment_file_name, SegmentWriteState};
@@ -270,7 +272,7 @@ impl<T: PostingsWriterBase, O: IndexOutput> BlockTreeTermsWriter<T, O> {
 }
 
 impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWriter<T, O> {
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         let mut last_field = String::new();
         for field in fields.fields() {
             debug_assert!(last_field < field);
@@ -278,10 +280,18 @@ impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWri
             if let Some(terms) = fields.terms(&field)? {
                 let mut terms_iter = terms.iterator()?;
                 let field_info = self.field_infos.field_info_by_name(&field).unwrap().clone();
+                let field_norms = match norms {
+                    Some(norms) if field_info.has_norms() => Some(norms.norms(&field_info)?),
+                    _ => None,
+                };
                 let mut terms_writer = TermsWriter::new(field_info, self);
 
                 while let Some(term) = terms_iter.next()? {
-                    terms_writer.write(&term, &mut terms_iter)?;
+                    terms_writer.write(
+                        &term,
+                        &mut terms_iter,
+                        field_norms.as_ref().map(|n| n.as_ref()),
+                    )?;
                 }
                 terms_writer.finish()?;
             }
@@ -697,11 +707,17 @@ impl<'a, T: PostingsWriterBase, O: IndexOutput> TermsWriter<'a, T, O> {
     }
 
     // Writes one term's worth of postings.
-    pub fn write(&mut self, text: &[u8], terms_iter: &mut impl TermIterator) -> Result<()> {
+    pub fn write(
+        &mut self,
+        text: &[u8],
+        terms_iter: &mut impl TermIterator,
+        norms: Option<&dyn NumericDocValues>,
+    ) -> Result<()> {
         if let Some(state) = self.block_tree_writer.postings_writer.write_term(
             text,
             terms_iter,
             &mut self.docs_seen,
+            norms,
         )? {
             assert_ne!(state.doc_freq, 0);
             assert!(
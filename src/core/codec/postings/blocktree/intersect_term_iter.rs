@@ -0,0 +1,950 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use core::codec::field_infos::{FieldInfo, FieldInfoRef};
+use core::codec::postings::blocktree::{
+    BlockTermState, FieldReader, MAX_LONGS_SIZE, OUTPUT_FLAGS_IS_FLOOR,
+};
+use core::codec::postings::{
+    lucene50_decode_term, Lucene50PostingIterator, Lucene50PostingsReaderRef,
+};
+use core::codec::{SeekStatus, TermIterator};
+use core::doc::IndexOptions;
+use core::store::io::{ByteArrayDataInput, DataInput, IndexInput};
+use core::util::automaton::ByteRunAutomaton;
+use core::util::fst::{
+    Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, FSTBytesReader, OutputFactory,
+    FST,
+};
+use core::util::BytesRef;
+use core::util::UnsignedShift;
+
+use error::{
+    ErrorKind::{CorruptIndex, UnsupportedOperation},
+    Result,
+};
+
+/// A `TermIterator` over the terms of a `FieldReader` that are accepted by an
+/// automaton.
+///
+/// This is the block tree counterpart of `AutomatonTermIterator`: instead of
+/// seeking the terms dictionary it walks the blocks directly and tracks the
+/// automaton state of each block prefix, so that whole blocks (and floor
+/// blocks) whose prefix can't lead to an accepted term are never loaded.
+///
+/// NOTE: the iterator can't seek, it only supports `next`.
+pub struct IntersectTermIterator {
+    field_info: FieldInfoRef,
+    postings_reader: Lucene50PostingsReaderRef,
+    longs_size: usize,
+    version_auto_prefix: bool,
+    input: Box<dyn IndexInput>,
+    index: Arc<FST<ByteSequenceOutputFactory>>,
+    fst_reader: FSTBytesReader,
+    outputs: ByteSequenceOutputFactory,
+    run_automaton: Arc<ByteRunAutomaton>,
+    stack: Vec<IntersectTermsIterFrame>,
+    current_frame_ord: usize,
+    term: Vec<u8>,
+    eof: bool,
+}
+
+impl IntersectTermIterator {
+    pub fn new(
+        field_reader: &FieldReader,
+        input: Box<dyn IndexInput>,
+        postings_reader: Lucene50PostingsReaderRef,
+        field_info: FieldInfoRef,
+        run_automaton: Arc<ByteRunAutomaton>,
+        start_term: Option<&[u8]>,
+    ) -> Result<Self> {
+        let index = Arc::clone(field_reader.index());
+        let fst_reader = index.bytes_reader();
+        let longs_size = field_reader.longs_size();
+        let version_auto_prefix = field_reader.parent().is_any_auto_prefix_terms();
+
+        let mut iter = IntersectTermIterator {
+            field_info,
+            postings_reader,
+            longs_size,
+            version_auto_prefix,
+            input,
+            index,
+            fst_reader,
+            outputs: ByteSequenceOutputFactory::new(),
+            run_automaton,
+            stack: vec![],
+            current_frame_ord: 0,
+            term: Vec::new(),
+            eof: false,
+        };
+
+        let arc = iter.index.root_arc();
+        // Empty string prefix must have an output in the index!
+        debug_assert!(arc.is_final());
+
+        // Special push_frame since it's the first one:
+        let mut frame = iter.new_frame(0);
+        frame.fp = field_reader.root_block_fp();
+        frame.fp_orig = frame.fp;
+        frame.prefix = 0;
+        frame.set_state(0, &iter.run_automaton);
+        frame.output_prefix = arc.output.clone().unwrap_or_else(ByteSequenceOutput::empty);
+        frame.arc = arc;
+        frame.load(
+            iter.input.as_mut(),
+            Some(field_reader.root_code()),
+            &iter.run_automaton,
+        )?;
+        iter.stack.push(frame);
+
+        if let Some(start_term) = start_term {
+            iter.seek_to_start_term(start_term)?;
+        }
+        Ok(iter)
+    }
+
+    fn new_frame(&self, ord: usize) -> IntersectTermsIterFrame {
+        let mut frame = IntersectTermsIterFrame::new(ord, self.version_auto_prefix);
+        frame.state = self.postings_reader.new_term_state();
+        frame.state.total_term_freq = -1;
+        frame
+    }
+
+    fn get_frame(&mut self, ord: usize) -> usize {
+        while ord >= self.stack.len() {
+            let frame = self.new_frame(self.stack.len());
+            self.stack.push(frame);
+        }
+        debug_assert_eq!(self.stack[ord].ord, ord);
+        ord
+    }
+
+    fn push_frame(&mut self, state: usize) -> Result<usize> {
+        let ord = self.get_frame(self.current_frame_ord + 1);
+
+        let (mut arc, mut output, mut idx, fp, prefix) = {
+            let current = &self.stack[self.current_frame_ord];
+            debug_assert!(current.suffix > 0);
+            (
+                current.arc.clone(),
+                current.output_prefix.clone(),
+                current.prefix,
+                current.last_sub_fp,
+                current.prefix + current.suffix,
+            )
+        };
+
+        // Walk the arc through the index -- we only
+        // "bother" with this so we can get the floor data
+        // from the index and skip floor blocks when
+        // possible:
+        while idx < prefix {
+            let target = i32::from(self.term[idx]);
+            arc = match self
+                .index
+                .find_target_arc(target, &arc, &mut self.fst_reader)?
+            {
+                Some(arc) => arc,
+                None => bail!(CorruptIndex(format!(
+                    "block prefix is missing in the terms index of field '{}'",
+                    self.field_info.name
+                ))),
+            };
+            if let Some(ref out) = arc.output {
+                if !out.is_empty() {
+                    output = self.outputs.add(&output, out);
+                }
+            }
+            idx += 1;
+        }
+        debug_assert!(arc.is_final());
+        let frame_data = match arc.next_final_output {
+            Some(ref out) => self.outputs.add(&output, out),
+            None => output.clone(),
+        };
+
+        let frame = &mut self.stack[ord];
+        frame.fp = fp;
+        frame.fp_orig = fp;
+        frame.prefix = prefix;
+        frame.set_state(state, &self.run_automaton);
+        frame.arc = arc;
+        frame.output_prefix = output;
+        frame.load(
+            self.input.as_mut(),
+            Some(frame_data.inner()),
+            &self.run_automaton,
+        )?;
+        Ok(ord)
+    }
+
+    // copies the term bytes of current entry of current frame
+    fn copy_term(&mut self) {
+        let frame = &self.stack[self.current_frame_ord];
+        let len = frame.prefix + frame.suffix;
+        self.term.resize(len, 0);
+        self.term[frame.prefix..].copy_from_slice(frame.suffix_bytes());
+    }
+
+    // the automaton state after the current entry of current frame, `None`
+    // if the automaton rejects every term starting with the entry
+    fn get_state(&self) -> Option<usize> {
+        let frame = &self.stack[self.current_frame_ord];
+        let mut state = frame.automaton_state;
+        for b in frame.suffix_bytes() {
+            state = self.run_automaton.step(state, u32::from(*b))?;
+        }
+        Some(state)
+    }
+
+    // NOTE: specialized to only doing the first-time
+    // seek, but we could generalize it to allow
+    // arbitrary seek_exact/ceil.  Note that this is a
+    // seek_floor!
+    fn seek_to_start_term(&mut self, target: &[u8]) -> Result<()> {
+        debug_assert_eq!(self.current_frame_ord, 0);
+        loop {
+            let cur = self.current_frame_ord;
+            let saved = self.stack[cur].save_position();
+            let is_sub_block = self.stack[cur].next()?;
+            self.copy_term();
+
+            if is_sub_block && target.starts_with(&self.term) {
+                // Recurse, unless the target isn't accepted and the automaton
+                // already rejects the block prefix: none of the terms in the
+                // block can be accepted, so the block is skipped as a whole
+                // like any other entry before the target
+                if let Some(state) = self.get_state() {
+                    self.current_frame_ord = self.push_frame(state)?;
+                    continue;
+                }
+            }
+
+            match self.term.as_slice().cmp(target) {
+                Ordering::Equal if !self.stack[cur].is_auto_prefix_term => {
+                    return Ok(());
+                }
+                Ordering::Less | Ordering::Equal => {
+                    let frame = &mut self.stack[cur];
+                    if frame.next_ent == frame.ent_count {
+                        if frame.is_last_in_floor {
+                            return Ok(());
+                        }
+                        // Advance to next floor block
+                        frame.load_next_floor_block(self.input.as_mut(), &self.run_automaton)?;
+                    }
+                }
+                Ordering::Greater => {
+                    // Fallback to prior entry: the semantics of
+                    // this method is that the first call to
+                    // next() will return the term after the
+                    // requested term
+                    self.stack[cur].restore_position(saved);
+                    self.copy_term();
+                    // If the last entry was a block we don't
+                    // need to bother recursing and pushing to
+                    // the last term under it because the first
+                    // next() will simply skip the frame anyway
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Pops finished frames and decodes the next entry, returns `None` if
+    // there are no more terms.
+    fn pop_push_next(&mut self) -> Result<Option<bool>> {
+        loop {
+            let frame = &mut self.stack[self.current_frame_ord];
+            if frame.next_ent != frame.ent_count {
+                break;
+            }
+            if !frame.is_last_in_floor {
+                // Advance to next floor block
+                frame.load_next_floor_block(self.input.as_mut(), &self.run_automaton)?;
+                break;
+            }
+            if frame.ord == 0 {
+                return Ok(None);
+            }
+            let last_fp = frame.fp_orig;
+            self.current_frame_ord -= 1;
+            debug_assert_eq!(self.stack[self.current_frame_ord].last_sub_fp, last_fp);
+        }
+
+        self.stack[self.current_frame_ord].next().map(Some)
+    }
+
+    // skips the entries of current frame whose lead byte is less than `min`,
+    // then decodes the next entry
+    fn skip_to_label(&mut self, min: i32) -> Result<Option<bool>> {
+        let frame = &mut self.stack[self.current_frame_ord];
+        while frame.next_ent < frame.ent_count {
+            let is_sub_block = frame.next()?;
+            if frame.suffix == 0 || i32::from(frame.suffix_bytes()[0]) >= min {
+                return Ok(Some(is_sub_block));
+            }
+        }
+        // End of frame:
+        self.pop_push_next()
+    }
+
+    // moves to the next accepted term, returns false if there is no more
+    fn next_term(&mut self) -> Result<bool> {
+        let mut next = self.pop_push_next()?;
+
+        'next_term: while let Some(is_sub_block) = next {
+            let cur = self.current_frame_ord;
+            let state;
+            let last_state;
+
+            // NOTE: suffix == 0 can only happen on the first term in a block, when
+            // there is a term exactly matching a prefix in the index.
+            if self.stack[cur].suffix != 0 {
+                // This is the first byte of the suffix of the term we are now on:
+                let label = i32::from(self.stack[cur].suffix_bytes()[0]);
+
+                if label < self.stack[cur].transition_min {
+                    // Common case: we are scanning terms in this block to "catch up" to
+                    // current transition in the automaton:
+                    let min = self.stack[cur].transition_min;
+                    next = self.skip_to_label(min)?;
+                    continue 'next_term;
+                }
+
+                // Advance where we are in the automaton to match this label:
+                while label > self.stack[cur].transition_max {
+                    if self.stack[cur].transition_index + 1 >= self.stack[cur].transition_count {
+                        // Pop this frame: no further matches are possible because
+                        // we've moved beyond what the max transition will allow
+                        if cur == 0 {
+                            return Ok(false);
+                        }
+                        self.current_frame_ord -= 1;
+                        next = self.pop_push_next()?;
+                        continue 'next_term;
+                    }
+                    self.stack[cur].next_transition(&self.run_automaton);
+
+                    if label < self.stack[cur].transition_min {
+                        let min = self.stack[cur].transition_min;
+                        next = self.skip_to_label(min)?;
+                        continue 'next_term;
+                    }
+                }
+
+                // See if the term suffix matches the automaton:
+
+                // We know from above that the first byte in our suffix (label) matches
+                // the current transition, so we step from the 2nd byte
+                // in the suffix:
+                let frame = &self.stack[cur];
+                let mut prev_state = frame.automaton_state;
+                let mut cur_state = frame.transition_dest;
+                for b in &frame.suffix_bytes()[1..] {
+                    prev_state = cur_state;
+                    match self.run_automaton.step(cur_state, u32::from(*b)) {
+                        Some(s) => cur_state = s,
+                        None => {
+                            // No match
+                            next = self.pop_push_next()?;
+                            continue 'next_term;
+                        }
+                    }
+                }
+                state = cur_state;
+                last_state = prev_state;
+            } else {
+                state = self.stack[cur].automaton_state;
+                last_state = self.stack[cur].last_automaton_state;
+            }
+
+            if is_sub_block {
+                // Match!  Recurse:
+                self.copy_term();
+                self.current_frame_ord = self.push_frame(state)?;
+                self.stack[self.current_frame_ord].last_automaton_state = last_state;
+            } else if self.run_automaton.is_accept(state) && !self.stack[cur].is_auto_prefix_term {
+                self.copy_term();
+                return Ok(true);
+            }
+            // else this term is a prefix of a term accepted by the
+            // automaton, but is not itself accepted
+
+            next = self.pop_push_next()?;
+        }
+        Ok(false)
+    }
+
+    fn current_frame(&mut self) -> &mut IntersectTermsIterFrame {
+        &mut self.stack[self.current_frame_ord]
+    }
+}
+
+impl TermIterator for IntersectTermIterator {
+    type Postings = Lucene50PostingIterator;
+    type TermState = BlockTermState;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.eof {
+            return Ok(None);
+        }
+        if self.next_term()? {
+            Ok(Some(self.term.clone()))
+        } else {
+            self.eof = true;
+            Ok(None)
+        }
+    }
+
+    fn seek_exact(&mut self, _text: &[u8]) -> Result<bool> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "intersect term iterator can't seek"
+        )))
+    }
+
+    fn seek_ceil(&mut self, _text: &[u8]) -> Result<SeekStatus> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "intersect term iterator can't seek"
+        )))
+    }
+
+    fn seek_exact_ord(&mut self, _ord: i64) -> Result<()> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "intersect term iterator can't seek"
+        )))
+    }
+
+    fn seek_exact_state(&mut self, _text: &[u8], _state: &Self::TermState) -> Result<()> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "intersect term iterator can't seek"
+        )))
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        Ok(&self.term)
+    }
+
+    fn ord(&self) -> Result<i64> {
+        bail!(UnsupportedOperation(Cow::Borrowed("")))
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        debug_assert!(!self.eof);
+        let field_info = Arc::clone(&self.field_info);
+        let longs_size = self.longs_size;
+        let frame = self.current_frame();
+        frame.decode_metadata(&field_info, longs_size)?;
+        Ok(frame.state.doc_freq)
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        debug_assert!(!self.eof);
+        let field_info = Arc::clone(&self.field_info);
+        let longs_size = self.longs_size;
+        let frame = self.current_frame();
+        frame.decode_metadata(&field_info, longs_size)?;
+        Ok(frame.state.total_term_freq)
+    }
+
+    fn postings(&mut self) -> Result<Self::Postings> {
+        self.postings_with_flags(0)
+    }
+
+    fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
+        debug_assert!(!self.eof);
+        let field_info = Arc::clone(&self.field_info);
+        let longs_size = self.longs_size;
+        let frame = &mut self.stack[self.current_frame_ord];
+        frame.decode_metadata(&field_info, longs_size)?;
+        self.postings_reader
+            .postings(field_info.as_ref(), &frame.state, flags)
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        let field_info = Arc::clone(&self.field_info);
+        let longs_size = self.longs_size;
+        let frame = self.current_frame();
+        frame.decode_metadata(&field_info, longs_size)?;
+        Ok(frame.state.clone())
+    }
+}
+
+// the decode position of a frame, used to step back one entry
+struct FramePosition {
+    next_ent: i32,
+    suffixes_pos: usize,
+    start_byte_pos: usize,
+    suffix: usize,
+    last_sub_fp: i64,
+    term_block_ord: i32,
+}
+
+/// A block of the block tree being visited by `IntersectTermIterator`,
+/// together with the automaton state of its prefix.
+struct IntersectTermsIterFrame {
+    ord: usize,
+    fp: i64,
+    fp_orig: i64,
+    last_sub_fp: i64,
+
+    // State in automaton
+    automaton_state: usize,
+    // State just before the last label
+    last_automaton_state: usize,
+
+    metadata_upto: i32,
+
+    suffix_bytes: Vec<u8>,
+    suffixes_reader: ByteArrayDataInput<BytesRef>,
+    stat_bytes: Vec<u8>,
+    stats_reader: ByteArrayDataInput<BytesRef>,
+    floor_data: Vec<u8>,
+    floor_data_reader: ByteArrayDataInput<BytesRef>,
+
+    // Length of prefix shared by all terms in this block
+    prefix: usize,
+    // Number of entries (term or sub-block) in this block
+    ent_count: i32,
+    // Which term we will next read
+    next_ent: i32,
+    // True if this block is either not a floor block,
+    // or, it's the last sub-block of a floor block
+    is_last_in_floor: bool,
+    // True if all entries are terms
+    is_leaf_block: bool,
+    version_auto_prefix: bool,
+    // True if the current entry is an auto-prefix term, which is skipped
+    is_auto_prefix_term: bool,
+
+    num_follow_floor_blocks: i32,
+    next_floor_label: i32,
+
+    // the current transition leaving `automaton_state`
+    transition_index: usize,
+    transition_count: usize,
+    transition_min: i32,
+    transition_max: i32,
+    transition_dest: usize,
+
+    arc: FSTArc<ByteSequenceOutput>,
+    // Cumulative output so far
+    output_prefix: ByteSequenceOutput,
+
+    state: BlockTermState,
+    // metadata buffer, holding monotonic values
+    longs: [i64; MAX_LONGS_SIZE],
+    // metadata buffer, holding general values
+    bytes: Vec<u8>,
+    bytes_reader: ByteArrayDataInput<BytesRef>,
+
+    start_byte_pos: usize,
+    suffix: usize,
+}
+
+impl IntersectTermsIterFrame {
+    fn new(ord: usize, version_auto_prefix: bool) -> Self {
+        IntersectTermsIterFrame {
+            ord,
+            fp: 0,
+            fp_orig: 0,
+            last_sub_fp: -1,
+            automaton_state: 0,
+            last_automaton_state: 0,
+            metadata_upto: 0,
+            suffix_bytes: vec![0; 128],
+            suffixes_reader: ByteArrayDataInput::new(BytesRef::default()),
+            stat_bytes: vec![0; 64],
+            stats_reader: ByteArrayDataInput::new(BytesRef::default()),
+            floor_data: vec![0; 32],
+            floor_data_reader: ByteArrayDataInput::new(BytesRef::default()),
+            prefix: 0,
+            ent_count: 0,
+            next_ent: 0,
+            is_last_in_floor: false,
+            is_leaf_block: false,
+            version_auto_prefix,
+            is_auto_prefix_term: false,
+            num_follow_floor_blocks: 0,
+            next_floor_label: 0,
+            transition_index: 0,
+            transition_count: 0,
+            transition_min: -1,
+            transition_max: -1,
+            transition_dest: 0,
+            arc: FSTArc::empty(),
+            output_prefix: ByteSequenceOutput::empty(),
+            state: BlockTermState::new(),
+            longs: [0; MAX_LONGS_SIZE],
+            bytes: vec![0; 32],
+            bytes_reader: ByteArrayDataInput::new(BytesRef::default()),
+            start_byte_pos: 0,
+            suffix: 0,
+        }
+    }
+
+    // the suffix bytes of the current entry
+    fn suffix_bytes(&self) -> &[u8] {
+        &self.suffix_bytes[self.start_byte_pos..self.start_byte_pos + self.suffix]
+    }
+
+    fn set_state(&mut self, state: usize, run_automaton: &ByteRunAutomaton) {
+        self.automaton_state = state;
+        self.transition_index = 0;
+        let transitions = run_automaton.automaton().transitions(state);
+        self.transition_count = transitions.len();
+        if let Some(t) = transitions.first() {
+            self.transition_min = t.min as i32;
+            self.transition_max = t.max as i32;
+            self.transition_dest = t.dest;
+        } else {
+            // Must set min to -1 so the "label < min" check never falsely triggers:
+            self.transition_min = -1;
+            // Must set max to -1 so we immediately realize we need to step to
+            // the next transition and then pop this frame:
+            self.transition_max = -1;
+        }
+    }
+
+    fn next_transition(&mut self, run_automaton: &ByteRunAutomaton) {
+        self.transition_index += 1;
+        let t = &run_automaton.automaton().transitions(self.automaton_state)[self.transition_index];
+        self.transition_min = t.min as i32;
+        self.transition_max = t.max as i32;
+        self.transition_dest = t.dest;
+    }
+
+    fn load_next_floor_block(
+        &mut self,
+        input: &mut dyn IndexInput,
+        run_automaton: &ByteRunAutomaton,
+    ) -> Result<()> {
+        debug_assert!(self.num_follow_floor_blocks > 0);
+        loop {
+            self.read_next_floor_fp()?;
+            if self.num_follow_floor_blocks == 0 || self.next_floor_label > self.transition_min {
+                break;
+            }
+        }
+        self.load(input, None, run_automaton)
+    }
+
+    fn read_next_floor_fp(&mut self) -> Result<()> {
+        self.fp = self.fp_orig + self.floor_data_reader.read_vlong()?.unsigned_shift(1);
+        self.num_follow_floor_blocks -= 1;
+        if self.num_follow_floor_blocks != 0 {
+            self.next_floor_label = i32::from(self.floor_data_reader.read_byte()?);
+        } else {
+            self.next_floor_label = 256;
+        }
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        input: &mut dyn IndexInput,
+        frame_index_data: Option<&[u8]>,
+        run_automaton: &ByteRunAutomaton,
+    ) -> Result<()> {
+        if let Some(data) = frame_index_data {
+            self.floor_data.resize(data.len(), 0);
+            self.floor_data.copy_from_slice(data);
+            self.floor_data_reader
+                .reset(BytesRef::new(&self.floor_data));
+            // Skip first long -- has redundant fp, hasTerms
+            // flag, isFloor flag
+            let code = self.floor_data_reader.read_vlong()?;
+            if (code & OUTPUT_FLAGS_IS_FLOOR) != 0 {
+                // Floor frame
+                self.num_follow_floor_blocks = self.floor_data_reader.read_vint()?;
+                self.next_floor_label = i32::from(self.floor_data_reader.read_byte()?);
+
+                // If current state is not accept, and has transitions, we must process
+                // first block in case it has empty suffix:
+                if !run_automaton.is_accept(self.automaton_state) && self.transition_count != 0 {
+                    // Maybe skip floor blocks:
+                    debug_assert_eq!(self.transition_index, 0);
+                    while self.num_follow_floor_blocks != 0
+                        && self.next_floor_label <= self.transition_min
+                    {
+                        self.read_next_floor_fp()?;
+                    }
+                }
+            }
+        }
+
+        input.seek(self.fp)?;
+        let mut code = input.read_vint()?;
+        self.ent_count = code.unsigned_shift(1);
+        debug_assert!(self.ent_count > 0);
+        self.is_last_in_floor = (code & 1) != 0;
+
+        // term suffixes:
+        code = input.read_vint()?;
+        self.is_leaf_block = (code & 1) != 0;
+        let num_bytes = code.unsigned_shift(1) as usize;
+        self.suffix_bytes.resize(num_bytes, 0);
+        input.read_bytes(&mut self.suffix_bytes, 0, num_bytes)?;
+        self.suffixes_reader
+            .reset(BytesRef::new(&self.suffix_bytes));
+
+        // stats
+        let num_bytes = input.read_vint()? as usize;
+        self.stat_bytes.resize(num_bytes, 0);
+        input.read_bytes(&mut self.stat_bytes, 0, num_bytes)?;
+        self.stats_reader.reset(BytesRef::new(&self.stat_bytes));
+        self.metadata_upto = 0;
+
+        self.state.term_block_ord = 0;
+        self.next_ent = 0;
+
+        // metadata
+        let num_bytes = input.read_vint()? as usize;
+        self.bytes.resize(num_bytes, 0);
+        input.read_bytes(&mut self.bytes, 0, num_bytes)?;
+        self.bytes_reader.reset(BytesRef::new(&self.bytes));
+        Ok(())
+    }
+
+    // Decodes next entry; returns true if it's a sub-block
+    fn next(&mut self) -> Result<bool> {
+        debug_assert!(self.next_ent != -1 && self.next_ent < self.ent_count);
+        self.next_ent += 1;
+        self.is_auto_prefix_term = false;
+        if self.is_leaf_block {
+            self.suffix = self.suffixes_reader.read_vint()? as usize;
+            self.start_byte_pos = self.suffixes_reader.position();
+            self.suffixes_reader.skip_bytes(self.suffix)?;
+            return Ok(false);
+        }
+
+        let code = self.suffixes_reader.read_vint()?;
+        let kind = if self.version_auto_prefix {
+            self.suffix = code.unsigned_shift(2) as usize;
+            code & 3
+        } else {
+            self.suffix = code.unsigned_shift(1) as usize;
+            code & 1
+        };
+        self.start_byte_pos = self.suffixes_reader.position();
+        self.suffixes_reader.skip_bytes(self.suffix)?;
+        match kind {
+            0 => {
+                // A normal term
+                self.state.term_block_ord += 1;
+                Ok(false)
+            }
+            1 => {
+                // A sub-block; make sub-FP absolute:
+                self.last_sub_fp = self.fp - self.suffixes_reader.read_vlong()?;
+                Ok(true)
+            }
+            _ => {
+                // A prefix term
+                self.state.term_block_ord += 1;
+                self.suffixes_reader.read_byte()?;
+                self.is_auto_prefix_term = true;
+                Ok(false)
+            }
+        }
+    }
+
+    fn save_position(&self) -> FramePosition {
+        FramePosition {
+            next_ent: self.next_ent,
+            suffixes_pos: self.suffixes_reader.position(),
+            start_byte_pos: self.start_byte_pos,
+            suffix: self.suffix,
+            last_sub_fp: self.last_sub_fp,
+            term_block_ord: self.state.term_block_ord,
+        }
+    }
+
+    fn restore_position(&mut self, position: FramePosition) {
+        self.next_ent = position.next_ent;
+        self.suffixes_reader.set_position(position.suffixes_pos);
+        self.start_byte_pos = position.start_byte_pos;
+        self.suffix = position.suffix;
+        self.last_sub_fp = position.last_sub_fp;
+        self.state.term_block_ord = position.term_block_ord;
+    }
+
+    fn term_block_ord(&self) -> i32 {
+        if self.is_leaf_block {
+            self.next_ent
+        } else {
+            self.state.term_block_ord
+        }
+    }
+
+    fn decode_metadata(&mut self, field_info: &FieldInfo, longs_size: usize) -> Result<()> {
+        // lazily catch up on metadata decode:
+        let limit = self.term_block_ord();
+        let mut absolute = self.metadata_upto == 0;
+        debug_assert!(limit > 0);
+
+        while self.metadata_upto < limit {
+            // stats
+            self.state.doc_freq = self.stats_reader.read_vint()?;
+            if field_info.index_options != IndexOptions::Docs {
+                self.state.total_term_freq =
+                    i64::from(self.state.doc_freq) + self.stats_reader.read_vlong()?;
+            }
+            // metadata
+            for i in 0..longs_size {
+                self.longs[i] = self.bytes_reader.read_vlong()?;
+            }
+            lucene50_decode_term(
+                &self.longs,
+                &mut self.bytes_reader,
+                field_info,
+                &mut self.state,
+                absolute,
+            )?;
+            self.metadata_upto += 1;
+            absolute = false;
+        }
+        self.state.term_block_ord = self.metadata_upto;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::Terms;
+    use core::doc::Fieldable;
+    use core::index::reader::{IndexReader, LeafReader};
+    use core::index::tests::*;
+    use core::search::query::{PrefixQuery, WildcardQuery};
+    use core::util::automaton::{
+        Automaton, AutomatonTermIterator, AutomatonType, CompiledAutomaton, LevenshteinAutomata,
+        RegExp, DEFAULT_MAX_DETERMINIZED_STATES,
+    };
+
+    // "a" to "eeee" and "x0" to "x999", the terms sharing a prefix are many
+    // enough for the block tree to write floor blocks and sub blocks
+    fn vocabulary() -> Vec<String> {
+        let mut terms = vec![String::new()];
+        let mut last = vec![String::new()];
+        for _ in 0..4 {
+            let mut next = Vec::new();
+            for prefix in &last {
+                for c in "abcde".chars() {
+                    next.push(format!("{}{}", prefix, c));
+                }
+            }
+            terms.extend(next.iter().cloned());
+            last = next;
+        }
+        terms.remove(0);
+        terms.extend((0..1000).map(|i| format!("x{}", i)));
+        terms
+    }
+
+    fn collect_terms<T: TermIterator>(mut iter: T) -> Vec<Vec<u8>> {
+        let mut terms = Vec::new();
+        while let Some(term) = iter.next().unwrap() {
+            terms.push(term);
+        }
+        terms
+    }
+
+    fn compile(automaton: &Automaton, finite: Option<bool>, is_binary: bool) -> CompiledAutomaton {
+        let compiled = CompiledAutomaton::with_options(
+            automaton,
+            finite,
+            true,
+            DEFAULT_MAX_DETERMINIZED_STATES,
+            is_binary,
+        )
+        .unwrap();
+        assert_eq!(compiled.automaton_type, AutomatonType::Normal);
+        compiled
+    }
+
+    #[test]
+    fn test_intersect() {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = vocabulary()
+            .chunks(50)
+            .map(|terms| vec![text_field("body", &terms.join(" "))])
+            .collect();
+        let index = TestIndex::new(docs, usize::max_value());
+        let vocabulary: Vec<Vec<u8>> = vocabulary().into_iter().map(String::into_bytes).collect();
+
+        let mut automata = vec![
+            compile(&PrefixQuery::to_automaton(b"ab"), None, true),
+            compile(&PrefixQuery::to_automaton(b"x1"), None, true),
+            compile(&WildcardQuery::to_automaton("a*c"), None, false),
+            compile(&WildcardQuery::to_automaton("x?5*"), None, false),
+            compile(&WildcardQuery::to_automaton("*b?"), None, false),
+        ];
+        for regexp in &["[a-c]+d?", "x[0-9]{2}", "(ab|ba)*e"] {
+            let automaton = RegExp::new(regexp)
+                .unwrap()
+                .to_automaton(DEFAULT_MAX_DETERMINIZED_STATES)
+                .unwrap();
+            automata.push(compile(&automaton, None, false));
+        }
+        for &(text, max_edits) in &[("abcd", 1), ("abcd", 2), ("x500", 1)] {
+            let automaton = LevenshteinAutomata::new(text, true)
+                .to_automaton(max_edits)
+                .unwrap();
+            automata.push(compile(&automaton, Some(true), false));
+        }
+
+        for leaf in index.reader.leaves() {
+            let terms = leaf.reader.terms("body").unwrap().unwrap();
+            for compiled in &automata {
+                let expected = collect_terms(AutomatonTermIterator::new(
+                    terms.iterator().unwrap(),
+                    compiled,
+                ));
+                assert!(!expected.is_empty());
+                assert_eq!(
+                    collect_terms(terms.intersect(compiled, None).unwrap()),
+                    expected
+                );
+
+                // the start term needn't be accepted, the terms after it are returned
+                let mut start_terms = vec![&expected[0], &expected[expected.len() / 2]];
+                start_terms.extend(
+                    vocabulary
+                        .iter()
+                        .filter(|t| !expected.contains(*t))
+                        .step_by(97),
+                );
+                for start_term in start_terms {
+                    let after: Vec<Vec<u8>> = expected
+                        .iter()
+                        .filter(|t| *t > start_term)
+                        .cloned()
+                        .collect();
+                    assert_eq!(
+                        collect_terms(
+                            terms
+                                .intersect(compiled, Some(start_term.as_slice()))
+                                .unwrap()
+                        ),
+                        after
+                    );
+                }
+            }
+        }
+    }
+}
@@ -49,8 +49,10 @@ mod terms_hash_per_field;
 
 pub use self::terms_hash_per_field::*;
 
+use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::FieldInfo;
 use core::codec::multi_fields::{MappedMultiFields, MultiFields};
+use core::codec::norms::NormsProducer;
 use core::codec::postings::blocktree::{
     BlockTermState, BlockTreeTermsReader, BlockTreeTermsWriter, FieldReaderRef,
 };
@@ -178,14 +180,21 @@ pub trait FieldsConsumer {
     ///
     /// - The provided Fields instance is limited: you cannot call any methods that return
     ///   statistics/counts; you cannot pass a non-null live docs when pulling docs/positions enums.
-    fn write(&mut self, fields: &impl Fields) -> Result<()>;
+    ///
+    /// - `norms` gives access to the norms of the segment being written, which are already
+    ///   flushed, so that the postings can record score impacts.
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()>;
 
     /// Merges in the fields from the readers in
     /// <code>mergeState</code>. The default implementation skips
     /// and maps around deleted documents, and calls {@link #write(Fields)}.
     /// Implementations can override this method for more sophisticated
     /// merging (bulk-byte copying, etc).
-    fn merge<D: Directory, C: Codec>(&mut self, merge_state: &mut MergeState<D, C>) -> Result<()> {
+    fn merge<D: Directory, C: Codec>(
+        &mut self,
+        merge_state: &mut MergeState<D, C>,
+        norms: Option<&dyn NormsProducer>,
+    ) -> Result<()> {
         let mut fields = vec![];
         let mut slices = vec![];
 
@@ -203,7 +212,7 @@ pub trait FieldsConsumer {
 
         let fields = MultiFields::new(fields, slices);
         let merged_fields = MappedMultiFields::new(merge_state, fields);
-        self.write(&merged_fields)
+        self.write(&merged_fields, norms)
     }
 }
 
@@ -213,20 +222,21 @@ pub enum FieldsConsumerEnum<D: Directory, DW: Directory, C: Codec> {
 }
 
 impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for FieldsConsumerEnum<D, DW, C> {
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         match self {
-            FieldsConsumerEnum::Lucene50(w) => w.write(fields),
-            FieldsConsumerEnum::PerField(w) => w.write(fields),
+            FieldsConsumerEnum::Lucene50(w) => w.write(fields, norms),
+            FieldsConsumerEnum::PerField(w) => w.write(fields, norms),
         }
     }
 
     fn merge<D1: Directory, C1: Codec>(
         &mut self,
         merge_state: &mut MergeState<D1, C1>,
+        norms: Option<&dyn NormsProducer>,
     ) -> Result<()> {
         match self {
-            FieldsConsumerEnum::Lucene50(w) => w.merge(merge_state),
-            FieldsConsumerEnum::PerField(w) => w.merge(merge_state),
+            FieldsConsumerEnum::Lucene50(w) => w.merge(merge_state, norms),
+            FieldsConsumerEnum::PerField(w) => w.merge(merge_state, norms),
         }
     }
 }
@@ -330,12 +340,14 @@ pub trait PostingsWriterBase {
     /// method must set the bit in the provided {@link
     /// FixedBitSet} for every docID written.  If no docs
     /// were written, this method should return null, and the
-    /// terms dict will skip the term.
+    /// terms dict will skip the term. `norms` are the norms of
+    /// the field, if it has any.
     fn write_term(
         &mut self,
         term: &[u8],
         terms: &mut impl TermIterator,
         docs_seen: &mut FixedBitSet,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<Option<BlockTermState>>;
 
     /// Encode metadata as [i64] and [u8]. {@param absolute} controls whether
@@ -17,6 +17,7 @@ use std::fmt;
 use std::mem;
 use std::sync::Arc;
 
+use core::codec::norms::NormsProducer;
 use core::codec::postings::blocktree::FieldReaderRef;
 use core::codec::postings::{
     postings_format_for_name, FieldsConsumer, FieldsConsumerEnum, FieldsProducer,
@@ -55,11 +56,24 @@ fn get_suffix(format: &str, suffix: &str) -> String {
 /// @see ServiceLoader
 /// @lucene.experimental
 #[derive(Copy, Clone)]
-pub struct PerFieldPostingsFormat;
+pub struct PerFieldPostingsFormat {
+    write_block_impacts: bool,
+}
 
 impl Default for PerFieldPostingsFormat {
     fn default() -> PerFieldPostingsFormat {
-        PerFieldPostingsFormat {}
+        PerFieldPostingsFormat::new(false)
+    }
+}
+
+impl PerFieldPostingsFormat {
+    /// Creates a format whose `Lucene50PostingsFormat` records the block
+    /// impacts if `write_block_impacts` is true, see
+    /// `Lucene50PostingsFormat::with_block_impacts`.
+    pub fn new(write_block_impacts: bool) -> PerFieldPostingsFormat {
+        PerFieldPostingsFormat {
+            write_block_impacts,
+        }
     }
 }
 
@@ -78,6 +92,7 @@ impl PostingsFormat for PerFieldPostingsFormat {
     ) -> Result<FieldsConsumerEnum<D, DW, C>> {
         Ok(FieldsConsumerEnum::PerField(PerFieldFieldsWriter::new(
             state,
+            self.write_block_impacts,
         )))
     }
 
@@ -182,12 +197,14 @@ struct FieldsGroup<D: Directory, DW: Directory, C: Codec> {
 
 pub struct PerFieldFieldsWriter<D: Directory, DW: Directory, C: Codec> {
     write_state: SegmentWriteState<D, DW, C>,
+    write_block_impacts: bool,
 }
 
 impl<D: Directory, DW: Directory, C: Codec> PerFieldFieldsWriter<D, DW, C> {
-    pub fn new(write_state: &SegmentWriteState<D, DW, C>) -> Self {
+    pub fn new(write_state: &SegmentWriteState<D, DW, C>, write_block_impacts: bool) -> Self {
         PerFieldFieldsWriter {
             write_state: write_state.clone(),
+            write_block_impacts,
         }
     }
 
@@ -202,17 +219,17 @@ impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for PerFieldFieldsWri
     // And we assume that we always use one format all the time.
     // so we won't implement this like lucene
     // when the format changes, it's easy to change the hard code then.
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         // this is only one format, so suffix is always "0"
         let segment_suffix =
             self.get_full_segment_suffix(&self.write_state.segment_suffix, "Lucene50_0".into());
         // always use lucene50, so just hard code it.
-        let format = Lucene50PostingsFormat::default();
+        let format = Lucene50PostingsFormat::default().with_block_impacts(self.write_block_impacts);
 
         let old_suffix = mem::replace(&mut self.write_state.segment_suffix, segment_suffix);
 
         let mut consumer = format.fields_consumer(&self.write_state)?;
-        consumer.write(fields)?;
+        consumer.write(fields, norms)?;
 
         self.write_state.segment_suffix = old_suffix;
 
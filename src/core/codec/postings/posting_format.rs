@@ -28,6 +28,7 @@ pub struct Lucene50PostingsFormat {
     name: &'static str,
     min_term_block_size: usize,
     max_term_block_size: usize,
+    write_block_impacts: bool,
 }
 
 /// Fixed packed block size, number of integers encoded in
@@ -59,8 +60,19 @@ impl Lucene50PostingsFormat {
             name: "Lucene50",
             min_term_block_size,
             max_term_block_size,
+            write_block_impacts: false,
         }
     }
+
+    /// Opts in to record the impacts of the postings blocks in the skip data,
+    /// which lets the top docs collection skip the uncompetitive blocks.
+    ///
+    /// NOTE: the segments written this way can't be read by Lucene 6.2.1, see
+    /// `VERSION_IMPACT_SKIP_DATA`.
+    pub fn with_block_impacts(mut self, write_block_impacts: bool) -> Lucene50PostingsFormat {
+        self.write_block_impacts = write_block_impacts;
+        self
+    }
 }
 
 impl PostingsFormat for Lucene50PostingsFormat {
@@ -77,7 +89,7 @@ impl PostingsFormat for Lucene50PostingsFormat {
         &self,
         state: &SegmentWriteState<D, DW, C>,
     ) -> Result<FieldsConsumerEnum<D, DW, C>> {
-        let postings_writer = Lucene50PostingsWriter::new(state)?;
+        let postings_writer = Lucene50PostingsWriter::new(state, self.write_block_impacts)?;
         Ok(FieldsConsumerEnum::Lucene50(BlockTreeTermsWriter::new(
             state,
             postings_writer,
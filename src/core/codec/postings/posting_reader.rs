@@ -18,7 +18,7 @@ use core::codec::postings::posting_format::BLOCK_SIZE;
 use core::codec::postings::skip_reader::*;
 use core::codec::segment_infos::{segment_file_name, SegmentReadState};
 use core::codec::{codec_util, Codec};
-use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::codec::{Impact, PostingIterator, PostingIteratorFlags};
 use core::search::{DocIterator, Payload, NO_MORE_DOCS};
 use core::store::directory::Directory;
 use core::store::io::{DataInput, IndexInput};
@@ -51,8 +51,21 @@ pub const POS_CODEC: &str = "Lucene50PostingsWriterPos";
 pub const PAY_CODEC: &str = "Lucene50PostingsWriterPay";
 
 // Increment version to change it
-const VERSION_START: i32 = 0;
-pub const VERSION_CURRENT: i32 = VERSION_START;
+/// The format of Lucene 6.2.1, written by default.
+pub(crate) const VERSION_START: i32 = 0;
+/// The skip data records the impact of each block, i.e. the highest freq and
+/// the most competitive norm of its docs.
+///
+/// This version is only written by the formats that opt in with
+/// `Lucene50PostingsFormat::with_block_impacts`, e.g. the codec of
+/// `Lucene62Codec::with_block_impacts`. The codec names are unchanged, so it
+/// breaks the compatibility with Lucene 6.2.1: Lucene and the readers of
+/// `VERSION_START` reject the segments on the header check. The segments of
+/// `VERSION_START` are still read, their postings have no block impacts and
+/// `advance_shallow` returns `NO_MORE_DOCS`, so their block max score is the
+/// max score of the term.
+pub const VERSION_IMPACT_SKIP_DATA: i32 = 1;
+pub const VERSION_CURRENT: i32 = VERSION_IMPACT_SKIP_DATA;
 
 fn clone_option_index_input(input: &Option<Box<dyn IndexInput>>) -> Result<Box<dyn IndexInput>> {
     debug_assert!(input.is_some());
@@ -184,6 +197,7 @@ impl Lucene50PostingsReader {
         let index_has_positions = options.has_positions();
         let index_has_offsets = options.has_offsets();
         let index_has_payloads = field_info.has_store_payloads;
+        let has_impacts = self.version >= VERSION_IMPACT_SKIP_DATA;
 
         if !index_has_positions
             || !PostingIteratorFlags::feature_requested(flags, PostingIteratorFlags::POSITIONS)
@@ -195,6 +209,7 @@ impl Lucene50PostingsReader {
                     state,
                     flags,
                     self.for_util.clone(),
+                    has_impacts,
                 )?,
             )))
         } else if (!index_has_offsets
@@ -210,6 +225,7 @@ impl Lucene50PostingsReader {
                     state,
                     flags,
                     self.for_util.clone(),
+                    has_impacts,
                 )?,
             )))
         } else {
@@ -224,6 +240,7 @@ impl Lucene50PostingsReader {
                     state,
                     flags,
                     self.for_util.clone(),
+                    has_impacts,
                 )?),
             ))
         }
@@ -317,6 +334,112 @@ fn read_vint_block(
     Ok(())
 }
 
+/// Reads the impacts of the skip data for `PostingIterator::advance_shallow`.
+///
+/// It has a skip reader of its own, so that the shallow advances never move
+/// the postings, which may be behind the target.
+struct ImpactsSkipper {
+    skipper: Option<Lucene50SkipReader>,
+    has_impacts: bool,
+    has_pos: bool,
+    has_offsets: bool,
+    has_payloads: bool,
+
+    doc_term_start_fp: i64,
+    skip_offset: i64,
+    doc_freq: i32,
+    skipped: bool,
+
+    /// last doc of the block of `impact`
+    up_to: DocId,
+    impact: Option<Impact>,
+}
+
+impl ImpactsSkipper {
+    fn new(
+        has_impacts: bool,
+        has_pos: bool,
+        has_offsets: bool,
+        has_payloads: bool,
+    ) -> ImpactsSkipper {
+        ImpactsSkipper {
+            skipper: None,
+            has_impacts,
+            has_pos,
+            has_offsets,
+            has_payloads,
+            doc_term_start_fp: 0,
+            skip_offset: -1,
+            doc_freq: 0,
+            skipped: false,
+            up_to: -1,
+            impact: None,
+        }
+    }
+
+    fn reset(&mut self, term_state: &BlockTermState) {
+        self.doc_term_start_fp = term_state.doc_start_fp;
+        self.skip_offset = term_state.skip_offset;
+        self.doc_freq = term_state.doc_freq;
+        self.skipped = false;
+        self.up_to = -1;
+        self.impact = None;
+    }
+
+    fn advance_shallow(
+        &mut self,
+        doc_in: &Option<Box<dyn IndexInput>>,
+        target: DocId,
+    ) -> Result<DocId> {
+        // short postings have no skip data, their single block is bounded
+        // by the global max score
+        if !self.has_impacts || self.doc_freq <= BLOCK_SIZE {
+            return Ok(NO_MORE_DOCS);
+        }
+        if target <= self.up_to {
+            return Ok(self.up_to);
+        }
+
+        if self.skipper.is_none() {
+            self.skipper = Some(Lucene50SkipReader::new(
+                clone_option_index_input(doc_in)?,
+                MAX_SKIP_LEVELS,
+                self.has_pos,
+                self.has_offsets,
+                self.has_payloads,
+                true,
+            ));
+        }
+        let skipper = self.skipper.as_mut().unwrap();
+        if !self.skipped {
+            debug_assert_ne!(self.skip_offset, -1);
+            skipper.init(
+                self.doc_term_start_fp + self.skip_offset,
+                self.doc_term_start_fp,
+                0,
+                0,
+                self.doc_freq,
+            )?;
+            self.skipped = true;
+        }
+        // the skip reader doesn't move for targets not after its current entry,
+        // which is initially 0
+        skipper.skip_to(target.max(1))?;
+        self.impact = skipper.impact();
+        // there is no skip entry for the last block
+        self.up_to = if self.impact.is_some() {
+            skipper.next_skip_doc()
+        } else {
+            NO_MORE_DOCS
+        };
+        Ok(self.up_to)
+    }
+
+    fn impact(&self) -> Option<Impact> {
+        self.impact
+    }
+}
+
 struct BlockDocIterator {
     encoded: [u8; MAX_ENCODED_SIZE],
 
@@ -327,6 +450,7 @@ struct BlockDocIterator {
 
     pub skipper: Option<Lucene50SkipReader>,
     skipped: bool,
+    impacts: ImpactsSkipper,
 
     start_doc_in: Box<dyn IndexInput>,
 
@@ -376,6 +500,7 @@ impl BlockDocIterator {
         term_state: &BlockTermState,
         flags: u16,
         for_util: ForUtil,
+        has_impacts: bool,
     ) -> Result<BlockDocIterator> {
         let options = &field_info.index_options;
         let mut iterator = BlockDocIterator {
@@ -386,6 +511,12 @@ impl BlockDocIterator {
             doc_buffer_upto: 0,
             skipped: false,
             skipper: None,
+            impacts: ImpactsSkipper::new(
+                has_impacts,
+                options.has_positions(),
+                options.has_offsets(),
+                field_info.has_store_payloads,
+            ),
             doc_in: None,
             doc_freq: 0,
             total_term_freq: 0,
@@ -438,6 +569,7 @@ impl BlockDocIterator {
         self.next_skip_doc = BLOCK_SIZE - 1; // we won't skip if target is found in first block
         self.doc_buffer_upto = BLOCK_SIZE;
         self.skipped = false;
+        self.impacts.reset(term_state);
         Ok(())
     }
 
@@ -503,6 +635,14 @@ impl PostingIterator for BlockDocIterator {
     fn payload(&self) -> Result<Payload> {
         Ok(Payload::new())
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.impacts.advance_shallow(&self.doc_in, target)
+    }
+
+    fn impact(&self) -> Option<Impact> {
+        self.impacts.impact()
+    }
 }
 
 impl DocIterator for BlockDocIterator {
@@ -542,6 +682,7 @@ impl DocIterator for BlockDocIterator {
                     self.index_has_pos,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.impacts.has_impacts,
                 ));
             }
 
@@ -628,6 +769,7 @@ struct BlockPostingIterator {
 
     skipper: Option<Lucene50SkipReader>,
     skipped: bool,
+    impacts: ImpactsSkipper,
 
     start_doc_in: Box<dyn IndexInput>,
 
@@ -697,6 +839,7 @@ impl BlockPostingIterator {
         term_state: &BlockTermState,
         _flags: u16,
         for_util: ForUtil,
+        has_impacts: bool,
     ) -> Result<BlockPostingIterator> {
         let options = &field_info.index_options;
         let mut iterator = BlockPostingIterator {
@@ -709,6 +852,12 @@ impl BlockPostingIterator {
             pos_buffer_upto: 0,
             skipped: false,
             skipper: None,
+            impacts: ImpactsSkipper::new(
+                has_impacts,
+                options.has_positions(),
+                options.has_offsets(),
+                field_info.has_store_payloads,
+            ),
             doc_in: None,
             doc_freq: 0,
             pos_in,
@@ -776,6 +925,7 @@ impl BlockPostingIterator {
         };
         self.doc_buffer_upto = BLOCK_SIZE;
         self.skipped = false;
+        self.impacts.reset(term_state);
         Ok(())
     }
 
@@ -914,6 +1064,14 @@ impl PostingIterator for BlockPostingIterator {
     fn payload(&self) -> Result<Payload> {
         Ok(Payload::new())
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.impacts.advance_shallow(&self.doc_in, target)
+    }
+
+    fn impact(&self) -> Option<Impact> {
+        self.impacts.impact()
+    }
 }
 
 impl DocIterator for BlockPostingIterator {
@@ -953,6 +1111,7 @@ impl DocIterator for BlockPostingIterator {
                     self.index_has_pos,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.impacts.has_impacts,
                 ));
             }
 
@@ -1052,6 +1211,7 @@ struct EverythingIterator {
 
     skipper: Option<Lucene50SkipReader>,
     skipped: bool,
+    impacts: ImpactsSkipper,
 
     start_doc_in: Box<dyn IndexInput>,
 
@@ -1121,7 +1281,7 @@ struct EverythingIterator {
 }
 
 impl<'a> EverythingIterator {
-    //#[allow(too_many_arguments)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         start_doc_in: Box<dyn IndexInput>,
         pos_in: Box<dyn IndexInput>,
@@ -1130,6 +1290,7 @@ impl<'a> EverythingIterator {
         term_state: &BlockTermState,
         flags: u16,
         for_util: ForUtil,
+        has_impacts: bool,
     ) -> Result<EverythingIterator> {
         let encoded = [0u8; MAX_ENCODED_SIZE];
         let index_has_offsets = field_info.index_options.has_offsets();
@@ -1190,6 +1351,7 @@ impl<'a> EverythingIterator {
             singleton_doc_id: 0,
             skip_offset: 0,
             skipper: None,
+            impacts: ImpactsSkipper::new(has_impacts, true, index_has_offsets, index_has_payloads),
             skipped: false,
             total_term_freq: 0,
             for_util,
@@ -1244,6 +1406,7 @@ impl<'a> EverythingIterator {
         };
         self.doc_buffer_upto = BLOCK_SIZE;
         self.skipped = false;
+        self.impacts.reset(term_state);
         Ok(())
     }
 
@@ -1512,6 +1675,14 @@ impl PostingIterator for EverythingIterator {
             Ok(self.payload_bytes[start..end].to_vec())
         }
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.impacts.advance_shallow(&self.doc_in, target)
+    }
+
+    fn impact(&self) -> Option<Impact> {
+        self.impacts.impact()
+    }
 }
 
 impl DocIterator for EverythingIterator {
@@ -1552,6 +1723,7 @@ impl DocIterator for EverythingIterator {
                     true,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.impacts.has_impacts,
                 ));
             }
 
@@ -1673,6 +1845,22 @@ impl PostingIterator for Lucene50PostingIterator {
             Lucene50PostingIterEnum::Everything(i) => i.payload(),
         }
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        match &mut self.0 {
+            Lucene50PostingIterEnum::Doc(i) => i.advance_shallow(target),
+            Lucene50PostingIterEnum::Posting(i) => i.advance_shallow(target),
+            Lucene50PostingIterEnum::Everything(i) => i.advance_shallow(target),
+        }
+    }
+
+    fn impact(&self) -> Option<Impact> {
+        match &self.0 {
+            Lucene50PostingIterEnum::Doc(i) => i.impact(),
+            Lucene50PostingIterEnum::Posting(i) => i.impact(),
+            Lucene50PostingIterEnum::Everything(i) => i.impact(),
+        }
+    }
 }
 
 impl DocIterator for Lucene50PostingIterator {
@@ -1748,3 +1936,191 @@ impl DocIterator for Lucene50PostingIterator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::field_infos::FieldInvertState;
+    use core::codec::{CodecEnum, Lucene62Codec};
+    use core::doc::{Fieldable, Term};
+    use core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
+    use core::index::reader::{IndexReader, LeafReader};
+    use core::index::tests::*;
+    use core::index::writer::IndexWriterConfig;
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::{BooleanQuery, Query, TermQuery};
+    use core::search::similarity::{BM25Similarity, SimWeight, Similarity};
+    use core::search::statistics::{CollectionStatistics, TermStatistics};
+    use core::search::IndexSearcher;
+    use core::util::KeyedContext;
+    use std::fmt;
+    use std::sync::Arc;
+
+    // "a" is in every doc with a freq from 1 to 5, "b" in one doc out of
+    // four and "d" in one doc out of 97
+    fn docs() -> Vec<Vec<Box<dyn Fieldable>>> {
+        (0..1000)
+            .map(|i| {
+                let mut words = vec!["a"; i % 5 + 1];
+                if i % 4 == 0 {
+                    words.push("b");
+                }
+                if i % 97 == 0 {
+                    words.push("d");
+                }
+                vec![text_field("body", &words.join(" "))]
+            })
+            .collect()
+    }
+
+    fn block_impacts_config(
+        similarity: Arc<dyn Similarity<CodecEnum>>,
+    ) -> IndexWriterConfig<CodecEnum, SerialMergeScheduler, TieredMergePolicy> {
+        let mut config = IndexWriterConfig::new(
+            Arc::new(CodecEnum::Lucene62(Lucene62Codec::with_block_impacts(true))),
+            SerialMergeScheduler {},
+            TieredMergePolicy::default(),
+        );
+        config.similarity = similarity;
+        config
+    }
+
+    // scores with BM25, but the norms are the field lengths
+    struct LengthSimilarity;
+
+    impl Similarity<CodecEnum> for LengthSimilarity {
+        fn compute_weight(
+            &self,
+            collection_stats: &CollectionStatistics,
+            term_stats: &[TermStatistics],
+            context: Option<&KeyedContext>,
+            boost: f32,
+        ) -> Box<dyn SimWeight<CodecEnum>> {
+            BM25Similarity::default().compute_weight(collection_stats, term_stats, context, boost)
+        }
+
+        fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+            i64::from(state.length)
+        }
+    }
+
+    impl fmt::Display for LengthSimilarity {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "LengthSimilarity")
+        }
+    }
+
+    fn term_query(text: &str) -> Box<dyn Query<CodecEnum>> {
+        Box::new(TermQuery::new(
+            Term::new("body".into(), text.as_bytes().to_vec()),
+            1.0,
+            None,
+        ))
+    }
+
+    fn top_hits(index: &TestIndex, query: &dyn Query<CodecEnum>) -> (usize, Vec<(DocId, f32)>) {
+        let mut collector = TopDocsCollector::new(10);
+        index.searcher().search(query, &mut collector).unwrap();
+        let top_docs = collector.top_docs();
+        let hits = top_docs
+            .score_docs()
+            .iter()
+            .map(|d| (d.doc_id(), d.score()))
+            .collect();
+        (top_docs.total_hits(), hits)
+    }
+
+    #[test]
+    fn test_read_version_start() {
+        // the block impacts are only written on demand
+        let old_index = TestIndex::new(docs(), usize::max_value());
+        let index = TestIndex::with_config(
+            docs(),
+            usize::max_value(),
+            block_impacts_config(Arc::new(BM25Similarity::default())),
+        );
+
+        // only the newer version has block impacts in the skip data
+        let term = Term::new("body".into(), b"a".to_vec());
+        for (index, has_impacts) in &[(&old_index, false), (&index, true)] {
+            let leaves = index.reader.leaves();
+            assert_eq!(leaves.len(), 1);
+            let mut postings = leaves[0]
+                .reader
+                .postings(&term, i32::from(PostingIteratorFlags::FREQS))
+                .unwrap()
+                .unwrap();
+            let up_to = postings.advance_shallow(0).unwrap();
+            assert_eq!(up_to != NO_MORE_DOCS, *has_impacts);
+            // the skip data of both versions lets the postings skip
+            assert_eq!(postings.advance(500).unwrap(), 500);
+            assert_eq!(postings.freq().unwrap(), 1);
+            assert_eq!(postings.advance(998).unwrap(), 998);
+            assert_eq!(postings.freq().unwrap(), 4);
+        }
+
+        let queries = vec![
+            (term_query("a"), 1000),
+            (
+                BooleanQuery::build(vec![], vec![term_query("a"), term_query("b")], vec![])
+                    .unwrap(),
+                1000,
+            ),
+            (
+                BooleanQuery::build(vec![term_query("a"), term_query("d")], vec![], vec![])
+                    .unwrap(),
+                11,
+            ),
+        ];
+        for (query, expected_total_hits) in &queries {
+            let (total_hits, hits) = top_hits(&old_index, query.as_ref());
+            assert_eq!(total_hits, *expected_total_hits);
+            assert_eq!(hits.len(), 10);
+            assert_eq!(top_hits(&index, query.as_ref()), (total_hits, hits));
+        }
+    }
+
+    #[test]
+    fn test_block_impact_norms() {
+        let term = Term::new("body".into(), b"a".to_vec());
+        let block_norms = |similarity: Arc<dyn Similarity<CodecEnum>>| -> Vec<i64> {
+            let index = TestIndex::with_config(
+                docs(),
+                usize::max_value(),
+                block_impacts_config(similarity),
+            );
+            let leaves = index.reader.leaves();
+            let mut postings = leaves[0]
+                .reader
+                .postings(&term, i32::from(PostingIteratorFlags::FREQS))
+                .unwrap()
+                .unwrap();
+            let mut norms = vec![];
+            let mut target = 0;
+            while target != NO_MORE_DOCS {
+                let up_to = postings.advance_shallow(target).unwrap();
+                if let Some(impact) = postings.impact() {
+                    assert_eq!(impact.freq, 5);
+                    norms.push(impact.norm);
+                }
+                target = if up_to == NO_MORE_DOCS {
+                    up_to
+                } else {
+                    up_to + 1
+                };
+            }
+            norms
+        };
+
+        // the norm of the shortest docs, that have a single "a"
+        let norm = i64::from(BM25Similarity::encode_norm_value(1.0, 1));
+        let norms = block_norms(Arc::new(BM25Similarity::default()));
+        assert!(!norms.is_empty());
+        assert!(norms.iter().all(|n| *n == norm));
+
+        // the longest docs have the largest norm, which isn't the most competitive
+        let norms = block_norms(Arc::new(LengthSimilarity));
+        assert!(!norms.is_empty());
+        assert!(norms.iter().all(|n| *n == -1));
+    }
+}
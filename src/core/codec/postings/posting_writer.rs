@@ -11,6 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::FieldInfo;
 use core::codec::postings::blocktree::BlockTermState;
 use core::codec::postings::for_util::*;
@@ -20,7 +21,7 @@ use core::codec::postings::skip_writer::Lucene50SkipWriter;
 use core::codec::postings::PostingsWriterBase;
 use core::codec::segment_infos::{segment_file_name, SegmentWriteState};
 use core::codec::{write_footer, write_index_header, Codec, TermIterator};
-use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::codec::{Impact, PostingIterator, PostingIteratorFlags};
 use core::doc::IndexOptions;
 use core::index::writer::INDEX_MAX_POSITION;
 use core::search::{DocIterator, NO_MORE_DOCS};
@@ -62,6 +63,9 @@ pub struct Lucene50PostingsWriter<O: IndexOutput> {
     last_block_pay_fp: i64,
     last_block_pos_buffer_upto: usize,
     last_block_payload_byte_upto: usize,
+    last_block_impact: Impact,
+    // impact of the docs of the current block
+    block_impact: Impact,
 
     last_doc_id: DocId,
     last_position: usize,
@@ -82,13 +86,25 @@ pub struct Lucene50PostingsWriter<O: IndexOutput> {
     write_positions: bool,
     write_payloads: bool,
     write_offsets: bool,
+    // the format version written, see `VERSION_IMPACT_SKIP_DATA`
+    version: i32,
+    // whether the norms bound the scores, see `SegmentWriteState::monotonic_norms`
+    monotonic_norms: bool,
 }
 
 impl<O: IndexOutput> Lucene50PostingsWriter<O> {
+    /// Creates a writer of `VERSION_IMPACT_SKIP_DATA` if `write_block_impacts`
+    /// is true, of the Lucene 6.2.1 compatible `VERSION_START` otherwise.
     pub fn new<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
         state: &SegmentWriteState<D, DW, C>,
+        write_block_impacts: bool,
     ) -> Result<Self> {
         let acceptable_overhead_ratio = COMPACT;
+        let version = if write_block_impacts {
+            VERSION_IMPACT_SKIP_DATA
+        } else {
+            VERSION_START
+        };
 
         let doc_file_name = segment_file_name(
             &state.segment_info.name,
@@ -108,7 +124,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
         write_index_header(
             &mut doc_out,
             DOC_CODEC,
-            VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -128,7 +144,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             write_index_header(
                 pos_out.as_mut().unwrap(),
                 POS_CODEC,
-                VERSION_CURRENT,
+                version,
                 state.segment_info.get_id(),
                 &state.segment_suffix,
             )?;
@@ -155,7 +171,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
                 write_index_header(
                     pay_out.as_mut().unwrap(),
                     PAY_CODEC,
-                    VERSION_CURRENT,
+                    version,
                     state.segment_info.get_id(),
                     &state.segment_suffix,
                 )?;
@@ -168,6 +184,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             state.segment_info.max_doc() as u32,
             pos_out.is_some(),
             pay_out.is_some(),
+            version >= VERSION_IMPACT_SKIP_DATA,
         );
 
         Ok(Lucene50PostingsWriter {
@@ -197,6 +214,8 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             last_block_pay_fp: 0,
             last_block_pos_buffer_upto: 0,
             last_block_payload_byte_upto: 0,
+            last_block_impact: Impact::default(),
+            block_impact: Impact::default(),
 
             last_doc_id: 0,
             last_position: 0,
@@ -211,6 +230,8 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             write_positions: false,
             write_payloads: false,
             write_offsets: false,
+            version,
+            monotonic_norms: state.monotonic_norms,
         })
     }
 
@@ -260,11 +281,14 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
         }
         self.last_doc_id = 0;
         self.last_block_doc_id = -1;
+        self.block_impact.reset();
         self.skip_writer
             .reset_skip(self.doc_start_fp, self.pos_start_fp, self.pay_start_fp);
     }
 
-    pub fn start_doc(&mut self, doc_id: DocId, term_doc_freq: i32) -> Result<()> {
+    /// Starts a new doc, `norm` is its norm for the impacts of the skip data, 0
+    /// if the field has no norms.
+    pub fn start_doc(&mut self, doc_id: DocId, term_doc_freq: i32, norm: i64) -> Result<()> {
         // Have collected a block of docs, and get a new doc.
         // Should write skip data as well as postings list for
         // current block.
@@ -277,6 +301,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
                 self.last_block_pos_buffer_upto,
                 self.last_block_payload_byte_upto,
                 self.doc_out.file_pointer(),
+                &self.last_block_impact,
             )?;
         }
 
@@ -291,6 +316,9 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             self.freq_buffer[self.doc_buffer_upto] = term_doc_freq;
         }
 
+        self.block_impact
+            .merge(&Impact::new(term_doc_freq.max(1), norm));
+
         self.doc_buffer_upto += 1;
         self.doc_count += 1;
 
@@ -410,6 +438,8 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
         // write them to skip file.
         if self.doc_buffer_upto == BLOCK_SIZE as usize {
             self.last_block_doc_id = self.last_doc_id;
+            self.last_block_impact = self.block_impact;
+            self.block_impact.reset();
             if self.pos_out.is_some() {
                 if self.pay_out.is_some() {
                     self.last_block_pay_fp = self.pay_out.as_ref().unwrap().file_pointer();
@@ -549,7 +579,7 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
         write_index_header(
             terms_out,
             TERMS_CODEC,
-            VERSION_CURRENT,
+            self.version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -572,6 +602,7 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
         _term: &[u8],
         terms: &mut impl TermIterator,
         docs_seen: &mut FixedBitSet,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<Option<BlockTermState>> {
         self.start_term();
         let mut postings_enum = terms.postings_with_flags(self.enum_flags)?;
@@ -593,7 +624,13 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
                 -1
             };
 
-            self.start_doc(doc_id, freq)?;
+            let norm = match norms {
+                Some(norms) if self.monotonic_norms => norms.get(doc_id)?,
+                // any doc may have the most competitive norm
+                Some(_) => -1,
+                None => 0,
+            };
+            self.start_doc(doc_id, freq, norm)?;
 
             if self.write_positions {
                 for _ in 0..freq {
@@ -15,6 +15,7 @@ use std::io;
 use std::io::Read;
 
 use core::codec::postings::posting_format::BLOCK_SIZE;
+use core::codec::Impact;
 use core::store::io::{DataInput, IndexInput, RandomAccessInput};
 use core::util::{log, DocId};
 
@@ -175,6 +176,10 @@ pub struct Lucene50SkipReader {
     last_payload_byte_upto: i32,
     last_doc_pointer: i64,
     last_pos_buffer_upto: i32,
+
+    /// Impact of current skip entry per level, `None` for the indices written
+    /// before the skip data recorded impacts.
+    impacts: Option<Vec<Impact>>,
 }
 
 impl Lucene50SkipReader {
@@ -216,6 +221,8 @@ impl Lucene50SkipReader {
             last_payload_byte_upto: self.last_payload_byte_upto,
             last_doc_pointer: self.last_doc_pointer,
             last_pos_buffer_upto: self.last_pos_buffer_upto,
+
+            impacts: self.impacts.clone(),
         })
     }
 
@@ -225,6 +232,7 @@ impl Lucene50SkipReader {
         has_pos: bool,
         has_offsets: bool,
         has_payloads: bool,
+        has_impacts: bool,
     ) -> Lucene50SkipReader {
         // fields for MultiLevelSkipReader part
         let max_number_of_skip_levels = max_skip_levels;
@@ -266,6 +274,11 @@ impl Lucene50SkipReader {
                 pay_pointer = Some(vec![0_i64; max_skip_levels]);
             }
         };
+        let impacts = if has_impacts {
+            Some(vec![Impact::default(); max_skip_levels])
+        } else {
+            None
+        };
         Lucene50SkipReader {
             // MultiLevelSkipListReader
             max_number_of_skip_levels,
@@ -295,6 +308,8 @@ impl Lucene50SkipReader {
             last_payload_byte_upto: 0,
             last_doc_pointer: 0,
             last_pos_buffer_upto: 0,
+
+            impacts,
         }
     }
 
@@ -381,6 +396,15 @@ impl Lucene50SkipReader {
         self.skip_doc[0]
     }
 
+    /// Returns the impact of the block that ends at `next_skip_doc()`, `None`
+    /// if the skip data has no impacts or the skip list is exhausted.
+    pub fn impact(&self) -> Option<Impact> {
+        match self.impacts {
+            Some(ref impacts) if self.skip_doc[0] != i32::max_value() => Some(impacts[0]),
+            _ => None,
+        }
+    }
+
     /// Seeks the skip entry on the given level
     pub fn seek_child(&mut self, level: i32) -> Result<()> {
         let ulevel = level as usize;
@@ -449,6 +473,12 @@ impl Lucene50SkipReader {
                 self.pay_pointer.as_mut().unwrap()[level] += pointer;
             }
         }
+
+        if self.impacts.is_some() {
+            let freq = self.stream(level)?.read_vint()?;
+            let norm = self.stream(level)?.read_zlong()?;
+            self.impacts.as_mut().unwrap()[level] = Impact::new(freq, norm);
+        }
         Ok(delta)
     }
 
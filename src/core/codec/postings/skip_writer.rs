@@ -13,6 +13,7 @@
 
 use std::cmp::min;
 
+use core::codec::Impact;
 use core::store::io::{DataOutput, IndexOutput, RAMOutputStream};
 use core::util::{fill_slice, log, DocId};
 
@@ -37,6 +38,8 @@ const SKIP_MULTIPLIER: u32 = 8;
 /// 2. its related file points(position, payload),
 /// 3. related numbers or uptos(position, payload).
 /// 4. start offset.
+/// 5. the impact of the docs since the previous skip point of the same level,
+/// i.e. their highest freq and most competitive norm.
 pub struct Lucene50SkipWriter {
     last_skip_doc: Vec<i32>,
     last_skip_doc_pointer: Vec<i64>,
@@ -52,6 +55,10 @@ pub struct Lucene50SkipWriter {
     cur_pay_pointer: i64,
     cur_pos_buffer_upto: usize,
     cur_payload_byte_upto: usize,
+    // impacts accumulated since the last skip point of each level
+    level_impacts: Vec<Impact>,
+    // false for the versions of the format without impacts in the skip data
+    write_impacts: bool,
     field_has_positions: bool,
     field_has_offsets: bool,
     field_has_payloads: bool,
@@ -84,6 +91,7 @@ impl Lucene50SkipWriter {
         doc_count: u32,
         write_pos: bool,
         write_pay: bool,
+        write_impacts: bool,
     ) -> Self {
         debug_assert!(block_size > 1);
         let number_of_skip_levels = if doc_count <= block_size {
@@ -112,6 +120,8 @@ impl Lucene50SkipWriter {
             cur_pay_pointer: 0,
             cur_pos_buffer_upto: 0,
             cur_payload_byte_upto: 0,
+            level_impacts: vec![Impact::default(); max_skip_levels],
+            write_impacts,
             field_has_positions: false,
             field_has_offsets: false,
             field_has_payloads: false,
@@ -153,6 +163,9 @@ impl Lucene50SkipWriter {
             self.reset_skip_base();
             fill_slice(&mut self.last_skip_doc, 0);
             fill_slice(&mut self.last_skip_doc_pointer, self.last_doc_fp);
+            for impact in &mut self.level_impacts {
+                impact.reset();
+            }
             if self.field_has_positions {
                 fill_slice(&mut self.last_skip_pos_pointer, self.last_pos_fp);
                 if self.field_has_offsets || self.field_has_payloads {
@@ -193,6 +206,7 @@ impl Lucene50SkipWriter {
         pos_buffer_upto: usize,
         payload_byte_upto: usize,
         doc_out_pointer: i64,
+        impact: &Impact,
     ) -> Result<()> {
         self.init_skip();
         self.cur_doc = doc;
@@ -201,6 +215,9 @@ impl Lucene50SkipWriter {
         self.cur_pay_pointer = pay_fp;
         self.cur_pos_buffer_upto = pos_buffer_upto;
         self.cur_payload_byte_upto = payload_byte_upto;
+        for level_impact in &mut self.level_impacts {
+            level_impact.merge(impact);
+        }
         self.buffer_skip_levels(num_docs)
     }
 
@@ -285,6 +302,13 @@ impl Lucene50SkipWriter {
             }
         }
 
+        if self.write_impacts {
+            let impact = self.level_impacts[level];
+            self.skip_buffer[level].write_vint(impact.freq)?;
+            self.skip_buffer[level].write_zlong(impact.norm)?;
+        }
+        self.level_impacts[level].reset();
+
         Ok(())
     }
 }
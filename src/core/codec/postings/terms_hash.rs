@@ -295,9 +295,9 @@ where
                     Arc::new(state.field_infos.clone()),
                     Arc::clone(sort_map),
                 );
-                consumer.write(&fields)
+                consumer.write(&fields, norms.map(|n| n as &dyn NormsProducer))
             } else {
-                consumer.write(&fields)
+                consumer.write(&fields, norms.map(|n| n as &dyn NormsProducer))
             }
         } else {
             Ok(())
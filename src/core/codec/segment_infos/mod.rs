@@ -718,6 +718,11 @@ pub struct SegmentWriteState<D: Directory, DW: Directory, C: Codec> {
     /// {@link IOContext} for all writes; you should pass this
     /// to {@link Directory#createOutput(String,IOContext)}. */
     pub context: IOContext,
+
+    /// True if the norms are computed by a similarity that declares
+    /// `Similarity::monotonic_norms`, so that the postings may record them in
+    /// the impacts of their blocks.
+    pub monotonic_norms: bool,
 }
 
 impl<D: Directory, DW: Directory, C: Codec> SegmentWriteState<D, DW, C> {
@@ -739,6 +744,7 @@ impl<D: Directory, DW: Directory, C: Codec> SegmentWriteState<D, DW, C> {
             live_docs: FixedBitSet::default(),
             segment_suffix,
             context,
+            monotonic_norms: false,
         }
     }
 
@@ -780,6 +786,7 @@ impl<D: Directory, DW: Directory, C: Codec> Clone for SegmentWriteState<D, DW, C
             // TODO, fake clone
             segment_suffix: self.segment_suffix.clone(),
             context: self.context,
+            monotonic_norms: self.monotonic_norms,
         }
     }
 }
@@ -47,6 +47,8 @@ impl Sorter {
         match sort {
             SortField::Simple(s) => s.field_type(),
             SortField::SortedNumeric(s) => s.numeric_type(),
+            // rejected by `IndexWriter` as an index sort
+            SortField::Expression(_) => SortFieldType::Custom,
         }
     }
 
@@ -1,4 +1,4 @@
-use core::analysis::TokenStream;
+use core::analysis::{Analyzer, TokenStream};
 use core::codec::field_infos::FieldInfo;
 use core::codec::segment_infos::SegmentWriteState;
 use core::codec::stored_fields::{
@@ -355,7 +355,7 @@ impl Fieldable for CopyField {
         unreachable!()
     }
 
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+    fn token_stream(&mut self, _analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
         unreachable!()
     }
 
@@ -12,6 +12,9 @@
 // limitations under the License.
 
 use core::codec::{EmptyPostingIterator, PostingIterator, PostingIteratorFlags};
+use core::util::automaton::{
+    AutomatonTermIterEnum, AutomatonTermIterator, AutomatonType, CompiledAutomaton,
+};
 
 use error::ErrorKind::{IllegalArgument, UnsupportedOperation};
 use error::Result;
@@ -47,60 +50,39 @@ pub trait Terms {
     /// terms. This method will not return null. */
     fn iterator(&self) -> Result<Self::Iterator>;
 
-    /// Returns a TermsEnum that iterates over all terms and
-    /// documents that are accepted by the provided {@link
-    /// CompiledAutomaton}.  If the <code>startTerm</code> is
-    /// provided then the returned enum will only return terms
-    /// {@code > startTerm}, but you still must call
-    /// next() first to get to the first term.  Note that the
-    /// provided <code>startTerm</code> must be accepted by
+    /// Returns a `TermIterator` that iterates over all terms that are
+    /// accepted by the provided `CompiledAutomaton`. If the `start_term` is
+    /// provided then the returned iterator will only return terms
+    /// `> start_term`, but you still must call `next()` first to get to the
+    /// first term. Note that the provided `start_term` must be accepted by
     /// the automaton.
     ///
-    /// <p>This is an expert low-level API and will only work
-    /// for {@code NORMAL} compiled automata.  To handle any
-    /// compiled automata you should instead use
-    /// {@link CompiledAutomaton#getTermsEnum} instead.
+    /// This is an expert low-level API and will only work for
+    /// `AutomatonType::Normal` compiled automata. To handle any compiled
+    /// automata you should instead use `CompiledAutomaton::term_iterator`.
     ///
-    /// <p><b>NOTE</b>: the returned TermsEnum cannot seek</p>.
+    /// NOTE: the returned iterator can't seek.
     ///
-    /// <p><b>NOTE</b>: the terms dictionary is free to
-    /// return arbitrary terms as long as the resulted visited
-    /// docs is the same.  E.g., {@link BlockTreeTermsWriter}
-    /// creates auto-prefix terms during indexing to reduce the
-    /// number of terms visited. */
-    // fn TermsEnum intersect(CompiledAutomaton compiled, final BytesRef startTerm) throws
-    // IOException {
-    //
-    // TODO: could we factor out a common interface b/w
-    // CompiledAutomaton and FST?  Then we could pass FST there too,
-    // and likely speed up resolving terms to deleted docs ... but
-    // AutomatonTermsEnum makes this tricky because of its on-the-fly cycle
-    // detection
-    //
-    // TODO: eventually we could support seekCeil/Exact on
-    // the returned enum, instead of only being able to seek
-    // at the start
-    //
-    // TermsEnum termsEnum = iterator();
-    //
-    // if (compiled.type != CompiledAutomaton.AUTOMATON_TYPE.NORMAL) {
-    // throw new IllegalArgumentException("please use CompiledAutomaton.getTermsEnum instead");
-    // }
-    //
-    // if (startTerm == null) {
-    // return new AutomatonTermsEnum(termsEnum, compiled);
-    // } else {
-    // return new AutomatonTermsEnum(termsEnum, compiled) {
-    // @Override
-    // protected BytesRef nextSeekTerm(BytesRef term) throws IOException {
-    // if (term == null) {
-    // term = startTerm;
-    // }
-    // return super.nextSeekTerm(term);
-    // }
-    // };
-    // }
-    // }
+    /// The default implementation filters `iterator()` with an
+    /// `AutomatonTermIterator`, codecs may override it to skip the parts of
+    /// the terms dictionary the automaton can't accept.
+    fn intersect(
+        &self,
+        compiled: &CompiledAutomaton,
+        start_term: Option<&[u8]>,
+    ) -> Result<AutomatonTermIterEnum<Self::Iterator>> {
+        if compiled.automaton_type != AutomatonType::Normal {
+            bail!(IllegalArgument(
+                "please use CompiledAutomaton::term_iterator instead".into()
+            ));
+        }
+        let mut iter = AutomatonTermIterator::new(self.iterator()?, compiled);
+        if let Some(start_term) = start_term {
+            iter.set_initial_seek_term(start_term.to_vec());
+        }
+        Ok(AutomatonTermIterEnum::Normal(iter))
+    }
+
     /// Returns the number of terms for this field, or -1 if this
     /// measure isn't stored by the codec. Note that, just like
     /// other term measures, this measure does not take deleted
@@ -228,6 +210,14 @@ impl<T: Terms> Terms for Arc<T> {
         (**self).iterator()
     }
 
+    fn intersect(
+        &self,
+        compiled: &CompiledAutomaton,
+        start_term: Option<&[u8]>,
+    ) -> Result<AutomatonTermIterEnum<Self::Iterator>> {
+        (**self).intersect(compiled, start_term)
+    }
+
     fn size(&self) -> Result<i64> {
         (**self).size()
     }
@@ -531,22 +521,22 @@ where
             debug_assert!(self.base().actual_term.is_some());
             match self.accept(self.base().actual_term.as_ref().unwrap().as_slice())? {
                 AcceptStatus::YesAndSeek => {
+                    // term accepted, but we need to seek on the next call
                     self.base_mut().do_seek = true;
+                    return Ok(self.base().actual_term.clone());
                 }
                 AcceptStatus::Yes => {
                     return Ok(self.base().actual_term.clone());
                 }
                 AcceptStatus::NoAndSeek => {
                     self.base_mut().do_seek = true;
-                    break;
                 }
                 AcceptStatus::End => {
                     return Ok(None);
                 }
-                _ => {}
+                AcceptStatus::No => {}
             }
         }
-        unreachable!()
     }
 
     fn seek_exact(&mut self, _text: &[u8]) -> Result<bool> {
@@ -580,4 +570,8 @@ where
     fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
         self.base_mut().terms.postings_with_flags(flags)
     }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        self.base_mut().terms.term_state()
+    }
 }
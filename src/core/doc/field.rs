@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::analysis::{BinaryTokenStream, StringTokenStream, TokenStream};
+use core::analysis::{Analyzer, BinaryTokenStream, StringTokenStream, TokenStream};
 use core::doc::{DocValuesType, IndexOptions};
 use core::util::{BytesRef, Numeric, VariantValue};
 
@@ -81,7 +81,7 @@ impl Fieldable for Field {
     }
 
     // TODO currently this function should only be called once per doc field
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
         debug_assert_ne!(self.field_type.index_options, IndexOptions::Null);
 
         if !self.field_type.tokenized {
@@ -102,12 +102,16 @@ impl Fieldable for Field {
             }
         }
 
-        if self.token_stream.is_some() {
-            Ok(self.token_stream.take().unwrap())
-        } else {
-            // TODO currently not support analyzer
-            unimplemented!()
+        if let Some(token_stream) = self.token_stream.take() {
+            return Ok(token_stream);
         }
+        if let Some(VariantValue::VString(ref s)) = self.field_data {
+            return analyzer.token_stream_from_str(&self.field_name, s);
+        }
+        bail!(IllegalArgument(format!(
+            "tokenized field '{}' must have either a TokenStream or a String value",
+            self.field_name
+        )))
     }
 
     fn binary_value(&self) -> Option<&[u8]> {
@@ -145,7 +149,10 @@ pub trait Fieldable {
     fn field_type(&self) -> &FieldType;
     fn boost(&self) -> f32;
     fn field_data(&self) -> Option<&VariantValue>;
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>>;
+    /// Creates the `TokenStream` used for indexing this field, tokenized
+    /// fields without a `TokenStream` of their own are analyzed by
+    /// `analyzer`.
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>>;
     fn binary_value(&self) -> Option<&[u8]>;
     fn string_value(&self) -> Option<&str>;
     fn numeric_value(&self) -> Option<Numeric>;
@@ -164,8 +171,8 @@ impl<T: Fieldable + ?Sized> Fieldable for Box<T> {
     fn field_data(&self) -> Option<&VariantValue> {
         (**self).field_data()
     }
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
-        (**self).token_stream()
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
+        (**self).token_stream(analyzer)
     }
     fn binary_value(&self) -> Option<&[u8]> {
         (**self).binary_value()
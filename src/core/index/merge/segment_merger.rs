@@ -13,10 +13,10 @@
 
 use core::codec::doc_values::{DocValuesConsumer, DocValuesFormat};
 use core::codec::field_infos::{FieldInfosBuilder, FieldInfosFormat, FieldNumbersRef};
-use core::codec::norms::{NormsConsumer, NormsFormat};
+use core::codec::norms::{NormsConsumer, NormsFormat, NormsProducer};
 use core::codec::points::{PointsFormat, PointsWriter};
 use core::codec::postings::{FieldsConsumer, PostingsFormat};
-use core::codec::segment_infos::{SegmentInfo, SegmentWriteState};
+use core::codec::segment_infos::{SegmentInfo, SegmentReadState, SegmentWriteState};
 use core::codec::stored_fields::{StoredFieldsFormat, StoredFieldsWriter};
 use core::codec::term_vectors::{TermVectorsFormat, TermVectorsWriter};
 use core::codec::Codec;
@@ -39,6 +39,8 @@ pub struct SegmentMerger<D: Directory + 'static, DW: Directory, C: Codec> {
     context: IOContext,
     pub merge_state: MergeState<D, C>,
     field_infos_builder: FieldInfosBuilder<FieldNumbersRef>,
+    // see `SegmentWriteState::monotonic_norms`
+    monotonic_norms: bool,
 }
 
 impl<D, DW, C> SegmentMerger<D, DW, C>
@@ -54,6 +56,7 @@ where
         directory: Arc<DW>,
         field_numbers: FieldNumbersRef,
         context: IOContext,
+        monotonic_norms: bool,
     ) -> Result<Self> {
         if !context.is_merge() {
             bail!(IllegalArgument("IOContext should be merge!".into()));
@@ -67,6 +70,7 @@ where
             context,
             merge_state,
             field_infos_builder,
+            monotonic_norms,
         })
     }
 
@@ -87,7 +91,7 @@ where
         let num_merged = self.merge_fields()?;
         assert_eq!(num_merged, self.merge_state.segment_info().max_doc);
 
-        let segment_write_state = SegmentWriteState::new(
+        let mut segment_write_state = SegmentWriteState::new(
             Arc::clone(&self.directory),
             self.merge_state.segment_info().clone(),
             self.merge_state
@@ -100,36 +104,38 @@ where
             self.context,
             "".into(),
         );
-        self.merge_terms(&segment_write_state)?;
-
+        segment_write_state.monotonic_norms = self.monotonic_norms;
+        // the norms are merged first, so that the postings can record impacts
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_doc_values
+            .has_norms
         {
-            self.merge_doc_values(&segment_write_state)?;
+            self.merge_norms(&segment_write_state)?;
         }
+        self.merge_terms(&segment_write_state)?;
+
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_point_values
+            .has_doc_values
         {
-            self.merge_points(&segment_write_state)?;
+            self.merge_doc_values(&segment_write_state)?;
         }
-
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_norms
+            .has_point_values
         {
-            self.merge_norms(&segment_write_state)?;
+            self.merge_points(&segment_write_state)?;
         }
+
         if self
             .merge_state
             .merge_field_infos
@@ -225,10 +231,28 @@ where
     }
 
     fn merge_terms(&mut self, segment_write_state: &SegmentWriteState<D, DW, C>) -> Result<()> {
+        let field_infos = Arc::clone(self.merge_state.merge_field_infos.as_ref().unwrap());
+        let segment_info = self.merge_state.segment_info().clone();
+        let norms = if field_infos.has_norms {
+            let read_state = SegmentReadState::new(
+                Arc::clone(&self.directory),
+                &segment_info,
+                field_infos,
+                &IOContext::READ,
+                segment_write_state.segment_suffix.clone(),
+            );
+            Some(self.codec.norms_format().norms_producer(&read_state)?)
+        } else {
+            None
+        };
+
         let mut consumer = self
             .codec
             .postings_format()
             .fields_consumer(segment_write_state)?;
-        consumer.merge(&mut self.merge_state)
+        consumer.merge(
+            &mut self.merge_state,
+            norms.as_ref().map(|n| n as &dyn NormsProducer),
+        )
     }
 }
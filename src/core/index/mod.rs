@@ -30,8 +30,12 @@ error_chain! {
 
 #[cfg(test)]
 pub mod tests {
+    extern crate tempfile;
+
+    use self::tempfile::TempDir;
     use std::collections::HashMap;
 
+    use core::analysis::WhitespaceAnalyzer;
     use core::codec::doc_values::{
         BinaryDocValues, DocValuesProducerRef, NumericDocValues, SortedDocValues,
         SortedNumericDocValues, SortedSetDocValues,
@@ -39,10 +43,16 @@ pub mod tests {
     use core::codec::field_infos::{FieldInfo, FieldInfos};
     use core::codec::tests::TestCodec;
     use core::codec::*;
-    use core::doc::{DocValuesType, Document, IndexOptions, StoredFieldVisitor};
+    use core::doc::{
+        DocValuesType, Document, Field, FieldType, Fieldable, IndexOptions, StoredFieldVisitor,
+    };
+    use core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
     use core::index::reader::*;
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
     use core::search::similarity::BM25Similarity;
     use core::search::sort_field::Sort;
+    use core::search::{DefaultIndexSearcher, DefaultSimilarityProducer};
+    use core::store::directory::FSDirectory;
     use core::util::external::Deferred;
     use core::util::*;
     use error::Result;
@@ -342,4 +352,89 @@ pub mod tests {
             1
         }
     }
+
+    /// The reader of a `TestIndex`.
+    pub type TestIndexReader =
+        StandardDirectoryReader<FSDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+    /// A searcher of a `TestIndex`.
+    pub type TestIndexSearcher = DefaultIndexSearcher<
+        CodecEnum,
+        TestIndexReader,
+        Arc<TestIndexReader>,
+        DefaultSimilarityProducer,
+    >;
+
+    /// A real index written with the default codec in a temporary directory,
+    /// for the tests that need actual postings, the directory is removed on
+    /// drop.
+    pub struct TestIndex {
+        pub reader: Arc<TestIndexReader>,
+        _writer: IndexWriter<FSDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>,
+        _dir: TempDir,
+    }
+
+    impl TestIndex {
+        /// Indexes `docs`, a segment is committed every `docs_per_segment`
+        /// docs. The text fields are split at whitespace.
+        pub fn new(docs: Vec<Vec<Box<dyn Fieldable>>>, docs_per_segment: usize) -> TestIndex {
+            Self::with_config(docs, docs_per_segment, IndexWriterConfig::default())
+        }
+
+        /// Indexes `docs` like `new`, with the codec and the similarity of
+        /// `config`.
+        pub fn with_config(
+            docs: Vec<Vec<Box<dyn Fieldable>>>,
+            docs_per_segment: usize,
+            mut config: IndexWriterConfig<CodecEnum, SerialMergeScheduler, TieredMergePolicy>,
+        ) -> TestIndex {
+            let dir = tempfile::tempdir().unwrap();
+            config.analyzer = Arc::new(WhitespaceAnalyzer);
+            let directory = Arc::new(FSDirectory::with_path(dir.path()).unwrap());
+            let writer = IndexWriter::new(directory, Arc::new(config)).unwrap();
+            for (i, doc) in docs.into_iter().enumerate() {
+                writer.add_document(doc).unwrap();
+                if (i + 1) % docs_per_segment.max(1) == 0 {
+                    writer.commit().unwrap();
+                }
+            }
+            writer.commit().unwrap();
+            let reader = Arc::new(writer.get_reader(true, false).unwrap());
+            TestIndex {
+                reader,
+                _writer: writer,
+                _dir: dir,
+            }
+        }
+
+        pub fn searcher(&self) -> TestIndexSearcher {
+            DefaultIndexSearcher::new(Arc::clone(&self.reader), None, None)
+        }
+    }
+
+    /// A text field with positions, not stored, split at whitespace.
+    pub fn text_field(name: &str, text: &str) -> Box<dyn Fieldable> {
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+        Box::new(Field::new(
+            name.to_string(),
+            field_type,
+            Some(VariantValue::VString(text.to_string())),
+            None,
+        ))
+    }
+
+    /// A single term field, indexed with freqs and stored.
+    pub fn keyword_field(name: &str, value: &str) -> Box<dyn Fieldable> {
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqs;
+        field_type.tokenized = false;
+        field_type.stored = true;
+        Box::new(Field::new(
+            name.to_string(),
+            field_type,
+            Some(VariantValue::VString(value.to_string())),
+            None,
+        ))
+    }
 }
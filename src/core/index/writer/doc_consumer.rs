@@ -34,7 +34,7 @@ use core::store::directory::Directory;
 use core::store::IOContext;
 use core::util::{BytesRef, DocId, VariantValue};
 
-use core::search::similarity::BM25Similarity;
+use core::search::similarity::Similarity;
 
 use error::{
     ErrorKind::{IllegalArgument, UnsupportedOperation},
@@ -253,7 +253,7 @@ where
     unsafe fn process_field(
         &mut self,
         field: &mut impl Fieldable,
-        doc_state: &DocState,
+        doc_state: &DocState<C>,
         field_gen: i64,
         field_count: usize,
     ) -> Result<usize> {
@@ -414,7 +414,7 @@ where
         field_idx: usize,
         dv_type: DocValuesType,
         field: &impl Fieldable,
-        doc_state: &DocState,
+        doc_state: &DocState<C>,
     ) -> Result<()> {
         let per_field = &mut self.field_hash[field_idx];
 
@@ -510,7 +510,7 @@ where
         &mut self,
         field_idx: usize,
         field: &impl Fieldable,
-        doc_state: &DocState,
+        doc_state: &DocState<C>,
     ) -> Result<()> {
         let doc_writer = unsafe { &mut (*self.parent) };
         let per_field = &mut self.field_hash[field_idx];
@@ -605,7 +605,7 @@ where
 {
     pub fn process_document<F: Fieldable>(
         &mut self,
-        doc_state: &mut DocState,
+        doc_state: &mut DocState<C>,
         doc: &mut [F],
     ) -> Result<()> {
         // debug_assert!(self.inited);
@@ -810,14 +810,14 @@ impl<T: TermsHashPerField> PerField<T> {
         self.invert = true;
     }
 
-    fn finish(&mut self, doc_state: &DocState) -> Result<()> {
+    fn finish<C: Codec>(&mut self, doc_state: &DocState<C>) -> Result<()> {
         if !self.field_info().omit_norms && self.invert_state.length != 0 {
             debug_assert!(self.norms.is_some());
             let doc_id = doc_state.doc_id;
-            self.norms
-                .as_mut()
-                .unwrap()
-                .add_value(doc_id, BM25Similarity::compute_norm(&self.invert_state));
+            self.norms.as_mut().unwrap().add_value(
+                doc_id,
+                doc_state.similarity.compute_norm(&self.invert_state),
+            );
         }
 
         self.term_hash_per_field
@@ -841,7 +841,7 @@ impl<T: TermsHashPerField> PerField<T> {
     fn invert<D, C, MS, MP>(
         &mut self,
         field: &mut impl Fieldable,
-        doc_state: &DocState,
+        doc_state: &DocState<C>,
         first: bool,
         consumer: &mut DocConsumer<D, C, MS, MP>,
     ) -> Result<()>
@@ -874,7 +874,7 @@ impl<T: TermsHashPerField> PerField<T> {
                 .omit_norms = true;
         }
 
-        // let analyzed = field.field_type().tokenized() && doc_state.analyzer.is_some();
+        let analyzed = field.field_type().tokenized;
         // only bother checking offsets if something will consume them.
         // TODO: after we fix analyzers, also check if termVectorOffsets will be indexed.
         let check_offset = index_options == IndexOptions::DocsAndFreqsAndPositionsAndOffsets;
@@ -883,7 +883,8 @@ impl<T: TermsHashPerField> PerField<T> {
         // write the field name to the infostream when we fail. We expect some caller to
         // eventually deal with the real exception, so we don't want any 'catch' clauses,
         // but rather a finally that takes note of the problem.
-        let mut token_stream: Box<dyn TokenStream> = field.token_stream()?;
+        let mut token_stream: Box<dyn TokenStream> =
+            field.token_stream(doc_state.analyzer.as_ref())?;
         token_stream.reset()?;
 
         self.term_hash_per_field
@@ -967,10 +968,12 @@ impl<T: TermsHashPerField> PerField<T> {
         self.invert_state.position += token_stream.position_attribute_mut().get_position() as i32;
         self.invert_state.offset += token_stream.offset_attribute_mut().end_offset();
 
-        //        if analyzed {
-        //            self.invert_state.position += doc_state.analyzer.get_position_increment_gap();
-        //            self.invert_state.offset += doc_state.analyzer.get_offset_gap();
-        //        }
+        if analyzed {
+            let analyzer = &doc_state.analyzer;
+            let field_name = &self.invert_state.name;
+            self.invert_state.position += analyzer.position_increment_gap(field_name) as i32;
+            self.invert_state.offset += analyzer.offset_gap(field_name);
+        }
 
         self.invert_state.boost *= field.boost();
 
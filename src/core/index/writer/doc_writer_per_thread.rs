@@ -12,6 +12,7 @@
 // limitations under the License.
 
 use core::{
+    analysis::Analyzer,
     codec::field_infos::{FieldInfos, FieldInfosBuilder, FieldNumbers, FieldNumbersRef},
     codec::segment_infos::{SegmentCommitInfo, SegmentInfo, SegmentInfoFormat, SegmentWriteState},
     codec::{Codec, LiveDocsFormat},
@@ -21,6 +22,7 @@ use core::{
         FrozenBufferedUpdates, IndexWriterConfig, IndexWriterInner, INDEX_MAX_DOCS,
     },
     index::{merge::MergePolicy, merge::MergeScheduler},
+    search::similarity::Similarity,
     store::directory::{Directory, LockValidatingDirectoryWrapper, TrackingDirectoryWrapper},
     store::{FlushInfo, IOContext},
     util::{
@@ -46,17 +48,20 @@ use error::Result;
 use std::mem::MaybeUninit;
 use std::ptr;
 
-#[derive(Default)]
-pub struct DocState {
-    // analyzer: Analyzer,  // TODO, current Analyzer is not implemented
-    // pub similarity: Option<Box<Similarity>>,
+pub struct DocState<C: Codec> {
+    pub analyzer: Arc<dyn Analyzer>,
+    pub similarity: Arc<dyn Similarity<C>>,
     pub doc_id: DocId,
     // pub doc: Vec<Box<dyn Fieldable>>,
 }
 
-impl DocState {
-    pub fn new() -> Self {
-        Default::default()
+impl<C: Codec> DocState<C> {
+    pub fn new(analyzer: Arc<dyn Analyzer>, similarity: Arc<dyn Similarity<C>>) -> Self {
+        DocState {
+            analyzer,
+            similarity,
+            doc_id: 0,
+        }
     }
     pub fn clear(&mut self) {
         // self.doc = Vec::with_capacity(0);
@@ -77,7 +82,7 @@ pub struct DocumentsWriterPerThread<
     // we should use TrackingDirectoryWrapper instead
     pub directory: Arc<TrackingValidDirectory<D>>,
     pub directory_orig: Arc<D>,
-    pub doc_state: DocState,
+    pub doc_state: DocState<C>,
     pub consumer: MaybeUninit<DocConsumer<D, C, MS, MP>>,
     pending_updates: BufferedUpdates<C>,
     pub segment_info: SegmentInfo<D, C>,
@@ -139,8 +144,10 @@ where
             writer.config.index_sort().map(|s| s.clone()),
         )?;
         let delete_slice = delete_queue.new_slice();
-        let doc_state = DocState::new();
-        // doc_state.similarity = Some(index_writer_config.similarity());
+        let doc_state = DocState::new(
+            Arc::clone(index_writer_config.analyzer()),
+            Arc::clone(index_writer_config.similarity()),
+        );
         Ok(DocumentsWriterPerThread {
             directory,
             directory_orig,
@@ -206,7 +213,6 @@ where
         self.reserve_one_doc()?;
         // self.doc_state.doc = doc;
         self.doc_state.doc_id = self.num_docs_in_ram as i32;
-        // self.doc_state.analyzer = analyzer;
 
         // Even on exception, the document is still added (but marked
         // deleted), so we don't need to un-reserve at that point.
@@ -396,6 +402,7 @@ where
             ctx,
             "".into(),
         );
+        flush_state.monotonic_norms = self.doc_state.similarity.monotonic_norms();
 
         // Apply delete-by-docID now (delete-byDocID only
         // happens when an exception is hit processing that
@@ -36,6 +36,8 @@ use core::index::writer::{
     NumericDocValuesUpdate, OpenMode,
 };
 use core::search::query::{MatchAllDocsQuery, Query};
+use core::search::similarity::Similarity;
+use core::search::sort_field::SortField;
 use core::store::directory::{Directory, LockValidatingDirectoryWrapper, TrackingDirectoryWrapper};
 use core::store::{FlushInfo, IOContext};
 use core::util::random_id;
@@ -1196,11 +1198,22 @@ where
     /// Confirms that the incoming index sort (if any) matches the existing index
     /// sort (if any). This is unfortunately just best effort, because it could
     /// be the old index only has flushed segments.
+    ///
+    /// The sort fields must be stored doc values fields, expressions can't
+    /// sort an index.
     fn validate_index_sort<MS1: MergeScheduler, MP1: MergePolicy>(
         config: &IndexWriterConfig<C, MS1, MP1>,
         segment_infos: &SegmentInfos<D, C>,
     ) -> Result<()> {
         if let Some(index_sort) = config.index_sort() {
+            for sort_field in index_sort.get_sort() {
+                if let SortField::Expression(_) = sort_field {
+                    bail!(IllegalArgument(format!(
+                        "expression sort field [{}] can not sort an index",
+                        sort_field.field()
+                    )));
+                }
+            }
             for info in &segment_infos.segments {
                 if let Some(segment_sort) = info.info.index_sort() {
                     if segment_sort != index_sort {
@@ -2978,6 +2991,7 @@ where
             Arc::clone(&dir_wrapper),
             FieldNumbersRef::new(Arc::clone(&index_writer.writer.global_field_numbers)),
             context,
+            index_writer.writer.config.similarity().monotonic_norms(),
         )?;
         merge.rate_limiter.check_abort()?;
         merge.merge_start_time.write(Some(SystemTime::now()));
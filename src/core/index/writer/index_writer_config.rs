@@ -11,11 +11,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::analysis::{Analyzer, StandardAnalyzer};
 use core::codec::{Codec, CodecEnum, Lucene62Codec};
 use core::index::merge::MergeScheduler;
 use core::index::merge::SerialMergeScheduler;
 use core::index::merge::{MergePolicy, TieredMergePolicy};
 use core::index::writer::KeepOnlyLastCommitDeletionPolicy;
+use core::search::similarity::{BM25Similarity, Similarity};
 use core::search::sort_field::Sort;
 
 use std::sync::Arc;
@@ -70,6 +72,13 @@ pub struct IndexWriterConfig<C: Codec, MS: MergeScheduler, MP: MergePolicy> {
     pub open_mode: OpenMode,
     pub codec: Arc<C>,
     pub commit_on_close: bool,
+    /// Analyzer used for the tokenized fields that don't provide their own
+    /// `TokenStream`, a `StandardAnalyzer` by default.
+    pub analyzer: Arc<dyn Analyzer>,
+    /// Similarity whose `Similarity::compute_norm` encodes the norms of the
+    /// indexed fields, a `BM25Similarity` by default. It should match the
+    /// similarity used at search time.
+    pub similarity: Arc<dyn Similarity<C>>,
 }
 
 impl Default for IndexWriterConfig<CodecEnum, SerialMergeScheduler, TieredMergePolicy> {
@@ -95,6 +104,8 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
             open_mode: OpenMode::CreateOrAppend,
             codec,
             commit_on_close: true,
+            analyzer: Arc::new(StandardAnalyzer::default()),
+            similarity: Arc::new(BM25Similarity::default()),
         }
     }
 
@@ -133,4 +144,12 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
     pub fn codec(&self) -> &C {
         self.codec.as_ref()
     }
+
+    pub fn analyzer(&self) -> &Arc<dyn Analyzer> {
+        &self.analyzer
+    }
+
+    pub fn similarity(&self) -> &Arc<dyn Similarity<C>> {
+        &self.similarity
+    }
 }
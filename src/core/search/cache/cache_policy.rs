@@ -15,7 +15,8 @@ use std::cmp::max;
 use std::sync::Mutex;
 
 use core::codec::Codec;
-use core::search::query::{Weight, CONSTANT, MATCH_ALL, POINT_RANGE, TERM};
+use core::search::query::{Weight, AUTOMATON, CONSTANT, MATCH_ALL, POINT_RANGE, TERM};
+use core::search::query::{PREFIX, REGEXP, TERM_IN_SET, WILDCARD};
 
 use error::Result;
 
@@ -71,8 +72,10 @@ impl UsageTrackingQueryCachingPolicy {
     }
 
     fn is_costly<C: Codec>(w: &dyn Weight<C>) -> bool {
-        // TODO currently only PointRangeQuery is costly
-        w.actual_query_type() == POINT_RANGE
+        match w.actual_query_type() {
+            POINT_RANGE | AUTOMATON | PREFIX | WILDCARD | REGEXP | TERM_IN_SET => true,
+            _ => false,
+        }
     }
 
     fn is_cheap<C: Codec>(w: &dyn Weight<C>) -> bool {
@@ -37,6 +37,7 @@ use core::util::{Bits, DocId};
 
 use core::codec::Codec;
 use error::Result;
+use fasthash::murmur3;
 
 // A cache for queries.
 pub trait QueryCache<C: Codec>: Send + Sync {
@@ -254,17 +255,47 @@ impl<C: Codec> QueryCache<C> for LRUQueryCache {
         policy: Arc<dyn QueryCachingPolicy<C>>,
     ) -> Box<dyn Weight<C>> {
         if weight.query_type() == CACHING_QUERY_TYPE_STR {
-            weight
-        } else {
-            Box::new(CachingWrapperWeight::new(
-                Arc::clone(&self.cache_data),
-                weight,
-                policy,
-            ))
+            return weight;
         }
+        let query_key = query_key(weight.as_ref());
+        Box::new(CachingWrapperWeight::new(
+            Arc::clone(&self.cache_data),
+            weight,
+            policy,
+            query_key,
+        ))
     }
 }
 
+// the keys of the queries are kept for every cached query, the longer ones,
+// e.g. of term sets with many terms, are made compact
+const MAX_QUERY_KEY_LENGTH: usize = 1024;
+// the length of the prefix kept by a compact key, which holds the query type
+// and usually the field
+const COMPACT_KEY_PREFIX_LENGTH: usize = 128;
+
+/// Returns the key of `weight` in the cache, its string representation.
+///
+/// A long key is replaced by a compact one: a prefix of the key followed by
+/// the length and the 128 bits murmur3 hash of the whole key, which are
+/// exact as long as the hashes don't collide.
+fn query_key<C: Codec>(weight: &dyn Weight<C>) -> String {
+    let key = format!("{}", weight);
+    if key.len() <= MAX_QUERY_KEY_LENGTH {
+        return key;
+    }
+    let mut prefix_length = COMPACT_KEY_PREFIX_LENGTH;
+    while !key.is_char_boundary(prefix_length) {
+        prefix_length -= 1;
+    }
+    format!(
+        "{}...(length: {}, hash: {:032x})",
+        &key[..prefix_length],
+        key.len(),
+        murmur3::hash128(key.as_bytes())
+    )
+}
+
 struct CachingWrapperWeight<C: Codec> {
     cache_data: Arc<RwLock<CacheData>>,
     weight: Box<dyn Weight<C>>,
@@ -279,8 +310,8 @@ impl<C: Codec> CachingWrapperWeight<C> {
         cache_data: Arc<RwLock<CacheData>>,
         weight: Box<dyn Weight<C>>,
         policy: Arc<dyn QueryCachingPolicy<C>>,
+        query_key: String,
     ) -> CachingWrapperWeight<C> {
-        let query_key = format!("{}", weight);
         let mut hasher = DefaultHasher::new();
         query_key.hash(&mut hasher);
         CachingWrapperWeight {
@@ -944,3 +975,86 @@ impl DocIterator for CachedDocIdSetIterEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::Fieldable;
+    use core::index::tests::*;
+    use core::search::cache::AlwaysCacheQueryCachingPolicy;
+    use core::search::query::{Query, TermInSetQuery};
+    use core::search::searcher::SearchPlanBuilder;
+
+    fn count_docs(scorer: &mut dyn Scorer) -> usize {
+        let mut count = 0;
+        while scorer.next().unwrap() != NO_MORE_DOCS {
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_cache_large_term_set() {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = (0..100)
+            .map(|i| vec![keyword_field("id", &i.to_string())])
+            .collect();
+        let index = TestIndex::new(docs, usize::max_value());
+        let searcher = index.searcher();
+        let leaves = searcher.leaves();
+
+        let cache = LRUQueryCache::new(10);
+        {
+            let mut cache_data = cache.cache_data.write().unwrap();
+            cache_data.min_size = 0;
+            cache_data.min_size_ratio = 0.0;
+        }
+        let policy: Arc<dyn QueryCachingPolicy<CodecEnum>> =
+            Arc::new(AlwaysCacheQueryCachingPolicy::default());
+
+        // the even ids, the terms of the set sort as strings
+        let terms = (0..5000)
+            .map(|i| (i * 2).to_string().into_bytes())
+            .collect();
+        let query = TermInSetQuery::new("id".into(), terms);
+        let weight = Query::<CodecEnum>::create_weight(&query, &searcher, false).unwrap();
+        let key = format!("{}", weight);
+        assert!(key.len() > MAX_QUERY_KEY_LENGTH);
+
+        let weight = cache.do_cache(weight, Arc::clone(&policy));
+        assert_eq!(weight.query_type(), CACHING_QUERY_TYPE_STR);
+        let mut scorer = weight.create_scorer(&leaves[0]).unwrap().unwrap();
+        assert_eq!(count_docs(scorer.as_mut()), 50);
+
+        {
+            let cache_data = cache.cache_data.read().unwrap();
+            assert_eq!(cache_data.unique_queries.len(), 1);
+            let leaf_cache = &cache_data.cache[leaves[0].reader.core_cache_key()];
+            assert_eq!(leaf_cache.leaf_cache.len(), 1);
+            let cached_key = leaf_cache.leaf_cache.keys().next().unwrap();
+            assert!(cached_key.len() <= COMPACT_KEY_PREFIX_LENGTH + 64);
+            assert!(key.starts_with(&cached_key[..COMPACT_KEY_PREFIX_LENGTH]));
+        }
+
+        // the same set is served from the cache, a set with one more term isn't
+        let terms = (0..5000)
+            .map(|i| (i * 2).to_string().into_bytes())
+            .collect();
+        let query = TermInSetQuery::new("id".into(), terms);
+        let weight = Query::<CodecEnum>::create_weight(&query, &searcher, false).unwrap();
+        let weight = cache.do_cache(weight, Arc::clone(&policy));
+        let mut scorer = weight.create_scorer(&leaves[0]).unwrap().unwrap();
+        assert_eq!(count_docs(scorer.as_mut()), 50);
+        assert_eq!(cache.cache_data.read().unwrap().unique_queries.len(), 1);
+
+        let terms = (0..5001)
+            .map(|i| (i * 2).to_string().into_bytes())
+            .collect();
+        let query = TermInSetQuery::new("id".into(), terms);
+        let weight = Query::<CodecEnum>::create_weight(&query, &searcher, false).unwrap();
+        let weight = cache.do_cache(weight, policy);
+        let mut scorer = weight.create_scorer(&leaves[0]).unwrap().unwrap();
+        assert_eq!(count_docs(scorer.as_mut()), 50);
+        assert_eq!(cache.cache_data.read().unwrap().unique_queries.len(), 2);
+    }
+}
@@ -14,7 +14,7 @@
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
-use core::search::scorer::Scorer;
+use core::search::scorer::{NonPruningScorer, Scorer};
 use core::util::DocId;
 use error::Result;
 
@@ -78,9 +78,21 @@ where
         self.first.needs_scores() || self.second.needs_scores()
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        self.first.wants_min_competitive_score() && self.second.wants_min_competitive_score()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
-        self.first.collect(doc, scorer)?;
-        self.second.collect(doc, scorer)
+        if self.wants_min_competitive_score() {
+            self.first.collect(doc, scorer)?;
+            self.second.collect(doc, scorer)
+        } else {
+            // one of the collectors must see every hit, the other one must
+            // not make the scorer skip the non competitive hits
+            let mut scorer = NonPruningScorer::new(scorer);
+            self.first.collect(doc, &mut scorer)?;
+            self.second.collect(doc, &mut scorer)
+        }
     }
 }
 
@@ -95,3 +107,71 @@ where
         self.first.finish_leaf()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Fieldable, Term};
+    use core::index::tests::*;
+    use core::search::collector::TopDocsCollector;
+    use core::search::facet::FacetsCollector;
+    use core::search::query::{BooleanQuery, Query, TermQuery};
+    use core::search::sort_field::TopDocs;
+    use core::search::IndexSearcher;
+
+    #[test]
+    fn test_chain_with_counting_collector() {
+        // "a" is in the even docs with a varying freq, "b" in the multiples
+        // of 3: 2000 of the 3000 docs match the disjunction
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = (0..3000)
+            .map(|i| {
+                let mut words = vec![];
+                if i % 2 == 0 {
+                    words.extend(::std::iter::repeat("a").take(i % 7 + 1));
+                }
+                if i % 3 == 0 {
+                    words.push("b");
+                }
+                words.push("c");
+                vec![text_field("body", &words.join(" "))]
+            })
+            .collect();
+        let index = TestIndex::new(docs, 1000);
+        let searcher = index.searcher();
+        let term_query = |text: &str| -> Box<dyn Query<CodecEnum>> {
+            Box::new(TermQuery::new(
+                Term::new("body".into(), text.as_bytes().to_vec()),
+                1.0,
+                None,
+            ))
+        };
+        let query =
+            BooleanQuery::build(vec![], vec![term_query("a"), term_query("b")], vec![]).unwrap();
+
+        // alone, the top docs collector lets the scorer skip the hits
+        let mut top_docs_collector = TopDocsCollector::new(10);
+        searcher.search(&*query, &mut top_docs_collector).unwrap();
+        let top_docs = top_docs_collector.top_docs();
+        assert!(top_docs.total_hits() <= 2000);
+
+        // chained with a collector that needs every hit, nothing is skipped
+        let mut collector =
+            ChainedCollector::new(TopDocsCollector::new(10), FacetsCollector::default());
+        assert!(!collector.wants_min_competitive_score());
+        searcher.search(&*query, &mut collector).unwrap();
+        assert_eq!(collector.second.total_hits(), 2000);
+        let chained_top_docs = collector.first.top_docs();
+        assert_eq!(chained_top_docs.total_hits(), 2000);
+
+        let hits = |top_docs: &TopDocs| -> Vec<(DocId, f32)> {
+            top_docs
+                .score_docs()
+                .iter()
+                .map(|d| (d.doc_id(), d.score()))
+                .collect()
+        };
+        assert_eq!(hits(&top_docs).len(), 10);
+        assert_eq!(hits(&top_docs), hits(&chained_top_docs));
+    }
+}
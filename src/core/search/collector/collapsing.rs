@@ -0,0 +1,143 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{
+    Collector, GroupField, GroupHeadsCollector, GroupHeadsLeafCollector, SearchCollector,
+};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{CollapseTopFieldDocs, ScoreDocHit, Sort, TopDocs};
+use core::util::DocId;
+use error::Result;
+
+/// A `Collector` that collapses the hits by the value of a doc values
+/// field, only the top doc of every group value is kept.
+///
+/// The groups are sorted by the sort values of their top doc, the result is
+/// a `TopDocs::Collapse` whose `collapse_values` are the group values of the
+/// hits. Unlike a two-pass grouping search, the hits are collected once.
+pub struct CollapsingTopDocsCollector {
+    sort: Sort,
+    collector: GroupHeadsCollector,
+}
+
+impl CollapsingTopDocsCollector {
+    pub fn new(group_field: GroupField, sort: Sort, estimated_hits: usize) -> Self {
+        let collector = GroupHeadsCollector::new(group_field, sort.get_sort(), estimated_hits);
+        CollapsingTopDocsCollector { sort, collector }
+    }
+
+    /// Returns the top doc of the top groups, the total groups is the
+    /// number of distinct group values of the hits.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let heads = self.collector.top_group_heads();
+        let mut score_docs = Vec::with_capacity(heads.len());
+        let mut collapse_values = Vec::with_capacity(heads.len());
+        for (group_value, doc) in heads {
+            collapse_values.push(group_value);
+            score_docs.push(ScoreDocHit::Field(doc));
+        }
+        let base = self.collector.base();
+        TopDocs::Collapse(CollapseTopFieldDocs::new(
+            base.group_field().field().to_string(),
+            base.total_hits(),
+            base.total_groups(),
+            score_docs,
+            self.sort.get_sort().to_vec(),
+            collapse_values,
+            base.max_score(),
+        ))
+    }
+}
+
+impl SearchCollector for CollapsingTopDocsCollector {
+    type LC = GroupHeadsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.collector.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        self.collector.init_parallel()
+    }
+
+    fn leaf_collector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self::LC> {
+        self.collector.leaf_collector(reader)
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        self.collector.finish_parallel()
+    }
+}
+
+impl Collector for CollapsingTopDocsCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::sort_field::SortField;
+    use core::search::*;
+
+    #[test]
+    fn test_collapse() {
+        // the mock numeric doc values have distinct values for the docs 1 and 2
+        let mut scorer = create_mock_scorer(vec![1, 2]);
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        let group_field = GroupField::Numeric("norm".to_string());
+        let sort = Sort::new(vec![SortField::new_score()]);
+        let mut collector = CollapsingTopDocsCollector::new(group_field, sort, 1);
+        assert!(collector.needs_scores());
+
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 2);
+        assert_eq!(top_docs.total_groups(), 2);
+        assert_eq!(top_docs.score_docs().len(), 1);
+        assert_eq!(top_docs.score_docs()[0].doc_id(), 2);
+        if let TopDocs::Collapse(ref c) = top_docs {
+            assert_eq!(c.collapse_values.len(), 1);
+            assert!(c.collapse_values[0].get_long().is_some());
+        } else {
+            panic!("expected collapse top docs");
+        }
+    }
+}
@@ -0,0 +1,1057 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::doc_values::{NumericDocValues, SortedDocValues};
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{
+    compare_field_docs, Collector, FieldValueHitQueue, ParallelLeafCollector, SearchCollector,
+};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{
+    CollectedSearchGroup, ComparatorValue, FieldComparator, FieldComparatorEnum, FieldDoc,
+    GroupDocs, ScoreDocHit, SortField, SortFieldType, SortInfo, TopGroups, WilsonInfo,
+};
+use core::util::{BitsMut, DocId, VariantValue};
+use error::{ErrorKind, ErrorKind::IllegalState, Result, ResultExt};
+
+/// The doc values field the hits are grouped by.
+///
+/// The group values of a sorted field are strings, or binaries if they are
+/// not valid utf-8, the ones of a numeric field are longs. The docs without
+/// a value are not grouped, they are only counted in the total hits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupField {
+    Sorted(String),
+    Numeric(String),
+}
+
+impl GroupField {
+    pub fn field(&self) -> &str {
+        match self {
+            GroupField::Sorted(f) | GroupField::Numeric(f) => f,
+        }
+    }
+
+    fn leaf_values<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<LeafGroupValues> {
+        let values = match self {
+            GroupField::Sorted(f) => {
+                LeafGroupValues::Sorted(reader.reader.get_sorted_doc_values(f)?)
+            }
+            GroupField::Numeric(f) => LeafGroupValues::Numeric(
+                reader.reader.get_numeric_doc_values(f)?,
+                reader.reader.get_docs_with_field(f)?,
+            ),
+        };
+        Ok(values)
+    }
+}
+
+enum LeafGroupValues {
+    Sorted(Box<dyn SortedDocValues>),
+    Numeric(Box<dyn NumericDocValues>, Box<dyn BitsMut>),
+}
+
+impl LeafGroupValues {
+    fn group_value(&mut self, doc: DocId) -> Result<Option<VariantValue>> {
+        match self {
+            LeafGroupValues::Sorted(values) => {
+                let ord = values.get_ord(doc)?;
+                if ord < 0 {
+                    return Ok(None);
+                }
+                let value = match String::from_utf8(values.lookup_ord(ord)?) {
+                    Ok(s) => VariantValue::VString(s),
+                    Err(e) => VariantValue::Binary(e.into_bytes()),
+                };
+                Ok(Some(value))
+            }
+            LeafGroupValues::Numeric(values, docs_with_field) => {
+                let value = values.get_mut(doc)?;
+                if value == 0 && !docs_with_field.get(doc as usize)? {
+                    return Ok(None);
+                }
+                Ok(Some(VariantValue::Long(value)))
+            }
+        }
+    }
+}
+
+// the group value of a doc of the current leaf
+fn leaf_group_value(
+    leaf_values: &mut Option<LeafGroupValues>,
+    doc: DocId,
+) -> Result<Option<VariantValue>> {
+    match leaf_values {
+        Some(values) => values.group_value(doc),
+        None => bail!(IllegalState(
+            "set_next_reader must be called before collect".into()
+        )),
+    }
+}
+
+struct GroupHead {
+    group_value: VariantValue,
+    slot: usize,
+    doc: DocId,
+    score: f32,
+}
+
+/// Collects the top doc of the top groups of a segment, or of all the
+/// segments when the collection isn't parallel.
+pub(crate) struct GroupHeadsBase {
+    group_field: GroupField,
+    sort_fields: Vec<SortField>,
+    top_n_groups: usize,
+    comparators: Vec<FieldComparatorEnum>,
+    reverses: Vec<bool>,
+    heads: Vec<GroupHead>,
+    head_index: HashMap<VariantValue, usize>,
+    // the slot where a doc is copied to be compared with the head of its group
+    spare_slot: usize,
+    // the index of the head of the least competitive group once full
+    bottom: usize,
+    all_groups: HashSet<VariantValue>,
+    leaf_values: Option<LeafGroupValues>,
+    needs_scores: bool,
+    total_hits: usize,
+    max_score: f32,
+    doc_base: DocId,
+}
+
+impl GroupHeadsBase {
+    pub(crate) fn new(
+        group_field: GroupField,
+        sort_fields: &[SortField],
+        top_n_groups: usize,
+    ) -> Self {
+        // the extra slot is the spare one
+        let comparators = sort_fields
+            .iter()
+            .map(|f| f.get_comparator(top_n_groups + 1, f.missing_value()))
+            .collect();
+        GroupHeadsBase {
+            group_field,
+            sort_fields: sort_fields.to_vec(),
+            top_n_groups,
+            comparators,
+            reverses: sort_fields.iter().map(|f| f.is_reverse()).collect(),
+            heads: Vec::with_capacity(top_n_groups),
+            head_index: HashMap::with_capacity(top_n_groups),
+            spare_slot: top_n_groups,
+            bottom: 0,
+            all_groups: HashSet::new(),
+            leaf_values: None,
+            needs_scores: sort_fields.iter().any(|f| f.needs_scores()),
+            total_hits: 0,
+            max_score: f32::NEG_INFINITY,
+            doc_base: 0,
+        }
+    }
+
+    pub(crate) fn group_field(&self) -> &GroupField {
+        &self.group_field
+    }
+
+    pub(crate) fn sort_fields(&self) -> &[SortField] {
+        &self.sort_fields
+    }
+
+    pub(crate) fn total_hits(&self) -> usize {
+        self.total_hits
+    }
+
+    pub(crate) fn total_groups(&self) -> usize {
+        self.all_groups.len()
+    }
+
+    pub(crate) fn max_score(&self) -> f32 {
+        if self.max_score.is_finite() {
+            self.max_score
+        } else {
+            0.0
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.heads.len() == self.top_n_groups
+    }
+
+    pub(crate) fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.doc_base = reader.doc_base;
+        self.leaf_values = Some(self.group_field.leaf_values(reader)?);
+        for c in &mut self.comparators {
+            c.get_information_from_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    fn copy(&mut self, slot: usize, doc: DocId, score: f32) -> Result<()> {
+        for c in &mut self.comparators {
+            let value = if c.get_type() == SortFieldType::Score {
+                ComparatorValue::Score(score)
+            } else {
+                ComparatorValue::DocScore(doc, score)
+            };
+            c.copy(slot, value)?;
+        }
+        Ok(())
+    }
+
+    // `Ordering::Greater` if the doc is competitive with the bottom group
+    fn compare_bottom(&mut self, doc: DocId, score: f32) -> Result<Ordering> {
+        for (c, reverse) in self.comparators.iter_mut().zip(self.reverses.iter()) {
+            let value = if c.get_type() == SortFieldType::Score {
+                ComparatorValue::Score(score)
+            } else {
+                ComparatorValue::DocScore(doc, score)
+            };
+            let cmp = c.compare_bottom(value)?;
+            if cmp != Ordering::Equal {
+                return Ok(if *reverse { cmp.reverse() } else { cmp });
+            }
+        }
+        // the docs are collected in increasing order, a tie is not competitive
+        Ok(Ordering::Equal)
+    }
+
+    // `Ordering::Greater` if the head `h1` sorts after the head `h2`
+    fn compare_slots(&self, slot1: usize, doc1: DocId, slot2: usize, doc2: DocId) -> Ordering {
+        for (c, reverse) in self.comparators.iter().zip(self.reverses.iter()) {
+            let cmp = c.compare(slot1, slot2);
+            if cmp != Ordering::Equal {
+                return if *reverse { cmp.reverse() } else { cmp };
+            }
+        }
+        doc1.cmp(&doc2)
+    }
+
+    fn update_bottom(&mut self) {
+        let mut bottom = 0;
+        for i in 1..self.heads.len() {
+            let (h, b) = (&self.heads[i], &self.heads[bottom]);
+            if self.compare_slots(h.slot, h.doc, b.slot, b.doc) == Ordering::Greater {
+                bottom = i;
+            }
+        }
+        self.bottom = bottom;
+        let slot = self.heads[bottom].slot;
+        for c in &mut self.comparators {
+            c.set_bottom(slot);
+        }
+    }
+
+    /// Drains the heads of the groups into unsorted `FieldDoc`s.
+    pub(crate) fn drain_heads(&mut self) -> Vec<(VariantValue, FieldDoc)> {
+        self.head_index.clear();
+        let heads = mem::replace(&mut self.heads, Vec::with_capacity(self.top_n_groups));
+        heads
+            .into_iter()
+            .map(|h| {
+                let fields = self.comparators.iter().map(|c| c.value(h.slot)).collect();
+                (h.group_value, FieldDoc::new(h.doc, h.score, fields))
+            })
+            .collect()
+    }
+}
+
+impl Collector for GroupHeadsBase {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        let score = if self.needs_scores {
+            let score = scorer.score()?;
+            if score > self.max_score {
+                self.max_score = score;
+            }
+            score
+        } else {
+            0.0
+        };
+        self.total_hits += 1;
+
+        let group_value = match leaf_group_value(&mut self.leaf_values, doc)? {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if !self.all_groups.contains(&group_value) {
+            self.all_groups.insert(group_value.clone());
+        }
+
+        // a doc that can't enter the top groups can't improve a top group either
+        if self.is_full() && self.compare_bottom(doc, score)? != Ordering::Greater {
+            return Ok(());
+        }
+
+        let global_doc = doc + self.doc_base;
+        match self.head_index.get(&group_value).cloned() {
+            Some(i) => {
+                let spare_slot = self.spare_slot;
+                self.copy(spare_slot, doc, score)?;
+                let head_slot = self.heads[i].slot;
+                let head_doc = self.heads[i].doc;
+                if self.compare_slots(spare_slot, global_doc, head_slot, head_doc) == Ordering::Less
+                {
+                    let head = &mut self.heads[i];
+                    head.slot = spare_slot;
+                    head.doc = global_doc;
+                    head.score = score;
+                    self.spare_slot = head_slot;
+                    if self.is_full() {
+                        self.update_bottom();
+                    }
+                }
+            }
+            None => {
+                let head = GroupHead {
+                    group_value: group_value.clone(),
+                    slot: 0,
+                    doc: global_doc,
+                    score,
+                };
+                let index = if self.is_full() {
+                    // the new group replaces the bottom one
+                    let bottom = self.bottom;
+                    self.head_index.remove(&self.heads[bottom].group_value);
+                    let slot = self.heads[bottom].slot;
+                    self.heads[bottom] = GroupHead { slot, ..head };
+                    bottom
+                } else {
+                    let slot = self.heads.len();
+                    self.heads.push(GroupHead { slot, ..head });
+                    slot
+                };
+                let slot = self.heads[index].slot;
+                self.copy(slot, doc, score)?;
+                self.head_index.insert(group_value, index);
+                if self.is_full() {
+                    self.update_bottom();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merges the heads of the groups collected from several segments, keeping
+/// the top doc of every group, and returns the top groups sorted.
+pub(crate) fn merge_group_heads(
+    sort_fields: &[SortField],
+    heads: Vec<(VariantValue, FieldDoc)>,
+    top_n_groups: usize,
+) -> Vec<(VariantValue, FieldDoc)> {
+    let mut merged: Vec<(VariantValue, FieldDoc)> = Vec::with_capacity(heads.len());
+    let mut index: HashMap<VariantValue, usize> = HashMap::with_capacity(heads.len());
+    for (group_value, doc) in heads {
+        if let Some(&i) = index.get(&group_value) {
+            if compare_field_docs(sort_fields, false, &doc, &merged[i].1) == Ordering::Less {
+                merged[i].1 = doc;
+            }
+        } else {
+            index.insert(group_value.clone(), merged.len());
+            merged.push((group_value, doc));
+        }
+    }
+    merged.sort_by(|(_, d1), (_, d2)| compare_field_docs(sort_fields, false, d1, d2));
+    merged.truncate(top_n_groups);
+    merged
+}
+
+struct LeafGroupHeads {
+    heads: Vec<(VariantValue, FieldDoc)>,
+    groups: HashSet<VariantValue>,
+    total_hits: usize,
+    max_score: f32,
+}
+
+/// Collects the top groups and their top doc, shared by the first pass
+/// grouping collector and the collapsing collector.
+pub(crate) struct GroupHeadsCollector {
+    base: GroupHeadsBase,
+    // the heads collected by the leaf collectors of a parallel search
+    leaf_heads: Vec<(VariantValue, FieldDoc)>,
+    channel: Option<(Sender<LeafGroupHeads>, Receiver<LeafGroupHeads>)>,
+}
+
+impl GroupHeadsCollector {
+    pub(crate) fn new(
+        group_field: GroupField,
+        sort_fields: &[SortField],
+        top_n_groups: usize,
+    ) -> Self {
+        assert!(
+            top_n_groups > 0,
+            format!("top_n_groups must always be > 0, got {}", top_n_groups)
+        );
+        GroupHeadsCollector {
+            base: GroupHeadsBase::new(group_field, sort_fields, top_n_groups),
+            leaf_heads: vec![],
+            channel: None,
+        }
+    }
+
+    pub(crate) fn base(&self) -> &GroupHeadsBase {
+        &self.base
+    }
+
+    /// Returns the top groups with their top doc, sorted.
+    pub(crate) fn top_group_heads(&mut self) -> Vec<(VariantValue, FieldDoc)> {
+        let mut heads = self.base.drain_heads();
+        heads.extend(self.leaf_heads.drain(..));
+        merge_group_heads(&self.base.sort_fields, heads, self.base.top_n_groups)
+    }
+
+    pub(crate) fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.base.set_next_reader(reader)
+    }
+
+    pub(crate) fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    pub(crate) fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<GroupHeadsLeafCollector> {
+        let mut collector = GroupHeadsBase::new(
+            self.base.group_field.clone(),
+            &self.base.sort_fields,
+            self.base.top_n_groups,
+        );
+        collector.set_next_reader(reader)?;
+        Ok(GroupHeadsLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    pub(crate) fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(heads) = receiver.recv() {
+                self.base.total_hits += heads.total_hits;
+                self.base.max_score = self.base.max_score.max(heads.max_score);
+                self.base.all_groups.extend(heads.groups);
+                self.leaf_heads.extend(heads.heads);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for GroupHeadsCollector {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+pub struct GroupHeadsLeafCollector {
+    collector: GroupHeadsBase,
+    channel: Sender<LeafGroupHeads>,
+}
+
+impl ParallelLeafCollector for GroupHeadsLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let heads = LeafGroupHeads {
+            heads: self.collector.drain_heads(),
+            groups: mem::replace(&mut self.collector.all_groups, HashSet::new()),
+            total_hits: self.collector.total_hits,
+            max_score: self.collector.max_score,
+        };
+        self.channel
+            .send(heads)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for GroupHeadsLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+/// The first pass of a grouping search, it finds the top groups sorted by
+/// the sort values of their top doc.
+///
+/// The top docs of the groups are then collected by a
+/// `SecondPassGroupingCollector`.
+pub struct FirstPassGroupingCollector {
+    collector: GroupHeadsCollector,
+}
+
+impl FirstPassGroupingCollector {
+    pub fn new(group_field: GroupField, group_sort: &[SortField], top_n_groups: usize) -> Self {
+        FirstPassGroupingCollector {
+            collector: GroupHeadsCollector::new(group_field, group_sort, top_n_groups),
+        }
+    }
+
+    pub fn total_hits(&self) -> usize {
+        self.collector.base().total_hits()
+    }
+
+    /// The number of distinct groups of the hits, not only the top ones.
+    pub fn total_group_count(&self) -> usize {
+        self.collector.base().total_groups()
+    }
+
+    /// Returns the top groups sorted by the group sort, the comparator slot
+    /// of a group is its rank as the comparators are not kept.
+    pub fn top_groups(&mut self) -> Vec<CollectedSearchGroup> {
+        let sort_fields = self.collector.base().sort_fields().to_vec();
+        self.collector
+            .top_group_heads()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, (group_value, doc))| {
+                let sort_info_list = sort_fields
+                    .iter()
+                    .zip(doc.fields.iter())
+                    .filter_map(|(f, v)| match f.field_type() {
+                        SortFieldType::Score => v.get_float().map(|s| {
+                            SortInfo::new(SortFieldType::Score, ComparatorValue::Score(s))
+                        }),
+                        SortFieldType::Doc => v
+                            .get_int()
+                            .map(|d| SortInfo::new(SortFieldType::Doc, ComparatorValue::Doc(d))),
+                        _ => None,
+                    })
+                    .collect();
+                let wilson_info = WilsonInfo::new(doc.doc, VariantValue::Float(doc.score), None);
+                CollectedSearchGroup::new(group_value, sort_info_list, wilson_info, rank, doc.doc)
+                    .with_sort_values(doc.fields)
+            })
+            .collect()
+    }
+}
+
+impl SearchCollector for FirstPassGroupingCollector {
+    type LC = GroupHeadsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.collector.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        self.collector.init_parallel()
+    }
+
+    fn leaf_collector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self::LC> {
+        self.collector.leaf_collector(reader)
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        self.collector.finish_parallel()
+    }
+}
+
+impl Collector for FirstPassGroupingCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+struct GroupQueues {
+    queues: Vec<FieldValueHitQueue>,
+    total_hits: Vec<usize>,
+    max_scores: Vec<f32>,
+    total_hits_all: usize,
+}
+
+impl GroupQueues {
+    fn new(within_group_sort: &[SortField], num_groups: usize, top_docs_per_group: usize) -> Self {
+        GroupQueues {
+            queues: (0..num_groups)
+                .map(|_| FieldValueHitQueue::new(within_group_sort, top_docs_per_group, false))
+                .collect(),
+            total_hits: vec![0; num_groups],
+            max_scores: vec![f32::NEG_INFINITY; num_groups],
+            total_hits_all: 0,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        for q in &mut self.queues {
+            q.set_next_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    fn collect(&mut self, group: usize, doc: DocId, doc_base: DocId, score: f32) -> Result<()> {
+        self.total_hits[group] += 1;
+        if score > self.max_scores[group] {
+            self.max_scores[group] = score;
+        }
+        let queue = &mut self.queues[group];
+        if queue.is_full() && queue.compare_bottom(doc, score)? != Ordering::Greater {
+            return Ok(());
+        }
+        queue.add(doc, doc_base, score)
+    }
+
+    fn drain(&mut self) -> Vec<Vec<FieldDoc>> {
+        self.queues
+            .iter_mut()
+            .map(|q| q.drain_field_docs())
+            .collect()
+    }
+}
+
+struct SecondPassBase {
+    group_field: GroupField,
+    group_index: HashMap<VariantValue, usize>,
+    queues: GroupQueues,
+    leaf_values: Option<LeafGroupValues>,
+    needs_scores: bool,
+    doc_base: DocId,
+}
+
+impl SecondPassBase {
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.doc_base = reader.doc_base;
+        self.leaf_values = Some(self.group_field.leaf_values(reader)?);
+        self.queues.set_next_reader(reader)
+    }
+}
+
+impl Collector for SecondPassBase {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.queues.total_hits_all += 1;
+        let group_value = match leaf_group_value(&mut self.leaf_values, doc)? {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if let Some(&group) = self.group_index.get(&group_value) {
+            let score = if self.needs_scores {
+                scorer.score()?
+            } else {
+                0.0
+            };
+            self.queues.collect(group, doc, self.doc_base, score)?;
+        }
+        Ok(())
+    }
+}
+
+struct LeafGroupDocs {
+    docs: Vec<Vec<FieldDoc>>,
+    total_hits: Vec<usize>,
+    max_scores: Vec<f32>,
+    total_hits_all: usize,
+}
+
+/// The second pass of a grouping search, it collects the top docs of every
+/// group found by a `FirstPassGroupingCollector`, sorted by the within
+/// group sort.
+pub struct SecondPassGroupingCollector {
+    groups: Vec<CollectedSearchGroup>,
+    group_sort: Vec<SortField>,
+    within_group_sort: Vec<SortField>,
+    top_docs_per_group: usize,
+    base: SecondPassBase,
+    // the docs collected by the leaf collectors of a parallel search
+    leaf_docs: Vec<Vec<FieldDoc>>,
+    channel: Option<(Sender<LeafGroupDocs>, Receiver<LeafGroupDocs>)>,
+}
+
+impl SecondPassGroupingCollector {
+    pub fn new(
+        group_field: GroupField,
+        groups: Vec<CollectedSearchGroup>,
+        group_sort: &[SortField],
+        within_group_sort: &[SortField],
+        top_docs_per_group: usize,
+    ) -> Result<Self> {
+        if top_docs_per_group == 0 {
+            bail!(ErrorKind::IllegalArgument(
+                "top_docs_per_group must be positive".into()
+            ));
+        }
+        let group_index = groups
+            .iter()
+            .enumerate()
+            .map(|(i, g)| (g.group_value.clone(), i))
+            .collect();
+        let base = SecondPassBase {
+            group_field,
+            group_index,
+            queues: GroupQueues::new(within_group_sort, groups.len(), top_docs_per_group),
+            leaf_values: None,
+            needs_scores: within_group_sort.iter().any(|f| f.needs_scores()),
+            doc_base: 0,
+        };
+        Ok(SecondPassGroupingCollector {
+            leaf_docs: vec![vec![]; groups.len()],
+            groups,
+            group_sort: group_sort.to_vec(),
+            within_group_sort: within_group_sort.to_vec(),
+            top_docs_per_group,
+            base,
+            channel: None,
+        })
+    }
+
+    /// Returns the top docs of the groups, in the order of the first pass.
+    pub fn top_groups(&mut self) -> TopGroups {
+        let mut total_grouped_hits = 0;
+        let docs = self.base.queues.drain();
+        let mut groups = Vec::with_capacity(self.groups.len());
+        for (i, (mut group_docs, leaf_docs)) in
+            docs.into_iter().zip(self.leaf_docs.iter_mut()).enumerate()
+        {
+            group_docs.extend(leaf_docs.drain(..));
+            let within_group_sort = &self.within_group_sort;
+            group_docs.sort_by(|d1, d2| compare_field_docs(within_group_sort, false, d1, d2));
+            group_docs.truncate(self.top_docs_per_group);
+
+            let total_hits = self.base.queues.total_hits[i];
+            total_grouped_hits += total_hits;
+            let max_score = self.base.queues.max_scores[i];
+            groups.push(GroupDocs {
+                group_value: self.groups[i].group_value.clone(),
+                group_sort_values: self.groups[i].sort_values.clone(),
+                total_hits,
+                score_docs: group_docs.into_iter().map(ScoreDocHit::Field).collect(),
+                max_score: if max_score.is_finite() {
+                    max_score
+                } else {
+                    0.0
+                },
+            });
+        }
+        TopGroups {
+            total_hits: self.base.queues.total_hits_all,
+            total_grouped_hits,
+            groups,
+            group_sort: self.group_sort.clone(),
+            within_group_sort: self.within_group_sort.clone(),
+        }
+    }
+}
+
+impl SearchCollector for SecondPassGroupingCollector {
+    type LC = SecondPassLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self::LC> {
+        let mut collector = SecondPassBase {
+            group_field: self.base.group_field.clone(),
+            group_index: self.base.group_index.clone(),
+            queues: GroupQueues::new(
+                &self.within_group_sort,
+                self.groups.len(),
+                self.top_docs_per_group,
+            ),
+            leaf_values: None,
+            needs_scores: self.base.needs_scores,
+            doc_base: 0,
+        };
+        collector.set_next_reader(reader)?;
+        Ok(SecondPassLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                let queues = &mut self.base.queues;
+                queues.total_hits_all += docs.total_hits_all;
+                for (i, group_docs) in docs.docs.into_iter().enumerate() {
+                    queues.total_hits[i] += docs.total_hits[i];
+                    queues.max_scores[i] = queues.max_scores[i].max(docs.max_scores[i]);
+                    self.leaf_docs[i].extend(group_docs);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for SecondPassGroupingCollector {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+pub struct SecondPassLeafCollector {
+    collector: SecondPassBase,
+    channel: Sender<LeafGroupDocs>,
+}
+
+impl ParallelLeafCollector for SecondPassLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let queues = &mut self.collector.queues;
+        let docs = LeafGroupDocs {
+            docs: queues.drain(),
+            total_hits: queues.total_hits.clone(),
+            max_scores: queues.max_scores.clone(),
+            total_hits_all: queues.total_hits_all,
+        };
+        self.channel
+            .send(docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for SecondPassLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Fieldable, NumericDocValuesField, Term};
+    use core::index::tests::*;
+    use core::search::query::{Query, TermQuery};
+    use core::search::sort_field::SimpleSortField;
+    use core::search::tests::create_mock_scorer;
+    use core::search::{DocIterator, IndexSearcher, SearchPlanBuilder, NO_MORE_DOCS};
+
+    // "group" is `i % 4` but for the docs `i % 5 == 4`, "price" is `i`
+    fn test_index() -> TestIndex {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = (0..40)
+            .map(|i| {
+                let mut doc: Vec<Box<dyn Fieldable>> = vec![
+                    text_field("body", "a"),
+                    Box::new(NumericDocValuesField::new("price", i)),
+                ];
+                if i % 5 != 4 {
+                    doc.push(Box::new(NumericDocValuesField::new("group", i % 4)));
+                }
+                doc
+            })
+            .collect();
+        TestIndex::new(docs, 10)
+    }
+
+    fn price_sort(reverse: bool) -> Vec<SortField> {
+        vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            reverse,
+        ))]
+    }
+
+    // collects every leaf with its own leaf collector, as a parallel search
+    // does, but in the current thread
+    fn collect_leaves<S: SearchCollector>(
+        searcher: &TestIndexSearcher,
+        query: &dyn Query<CodecEnum>,
+        collector: &mut S,
+    ) {
+        let weight = searcher
+            .create_weight(query, collector.needs_scores())
+            .unwrap();
+        collector.init_parallel();
+        for leaf in searcher.leaves() {
+            let mut leaf_collector = collector.leaf_collector(&leaf).unwrap();
+            if let Some(mut scorer) = weight.create_scorer(&leaf).unwrap() {
+                loop {
+                    let doc = scorer.next().unwrap();
+                    if doc == NO_MORE_DOCS {
+                        break;
+                    }
+                    leaf_collector.collect(doc, scorer.as_mut()).unwrap();
+                }
+            }
+            leaf_collector.finish_leaf().unwrap();
+        }
+        collector.finish_parallel().unwrap();
+    }
+
+    fn grouped_prices(top_groups: &TopGroups) -> Vec<(VariantValue, Vec<VariantValue>)> {
+        top_groups
+            .groups
+            .iter()
+            .map(|g| {
+                let prices = g
+                    .score_docs
+                    .iter()
+                    .map(|d| match d {
+                        ScoreDocHit::Field(f) => f.fields[0].clone(),
+                        ScoreDocHit::Score(_) => unreachable!(),
+                    })
+                    .collect();
+                (g.group_value.clone(), prices)
+            })
+            .collect()
+    }
+
+    fn group_search(parallel: bool) -> (FirstPassGroupingCollector, TopGroups) {
+        let index = test_index();
+        let searcher = index.searcher();
+        let query = TermQuery::new(Term::new("body".into(), b"a".to_vec()), 1.0, None);
+        let group_field = GroupField::Numeric("group".into());
+
+        // the groups by their most expensive doc, the 3 cheapest docs of each
+        let mut first_pass =
+            FirstPassGroupingCollector::new(group_field.clone(), &price_sort(true), 3);
+        if parallel {
+            collect_leaves(&searcher, &query, &mut first_pass);
+        } else {
+            searcher.search(&query, &mut first_pass).unwrap();
+        }
+        let mut second_pass = SecondPassGroupingCollector::new(
+            group_field,
+            first_pass.top_groups(),
+            &price_sort(true),
+            &price_sort(false),
+            3,
+        )
+        .unwrap();
+        if parallel {
+            collect_leaves(&searcher, &query, &mut second_pass);
+        } else {
+            searcher.search(&query, &mut second_pass).unwrap();
+        }
+        let top_groups = second_pass.top_groups();
+        (first_pass, top_groups)
+    }
+
+    #[test]
+    fn test_grouping() {
+        let (first_pass, top_groups) = group_search(false);
+        // the docs without a group are counted, but not grouped
+        assert_eq!(first_pass.total_hits(), 40);
+        assert_eq!(first_pass.total_group_count(), 4);
+        assert_eq!(top_groups.total_hits, 40);
+        assert_eq!(top_groups.total_grouped_hits, 24);
+
+        // the group 3 is the 4th one, its most expensive doc 39 has no group
+        let group_sort_values: Vec<_> = top_groups
+            .groups
+            .iter()
+            .map(|g| g.group_sort_values.clone())
+            .collect();
+        assert_eq!(
+            group_sort_values,
+            vec![
+                vec![VariantValue::Long(38)],
+                vec![VariantValue::Long(37)],
+                vec![VariantValue::Long(36)],
+            ]
+        );
+        let long = |v: i64| VariantValue::Long(v);
+        assert_eq!(
+            grouped_prices(&top_groups),
+            vec![
+                (long(2), vec![long(2), long(6), long(10)]),
+                (long(1), vec![long(1), long(5), long(13)]),
+                (long(0), vec![long(0), long(8), long(12)]),
+            ]
+        );
+        for group in &top_groups.groups {
+            assert_eq!(group.total_hits, 8);
+        }
+    }
+
+    #[test]
+    fn test_parallel_grouping() {
+        let (serial_first_pass, serial_groups) = group_search(false);
+        let (first_pass, top_groups) = group_search(true);
+        assert_eq!(first_pass.total_hits(), serial_first_pass.total_hits());
+        assert_eq!(
+            first_pass.total_group_count(),
+            serial_first_pass.total_group_count()
+        );
+        assert_eq!(top_groups.total_hits, serial_groups.total_hits);
+        assert_eq!(
+            top_groups.total_grouped_hits,
+            serial_groups.total_grouped_hits
+        );
+        assert_eq!(grouped_prices(&top_groups), grouped_prices(&serial_groups));
+        let totals =
+            |g: &TopGroups| -> Vec<usize> { g.groups.iter().map(|g| g.total_hits).collect() };
+        assert_eq!(totals(&top_groups), totals(&serial_groups));
+    }
+
+    #[test]
+    fn test_collect_before_set_next_reader() {
+        let mut collector = FirstPassGroupingCollector::new(
+            GroupField::Numeric("group".into()),
+            &price_sort(true),
+            3,
+        );
+        let mut scorer = create_mock_scorer(vec![1]);
+        assert!(collector.collect(1, &mut scorer).is_err());
+    }
+}
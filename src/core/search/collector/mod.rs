@@ -15,6 +15,18 @@ mod top_docs;
 
 pub use self::top_docs::*;
 
+mod top_field;
+
+pub use self::top_field::*;
+
+mod grouping;
+
+pub use self::grouping::*;
+
+mod collapsing;
+
+pub use self::collapsing::*;
+
 mod early_terminating;
 
 pub use self::early_terminating::*;
@@ -118,6 +130,14 @@ pub trait Collector {
     /// return `true` if scores are needed.
     fn needs_scores(&self) -> bool;
 
+    /// Indicates if the scorer may skip the hits that are not competitive
+    /// once this collector calls `Scorer::set_min_competitive_score`, like
+    /// Lucene's `ScoreMode.TOP_SCORES`. Collectors that must see every hit,
+    /// e.g. to count or aggregate them, keep the default `false`.
+    fn wants_min_competitive_score(&self) -> bool {
+        false
+    }
+
     /// Called once for every document matching a query, with the unbased document
     /// number.
     /// Note: The collection of the current segment can be terminated by throwing
@@ -136,6 +156,10 @@ impl<'a, T: Collector + 'a> Collector for &'a mut T {
         (**self).needs_scores()
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        (**self).wants_min_competitive_score()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         (**self).collect(doc, scorer)
     }
@@ -83,6 +83,10 @@ impl Collector for TimeoutCollector {
         false
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        true
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
         let now = SystemTime::now();
         if self.start_time < now && now.duration_since(self.start_time)? >= self.timeout_duration {
@@ -120,6 +124,10 @@ impl Collector for TimeoutLeafCollector {
         false
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        true
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, _doc: i32, _scorer: &mut S) -> Result<()> {
         let now = SystemTime::now();
         if self.start_time < now && now.duration_since(self.start_time)? >= self.timeout_duration {
@@ -11,6 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp::Ordering;
 use std::collections::binary_heap::BinaryHeap;
 use std::f32;
 use std::mem;
@@ -21,10 +22,13 @@ use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
 use core::search::scorer::Scorer;
-use core::search::sort_field::{ScoreDoc, ScoreDocHit, TopDocs, TopScoreDocs};
+use core::search::sort_field::{ScoreDoc, ScoreDocHit, TopDocs, TopScoreDocs, TotalHitsRelation};
 use core::util::DocId;
 use error::{ErrorKind::IllegalState, Result, ResultExt};
 
+/// The hits are counted exactly up to this number, unless more are requested.
+pub const DEFAULT_TOTAL_HITS_THRESHOLD: usize = 1000;
+
 struct TopDocsBaseCollector {
     /// The priority queue which holds the top documents. Note that different
     /// implementations of PriorityQueue give different meaning to 'top documents'.
@@ -37,20 +41,54 @@ struct TopDocsBaseCollector {
     /// The total number of documents that the collector encountered.
     total_hits: usize,
 
+    /// Beyond this number of hits, the scorer may skip the non competitive docs
+    /// and `total_hits` becomes a lower bound.
+    total_hits_threshold: usize,
+    total_hits_relation: TotalHitsRelation,
+
+    /// The min competitive score the scorer of the current leaf was told.
+    min_competitive_score: f32,
+
     cur_doc_base: DocId,
+
+    /// The last hit of the previous page, the hits sorting before it are
+    /// counted but not collected.
+    after: Option<ScoreDoc>,
 }
 
 impl TopDocsBaseCollector {
-    fn new(estimated_hits: usize) -> Self {
+    fn new(estimated_hits: usize, total_hits_threshold: usize) -> Self {
         let pq = BinaryHeap::with_capacity(estimated_hits);
         Self {
             pq,
             estimated_hits,
             total_hits: 0,
+            total_hits_threshold,
+            total_hits_relation: TotalHitsRelation::EqualTo,
+            min_competitive_score: 0.0,
             cur_doc_base: 0,
+            after: None,
         }
     }
 
+    // whether the hit sorts before `after`, i.e. it was on a previous page
+    fn is_before_after(&self, doc_id: DocId, score: f32) -> bool {
+        match self.after {
+            Some(ref after) => match score.partial_cmp(&after.score) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Equal) => doc_id <= after.doc,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    fn set_next_reader(&mut self, doc_base: DocId) {
+        self.cur_doc_base = doc_base;
+        // the scorer of the new leaf doesn't know the min competitive score yet
+        self.min_competitive_score = 0.0;
+    }
+
     /// Returns the top docs that were collected by this collector.
     fn top_docs(&mut self) -> TopDocs {
         let size = self.total_hits.min(self.pq.len());
@@ -61,7 +99,10 @@ impl TopDocsBaseCollector {
         }
 
         score_docs.reverse();
-        TopDocs::Score(TopScoreDocs::new(self.total_hits, score_docs))
+        TopDocs::Score(
+            TopScoreDocs::new(self.total_hits, score_docs)
+                .with_total_hits_relation(self.total_hits_relation),
+        )
     }
 
     fn add_doc(&mut self, doc_id: DocId, score: f32) {
@@ -80,6 +121,22 @@ impl TopDocsBaseCollector {
             }
         }
     }
+
+    fn update_min_competitive_score<S: Scorer + ?Sized>(&mut self, scorer: &mut S) -> Result<()> {
+        if self.total_hits <= self.total_hits_threshold || self.pq.len() < self.estimated_hits {
+            return Ok(());
+        }
+        // the docs that don't score more than the bottom of the queue are not
+        // collected, from now on the scorer may skip them without counting them
+        self.total_hits_relation = TotalHitsRelation::GreaterThanOrEqualTo;
+        if let Some(bottom) = self.pq.peek() {
+            if bottom.score > self.min_competitive_score {
+                scorer.set_min_competitive_score(bottom.score)?;
+                self.min_competitive_score = bottom.score;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Collector for TopDocsBaseCollector {
@@ -87,15 +144,23 @@ impl Collector for TopDocsBaseCollector {
         true
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        true
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         let score = scorer.score()?;
         debug_assert!((score - f32::NEG_INFINITY).abs() >= f32::EPSILON);
         debug_assert!(!score.is_nan());
 
         let id = doc + self.cur_doc_base;
+        if self.is_before_after(id, score) {
+            self.total_hits += 1;
+            return Ok(());
+        }
         self.add_doc(id, score);
 
-        Ok(())
+        self.update_min_competitive_score(scorer)
     }
 }
 
@@ -111,13 +176,30 @@ pub struct TopDocsCollector {
 
 impl TopDocsCollector {
     pub fn new(estimated_hits: usize) -> Self {
-        let base = TopDocsBaseCollector::new(estimated_hits);
+        Self::with_total_hits_threshold(
+            estimated_hits,
+            estimated_hits.max(DEFAULT_TOTAL_HITS_THRESHOLD),
+        )
+    }
+
+    /// Creates a collector that counts the hits exactly up to
+    /// `total_hits_threshold`, beyond which the scorers may skip the docs that
+    /// can't make the top hits and the total hits become a lower bound.
+    pub fn with_total_hits_threshold(estimated_hits: usize, total_hits_threshold: usize) -> Self {
+        let base = TopDocsBaseCollector::new(estimated_hits, total_hits_threshold);
         Self {
             base,
             channel: None,
         }
     }
 
+    /// Collects the hits sorting after `after`, the last hit of the previous
+    /// page, the hits of the previous pages are only counted in the total hits.
+    pub fn with_search_after(mut self, after: ScoreDoc) -> Self {
+        self.base.after = Some(after);
+        self
+    }
+
     /// Returns the top docs that were collected by this collector.
     pub fn top_docs(&mut self) -> TopDocs {
         self.base.top_docs()
@@ -132,7 +214,7 @@ impl SearchCollector for TopDocsCollector {
     type LC = TopDocsLeafCollector;
 
     fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
-        self.base.cur_doc_base = reader.doc_base;
+        self.base.set_next_reader(reader.doc_base);
 
         Ok(())
     }
@@ -151,8 +233,10 @@ impl SearchCollector for TopDocsCollector {
         &self,
         reader: &LeafReaderContext<'_, C>,
     ) -> Result<TopDocsLeafCollector> {
-        let mut collector = TopDocsBaseCollector::new(self.base.estimated_hits);
-        collector.cur_doc_base = reader.doc_base;
+        let mut collector =
+            TopDocsBaseCollector::new(self.base.estimated_hits, self.base.total_hits_threshold);
+        collector.after = self.base.after.clone();
+        collector.set_next_reader(reader.doc_base);
         Ok(TopDocsLeafCollector::new(
             collector,
             self.channel.as_ref().unwrap().0.clone(),
@@ -167,6 +251,9 @@ impl SearchCollector for TopDocsCollector {
             drop(sender);
             while let Ok(docs) = receiver.recv() {
                 self.base.total_hits += docs.total_hits;
+                if docs.total_hits_relation == TotalHitsRelation::GreaterThanOrEqualTo {
+                    self.base.total_hits_relation = TotalHitsRelation::GreaterThanOrEqualTo;
+                }
                 for doc in docs.docs {
                     self.add_doc(doc.doc, doc.score);
                 }
@@ -182,6 +269,10 @@ impl Collector for TopDocsCollector {
         true
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        true
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
         self.base.collect(doc, scorer)
     }
@@ -190,6 +281,7 @@ impl Collector for TopDocsCollector {
 struct LeafTopDocs {
     docs: Vec<ScoreDoc>,
     total_hits: usize,
+    total_hits_relation: TotalHitsRelation,
 }
 
 pub struct TopDocsLeafCollector {
@@ -211,6 +303,7 @@ impl ParallelLeafCollector for TopDocsLeafCollector {
             // the doc is not sorted, but this is ok.
             docs: docs.into_vec(),
             total_hits: self.collector.total_hits,
+            total_hits_relation: self.collector.total_hits_relation,
         };
         self.channel
             .send(top_docs)
@@ -223,6 +316,10 @@ impl Collector for TopDocsLeafCollector {
         true
     }
 
+    fn wants_min_competitive_score(&self) -> bool {
+        true
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         self.collector.collect(doc, scorer)
     }
@@ -260,6 +357,7 @@ mod tests {
 
         let top_docs = collector.top_docs();
         assert_eq!(top_docs.total_hits(), 5);
+        assert_eq!(top_docs.total_hits_relation(), TotalHitsRelation::EqualTo);
 
         let score_docs = top_docs.score_docs();
         assert_eq!(score_docs.len(), 3);
@@ -267,4 +365,29 @@ mod tests {
         assert_eq!(score_docs[1].doc_id(), 3);
         assert_eq!(score_docs[2].doc_id(), 3);
     }
+
+    #[test]
+    fn test_collect_search_after() {
+        let mut scorer = create_mock_scorer(vec![1, 2, 3, 5]);
+
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+        let mut collector = TopDocsCollector::new(3).with_search_after(ScoreDoc::new(3, 3.0));
+
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 4);
+        let docs: Vec<DocId> = top_docs.score_docs().iter().map(|d| d.doc_id()).collect();
+        assert_eq!(docs, vec![2, 1]);
+    }
 }
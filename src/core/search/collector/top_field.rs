@@ -0,0 +1,698 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{
+    ComparatorValue, FieldComparator, FieldComparatorEnum, FieldDoc, ScoreDocHit, Sort, SortField,
+    SortFieldType, TopDocs, TopFieldDocs, TotalHitsRelation,
+};
+use core::util::{DocId, VariantValue};
+use error::{ErrorKind, ErrorKind::IllegalState, Result, ResultExt};
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    slot: usize,
+    doc: DocId,
+    score: f32,
+}
+
+/// Expert: A priority queue of the hits of a search sorted by a `Sort`.
+///
+/// The sort values of the hits are kept in the slots of the comparators of
+/// the sort fields, the top of the queue is the least competitive hit, the
+/// bottom of the comparators.
+pub struct FieldValueHitQueue {
+    comparators: Vec<FieldComparatorEnum>,
+    reverses: Vec<bool>,
+    score_tiebreak: bool,
+    // a binary heap, the first entry sorts after all the others
+    entries: Vec<Entry>,
+    max_size: usize,
+    // the doc and the score of the hit to search after
+    top: Option<(DocId, f32)>,
+}
+
+impl FieldValueHitQueue {
+    pub fn new(sort_fields: &[SortField], max_size: usize, score_tiebreak: bool) -> Self {
+        let comparators = sort_fields
+            .iter()
+            .map(|f| f.get_comparator(max_size, f.missing_value()))
+            .collect();
+        let reverses = sort_fields.iter().map(|f| f.is_reverse()).collect();
+        FieldValueHitQueue {
+            comparators,
+            reverses,
+            score_tiebreak,
+            entries: Vec::with_capacity(max_size),
+            max_size,
+            top: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.len() == self.max_size
+    }
+
+    /// Notifies the comparators that the following docs are from `reader`.
+    pub fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        for c in &mut self.comparators {
+            c.get_information_from_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    /// Compares the bottom of the queue with the doc of the current reader,
+    /// `Ordering::Greater` means the doc is competitive.
+    ///
+    /// The docs are collected in increasing order, so a doc that ties with
+    /// the bottom is never competitive.
+    pub fn compare_bottom(&mut self, doc: DocId, score: f32) -> Result<Ordering> {
+        debug_assert!(self.is_full());
+        for (c, reverse) in self.comparators.iter_mut().zip(self.reverses.iter()) {
+            let value = comparator_value(c, doc, score);
+            let cmp = c.compare_bottom(value)?;
+            if cmp != Ordering::Equal {
+                return Ok(if *reverse { cmp.reverse() } else { cmp });
+            }
+        }
+        if self.score_tiebreak {
+            return Ok(score
+                .partial_cmp(&self.entries[0].score)
+                .unwrap_or(Ordering::Equal));
+        }
+        Ok(Ordering::Equal)
+    }
+
+    /// Sets the hit after which the docs are collected, usually the last hit
+    /// of the previous page. Its sort values must match the sort fields.
+    pub fn set_top(&mut self, after: &FieldDoc) -> Result<()> {
+        if after.fields.len() != self.comparators.len() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "after has {} sort values, but the sort has {} fields",
+                after.fields.len(),
+                self.comparators.len()
+            )));
+        }
+        for (c, value) in self.comparators.iter_mut().zip(after.fields.iter()) {
+            c.set_top_value(value)?;
+        }
+        self.top = Some((after.doc, after.score));
+        Ok(())
+    }
+
+    /// Compares the doc of the current reader with the top set by `set_top`,
+    /// `Ordering::Greater` means the doc sorts after the top and may be
+    /// collected. `doc_base` rebases the doc to the top level doc id.
+    pub fn compare_top(&mut self, doc: DocId, doc_base: DocId, score: f32) -> Result<Ordering> {
+        let (top_doc, top_score) = match self.top {
+            Some(top) => top,
+            None => return Ok(Ordering::Greater),
+        };
+        for (c, reverse) in self.comparators.iter_mut().zip(self.reverses.iter()) {
+            let value = comparator_value(c, doc, score);
+            let cmp = c.compare_top(value)?;
+            if cmp != Ordering::Equal {
+                return Ok(if *reverse { cmp } else { cmp.reverse() });
+            }
+        }
+        if self.score_tiebreak {
+            let cmp = top_score.partial_cmp(&score).unwrap_or(Ordering::Equal);
+            if cmp != Ordering::Equal {
+                return Ok(cmp);
+            }
+        }
+        Ok((doc + doc_base).cmp(&top_doc))
+    }
+
+    /// Adds the doc of the current reader, `doc_base` rebases it to the
+    /// top level doc id. If the queue is full the bottom is replaced, the
+    /// caller must have checked that the doc is competitive.
+    pub fn add(&mut self, doc: DocId, doc_base: DocId, score: f32) -> Result<()> {
+        let slot = if self.is_full() {
+            self.entries[0].slot
+        } else {
+            self.entries.len()
+        };
+        for c in &mut self.comparators {
+            let value = comparator_value(c, doc, score);
+            c.copy(slot, value)?;
+        }
+        let entry = Entry {
+            slot,
+            doc: doc + doc_base,
+            score,
+        };
+        if self.is_full() {
+            self.entries[0] = entry;
+            self.down_heap(0);
+        } else {
+            self.entries.push(entry);
+            let last = self.entries.len() - 1;
+            self.up_heap(last);
+        }
+        if self.is_full() {
+            let bottom = self.entries[0].slot;
+            for c in &mut self.comparators {
+                c.set_bottom(bottom);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drains the queue into unsorted `FieldDoc`s holding the sort values
+    /// of the hits.
+    pub fn drain_field_docs(&mut self) -> Vec<FieldDoc> {
+        let entries = mem::replace(&mut self.entries, Vec::with_capacity(self.max_size));
+        entries
+            .into_iter()
+            .map(|e| {
+                let fields = self.comparators.iter().map(|c| c.value(e.slot)).collect();
+                FieldDoc::new(e.doc, e.score, fields)
+            })
+            .collect()
+    }
+
+    // `Ordering::Greater` if `e1` sorts after `e2`
+    fn compare(&self, e1: &Entry, e2: &Entry) -> Ordering {
+        for (c, reverse) in self.comparators.iter().zip(self.reverses.iter()) {
+            let cmp = c.compare(e1.slot, e2.slot);
+            if cmp != Ordering::Equal {
+                return if *reverse { cmp.reverse() } else { cmp };
+            }
+        }
+        if self.score_tiebreak {
+            let cmp = e2.score.partial_cmp(&e1.score).unwrap_or(Ordering::Equal);
+            if cmp != Ordering::Equal {
+                return cmp;
+            }
+        }
+        e1.doc.cmp(&e2.doc)
+    }
+
+    fn up_heap(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.compare(&self.entries[i], &self.entries[parent]) != Ordering::Greater {
+                break;
+            }
+            self.entries.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn down_heap(&mut self, mut i: usize) {
+        let len = self.entries.len();
+        loop {
+            let mut largest = i;
+            for child in &[2 * i + 1, 2 * i + 2] {
+                if *child < len
+                    && self.compare(&self.entries[*child], &self.entries[largest])
+                        == Ordering::Greater
+                {
+                    largest = *child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.entries.swap(i, largest);
+            i = largest;
+        }
+    }
+}
+
+fn comparator_value(comparator: &FieldComparatorEnum, doc: DocId, score: f32) -> ComparatorValue {
+    if comparator.get_type() == SortFieldType::Score {
+        ComparatorValue::Score(score)
+    } else {
+        ComparatorValue::DocScore(doc, score)
+    }
+}
+
+fn compare_values(v1: &VariantValue, v2: &VariantValue) -> Ordering {
+    match (v1, v2) {
+        (VariantValue::Float(f1), VariantValue::Float(f2)) => {
+            f1.partial_cmp(f2).unwrap_or(Ordering::Equal)
+        }
+        (VariantValue::Double(d1), VariantValue::Double(d2)) => {
+            d1.partial_cmp(d2).unwrap_or(Ordering::Equal)
+        }
+        _ => v1.cmp(v2),
+    }
+}
+
+/// Compares two hits by the sort values of their `FieldDoc`s, in the same
+/// order as the `FieldValueHitQueue` of `sort_fields` does.
+pub fn compare_field_docs(
+    sort_fields: &[SortField],
+    score_tiebreak: bool,
+    d1: &FieldDoc,
+    d2: &FieldDoc,
+) -> Ordering {
+    for (i, field) in sort_fields.iter().enumerate() {
+        let mut cmp = compare_values(&d1.fields[i], &d2.fields[i]);
+        // relevance sorts by descending score
+        if field.field_type() == SortFieldType::Score {
+            cmp = cmp.reverse();
+        }
+        if field.is_reverse() {
+            cmp = cmp.reverse();
+        }
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+    }
+    if score_tiebreak {
+        let cmp = d2.score.partial_cmp(&d1.score).unwrap_or(Ordering::Equal);
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+    }
+    d1.doc.cmp(&d2.doc)
+}
+
+/// Whether the docs of a segment sorted by `index_sort` are also sorted by
+/// `sort`, i.e. the sort fields are a prefix of the index sort.
+pub fn can_early_terminate(sort: &Sort, index_sort: Option<&Sort>) -> bool {
+    match index_sort {
+        Some(index_sort) => {
+            let fields = sort.get_sort();
+            let index_fields = index_sort.get_sort();
+            fields.len() <= index_fields.len() && fields == &index_fields[..fields.len()]
+        }
+        None => false,
+    }
+}
+
+struct TopFieldBaseCollector {
+    queue: FieldValueHitQueue,
+    needs_scores: bool,
+    total_hits: usize,
+    total_hits_relation: TotalHitsRelation,
+    max_score: f32,
+    cur_doc_base: DocId,
+    // the current segment is sorted by the sort, the collection of the segment
+    // can stop at the first doc that is not competitive
+    early_terminate: bool,
+}
+
+impl TopFieldBaseCollector {
+    fn new(sort: &Sort, estimated_hits: usize, score_tiebreak: bool) -> Self {
+        TopFieldBaseCollector {
+            queue: FieldValueHitQueue::new(sort.get_sort(), estimated_hits, score_tiebreak),
+            needs_scores: score_tiebreak || sort.needs_scores(),
+            total_hits: 0,
+            total_hits_relation: TotalHitsRelation::EqualTo,
+            max_score: f32::NEG_INFINITY,
+            cur_doc_base: 0,
+            early_terminate: false,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(
+        &mut self,
+        sort: &Sort,
+        score_tiebreak: bool,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        // the docs with the same sort values are not sorted by score in the segment
+        self.early_terminate =
+            !score_tiebreak && can_early_terminate(sort, reader.reader.index_sort());
+        self.queue.set_next_reader(reader)
+    }
+
+    fn max_score(&self) -> f32 {
+        if self.max_score.is_finite() {
+            self.max_score
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Collector for TopFieldBaseCollector {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        let score = if self.needs_scores {
+            let score = scorer.score()?;
+            debug_assert!(!score.is_nan());
+            if score > self.max_score {
+                self.max_score = score;
+            }
+            score
+        } else {
+            0.0
+        };
+        self.total_hits += 1;
+
+        if self.queue.compare_top(doc, self.cur_doc_base, score)? != Ordering::Greater {
+            // the hit was on a previous page
+            return Ok(());
+        }
+        if self.queue.is_full() && self.queue.compare_bottom(doc, score)? != Ordering::Greater {
+            if self.early_terminate {
+                // the following docs of the segment sort after this one
+                self.total_hits_relation = TotalHitsRelation::GreaterThanOrEqualTo;
+                bail!(ErrorKind::Collector(
+                    collector::ErrorKind::LeafCollectionTerminated,
+                ));
+            }
+            return Ok(());
+        }
+        self.queue.add(doc, self.cur_doc_base, score)
+    }
+}
+
+/// A `Collector` that sorts the hits by a `Sort` and returns them as a
+/// `TopDocs::Field`, whose `FieldDoc`s hold the sort values of the hits.
+///
+/// Missing values are handled by the comparators of the sort fields, see
+/// `SortField::set_missing_value`. Hits with the same sort values are
+/// ordered by descending score if the score tiebreak is enabled, then by
+/// increasing doc id.
+///
+/// If the segments are sorted by an index sort whose first fields are the
+/// sort fields, the collection of a segment stops at the first hit that is
+/// not competitive and the total hits become a lower bound.
+pub struct TopFieldCollector {
+    sort: Sort,
+    estimated_hits: usize,
+    score_tiebreak: bool,
+    after: Option<FieldDoc>,
+    base: TopFieldBaseCollector,
+    // the hits collected by the leaf collectors of a parallel search
+    leaf_docs: Vec<FieldDoc>,
+    leaf_max_score: f32,
+    channel: Option<(Sender<LeafTopFieldDocs>, Receiver<LeafTopFieldDocs>)>,
+}
+
+impl TopFieldCollector {
+    pub fn new(sort: Sort, estimated_hits: usize) -> Self {
+        assert!(
+            estimated_hits > 0,
+            format!("estimated_hits must always be > 0, got {}", estimated_hits)
+        );
+        let base = TopFieldBaseCollector::new(&sort, estimated_hits, false);
+        TopFieldCollector {
+            sort,
+            estimated_hits,
+            score_tiebreak: false,
+            after: None,
+            base,
+            leaf_docs: vec![],
+            leaf_max_score: f32::NEG_INFINITY,
+            channel: None,
+        }
+    }
+
+    /// Orders the hits with the same sort values by descending score, the
+    /// scores are computed even if the sort doesn't need them.
+    pub fn with_score_tiebreak(mut self, score_tiebreak: bool) -> Self {
+        self.score_tiebreak = score_tiebreak;
+        self.base = TopFieldBaseCollector::new(&self.sort, self.estimated_hits, score_tiebreak);
+        if let Some(ref after) = self.after {
+            // the values were validated by `with_search_after`
+            self.base.queue.set_top(after).unwrap();
+        }
+        self
+    }
+
+    /// Collects the hits sorting after `after`, the last hit of the previous
+    /// page, the hits of the previous pages are only counted in the total
+    /// hits. The sort values of `after` must match the sort.
+    pub fn with_search_after(mut self, after: FieldDoc) -> Result<Self> {
+        self.base.queue.set_top(&after)?;
+        self.after = Some(after);
+        Ok(self)
+    }
+
+    pub fn sort(&self) -> &Sort {
+        &self.sort
+    }
+
+    /// Returns the top docs that were collected by this collector, the
+    /// scores of the hits are 0 if neither the sort nor the tiebreak need
+    /// them.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let mut docs = self.base.queue.drain_field_docs();
+        docs.extend(self.leaf_docs.drain(..));
+        let sort_fields = self.sort.get_sort();
+        let score_tiebreak = self.score_tiebreak;
+        docs.sort_by(|d1, d2| compare_field_docs(sort_fields, score_tiebreak, d1, d2));
+        docs.truncate(self.estimated_hits);
+
+        let max_score = self.base.max_score().max(self.leaf_max_score);
+        let score_docs = docs.into_iter().map(ScoreDocHit::Field).collect();
+        TopDocs::Field(
+            TopFieldDocs::new(
+                self.base.total_hits,
+                score_docs,
+                sort_fields.to_vec(),
+                max_score,
+            )
+            .with_total_hits_relation(self.base.total_hits_relation),
+        )
+    }
+}
+
+impl SearchCollector for TopFieldCollector {
+    type LC = TopFieldLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base
+            .set_next_reader(&self.sort, self.score_tiebreak, reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TopFieldLeafCollector> {
+        let mut collector =
+            TopFieldBaseCollector::new(&self.sort, self.estimated_hits, self.score_tiebreak);
+        if let Some(ref after) = self.after {
+            collector.queue.set_top(after)?;
+        }
+        collector.set_next_reader(&self.sort, self.score_tiebreak, reader)?;
+        Ok(TopFieldLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.base.total_hits += docs.total_hits;
+                if docs.total_hits_relation == TotalHitsRelation::GreaterThanOrEqualTo {
+                    self.base.total_hits_relation = TotalHitsRelation::GreaterThanOrEqualTo;
+                }
+                self.leaf_max_score = self.leaf_max_score.max(docs.max_score);
+                self.leaf_docs.extend(docs.docs);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for TopFieldCollector {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+struct LeafTopFieldDocs {
+    docs: Vec<FieldDoc>,
+    total_hits: usize,
+    total_hits_relation: TotalHitsRelation,
+    max_score: f32,
+}
+
+pub struct TopFieldLeafCollector {
+    collector: TopFieldBaseCollector,
+    channel: Sender<LeafTopFieldDocs>,
+}
+
+impl ParallelLeafCollector for TopFieldLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let top_docs = LeafTopFieldDocs {
+            // the docs are not sorted, they are merged by the parent collector
+            docs: self.collector.queue.drain_field_docs(),
+            total_hits: self.collector.total_hits,
+            total_hits_relation: self.collector.total_hits_relation,
+            max_score: self.collector.max_score(),
+        };
+        self.channel
+            .send(top_docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for TopFieldLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::sort_field::SimpleSortField;
+    use core::search::*;
+
+    fn collect_all(collector: &mut TopFieldCollector, docs: Vec<DocId>) {
+        let mut scorer = create_mock_scorer(docs);
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_collect_sorted_by_doc() {
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            String::new(),
+            SortFieldType::Doc,
+            true,
+        ))]);
+        let mut collector = TopFieldCollector::new(sort, 3);
+        assert!(!collector.needs_scores());
+        collect_all(&mut collector, vec![1, 2, 4, 5, 7]);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 5);
+        assert_eq!(top_docs.total_hits_relation(), TotalHitsRelation::EqualTo);
+
+        let docs: Vec<DocId> = top_docs.score_docs().iter().map(|d| d.doc_id()).collect();
+        assert_eq!(docs, vec![7, 5, 4]);
+        if let ScoreDocHit::Field(ref f) = top_docs.score_docs()[0] {
+            assert_eq!(f.fields, vec![VariantValue::Int(7)]);
+        } else {
+            panic!("expected field doc");
+        }
+    }
+
+    #[test]
+    fn test_collect_search_after() {
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            String::new(),
+            SortFieldType::Doc,
+            true,
+        ))]);
+        let after = FieldDoc::new(5, 0.0, vec![VariantValue::Int(5)]);
+        let mut collector = TopFieldCollector::new(sort.clone(), 2)
+            .with_search_after(after)
+            .unwrap();
+        collect_all(&mut collector, vec![1, 2, 4, 5, 7]);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 5);
+        let docs: Vec<DocId> = top_docs.score_docs().iter().map(|d| d.doc_id()).collect();
+        assert_eq!(docs, vec![4, 2]);
+
+        let invalid = FieldDoc::new(5, 0.0, vec![VariantValue::Long(5)]);
+        assert!(TopFieldCollector::new(sort, 2)
+            .with_search_after(invalid)
+            .is_err());
+    }
+
+    #[test]
+    fn test_collect_sorted_by_score() {
+        let mut collector = TopFieldCollector::new(Sort::new(vec![SortField::new_score()]), 2)
+            .with_score_tiebreak(true);
+        assert!(collector.needs_scores());
+        collect_all(&mut collector, vec![1, 2, 3, 6]);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 4);
+        let score_docs = top_docs.score_docs();
+        assert_eq!(score_docs.len(), 2);
+        assert_eq!(score_docs[0].doc_id(), 6);
+        assert!((score_docs[0].score() - 6.0).abs() < f32::EPSILON);
+        assert_eq!(score_docs[1].doc_id(), 3);
+    }
+
+    #[test]
+    fn test_can_early_terminate() {
+        let by_doc = SortField::Simple(SimpleSortField::new(
+            String::new(),
+            SortFieldType::Doc,
+            false,
+        ));
+        let sort = Sort::new(vec![SortField::new_score()]);
+        let index_sort = Sort::new(vec![SortField::new_score(), by_doc.clone()]);
+        assert!(can_early_terminate(&sort, Some(&index_sort)));
+        assert!(!can_early_terminate(&index_sort, Some(&sort)));
+        assert!(!can_early_terminate(
+            &Sort::new(vec![by_doc]),
+            Some(&index_sort)
+        ));
+        assert!(!can_early_terminate(&sort, None));
+    }
+}
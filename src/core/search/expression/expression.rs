@@ -0,0 +1,445 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::expression::Parser;
+
+use error::Result;
+
+use std::fmt;
+
+/// The variable bound to the score of the current doc.
+pub const SCORE_VARIABLE: &str = "_score";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    pub fn from_symbol(symbol: &str) -> Option<BinaryOp> {
+        Some(match symbol {
+            "||" => BinaryOp::Or,
+            "&&" => BinaryOp::And,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Rem,
+            _ => return None,
+        })
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+
+    /// Operators with a higher precedence bind tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 0,
+            BinaryOp::And => 1,
+            BinaryOp::Eq | BinaryOp::Ne => 2,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 3,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 5,
+        }
+    }
+
+    #[allow(clippy::float_cmp)]
+    fn apply(self, left: f64, right: f64) -> f64 {
+        match self {
+            BinaryOp::Or => bool_value(truth(left) || truth(right)),
+            BinaryOp::And => bool_value(truth(left) && truth(right)),
+            BinaryOp::Eq => bool_value(left == right),
+            BinaryOp::Ne => bool_value(left != right),
+            BinaryOp::Lt => bool_value(left < right),
+            BinaryOp::Le => bool_value(left <= right),
+            BinaryOp::Gt => bool_value(left > right),
+            BinaryOp::Ge => bool_value(left >= right),
+            BinaryOp::Add => left + right,
+            BinaryOp::Sub => left - right,
+            BinaryOp::Mul => left * right,
+            BinaryOp::Div => left / right,
+            BinaryOp::Rem => left % right,
+        }
+    }
+}
+
+/// The functions that can be called in an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Ceil,
+    Floor,
+    Exp,
+    /// The natural logarithm, same as `ln`.
+    Log,
+    Ln,
+    Log10,
+    Log1p,
+    Sqrt,
+    Pow,
+    Min,
+    Max,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "abs" => Function::Abs,
+            "ceil" => Function::Ceil,
+            "floor" => Function::Floor,
+            "exp" => Function::Exp,
+            "log" => Function::Log,
+            "ln" => Function::Ln,
+            "log10" => Function::Log10,
+            "log1p" => Function::Log1p,
+            "sqrt" => Function::Sqrt,
+            "pow" => Function::Pow,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Abs => "abs",
+            Function::Ceil => "ceil",
+            Function::Floor => "floor",
+            Function::Exp => "exp",
+            Function::Log => "log",
+            Function::Ln => "ln",
+            Function::Log10 => "log10",
+            Function::Log1p => "log1p",
+            Function::Sqrt => "sqrt",
+            Function::Pow => "pow",
+            Function::Min => "min",
+            Function::Max => "max",
+        }
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Function::Pow | Function::Min | Function::Max => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Abs => args[0].abs(),
+            Function::Ceil => args[0].ceil(),
+            Function::Floor => args[0].floor(),
+            Function::Exp => args[0].exp(),
+            Function::Log | Function::Ln => args[0].ln(),
+            Function::Log10 => args[0].log10(),
+            Function::Log1p => args[0].ln_1p(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Min => args[0].min(args[1]),
+            Function::Max => args[0].max(args[1]),
+        }
+    }
+}
+
+fn truth(value: f64) -> bool {
+    value != 0.0
+}
+
+fn bool_value(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// A node of a parsed expression, booleans are represented as 1.0 and 0.0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Const(f64),
+    /// The variable at the index in `Expression::variables`.
+    Var(usize),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    /// Evaluates the constant sub expressions.
+    fn fold(self) -> Expr {
+        match self {
+            Expr::Unary(op, e) => match (*e).fold() {
+                Expr::Const(v) => Expr::Const(eval_unary(op, v)),
+                e => Expr::Unary(op, Box::new(e)),
+            },
+            Expr::Binary(op, l, r) => match ((*l).fold(), (*r).fold()) {
+                (Expr::Const(l), Expr::Const(r)) => Expr::Const(op.apply(l, r)),
+                (l, r) => Expr::Binary(op, Box::new(l), Box::new(r)),
+            },
+            Expr::Conditional(c, t, f) => match (*c).fold() {
+                Expr::Const(c) => {
+                    if truth(c) {
+                        (*t).fold()
+                    } else {
+                        (*f).fold()
+                    }
+                }
+                c => Expr::Conditional(Box::new(c), Box::new((*t).fold()), Box::new((*f).fold())),
+            },
+            Expr::Call(function, args) => {
+                let args: Vec<Expr> = args.into_iter().map(Expr::fold).collect();
+                if args.iter().all(|a| a.as_const().is_some()) {
+                    let values: Vec<f64> = args.iter().filter_map(Expr::as_const).collect();
+                    Expr::Const(function.apply(&values))
+                } else {
+                    Expr::Call(function, args)
+                }
+            }
+            e => e,
+        }
+    }
+
+    fn as_const(&self) -> Option<f64> {
+        match self {
+            Expr::Const(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn evaluate(&self, values: &[f64]) -> f64 {
+        match self {
+            Expr::Const(v) => *v,
+            Expr::Var(idx) => values[*idx],
+            Expr::Unary(op, e) => eval_unary(*op, e.evaluate(values)),
+            Expr::Binary(BinaryOp::And, l, r) => {
+                bool_value(truth(l.evaluate(values)) && truth(r.evaluate(values)))
+            }
+            Expr::Binary(BinaryOp::Or, l, r) => {
+                bool_value(truth(l.evaluate(values)) || truth(r.evaluate(values)))
+            }
+            Expr::Binary(op, l, r) => op.apply(l.evaluate(values), r.evaluate(values)),
+            Expr::Conditional(c, t, f) => {
+                if truth(c.evaluate(values)) {
+                    t.evaluate(values)
+                } else {
+                    f.evaluate(values)
+                }
+            }
+            Expr::Call(function, args) => match args.len() {
+                1 => function.apply(&[args[0].evaluate(values)]),
+                _ => function.apply(&[args[0].evaluate(values), args[1].evaluate(values)]),
+            },
+        }
+    }
+
+    fn write(&self, variables: &[String], f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(v) => write!(f, "{}", v),
+            Expr::Var(idx) => write!(f, "{}", variables[*idx]),
+            Expr::Unary(op, e) => {
+                write!(f, "{}", if *op == UnaryOp::Negate { "-" } else { "!" })?;
+                e.write(variables, f)
+            }
+            Expr::Binary(op, l, r) => {
+                write!(f, "(")?;
+                l.write(variables, f)?;
+                write!(f, " {} ", op.symbol())?;
+                r.write(variables, f)?;
+                write!(f, ")")
+            }
+            Expr::Conditional(c, t, e) => {
+                write!(f, "(")?;
+                c.write(variables, f)?;
+                write!(f, " ? ")?;
+                t.write(variables, f)?;
+                write!(f, " : ")?;
+                e.write(variables, f)?;
+                write!(f, ")")
+            }
+            Expr::Call(function, args) => {
+                write!(f, "{}(", function.name())?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    arg.write(variables, f)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn eval_unary(op: UnaryOp, value: f64) -> f64 {
+    match op {
+        UnaryOp::Negate => -value,
+        UnaryOp::Not => bool_value(!truth(value)),
+    }
+}
+
+/// A compiled expression, e.g. `_score * log(1 + popularity) / (1 + age_days * 0.1)`.
+///
+/// The language supports numbers, variables, the arithmetic operators
+/// `+ - * / %`, the comparison operators `< <= > >= == !=`, the boolean
+/// operators `&& || !`, the conditional `c ? a : b`, parentheses and the
+/// calls of the functions of `Function`.
+///
+/// Expressions are parsed once and constant folded, then evaluated for each
+/// doc with the values of their `variables`, see `ExpressionValueSource` to
+/// bind them to doc values fields and the score.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Expr,
+    variables: Vec<String>,
+}
+
+impl Expression {
+    pub fn compile(source: &str) -> Result<Expression> {
+        let mut parser = Parser::new(source)?;
+        let root = parser.parse()?.fold();
+        // the variables only referred to by folded sub expressions are
+        // still reported, their values are just ignored
+        Ok(Expression {
+            source: source.to_string(),
+            root,
+            variables: parser.variables,
+        })
+    }
+
+    /// Returns the source text of the expression.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the names of the variables, in the order of their values in
+    /// `evaluate`.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn needs_score(&self) -> bool {
+        self.variables.iter().any(|v| v == SCORE_VARIABLE)
+    }
+
+    /// Returns the value of the expression if it does not depend on any
+    /// variable.
+    pub fn constant_value(&self) -> Option<f64> {
+        self.root.as_const()
+    }
+
+    /// Evaluates the expression with the values of the variables.
+    pub fn evaluate(&self, values: &[f64]) -> f64 {
+        debug_assert_eq!(values.len(), self.variables.len());
+        self.root.evaluate(values)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.write(&self.variables, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, values: &[f64]) -> f64 {
+        Expression::compile(source).unwrap().evaluate(values)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert!((eval("1 + 2 * 3 - 4 / 2", &[]) - 5.0).abs() < 1e-9);
+        assert!((eval("-(1 + 2) * 3 % 4", &[]) + 1.0).abs() < 1e-9);
+        assert!((eval("2 > 1 && 1 >= 1 ? 10 : 20", &[]) - 10.0).abs() < 1e-9);
+        assert!((eval("max(x, 3) + pow(2, 10)", &[5.0]) - 1029.0).abs() < 1e-9);
+
+        let expr =
+            Expression::compile("_score * log(1 + popularity) / (1 + age_days * 0.1)").unwrap();
+        assert_eq!(expr.variables(), &["_score", "popularity", "age_days"]);
+        assert!(expr.needs_score());
+        let expected = 2.0 * (1.0f64 + 9.0).ln() / (1.0 + 10.0 * 0.1);
+        assert!((expr.evaluate(&[2.0, 9.0, 10.0]) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let expr = Expression::compile("x * (2 + 3) + sqrt(16)").unwrap();
+        assert_eq!(expr.to_string(), "((x * 5) + 4)");
+        assert_eq!(
+            Expression::compile("1 < 2 ? 3 : x")
+                .unwrap()
+                .constant_value(),
+            Some(3.0)
+        );
+        assert_eq!(
+            Expression::compile("1 > 2 ? 3 : x")
+                .unwrap()
+                .constant_value(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Expression::compile("1 +").is_err());
+        assert!(Expression::compile("(1 + 2").is_err());
+        assert!(Expression::compile("foo(1)").is_err());
+        assert!(Expression::compile("pow(1)").is_err());
+        assert!(Expression::compile("1 $ 2").is_err());
+        assert!(Expression::compile("1 2").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::IndexReader;
+use core::search::explanation::Explanation;
+use core::search::expression::{ExpressionLeafValues, ExpressionValueSource};
+use core::search::scorer::{merge_rescored_hits, score_sort_field_index, RescoreRequest, Rescorer};
+use core::search::searcher::IndexSearcher;
+use core::search::sort_field::{ScoreDocHit, TopDocs};
+use core::util::{DocId, VariantValue};
+
+use error::Result;
+
+use std::collections::HashMap;
+
+/// A `Rescorer` that uses an expression to re-score the first pass hits,
+/// the `_score` variable of the expression is bound to the first pass score.
+///
+/// The expression value replaces the score of the hits in the rescore
+/// window, the query of the `RescoreRequest` is not used.
+pub struct ExpressionRescorer {
+    source: ExpressionValueSource,
+}
+
+impl ExpressionRescorer {
+    pub fn new(source: ExpressionValueSource) -> Self {
+        ExpressionRescorer { source }
+    }
+
+    pub fn value_source(&self) -> &ExpressionValueSource {
+        &self.source
+    }
+
+    // calls `f` with the expression values of the leaf of every hit, the hits
+    // must be sorted by doc id
+    fn for_each_hit<C, IS, F>(
+        &self,
+        searcher: &IS,
+        hits: &mut [ScoreDocHit],
+        mut f: F,
+    ) -> Result<()>
+    where
+        C: Codec,
+        IS: IndexSearcher<C>,
+        F: FnMut(&mut ExpressionLeafValues, DocId, &mut ScoreDocHit) -> Result<()>,
+    {
+        let readers = searcher.reader().leaves();
+        let mut reader_idx = 0;
+        let mut leaf_values = None;
+        for hit in hits {
+            let doc_id = hit.doc_id();
+            while reader_idx < readers.len()
+                && doc_id >= readers[reader_idx].doc_base + readers[reader_idx].reader.max_doc()
+            {
+                reader_idx += 1;
+                leaf_values = None;
+            }
+            debug_assert!(reader_idx < readers.len());
+            let reader = &readers[reader_idx];
+            if leaf_values.is_none() {
+                leaf_values = Some(self.source.leaf_values(reader)?);
+            }
+            f(leaf_values.as_mut().unwrap(), doc_id - reader.doc_base, hit)?;
+        }
+        Ok(())
+    }
+
+    fn window_hits<C: Codec>(top_docs: &TopDocs, req: &RescoreRequest<C>) -> Vec<ScoreDocHit> {
+        let mut hits = top_docs.score_docs().to_vec();
+        hits.truncate(req.window_size);
+        hits.sort_by(ScoreDocHit::order_by_doc);
+        hits
+    }
+}
+
+impl Rescorer for ExpressionRescorer {
+    fn rescore<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<()> {
+        if top_docs.total_hits() == 0 || top_docs.score_docs().is_empty() {
+            return Ok(());
+        }
+
+        let score_field_index = score_sort_field_index(top_docs);
+        let mut hits = Self::window_hits(top_docs, req);
+        self.for_each_hit(searcher, &mut hits, |values, doc, hit| {
+            let score = values.value(doc, hit.score())? as f32;
+            hit.set_score(score);
+            if score_field_index >= 0 {
+                if let ScoreDocHit::Field(ref mut f) = hit {
+                    f.fields[score_field_index as usize] = VariantValue::from(score);
+                }
+            }
+            Ok(())
+        })?;
+        hits.sort();
+        merge_rescored_hits(top_docs, hits, 1.0);
+        Ok(())
+    }
+
+    fn rescore_features<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<Vec<HashMap<String, VariantValue>>> {
+        if top_docs.total_hits() == 0 || top_docs.score_docs().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits = Self::window_hits(top_docs, req);
+        let mut result_features = Vec::with_capacity(hits.len());
+        let variables = self.source.expression().variables();
+        self.for_each_hit(searcher, &mut hits, |values, doc, hit| {
+            values.value(doc, hit.score())?;
+            let mut feature_map = HashMap::with_capacity(variables.len() + 1);
+            for (name, value) in variables.iter().zip(values.variable_values()) {
+                feature_map.insert(name.clone(), VariantValue::from(*value));
+            }
+            feature_map.insert(
+                "previous_score".to_string(),
+                VariantValue::from(hit.score()),
+            );
+            result_features.push(feature_map);
+            Ok(())
+        })?;
+        {
+            let score_docs = top_docs.score_docs_mut();
+            *score_docs = hits;
+        }
+        Ok(result_features)
+    }
+
+    fn explain<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        _req: &RescoreRequest<C>,
+        first: Explanation,
+        doc: DocId,
+    ) -> Result<Explanation> {
+        if !first.is_match() {
+            return Ok(Explanation::new(
+                false,
+                0.0f32,
+                "First pass did not match".to_string(),
+                vec![first],
+            ));
+        }
+        let reader = searcher.reader().leaf_reader_for_doc(doc);
+        let expl = self
+            .source
+            .leaf_values(&reader)?
+            .explain(doc - reader.doc_base, first.value())?;
+        let mut details = vec![first];
+        details.extend(expl.details().iter().cloned());
+        Ok(Explanation::new(
+            true,
+            expl.value(),
+            format!(
+                "expression rescore: {}, computed from:",
+                self.source.expression().source()
+            ),
+            details,
+        ))
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expressions over the score and numeric doc values of docs, e.g.
+//! `_score * ln(1 + popularity)`, usable for sorting and rescoring.
+
+mod expression;
+
+pub use self::expression::*;
+
+mod parser;
+
+pub(crate) use self::parser::*;
+
+mod value_source;
+
+pub use self::value_source::*;
+
+mod expression_rescorer;
+
+pub use self::expression_rescorer::*;
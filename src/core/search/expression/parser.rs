@@ -0,0 +1,292 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::expression::{BinaryOp, Expr, Function, UnaryOp};
+
+use error::{ErrorKind, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+    Question,
+    Colon,
+    End,
+}
+
+// the operators, longest first so that `<=` is not read as `<`
+const OPERATORS: [&str; 14] = [
+    "<=", ">=", "==", "!=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit())
+        {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse::<f64>().map_err(|_| {
+                ErrorKind::IllegalArgument(format!("invalid number '{}' at {}", text, start))
+            })?;
+            tokens.push((Token::Number(value), start));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+            continue;
+        }
+        let token = match c {
+            '(' => Some(Token::LeftParen),
+            ')' => Some(Token::RightParen),
+            ',' => Some(Token::Comma),
+            '?' => Some(Token::Question),
+            ':' => Some(Token::Colon),
+            _ => None,
+        };
+        if let Some(token) = token {
+            tokens.push((token, start));
+            i += 1;
+            continue;
+        }
+        for op in &OPERATORS {
+            let len = op.len();
+            if i + len <= chars.len() && chars[i..i + len].iter().cloned().eq(op.chars()) {
+                tokens.push((Token::Op(op), start));
+                i += len;
+                continue 'outer;
+            }
+        }
+        bail!(ErrorKind::IllegalArgument(format!(
+            "unexpected character '{}' at {}",
+            c, start
+        )));
+    }
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+/// A recursive descent parser of the expression language, the variables
+/// are numbered in the order of their first occurrence.
+pub(crate) struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    pub variables: Vec<String>,
+}
+
+impl Parser {
+    pub fn new(source: &str) -> Result<Self> {
+        Ok(Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            variables: vec![],
+        })
+    }
+
+    pub fn parse(&mut self) -> Result<Expr> {
+        let expr = self.conditional()?;
+        if self.peek() != &Token::End {
+            return self.unexpected();
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn unexpected<T>(&self) -> Result<T> {
+        let (ref token, offset) = self.tokens[self.pos];
+        let desc = match token {
+            Token::End => "end of expression".to_string(),
+            Token::Number(n) => format!("'{}'", n),
+            Token::Ident(s) => format!("'{}'", s),
+            Token::Op(op) => format!("'{}'", op),
+            Token::LeftParen => "'('".to_string(),
+            Token::RightParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Question => "'?'".to_string(),
+            Token::Colon => "':'".to_string(),
+        };
+        bail!(ErrorKind::IllegalArgument(format!(
+            "unexpected {} at {}",
+            desc, offset
+        )))
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        if self.peek() != token {
+            return self.unexpected();
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn conditional(&mut self) -> Result<Expr> {
+        let condition = self.binary(0)?;
+        if self.peek() != &Token::Question {
+            return Ok(condition);
+        }
+        self.advance();
+        let then = self.conditional()?;
+        self.expect(&Token::Colon)?;
+        let otherwise = self.conditional()?;
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    // parses the left associative binary operators with a precedence of at
+    // least `min_precedence`
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Op(op) => match BinaryOp::from_symbol(op) {
+                    Some(op) if op.precedence() >= min_precedence => op,
+                    _ => break,
+                },
+                _ => break,
+            };
+            self.advance();
+            let right = self.binary(op.precedence() + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Token::Op("-") => {
+                self.advance();
+                Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.unary()?)))
+            }
+            Token::Op("+") => {
+                self.advance();
+                self.unary()
+            }
+            Token::Op("!") => {
+                self.advance();
+                Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.peek().clone() {
+            Token::Number(n) => {
+                self.advance();
+                Ok(Expr::Const(n))
+            }
+            Token::LeftParen => {
+                self.advance();
+                let expr = self.conditional()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expr)
+            }
+            Token::Ident(name) => {
+                let offset = self.tokens[self.pos].1;
+                self.advance();
+                if self.peek() == &Token::LeftParen {
+                    self.advance();
+                    self.call(&name, offset)
+                } else {
+                    Ok(Expr::Var(self.variable(name)))
+                }
+            }
+            _ => self.unexpected(),
+        }
+    }
+
+    fn call(&mut self, name: &str, offset: usize) -> Result<Expr> {
+        let function = match Function::from_name(name) {
+            Some(f) => f,
+            None => bail!(ErrorKind::IllegalArgument(format!(
+                "unknown function '{}' at {}",
+                name, offset
+            ))),
+        };
+        let mut args = vec![];
+        if self.peek() != &Token::RightParen {
+            loop {
+                args.push(self.conditional()?);
+                if self.peek() != &Token::Comma {
+                    break;
+                }
+                self.advance();
+            }
+        }
+        self.expect(&Token::RightParen)?;
+        if args.len() != function.arity() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "function '{}' at {} expects {} arguments, got {}",
+                name,
+                offset,
+                function.arity(),
+                args.len()
+            )));
+        }
+        Ok(Expr::Call(function, args))
+    }
+
+    fn variable(&mut self, name: String) -> usize {
+        if let Some(idx) = self.variables.iter().position(|v| *v == name) {
+            idx
+        } else {
+            self.variables.push(name);
+            self.variables.len() - 1
+        }
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::expression::{Expression, SCORE_VARIABLE};
+use core::search::query::{NumericFieldSource, NumericFieldValues};
+use core::search::sort_field::{
+    ComparatorValue, FieldComparator, FieldComparatorEnum, SimpleSortField, SortFieldType,
+};
+use core::util::{DocId, VariantValue};
+
+use error::{ErrorKind, Result};
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Binds the variables of expressions to numeric doc values fields.
+///
+/// The `_score` variable is always bound to the score of the doc.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    fields: HashMap<String, NumericFieldSource>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds the variable `name` to the values of `source`.
+    pub fn add(&mut self, name: &str, source: NumericFieldSource) {
+        self.fields.insert(name.to_string(), source);
+    }
+
+    /// Binds the variable of the same name as `field` to its values.
+    pub fn add_field(&mut self, field: &str, numeric_type: SortFieldType) -> Result<()> {
+        let source = NumericFieldSource::new(field, numeric_type)?;
+        self.add(field, source);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NumericFieldSource> {
+        self.fields.get(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum VariableSource {
+    Score,
+    Field(NumericFieldSource),
+}
+
+/// The values of an `Expression` with its variables bound by `Bindings`.
+///
+/// Docs without a value for a field evaluate the variable as 0.
+#[derive(Debug, Clone)]
+pub struct ExpressionValueSource {
+    expression: Arc<Expression>,
+    variables: Vec<VariableSource>,
+}
+
+impl ExpressionValueSource {
+    pub fn new(expression: Arc<Expression>, bindings: &Bindings) -> Result<Self> {
+        let mut variables = Vec::with_capacity(expression.variables().len());
+        for name in expression.variables() {
+            if name == SCORE_VARIABLE {
+                variables.push(VariableSource::Score);
+            } else if let Some(source) = bindings.get(name) {
+                variables.push(VariableSource::Field(source.clone()));
+            } else {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "variable '{}' of expression '{}' is not bound",
+                    name,
+                    expression.source()
+                )));
+            }
+        }
+        Ok(ExpressionValueSource {
+            expression,
+            variables,
+        })
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn needs_score(&self) -> bool {
+        self.expression.needs_score()
+    }
+
+    /// Returns the per segment values of the expression.
+    pub fn leaf_values<C: Codec>(
+        &self,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<ExpressionLeafValues> {
+        let mut variables = Vec::with_capacity(self.variables.len());
+        for v in &self.variables {
+            variables.push(match v {
+                VariableSource::Score => None,
+                VariableSource::Field(source) => Some(source.values(ctx)?),
+            });
+        }
+        Ok(ExpressionLeafValues {
+            expression: Arc::clone(&self.expression),
+            values: vec![0.0; variables.len()],
+            variables,
+        })
+    }
+}
+
+impl PartialEq for ExpressionValueSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression.source() == other.expression.source() && self.variables == other.variables
+    }
+}
+
+impl Eq for ExpressionValueSource {}
+
+impl fmt::Display for ExpressionValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expr({})", self.expression.source())
+    }
+}
+
+/// The values of an `ExpressionValueSource` in a segment.
+pub struct ExpressionLeafValues {
+    expression: Arc<Expression>,
+    // the field values of the variables, `None` for the score
+    variables: Vec<Option<NumericFieldValues>>,
+    values: Vec<f64>,
+}
+
+impl ExpressionLeafValues {
+    /// Evaluates the expression for `doc` whose score is `score`.
+    pub fn value(&mut self, doc: DocId, score: f32) -> Result<f64> {
+        self.bind(doc, score)?;
+        Ok(self.expression.evaluate(&self.values))
+    }
+
+    /// Returns the values of the variables bound by the last call to
+    /// `value` or `explain`.
+    pub fn variable_values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Explains the value of the expression for `doc` with the values of its
+    /// variables.
+    pub fn explain(&mut self, doc: DocId, score: f32) -> Result<Explanation> {
+        let value = self.value(doc, score)?;
+        let details = self
+            .expression
+            .variables()
+            .iter()
+            .zip(self.values.iter())
+            .map(|(name, v)| Explanation::new(true, *v as f32, name.clone(), vec![]))
+            .collect();
+        Ok(Explanation::new(
+            true,
+            value as f32,
+            format!("expression: {}, computed from:", self.expression.source()),
+            details,
+        ))
+    }
+
+    fn bind(&mut self, doc: DocId, score: f32) -> Result<()> {
+        for (variable, value) in self.variables.iter_mut().zip(self.values.iter_mut()) {
+            *value = match variable {
+                Some(values) => values.get(doc)?.unwrap_or(0.0),
+                None => f64::from(score),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Sorts by the values of an expression, e.g. `SortField::Expression`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpressionSortField {
+    source: ExpressionValueSource,
+    raw_field: SimpleSortField,
+}
+
+impl ExpressionSortField {
+    pub fn new(source: ExpressionValueSource, reverse: bool) -> Self {
+        let raw_field = SimpleSortField::new(
+            source.expression().source().to_string(),
+            SortFieldType::Custom,
+            reverse,
+        );
+        ExpressionSortField { source, raw_field }
+    }
+
+    pub fn value_source(&self) -> &ExpressionValueSource {
+        &self.source
+    }
+
+    pub fn needs_scores(&self) -> bool {
+        self.source.needs_score()
+    }
+
+    pub fn get_comparator(&self, num_hits: usize) -> FieldComparatorEnum {
+        FieldComparatorEnum::Expression(ExpressionComparator::new(num_hits, self.source.clone()))
+    }
+
+    #[inline]
+    pub fn raw_field(&self) -> &SimpleSortField {
+        &self.raw_field
+    }
+
+    #[inline]
+    pub fn raw_field_mut(&mut self) -> &mut SimpleSortField {
+        &mut self.raw_field
+    }
+}
+
+/// Compares hits by the values of an expression.
+///
+/// Expressions using `_score` need to be passed `ComparatorValue::DocScore`.
+pub struct ExpressionComparator {
+    source: ExpressionValueSource,
+    leaf_values: Option<ExpressionLeafValues>,
+    values: Vec<f64>,
+    bottom: f64,
+    top_value: f64,
+}
+
+impl ExpressionComparator {
+    pub fn new(num_hits: usize, source: ExpressionValueSource) -> Self {
+        ExpressionComparator {
+            source,
+            leaf_values: None,
+            values: vec![0.0; num_hits],
+            bottom: 0.0,
+            top_value: 0.0,
+        }
+    }
+
+    fn doc_value(&mut self, value: ComparatorValue) -> Result<f64> {
+        let (doc, score) = match value {
+            ComparatorValue::Doc(doc) => (doc, 0.0),
+            ComparatorValue::DocScore(doc, score) => (doc, score),
+            ComparatorValue::Score(_) => unreachable!(),
+        };
+        self.leaf_values.as_mut().unwrap().value(doc, score)
+    }
+}
+
+impl FieldComparator for ExpressionComparator {
+    fn compare(&self, slot1: usize, slot2: usize) -> Ordering {
+        self.values[slot1]
+            .partial_cmp(&self.values[slot2])
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn value(&self, slot: usize) -> VariantValue {
+        VariantValue::Double(self.values[slot])
+    }
+
+    fn set_bottom(&mut self, slot: usize) {
+        self.bottom = self.values[slot];
+    }
+
+    fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        let value = self.doc_value(value)?;
+        Ok(self.bottom.partial_cmp(&value).unwrap_or(Ordering::Equal))
+    }
+
+    fn set_top_value(&mut self, value: &VariantValue) -> Result<()> {
+        match value.get_double() {
+            Some(v) => self.top_value = v,
+            None => bail!(ErrorKind::IllegalArgument(format!(
+                "invalid top value {:?} for expression {}",
+                value, self.source
+            ))),
+        }
+        Ok(())
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        let value = self.doc_value(value)?;
+        Ok(self
+            .top_value
+            .partial_cmp(&value)
+            .unwrap_or(Ordering::Equal))
+    }
+
+    fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
+        self.values[slot] = self.doc_value(value)?;
+        Ok(())
+    }
+
+    fn get_information_from_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.leaf_values = Some(self.source.leaf_values(reader)?);
+        Ok(())
+    }
+
+    fn get_type(&self) -> SortFieldType {
+        SortFieldType::Custom
+    }
+}
+
+impl fmt::Display for ExpressionComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ExpressionComparator(source: {}, bottom: {})",
+            self.source, self.bottom
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::sort_field::{Sort, SortField};
+    use core::store::directory::FSDirectory;
+
+    #[test]
+    fn test_unbound_variables() {
+        let expression = Arc::new(Expression::compile("_score * log(1 + popularity)").unwrap());
+        let mut bindings = Bindings::new();
+        assert!(ExpressionValueSource::new(Arc::clone(&expression), &bindings).is_err());
+
+        bindings
+            .add_field("popularity", SortFieldType::Long)
+            .unwrap();
+        let source = ExpressionValueSource::new(expression, &bindings).unwrap();
+        assert!(source.needs_score());
+    }
+
+    #[test]
+    fn test_expression_index_sort() {
+        let expression = Arc::new(Expression::compile("log(1 + popularity)").unwrap());
+        let mut bindings = Bindings::new();
+        bindings
+            .add_field("popularity", SortFieldType::Long)
+            .unwrap();
+        let source = ExpressionValueSource::new(expression, &bindings).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(dir.path()).unwrap());
+        let mut config = IndexWriterConfig::default();
+        config.index_sort = Some(Sort::new(vec![SortField::Expression(
+            ExpressionSortField::new(source, false),
+        )]));
+        assert!(IndexWriter::new(directory, Arc::new(config)).is_err());
+    }
+}
@@ -0,0 +1,682 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::f64;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::facet::{DoubleRange, LeafNumericValues, LongRange, NumericValuesSource};
+use core::search::scorer::Scorer;
+use core::util::{DocId, VariantValue};
+use error::{ErrorKind, ErrorKind::IllegalState, Result, ResultExt};
+
+const SECOND: i64 = 1000;
+const MINUTE: i64 = 60 * SECOND;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+/// The interval of the buckets of a date histogram, the weeks start on
+/// monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateInterval {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl DateInterval {
+    /// Returns the start of the bucket of `millis`, both in milliseconds
+    /// since the epoch, `utc_offset` is the offset in milliseconds of the
+    /// time zone the buckets are aligned to.
+    pub fn bucket_start(self, millis: i64, utc_offset: i64) -> i64 {
+        let local = millis.saturating_add(utc_offset);
+        let start = match self {
+            DateInterval::Second => local - local.rem_euclid(SECOND),
+            DateInterval::Minute => local - local.rem_euclid(MINUTE),
+            DateInterval::Hour => local - local.rem_euclid(HOUR),
+            DateInterval::Day => local - local.rem_euclid(DAY),
+            DateInterval::Week => {
+                // the epoch is a thursday
+                let days = local.div_euclid(DAY);
+                (days - (days + 3).rem_euclid(7)).saturating_mul(DAY)
+            }
+            DateInterval::Month | DateInterval::Quarter | DateInterval::Year => {
+                let (year, month, _) = civil_from_days(local.div_euclid(DAY));
+                let month = match self {
+                    DateInterval::Month => month,
+                    DateInterval::Quarter => (month - 1) / 3 * 3 + 1,
+                    _ => 1,
+                };
+                days_from_civil(year, month, 1).saturating_mul(DAY)
+            }
+        };
+        start.saturating_sub(utc_offset)
+    }
+}
+
+// The days since the epoch of a date of the proleptic gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The (year, month, day) of a number of days since the epoch.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// How the hits of a `NumericAggregation` are bucketed by their values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Buckets {
+    /// One bucket per range, the ranges may overlap.
+    LongRanges(Vec<LongRange>),
+    DoubleRanges(Vec<DoubleRange>),
+    /// Buckets of width `interval`, the lower bound of the buckets are
+    /// `offset + k * interval`.
+    Histogram {
+        interval: f64,
+        offset: f64,
+    },
+    /// Buckets of calendar intervals over the values of a date field in
+    /// milliseconds since the epoch.
+    DateHistogram {
+        interval: DateInterval,
+        utc_offset: i64,
+    },
+}
+
+/// The statistics of the metric values of the hits of a bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MetricStats {
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+}
+
+impl Default for MetricStats {
+    fn default() -> Self {
+        MetricStats {
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+        }
+    }
+}
+
+impl MetricStats {
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+    }
+
+    pub fn merge(&mut self, other: &MetricStats) {
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+    }
+
+    /// Returns the average of the values, `None` if there are no values.
+    pub fn avg(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.sum / self.count as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// A bucket of an `AggregationResult`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bucket {
+    /// The label of a range, the lower bound of a histogram bucket, or the
+    /// start in milliseconds of a date histogram bucket.
+    pub key: VariantValue,
+    pub doc_count: u64,
+    /// The statistics of the metric values, `None` if the aggregation has
+    /// no metric.
+    pub metric: Option<MetricStats>,
+}
+
+/// The buckets of a `NumericAggregation`.
+///
+/// The buckets of ranges are in the order of the ranges, including the
+/// empty ones. The buckets of histograms are in increasing key order, only
+/// the buckets with hits are returned.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregationResult {
+    pub name: String,
+    pub buckets: Vec<Bucket>,
+}
+
+/// Buckets the hits by the values of a numeric field, and optionally
+/// computes the min, max, sum and average of the values of another field
+/// in every bucket.
+///
+/// A hit is counted once per bucket even if several of its values are in
+/// the bucket, all the metric values of a hit are added to its buckets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericAggregation {
+    name: String,
+    source: NumericValuesSource,
+    buckets: Buckets,
+    metric: Option<NumericValuesSource>,
+}
+
+impl NumericAggregation {
+    pub fn new(name: &str, source: NumericValuesSource, buckets: Buckets) -> Result<Self> {
+        match buckets {
+            Buckets::LongRanges(_) | Buckets::DateHistogram { .. } if source.is_floating() => {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "aggregation '{}' needs an integer field, '{}' is a floating point field",
+                    name,
+                    source.field()
+                )));
+            }
+            Buckets::Histogram { interval, offset }
+                if !(interval.is_finite() && interval > 0.0 && offset.is_finite()) =>
+            {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "the interval of histogram '{}' must be positive",
+                    name
+                )));
+            }
+            _ => {}
+        }
+        Ok(NumericAggregation {
+            name: name.to_string(),
+            source,
+            buckets,
+            metric: None,
+        })
+    }
+
+    /// Computes the statistics of the values of `metric` in every bucket.
+    pub fn with_metric(mut self, metric: NumericValuesSource) -> Self {
+        self.metric = Some(metric);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn buckets(&self) -> &Buckets {
+        &self.buckets
+    }
+
+    // adds the distinct keys of the buckets of `doc` to `keys`, the keys of
+    // the ranges are their indexes
+    fn add_keys(
+        &self,
+        values: &mut LeafNumericValues,
+        doc: DocId,
+        keys: &mut Vec<i64>,
+    ) -> Result<()> {
+        match self.buckets {
+            Buckets::LongRanges(ref ranges) => {
+                let values = values.longs(doc)?;
+                for (i, range) in ranges.iter().enumerate() {
+                    if values.iter().any(|v| range.accept(*v)) {
+                        keys.push(i as i64);
+                    }
+                }
+            }
+            Buckets::DoubleRanges(ref ranges) => {
+                let values = values.doubles(doc)?;
+                for (i, range) in ranges.iter().enumerate() {
+                    if values.iter().any(|v| range.accept(*v)) {
+                        keys.push(i as i64);
+                    }
+                }
+            }
+            Buckets::Histogram { interval, offset } => {
+                for v in values.doubles(doc)? {
+                    let key = ((v - offset) / interval).floor();
+                    // NaN and out of range values have no bucket
+                    if key >= i64::min_value() as f64 && key < i64::max_value() as f64 {
+                        keys.push(key as i64);
+                    }
+                }
+                keys.sort();
+                keys.dedup();
+            }
+            Buckets::DateHistogram {
+                interval,
+                utc_offset,
+            } => {
+                for v in values.longs(doc)? {
+                    keys.push(interval.bucket_start(*v, utc_offset));
+                }
+                keys.sort();
+                keys.dedup();
+            }
+        }
+        Ok(())
+    }
+
+    fn bucket_key(&self, key: i64) -> VariantValue {
+        match self.buckets {
+            Buckets::LongRanges(ref ranges) => {
+                VariantValue::VString(ranges[key as usize].label.clone())
+            }
+            Buckets::DoubleRanges(ref ranges) => {
+                VariantValue::VString(ranges[key as usize].label.clone())
+            }
+            Buckets::Histogram { interval, offset } => {
+                VariantValue::Double(key as f64 * interval + offset)
+            }
+            Buckets::DateHistogram { .. } => VariantValue::Long(key),
+        }
+    }
+
+    fn result(&self, stats: &BTreeMap<i64, BucketStats>) -> AggregationResult {
+        let bucket = |key: i64, stats: &BucketStats| Bucket {
+            key: self.bucket_key(key),
+            doc_count: stats.doc_count,
+            metric: self.metric.as_ref().map(|_| stats.metric),
+        };
+        let range_count = match self.buckets {
+            Buckets::LongRanges(ref ranges) => Some(ranges.len()),
+            Buckets::DoubleRanges(ref ranges) => Some(ranges.len()),
+            _ => None,
+        };
+        let buckets = match range_count {
+            Some(count) => {
+                let empty = BucketStats::default();
+                (0..count as i64)
+                    .map(|key| bucket(key, stats.get(&key).unwrap_or(&empty)))
+                    .collect()
+            }
+            None => stats.iter().map(|(key, s)| bucket(*key, s)).collect(),
+        };
+        AggregationResult {
+            name: self.name.clone(),
+            buckets,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct BucketStats {
+    doc_count: u64,
+    metric: MetricStats,
+}
+
+impl BucketStats {
+    fn merge(&mut self, other: &BucketStats) {
+        self.doc_count += other.doc_count;
+        self.metric.merge(&other.metric);
+    }
+}
+
+struct LeafAggregation {
+    values: LeafNumericValues,
+    metric: Option<LeafNumericValues>,
+}
+
+struct AggregationsBase {
+    aggregations: Arc<Vec<NumericAggregation>>,
+    // aggregation -> bucket key -> stats
+    buckets: Vec<BTreeMap<i64, BucketStats>>,
+    total_hits: usize,
+    leaves: Vec<LeafAggregation>,
+    keys: Vec<i64>,
+}
+
+impl AggregationsBase {
+    fn new(aggregations: Arc<Vec<NumericAggregation>>) -> AggregationsBase {
+        let buckets = vec![BTreeMap::new(); aggregations.len()];
+        AggregationsBase {
+            aggregations,
+            buckets,
+            total_hits: 0,
+            leaves: vec![],
+            keys: vec![],
+        }
+    }
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        let mut leaves = Vec::with_capacity(self.aggregations.len());
+        for aggregation in self.aggregations.iter() {
+            let metric = match aggregation.metric {
+                Some(ref metric) => Some(metric.leaf_values(reader)?),
+                None => None,
+            };
+            leaves.push(LeafAggregation {
+                values: aggregation.source.leaf_values(reader)?,
+                metric,
+            });
+        }
+        self.leaves = leaves;
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId) -> Result<()> {
+        self.total_hits += 1;
+        for (i, aggregation) in self.aggregations.iter().enumerate() {
+            let leaf = &mut self.leaves[i];
+            self.keys.clear();
+            aggregation.add_keys(&mut leaf.values, doc, &mut self.keys)?;
+            if self.keys.is_empty() {
+                continue;
+            }
+            let metric_values: &[f64] = match leaf.metric {
+                Some(ref mut metric) => metric.doubles(doc)?,
+                None => &[],
+            };
+            for key in &self.keys {
+                let stats = self.buckets[i]
+                    .entry(*key)
+                    .or_insert_with(BucketStats::default);
+                stats.doc_count += 1;
+                for v in metric_values {
+                    stats.metric.add(*v);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, buckets: Vec<BTreeMap<i64, BucketStats>>, total_hits: usize) {
+        self.total_hits += total_hits;
+        for (merged, leaf_buckets) in self.buckets.iter_mut().zip(buckets.into_iter()) {
+            for (key, stats) in leaf_buckets {
+                merged
+                    .entry(key)
+                    .or_insert_with(BucketStats::default)
+                    .merge(&stats);
+            }
+        }
+    }
+}
+
+struct LeafBuckets {
+    buckets: Vec<BTreeMap<i64, BucketStats>>,
+    total_hits: usize,
+}
+
+/// A `Collector` that computes several `NumericAggregation`s of the hits
+/// in a single pass.
+pub struct NumericAggregationCollector {
+    base: AggregationsBase,
+    channel: Option<(Sender<LeafBuckets>, Receiver<LeafBuckets>)>,
+}
+
+impl NumericAggregationCollector {
+    pub fn new(aggregations: Vec<NumericAggregation>) -> NumericAggregationCollector {
+        NumericAggregationCollector {
+            base: AggregationsBase::new(Arc::new(aggregations)),
+            channel: None,
+        }
+    }
+
+    pub fn total_hits(&self) -> usize {
+        self.base.total_hits
+    }
+
+    /// Returns the results of the aggregations, in the order of the
+    /// aggregations.
+    pub fn results(&self) -> Vec<AggregationResult> {
+        self.base
+            .aggregations
+            .iter()
+            .zip(self.base.buckets.iter())
+            .map(|(aggregation, buckets)| aggregation.result(buckets))
+            .collect()
+    }
+}
+
+impl SearchCollector for NumericAggregationCollector {
+    type LC = NumericAggregationLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self::LC> {
+        let mut base = AggregationsBase::new(Arc::clone(&self.base.aggregations));
+        base.set_next_reader(reader)?;
+        Ok(NumericAggregationLeafCollector {
+            base,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(leaf) = receiver.recv() {
+                self.base.merge(leaf.buckets, leaf.total_hits);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for NumericAggregationCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.base.collect(doc)
+    }
+}
+
+pub struct NumericAggregationLeafCollector {
+    base: AggregationsBase,
+    channel: Sender<LeafBuckets>,
+}
+
+impl ParallelLeafCollector for NumericAggregationLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let leaf = LeafBuckets {
+            buckets: mem::replace(&mut self.base.buckets, vec![]),
+            total_hits: self.base.total_hits,
+        };
+        self.channel
+            .send(leaf)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for NumericAggregationLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.base.collect(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::doc_values::NumericDocValues;
+    use core::search::sort_field::SortFieldType;
+    use core::search::tests::*;
+
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::*;
+
+    #[test]
+    fn test_date_bucket_start() {
+        // 2019-11-13T10:20:30.400Z, a wednesday
+        let millis = 1_573_640_430_400;
+        assert_eq!(
+            DateInterval::Second.bucket_start(millis, 0),
+            1_573_640_430_000
+        );
+        assert_eq!(
+            DateInterval::Hour.bucket_start(millis, 0),
+            1_573_639_200_000
+        );
+        // 2019-11-13T00:00:00Z
+        assert_eq!(DateInterval::Day.bucket_start(millis, 0), 1_573_603_200_000);
+        // 2019-11-11T00:00:00Z, the monday
+        assert_eq!(
+            DateInterval::Week.bucket_start(millis, 0),
+            1_573_430_400_000
+        );
+        // 2019-11-01T00:00:00Z
+        assert_eq!(
+            DateInterval::Month.bucket_start(millis, 0),
+            1_572_566_400_000
+        );
+        // 2019-10-01T00:00:00Z
+        assert_eq!(
+            DateInterval::Quarter.bucket_start(millis, 0),
+            1_569_888_000_000
+        );
+        // 2019-01-01T00:00:00Z
+        assert_eq!(
+            DateInterval::Year.bucket_start(millis, 0),
+            1_546_300_800_000
+        );
+        // 2019-11-13T00:00:00+08:00
+        assert_eq!(
+            DateInterval::Day.bucket_start(millis, 8 * HOUR),
+            1_573_603_200_000 - 8 * HOUR
+        );
+        // 1969-12-31T23:59:59.999Z is in 1969
+        assert_eq!(DateInterval::Year.bucket_start(-1, 0), -365 * DAY);
+        assert_eq!(DateInterval::Week.bucket_start(0, 0), -3 * DAY);
+
+        for days in &[-719_468, -1, 0, 59, 10_957, 18_213] {
+            let (year, month, day) = civil_from_days(*days);
+            assert_eq!(days_from_civil(year, month, day), *days);
+        }
+    }
+
+    #[test]
+    fn test_aggregation_collector() {
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        // the mock field has a numeric doc value for the docs 1 and 2
+        let mock_values = MockNumericValues::default();
+        let v1 = mock_values.get(1).unwrap();
+        let v2 = mock_values.get(2).unwrap();
+
+        let source = NumericValuesSource::new("test", SortFieldType::Long).unwrap();
+        let ranges = vec![
+            LongRange::new("all", i64::min_value(), true, i64::max_value(), true).unwrap(),
+            LongRange::new("none", 1000, true, 2000, true).unwrap(),
+        ];
+        let range_aggregation =
+            NumericAggregation::new("ranges", source.clone(), Buckets::LongRanges(ranges))
+                .unwrap()
+                .with_metric(source.clone());
+        let histogram = Buckets::Histogram {
+            interval: 1000.0,
+            offset: 0.0,
+        };
+        let histogram_aggregation =
+            NumericAggregation::new("histogram", source.clone(), histogram).unwrap();
+        let bad_histogram = Buckets::Histogram {
+            interval: 0.0,
+            offset: 0.0,
+        };
+        assert!(NumericAggregation::new("bad", source.clone(), bad_histogram).is_err());
+
+        let mut collector =
+            NumericAggregationCollector::new(vec![range_aggregation, histogram_aggregation]);
+        assert!(!collector.needs_scores());
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        let mut scorer = create_mock_scorer(vec![1, 2]);
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+
+        assert_eq!(collector.total_hits(), 2);
+        let results = collector.results();
+        assert_eq!(results.len(), 2);
+
+        let ranges = &results[0];
+        assert_eq!(ranges.buckets.len(), 2);
+        assert_eq!(ranges.buckets[0].key, VariantValue::VString("all".into()));
+        assert_eq!(ranges.buckets[0].doc_count, 2);
+        let metric = ranges.buckets[0].metric.unwrap();
+        assert_eq!(metric.count, 2);
+        assert!((metric.min - v1.min(v2) as f64).abs() < ::std::f64::EPSILON);
+        assert!((metric.max - v1.max(v2) as f64).abs() < ::std::f64::EPSILON);
+        assert!((metric.avg().unwrap() - (v1 + v2) as f64 / 2.0).abs() < 1e-9);
+        assert_eq!(ranges.buckets[1].doc_count, 0);
+        assert_eq!(ranges.buckets[1].metric.unwrap().avg(), None);
+
+        // the mock values are bytes, they are all in the first bucket
+        let histogram = &results[1];
+        assert_eq!(histogram.buckets.len(), 1);
+        assert_eq!(histogram.buckets[0].key, VariantValue::Double(0.0));
+        assert_eq!(histogram.buckets[0].doc_count, 2);
+        assert!(histogram.buckets[0].metric.is_none());
+    }
+}
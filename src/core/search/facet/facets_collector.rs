@@ -0,0 +1,217 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::util::DocId;
+use error::{ErrorKind::IllegalState, Result, ResultExt};
+
+/// The hits of a segment.
+#[derive(Debug, Clone)]
+pub struct MatchingDocs {
+    /// The ord of the segment in the top level reader.
+    pub ord: usize,
+    pub doc_base: DocId,
+    /// The segment doc ids of the hits, in increasing order.
+    pub docs: Vec<DocId>,
+    /// The scores of the hits, empty unless the scores are kept.
+    pub scores: Vec<f32>,
+}
+
+impl MatchingDocs {
+    fn new(ord: usize, doc_base: DocId) -> MatchingDocs {
+        MatchingDocs {
+            ord,
+            doc_base,
+            docs: vec![],
+            scores: vec![],
+        }
+    }
+
+    fn add<S: Scorer + ?Sized>(
+        &mut self,
+        doc: DocId,
+        scorer: &mut S,
+        keep_scores: bool,
+    ) -> Result<()> {
+        self.docs.push(doc);
+        if keep_scores {
+            self.scores.push(scorer.score()?);
+        }
+        Ok(())
+    }
+}
+
+/// A `Collector` that records the hits of every segment, for the facet
+/// counts to be computed after the search, e.g. by
+/// `SortedSetDocValuesFacetCounts`.
+pub struct FacetsCollector {
+    keep_scores: bool,
+    matching_docs: Vec<MatchingDocs>,
+    channel: Option<(Sender<MatchingDocs>, Receiver<MatchingDocs>)>,
+}
+
+impl Default for FacetsCollector {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl FacetsCollector {
+    pub fn new(keep_scores: bool) -> FacetsCollector {
+        FacetsCollector {
+            keep_scores,
+            matching_docs: vec![],
+            channel: None,
+        }
+    }
+
+    pub fn keep_scores(&self) -> bool {
+        self.keep_scores
+    }
+
+    /// Returns the hits of the segments, ordered by segment ord.
+    pub fn matching_docs(&self) -> &[MatchingDocs] {
+        &self.matching_docs
+    }
+
+    pub fn total_hits(&self) -> usize {
+        self.matching_docs.iter().map(|m| m.docs.len()).sum()
+    }
+}
+
+impl SearchCollector for FacetsCollector {
+    type LC = FacetsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.matching_docs
+            .push(MatchingDocs::new(reader.ord, reader.doc_base));
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self::LC> {
+        Ok(FacetsLeafCollector {
+            matching_docs: MatchingDocs::new(reader.ord, reader.doc_base),
+            keep_scores: self.keep_scores,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.matching_docs.push(docs);
+            }
+            self.matching_docs.sort_by_key(|m| m.ord);
+        }
+        Ok(())
+    }
+}
+
+impl Collector for FacetsCollector {
+    fn needs_scores(&self) -> bool {
+        self.keep_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        debug_assert!(!self.matching_docs.is_empty());
+        let keep_scores = self.keep_scores;
+        self.matching_docs
+            .last_mut()
+            .unwrap()
+            .add(doc, scorer, keep_scores)
+    }
+}
+
+pub struct FacetsLeafCollector {
+    matching_docs: MatchingDocs,
+    keep_scores: bool,
+    channel: Sender<MatchingDocs>,
+}
+
+impl ParallelLeafCollector for FacetsLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let ord = self.matching_docs.ord;
+        let doc_base = self.matching_docs.doc_base;
+        let docs = mem::replace(&mut self.matching_docs, MatchingDocs::new(ord, doc_base));
+        self.channel
+            .send(docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for FacetsLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.keep_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.matching_docs.add(doc, scorer, self.keep_scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    use core::index::reader::IndexReader;
+    use core::index::tests::*;
+    use core::search::*;
+
+    #[test]
+    fn test_facets_collector() {
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        let mut collector = FacetsCollector::new(true);
+        assert!(collector.needs_scores());
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+
+        let mut scorer = create_mock_scorer(vec![1, 2, 3, 5]);
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+
+        assert_eq!(collector.total_hits(), 4);
+        let matching_docs = collector.matching_docs();
+        assert_eq!(matching_docs.len(), 1);
+        assert_eq!(matching_docs[0].docs, vec![1, 2, 3, 5]);
+        assert_eq!(matching_docs[0].scores.len(), 4);
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod facets_collector;
+
+pub use self::facets_collector::*;
+
+mod sorted_set_facet_counts;
+
+pub use self::sorted_set_facet_counts::*;
+
+mod numeric_values;
+
+pub use self::numeric_values::*;
+
+mod range_facet_counts;
+
+pub use self::range_facet_counts::*;
+
+mod aggregation;
+
+pub use self::aggregation::*;
+
+use error::{ErrorKind, Result};
+
+/// The delimiter of the components of a hierarchical facet path, e.g.
+/// `topics/science/physics`.
+pub const FACET_PATH_DELIMITER: char = '/';
+
+/// Returns the values to index for the facet `path`, which are the path
+/// and all its ancestors, e.g. `a`, `a/b` and `a/b/c` for `a/b/c`.
+///
+/// Indexing the ancestors lets every level of the hierarchy be counted
+/// exactly, a doc is counted once for `a` even if it has both `a/b` and
+/// `a/c`.
+pub fn facet_path_values(path: &str) -> Result<Vec<String>> {
+    let mut values = Vec::new();
+    for (i, c) in path.char_indices() {
+        if c == FACET_PATH_DELIMITER {
+            values.push(path[..i].to_string());
+        }
+    }
+    values.push(path.to_string());
+    if values
+        .iter()
+        .any(|v| v.is_empty() || v.ends_with(FACET_PATH_DELIMITER))
+    {
+        bail!(ErrorKind::IllegalArgument(format!(
+            "facet path '{}' has an empty component",
+            path
+        )));
+    }
+    Ok(values)
+}
+
+fn join_facet_path(path: &[&str]) -> Result<String> {
+    for component in path {
+        if component.is_empty() || component.contains(FACET_PATH_DELIMITER) {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "invalid facet path component '{}'",
+                component
+            )));
+        }
+    }
+    Ok(path.join(&FACET_PATH_DELIMITER.to_string()))
+}
+
+/// A label and its count, a child of a `FacetResult`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LabelAndValue {
+    pub label: String,
+    pub value: i64,
+}
+
+impl LabelAndValue {
+    pub fn new(label: String, value: i64) -> LabelAndValue {
+        LabelAndValue { label, value }
+    }
+}
+
+/// The top children of a facet path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FacetResult {
+    /// The path whose children are counted, empty for the top level labels.
+    pub path: Vec<String>,
+    /// The number of hits of the path, `-1` if it is unknown.
+    pub value: i64,
+    /// The number of children with a non zero count.
+    pub child_count: usize,
+    /// The top children, by descending count.
+    pub label_values: Vec<LabelAndValue>,
+}
+
+impl FacetResult {
+    pub fn new(
+        path: Vec<String>,
+        value: i64,
+        child_count: usize,
+        label_values: Vec<LabelAndValue>,
+    ) -> FacetResult {
+        FacetResult {
+            path,
+            value,
+            child_count,
+            label_values,
+        }
+    }
+
+    /// The first component of the path.
+    pub fn dim(&self) -> &str {
+        self.path.first().map(String::as_str).unwrap_or("")
+    }
+}
+
+/// The counts of the facets of the hits collected by a `FacetsCollector`.
+pub trait Facets {
+    /// Returns the `top_n` children of `path` with the highest counts,
+    /// `None` if the path has no children.
+    fn get_top_children(&self, top_n: usize, path: &[&str]) -> Result<Option<FacetResult>>;
+
+    /// Returns the count of `path`, 0 if it is not a facet value.
+    fn get_specific_value(&self, path: &[&str]) -> Result<i64>;
+
+    /// Returns the top children of every top level label, by descending
+    /// count of the label.
+    fn get_all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facet_path_values() {
+        let values = facet_path_values("a/b/c").unwrap();
+        assert_eq!(values, vec!["a", "a/b", "a/b/c"]);
+        assert_eq!(facet_path_values("a").unwrap(), vec!["a"]);
+        assert!(facet_path_values("a//c").is_err());
+        assert!(facet_path_values("/a").is_err());
+        assert!(facet_path_values("a/").is_err());
+
+        assert_eq!(join_facet_path(&["a", "b"]).unwrap(), "a/b");
+        assert_eq!(join_facet_path(&[]).unwrap(), "");
+        assert!(join_facet_path(&["a/b"]).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::search::facet::FacetsCollector;
+use core::search::sort_field::SortFieldType;
+use core::util::{sortable_int2float, sortable_long2double, BitsMut, DocId};
+use error::{ErrorKind, Result};
+
+/// The numeric doc values of a field read by the range facets and the
+/// numeric aggregations.
+///
+/// A single valued field is read from the numeric doc values, a multi
+/// valued one from the sorted numeric doc values, and all the values of a
+/// doc are used. Date fields are read as `Long` values, e.g. milliseconds
+/// since the epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericValuesSource {
+    field: String,
+    numeric_type: SortFieldType,
+    multi_valued: bool,
+}
+
+impl NumericValuesSource {
+    pub fn new(field: &str, numeric_type: SortFieldType) -> Result<Self> {
+        Self::build(field, numeric_type, false)
+    }
+
+    pub fn multi_valued(field: &str, numeric_type: SortFieldType) -> Result<Self> {
+        Self::build(field, numeric_type, true)
+    }
+
+    fn build(field: &str, numeric_type: SortFieldType, multi_valued: bool) -> Result<Self> {
+        match numeric_type {
+            SortFieldType::Int
+            | SortFieldType::Long
+            | SortFieldType::Float
+            | SortFieldType::Double => {}
+            _ => bail!(ErrorKind::IllegalArgument(format!(
+                "field '{}' must be of a numeric type, got {:?}",
+                field, numeric_type
+            ))),
+        }
+        Ok(NumericValuesSource {
+            field: field.to_string(),
+            numeric_type,
+            multi_valued,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn numeric_type(&self) -> SortFieldType {
+        self.numeric_type
+    }
+
+    pub fn is_multi_valued(&self) -> bool {
+        self.multi_valued
+    }
+
+    /// Whether the values are floating point values.
+    pub fn is_floating(&self) -> bool {
+        self.numeric_type == SortFieldType::Float || self.numeric_type == SortFieldType::Double
+    }
+
+    /// Returns the values of the field in a leaf, a leaf without doc values
+    /// for the field has no values.
+    pub fn leaf_values<C: Codec>(
+        &self,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<LeafNumericValues> {
+        let dv_type = if self.multi_valued {
+            DocValuesType::SortedNumeric
+        } else {
+            DocValuesType::Numeric
+        };
+        let values = match ctx.reader.field_infos().field_info_by_name(&self.field) {
+            Some(fi) if fi.doc_values_type == dv_type => {
+                if self.multi_valued {
+                    LeafValues::Multi(ctx.reader.get_sorted_numeric_doc_values(&self.field)?)
+                } else {
+                    LeafValues::Single(
+                        ctx.reader.get_numeric_doc_values(&self.field)?,
+                        ctx.reader.get_docs_with_field(&self.field)?,
+                    )
+                }
+            }
+            _ => LeafValues::Empty,
+        };
+        Ok(LeafNumericValues {
+            values,
+            numeric_type: self.numeric_type,
+            longs: vec![],
+            doubles: vec![],
+        })
+    }
+}
+
+enum LeafValues {
+    Single(Box<dyn NumericDocValues>, Box<dyn BitsMut>),
+    Multi(Box<dyn SortedNumericDocValues>),
+    Empty,
+}
+
+/// The per segment values of a `NumericValuesSource`.
+pub struct LeafNumericValues {
+    values: LeafValues,
+    numeric_type: SortFieldType,
+    longs: Vec<i64>,
+    doubles: Vec<f64>,
+}
+
+impl LeafNumericValues {
+    /// Returns the values of `doc` of an integer field, empty if it has
+    /// no value.
+    pub fn longs(&mut self, doc: DocId) -> Result<&[i64]> {
+        debug_assert!(
+            self.numeric_type == SortFieldType::Int || self.numeric_type == SortFieldType::Long
+        );
+        self.read(doc)?;
+        Ok(self.longs.as_slice())
+    }
+
+    /// Returns the values of `doc` converted to doubles, empty if it has
+    /// no value.
+    pub fn doubles(&mut self, doc: DocId) -> Result<&[f64]> {
+        self.read(doc)?;
+        let multi_valued = match self.values {
+            LeafValues::Multi(_) => true,
+            _ => false,
+        };
+        self.doubles.clear();
+        for &raw in &self.longs {
+            // the floating point values of the sorted numeric doc values are
+            // stored in sortable order
+            let value = match (self.numeric_type, multi_valued) {
+                (SortFieldType::Float, false) => f64::from(f32::from_bits(raw as u32)),
+                (SortFieldType::Float, true) => f64::from(sortable_int2float(raw as i32)),
+                (SortFieldType::Double, false) => f64::from_bits(raw as u64),
+                (SortFieldType::Double, true) => sortable_long2double(raw),
+                _ => raw as f64,
+            };
+            self.doubles.push(value);
+        }
+        Ok(self.doubles.as_slice())
+    }
+
+    fn read(&mut self, doc: DocId) -> Result<()> {
+        self.longs.clear();
+        match self.values {
+            LeafValues::Single(ref mut values, ref mut docs_with_field) => {
+                if docs_with_field.get(doc as usize)? {
+                    self.longs.push(values.get_mut(doc)?);
+                }
+            }
+            LeafValues::Multi(ref mut values) => {
+                values.set_document(doc)?;
+                for i in 0..values.count() {
+                    self.longs.push(values.value_at(i)?);
+                }
+            }
+            LeafValues::Empty => {}
+        }
+        Ok(())
+    }
+}
+
+/// Calls `f` with the values of `source` of every hit of `hits`, which
+/// must be collected on `reader`.
+pub(crate) fn for_each_hit<C, F>(
+    source: &NumericValuesSource,
+    reader: &dyn IndexReader<Codec = C>,
+    hits: &FacetsCollector,
+    mut f: F,
+) -> Result<()>
+where
+    C: Codec,
+    F: FnMut(&mut LeafNumericValues, DocId) -> Result<()>,
+{
+    let leaves = reader.leaves();
+    for matching_docs in hits.matching_docs() {
+        if matching_docs.docs.is_empty() {
+            continue;
+        }
+        if matching_docs.ord >= leaves.len() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "the hits are collected on another reader, unknown segment {}",
+                matching_docs.ord
+            )));
+        }
+        let mut values = source.leaf_values(&leaves[matching_docs.ord])?;
+        for &doc in &matching_docs.docs {
+            f(&mut values, doc)?;
+        }
+    }
+    Ok(())
+}
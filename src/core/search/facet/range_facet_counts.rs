@@ -0,0 +1,385 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::IndexReader;
+use core::search::facet::{
+    for_each_hit, FacetResult, Facets, FacetsCollector, LabelAndValue, NumericValuesSource,
+};
+use error::{ErrorKind, Result};
+
+/// A range of long values, e.g. a price range in cents or a time range in
+/// milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LongRange {
+    pub label: String,
+    /// The inclusive lower bound.
+    pub min: i64,
+    /// The inclusive upper bound.
+    pub max: i64,
+}
+
+impl LongRange {
+    pub fn new(
+        label: &str,
+        min: i64,
+        min_inclusive: bool,
+        max: i64,
+        max_inclusive: bool,
+    ) -> Result<LongRange> {
+        let min = if min_inclusive {
+            Some(min)
+        } else {
+            min.checked_add(1)
+        };
+        let max = if max_inclusive {
+            Some(max)
+        } else {
+            max.checked_sub(1)
+        };
+        match (min, max) {
+            (Some(min), Some(max)) if min <= max => Ok(LongRange {
+                label: label.to_string(),
+                min,
+                max,
+            }),
+            _ => bail!(ErrorKind::IllegalArgument(format!(
+                "range '{}' matches no values",
+                label
+            ))),
+        }
+    }
+
+    pub fn accept(&self, value: i64) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+/// A range of double values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoubleRange {
+    pub label: String,
+    pub min: f64,
+    pub min_inclusive: bool,
+    pub max: f64,
+    pub max_inclusive: bool,
+}
+
+impl DoubleRange {
+    pub fn new(
+        label: &str,
+        min: f64,
+        min_inclusive: bool,
+        max: f64,
+        max_inclusive: bool,
+    ) -> Result<DoubleRange> {
+        if min.is_nan() || max.is_nan() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "the bounds of range '{}' must not be NaN",
+                label
+            )));
+        }
+        if min > max || (min >= max && !(min_inclusive && max_inclusive)) {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "range '{}' matches no values",
+                label
+            )));
+        }
+        Ok(DoubleRange {
+            label: label.to_string(),
+            min,
+            min_inclusive,
+            max,
+            max_inclusive,
+        })
+    }
+
+    pub fn accept(&self, value: f64) -> bool {
+        let above_min = if self.min_inclusive {
+            value >= self.min
+        } else {
+            value > self.min
+        };
+        let below_max = if self.max_inclusive {
+            value <= self.max
+        } else {
+            value < self.max
+        };
+        above_min && below_max
+    }
+}
+
+// The counts of the ranges of a field, a doc is counted once per range
+// even if several of its values are in the range.
+struct RangeCounts {
+    field: String,
+    labels: Vec<String>,
+    counts: Vec<i64>,
+    // the number of docs in at least one range
+    total_count: i64,
+}
+
+impl RangeCounts {
+    fn new(field: &str, labels: Vec<String>) -> RangeCounts {
+        let counts = vec![0; labels.len()];
+        RangeCounts {
+            field: field.to_string(),
+            labels,
+            counts,
+            total_count: 0,
+        }
+    }
+
+    fn add_doc<F: Fn(usize) -> bool>(&mut self, in_range: F) {
+        let mut matched = false;
+        for (i, count) in self.counts.iter_mut().enumerate() {
+            if in_range(i) {
+                *count += 1;
+                matched = true;
+            }
+        }
+        if matched {
+            self.total_count += 1;
+        }
+    }
+
+    fn check_dim(&self, dim: &str) -> Result<()> {
+        if dim != self.field {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "invalid dim '{}', the ranges are counted on field '{}'",
+                dim, self.field
+            )));
+        }
+        Ok(())
+    }
+
+    fn result(&self) -> FacetResult {
+        let label_values = self
+            .labels
+            .iter()
+            .zip(self.counts.iter())
+            .map(|(label, count)| LabelAndValue::new(label.clone(), *count))
+            .collect();
+        FacetResult::new(
+            vec![self.field.clone()],
+            self.total_count,
+            self.labels.len(),
+            label_values,
+        )
+    }
+}
+
+impl Facets for RangeCounts {
+    fn get_top_children(&self, _top_n: usize, path: &[&str]) -> Result<Option<FacetResult>> {
+        if path.len() != 1 {
+            bail!(ErrorKind::IllegalArgument(
+                "the path of the ranges must be the field only".into()
+            ));
+        }
+        self.check_dim(path[0])?;
+        Ok(Some(self.result()))
+    }
+
+    fn get_specific_value(&self, path: &[&str]) -> Result<i64> {
+        if path.len() != 2 {
+            bail!(ErrorKind::IllegalArgument(
+                "the path of a range must be the field and the range label".into()
+            ));
+        }
+        self.check_dim(path[0])?;
+        match self.labels.iter().position(|l| l == path[1]) {
+            Some(i) => Ok(self.counts[i]),
+            None => bail!(ErrorKind::IllegalArgument(format!(
+                "unknown range '{}'",
+                path[1]
+            ))),
+        }
+    }
+
+    fn get_all_dims(&self, _top_n: usize) -> Result<Vec<FacetResult>> {
+        Ok(vec![self.result()])
+    }
+}
+
+/// Counts the hits of a `FacetsCollector` in every `LongRange`.
+///
+/// The ranges may overlap. The facet result has one child per range, in
+/// the order of the ranges whatever the `top_n`, its value is the number of
+/// hits in at least one range.
+pub struct LongRangeFacetCounts {
+    ranges: Vec<LongRange>,
+    counts: RangeCounts,
+}
+
+impl LongRangeFacetCounts {
+    pub fn new<C: Codec>(
+        source: &NumericValuesSource,
+        ranges: Vec<LongRange>,
+        reader: &dyn IndexReader<Codec = C>,
+        hits: &FacetsCollector,
+    ) -> Result<Self> {
+        if source.is_floating() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "long ranges can't be counted on floating point field '{}'",
+                source.field()
+            )));
+        }
+        let labels = ranges.iter().map(|r| r.label.clone()).collect();
+        let mut counts = RangeCounts::new(source.field(), labels);
+        for_each_hit(source, reader, hits, |values, doc| {
+            let values = values.longs(doc)?;
+            counts.add_doc(|i| values.iter().any(|v| ranges[i].accept(*v)));
+            Ok(())
+        })?;
+        Ok(LongRangeFacetCounts { ranges, counts })
+    }
+
+    pub fn ranges(&self) -> &[LongRange] {
+        &self.ranges
+    }
+}
+
+impl Facets for LongRangeFacetCounts {
+    fn get_top_children(&self, top_n: usize, path: &[&str]) -> Result<Option<FacetResult>> {
+        self.counts.get_top_children(top_n, path)
+    }
+
+    fn get_specific_value(&self, path: &[&str]) -> Result<i64> {
+        self.counts.get_specific_value(path)
+    }
+
+    fn get_all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        self.counts.get_all_dims(top_n)
+    }
+}
+
+/// Counts the hits of a `FacetsCollector` in every `DoubleRange`, the
+/// values of an integer field are converted to doubles.
+///
+/// The facet result is the same as the one of `LongRangeFacetCounts`.
+pub struct DoubleRangeFacetCounts {
+    ranges: Vec<DoubleRange>,
+    counts: RangeCounts,
+}
+
+impl DoubleRangeFacetCounts {
+    pub fn new<C: Codec>(
+        source: &NumericValuesSource,
+        ranges: Vec<DoubleRange>,
+        reader: &dyn IndexReader<Codec = C>,
+        hits: &FacetsCollector,
+    ) -> Result<Self> {
+        let labels = ranges.iter().map(|r| r.label.clone()).collect();
+        let mut counts = RangeCounts::new(source.field(), labels);
+        for_each_hit(source, reader, hits, |values, doc| {
+            let values = values.doubles(doc)?;
+            counts.add_doc(|i| values.iter().any(|v| ranges[i].accept(*v)));
+            Ok(())
+        })?;
+        Ok(DoubleRangeFacetCounts { ranges, counts })
+    }
+
+    pub fn ranges(&self) -> &[DoubleRange] {
+        &self.ranges
+    }
+}
+
+impl Facets for DoubleRangeFacetCounts {
+    fn get_top_children(&self, top_n: usize, path: &[&str]) -> Result<Option<FacetResult>> {
+        self.counts.get_top_children(top_n, path)
+    }
+
+    fn get_specific_value(&self, path: &[&str]) -> Result<i64> {
+        self.counts.get_specific_value(path)
+    }
+
+    fn get_all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        self.counts.get_all_dims(top_n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::doc_values::NumericDocValues;
+    use core::search::collector::{Collector, SearchCollector};
+    use core::search::sort_field::SortFieldType;
+    use core::search::tests::*;
+
+    use core::index::tests::*;
+    use core::search::*;
+
+    #[test]
+    fn test_ranges() {
+        assert!(LongRange::new("a", 5, false, 6, false).is_err());
+        assert!(LongRange::new("a", i64::max_value(), false, 0, true).is_err());
+        let range = LongRange::new("a", 5, false, 10, true).unwrap();
+        assert!(!range.accept(5));
+        assert!(range.accept(6));
+        assert!(range.accept(10));
+
+        assert!(DoubleRange::new("b", 1.0, true, 1.0, false).is_err());
+        assert!(DoubleRange::new("b", ::std::f64::NAN, true, 1.0, true).is_err());
+        let range = DoubleRange::new("b", 0.5, true, 1.0, false).unwrap();
+        assert!(range.accept(0.5));
+        assert!(!range.accept(1.0));
+    }
+
+    #[test]
+    fn test_long_range_facet_counts() {
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        let mut collector = FacetsCollector::default();
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        let mut scorer = create_mock_scorer(vec![1, 2]);
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+
+        // the mock field has a numeric doc value for the docs 1 and 2
+        let mock_values = MockNumericValues::default();
+        let v1 = mock_values.get(1).unwrap();
+        let v2 = mock_values.get(2).unwrap();
+        let ranges = vec![
+            LongRange::new("first", v1, true, v1, true).unwrap(),
+            LongRange::new("all", i64::min_value(), true, i64::max_value(), true).unwrap(),
+            LongRange::new("none", 1000, true, 2000, true).unwrap(),
+        ];
+        let source = NumericValuesSource::new("test", SortFieldType::Long).unwrap();
+        let counts = LongRangeFacetCounts::new(&source, ranges, &index_reader, &collector).unwrap();
+
+        let result = counts.get_top_children(1, &["test"]).unwrap().unwrap();
+        assert_eq!(result.value, 2);
+        assert_eq!(result.child_count, 3);
+        let first = if v1 == v2 { 2 } else { 1 };
+        assert_eq!(
+            result.label_values[0],
+            LabelAndValue::new("first".into(), first)
+        );
+        assert_eq!(result.label_values[1].value, 2);
+        assert_eq!(result.label_values[2].value, 0);
+        assert_eq!(counts.get_specific_value(&["test", "all"]).unwrap(), 2);
+        assert!(counts.get_specific_value(&["test", "unknown"]).is_err());
+        assert!(counts.get_top_children(10, &["other"]).is_err());
+
+        let source = NumericValuesSource::new("test", SortFieldType::Double).unwrap();
+        assert!(LongRangeFacetCounts::new(&source, vec![], &index_reader, &collector).is_err());
+    }
+}
@@ -0,0 +1,559 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use core::codec::doc_values::{
+    EmptySortedSetDocValues, OrdinalMap, SortedSetDocValues, NO_MORE_ORDS,
+};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::search::facet::{
+    join_facet_path, FacetResult, Facets, FacetsCollector, LabelAndValue, FACET_PATH_DELIMITER,
+};
+use core::util::packed::COMPACT;
+use core::util::{DocId, LongValues};
+use error::{ErrorKind, Result};
+
+fn leaf_sorted_set_values<C: Codec>(
+    reader: &LeafReaderContext<'_, C>,
+    field: &str,
+) -> Result<Box<dyn SortedSetDocValues>> {
+    match reader.reader.field_infos().field_info_by_name(field) {
+        Some(fi) if fi.doc_values_type == DocValuesType::SortedSet => {
+            reader.reader.get_sorted_set_doc_values(field)
+        }
+        _ => Ok(Box::new(EmptySortedSetDocValues)),
+    }
+}
+
+/// The global ordinals of the values of a `SortedSet` doc values field of a
+/// top level reader, and the hierarchy of the facet paths of the values.
+///
+/// Building the state reads all the values of the field, it should be built
+/// once per opened reader and shared by the searches on that reader.
+pub struct SortedSetDocValuesReaderState {
+    field: String,
+    leaf_count: usize,
+    // None if the reader has at most one segment, the segment ords are
+    // the global ords then
+    ordinal_map: Option<OrdinalMap>,
+    value_count: usize,
+    // parent path -> (global ord, label) of the children, in term order
+    children: HashMap<String, Vec<(usize, String)>>,
+    // path -> global ord
+    ords: HashMap<String, usize>,
+}
+
+impl SortedSetDocValuesReaderState {
+    pub fn new<C: Codec>(reader: &dyn IndexReader<Codec = C>, field: &str) -> Result<Self> {
+        let leaves = reader.leaves();
+        let mut values = Vec::with_capacity(leaves.len());
+        for leaf in &leaves {
+            values.push(leaf_sorted_set_values(leaf, field)?);
+        }
+
+        let ordinal_map = if values.len() > 1 {
+            let mut subs = Vec::with_capacity(values.len());
+            let mut weights = Vec::with_capacity(values.len());
+            for dv in &values {
+                subs.push(Some(dv.term_iterator()?));
+                weights.push(dv.get_value_count());
+            }
+            Some(OrdinalMap::build(subs, weights, COMPACT)?)
+        } else {
+            None
+        };
+        let value_count = match ordinal_map {
+            Some(ref map) => map.value_count() as usize,
+            None => values.first().map_or(0, |dv| dv.get_value_count()),
+        };
+
+        let mut children: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+        let mut ords = HashMap::with_capacity(value_count);
+        for global_ord in 0..value_count {
+            let term = match ordinal_map {
+                Some(ref map) => {
+                    let segment = map.first_segment_number(global_ord as i64) as usize;
+                    let segment_ord = map.first_segment_ord(global_ord as i64);
+                    values[segment].lookup_ord(segment_ord)?
+                }
+                None => values[0].lookup_ord(global_ord as i64)?,
+            };
+            let path = match String::from_utf8(term) {
+                Ok(path) => path,
+                Err(_) => bail!(ErrorKind::IllegalArgument(format!(
+                    "facet field '{}' has a value which is not valid utf-8",
+                    field
+                ))),
+            };
+            let (parent, label) = match path.rfind(FACET_PATH_DELIMITER) {
+                Some(i) => (&path[..i], &path[i + 1..]),
+                None => ("", path.as_str()),
+            };
+            children
+                .entry(parent.to_string())
+                .or_insert_with(Vec::new)
+                .push((global_ord, label.to_string()));
+            ords.insert(path, global_ord);
+        }
+
+        Ok(SortedSetDocValuesReaderState {
+            field: field.to_string(),
+            leaf_count: leaves.len(),
+            ordinal_map,
+            value_count,
+            children,
+            ords,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The number of distinct values of the field in the reader.
+    pub fn value_count(&self) -> usize {
+        self.value_count
+    }
+
+    pub fn ordinal_map(&self) -> Option<&OrdinalMap> {
+        self.ordinal_map.as_ref()
+    }
+
+    fn global_ords(&self, segment: usize) -> Option<Rc<dyn LongValues>> {
+        self.ordinal_map
+            .as_ref()
+            .map(|map| map.get_global_ords(segment))
+    }
+}
+
+/// Counts the facet values of the hits of a `FacetsCollector`, the values
+/// are the ones of a `SortedSet` doc values field.
+///
+/// The values are facet paths such as `topics/science/physics`, the count of
+/// a path is the number of hits with that value. For the counts of every
+/// level to be exact, a doc should have the ancestors of its paths as
+/// values as well, see `facet_path_values`.
+pub struct SortedSetDocValuesFacetCounts<'a> {
+    state: &'a SortedSetDocValuesReaderState,
+    counts: Vec<i64>,
+}
+
+impl<'a> SortedSetDocValuesFacetCounts<'a> {
+    /// Counts the hits of `hits`, which must be collected on `reader`, the
+    /// reader `state` is built for.
+    pub fn new<C: Codec>(
+        state: &'a SortedSetDocValuesReaderState,
+        reader: &dyn IndexReader<Codec = C>,
+        hits: &FacetsCollector,
+    ) -> Result<Self> {
+        let leaves = reader.leaves();
+        if leaves.len() != state.leaf_count {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "the facet state of field '{}' is built for another reader",
+                state.field
+            )));
+        }
+
+        let mut counts = vec![0i64; state.value_count];
+        for matching_docs in hits.matching_docs() {
+            if matching_docs.docs.is_empty() {
+                continue;
+            }
+            let mut dv = leaf_sorted_set_values(&leaves[matching_docs.ord], &state.field)?;
+            match state.global_ords(matching_docs.ord) {
+                Some(global_ords) => {
+                    let segment_value_count = dv.get_value_count();
+                    if matching_docs.docs.len() < segment_value_count / 10 {
+                        // few hits, map every ord to the global ord
+                        for &doc in &matching_docs.docs {
+                            dv.set_document(doc)?;
+                            loop {
+                                let ord = dv.next_ord()?;
+                                if ord == NO_MORE_ORDS {
+                                    break;
+                                }
+                                counts[global_ords.get64(ord)? as usize] += 1;
+                            }
+                        }
+                    } else {
+                        // count the segment ords first, then map the distinct ords
+                        let mut segment_counts = vec![0i64; segment_value_count];
+                        count_segment_ords(dv.as_mut(), &matching_docs.docs, &mut segment_counts)?;
+                        for (ord, &count) in segment_counts.iter().enumerate() {
+                            if count > 0 {
+                                counts[global_ords.get64(ord as i64)? as usize] += count;
+                            }
+                        }
+                    }
+                }
+                None => count_segment_ords(dv.as_mut(), &matching_docs.docs, &mut counts)?,
+            }
+        }
+        Ok(SortedSetDocValuesFacetCounts { state, counts })
+    }
+
+    fn count(&self, path: &str) -> Option<i64> {
+        self.state.ords.get(path).map(|&ord| self.counts[ord])
+    }
+}
+
+fn count_segment_ords(
+    dv: &mut dyn SortedSetDocValues,
+    docs: &[DocId],
+    counts: &mut [i64],
+) -> Result<()> {
+    for &doc in docs {
+        dv.set_document(doc)?;
+        loop {
+            let ord = dv.next_ord()?;
+            if ord == NO_MORE_ORDS {
+                break;
+            }
+            counts[ord as usize] += 1;
+        }
+    }
+    Ok(())
+}
+
+impl<'a> Facets for SortedSetDocValuesFacetCounts<'a> {
+    fn get_top_children(&self, top_n: usize, path: &[&str]) -> Result<Option<FacetResult>> {
+        if top_n == 0 {
+            bail!(ErrorKind::IllegalArgument(
+                "top_n must be positive".to_string()
+            ));
+        }
+        let parent = join_facet_path(path)?;
+        let children = match self.state.children.get(&parent) {
+            Some(children) => children,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        // the children are in term order, the stable sort breaks count ties by label
+        let mut label_values: Vec<LabelAndValue> = children
+            .iter()
+            .filter(|(ord, _)| self.counts[*ord] > 0)
+            .map(|(ord, label)| LabelAndValue::new(label.clone(), self.counts[*ord]))
+            .collect();
+        if label_values.is_empty() {
+            return Ok(None);
+        }
+        let child_count = label_values.len();
+        label_values.sort_by(|a, b| b.value.cmp(&a.value));
+        label_values.truncate(top_n);
+
+        let value = self.count(&parent).unwrap_or(-1);
+        let path = path.iter().map(|c| c.to_string()).collect();
+        Ok(Some(FacetResult::new(
+            path,
+            value,
+            child_count,
+            label_values,
+        )))
+    }
+
+    fn get_specific_value(&self, path: &[&str]) -> Result<i64> {
+        let path = join_facet_path(path)?;
+        Ok(self.count(&path).unwrap_or(0))
+    }
+
+    fn get_all_dims(&self, top_n: usize) -> Result<Vec<FacetResult>> {
+        let mut results = Vec::new();
+        if let Some(dims) = self.state.children.get("") {
+            for (ord, dim) in dims {
+                if self.counts[*ord] == 0 {
+                    continue;
+                }
+                if let Some(result) = self.get_top_children(top_n, &[dim.as_str()])? {
+                    results.push(result);
+                }
+            }
+        }
+        results.sort_by(|a, b| b.value.cmp(&a.value));
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::collector::{Collector, SearchCollector};
+    use core::search::tests::*;
+
+    use core::doc::{Fieldable, SortedSetDocValuesField, Term};
+    use core::index::tests::*;
+    use core::index::writer::IndexWriterConfig;
+    use core::search::facet::facet_path_values;
+    use core::search::query::TermQuery;
+    use core::search::*;
+
+    // the topic of doc `i`, each segment of 10 docs has its own mix of topics
+    fn topic(i: usize) -> &'static str {
+        if i < 10 {
+            if i % 2 == 0 {
+                "a/b/c"
+            } else {
+                "a/e"
+            }
+        } else if i < 20 {
+            if i % 3 == 0 {
+                "a/b/d"
+            } else {
+                "a/b/c"
+            }
+        } else if i % 2 == 0 {
+            "f"
+        } else {
+            "a/b/d"
+        }
+    }
+
+    // 30 docs with the body "all", and "one" for the docs 3, 14 and 25, a
+    // facet topic with its ancestors and 5 distinct tags `tag/5i`..`tag/5i+4`
+    fn test_index(docs_per_segment: usize) -> TestIndex {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = (0..30)
+            .map(|i| {
+                let body = if i % 11 == 3 { "all one" } else { "all" };
+                let mut doc: Vec<Box<dyn Fieldable>> = vec![text_field("body", body)];
+                let mut values = facet_path_values(topic(i)).unwrap();
+                values.push("tag".to_string());
+                values.extend((5 * i..5 * i + 5).map(|t| format!("tag/{}", t)));
+                for v in &values {
+                    doc.push(Box::new(SortedSetDocValuesField::new(
+                        "facet",
+                        v.as_bytes(),
+                    )));
+                }
+                doc
+            })
+            .collect();
+        // no merge, every commit stays a segment
+        let mut config = IndexWriterConfig::default();
+        config.merge_policy.set_max_merged_segment_mb(0.0).unwrap();
+        TestIndex::with_config(docs, docs_per_segment, config)
+    }
+
+    fn facets_search(searcher: &TestIndexSearcher, term: &str, parallel: bool) -> FacetsCollector {
+        let query = TermQuery::new(
+            Term::new("body".into(), term.as_bytes().to_vec()),
+            1.0,
+            None,
+        );
+        let mut collector = FacetsCollector::default();
+        if parallel {
+            searcher.search_parallel(&query, &mut collector).unwrap();
+        } else {
+            searcher.search(&query, &mut collector).unwrap();
+        }
+        collector
+    }
+
+    fn labels(result: &FacetResult) -> Vec<(&str, i64)> {
+        result
+            .label_values
+            .iter()
+            .map(|lv| (lv.label.as_str(), lv.value))
+            .collect()
+    }
+
+    // the counts of the hits of "all"
+    fn check_all_counts(counts: &SortedSetDocValuesFacetCounts) {
+        assert_eq!(counts.get_specific_value(&["a"]).unwrap(), 25);
+        assert_eq!(counts.get_specific_value(&["a", "b"]).unwrap(), 20);
+        assert_eq!(counts.get_specific_value(&["a", "b", "c"]).unwrap(), 12);
+        assert_eq!(counts.get_specific_value(&["a", "b", "d"]).unwrap(), 8);
+        assert_eq!(counts.get_specific_value(&["a", "e"]).unwrap(), 5);
+        assert_eq!(counts.get_specific_value(&["f"]).unwrap(), 5);
+        assert_eq!(counts.get_specific_value(&["tag"]).unwrap(), 30);
+
+        let a = counts.get_top_children(10, &["a"]).unwrap().unwrap();
+        assert_eq!(a.path, vec!["a"]);
+        assert_eq!(a.value, 25);
+        assert_eq!(a.child_count, 2);
+        assert_eq!(labels(&a), vec![("b", 20), ("e", 5)]);
+
+        let ab = counts.get_top_children(1, &["a", "b"]).unwrap().unwrap();
+        assert_eq!(ab.value, 20);
+        assert_eq!(ab.child_count, 2);
+        assert_eq!(labels(&ab), vec![("c", 12)]);
+        assert!(counts
+            .get_top_children(10, &["a", "b", "c"])
+            .unwrap()
+            .is_none());
+
+        // "f" is a leaf, it is not a dim with children; the tags all count 1,
+        // the ties are in term order
+        let dims = counts.get_all_dims(3).unwrap();
+        assert_eq!(dims.len(), 2);
+        assert_eq!(dims[0].dim(), "tag");
+        assert_eq!(dims[0].value, 30);
+        assert_eq!(dims[0].child_count, 150);
+        assert_eq!(labels(&dims[0]), vec![("0", 1), ("1", 1), ("10", 1)]);
+        assert_eq!(dims[1].dim(), "a");
+        assert_eq!(labels(&dims[1]), vec![("b", 20), ("e", 5)]);
+    }
+
+    // the counts of the hits of "one", the docs 3, 14 and 25
+    fn check_one_counts(counts: &SortedSetDocValuesFacetCounts) {
+        assert_eq!(counts.get_specific_value(&["a"]).unwrap(), 3);
+        assert_eq!(counts.get_specific_value(&["f"]).unwrap(), 0);
+        assert!(counts.get_top_children(10, &["f"]).unwrap().is_none());
+
+        let a = counts.get_top_children(10, &["a"]).unwrap().unwrap();
+        assert_eq!(labels(&a), vec![("b", 2), ("e", 1)]);
+
+        // "c" and "d" tie, "c" comes first in term order
+        let ab = counts.get_top_children(1, &["a", "b"]).unwrap().unwrap();
+        assert_eq!(ab.value, 2);
+        assert_eq!(ab.child_count, 2);
+        assert_eq!(labels(&ab), vec![("c", 1)]);
+
+        // the term order of the tags of doc 25 and 3 puts "tag/125" first
+        let tags = counts.get_top_children(3, &["tag"]).unwrap().unwrap();
+        assert_eq!(tags.value, 3);
+        assert_eq!(tags.child_count, 15);
+        assert_eq!(labels(&tags), vec![("125", 1), ("126", 1), ("127", 1)]);
+    }
+
+    #[test]
+    fn test_counts_multi_segment() {
+        let index = test_index(10);
+        let reader = index.reader.as_ref();
+        let leaves = reader.leaves();
+        assert_eq!(leaves.len(), 3);
+
+        // 150 tags, "tag", "a", "a/b", "a/b/c", "a/b/d", "a/e" and "f"
+        let state = SortedSetDocValuesReaderState::new(reader, "facet").unwrap();
+        assert_eq!(state.value_count(), 157);
+        assert!(state.ordinal_map().is_some());
+
+        // the segment ords map to the global ords of the same values
+        for leaf in &leaves {
+            let dv = leaf_sorted_set_values(leaf, "facet").unwrap();
+            let global_ords = state.global_ords(leaf.ord).unwrap();
+            for ord in 0..dv.get_value_count() as i64 {
+                let value = String::from_utf8(dv.lookup_ord(ord).unwrap()).unwrap();
+                let global_ord = global_ords.get64(ord).unwrap() as usize;
+                assert_eq!(state.ords[&value], global_ord);
+            }
+        }
+
+        let searcher = index.searcher();
+
+        // 10 hits per segment, the segment ords are counted first
+        let hits = facets_search(&searcher, "all", false);
+        assert_eq!(hits.total_hits(), 30);
+        for matching_docs in hits.matching_docs() {
+            let dv = leaf_sorted_set_values(&leaves[matching_docs.ord], "facet").unwrap();
+            assert!(matching_docs.docs.len() >= dv.get_value_count() / 10);
+        }
+        let counts = SortedSetDocValuesFacetCounts::new(&state, reader, &hits).unwrap();
+        check_all_counts(&counts);
+
+        // a hit per segment, every ord is mapped to its global ord
+        let hits = facets_search(&searcher, "one", false);
+        assert_eq!(hits.total_hits(), 3);
+        for matching_docs in hits.matching_docs() {
+            let dv = leaf_sorted_set_values(&leaves[matching_docs.ord], "facet").unwrap();
+            assert!(matching_docs.docs.len() < dv.get_value_count() / 10);
+        }
+        let counts = SortedSetDocValuesFacetCounts::new(&state, reader, &hits).unwrap();
+        check_one_counts(&counts);
+
+        // the state of another reader is rejected
+        let single = test_index(30);
+        let single_state =
+            SortedSetDocValuesReaderState::new(single.reader.as_ref(), "facet").unwrap();
+        assert!(single_state.ordinal_map().is_none());
+        assert!(SortedSetDocValuesFacetCounts::new(&single_state, reader, &hits).is_err());
+    }
+
+    #[test]
+    fn test_counts_single_segment() {
+        let index = test_index(30);
+        let reader = index.reader.as_ref();
+        assert_eq!(reader.leaves().len(), 1);
+        let state = SortedSetDocValuesReaderState::new(reader, "facet").unwrap();
+        assert_eq!(state.value_count(), 157);
+        assert!(state.ordinal_map().is_none());
+
+        let searcher = index.searcher();
+        let hits = facets_search(&searcher, "all", false);
+        let counts = SortedSetDocValuesFacetCounts::new(&state, reader, &hits).unwrap();
+        check_all_counts(&counts);
+        let hits = facets_search(&searcher, "one", false);
+        let counts = SortedSetDocValuesFacetCounts::new(&state, reader, &hits).unwrap();
+        check_one_counts(&counts);
+    }
+
+    #[test]
+    fn test_counts_parallel() {
+        // a segment per doc, the 30 segments are searched in 2 slices
+        let index = test_index(1);
+        let reader = index.reader.as_ref();
+        assert_eq!(reader.leaves().len(), 30);
+        let state = SortedSetDocValuesReaderState::new(reader, "facet").unwrap();
+        assert_eq!(state.value_count(), 157);
+
+        let mut searcher = index.searcher();
+        searcher.with_thread_pool(2);
+        for &parallel in &[false, true] {
+            let hits = facets_search(&searcher, "all", parallel);
+            assert_eq!(hits.total_hits(), 30);
+            let ords: Vec<usize> = hits.matching_docs().iter().map(|m| m.ord).collect();
+            assert_eq!(ords, (0..30).collect::<Vec<_>>());
+            let counts = SortedSetDocValuesFacetCounts::new(&state, reader, &hits).unwrap();
+            check_all_counts(&counts);
+
+            let hits = facets_search(&searcher, "one", parallel);
+            assert_eq!(hits.total_hits(), 3);
+            let counts = SortedSetDocValuesFacetCounts::new(&state, reader, &hits).unwrap();
+            check_one_counts(&counts);
+        }
+    }
+
+    #[test]
+    fn test_counts_without_values() {
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        // the mock field is a numeric field, it has no sorted set values
+        let state = SortedSetDocValuesReaderState::new(&index_reader, "test").unwrap();
+        assert_eq!(state.value_count(), 0);
+        assert!(state.ordinal_map().is_none());
+
+        let mut collector = FacetsCollector::default();
+        collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+        let mut scorer = create_mock_scorer(vec![1, 2]);
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc != NO_MORE_DOCS {
+                collector.collect(doc, &mut scorer).unwrap();
+            } else {
+                break;
+            }
+        }
+
+        let counts = SortedSetDocValuesFacetCounts::new(&state, &index_reader, &collector).unwrap();
+        assert!(counts.get_top_children(10, &["topics"]).unwrap().is_none());
+        assert!(counts.get_top_children(0, &["topics"]).is_err());
+        assert_eq!(counts.get_specific_value(&["topics", "rust"]).unwrap(), 0);
+        assert!(counts.get_all_dims(10).unwrap().is_empty());
+    }
+}
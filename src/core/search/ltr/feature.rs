@@ -0,0 +1,327 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{Codec, CodecPostingIterator, CodecTermState};
+use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::{NumericFieldSource, NumericFieldValues, Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::Result;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// The statistic of the terms of a `LtrFeature::term_stat` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermStatType {
+    /// The number of docs containing the term.
+    DocFreq,
+    /// The number of occurrences of the term in all docs.
+    TotalTermFreq,
+    /// The number of occurrences of the term in the doc.
+    TermFreq,
+}
+
+/// How the statistics of several terms are reduced to a feature value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatAggregation {
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl StatAggregation {
+    /// Aggregates the values, 0 if there are none.
+    pub fn aggregate(self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        match self {
+            StatAggregation::Sum => values.iter().sum(),
+            StatAggregation::Min => values.iter().cloned().fold(values[0], f64::min),
+            StatAggregation::Max => values.iter().cloned().fold(values[0], f64::max),
+            StatAggregation::Avg => values.iter().sum::<f64>() / values.len() as f64,
+        }
+    }
+}
+
+enum FeatureKind<C: Codec> {
+    Query(Box<dyn Query<C>>),
+    Field {
+        source: NumericFieldSource,
+        missing: f64,
+    },
+    TermStat {
+        terms: Vec<Term>,
+        stat: TermStatType,
+        aggregation: StatAggregation,
+    },
+}
+
+/// A named feature of a learning to rank feature set, extracted for every
+/// rescored doc.
+pub struct LtrFeature<C: Codec> {
+    name: String,
+    kind: FeatureKind<C>,
+}
+
+impl<C: Codec> LtrFeature<C> {
+    /// The score of `query`, 0 for docs it does not match.
+    pub fn query(name: &str, query: Box<dyn Query<C>>) -> Self {
+        Self::new(name, FeatureKind::Query(query))
+    }
+
+    /// The value of a numeric doc values field, `missing` for docs without
+    /// a value. Use `NaN` as the missing value for tree models with a
+    /// default direction for missing values.
+    pub fn field(name: &str, source: NumericFieldSource, missing: f64) -> Self {
+        Self::new(name, FeatureKind::Field { source, missing })
+    }
+
+    /// A statistic of `terms`, reduced to a single value by `aggregation`.
+    pub fn term_stat(
+        name: &str,
+        terms: Vec<Term>,
+        stat: TermStatType,
+        aggregation: StatAggregation,
+    ) -> Self {
+        Self::new(
+            name,
+            FeatureKind::TermStat {
+                terms,
+                stat,
+                aggregation,
+            },
+        )
+    }
+
+    fn new(name: &str, kind: FeatureKind<C>) -> Self {
+        LtrFeature {
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn extract_terms(&self) -> Vec<TermQuery> {
+        match &self.kind {
+            FeatureKind::Query(q) => q.extract_terms(),
+            _ => vec![],
+        }
+    }
+
+    pub(crate) fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+    ) -> Result<FeatureWeight<C>> {
+        let weight = match &self.kind {
+            FeatureKind::Query(q) => {
+                FeatureWeight::Query(searcher.create_normalized_weight(q.as_ref(), true)?)
+            }
+            FeatureKind::Field { source, missing } => FeatureWeight::Field {
+                source: source.clone(),
+                missing: *missing,
+            },
+            FeatureKind::TermStat {
+                terms,
+                stat,
+                aggregation,
+            } => {
+                let mut contexts = Vec::with_capacity(terms.len());
+                for term in terms {
+                    contexts.push((term.clone(), searcher.term_state(term)?));
+                }
+                match stat {
+                    TermStatType::TermFreq => FeatureWeight::TermFreq {
+                        terms: contexts
+                            .into_iter()
+                            .map(|(term, ctx)| (term, ctx.term_states()))
+                            .collect(),
+                        aggregation: *aggregation,
+                    },
+                    TermStatType::DocFreq | TermStatType::TotalTermFreq => {
+                        let values: Vec<f64> = contexts
+                            .iter()
+                            .map(|(_, ctx)| {
+                                if *stat == TermStatType::DocFreq {
+                                    f64::from(ctx.doc_freq)
+                                } else {
+                                    ctx.total_term_freq.max(0) as f64
+                                }
+                            })
+                            .collect();
+                        FeatureWeight::Constant(aggregation.aggregate(&values))
+                    }
+                }
+            }
+        };
+        Ok(weight)
+    }
+}
+
+impl<C: Codec> fmt::Display for LtrFeature<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            FeatureKind::Query(q) => write!(f, "{}: {}", self.name, q),
+            FeatureKind::Field { source, .. } => {
+                write!(f, "{}: field({})", self.name, source.field())
+            }
+            FeatureKind::TermStat {
+                terms,
+                stat,
+                aggregation,
+            } => write!(
+                f,
+                "{}: {:?}({:?} of {} terms)",
+                self.name,
+                aggregation,
+                stat,
+                terms.len()
+            ),
+        }
+    }
+}
+
+/// Returns the names of the features, in the order a model bound to the
+/// feature set expects them.
+pub fn feature_names<C: Codec>(features: &[LtrFeature<C>]) -> Vec<String> {
+    features.iter().map(|f| f.name.clone()).collect()
+}
+
+/// The state of a feature for a search.
+pub(crate) enum FeatureWeight<C: Codec> {
+    Query(Box<dyn Weight<C>>),
+    Field {
+        source: NumericFieldSource,
+        missing: f64,
+    },
+    TermFreq {
+        terms: Vec<(Term, HashMap<DocId, CodecTermState<C>>)>,
+        aggregation: StatAggregation,
+    },
+    // a feature with the same value for all docs, e.g. term statistics
+    Constant(f64),
+}
+
+impl<C: Codec> FeatureWeight<C> {
+    pub(crate) fn leaf_feature(&self, ctx: &LeafReaderContext<'_, C>) -> Result<LeafFeature<C>> {
+        let feature = match self {
+            FeatureWeight::Query(weight) => LeafFeature::Query(weight.create_scorer(ctx)?),
+            FeatureWeight::Field { source, missing } => {
+                LeafFeature::Field(source.values(ctx)?, *missing)
+            }
+            FeatureWeight::TermFreq { terms, aggregation } => {
+                let mut postings = Vec::with_capacity(terms.len());
+                for (term, states) in terms {
+                    postings.push(match states.get(&ctx.doc_base) {
+                        Some(state) => ctx.reader.postings_from_state(
+                            term,
+                            state,
+                            i32::from(PostingIteratorFlags::FREQS),
+                        )?,
+                        None => None,
+                    });
+                }
+                LeafFeature::TermFreq {
+                    postings,
+                    freqs: Vec::with_capacity(terms.len()),
+                    aggregation: *aggregation,
+                }
+            }
+            FeatureWeight::Constant(value) => LeafFeature::Constant(*value),
+        };
+        Ok(feature)
+    }
+}
+
+/// The values of a feature in a segment, the docs must be visited in
+/// increasing order.
+pub(crate) enum LeafFeature<C: Codec> {
+    Query(Option<Box<dyn Scorer>>),
+    Field(NumericFieldValues, f64),
+    TermFreq {
+        postings: Vec<Option<CodecPostingIterator<C>>>,
+        freqs: Vec<f64>,
+        aggregation: StatAggregation,
+    },
+    Constant(f64),
+}
+
+impl<C: Codec> LeafFeature<C> {
+    pub(crate) fn value(&mut self, doc: DocId) -> Result<f64> {
+        let value = match self {
+            LeafFeature::Query(Some(scorer)) => {
+                if scorer.doc_id() < doc {
+                    scorer.advance(doc)?;
+                }
+                if scorer.doc_id() == doc {
+                    f64::from(scorer.score()?)
+                } else {
+                    0.0
+                }
+            }
+            LeafFeature::Query(None) => 0.0,
+            LeafFeature::Field(values, missing) => values.get(doc)?.unwrap_or(*missing),
+            LeafFeature::TermFreq {
+                postings,
+                freqs,
+                aggregation,
+            } => {
+                freqs.clear();
+                for p in postings.iter_mut() {
+                    let freq = match p {
+                        Some(p) => {
+                            if p.doc_id() < doc {
+                                p.advance(doc)?;
+                            }
+                            if p.doc_id() == doc {
+                                f64::from(p.freq()?)
+                            } else {
+                                0.0
+                            }
+                        }
+                        None => 0.0,
+                    };
+                    freqs.push(freq);
+                }
+                aggregation.aggregate(freqs)
+            }
+            LeafFeature::Constant(value) => *value,
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let values = [2.0, 6.0, 1.0];
+        assert!((StatAggregation::Sum.aggregate(&values) - 9.0).abs() < 1e-9);
+        assert!((StatAggregation::Min.aggregate(&values) - 1.0).abs() < 1e-9);
+        assert!((StatAggregation::Max.aggregate(&values) - 6.0).abs() < 1e-9);
+        assert!((StatAggregation::Avg.aggregate(&values) - 3.0).abs() < 1e-9);
+        assert!(StatAggregation::Max.aggregate(&[]).abs() < 1e-9);
+    }
+}
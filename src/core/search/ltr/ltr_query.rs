@@ -0,0 +1,341 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::ltr::{FeatureWeight, LeafFeature, LtrFeature, LtrModel};
+use core::search::query::{AllDocsIterator, Query, TermQuery, Weight};
+use core::search::scorer::{BatchScorer, FeatureResult, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::{DocId, IndexedContext, VariantValue};
+
+use error::{ErrorKind, Result};
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const LTR_QUERY: &str = "ltr";
+
+/// A query that matches all docs and scores them with a learning to rank
+/// model over a feature set.
+///
+/// It is meant to rescore the top hits of another query, e.g. with a
+/// `LtrRescorer`, as extracting the features of every doc is expensive.
+/// Its scorers expose the feature values with `score_context`, indexed by
+/// feature, and `score_feature`, by name.
+pub struct LtrQuery<C: Codec> {
+    features: Vec<LtrFeature<C>>,
+    model: Arc<LtrModel>,
+}
+
+impl<C: Codec> LtrQuery<C> {
+    /// Creates the query, `model` must be bound to the names of `features`,
+    /// see `feature_names`.
+    pub fn new(features: Vec<LtrFeature<C>>, model: Arc<LtrModel>) -> Result<Self> {
+        if model.num_features() != features.len() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "model expects {} features, got {}",
+                model.num_features(),
+                features.len()
+            )));
+        }
+        for (i, f) in features.iter().enumerate() {
+            if features[..i].iter().any(|o| o.name() == f.name()) {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "duplicate feature '{}'",
+                    f.name()
+                )));
+            }
+        }
+        Ok(LtrQuery { features, model })
+    }
+
+    pub fn features(&self) -> &[LtrFeature<C>] {
+        &self.features
+    }
+
+    pub fn model(&self) -> &LtrModel {
+        &self.model
+    }
+}
+
+impl<C: Codec> Query<C> for LtrQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let mut features = Vec::with_capacity(self.features.len());
+        for f in &self.features {
+            features.push(f.create_weight(searcher)?);
+        }
+        Ok(Box::new(LtrWeight {
+            features,
+            names: Arc::new(self.features.iter().map(|f| f.name().to_string()).collect()),
+            model: Arc::clone(&self.model),
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.features
+            .iter()
+            .flat_map(|f| f.extract_terms())
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for LtrQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let features: Vec<String> = self.features.iter().map(|f| f.to_string()).collect();
+        write!(
+            f,
+            "LtrQuery(features: [{}], model: {})",
+            features.join(", "),
+            self.model
+        )
+    }
+}
+
+struct LtrWeight<C: Codec> {
+    features: Vec<FeatureWeight<C>>,
+    names: Arc<Vec<String>>,
+    model: Arc<LtrModel>,
+}
+
+impl<C: Codec> LtrWeight<C> {
+    fn ltr_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<LtrScorer<C>> {
+        let mut features = Vec::with_capacity(self.features.len());
+        for f in &self.features {
+            features.push(f.leaf_feature(ctx)?);
+        }
+        Ok(LtrScorer {
+            iterator: AllDocsIterator::new(ctx.reader.max_doc()),
+            values: vec![0.0; features.len()],
+            features,
+            names: Arc::clone(&self.names),
+            model: Arc::clone(&self.model),
+        })
+    }
+}
+
+impl<C: Codec> Weight<C> for LtrWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        Ok(Some(Box::new(self.ltr_scorer(ctx)?)))
+    }
+
+    fn query_type(&self) -> &'static str {
+        LTR_QUERY
+    }
+
+    // the features are normalized on their own, and the model score is
+    // not affected by the normalization of the enclosing query
+    fn normalize(&mut self, _norm: f32, _boost: f32) {}
+
+    fn value_for_normalization(&self) -> f32 {
+        1.0
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn create_batch_scorer(&self) -> Option<Box<dyn BatchScorer>> {
+        Some(Box::new(LtrBatchScorer {
+            model: Arc::clone(&self.model),
+        }))
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.ltr_scorer(reader)?;
+        scorer.extract(doc)?;
+        let score = self.model.score(&scorer.values) as f32;
+        let details = self
+            .names
+            .iter()
+            .zip(scorer.values.iter())
+            .map(|(name, v)| Explanation::new(true, *v as f32, name.clone(), vec![]))
+            .collect();
+        Ok(Explanation::new(
+            true,
+            score,
+            format!("ltr model {}, computed from features:", self.model),
+            details,
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for LtrWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LtrWeight(features: [{}], model: {})",
+            self.names.join(", "),
+            self.model
+        )
+    }
+}
+
+struct LtrBatchScorer {
+    model: Arc<LtrModel>,
+}
+
+impl BatchScorer for LtrBatchScorer {
+    fn scores(&self, score_context: Vec<&IndexedContext>) -> Result<Vec<f32>> {
+        self.model.scores(score_context)
+    }
+}
+
+struct LtrScorer<C: Codec> {
+    iterator: AllDocsIterator,
+    features: Vec<LeafFeature<C>>,
+    values: Vec<f64>,
+    names: Arc<Vec<String>>,
+    model: Arc<LtrModel>,
+}
+
+impl<C: Codec> LtrScorer<C> {
+    fn extract(&mut self, doc: DocId) -> Result<()> {
+        for (f, v) in self.features.iter_mut().zip(self.values.iter_mut()) {
+            *v = f.value(doc)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: Codec> Scorer for LtrScorer<C> {
+    fn score(&mut self) -> Result<f32> {
+        let doc = self.iterator.doc_id();
+        self.extract(doc)?;
+        Ok(self.model.score(&self.values) as f32)
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        let doc = self.iterator.doc_id();
+        self.extract(doc)?;
+        let mut ctx = IndexedContext::default();
+        for (i, v) in self.values.iter().enumerate() {
+            ctx.set_double(i, *v);
+        }
+        Ok(ctx)
+    }
+
+    fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
+        let doc = self.iterator.doc_id();
+        self.extract(doc)?;
+        let params: HashMap<String, VariantValue> = self
+            .names
+            .iter()
+            .zip(self.values.iter())
+            .map(|(name, v)| (name.clone(), VariantValue::Double(*v)))
+            .collect();
+        Ok(vec![FeatureResult::new(params)])
+    }
+}
+
+impl<C: Codec> DocIterator for LtrScorer<C> {
+    fn doc_id(&self) -> DocId {
+        self.iterator.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.iterator.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.iterator.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.iterator.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::Term;
+    use core::search::ltr::tests::*;
+    use core::search::ltr::{LinearModel, StatAggregation, TermStatType};
+    use core::search::searcher::SearchPlanBuilder;
+
+    #[test]
+    fn test_ltr_query_features() {
+        let index = ltr_test_index();
+        let searcher = index.searcher();
+        let query = ltr_test_query();
+        let weight = query.create_weight(&searcher, true).unwrap();
+
+        let mut features = vec![];
+        let mut scores = vec![];
+        for leaf in searcher.leaves() {
+            let mut scorer = weight.create_scorer(&leaf).unwrap().unwrap();
+            for doc in 0..leaf.reader.max_doc() {
+                assert_eq!(scorer.advance(doc).unwrap(), doc);
+                let mut params = scorer.score_feature().unwrap().remove(0).extra_params;
+                let mut value = |name: &str| params.remove(name).unwrap().get_double().unwrap();
+                features.push((
+                    value("popularity"),
+                    value("tf_b"),
+                    value("df_b"),
+                    value("has_c") > 0.0,
+                ));
+                scores.push(scorer.score().unwrap());
+            }
+        }
+
+        assert_eq!(
+            features,
+            vec![
+                (5.0, 0.0, 4.0, false),
+                (1.0, 1.0, 4.0, false),
+                (3.0, 2.0, 4.0, false),
+                (10.0, 0.0, 4.0, true),
+                (-1.0, 1.0, 4.0, true),
+                (2.0, 3.0, 4.0, false),
+            ]
+        );
+        assert_eq!(scores, vec![7.0, 5.0, 9.0, 12.0, 3.0, 10.0]);
+    }
+
+    #[test]
+    fn test_ltr_query_validation() {
+        let feature = |name: &str| -> LtrFeature<CodecEnum> {
+            let term = Term::new("body".into(), b"b".to_vec());
+            LtrFeature::term_stat(
+                name,
+                vec![term],
+                TermStatType::DocFreq,
+                StatAggregation::Sum,
+            )
+        };
+        let model = |num_features: usize| {
+            Arc::new(LtrModel::Linear(LinearModel::new(
+                vec![1.0; num_features],
+                0.0,
+            )))
+        };
+        assert!(LtrQuery::new(vec![feature("x"), feature("y")], model(2)).is_ok());
+        // the model must be bound to the feature set
+        assert!(LtrQuery::new(vec![feature("x")], model(2)).is_err());
+        assert!(LtrQuery::new(vec![feature("x"), feature("x")], model(2)).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::IndexReader;
+use core::search::explanation::Explanation;
+use core::search::query::Weight;
+use core::search::scorer::{
+    merge_rescored_hits, score_sort_field_index, RescoreRequest, Rescorer, Scorer,
+};
+use core::search::searcher::IndexSearcher;
+use core::search::sort_field::{ScoreDocHit, TopDocs};
+use core::util::{DocId, VariantValue};
+
+use error::Result;
+
+use std::collections::HashMap;
+
+/// A `Rescorer` for learning to rank, the scores of the query of the
+/// `RescoreRequest`, usually a `LtrQuery`, replace the first pass scores of
+/// the hits in the rescore window.
+///
+/// The features of the hits are scored in one batch if the weight of the
+/// query provides a `BatchScorer`. Hits the query does not match keep their
+/// first pass score, the weights and the mode of the request are not used.
+#[derive(Default)]
+pub struct LtrRescorer;
+
+impl LtrRescorer {
+    // calls `f` with the index of every hit matched by `weight` and the scorer
+    // positioned on it, the hits must be sorted by doc id
+    fn for_each_match<C, IS, F>(
+        &self,
+        searcher: &IS,
+        weight: &dyn Weight<C>,
+        hits: &[ScoreDocHit],
+        mut f: F,
+    ) -> Result<()>
+    where
+        C: Codec,
+        IS: IndexSearcher<C>,
+        F: FnMut(usize, &mut dyn Scorer) -> Result<()>,
+    {
+        let readers = searcher.reader().leaves();
+        let mut reader_idx = 0;
+        let mut scorer = None;
+        for (i, hit) in hits.iter().enumerate() {
+            let doc_id = hit.doc_id();
+            while reader_idx < readers.len()
+                && doc_id >= readers[reader_idx].doc_base + readers[reader_idx].reader.max_doc()
+            {
+                reader_idx += 1;
+                scorer = None;
+            }
+            debug_assert!(reader_idx < readers.len());
+            let reader = &readers[reader_idx];
+            if scorer.is_none() {
+                scorer = Some(weight.create_scorer(reader)?);
+            }
+            if let Some(Some(ref mut scorer)) = scorer {
+                let target_doc = doc_id - reader.doc_base;
+                let mut actual_doc = scorer.doc_id();
+                if actual_doc < target_doc {
+                    actual_doc = scorer.advance(target_doc)?;
+                }
+                if actual_doc == target_doc {
+                    f(i, scorer.as_mut())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn window_hits<C: Codec>(top_docs: &TopDocs, req: &RescoreRequest<C>) -> Vec<ScoreDocHit> {
+        let mut hits = top_docs.score_docs().to_vec();
+        hits.truncate(req.window_size);
+        hits.sort_by(ScoreDocHit::order_by_doc);
+        hits
+    }
+}
+
+impl Rescorer for LtrRescorer {
+    fn rescore<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<()> {
+        if top_docs.total_hits() == 0 || top_docs.score_docs().is_empty() {
+            return Ok(());
+        }
+
+        let mut hits = Self::window_hits(top_docs, req);
+        let weight = req.query().create_weight(searcher, true)?;
+        let mut scores = Vec::with_capacity(hits.len());
+        if let Some(batch_scorer) = weight.create_batch_scorer() {
+            let mut matched = Vec::with_capacity(hits.len());
+            let mut contexts = Vec::with_capacity(hits.len());
+            self.for_each_match(searcher, weight.as_ref(), &hits, |i, scorer| {
+                matched.push(i);
+                contexts.push(scorer.score_context()?);
+                Ok(())
+            })?;
+            let batch_scores = batch_scorer.scores(contexts.iter().collect())?;
+            scores.extend(matched.into_iter().zip(batch_scores));
+        } else {
+            self.for_each_match(searcher, weight.as_ref(), &hits, |i, scorer| {
+                scores.push((i, scorer.score()?));
+                Ok(())
+            })?;
+        }
+        for (i, score) in scores {
+            hits[i].set_score(score);
+        }
+
+        let score_field_index = score_sort_field_index(top_docs);
+        if score_field_index >= 0 {
+            for hit in &mut hits {
+                if let ScoreDocHit::Field(ref mut f) = hit {
+                    f.fields[score_field_index as usize] = VariantValue::from(f.score);
+                }
+            }
+        }
+
+        hits.sort();
+        merge_rescored_hits(top_docs, hits, 1.0);
+        Ok(())
+    }
+
+    fn rescore_features<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<Vec<HashMap<String, VariantValue>>> {
+        if top_docs.total_hits() == 0 || top_docs.score_docs().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let hits = Self::window_hits(top_docs, req);
+        let weight = req.query().create_weight(searcher, true)?;
+        let mut result_features: Vec<HashMap<String, VariantValue>> = hits
+            .iter()
+            .map(|hit| {
+                let mut feature_map = HashMap::new();
+                feature_map.insert(
+                    "previous_score".to_string(),
+                    VariantValue::from(hit.score()),
+                );
+                feature_map
+            })
+            .collect();
+        self.for_each_match(searcher, weight.as_ref(), &hits, |i, scorer| {
+            for f in scorer.score_feature()? {
+                result_features[i].extend(f.extra_params);
+            }
+            Ok(())
+        })?;
+        *top_docs.score_docs_mut() = hits;
+        Ok(result_features)
+    }
+
+    fn explain<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        first: Explanation,
+        doc: DocId,
+    ) -> Result<Explanation> {
+        let rescore = searcher.explain(req.query(), doc)?;
+        if !rescore.is_match() {
+            return Ok(first);
+        }
+        Ok(Explanation::new(
+            true,
+            rescore.value(),
+            "ltr rescore, replaced first pass score with:".to_string(),
+            vec![rescore, first],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::ltr::tests::*;
+    use core::search::scorer::RescoreMode;
+    use core::search::sort_field::{ScoreDoc, TopScoreDocs};
+
+    // the doc `i` has the first pass score `6 - i`
+    fn first_pass_top_docs() -> TopDocs {
+        let hits = (0..6)
+            .map(|i| ScoreDocHit::Score(ScoreDoc::new(i, (6 - i) as f32)))
+            .collect();
+        TopDocs::Score(TopScoreDocs::new(6, hits))
+    }
+
+    #[test]
+    fn test_ltr_rescore() {
+        let index = ltr_test_index();
+        let searcher = index.searcher();
+        let req = RescoreRequest::new(
+            Box::new(ltr_test_query()),
+            1.0,
+            1.0,
+            RescoreMode::Total,
+            4,
+            false,
+        );
+
+        let mut top_docs = first_pass_top_docs();
+        LtrRescorer.rescore(&searcher, &req, &mut top_docs).unwrap();
+        // the model scores replace the scores of the first 4 hits, the doc 5
+        // would be second but is out of the window
+        let hits: Vec<(DocId, f32)> = top_docs
+            .score_docs()
+            .iter()
+            .map(|h| (h.doc_id(), h.score()))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![(3, 12.0), (2, 9.0), (0, 7.0), (1, 5.0), (4, 2.0), (5, 1.0)]
+        );
+
+        let mut top_docs = first_pass_top_docs();
+        let features = LtrRescorer
+            .rescore_features(&searcher, &req, &mut top_docs)
+            .unwrap();
+        assert_eq!(features.len(), 4);
+        assert_eq!(features[2]["previous_score"].get_float(), Some(4.0));
+        assert_eq!(features[2]["popularity"].get_double(), Some(3.0));
+        assert_eq!(features[2]["tf_b"].get_double(), Some(2.0));
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Learning to rank: a feature set extracted for the top hits of a search
+//! and a model scoring them, usually applied with `LtrRescorer`.
+
+mod feature;
+
+pub use self::feature::*;
+
+mod model;
+
+pub use self::model::*;
+
+mod ltr_query;
+
+pub use self::ltr_query::*;
+
+mod ltr_rescorer;
+
+pub use self::ltr_rescorer::*;
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Fieldable, NumericDocValuesField, Term};
+    use core::index::tests::*;
+    use core::search::query::{ConstantScoreQuery, NumericFieldSource, Query, TermQuery};
+    use core::search::sort_field::SortFieldType;
+
+    use std::sync::Arc;
+
+    /// The texts and the popularity of the docs of `ltr_test_index`, the doc
+    /// 4 has no popularity.
+    pub const LTR_TEST_DOCS: [(&str, Option<i64>); 6] = [
+        ("a", Some(5)),
+        ("a b", Some(1)),
+        ("a b b", Some(3)),
+        ("a c", Some(10)),
+        ("a b c", None),
+        ("a b b b", Some(2)),
+    ];
+
+    pub fn ltr_test_index() -> TestIndex {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = LTR_TEST_DOCS
+            .iter()
+            .map(|(text, popularity)| {
+                let mut doc = vec![text_field("body", text)];
+                if let Some(popularity) = popularity {
+                    doc.push(Box::new(NumericDocValuesField::new(
+                        "popularity",
+                        *popularity,
+                    )));
+                }
+                doc
+            })
+            .collect();
+        TestIndex::new(docs, 2)
+    }
+
+    fn term(text: &str) -> Term {
+        Term::new("body".into(), text.as_bytes().to_vec())
+    }
+
+    /// A linear model of the popularity, missing as -1, the freq of "b", the
+    /// doc freq of "b" and whether the doc has a "c", which is not weighted:
+    /// the scores of the docs are 7, 5, 9, 12, 3 and 10.
+    pub fn ltr_test_query() -> LtrQuery<CodecEnum> {
+        let has_c: Box<dyn Query<CodecEnum>> = Box::new(ConstantScoreQuery::with_boost(
+            Box::new(TermQuery::new(term("c"), 1.0, None)),
+            1.0,
+        ));
+        let features = vec![
+            LtrFeature::field(
+                "popularity",
+                NumericFieldSource::new("popularity", SortFieldType::Long).unwrap(),
+                -1.0,
+            ),
+            LtrFeature::term_stat(
+                "tf_b",
+                vec![term("b")],
+                TermStatType::TermFreq,
+                StatAggregation::Sum,
+            ),
+            LtrFeature::term_stat(
+                "df_b",
+                vec![term("b")],
+                TermStatType::DocFreq,
+                StatAggregation::Sum,
+            ),
+            LtrFeature::query("has_c", has_c),
+        ];
+        let model = LinearModel::new(vec![1.0, 2.0, 0.5, 0.0], 0.0);
+        LtrQuery::new(features, Arc::new(LtrModel::Linear(model))).unwrap()
+    }
+}
@@ -0,0 +1,506 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::scorer::BatchScorer;
+use core::util::IndexedContext;
+
+use error::{ErrorKind, Result};
+
+use serde_json;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A learning to rank model, which computes the score of a doc from the
+/// values of a feature set.
+///
+/// The features are identified by their index in the feature set, the
+/// names of the features are only used to bind a model to a feature set
+/// when loading it.
+#[derive(Debug, Clone)]
+pub enum LtrModel {
+    Linear(LinearModel),
+    TreeEnsemble(TreeEnsembleModel),
+}
+
+impl LtrModel {
+    pub fn num_features(&self) -> usize {
+        match self {
+            LtrModel::Linear(m) => m.weights.len(),
+            LtrModel::TreeEnsemble(m) => m.num_features,
+        }
+    }
+
+    /// Scores a feature vector, missing features are `NaN` and handled by
+    /// the model: a linear model skips them, a tree follows the direction of
+    /// each split for missing values.
+    pub fn score(&self, features: &[f64]) -> f64 {
+        debug_assert_eq!(features.len(), self.num_features());
+        match self {
+            LtrModel::Linear(m) => m.score(features),
+            LtrModel::TreeEnsemble(m) => m.score(features),
+        }
+    }
+}
+
+impl BatchScorer for LtrModel {
+    /// Scores feature vectors stored as `f64` values in the contexts, indexed
+    /// by the feature index, the features without a value are `NaN`.
+    fn scores(&self, score_context: Vec<&IndexedContext>) -> Result<Vec<f32>> {
+        let mut features = vec![0.0; self.num_features()];
+        let mut scores = Vec::with_capacity(score_context.len());
+        for ctx in score_context {
+            for (i, v) in features.iter_mut().enumerate() {
+                *v = ctx.get_double(i).unwrap_or(::std::f64::NAN);
+            }
+            scores.push(self.score(&features) as f32);
+        }
+        Ok(scores)
+    }
+}
+
+impl From<LinearModel> for LtrModel {
+    fn from(m: LinearModel) -> Self {
+        LtrModel::Linear(m)
+    }
+}
+
+impl From<TreeEnsembleModel> for LtrModel {
+    fn from(m: TreeEnsembleModel) -> Self {
+        LtrModel::TreeEnsemble(m)
+    }
+}
+
+impl fmt::Display for LtrModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LtrModel::Linear(m) => write!(f, "linear(features: {})", m.weights.len()),
+            LtrModel::TreeEnsemble(m) => write!(
+                f,
+                "tree_ensemble(trees: {}, features: {})",
+                m.trees.len(),
+                m.num_features
+            ),
+        }
+    }
+}
+
+fn feature_index(feature_names: &[String], name: &str) -> Result<usize> {
+    match feature_names.iter().position(|f| f == name) {
+        Some(idx) => Ok(idx),
+        None => bail!(ErrorKind::IllegalArgument(format!(
+            "model uses unknown feature '{}'",
+            name
+        ))),
+    }
+}
+
+#[derive(Deserialize)]
+struct LinearModelDef {
+    #[serde(default)]
+    bias: f64,
+    weights: HashMap<String, f64>,
+}
+
+/// A linear model: `bias + sum(weight[i] * feature[i])`, the missing
+/// features, i.e. `NaN`, are skipped as if their value was zero.
+#[derive(Debug, Clone)]
+pub struct LinearModel {
+    weights: Vec<f64>,
+    bias: f64,
+}
+
+impl LinearModel {
+    pub fn new(weights: Vec<f64>, bias: f64) -> Self {
+        LinearModel { weights, bias }
+    }
+
+    /// Loads a model of the form `{"bias": 0.5, "weights": {"title": 1.2}}`,
+    /// the features without a weight are ignored.
+    pub fn from_json(json: &str, feature_names: &[String]) -> Result<Self> {
+        let def: LinearModelDef = serde_json::from_str(json)?;
+        let mut weights = vec![0.0; feature_names.len()];
+        for (name, weight) in def.weights {
+            weights[feature_index(feature_names, &name)?] = weight;
+        }
+        Ok(LinearModel::new(weights, def.bias))
+    }
+
+    pub fn score(&self, features: &[f64]) -> f64 {
+        self.weights
+            .iter()
+            .zip(features.iter())
+            .filter(|(_, v)| !v.is_nan())
+            .fold(self.bias, |acc, (w, v)| acc + w * v)
+    }
+}
+
+/// How a split of a tree handles missing feature values, i.e. `NaN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MissingType {
+    /// Missing values go to the default child.
+    Nan,
+    /// Missing values and zeros go to the default child.
+    Zero,
+    /// Missing values are compared as zeros.
+    None,
+}
+
+#[derive(Debug, Clone)]
+enum TreeNode {
+    Split {
+        feature: usize,
+        threshold: f64,
+        // whether values equal to the threshold go to the left child
+        inclusive: bool,
+        missing_type: MissingType,
+        default_left: bool,
+        left: usize,
+        right: usize,
+    },
+    Leaf(f64),
+}
+
+/// A regression tree stored as a flat list of nodes, the root first.
+#[derive(Debug, Clone)]
+struct Tree {
+    nodes: Vec<TreeNode>,
+}
+
+impl Tree {
+    fn score(&self, features: &[f64]) -> f64 {
+        let mut idx = 0;
+        loop {
+            match self.nodes[idx] {
+                TreeNode::Leaf(value) => return value,
+                TreeNode::Split {
+                    feature,
+                    threshold,
+                    inclusive,
+                    missing_type,
+                    default_left,
+                    left,
+                    right,
+                } => {
+                    let mut value = features[feature];
+                    let is_missing = match missing_type {
+                        MissingType::Nan => value.is_nan(),
+                        MissingType::Zero => value.is_nan() || value == 0.0,
+                        MissingType::None => {
+                            if value.is_nan() {
+                                value = 0.0;
+                            }
+                            false
+                        }
+                    };
+                    let go_left = if is_missing {
+                        default_left
+                    } else if inclusive {
+                        value <= threshold
+                    } else {
+                        value < threshold
+                    };
+                    idx = if go_left { left } else { right };
+                }
+            }
+        }
+    }
+}
+
+/// A gradient boosted tree ensemble: `base_score + sum(tree(features))`.
+///
+/// The models are loaded from the JSON dumps of XGBoost or LightGBM, the
+/// score is the raw margin of the model, without the transformation of the
+/// objective, which preserves the ranking.
+#[derive(Debug, Clone)]
+pub struct TreeEnsembleModel {
+    trees: Vec<Tree>,
+    base_score: f64,
+    num_features: usize,
+}
+
+impl TreeEnsembleModel {
+    /// Loads the trees of `Booster.get_dump(dump_format='json')` of XGBoost,
+    /// given as a JSON array. The splits refer to the features by name, or by
+    /// index as `f0`, `f1`, ... if the model was trained without names.
+    pub fn from_xgboost_json(json: &str, feature_names: &[String]) -> Result<Self> {
+        let trees: Vec<XgbNode> = serde_json::from_str(json)?;
+        let mut ensemble = TreeEnsembleModel {
+            trees: Vec::with_capacity(trees.len()),
+            base_score: 0.0,
+            num_features: feature_names.len(),
+        };
+        for root in &trees {
+            let mut nodes = vec![];
+            root.build(&mut nodes, feature_names)?;
+            ensemble.trees.push(Tree { nodes });
+        }
+        Ok(ensemble)
+    }
+
+    /// Loads the trees of `Booster.dump_model()` of LightGBM, the splits
+    /// refer to the `feature_names` of the dump, which are bound by name.
+    pub fn from_lightgbm_json(json: &str, feature_names: &[String]) -> Result<Self> {
+        let model: LgbModel = serde_json::from_str(json)?;
+        let mut features = Vec::with_capacity(model.feature_names.len());
+        for name in &model.feature_names {
+            features.push(feature_index(feature_names, name)?);
+        }
+        let mut ensemble = TreeEnsembleModel {
+            trees: Vec::with_capacity(model.tree_info.len()),
+            base_score: 0.0,
+            num_features: feature_names.len(),
+        };
+        for tree in &model.tree_info {
+            let mut nodes = vec![];
+            tree.tree_structure.build(&mut nodes, &features)?;
+            ensemble.trees.push(Tree { nodes });
+        }
+        Ok(ensemble)
+    }
+
+    /// Sets the initial score of the ensemble, which XGBoost does not dump.
+    pub fn with_base_score(mut self, base_score: f64) -> Self {
+        self.base_score = base_score;
+        self
+    }
+
+    pub fn num_trees(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn score(&self, features: &[f64]) -> f64 {
+        self.trees
+            .iter()
+            .fold(self.base_score, |acc, t| acc + t.score(features))
+    }
+}
+
+#[derive(Deserialize)]
+struct XgbNode {
+    nodeid: usize,
+    split: Option<String>,
+    split_condition: Option<f64>,
+    yes: Option<usize>,
+    no: Option<usize>,
+    missing: Option<usize>,
+    #[serde(default)]
+    children: Vec<XgbNode>,
+    leaf: Option<f64>,
+}
+
+impl XgbNode {
+    // appends the nodes of the subtree to `nodes` and returns its index
+    fn build(&self, nodes: &mut Vec<TreeNode>, feature_names: &[String]) -> Result<usize> {
+        let idx = nodes.len();
+        if let Some(leaf) = self.leaf {
+            nodes.push(TreeNode::Leaf(leaf));
+            return Ok(idx);
+        }
+        let (split, threshold, yes, no) =
+            match (&self.split, self.split_condition, self.yes, self.no) {
+                (Some(split), Some(threshold), Some(yes), Some(no)) => (split, threshold, yes, no),
+                _ => bail!(ErrorKind::IllegalArgument(format!(
+                    "invalid xgboost node {}: neither a leaf nor a split",
+                    self.nodeid
+                ))),
+            };
+        let feature = match feature_index(feature_names, split) {
+            Ok(idx) => idx,
+            Err(e) => match split.get(1..).and_then(|s| s.parse::<usize>().ok()) {
+                Some(idx) if split.starts_with('f') && idx < feature_names.len() => idx,
+                _ => return Err(e),
+            },
+        };
+        nodes.push(TreeNode::Leaf(0.0));
+        let left = self.child(yes)?.build(nodes, feature_names)?;
+        let right = self.child(no)?.build(nodes, feature_names)?;
+        nodes[idx] = TreeNode::Split {
+            feature,
+            threshold,
+            inclusive: false,
+            missing_type: MissingType::Nan,
+            default_left: self.missing.map_or(true, |m| m == yes),
+            left,
+            right,
+        };
+        Ok(idx)
+    }
+
+    fn child(&self, nodeid: usize) -> Result<&XgbNode> {
+        match self.children.iter().find(|c| c.nodeid == nodeid) {
+            Some(c) => Ok(c),
+            None => bail!(ErrorKind::IllegalArgument(format!(
+                "xgboost node {} has no child {}",
+                self.nodeid, nodeid
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LgbModel {
+    feature_names: Vec<String>,
+    tree_info: Vec<LgbTree>,
+}
+
+#[derive(Deserialize)]
+struct LgbTree {
+    tree_structure: LgbNode,
+}
+
+#[derive(Deserialize)]
+struct LgbNode {
+    split_feature: Option<usize>,
+    threshold: Option<serde_json::Value>,
+    decision_type: Option<String>,
+    #[serde(default)]
+    default_left: bool,
+    missing_type: Option<String>,
+    left_child: Option<Box<LgbNode>>,
+    right_child: Option<Box<LgbNode>>,
+    leaf_value: Option<f64>,
+}
+
+impl LgbNode {
+    // appends the nodes of the subtree to `nodes` and returns its index,
+    // `features` maps the feature indices of the dump to the feature set
+    fn build(&self, nodes: &mut Vec<TreeNode>, features: &[usize]) -> Result<usize> {
+        let idx = nodes.len();
+        if let Some(leaf) = self.leaf_value {
+            nodes.push(TreeNode::Leaf(leaf));
+            return Ok(idx);
+        }
+        let (feature, left_child, right_child) =
+            match (self.split_feature, &self.left_child, &self.right_child) {
+                (Some(feature), Some(left), Some(right)) if feature < features.len() => {
+                    (features[feature], left, right)
+                }
+                _ => bail!(ErrorKind::IllegalArgument(
+                    "invalid lightgbm node: neither a leaf nor a split".into()
+                )),
+            };
+        let inclusive = match self.decision_type.as_ref().map(String::as_str) {
+            None | Some("<=") => true,
+            Some("<") => false,
+            Some(t) => bail!(ErrorKind::IllegalArgument(format!(
+                "unsupported lightgbm decision type '{}'",
+                t
+            ))),
+        };
+        let threshold = match self.threshold.as_ref().and_then(serde_json::Value::as_f64) {
+            Some(t) => t,
+            None => bail!(ErrorKind::IllegalArgument(
+                "lightgbm split without a numeric threshold".into()
+            )),
+        };
+        let missing_type = match self.missing_type.as_ref().map(String::as_str) {
+            Some("NaN") => MissingType::Nan,
+            Some("Zero") => MissingType::Zero,
+            _ => MissingType::None,
+        };
+        nodes.push(TreeNode::Leaf(0.0));
+        let left = left_child.build(nodes, features)?;
+        let right = right_child.build(nodes, features)?;
+        nodes[idx] = TreeNode::Split {
+            feature,
+            threshold,
+            inclusive,
+            missing_type,
+            default_left: self.default_left,
+            left,
+            right,
+        };
+        Ok(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec!["title".to_string(), "popularity".to_string()]
+    }
+
+    #[test]
+    fn test_linear_model() {
+        let json = r#"{"bias": 0.5, "weights": {"popularity": 2.0}}"#;
+        let model = LinearModel::from_json(json, &names()).unwrap();
+        assert!((model.score(&[10.0, 1.5]) - 3.5).abs() < 1e-9);
+        assert!((model.score(&[10.0, ::std::f64::NAN]) - 0.5).abs() < 1e-9);
+
+        let json = r#"{"weights": {"unknown": 2.0}}"#;
+        assert!(LinearModel::from_json(json, &names()).is_err());
+    }
+
+    #[test]
+    fn test_xgboost_model() {
+        let json = r#"[
+            {"nodeid": 0, "depth": 0, "split": "title", "split_condition": 1.0,
+             "yes": 1, "no": 2, "missing": 2, "children": [
+                {"nodeid": 1, "leaf": -0.5},
+                {"nodeid": 2, "depth": 1, "split": "f1", "split_condition": 10.0,
+                 "yes": 3, "no": 4, "missing": 3, "children": [
+                    {"nodeid": 3, "leaf": 0.25},
+                    {"nodeid": 4, "leaf": 1.0}
+                ]}
+            ]},
+            {"nodeid": 0, "leaf": 0.125}
+        ]"#;
+        let model = TreeEnsembleModel::from_xgboost_json(json, &names())
+            .unwrap()
+            .with_base_score(0.5);
+        assert_eq!(model.num_trees(), 2);
+        assert!((model.score(&[0.5, 20.0]) - 0.125).abs() < 1e-9);
+        assert!((model.score(&[1.0, 20.0]) - 1.625).abs() < 1e-9);
+        assert!((model.score(&[2.0, 5.0]) - 0.875).abs() < 1e-9);
+        assert!((model.score(&[::std::f64::NAN, ::std::f64::NAN]) - 0.875).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lightgbm_model() {
+        let json = r#"{
+            "feature_names": ["popularity", "title"],
+            "tree_info": [{"tree_index": 0, "tree_structure": {
+                "split_feature": 1, "threshold": 1.0, "decision_type": "<=",
+                "default_left": false, "missing_type": "NaN",
+                "left_child": {"leaf_value": -1.0},
+                "right_child": {
+                    "split_feature": 0, "threshold": 0.0, "decision_type": "<=",
+                    "default_left": true, "missing_type": "Zero",
+                    "left_child": {"leaf_value": 0.5},
+                    "right_child": {"leaf_value": 2.0}
+                }
+            }}]
+        }"#;
+        let model = LtrModel::from(TreeEnsembleModel::from_lightgbm_json(json, &names()).unwrap());
+        assert!((model.score(&[1.0, 3.0]) + 1.0).abs() < 1e-9);
+        assert!((model.score(&[2.0, 3.0]) - 2.0).abs() < 1e-9);
+        assert!((model.score(&[2.0, 0.0]) - 0.5).abs() < 1e-9);
+        assert!((model.score(&[::std::f64::NAN, 3.0]) - 2.0).abs() < 1e-9);
+
+        let mut ctx = IndexedContext::default();
+        ctx.set_double(0, 2.0);
+        ctx.set_double(1, 3.0);
+        let scores = model.scores(vec![&ctx]).unwrap();
+        assert_eq!(scores, vec![2.0]);
+
+        // a feature without a value is missing, not zero: the first split
+        // sends it right, where a zero would go left
+        let mut ctx = IndexedContext::default();
+        ctx.set_double(1, 3.0);
+        let scores = model.scores(vec![&ctx]).unwrap();
+        assert_eq!(scores, vec![2.0]);
+    }
+}
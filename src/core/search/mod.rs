@@ -13,6 +13,9 @@
 
 pub mod cache;
 pub mod collector;
+pub mod expression;
+pub mod facet;
+pub mod ltr;
 pub mod query;
 pub mod scorer;
 pub mod similarity;
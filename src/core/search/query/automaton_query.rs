@@ -0,0 +1,490 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::{Codec, Terms};
+use core::doc::Term;
+use core::search::query::{MultiTermIterEnum, MultiTermQuery, MultiTermQueryConstantScoreWeight};
+use core::search::query::{Query, TermQuery, TermText, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{
+    Automata, Automaton, CompiledAutomaton, Operations, RegExp, RegExpFlags,
+    DEFAULT_MAX_DETERMINIZED_STATES, MAX_BYTE_LABEL,
+};
+
+use error::Result;
+
+pub const AUTOMATON: &str = "automaton";
+pub const PREFIX: &str = "prefix";
+pub const WILDCARD: &str = "wildcard";
+pub const REGEXP: &str = "regexp";
+
+/// A `Query` that will match terms against a finite-state machine.
+///
+/// This query will match documents that contain terms accepted by a given
+/// finite-state machine. The automaton can be constructed with the
+/// `Automata` and `Operations` utilities, or with `RegExp`.
+///
+/// The `term` is only used to carry the field and to describe the query, its
+/// text should identify the automaton since it is part of the cache key.
+#[derive(Clone)]
+pub struct AutomatonQuery {
+    term: Term,
+    compiled: CompiledAutomaton,
+    boost: f32,
+}
+
+impl AutomatonQuery {
+    pub fn new(term: Term, automaton: &Automaton) -> Result<AutomatonQuery> {
+        AutomatonQuery::with_options(term, automaton, DEFAULT_MAX_DETERMINIZED_STATES, false)
+    }
+
+    /// Create a new AutomatonQuery, `max_determinized_states` is the maximum
+    /// number of states that determinizing the automaton may create and
+    /// `is_binary` is true if the automaton labels are bytes rather than
+    /// unicode code points.
+    pub fn with_options(
+        term: Term,
+        automaton: &Automaton,
+        max_determinized_states: usize,
+        is_binary: bool,
+    ) -> Result<AutomatonQuery> {
+        let compiled = CompiledAutomaton::with_options(
+            automaton,
+            None,
+            true,
+            max_determinized_states,
+            is_binary,
+        )?;
+        Ok(AutomatonQuery {
+            term,
+            compiled,
+            boost: 1.0f32,
+        })
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn compiled(&self) -> &CompiledAutomaton {
+        &self.compiled
+    }
+
+    fn term_text(&self) -> TermText {
+        TermText(&self.term.bytes)
+    }
+}
+
+impl MultiTermQuery for AutomatonQuery {
+    fn field(&self) -> &str {
+        &self.term.field
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        Ok(self
+            .compiled
+            .term_iterator(terms)?
+            .map(MultiTermIterEnum::Automaton))
+    }
+}
+
+impl<C: Codec> Query<C> for AutomatonQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            AUTOMATON,
+            self.boost,
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for AutomatonQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AutomatonQuery(field: {}, term: {}, boost: {})",
+            &self.term.field,
+            self.term_text(),
+            self.boost
+        )
+    }
+}
+
+/// A Query that matches documents containing terms with a specified prefix.
+#[derive(Clone)]
+pub struct PrefixQuery {
+    query: AutomatonQuery,
+}
+
+impl PrefixQuery {
+    /// Constructs a query for terms starting with `prefix`.
+    pub fn new(prefix: Term) -> Result<PrefixQuery> {
+        let automaton = PrefixQuery::to_automaton(&prefix.bytes);
+        let query = AutomatonQuery::with_options(
+            prefix,
+            &automaton,
+            DEFAULT_MAX_DETERMINIZED_STATES,
+            true,
+        )?;
+        Ok(PrefixQuery { query })
+    }
+
+    /// Build an automaton accepting all terms with the specified prefix.
+    pub fn to_automaton(prefix: &[u8]) -> Automaton {
+        let mut automaton = Automata::make_binary(prefix);
+        let last = automaton.num_states() - 1;
+        automaton.add_transition(last, last, 0, MAX_BYTE_LABEL);
+        automaton.finish();
+        automaton
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+
+    /// Returns the prefix of this query.
+    pub fn prefix(&self) -> &Term {
+        self.query.term()
+    }
+}
+
+impl MultiTermQuery for PrefixQuery {
+    fn field(&self) -> &str {
+        self.query.field()
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        self.query.term_iterator(terms)
+    }
+}
+
+impl<C: Codec> Query<C> for PrefixQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            PREFIX,
+            self.query.boost,
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for PrefixQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PrefixQuery(field: {}, prefix: {}, boost: {})",
+            self.query.field(),
+            self.query.term_text(),
+            self.query.boost
+        )
+    }
+}
+
+/// String equality with support for wildcards
+pub const WILDCARD_STRING: char = '*';
+
+/// Char equality with support for wildcards
+pub const WILDCARD_CHAR: char = '?';
+
+/// Escape character
+pub const WILDCARD_ESCAPE: char = '\\';
+
+/// Implements the wildcard search query.
+///
+/// Supported wildcards are `*`, which matches any character sequence
+/// (including the empty one), and `?`, which matches any single character.
+/// `\` is the escape character.
+///
+/// Note this query can be slow, as it needs to iterate over many terms. In
+/// order to prevent extremely slow WildcardQueries, a Wildcard term should
+/// not start with the wildcard `*`.
+#[derive(Clone)]
+pub struct WildcardQuery {
+    query: AutomatonQuery,
+}
+
+impl WildcardQuery {
+    pub fn new(term: Term) -> Result<WildcardQuery> {
+        WildcardQuery::with_max_determinized_states(term, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    pub fn with_max_determinized_states(
+        term: Term,
+        max_determinized_states: usize,
+    ) -> Result<WildcardQuery> {
+        let automaton = WildcardQuery::to_automaton(&term.text()?);
+        let query = AutomatonQuery::with_options(term, &automaton, max_determinized_states, false)?;
+        Ok(WildcardQuery { query })
+    }
+
+    /// Convert Lucene wildcard syntax into an automaton.
+    pub fn to_automaton(wildcard_text: &str) -> Automaton {
+        let mut automata = Vec::new();
+        let mut chars = wildcard_text.chars();
+        while let Some(c) = chars.next() {
+            let a = match c {
+                WILDCARD_STRING => Automata::make_any_string(),
+                WILDCARD_CHAR => Automata::make_any_char(),
+                WILDCARD_ESCAPE => {
+                    // add the next codepoint instead, if it exists
+                    Automata::make_char(chars.next().unwrap_or(WILDCARD_ESCAPE) as u32)
+                }
+                _ => Automata::make_char(c as u32),
+            };
+            automata.push(a);
+        }
+        let refs: Vec<&Automaton> = automata.iter().collect();
+        Operations::concatenate(&refs)
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+
+    /// Returns the pattern term.
+    pub fn term(&self) -> &Term {
+        self.query.term()
+    }
+}
+
+impl MultiTermQuery for WildcardQuery {
+    fn field(&self) -> &str {
+        self.query.field()
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        self.query.term_iterator(terms)
+    }
+}
+
+impl<C: Codec> Query<C> for WildcardQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            WILDCARD,
+            self.query.boost,
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for WildcardQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WildcardQuery(field: {}, term: {}, boost: {})",
+            self.query.field(),
+            self.query.term_text(),
+            self.query.boost
+        )
+    }
+}
+
+/// A fast regular expression query based on the `core::util::automaton`
+/// package.
+///
+/// The syntax is described by `RegExp`, it is not the same as the one of
+/// the `regex` crate.
+#[derive(Clone)]
+pub struct RegexpQuery {
+    query: AutomatonQuery,
+}
+
+impl RegexpQuery {
+    /// Constructs a query for terms matching `term`, all optional regexp
+    /// syntax is enabled.
+    pub fn new(term: Term) -> Result<RegexpQuery> {
+        RegexpQuery::with_flags(term, RegExpFlags::ALL, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Constructs a query for terms matching `term`, `flags` are the optional
+    /// `RegExp` features.
+    pub fn with_flags(
+        term: Term,
+        flags: u32,
+        max_determinized_states: usize,
+    ) -> Result<RegexpQuery> {
+        let automaton =
+            RegExp::with_flags(&term.text()?, flags)?.to_automaton(max_determinized_states)?;
+        let query = AutomatonQuery::with_options(term, &automaton, max_determinized_states, false)?;
+        Ok(RegexpQuery { query })
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+
+    /// Returns the regexp term.
+    pub fn regexp(&self) -> &Term {
+        self.query.term()
+    }
+}
+
+impl MultiTermQuery for RegexpQuery {
+    fn field(&self) -> &str {
+        self.query.field()
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        self.query.term_iterator(terms)
+    }
+}
+
+impl<C: Codec> Query<C> for RegexpQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            REGEXP,
+            self.query.boost,
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for RegexpQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RegexpQuery(field: {}, regexp: {}, boost: {})",
+            self.query.field(),
+            self.query.term_text(),
+            self.query.boost
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::util::automaton::{AutomatonType, ByteRunAutomaton};
+
+    fn run_automaton(a: &Automaton, is_binary: bool) -> ByteRunAutomaton {
+        ByteRunAutomaton::new(a, is_binary, DEFAULT_MAX_DETERMINIZED_STATES).unwrap()
+    }
+
+    #[test]
+    fn test_wildcard_automaton() {
+        let a = run_automaton(&WildcardQuery::to_automaton("ab*c?\\*"), false);
+        assert!(a.run(b"abc1*"));
+        assert!(a.run("abxyzc\u{4e2d}*".as_bytes()));
+        assert!(!a.run(b"abc*"));
+        assert!(!a.run(b"abc12"));
+
+        let a = run_automaton(&WildcardQuery::to_automaton("a\\"), false);
+        assert!(a.run(b"a\\"));
+    }
+
+    #[test]
+    fn test_prefix_automaton() {
+        let a = run_automaton(&PrefixQuery::to_automaton(b"ab"), true);
+        assert!(a.run(b"ab"));
+        assert!(a.run(&[b'a', b'b', 0xff, 0]));
+        assert!(!a.run(b"a"));
+        assert!(!a.run(b"ba"));
+
+        let query = PrefixQuery::new(Term::new("f".into(), vec![])).unwrap();
+        assert_eq!(query.query.compiled().automaton_type, AutomatonType::All);
+    }
+
+    #[test]
+    fn test_compiled_types() {
+        let query = WildcardQuery::new(Term::new("f".into(), b"abc".to_vec())).unwrap();
+        assert_eq!(query.query.compiled().automaton_type, AutomatonType::Single);
+        assert_eq!(query.query.compiled().term, Some(b"abc".to_vec()));
+
+        let query = RegexpQuery::new(Term::new("f".into(), b"a#".to_vec())).unwrap();
+        assert_eq!(query.query.compiled().automaton_type, AutomatonType::Empty);
+
+        let query = RegexpQuery::new(Term::new("f".into(), b"a[bc]+".to_vec())).unwrap();
+        let compiled = query.query.compiled();
+        assert_eq!(compiled.automaton_type, AutomatonType::Normal);
+        assert!(!compiled.finite);
+        assert!(compiled.run_automaton.as_ref().unwrap().run(b"acbb"));
+    }
+
+    #[test]
+    fn test_display() {
+        fn prefix(bytes: &[u8]) -> String {
+            PrefixQuery::new(Term::new("f".into(), bytes.to_vec()))
+                .unwrap()
+                .to_string()
+        }
+        assert_eq!(
+            prefix(b"a\"b"),
+            "PrefixQuery(field: f, prefix: \"a\\\"b\", boost: 1)"
+        );
+        // the invalid UTF-8 prefixes are told apart
+        assert_eq!(
+            prefix(&[b'a', 0xff]),
+            "PrefixQuery(field: f, prefix: 0x61ff, boost: 1)"
+        );
+        assert_ne!(prefix(&[b'a', 0xfe]), prefix(&[b'a', 0xff]));
+    }
+}
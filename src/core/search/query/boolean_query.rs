@@ -18,37 +18,67 @@ use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
 use core::search::query::{ConstantScoreQuery, Query, TermQuery, Weight};
-use core::search::scorer::{ConjunctionScorer, DisjunctionSumScorer, ReqOptScorer, Scorer};
+use core::search::scorer::{
+    ConjunctionScorer, DisjunctionSumScorer, MinShouldMatchSumScorer, ReqExclScorer, ReqOptScorer,
+    Scorer, TopScoresDisjunctionScorer,
+};
 use core::search::searcher::SearchPlanBuilder;
 use core::util::DocId;
 use error::{ErrorKind::IllegalArgument, Result};
 
 /// A Query that matches documents matching boolean combinations of other queries.
+///
+/// The documents must match all the `must` and `filter` clauses, none of the
+/// `must_not` clauses, and at least `minimum_should_match` of the `should`
+/// clauses. A query with only `must_not` clauses matches no document.
 pub struct BooleanQuery<C: Codec> {
     must_queries: Vec<Box<dyn Query<C>>>,
     should_queries: Vec<Box<dyn Query<C>>>,
     filter_queries: Vec<Box<dyn Query<C>>>,
+    must_not_queries: Vec<Box<dyn Query<C>>>,
     minimum_should_match: i32,
 }
 
 pub const BOOLEAN: &str = "boolean";
 
 impl<C: Codec> BooleanQuery<C> {
+    /// Builds a query without `must_not` clauses, at least one of the
+    /// `should` clauses must match when there are no `must` clauses.
     pub fn build(
         musts: Vec<Box<dyn Query<C>>>,
         shoulds: Vec<Box<dyn Query<C>>>,
         filters: Vec<Box<dyn Query<C>>>,
     ) -> Result<Box<dyn Query<C>>> {
         let minimum_should_match = if musts.is_empty() { 1 } else { 0 };
+        BooleanQuery::with_options(musts, shoulds, filters, vec![], minimum_should_match)
+    }
+
+    /// Builds a query with prohibited clauses and an explicit number of
+    /// `should` clauses that must match.
+    pub fn with_options(
+        musts: Vec<Box<dyn Query<C>>>,
+        shoulds: Vec<Box<dyn Query<C>>>,
+        filters: Vec<Box<dyn Query<C>>>,
+        must_nots: Vec<Box<dyn Query<C>>>,
+        minimum_should_match: i32,
+    ) -> Result<Box<dyn Query<C>>> {
         let mut musts = musts;
         let mut shoulds = shoulds;
         let mut filters = filters;
-        if musts.len() + shoulds.len() + filters.len() == 0 {
+        if musts.len() + shoulds.len() + filters.len() + must_nots.len() == 0 {
             bail!(IllegalArgument(
                 "boolean query should at least contain one inner query!".into()
             ));
         }
-        if musts.len() + shoulds.len() + filters.len() == 1 {
+        if minimum_should_match < 0 {
+            bail!(IllegalArgument(
+                "minimum_should_match must not be negative".into()
+            ));
+        }
+        if musts.len() + shoulds.len() + filters.len() == 1
+            && must_nots.is_empty()
+            && minimum_should_match <= 1
+        {
             let query = if musts.len() == 1 {
                 musts.remove(0)
             } else if shoulds.len() == 1 {
@@ -62,6 +92,7 @@ impl<C: Codec> BooleanQuery<C> {
             must_queries: musts,
             should_queries: shoulds,
             filter_queries: filters,
+            must_not_queries: must_nots,
             minimum_should_match,
         }))
     }
@@ -90,10 +121,16 @@ impl<C: Codec> Query<C> for BooleanQuery<C> {
         for q in &self.should_queries {
             should_weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
         }
+        let mut must_not_weights = Vec::with_capacity(self.must_not_queries.len());
+        for q in &self.must_not_queries {
+            must_not_weights.push(searcher.create_weight(q.as_ref(), false)?);
+        }
 
-        Ok(Box::new(BooleanWeight::new(
+        Ok(Box::new(BooleanWeight::with_options(
             must_weights,
             should_weights,
+            must_not_weights,
+            self.minimum_should_match,
             needs_scores,
         )))
     }
@@ -128,16 +165,23 @@ impl<C: Codec> fmt::Display for BooleanQuery<C> {
         let filters_str = self.queries_to_str(&self.filter_queries);
         write!(
             f,
-            "BooleanQuery(must: [{}], should: [{}], filters: [{}], match: {})",
-            must_str, should_str, filters_str, self.minimum_should_match
-        )
+            "BooleanQuery(must: [{}], should: [{}], filters: [{}], ",
+            must_str, should_str, filters_str
+        )?;
+        if !self.must_not_queries.is_empty() {
+            let must_not_str = self.queries_to_str(&self.must_not_queries);
+            write!(f, "must_not: [{}], ", must_not_str)?;
+        }
+        write!(f, "match: {})", self.minimum_should_match)
     }
 }
 
-struct BooleanWeight<C: Codec> {
+/// The `Weight` of a `BooleanQuery`, it is also used by queries that
+/// rewrite to a disjunction of terms, like `FuzzyQuery`.
+pub struct BooleanWeight<C: Codec> {
     must_weights: Vec<Box<dyn Weight<C>>>,
     should_weights: Vec<Box<dyn Weight<C>>>,
-    #[allow(dead_code)]
+    must_not_weights: Vec<Box<dyn Weight<C>>>,
     minimum_should_match: i32,
     needs_scores: bool,
 }
@@ -149,9 +193,20 @@ impl<C: Codec> BooleanWeight<C> {
         needs_scores: bool,
     ) -> BooleanWeight<C> {
         let minimum_should_match = if musts.is_empty() { 1 } else { 0 };
+        BooleanWeight::with_options(musts, shoulds, vec![], minimum_should_match, needs_scores)
+    }
+
+    pub fn with_options(
+        musts: Vec<Box<dyn Weight<C>>>,
+        shoulds: Vec<Box<dyn Weight<C>>>,
+        must_nots: Vec<Box<dyn Weight<C>>>,
+        minimum_should_match: i32,
+        needs_scores: bool,
+    ) -> BooleanWeight<C> {
         BooleanWeight {
             must_weights: musts,
             should_weights: shoulds,
+            must_not_weights: must_nots,
             minimum_should_match,
             needs_scores,
         }
@@ -192,9 +247,23 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
                     scorers.push(scorer);
                 }
             }
+            if scorers.len() < self.minimum_should_match as usize {
+                return Ok(None);
+            }
             match scorers.len() {
                 0 => None,
                 1 => Some(scorers.remove(0)),
+                _ if self.minimum_should_match > 1 => Some(Box::new(MinShouldMatchSumScorer::new(
+                    scorers,
+                    self.minimum_should_match as usize,
+                ))),
+                // pure disjunctions can skip the docs that are not competitive
+                _ if self.needs_scores
+                    && self.must_weights.is_empty()
+                    && self.must_not_weights.is_empty() =>
+                {
+                    Some(Box::new(TopScoresDisjunctionScorer::new(scorers)))
+                }
                 _ => Some(Box::new(DisjunctionSumScorer::new(
                     scorers,
                     self.needs_scores,
@@ -202,17 +271,29 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
             }
         };
 
-        if let Some(must) = must_scorer {
-            if let Some(should) = should_scorer {
-                Ok(Some(Box::new(ReqOptScorer::new(must, should))))
-            } else {
-                Ok(Some(must))
+        let scorer: Box<dyn Scorer> = match (must_scorer, should_scorer) {
+            // the should clauses are required as soon as some of them must match
+            (Some(must), Some(should)) if self.minimum_should_match > 0 => {
+                Box::new(ConjunctionScorer::new(vec![must, should]))
+            }
+            (Some(must), Some(should)) => Box::new(ReqOptScorer::new(must, should)),
+            (Some(must), None) => must,
+            (None, Some(should)) => should,
+            (None, None) => return Ok(None),
+        };
+
+        let mut excl_scorers = vec![];
+        for weight in &self.must_not_weights {
+            if let Some(scorer) = weight.create_scorer(leaf_reader)? {
+                excl_scorers.push(scorer);
             }
-        } else if let Some(should) = should_scorer {
-            Ok(Some(should))
-        } else {
-            Ok(None)
         }
+        let excl_scorer: Box<dyn Scorer> = match excl_scorers.len() {
+            0 => return Ok(Some(scorer)),
+            1 => excl_scorers.remove(0),
+            _ => Box::new(DisjunctionSumScorer::new(excl_scorers, false)),
+        };
+        Ok(Some(Box::new(ReqExclScorer::new(scorer, excl_scorer))))
     }
 
     fn query_type(&self) -> &'static str {
@@ -230,7 +311,7 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
 
     fn value_for_normalization(&self) -> f32 {
         let mut sum = 0f32;
-        for weight in &self.must_weights {
+        for weight in self.must_weights.iter().chain(self.should_weights.iter()) {
             if weight.needs_scores() {
                 sum += weight.value_for_normalization();
             }
@@ -284,6 +365,19 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
             }
         }
 
+        for w in &self.must_not_weights {
+            let e = w.explain(reader, doc)?;
+            if e.is_match() {
+                fail = true;
+                subs.push(Explanation::new(
+                    false,
+                    0.0f32,
+                    format!("match on prohibited clause ({})", w),
+                    vec![e],
+                ));
+            }
+        }
+
         if fail {
             Ok(Explanation::new(
                 false,
@@ -339,10 +433,12 @@ impl<C: Codec> fmt::Display for BooleanWeight<C> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let must_str = self.weights_to_str(&self.must_weights);
         let should_str = self.weights_to_str(&self.should_weights);
+        let must_not_str = self.weights_to_str(&self.must_not_weights);
         write!(
             f,
-            "BooleanWeight(must: [{}], should: [{}], min match: {}, needs score: {})",
-            must_str, should_str, self.minimum_should_match, self.needs_scores
+            "BooleanWeight(must: [{}], should: [{}], must_not: [{}], min match: {}, needs score: \
+             {})",
+            must_str, should_str, must_not_str, self.minimum_should_match, self.needs_scores
         )
     }
 }
@@ -0,0 +1,485 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{LeafScoreFunction, Query, ScoreFunction, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::{ErrorKind, Result};
+
+use std::any::Any;
+use std::fmt;
+
+const FUNCTION_SCORE_QUERY: &str = "function_score";
+
+/// How the values of the functions of a `FunctionScoreQuery` are combined
+/// with each other and with the score of the wrapped query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionScoreMode {
+    /// The product of the query score and the function values.
+    Multiply,
+    /// The sum of the query score and the function values.
+    Sum,
+    /// The max of the query score and the function values.
+    Max,
+    /// The product of the function values, the query score is ignored.
+    Replace,
+}
+
+impl FunctionScoreMode {
+    pub fn combine(self, query_score: f64, function_scores: &[f64]) -> f64 {
+        match self {
+            FunctionScoreMode::Multiply => function_scores.iter().fold(query_score, |a, b| a * b),
+            FunctionScoreMode::Sum => function_scores.iter().fold(query_score, |a, b| a + b),
+            FunctionScoreMode::Max => function_scores.iter().fold(query_score, |a, b| a.max(*b)),
+            FunctionScoreMode::Replace => function_scores.iter().product(),
+        }
+    }
+}
+
+impl fmt::Display for FunctionScoreMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionScoreMode::Multiply => write!(f, "multiply"),
+            FunctionScoreMode::Sum => write!(f, "sum"),
+            FunctionScoreMode::Max => write!(f, "max"),
+            FunctionScoreMode::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+/// A query that modifies the scores of the docs matched by another query
+/// with `ScoreFunction`s, e.g. to boost docs by popularity or freshness
+/// stored in numeric doc values.
+pub struct FunctionScoreQuery<C: Codec> {
+    query: Box<dyn Query<C>>,
+    functions: Vec<ScoreFunction>,
+    score_mode: FunctionScoreMode,
+}
+
+impl<C: Codec> FunctionScoreQuery<C> {
+    pub fn new(
+        query: Box<dyn Query<C>>,
+        functions: Vec<ScoreFunction>,
+        score_mode: FunctionScoreMode,
+    ) -> Self {
+        FunctionScoreQuery {
+            query,
+            functions,
+            score_mode,
+        }
+    }
+
+    pub fn functions(&self) -> &[ScoreFunction] {
+        &self.functions
+    }
+
+    pub fn score_mode(&self) -> FunctionScoreMode {
+        self.score_mode
+    }
+}
+
+impl<C: Codec> Query<C> for FunctionScoreQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        if !needs_scores {
+            return self.query.create_weight(searcher, false);
+        }
+        let needs_query_score = self.score_mode != FunctionScoreMode::Replace;
+        let weight = self.query.create_weight(searcher, needs_query_score)?;
+        Ok(Box::new(FunctionScoreWeight {
+            weight,
+            functions: self.functions.clone(),
+            score_mode: self.score_mode,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<C: Codec> fmt::Display for FunctionScoreQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let functions: Vec<String> = self.functions.iter().map(|f| f.to_string()).collect();
+        write!(
+            f,
+            "FunctionScoreQuery(query: {}, functions: [{}], score_mode: {})",
+            self.query,
+            functions.join(", "),
+            self.score_mode
+        )
+    }
+}
+
+struct FunctionScoreWeight<C: Codec> {
+    weight: Box<dyn Weight<C>>,
+    functions: Vec<ScoreFunction>,
+    score_mode: FunctionScoreMode,
+}
+
+impl<C: Codec> FunctionScoreWeight<C> {
+    fn leaf_functions(
+        &self,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Vec<Box<dyn LeafScoreFunction>>> {
+        let mut functions = Vec::with_capacity(self.functions.len());
+        for f in &self.functions {
+            functions.push(f.leaf_function(ctx)?);
+        }
+        Ok(functions)
+    }
+}
+
+impl<C: Codec> Weight<C> for FunctionScoreWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        if let Some(scorer) = self.weight.create_scorer(ctx)? {
+            Ok(Some(Box::new(FunctionScoreScorer {
+                scorer,
+                functions: self.leaf_functions(ctx)?,
+                function_scores: vec![0.0; self.functions.len()],
+                score_mode: self.score_mode,
+            })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        FUNCTION_SCORE_QUERY
+    }
+
+    fn actual_query_type(&self) -> &'static str {
+        self.weight.query_type()
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let query_expl = self.weight.explain(reader, doc)?;
+        if !query_expl.is_match() {
+            return Ok(query_expl);
+        }
+
+        // combine the same f64 values as the scorer, not the rounded values
+        // of the explanations
+        let mut function_scores = Vec::with_capacity(self.functions.len());
+        let mut details = Vec::with_capacity(self.functions.len() + 1);
+        for mut f in self.leaf_functions(reader)? {
+            function_scores.push(f.score(doc)?);
+            details.push(f.explain(doc)?);
+        }
+        let query_score = if self.score_mode != FunctionScoreMode::Replace {
+            let query_score = f64::from(query_expl.value());
+            details.insert(0, query_expl);
+            query_score
+        } else {
+            0.0
+        };
+        let score = self.score_mode.combine(query_score, &function_scores);
+        Ok(Explanation::new(
+            true,
+            score as f32,
+            format!(
+                "function score, score mode [{}], computed from:",
+                self.score_mode
+            ),
+            details,
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for FunctionScoreWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FunctionScoreWeight(weight: {}, functions: {}, score_mode: {})",
+            self.weight,
+            self.functions.len(),
+            self.score_mode
+        )
+    }
+}
+
+struct FunctionScoreScorer {
+    scorer: Box<dyn Scorer>,
+    functions: Vec<Box<dyn LeafScoreFunction>>,
+    function_scores: Vec<f64>,
+    score_mode: FunctionScoreMode,
+}
+
+impl Scorer for FunctionScoreScorer {
+    fn score(&mut self) -> Result<f32> {
+        let doc = self.scorer.doc_id();
+        let query_score = if self.score_mode != FunctionScoreMode::Replace {
+            f64::from(self.scorer.score()?)
+        } else {
+            0.0
+        };
+        for (f, score) in self
+            .functions
+            .iter_mut()
+            .zip(self.function_scores.iter_mut())
+        {
+            *score = f.score(doc)?;
+        }
+        let score = self.score_mode.combine(query_score, &self.function_scores) as f32;
+        if !score.is_finite() {
+            bail!(ErrorKind::IllegalState(format!(
+                "function score of doc {} is not a finite number: {}",
+                doc, score
+            )));
+        }
+        Ok(score)
+    }
+}
+
+impl DocIterator for FunctionScoreScorer {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.scorer.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.scorer.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Fieldable, NumericDocValuesField, SortedNumericDocValuesField, Term};
+    use core::index::tests::*;
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::{
+        DecayFunction, DecayType, FieldValueFactorFunction, FieldValueModifier, NumericFieldSource,
+    };
+    use core::search::sort_field::{SortFieldType, SortedNumericSelectorType};
+    use core::search::IndexSearcher;
+
+    // 6 docs in 2 segments, "popularity" is `2i + 1` but for the docs 2 and
+    // 5, "prices" is `i` and `10 + i` but for the doc 5
+    fn test_index() -> TestIndex {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = (0..6)
+            .map(|i| {
+                let mut doc: Vec<Box<dyn Fieldable>> = vec![text_field("body", "a")];
+                if i != 2 && i != 5 {
+                    doc.push(Box::new(NumericDocValuesField::new(
+                        "popularity",
+                        2 * i + 1,
+                    )));
+                }
+                if i != 5 {
+                    doc.push(Box::new(SortedNumericDocValuesField::new("prices", 10 + i)));
+                    doc.push(Box::new(SortedNumericDocValuesField::new("prices", i)));
+                }
+                doc
+            })
+            .collect();
+        TestIndex::new(docs, 3)
+    }
+
+    fn function_score(
+        functions: Vec<ScoreFunction>,
+        score_mode: FunctionScoreMode,
+    ) -> FunctionScoreQuery<CodecEnum> {
+        let query = TermQuery::new(Term::new("body".into(), b"a".to_vec()), 1.0, None);
+        FunctionScoreQuery::new(Box::new(query), functions, score_mode)
+    }
+
+    // the (doc, score, explanation) of every hit, by doc
+    fn search_hits(
+        index: &TestIndex,
+        query: &dyn Query<CodecEnum>,
+    ) -> Vec<(DocId, f32, Explanation)> {
+        let searcher = index.searcher();
+        let mut collector = TopDocsCollector::new(10);
+        searcher.search(query, &mut collector).unwrap();
+        let mut hits: Vec<_> = collector
+            .top_docs()
+            .score_docs()
+            .iter()
+            .map(|d| {
+                let expl = searcher.explain(query, d.doc_id()).unwrap();
+                assert!(expl.is_match());
+                (d.doc_id(), d.score(), expl)
+            })
+            .collect();
+        hits.sort_by_key(|h| h.0);
+        hits
+    }
+
+    fn scores(hits: &[(DocId, f32, Explanation)]) -> Vec<f32> {
+        hits.iter().map(|h| h.1).collect()
+    }
+
+    #[test]
+    fn test_field_value_factor_scores() {
+        let index = test_index();
+        let source = NumericFieldSource::new("popularity", SortFieldType::Long).unwrap();
+        let function = FieldValueFactorFunction::new(source, 2.0, FieldValueModifier::Square);
+
+        // the docs without a value score 1.0 without a missing value
+        let query = function_score(vec![function.clone().into()], FunctionScoreMode::Replace);
+        let hits = search_hits(&index, &query);
+        assert_eq!(scores(&hits), vec![4.0, 36.0, 1.0, 196.0, 324.0, 1.0]);
+        for (_, score, expl) in &hits {
+            assert_eq!(expl.value(), *score);
+        }
+
+        // factor and modifier apply to the missing value: (2 * 1.5)^2
+        let query = function_score(
+            vec![function.with_missing(1.5).into()],
+            FunctionScoreMode::Replace,
+        );
+        let hits = search_hits(&index, &query);
+        assert_eq!(scores(&hits), vec![4.0, 36.0, 9.0, 196.0, 324.0, 9.0]);
+        let missing_expl = &hits[2].2.details()[0];
+        assert_eq!(missing_expl.value(), 9.0);
+        assert!(missing_expl.description().contains("missing value 1.5"));
+    }
+
+    #[test]
+    fn test_multi_valued_selector() {
+        let index = test_index();
+        for &(selector, expected) in &[
+            (
+                SortedNumericSelectorType::Min,
+                [0.0f32, 1.0, 2.0, 3.0, 4.0, 100.0],
+            ),
+            (
+                SortedNumericSelectorType::Max,
+                [10.0f32, 11.0, 12.0, 13.0, 14.0, 100.0],
+            ),
+        ] {
+            let source =
+                NumericFieldSource::multi_valued("prices", SortFieldType::Long, selector).unwrap();
+            let function = FieldValueFactorFunction::new(source, 1.0, FieldValueModifier::None)
+                .with_missing(100.0);
+            let query = function_score(vec![function.into()], FunctionScoreMode::Replace);
+            let hits = search_hits(&index, &query);
+            assert_eq!(scores(&hits), expected.to_vec());
+            for (_, score, expl) in &hits {
+                assert_eq!(expl.value(), *score);
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain_matches_score() {
+        let index = test_index();
+        let popularity = NumericFieldSource::new("popularity", SortFieldType::Long).unwrap();
+        // 1.0 at 7 = 5 + 2, where the gauss decay is 0.5
+        let decay =
+            DecayFunction::new(popularity.clone(), DecayType::Gauss, 5.0, 2.0, 0.0, 0.5).unwrap();
+        let functions: Vec<ScoreFunction> = vec![
+            FieldValueFactorFunction::new(popularity, 1.0, FieldValueModifier::Ln1p)
+                .with_missing(0.0)
+                .into(),
+            ScoreFunction::from(decay).weighted(2.0),
+            ScoreFunction::Weight(1.5),
+        ];
+
+        for &mode in &[
+            FunctionScoreMode::Multiply,
+            FunctionScoreMode::Sum,
+            FunctionScoreMode::Max,
+            FunctionScoreMode::Replace,
+        ] {
+            let query = function_score(functions.clone(), mode);
+            let hits = search_hits(&index, &query);
+            assert_eq!(hits.len(), 6);
+            for (_, score, expl) in &hits {
+                // the query explanation may round differently than its scorer
+                assert!((expl.value() - score).abs() <= 1e-5 * score.abs().max(1.0));
+                let detail_count = if mode == FunctionScoreMode::Replace {
+                    3
+                } else {
+                    4
+                };
+                assert_eq!(expl.details().len(), detail_count);
+            }
+            if mode == FunctionScoreMode::Replace {
+                // doc 3: ln(1 + 7) * 2 * 0.5 * 1.5
+                assert!((f64::from(hits[3].1) - 8f64.ln() * 1.5).abs() < 1e-5);
+                assert_eq!(hits[3].2.value(), hits[3].1);
+                // doc 2 has no popularity, ln(1 + 0) is 0
+                assert_eq!(hits[2].1, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_combine() {
+        let functions = [2.0, 0.5, 3.0];
+        let eq = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(eq(
+            FunctionScoreMode::Multiply.combine(4.0, &functions),
+            12.0
+        ));
+        assert!(eq(FunctionScoreMode::Sum.combine(4.0, &functions), 9.5));
+        assert!(eq(FunctionScoreMode::Max.combine(4.0, &functions), 4.0));
+        assert!(eq(FunctionScoreMode::Replace.combine(4.0, &functions), 3.0));
+        assert!(eq(FunctionScoreMode::Replace.combine(4.0, &[]), 1.0));
+    }
+}
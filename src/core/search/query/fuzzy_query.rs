@@ -0,0 +1,641 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::{max, Ordering};
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+
+use core::codec::{Codec, CodecTermState, TermIterator, TermState, Terms};
+use core::doc::Term;
+use core::search::query::{BooleanWeight, Query, TermQuery, Weight};
+use core::search::searcher::{SearchPlanBuilder, TermContext};
+use core::util::automaton::{
+    AutomatonTermIterEnum, AutomatonType, CompiledAutomaton, LevenshteinAutomata,
+    MAXIMUM_SUPPORTED_DISTANCE,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const FUZZY: &str = "fuzzy";
+
+// the initial capacity of the top terms queue, it grows up to `max_expansions`
+const MAX_INITIAL_QUEUE_SIZE: usize = 1024;
+
+/// Implements the fuzzy search query. The similarity measurement
+/// is based on the Damerau-Levenshtein (optimal string alignment) algorithm,
+/// though you can explicitly choose classic Levenshtein by passing `false`
+/// to the `transpositions` parameter.
+///
+/// This query uses a top terms blended freq scoring rewrite: at most
+/// `max_expansions` terms, the ones with the smallest edit distance, are
+/// scored as a disjunction of `TermQuery`s. Each term is boosted by its
+/// similarity to the query term and every term gets the same blended document
+/// frequency (the maximum of the document frequencies of the expanded terms),
+/// so that rare misspellings don't rank above the exact matches.
+///
+/// At most, this query will match terms up to `MAXIMUM_SUPPORTED_DISTANCE`
+/// edits. Higher distances (especially with transpositions enabled), are
+/// generally not useful and will match a significant amount of the term
+/// dictionary.
+///
+/// NOTE: terms of length 1 or 2 will sometimes not match because of how the
+/// scaled distance between two terms is computed. For a term to match, the
+/// edit distance between the terms must be less than the minimum length term
+/// (either the input term, or the candidate term).
+pub struct FuzzyQuery {
+    term: Term,
+    max_edits: usize,
+    prefix_length: usize,
+    max_expansions: usize,
+    transpositions: bool,
+    boost: f32,
+    // number of code points of the term text
+    term_length: usize,
+    // `automata[n]` accepts the terms within `n` edits of the query term
+    automata: Vec<CompiledAutomaton>,
+    // the terms and boosts of the latest rewrite, for `extract_terms`
+    rewritten_terms: Mutex<Vec<(Vec<u8>, f32)>>,
+}
+
+impl Clone for FuzzyQuery {
+    fn clone(&self) -> Self {
+        FuzzyQuery {
+            term: self.term.clone(),
+            max_edits: self.max_edits,
+            prefix_length: self.prefix_length,
+            max_expansions: self.max_expansions,
+            transpositions: self.transpositions,
+            boost: self.boost,
+            term_length: self.term_length,
+            automata: self.automata.clone(),
+            rewritten_terms: Mutex::new(self.rewritten_terms()),
+        }
+    }
+}
+
+impl FuzzyQuery {
+    pub const DEFAULT_MAX_EDITS: usize = MAXIMUM_SUPPORTED_DISTANCE;
+    pub const DEFAULT_PREFIX_LENGTH: usize = 0;
+    pub const DEFAULT_MAX_EXPANSIONS: usize = 50;
+    pub const DEFAULT_TRANSPOSITIONS: bool = true;
+
+    /// Calls `FuzzyQuery::with_options` with the default max edits, prefix
+    /// length, max expansions and transpositions.
+    pub fn new(term: Term) -> Result<FuzzyQuery> {
+        FuzzyQuery::with_options(
+            term,
+            FuzzyQuery::DEFAULT_MAX_EDITS,
+            FuzzyQuery::DEFAULT_PREFIX_LENGTH,
+            FuzzyQuery::DEFAULT_MAX_EXPANSIONS,
+            FuzzyQuery::DEFAULT_TRANSPOSITIONS,
+        )
+    }
+
+    /// Create a new FuzzyQuery that will match terms with an edit distance
+    /// of at most `max_edits` to `term`. If a `prefix_length` > 0 is
+    /// specified, a common prefix of that length is also required.
+    ///
+    /// * `max_edits` - must be >= 0 and <= `MAXIMUM_SUPPORTED_DISTANCE`.
+    /// * `prefix_length` - length of common (non-fuzzy) prefix
+    /// * `max_expansions` - the maximum number of terms to match.
+    /// * `transpositions` - true if transpositions should be treated as a primitive edit operation.
+    ///   If this is false, comparisons will implement the classic Levenshtein algorithm.
+    pub fn with_options(
+        term: Term,
+        max_edits: usize,
+        prefix_length: usize,
+        max_expansions: usize,
+        transpositions: bool,
+    ) -> Result<FuzzyQuery> {
+        if max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            bail!(IllegalArgument(format!(
+                "max_edits must be between 0 and {}",
+                MAXIMUM_SUPPORTED_DISTANCE
+            )));
+        }
+        if max_expansions == 0 {
+            bail!(IllegalArgument("max_expansions must be positive.".into()));
+        }
+
+        let text = term.text()?;
+        let term_length = text.chars().count();
+        let prefix_end = text
+            .char_indices()
+            .nth(prefix_length)
+            .map_or(text.len(), |(i, _)| i);
+        let (prefix, suffix) = text.split_at(prefix_end);
+        let builder = LevenshteinAutomata::new(suffix, transpositions);
+        let mut automata = Vec::with_capacity(max_edits + 1);
+        for n in 0..=max_edits {
+            // levenshtein automata have a number of states linear in the term
+            // length, there is no need to limit the determinization
+            automata.push(CompiledAutomaton::with_options(
+                &builder.to_automaton_with_prefix(n, prefix)?,
+                Some(true),
+                false,
+                usize::max_value(),
+                false,
+            )?);
+        }
+
+        Ok(FuzzyQuery {
+            term,
+            max_edits,
+            prefix_length,
+            max_expansions,
+            transpositions,
+            boost: 1.0f32,
+            term_length,
+            automata,
+            rewritten_terms: Mutex::new(vec![]),
+        })
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+
+    /// Returns the pattern term.
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn max_edits(&self) -> usize {
+        self.max_edits
+    }
+
+    /// Returns the non-fuzzy prefix length. This is the number of characters
+    /// at the start of a term that must be identical (not fuzzy) to the query
+    /// term if the query is to match that term.
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    pub fn max_expansions(&self) -> usize {
+        self.max_expansions
+    }
+
+    /// Returns true if transpositions should be treated as a primitive edit
+    /// operation. If this is false, comparisons will implement the classic
+    /// Levenshtein algorithm.
+    pub fn transpositions(&self) -> bool {
+        self.transpositions
+    }
+
+    // iterates the terms within `max_edits` edits, starting after `start_term`
+    // if it is given.
+    fn term_iterator_from<T: Terms>(
+        &self,
+        terms: &T,
+        max_edits: usize,
+        start_term: Option<&[u8]>,
+    ) -> Result<Option<AutomatonTermIterEnum<T::Iterator>>> {
+        let compiled = &self.automata[max_edits];
+        match start_term {
+            Some(start_term) if compiled.automaton_type == AutomatonType::Normal => {
+                terms.intersect(compiled, Some(start_term)).map(Some)
+            }
+            _ => compiled.term_iterator(terms),
+        }
+    }
+
+    fn accepts(&self, max_edits: usize, term: &[u8]) -> bool {
+        self.automata[max_edits]
+            .run_automaton
+            .as_ref()
+            .unwrap()
+            .run(term)
+    }
+
+    /// Returns the boost of `term`, accepted by the automaton of `max_edits`,
+    /// which is a rough estimate of its similarity to the query term, `None`
+    /// if the term isn't similar at all.
+    fn term_boost(&self, term: &[u8], max_edits: usize) -> Option<f32> {
+        // the outer automaton always matches, find the exact edit distance
+        let mut ed = max_edits;
+        while ed > 0 && self.accepts(ed - 1, term) {
+            ed -= 1;
+        }
+        if ed == 0 {
+            return Some(1.0f32);
+        }
+        // count the utf8 lead bytes
+        let code_points = term.iter().filter(|b| (**b & 0xc0) != 0x80).count();
+        let min_term_length = code_points.min(self.term_length);
+        if min_term_length > ed {
+            Some(1.0f32 - ed as f32 / min_term_length as f32)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the edit distance a term must be within to be competitive
+    /// once the queue is full and its least competitive term is `bottom`.
+    fn competitive_max_edits(
+        &self,
+        max_edits: usize,
+        bottom: &ScoreTermKey,
+        last_term: Option<&[u8]>,
+    ) -> usize {
+        // the terms visited from now on are after `last_term`, so ties with
+        // the bottom term lose if they come after it
+        let term_after = last_term.map_or(false, |t| t >= bottom.term.as_slice());
+        let mut max_edits = max_edits;
+        while max_edits > 0 && self.term_length > 0 {
+            let max_boost = 1.0f32 - max_edits as f32 / self.term_length as f32;
+            match bottom.boost.partial_cmp(&max_boost) {
+                Some(Ordering::Less) => break,
+                Some(Ordering::Equal) if !term_after => break,
+                _ => {}
+            }
+            max_edits -= 1;
+        }
+        max_edits
+    }
+
+    /// Collects the top `max_expansions` terms, by boost, of all the leaves.
+    fn top_terms<C: Codec>(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+    ) -> Result<Vec<(Vec<u8>, ScoreTerm<CodecTermState<C>>)>> {
+        let mut queue: BinaryHeap<ScoreTermKey> =
+            BinaryHeap::with_capacity(self.max_expansions.min(MAX_INITIAL_QUEUE_SIZE) + 1);
+        let mut visited: HashMap<Vec<u8>, ScoreTerm<CodecTermState<C>>> = HashMap::new();
+
+        for leaf in searcher.leaves() {
+            let terms = match leaf.reader.terms(self.term.field())? {
+                Some(terms) => terms,
+                None => continue,
+            };
+            // the terms of this leaf are visited from the start, so ties with
+            // the bottom term may still be competitive
+            let mut max_edits = match queue.peek() {
+                Some(bottom) if queue.len() == self.max_expansions => {
+                    self.competitive_max_edits(self.max_edits, bottom, None)
+                }
+                _ => self.max_edits,
+            };
+            let mut iter = match self.term_iterator_from(&terms, max_edits, None)? {
+                Some(iter) => iter,
+                None => continue,
+            };
+
+            while let Some(term) = iter.next()? {
+                let boost = match self.term_boost(&term, max_edits) {
+                    Some(boost) => boost,
+                    None => continue,
+                };
+                let key = ScoreTermKey { boost, term };
+                // ignore uncompetitive hits
+                if queue.len() == self.max_expansions && key > *queue.peek().unwrap() {
+                    continue;
+                }
+
+                let state = iter.term_state()?;
+                let doc_freq = iter.doc_freq()?;
+                let total_term_freq = iter.total_term_freq()?;
+                if let Some(score_term) = visited.get_mut(&key.term) {
+                    // the term is already in the queue, only update its stats
+                    debug_assert!((score_term.boost - boost).abs() < ::std::f32::EPSILON);
+                    score_term
+                        .context
+                        .register(leaf.doc_base, state, doc_freq, total_term_freq);
+                    continue;
+                }
+
+                let mut context = TermContext {
+                    doc_freq: 0,
+                    total_term_freq: 0,
+                    states: Vec::with_capacity(1),
+                };
+                context.register(leaf.doc_base, state, doc_freq, total_term_freq);
+                visited.insert(key.term.clone(), ScoreTerm { boost, context });
+                queue.push(key);
+                // possibly drop entries from queue
+                if queue.len() > self.max_expansions {
+                    let dropped = queue.pop().unwrap();
+                    visited.remove(&dropped.term);
+                }
+
+                // once the queue is full, terms with too many edits can't be
+                // competitive any more, continue with a smaller automaton.
+                if queue.len() == self.max_expansions {
+                    let last_term = iter.term()?.to_vec();
+                    let bottom = queue.peek().unwrap();
+                    let edits = self.competitive_max_edits(max_edits, bottom, Some(&last_term));
+                    if edits < max_edits {
+                        max_edits = edits;
+                        iter = match self.term_iterator_from(&terms, max_edits, Some(&last_term))? {
+                            Some(iter) => iter,
+                            None => break,
+                        };
+                    }
+                }
+            }
+        }
+
+        let mut score_terms: Vec<_> = visited.into_iter().collect();
+        score_terms.sort_by(|t1, t2| t1.0.cmp(&t2.0));
+
+        let rewritten = score_terms
+            .iter()
+            .map(|(term, score_term)| (term.clone(), score_term.boost))
+            .collect();
+        if let Ok(mut rewritten_terms) = self.rewritten_terms.lock() {
+            *rewritten_terms = rewritten;
+        }
+        Ok(score_terms)
+    }
+
+    fn rewritten_terms(&self) -> Vec<(Vec<u8>, f32)> {
+        match self.rewritten_terms.lock() {
+            Ok(terms) => terms.clone(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn term_queries(&self, terms: Vec<(Vec<u8>, f32)>) -> Vec<TermQuery> {
+        terms
+            .into_iter()
+            .map(|(term, boost)| {
+                TermQuery::new(
+                    Term::new(self.term.field.clone(), term),
+                    self.boost * boost,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    /// Rewrites the query to the `TermQuery`s of its top terms in the index
+    /// of `searcher`, boosted by their similarity to the query term.
+    pub fn rewrite<C: Codec>(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Vec<TermQuery>> {
+        let terms = self
+            .top_terms(searcher)?
+            .into_iter()
+            .map(|(term, score_term)| (term, score_term.boost))
+            .collect();
+        Ok(self.term_queries(terms))
+    }
+}
+
+impl<C: Codec> Query<C> for FuzzyQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let score_terms = self.top_terms(searcher)?;
+
+        // blend the statistics: the doc freq and the total term freq are the
+        // max of all doc freqs and total term freqs
+        let mut doc_freq = 0;
+        let mut total_term_freq = 0i64;
+        for (_, score_term) in &score_terms {
+            doc_freq = max(doc_freq, score_term.context.doc_freq);
+            if total_term_freq >= 0 && score_term.context.total_term_freq >= 0 {
+                total_term_freq = max(total_term_freq, score_term.context.total_term_freq);
+            } else {
+                total_term_freq = -1;
+            }
+        }
+
+        let mut weights = Vec::with_capacity(score_terms.len());
+        for (term, score_term) in score_terms {
+            let context = TermContext {
+                doc_freq,
+                total_term_freq,
+                states: score_term.context.states,
+            };
+            let query = TermQuery::new(
+                Term::new(self.term.field.clone(), term),
+                self.boost * score_term.boost,
+                None,
+            );
+            weights.push(query.create_weight_with_context(searcher, needs_scores, &context)?);
+        }
+        Ok(Box::new(BooleanWeight::new(vec![], weights, needs_scores)))
+    }
+
+    /// Returns the terms of the latest rewrite of the query, by
+    /// `create_weight` or `rewrite`, empty if it is not rewritten yet.
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.term_queries(self.rewritten_terms())
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for FuzzyQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FuzzyQuery(field: {}, term: {}, max_edits: {}, prefix_length: {}, max_expansions: \
+             {}, transpositions: {}, boost: {})",
+            self.term.field(),
+            String::from_utf8_lossy(&self.term.bytes),
+            self.max_edits,
+            self.prefix_length,
+            self.max_expansions,
+            self.transpositions,
+            self.boost
+        )
+    }
+}
+
+struct ScoreTerm<S: TermState> {
+    boost: f32,
+    context: TermContext<S>,
+}
+
+// Queue entry of a collected term, the least competitive term, the one with
+// the lowest boost and then the greatest term, is the greatest entry.
+struct ScoreTermKey {
+    boost: f32,
+    term: Vec<u8>,
+}
+
+impl PartialEq for ScoreTermKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoreTermKey {}
+
+impl PartialOrd for ScoreTermKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoreTermKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .boost
+            .partial_cmp(&self.boost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.term.cmp(&other.term))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::Fieldable;
+    use core::index::tests::*;
+    use core::search::collector::TopDocsCollector;
+    use core::search::IndexSearcher;
+
+    fn fuzzy(text: &str, max_edits: usize, prefix_length: usize) -> FuzzyQuery {
+        FuzzyQuery::with_options(
+            Term::new("title".into(), text.as_bytes().to_vec()),
+            max_edits,
+            prefix_length,
+            FuzzyQuery::DEFAULT_MAX_EXPANSIONS,
+            true,
+        )
+        .unwrap()
+    }
+
+    fn accepts(query: &FuzzyQuery, text: &str) -> bool {
+        query.accepts(query.max_edits, text.as_bytes())
+    }
+
+    #[test]
+    fn test_fuzzy_automata() {
+        let query = fuzzy("lucene", 2, 0);
+        assert!(accepts(&query, "lucene"));
+        assert!(accepts(&query, "lucine"));
+        assert!(accepts(&query, "ulcene"));
+        assert!(accepts(&query, "lucenexx"));
+        assert!(!accepts(&query, "lucenexxx"));
+
+        let query = fuzzy("lucene", 1, 2);
+        assert!(accepts(&query, "lucine"));
+        assert!(!accepts(&query, "ulcene"));
+        assert!(!accepts(&query, "lacene"));
+        assert!(!accepts(&query, "lucinx"));
+
+        assert!(FuzzyQuery::with_options(
+            Term::new("title".into(), b"lucene".to_vec()),
+            3,
+            0,
+            FuzzyQuery::DEFAULT_MAX_EXPANSIONS,
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_term_boost() {
+        let query = fuzzy("lucene", 2, 0);
+        assert_eq!(query.term_boost(b"lucene", 2), Some(1.0));
+        assert_eq!(query.term_boost(b"lucine", 2), Some(1.0 - 1.0 / 6.0));
+        assert_eq!(query.term_boost(b"lucen", 2), Some(1.0 - 1.0 / 5.0));
+        assert_eq!(query.term_boost(b"lacine", 2), Some(1.0 - 2.0 / 6.0));
+
+        let query = fuzzy("ab", 2, 0);
+        assert_eq!(query.term_boost(b"ab", 2), Some(1.0));
+        assert_eq!(query.term_boost(b"ba", 2), Some(0.5));
+        assert_eq!(query.term_boost(b"b", 2), None);
+        assert_eq!(query.term_boost(b"bc", 2), None);
+    }
+
+    #[test]
+    fn test_score_term_order() {
+        let mut queue = BinaryHeap::new();
+        for (boost, term) in &[(0.5f32, "b"), (1.0, "c"), (0.5, "a"), (0.8, "d")] {
+            queue.push(ScoreTermKey {
+                boost: *boost,
+                term: term.as_bytes().to_vec(),
+            });
+        }
+        let order: Vec<Vec<u8>> = ::std::iter::from_fn(|| queue.pop().map(|k| k.term)).collect();
+        assert_eq!(
+            order,
+            vec![b"b".to_vec(), b"a".to_vec(), b"d".to_vec(), b"c".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = ["abcd", "abce", "abxd", "zzzz", "abcdef"]
+            .iter()
+            .map(|text| vec![text_field("body", text)])
+            .collect();
+        let index = TestIndex::new(docs, 2);
+        let searcher = index.searcher();
+
+        // the queue must not be allocated for all the expansions
+        let query = FuzzyQuery::with_options(
+            Term::new("body".into(), b"abcd".to_vec()),
+            1,
+            0,
+            usize::max_value(),
+            true,
+        )
+        .unwrap();
+        assert!(Query::<CodecEnum>::extract_terms(&query).is_empty());
+
+        let mut collector = TopDocsCollector::new(10);
+        searcher.search(&query, &mut collector).unwrap();
+        assert_eq!(collector.top_docs().total_hits(), 3);
+
+        let terms: Vec<(Vec<u8>, f32)> = Query::<CodecEnum>::extract_terms(&query)
+            .iter()
+            .map(|q| (q.term.bytes.clone(), q.boost))
+            .collect();
+        assert_eq!(
+            terms,
+            vec![
+                (b"abcd".to_vec(), 1.0),
+                (b"abce".to_vec(), 0.75),
+                (b"abxd".to_vec(), 0.75)
+            ]
+        );
+        let rewritten = query.rewrite::<CodecEnum>(&searcher).unwrap();
+        assert_eq!(rewritten.len(), 3);
+        assert_eq!(rewritten[1].term.bytes, b"abce".to_vec());
+    }
+
+    #[test]
+    fn test_rewrite_resumes_after_rejected_term() {
+        // "aabbbb" is the first term within 2 edits of "bbbb", the smaller
+        // automaton the iteration resumes with rejects it, as well as the
+        // prefix of the block tree block holding it
+        let mut texts: Vec<String> = (0..50).map(|i| format!("aa{:02}", i)).collect();
+        texts.extend(["aabbbb", "bbbc", "zzzz"].iter().map(|t| t.to_string()));
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = texts
+            .iter()
+            .map(|text| vec![text_field("body", text)])
+            .collect();
+        let index = TestIndex::new(docs, usize::max_value());
+        let searcher = index.searcher();
+
+        let query =
+            FuzzyQuery::with_options(Term::new("body".into(), b"bbbb".to_vec()), 2, 0, 1, true)
+                .unwrap();
+        let rewritten: Vec<(Vec<u8>, f32)> = query
+            .rewrite::<CodecEnum>(&searcher)
+            .unwrap()
+            .into_iter()
+            .map(|q| (q.term.bytes, q.boost))
+            .collect();
+        assert_eq!(rewritten, vec![(b"bbbc".to_vec(), 0.75)]);
+    }
+}
@@ -37,7 +37,7 @@ impl<C: Codec> Query<C> for MatchAllDocsQuery {
     }
 
     fn extract_terms(&self) -> Vec<TermQuery> {
-        unimplemented!()
+        vec![]
     }
 
     fn as_any(&self) -> &dyn (::std::any::Any) {
@@ -114,6 +114,83 @@ impl fmt::Display for MatchAllDocsWeight {
     }
 }
 
+pub const MATCH_NO_DOCS: &str = "match_no_docs";
+
+/// A query that matches no documents, e.g. a range whose exclusive bounds
+/// leave no value to match.
+pub struct MatchNoDocsQuery {
+    reason: String,
+}
+
+impl MatchNoDocsQuery {
+    pub fn new(reason: String) -> MatchNoDocsQuery {
+        MatchNoDocsQuery { reason }
+    }
+}
+
+impl<C: Codec> Query<C> for MatchNoDocsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MatchNoDocsWeight {
+            reason: self.reason.clone(),
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for MatchNoDocsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MatchNoDocsQuery(reason: {})", self.reason)
+    }
+}
+
+struct MatchNoDocsWeight {
+    reason: String,
+}
+
+impl<C: Codec> Weight<C> for MatchNoDocsWeight {
+    fn create_scorer(
+        &self,
+        _leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        Ok(None)
+    }
+
+    fn query_type(&self) -> &'static str {
+        MATCH_NO_DOCS
+    }
+
+    fn normalize(&mut self, _norm: f32, _boost: f32) {}
+
+    fn value_for_normalization(&self) -> f32 {
+        0f32
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, _reader: &LeafReaderContext<'_, C>, _doc: DocId) -> Result<Explanation> {
+        Ok(Explanation::new(false, 0f32, self.reason.clone(), vec![]))
+    }
+}
+
+impl fmt::Display for MatchNoDocsWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MatchNoDocsWeight(reason: {})", self.reason)
+    }
+}
+
 /// a `DocIterator` for all docs
 pub struct AllDocsIterator {
     doc: DocId,
@@ -14,6 +14,10 @@
 #[macro_use]
 pub mod spans;
 
+mod automaton_query;
+
+pub use self::automaton_query::*;
+
 mod boolean_query;
 
 pub use self::boolean_query::*;
@@ -26,10 +30,30 @@ mod filter_query;
 
 pub use self::filter_query::*;
 
+mod function_score_query;
+
+pub use self::function_score_query::*;
+
+mod fuzzy_query;
+
+pub use self::fuzzy_query::*;
+
 mod match_all_query;
 
 pub use self::match_all_query::*;
 
+mod multi_phrase_query;
+
+pub use self::multi_phrase_query::*;
+
+mod multi_term_query;
+
+pub use self::multi_term_query::*;
+
+mod payload_score_query;
+
+pub use self::payload_score_query::*;
+
 mod phrase_query;
 
 pub use self::phrase_query::*;
@@ -38,14 +62,30 @@ mod point_range_query;
 
 pub use self::point_range_query::*;
 
+mod query_parser;
+
+pub use self::query_parser::*;
+
 mod query_string;
 
 pub use self::query_string::*;
 
+mod score_function;
+
+pub use self::score_function::*;
+
 mod term_query;
 
 pub use self::term_query::*;
 
+mod term_in_set_query;
+
+pub use self::term_in_set_query::*;
+
+mod term_range_query;
+
+pub use self::term_range_query::*;
+
 mod disjunction_max_query;
 
 pub use self::disjunction_max_query::*;
@@ -90,8 +130,14 @@ use std::hash::{Hash, Hasher};
 /// * [`ConstantScoreQuery`](match_all/struct.ConstantScoreQuery.html)
 /// * [`DisjunctionMaxQuery`](disjunction/struct.DisjunctionMaxQuery.html)
 /// * [`MatchAllDocsQuery`](match_all/struct.MatchAllDocsQuery.html)
+/// * [`MatchNoDocsQuery`]
+/// * [`PrefixQuery`], [`WildcardQuery`] and [`RegexpQuery`], see also [`AutomatonQuery`]
+/// * [`FuzzyQuery`]
+/// * [`TermRangeQuery`]
+/// * [`TermInSetQuery`]
 ///
-/// See also the family of [`Span Queries`](spans/index.html)
+/// See also the family of [`Span Queries`](spans/index.html), and [`QueryParser`] to build
+/// the queries from a query string.
 pub trait Query<C: Codec>: Display {
     /// Create new `Scorer` based on query.
     fn create_weight(
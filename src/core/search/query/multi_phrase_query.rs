@@ -0,0 +1,543 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::{ErrorKind, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+use core::codec::{Codec, CodecPostingIterator, CodecTermState};
+use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::codec::{TermIterator, Terms};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{
+    term_positions_cost, ExactPhraseScorer, PostingsAndFreq, Query, SloppyPhraseScorer, TermQuery,
+    Weight,
+};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::{SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::search::{DocIterator, Payload};
+use core::util::{DisiPriorityQueue, DocId, KeyedContext};
+
+pub const MULTI_PHRASE: &str = "multi_phrase";
+
+/// A generalized version of `PhraseQuery`, with the possibility of adding
+/// more than one term at the same position that are treated as a disjunction
+/// (OR).
+///
+/// To use this class to search for the phrase "Microsoft app*" first create
+/// a term array with the term "microsoft", then find all terms that have
+/// "app" as prefix and use them as the alternatives of the second position.
+/// Synonyms coming out of a token stream are handled the same way, the
+/// terms sharing a position are the alternatives of that position.
+#[derive(Clone, Debug)]
+pub struct MultiPhraseQuery {
+    field: String,
+    term_arrays: Vec<Vec<Term>>,
+    positions: Vec<i32>,
+    slop: i32,
+    ctx: Option<KeyedContext>,
+}
+
+impl MultiPhraseQuery {
+    pub fn new<S: Into<Option<KeyedContext>>>(
+        term_arrays: Vec<Vec<Term>>,
+        positions: Vec<i32>,
+        slop: i32,
+        ctx: S,
+    ) -> Result<MultiPhraseQuery> {
+        if term_arrays.len() != positions.len() {
+            bail!(ErrorKind::IllegalArgument(
+                "Must have as many term arrays as positions".into()
+            ));
+        }
+        if slop < 0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "Slop must be >= 0, got {}",
+                slop
+            )));
+        }
+        if term_arrays.len() < 2 {
+            bail!(ErrorKind::IllegalArgument(
+                "multi phrase query positions should not be less than 2!".into()
+            ));
+        }
+        if term_arrays.iter().any(Vec::is_empty) {
+            bail!(ErrorKind::IllegalArgument(
+                "multi phrase query positions must have at least one term!".into()
+            ));
+        }
+        let field = term_arrays[0][0].field.clone();
+        for term in term_arrays.iter().flat_map(|terms| terms.iter()) {
+            if term.field != field {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "All phrase terms must be in the same field ({}): {:?}",
+                    field, term
+                )));
+            }
+        }
+        for i in 1..positions.len() {
+            if positions[i - 1] > positions[i] {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "Positions should not go backwards, got {} before {}",
+                    positions[i - 1],
+                    positions[i]
+                )));
+            }
+        }
+        // normalize positions
+        let mut positions = positions;
+        let first = positions[0];
+        for pos in &mut positions {
+            *pos -= first;
+        }
+
+        Ok(MultiPhraseQuery {
+            field,
+            term_arrays,
+            positions,
+            slop,
+            ctx: ctx.into(),
+        })
+    }
+
+    /// Create a query whose term arrays are at consecutive positions.
+    pub fn build<S: Into<Option<KeyedContext>>>(
+        term_arrays: Vec<Vec<Term>>,
+        slop: i32,
+        ctx: S,
+    ) -> Result<MultiPhraseQuery> {
+        let positions = (0..term_arrays.len() as i32).collect();
+        Self::new(term_arrays, positions, slop, ctx)
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn term_arrays(&self) -> &[Vec<Term>] {
+        &self.term_arrays
+    }
+
+    pub fn positions(&self) -> &[i32] {
+        &self.positions
+    }
+
+    pub fn slop(&self) -> i32 {
+        self.slop
+    }
+}
+
+impl<C: Codec> Query<C> for MultiPhraseQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let max_doc = i64::from(searcher.max_doc());
+        let mut term_states = Vec::with_capacity(self.term_arrays.len());
+        let mut term_stats: Vec<TermStatistics> = Vec::new();
+
+        for terms in &self.term_arrays {
+            let mut states = Vec::with_capacity(terms.len());
+            for term in terms {
+                let term_context = searcher.term_state(term)?;
+
+                term_stats.push(searcher.term_statistics(term, term_context.as_ref()));
+                states.push(term_context.term_states());
+            }
+            term_states.push(states);
+        }
+
+        let collection_stats = if needs_scores {
+            searcher.collections_statistics(&self.field)?
+        } else {
+            CollectionStatistics::new(self.field.clone(), max_doc, -1, -1, -1)
+        };
+
+        let similarity = searcher.similarity(&self.field, needs_scores);
+
+        let sim_weight =
+            similarity.compute_weight(&collection_stats, &term_stats, self.ctx.as_ref(), 1.0f32);
+
+        Ok(Box::new(MultiPhraseWeight {
+            field: self.field.clone(),
+            term_arrays: self.term_arrays.clone(),
+            positions: self.positions.clone(),
+            slop: self.slop,
+            similarity,
+            sim_weight,
+            needs_scores,
+            term_states,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        let mut term_query_list: Vec<TermQuery> = vec![];
+        for term in self.term_arrays.iter().flat_map(|terms| terms.iter()) {
+            let term_query = TermQuery::new(term.clone(), 1.0f32, None);
+            if !term_query_list.contains(&term_query) {
+                term_query_list.push(term_query);
+            }
+        }
+        term_query_list
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for MultiPhraseQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MultiPhraseQuery(field: {}, term_arrays: {:?}, positions: {:?}, slop: {})",
+            &self.field, &self.term_arrays, &self.positions, self.slop,
+        )
+    }
+}
+
+struct MultiPhraseWeight<C: Codec> {
+    field: String,
+    term_arrays: Vec<Vec<Term>>,
+    positions: Vec<i32>,
+    slop: i32,
+    similarity: Box<dyn Similarity<C>>,
+    sim_weight: Box<dyn SimWeight<C>>,
+    needs_scores: bool,
+    // term states of each term of each position
+    term_states: Vec<Vec<HashMap<DocId, CodecTermState<C>>>>,
+}
+
+impl<C: Codec> MultiPhraseWeight<C> {
+    /// Returns the postings of each position with the total match cost, or
+    /// `None` if one of the positions has no term in this segment.
+    fn phrase_postings(
+        &self,
+        reader_context: &LeafReaderContext<'_, C>,
+    ) -> Result<
+        Option<(
+            Vec<PostingsAndFreq<UnionPostingIterator<CodecPostingIterator<C>>>>,
+            f32,
+        )>,
+    > {
+        let mut term_iter = if let Some(field_terms) = reader_context.reader.terms(&self.field)? {
+            debug_assert!(
+                field_terms.has_positions()?,
+                format!(
+                    "field {} was indexed without position data; cannot run MultiPhraseQuery \
+                     (phrase={:?})",
+                    self.field, self.term_arrays
+                )
+            );
+            field_terms.iterator()?
+        } else {
+            return Ok(None);
+        };
+
+        let mut total_match_cost = 0f32;
+        let mut postings_freqs = Vec::with_capacity(self.term_arrays.len());
+        for (i, terms) in self.term_arrays.iter().enumerate() {
+            let mut postings = Vec::with_capacity(terms.len());
+            for (j, term) in terms.iter().enumerate() {
+                if let Some(state) = self.term_states[i][j].get(&reader_context.doc_base) {
+                    term_iter.seek_exact_state(term.bytes.as_ref(), state)?;
+                    total_match_cost += term_positions_cost(&mut term_iter)?;
+
+                    postings.push(term_iter.postings_with_flags(PostingIteratorFlags::POSITIONS)?);
+                }
+            }
+
+            if postings.is_empty() {
+                // none of the terms at this position is in the segment
+                return Ok(None);
+            }
+
+            postings_freqs.push(PostingsAndFreq::with_terms(
+                UnionPostingIterator::new(postings),
+                self.positions[i],
+                terms,
+            ));
+        }
+        Ok(Some((postings_freqs, total_match_cost)))
+    }
+}
+
+impl<C: Codec> Weight<C> for MultiPhraseWeight<C> {
+    fn create_scorer(
+        &self,
+        reader_context: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let (mut postings_freqs, total_match_cost) = match self.phrase_postings(reader_context)? {
+            Some(postings) => postings,
+            None => return Ok(None),
+        };
+
+        let sim_scorer = self.sim_weight.sim_scorer(reader_context.reader)?;
+        let scorer: Box<dyn Scorer> = if self.slop == 0 {
+            // sort by increasing docFreq order
+            // optimize exact case
+            postings_freqs.sort();
+            Box::new(ExactPhraseScorer::new(
+                postings_freqs,
+                sim_scorer,
+                self.needs_scores,
+                total_match_cost,
+            ))
+        } else {
+            Box::new(SloppyPhraseScorer::new(
+                postings_freqs,
+                self.slop,
+                sim_scorer,
+                self.needs_scores,
+                total_match_cost,
+            ))
+        };
+        Ok(Some(scorer))
+    }
+
+    fn query_type(&self) -> &'static str {
+        MULTI_PHRASE
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.sim_weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.sim_weight.get_value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        if let Some((mut postings_freqs, total_match_cost)) = self.phrase_postings(reader)? {
+            let sim_scorer = self.sim_weight.sim_scorer(reader.reader)?;
+            let freq = if self.slop == 0 {
+                postings_freqs.sort();
+                let mut scorer = ExactPhraseScorer::new(
+                    postings_freqs,
+                    sim_scorer,
+                    self.needs_scores,
+                    total_match_cost,
+                );
+                if scorer.advance(doc)? == doc {
+                    Some(scorer.freq() as f32)
+                } else {
+                    None
+                }
+            } else {
+                let mut scorer = SloppyPhraseScorer::new(
+                    postings_freqs,
+                    self.slop,
+                    sim_scorer,
+                    self.needs_scores,
+                    total_match_cost,
+                );
+                if scorer.advance(doc)? == doc {
+                    Some(scorer.sloppy_freq())
+                } else {
+                    None
+                }
+            };
+
+            if let Some(freq) = freq {
+                let freq_expl =
+                    Explanation::new(true, freq, format!("phraseFreq={}", freq), vec![]);
+                let score_expl = self.sim_weight.explain(reader.reader, doc, freq_expl)?;
+
+                return Ok(Explanation::new(
+                    true,
+                    score_expl.value(),
+                    format!("weight({} in {}), result of:", self, doc),
+                    vec![score_expl],
+                ));
+            }
+        }
+
+        Ok(Explanation::new(
+            false,
+            0.0f32,
+            "no matching term".to_string(),
+            vec![],
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for MultiPhraseWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MultiPhraseWeight(field: {}, term_arrays: {:?}, positions: {:?}, similarity: {}, \
+             need_score: {})",
+            &self.field, &self.term_arrays, &self.positions, &self.similarity, self.needs_scores
+        )
+    }
+}
+
+/// Takes the logical union of multiple `PostingIterator`s, the positions of
+/// a doc are the merged positions of all the sub iterators on that doc.
+///
+/// Offsets and payloads are not supported.
+pub struct UnionPostingIterator<T: PostingIterator> {
+    docs_queue: DisiPriorityQueue<T>,
+    cost: usize,
+    // the sorted positions of the current doc, loaded on first `next_position`
+    positions: Vec<i32>,
+    positions_upto: usize,
+    positions_doc: DocId,
+}
+
+impl<T: PostingIterator> UnionPostingIterator<T> {
+    pub fn new(subs: Vec<T>) -> Self {
+        debug_assert!(!subs.is_empty());
+        let cost = subs.iter().map(DocIterator::cost).sum();
+        UnionPostingIterator {
+            docs_queue: DisiPriorityQueue::new(subs),
+            cost,
+            positions: Vec::new(),
+            positions_upto: 0,
+            positions_doc: -2,
+        }
+    }
+
+    fn load_positions(&mut self) -> Result<()> {
+        self.positions.clear();
+        self.positions_upto = 0;
+        let mut sub = self.docs_queue.top_list();
+        loop {
+            let freq = sub.inner().freq()?;
+            for _ in 0..freq {
+                let position = sub.inner_mut().next_position()?;
+                self.positions.push(position);
+            }
+            if sub.next.is_null() {
+                break;
+            }
+            unsafe {
+                sub = &mut *sub.next;
+            }
+        }
+        self.positions.sort();
+        self.positions_doc = self.doc_id();
+        Ok(())
+    }
+}
+
+impl<T: PostingIterator> PostingIterator for UnionPostingIterator<T> {
+    fn freq(&self) -> Result<i32> {
+        let doc = self.doc_id();
+        if doc == self.positions_doc {
+            return Ok(self.positions.len() as i32);
+        }
+        let mut freq = 0;
+        for sub in &self.docs_queue {
+            if sub.doc_id() == doc {
+                freq += sub.freq()?;
+            }
+        }
+        Ok(freq)
+    }
+
+    fn next_position(&mut self) -> Result<i32> {
+        if self.positions_doc != self.doc_id() {
+            self.load_positions()?;
+        }
+        debug_assert!(self.positions_upto < self.positions.len());
+        let position = self.positions[self.positions_upto];
+        self.positions_upto += 1;
+        Ok(position)
+    }
+
+    fn start_offset(&self) -> Result<i32> {
+        Ok(-1)
+    }
+
+    fn end_offset(&self) -> Result<i32> {
+        Ok(-1)
+    }
+
+    fn payload(&self) -> Result<Payload> {
+        Ok(Payload::new())
+    }
+}
+
+impl<T: PostingIterator> DocIterator for UnionPostingIterator<T> {
+    fn doc_id(&self) -> DocId {
+        self.docs_queue.peek().doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let current_doc = self.docs_queue.peek().doc;
+        loop {
+            self.docs_queue.peek_mut().next_doc()?;
+            if self.docs_queue.peek().doc != current_doc {
+                break;
+            }
+        }
+        Ok(self.docs_queue.peek().doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        loop {
+            self.docs_queue.peek_mut().advance(target)?;
+            if self.docs_queue.peek().doc >= target {
+                break;
+            }
+        }
+        Ok(self.docs_queue.peek().doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    #[test]
+    fn test_multi_phrase_query_validation() {
+        let term = |text: &str| Term::new("title".into(), text.as_bytes().to_vec());
+
+        let query = MultiPhraseQuery::new(
+            vec![vec![term("fast")], vec![term("car"), term("automobile")]],
+            vec![2, 3],
+            0,
+            None,
+        )
+        .unwrap();
+        assert_eq!(query.positions(), &[0, 1]);
+        assert_eq!(Query::<TestCodec>::extract_terms(&query).len(), 3);
+
+        assert!(MultiPhraseQuery::build(vec![vec![term("fast")]], 0, None).is_err());
+        assert!(MultiPhraseQuery::build(vec![vec![term("fast")], vec![]], 0, None).is_err());
+        assert!(MultiPhraseQuery::new(
+            vec![vec![term("fast")], vec![term("car")]],
+            vec![1, 0],
+            0,
+            None
+        )
+        .is_err());
+        let other = Term::new("body".into(), b"car".to_vec());
+        assert!(MultiPhraseQuery::build(vec![vec![term("fast")], vec![other]], 0, None).is_err());
+    }
+}
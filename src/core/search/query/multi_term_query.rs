@@ -0,0 +1,419 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::{Codec, PostingIteratorFlags, SeekStatus, TermIterator, Terms};
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{
+    AutomatonQuery, PrefixQuery, RegexpQuery, TermInSetTermIterator, TermRangeQuery,
+    TermRangeTermIterator, Weight, WildcardQuery,
+};
+use core::search::scorer::{ConstantScoreScorer, DisjunctionSumScorer, Scorer};
+use core::search::{DocIdSet, DocIterator};
+use core::util::automaton::AutomatonTermIterEnum;
+use core::util::{DocId, DocIdSetBuilder};
+
+use error::Result;
+
+/// Up to this many matching terms the constant score rewrite builds a
+/// disjunction over the postings, beyond it the matching docs are collected
+/// into a doc id set.
+pub const BOOLEAN_REWRITE_TERM_COUNT_THRESHOLD: usize = 16;
+
+/// An abstract `Query` that matches documents containing a subset of terms
+/// provided by a `FilteredTermIterator` enumeration.
+///
+/// The terms of each segment are enumerated by the `term_iterator`, matching
+/// documents are scored with a constant score equal to the query boost (see
+/// `MultiTermQueryConstantScoreWeight`).
+pub trait MultiTermQuery: fmt::Display {
+    /// Returns the field name for this query.
+    fn field(&self) -> &str;
+
+    /// Construct the enumeration to be used, expanding the pattern term.
+    /// Returns `None` if no term can match.
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>>;
+}
+
+/// Displays the bytes of a term exactly, as they are part of the cache keys
+/// of the queries: a quoted and escaped string if they are valid UTF-8, their
+/// hex dump otherwise.
+pub(crate) struct TermText<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for TermText<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match ::std::str::from_utf8(self.0) {
+            Ok(text) => write!(f, "{:?}", text),
+            Err(_) => {
+                write!(f, "0x")?;
+                for b in self.0 {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The `TermIterator`s used by the `MultiTermQuery` implementations.
+pub enum MultiTermIterEnum<T: TermIterator> {
+    Automaton(AutomatonTermIterEnum<T>),
+    TermRange(TermRangeTermIterator<T>),
+    TermInSet(TermInSetTermIterator<T>),
+}
+
+impl<T: TermIterator> TermIterator for MultiTermIterEnum<T> {
+    type Postings = T::Postings;
+    type TermState = T::TermState;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.next(),
+            MultiTermIterEnum::TermRange(i) => i.next(),
+            MultiTermIterEnum::TermInSet(i) => i.next(),
+        }
+    }
+
+    fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.seek_exact(text),
+            MultiTermIterEnum::TermRange(i) => i.seek_exact(text),
+            MultiTermIterEnum::TermInSet(i) => i.seek_exact(text),
+        }
+    }
+
+    fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.seek_ceil(text),
+            MultiTermIterEnum::TermRange(i) => i.seek_ceil(text),
+            MultiTermIterEnum::TermInSet(i) => i.seek_ceil(text),
+        }
+    }
+
+    fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.seek_exact_ord(ord),
+            MultiTermIterEnum::TermRange(i) => i.seek_exact_ord(ord),
+            MultiTermIterEnum::TermInSet(i) => i.seek_exact_ord(ord),
+        }
+    }
+
+    fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.seek_exact_state(text, state),
+            MultiTermIterEnum::TermRange(i) => i.seek_exact_state(text, state),
+            MultiTermIterEnum::TermInSet(i) => i.seek_exact_state(text, state),
+        }
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.term(),
+            MultiTermIterEnum::TermRange(i) => i.term(),
+            MultiTermIterEnum::TermInSet(i) => i.term(),
+        }
+    }
+
+    fn ord(&self) -> Result<i64> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.ord(),
+            MultiTermIterEnum::TermRange(i) => i.ord(),
+            MultiTermIterEnum::TermInSet(i) => i.ord(),
+        }
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.doc_freq(),
+            MultiTermIterEnum::TermRange(i) => i.doc_freq(),
+            MultiTermIterEnum::TermInSet(i) => i.doc_freq(),
+        }
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.total_term_freq(),
+            MultiTermIterEnum::TermRange(i) => i.total_term_freq(),
+            MultiTermIterEnum::TermInSet(i) => i.total_term_freq(),
+        }
+    }
+
+    fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.postings_with_flags(flags),
+            MultiTermIterEnum::TermRange(i) => i.postings_with_flags(flags),
+            MultiTermIterEnum::TermInSet(i) => i.postings_with_flags(flags),
+        }
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        match self {
+            MultiTermIterEnum::Automaton(i) => i.term_state(),
+            MultiTermIterEnum::TermRange(i) => i.term_state(),
+            MultiTermIterEnum::TermInSet(i) => i.term_state(),
+        }
+    }
+}
+
+/// The `MultiTermQuery`s expanding a pattern, for the queries that wrap any
+/// of them, e.g. `SpanMultiTermQueryWrapper`.
+pub enum MultiTermQueryEnum {
+    Automaton(AutomatonQuery),
+    Prefix(PrefixQuery),
+    Wildcard(WildcardQuery),
+    Regexp(RegexpQuery),
+    TermRange(TermRangeQuery),
+}
+
+impl MultiTermQuery for MultiTermQueryEnum {
+    fn field(&self) -> &str {
+        match self {
+            MultiTermQueryEnum::Automaton(q) => q.field(),
+            MultiTermQueryEnum::Prefix(q) => q.field(),
+            MultiTermQueryEnum::Wildcard(q) => q.field(),
+            MultiTermQueryEnum::Regexp(q) => q.field(),
+            MultiTermQueryEnum::TermRange(q) => q.field(),
+        }
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        match self {
+            MultiTermQueryEnum::Automaton(q) => q.term_iterator(terms),
+            MultiTermQueryEnum::Prefix(q) => q.term_iterator(terms),
+            MultiTermQueryEnum::Wildcard(q) => q.term_iterator(terms),
+            MultiTermQueryEnum::Regexp(q) => q.term_iterator(terms),
+            MultiTermQueryEnum::TermRange(q) => q.term_iterator(terms),
+        }
+    }
+}
+
+impl fmt::Display for MultiTermQueryEnum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MultiTermQueryEnum::Automaton(q) => write!(f, "{}", q),
+            MultiTermQueryEnum::Prefix(q) => write!(f, "{}", q),
+            MultiTermQueryEnum::Wildcard(q) => write!(f, "{}", q),
+            MultiTermQueryEnum::Regexp(q) => write!(f, "{}", q),
+            MultiTermQueryEnum::TermRange(q) => write!(f, "{}", q),
+        }
+    }
+}
+
+impl From<AutomatonQuery> for MultiTermQueryEnum {
+    fn from(q: AutomatonQuery) -> Self {
+        MultiTermQueryEnum::Automaton(q)
+    }
+}
+
+impl From<PrefixQuery> for MultiTermQueryEnum {
+    fn from(q: PrefixQuery) -> Self {
+        MultiTermQueryEnum::Prefix(q)
+    }
+}
+
+impl From<WildcardQuery> for MultiTermQueryEnum {
+    fn from(q: WildcardQuery) -> Self {
+        MultiTermQueryEnum::Wildcard(q)
+    }
+}
+
+impl From<RegexpQuery> for MultiTermQueryEnum {
+    fn from(q: RegexpQuery) -> Self {
+        MultiTermQueryEnum::Regexp(q)
+    }
+}
+
+impl From<TermRangeQuery> for MultiTermQueryEnum {
+    fn from(q: TermRangeQuery) -> Self {
+        MultiTermQueryEnum::TermRange(q)
+    }
+}
+
+/// A `Weight` for `MultiTermQuery`s that scores every matching document with
+/// the query boost.
+///
+/// If the segment has few matching terms (see
+/// `BOOLEAN_REWRITE_TERM_COUNT_THRESHOLD`) the postings are merged on the fly,
+/// otherwise the matching documents are first collected into a
+/// `DocIdSetBuilder`.
+pub struct MultiTermQueryConstantScoreWeight<Q: MultiTermQuery> {
+    query: Q,
+    query_type: &'static str,
+    boost: f32,
+    weight: f32,
+    norm: f32,
+    needs_scores: bool,
+}
+
+impl<Q: MultiTermQuery> MultiTermQueryConstantScoreWeight<Q> {
+    pub fn new(
+        query: Q,
+        query_type: &'static str,
+        boost: f32,
+        needs_scores: bool,
+    ) -> MultiTermQueryConstantScoreWeight<Q> {
+        MultiTermQueryConstantScoreWeight {
+            query,
+            query_type,
+            boost,
+            weight: boost,
+            norm: 1.0f32,
+            needs_scores,
+        }
+    }
+
+    // collects the postings of at most `threshold` terms, returns true if
+    // the iterator is exhausted.
+    fn collect_terms<T: TermIterator>(
+        iter: &mut T,
+        threshold: usize,
+        postings: &mut Vec<T::Postings>,
+    ) -> Result<bool> {
+        for _ in 0..threshold {
+            if iter.next()?.is_none() {
+                return Ok(true);
+            }
+            postings.push(iter.postings_with_flags(PostingIteratorFlags::NONE)?);
+        }
+        Ok(iter.next()?.is_none())
+    }
+}
+
+impl<C: Codec, Q: MultiTermQuery + 'static> Weight<C> for MultiTermQueryConstantScoreWeight<Q> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let terms = match reader.reader.terms(self.query.field())? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+        let mut iter = match self.query.term_iterator(&terms)? {
+            Some(iter) => iter,
+            None => return Ok(None),
+        };
+
+        let mut postings = Vec::with_capacity(BOOLEAN_REWRITE_TERM_COUNT_THRESHOLD);
+        if Self::collect_terms(
+            &mut iter,
+            BOOLEAN_REWRITE_TERM_COUNT_THRESHOLD,
+            &mut postings,
+        )? {
+            // few terms: merge the postings lazily
+            return Ok(match postings.len() {
+                0 => None,
+                1 => {
+                    let iterator = postings.pop().unwrap();
+                    let cost = iterator.cost();
+                    Some(Box::new(ConstantScoreScorer::new(
+                        self.weight,
+                        iterator,
+                        cost,
+                    )))
+                }
+                _ => {
+                    let children: Vec<_> = postings
+                        .into_iter()
+                        .map(|p| {
+                            let cost = p.cost();
+                            ConstantScoreScorer::new(self.weight, p, cost)
+                        })
+                        .collect();
+                    let iterator = DisjunctionSumScorer::new(children, false);
+                    let cost = iterator.cost();
+                    Some(Box::new(ConstantScoreScorer::new(
+                        self.weight,
+                        iterator,
+                        cost,
+                    )))
+                }
+            });
+        }
+
+        // too many terms: collect the matching docs in a set
+        let mut builder = DocIdSetBuilder::from_terms(reader.reader.max_doc(), &terms)?;
+        for mut p in postings {
+            builder.add(&mut p)?;
+        }
+        loop {
+            let mut p = iter.postings_with_flags(PostingIteratorFlags::NONE)?;
+            builder.add(&mut p)?;
+            if iter.next()?.is_none() {
+                break;
+            }
+        }
+        if let Some(iterator) = builder.build().iterator()? {
+            let cost = iterator.cost();
+            Ok(Some(Box::new(ConstantScoreScorer::new(
+                self.weight,
+                iterator,
+                cost,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.query_type
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.norm = norm;
+        self.weight = norm * boost * self.boost;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut scorer) = <Self as Weight<C>>::create_scorer(self, reader)? {
+            scorer.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self.query),
+                vec![
+                    Explanation::new(true, self.boost, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0.0f32,
+                format!("{} doesn't match id {}", self.query, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl<Q: MultiTermQuery> fmt::Display for MultiTermQueryConstantScoreWeight<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MultiTermQueryConstantScoreWeight(query: {}, weight: {})",
+            self.query, self.weight
+        )
+    }
+}
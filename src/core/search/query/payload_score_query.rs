@@ -0,0 +1,614 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{decode_float, decode_int};
+use core::codec::{Codec, CodecPostingIterator, PostingIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    PostingsFlag, SpanCollector, SpanQuery, SpanQueryEnum, SpanWeight, SpanWeightEnum, Spans,
+    SpansEnum, NO_MORE_POSITIONS,
+};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::SimScorer;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::Result;
+
+use std::any::Any;
+use std::fmt;
+
+const PAYLOAD_SCORE_QUERY: &str = "payload_score";
+
+/// Combines the payload factors of the matching positions of a doc into a
+/// single payload score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadFunction {
+    Min,
+    Max,
+    Average,
+}
+
+impl PayloadFunction {
+    /// Folds the factor of the current payload into the score of the
+    /// `payloads_seen` payloads before it.
+    pub fn current_score(
+        self,
+        payloads_seen: usize,
+        current_score: f32,
+        current_payload_score: f32,
+    ) -> f32 {
+        if payloads_seen == 0 {
+            return current_payload_score;
+        }
+        match self {
+            PayloadFunction::Min => current_score.min(current_payload_score),
+            PayloadFunction::Max => current_score.max(current_payload_score),
+            PayloadFunction::Average => current_score + current_payload_score,
+        }
+    }
+
+    /// Returns the final payload score of a doc, 1.0 if no payload was seen.
+    pub fn doc_score(self, payloads_seen: usize, payload_score: f32) -> f32 {
+        if payloads_seen == 0 {
+            return 1.0;
+        }
+        match self {
+            PayloadFunction::Min | PayloadFunction::Max => payload_score,
+            PayloadFunction::Average => payload_score / payloads_seen as f32,
+        }
+    }
+}
+
+impl fmt::Display for PayloadFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadFunction::Min => write!(f, "min"),
+            PayloadFunction::Max => write!(f, "max"),
+            PayloadFunction::Average => write!(f, "avg"),
+        }
+    }
+}
+
+/// Turns the payload of a matching position into a scoring factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadDecoder {
+    /// Calls `SimScorer::compute_payload_factor` of the similarity of the
+    /// field.
+    Similarity,
+    /// Decodes the payloads written by `FloatEncoder`.
+    Float,
+    /// Decodes the payloads written by `IntegerEncoder`.
+    Integer,
+}
+
+impl PayloadDecoder {
+    /// Returns the factor of the payload of a position in `[start, end)` of
+    /// `doc`, 1.0 if the payload can't be decoded.
+    pub fn compute_payload_factor(
+        self,
+        doc_scorer: &dyn SimScorer,
+        doc: DocId,
+        start: i32,
+        end: i32,
+        payload: &[u8],
+    ) -> f32 {
+        match self {
+            PayloadDecoder::Similarity => {
+                doc_scorer.compute_payload_factor(doc, start, end, payload)
+            }
+            PayloadDecoder::Float => decode_float(payload).unwrap_or(1.0),
+            PayloadDecoder::Integer => decode_int(payload).map(|v| v as f32).unwrap_or(1.0),
+        }
+    }
+}
+
+impl fmt::Display for PayloadDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadDecoder::Similarity => write!(f, "similarity"),
+            PayloadDecoder::Float => write!(f, "float"),
+            PayloadDecoder::Integer => write!(f, "int"),
+        }
+    }
+}
+
+/// A `Query` for matching spans and scoring them by the payloads of their
+/// terms.
+///
+/// The payload of each matching position is turned into a factor by the
+/// `PayloadDecoder`, and the factors of a doc are combined by the
+/// `PayloadFunction`. If `include_span_score` is true the result is
+/// multiplied with the score of the wrapped span query.
+pub struct PayloadScoreQuery {
+    query: SpanQueryEnum,
+    function: PayloadFunction,
+    decoder: PayloadDecoder,
+    include_span_score: bool,
+}
+
+impl PayloadScoreQuery {
+    pub fn new(
+        query: SpanQueryEnum,
+        function: PayloadFunction,
+        decoder: PayloadDecoder,
+        include_span_score: bool,
+    ) -> Self {
+        PayloadScoreQuery {
+            query,
+            function,
+            decoder,
+            include_span_score,
+        }
+    }
+
+    pub fn wrapped_query(&self) -> &SpanQueryEnum {
+        &self.query
+    }
+
+    pub fn function(&self) -> PayloadFunction {
+        self.function
+    }
+
+    pub fn decoder(&self) -> PayloadDecoder {
+        self.decoder
+    }
+}
+
+impl<C: Codec> Query<C> for PayloadScoreQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let weight = self.query.span_weight(searcher, needs_scores)?;
+        if !needs_scores {
+            // payloads only affect the scores
+            return Ok(Box::new(weight));
+        }
+        Ok(Box::new(PayloadScoreWeight {
+            weight,
+            function: self.function,
+            decoder: self.decoder,
+            include_span_score: self.include_span_score,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        Query::<C>::extract_terms(&self.query)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for PayloadScoreQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PayloadScoreQuery(query: {}, function: {}, decoder: {}, include_span_score: {})",
+            self.query, self.function, self.decoder, self.include_span_score
+        )
+    }
+}
+
+struct PayloadScoreWeight<C: Codec> {
+    weight: SpanWeightEnum<C>,
+    function: PayloadFunction,
+    decoder: PayloadDecoder,
+    include_span_score: bool,
+}
+
+impl<C: Codec> PayloadScoreWeight<C> {
+    fn payload_scorer(
+        &self,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<PayloadSpanScorer<SpansEnum<CodecPostingIterator<C>>>>> {
+        if let Some(spans) = self.weight.get_spans(ctx, &PostingsFlag::Payloads)? {
+            if let Some(doc_scorer) = self.weight.sim_scorer(ctx.reader)? {
+                return Ok(Some(PayloadSpanScorer::new(
+                    spans,
+                    doc_scorer,
+                    self.function,
+                    self.decoder,
+                    self.include_span_score,
+                )));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<C: Codec> Weight<C> for PayloadScoreWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        if self.weight.sim_weight().is_none() {
+            return self.weight.create_scorer(ctx);
+        }
+        Ok(self
+            .payload_scorer(ctx)?
+            .map(|s| -> Box<dyn Scorer> { Box::new(s) }))
+    }
+
+    fn query_type(&self) -> &'static str {
+        PAYLOAD_SCORE_QUERY
+    }
+
+    fn actual_query_type(&self) -> &'static str {
+        self.weight.actual_query_type()
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let span_expl = self.weight.explain(reader, doc)?;
+        if !span_expl.is_match() {
+            return Ok(span_expl);
+        }
+        let mut scorer = match self.payload_scorer(reader)? {
+            Some(scorer) => scorer,
+            None => return Ok(span_expl),
+        };
+        if scorer.advance(doc)? != doc {
+            return Ok(span_expl);
+        }
+        scorer.ensure_freq()?;
+        let payload_score = scorer.payload_score();
+        let payload_expl = Explanation::new(
+            true,
+            payload_score,
+            format!(
+                "PayloadScoreQuery, function: {}, payloads seen: {}",
+                self.function, scorer.payloads_seen
+            ),
+            vec![],
+        );
+        if self.include_span_score {
+            Ok(Explanation::new(
+                true,
+                span_expl.value() * payload_score,
+                format!("weight({} in {}), product of:", self, doc),
+                vec![span_expl, payload_expl],
+            ))
+        } else {
+            Ok(payload_expl)
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for PayloadScoreWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PayloadScoreWeight(weight: {}, function: {}, decoder: {}, include_span_score: {})",
+            self.weight, self.function, self.decoder, self.include_span_score
+        )
+    }
+}
+
+/// Collects the payload factors of the leaves of the current span.
+struct PayloadSpanCollector<'a> {
+    doc_scorer: &'a dyn SimScorer,
+    function: PayloadFunction,
+    decoder: PayloadDecoder,
+    doc: DocId,
+    start: i32,
+    end: i32,
+    payload_score: f32,
+    payloads_seen: usize,
+}
+
+impl<'a> SpanCollector for PayloadSpanCollector<'a> {
+    fn collect_leaf(
+        &mut self,
+        postings: &impl PostingIterator,
+        _position: i32,
+        _term: &Term,
+    ) -> Result<()> {
+        let payload = postings.payload()?;
+        if payload.is_empty() {
+            return Ok(());
+        }
+        let factor = self.decoder.compute_payload_factor(
+            self.doc_scorer,
+            self.doc,
+            self.start,
+            self.end,
+            &payload,
+        );
+        self.payload_score =
+            self.function
+                .current_score(self.payloads_seen, self.payload_score, factor);
+        self.payloads_seen += 1;
+        Ok(())
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// A `Scorer` over `Spans` which also collects the payloads of all the
+/// matches of the current doc.
+struct PayloadSpanScorer<S: Spans> {
+    spans: S,
+    doc_scorer: Box<dyn SimScorer>,
+    function: PayloadFunction,
+    decoder: PayloadDecoder,
+    include_span_score: bool,
+    freq: f32,
+    payload_score: f32,
+    payloads_seen: usize,
+    last_scored_doc: DocId,
+}
+
+impl<S: Spans> PayloadSpanScorer<S> {
+    fn new(
+        spans: S,
+        doc_scorer: Box<dyn SimScorer>,
+        function: PayloadFunction,
+        decoder: PayloadDecoder,
+        include_span_score: bool,
+    ) -> Self {
+        PayloadSpanScorer {
+            spans,
+            doc_scorer,
+            function,
+            decoder,
+            include_span_score,
+            freq: 0.0,
+            payload_score: 0.0,
+            payloads_seen: 0,
+            last_scored_doc: -1,
+        }
+    }
+
+    fn ensure_freq(&mut self) -> Result<()> {
+        let current_doc = self.doc_id();
+        if self.last_scored_doc != current_doc {
+            self.set_freq_current_doc()?;
+            self.last_scored_doc = current_doc;
+        }
+        Ok(())
+    }
+
+    fn set_freq_current_doc(&mut self) -> Result<()> {
+        self.freq = 0.0;
+        self.spans.do_start_current_doc()?;
+
+        let mut collector = PayloadSpanCollector {
+            doc_scorer: self.doc_scorer.as_ref(),
+            function: self.function,
+            decoder: self.decoder,
+            doc: self.spans.doc_id(),
+            start: -1,
+            end: -1,
+            payload_score: 0.0,
+            payloads_seen: 0,
+        };
+        let mut start_pos = self.spans.next_start_position()?;
+        debug_assert_ne!(start_pos, NO_MORE_POSITIONS);
+        while start_pos != NO_MORE_POSITIONS {
+            self.freq += collector.doc_scorer.compute_slop_factor(self.spans.width());
+            self.spans.do_current_spans()?;
+            collector.start = start_pos;
+            collector.end = self.spans.end_position();
+            self.spans.collect(&mut collector)?;
+            start_pos = self.spans.next_start_position()?;
+        }
+        self.payload_score = collector.payload_score;
+        self.payloads_seen = collector.payloads_seen;
+        Ok(())
+    }
+
+    fn payload_score(&self) -> f32 {
+        self.function
+            .doc_score(self.payloads_seen, self.payload_score)
+    }
+}
+
+impl<S: Spans> Scorer for PayloadSpanScorer<S> {
+    fn score(&mut self) -> Result<f32> {
+        self.ensure_freq()?;
+        let payload_score = self.payload_score();
+        if self.include_span_score {
+            let doc = self.doc_id();
+            let span_score = self.doc_scorer.score(doc, self.freq)?;
+            Ok(span_score * payload_score)
+        } else {
+            Ok(payload_score)
+        }
+    }
+}
+
+impl<S: Spans> DocIterator for PayloadSpanScorer<S> {
+    fn doc_id(&self) -> DocId {
+        self.spans.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.spans.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.spans.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.spans.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.spans.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.spans.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.spans.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.spans.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.spans.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::{
+        encode_float, encode_int, DelimitedPayloadTokenFilter, IntegerEncoder, WhitespaceTokenizer,
+        DEFAULT_PAYLOAD_DELIMITER,
+    };
+    use core::codec::CodecEnum;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions};
+    use core::index::tests::*;
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::spans::SpanTermQuery;
+    use core::search::IndexSearcher;
+    use std::io::Cursor;
+
+    // a text field whose tokens carry the int payloads after their `|`
+    fn int_payload_field(text: &str) -> Box<dyn Fieldable> {
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.to_string())));
+        let tokens = DelimitedPayloadTokenFilter::new(
+            Box::new(tokenizer),
+            DEFAULT_PAYLOAD_DELIMITER,
+            Box::new(IntegerEncoder),
+        );
+        Box::new(Field::new(
+            "body".to_string(),
+            field_type,
+            None,
+            Some(Box::new(tokens)),
+        ))
+    }
+
+    fn payload_scores(
+        index: &TestIndex,
+        function: PayloadFunction,
+        decoder: PayloadDecoder,
+    ) -> Vec<(DocId, f32)> {
+        let span_query = SpanTermQuery::new(Term::new("body".into(), b"x".to_vec()), None);
+        let query =
+            PayloadScoreQuery::new(SpanQueryEnum::Term(span_query), function, decoder, false);
+        let mut collector = TopDocsCollector::new(10);
+        index
+            .searcher()
+            .search(&query as &dyn Query<CodecEnum>, &mut collector)
+            .unwrap();
+        let mut hits: Vec<(DocId, f32)> = collector
+            .top_docs()
+            .score_docs()
+            .iter()
+            .map(|d| (d.doc_id(), d.score()))
+            .collect();
+        hits.sort_by_key(|&(doc, _)| doc);
+        hits
+    }
+
+    #[test]
+    fn test_int_payloads() {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = ["x|3 y x|7", "x|2", "y|9 z", "x y"]
+            .iter()
+            .map(|text| vec![int_payload_field(text)])
+            .collect();
+        let index = TestIndex::new(docs, usize::max_value());
+
+        // the last doc has no payload for "x", its payload score is 1
+        let expected = [
+            (PayloadFunction::Max, vec![(0, 7.0), (1, 2.0), (3, 1.0)]),
+            (PayloadFunction::Min, vec![(0, 3.0), (1, 2.0), (3, 1.0)]),
+            (PayloadFunction::Average, vec![(0, 5.0), (1, 2.0), (3, 1.0)]),
+        ];
+        for (function, expected) in &expected {
+            assert_eq!(
+                &payload_scores(&index, *function, PayloadDecoder::Integer),
+                expected
+            );
+        }
+
+        // the default similarity doesn't interpret the payloads
+        assert_eq!(
+            payload_scores(&index, PayloadFunction::Max, PayloadDecoder::Similarity),
+            vec![(0, 1.0), (1, 1.0), (3, 1.0)]
+        );
+    }
+
+    struct ConstantSimScorer;
+
+    impl SimScorer for ConstantSimScorer {
+        fn score(&mut self, _doc: DocId, _freq: f32) -> Result<f32> {
+            Ok(1.0)
+        }
+
+        fn compute_slop_factor(&self, _distance: i32) -> f32 {
+            1.0
+        }
+    }
+
+    #[test]
+    fn test_payload_decoders() {
+        let doc_scorer = ConstantSimScorer;
+        let factor = |decoder: PayloadDecoder, payload: &[u8]| {
+            decoder.compute_payload_factor(&doc_scorer, 0, 0, 1, payload)
+        };
+        assert!(
+            (factor(PayloadDecoder::Integer, &encode_int(7)) - 7.0).abs() < ::std::f32::EPSILON
+        );
+        assert!(
+            (factor(PayloadDecoder::Float, &encode_float(0.5)) - 0.5).abs() < ::std::f32::EPSILON
+        );
+        assert!(
+            (factor(PayloadDecoder::Similarity, &encode_int(7)) - 1.0).abs() < ::std::f32::EPSILON
+        );
+        // a payload of another length is ignored
+        assert!((factor(PayloadDecoder::Integer, b"ab") - 1.0).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_payload_functions() {
+        let factors = [0.5f32, 2.0, 1.5];
+        let fold = |function: PayloadFunction| {
+            let mut score = 0.0;
+            for (i, factor) in factors.iter().enumerate() {
+                score = function.current_score(i, score, *factor);
+            }
+            function.doc_score(factors.len(), score)
+        };
+        assert!((fold(PayloadFunction::Min) - 0.5).abs() < ::std::f32::EPSILON);
+        assert!((fold(PayloadFunction::Max) - 2.0).abs() < ::std::f32::EPSILON);
+        assert!((fold(PayloadFunction::Average) - 4.0 / 3.0).abs() < ::std::f32::EPSILON);
+        assert!((PayloadFunction::Max.doc_score(0, 0.0) - 1.0).abs() < ::std::f32::EPSILON);
+    }
+}
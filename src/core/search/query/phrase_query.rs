@@ -255,20 +255,23 @@ impl<C: Codec> PhraseWeight<C> {
             term_states,
         }
     }
+}
 
-    fn term_positions_cost(&self, term_iter: &mut impl TermIterator) -> Result<f32> {
-        let doc_freq = term_iter.doc_freq()?;
-        debug_assert!(doc_freq > 0);
-        let total_term_freq = term_iter.total_term_freq()?; // -1 when not available
-        let exp_occurrences_in_matching_doc = if total_term_freq < i64::from(doc_freq) {
-            1.0f32
-        } else {
-            total_term_freq as f32 / doc_freq as f32
-        };
-
-        Ok(TERM_POSNS_SEEK_OPS_PER_DOC as f32
-            + exp_occurrences_in_matching_doc * TERM_OPS_PER_POS as f32)
-    }
+/// Returns an expected cost in simple operations of processing the
+/// occurrences of the term the `term_iter` is positioned on in a document
+/// which matches the phrase.
+pub fn term_positions_cost(term_iter: &mut impl TermIterator) -> Result<f32> {
+    let doc_freq = term_iter.doc_freq()?;
+    debug_assert!(doc_freq > 0);
+    let total_term_freq = term_iter.total_term_freq()?; // -1 when not available
+    let exp_occurrences_in_matching_doc = if total_term_freq < i64::from(doc_freq) {
+        1.0f32
+    } else {
+        total_term_freq as f32 / doc_freq as f32
+    };
+
+    Ok(TERM_POSNS_SEEK_OPS_PER_DOC as f32
+        + exp_occurrences_in_matching_doc * TERM_OPS_PER_POS as f32)
 }
 
 impl<C: Codec> Weight<C> for PhraseWeight<C> {
@@ -297,7 +300,7 @@ impl<C: Codec> Weight<C> for PhraseWeight<C> {
         for i in 0..self.terms.len() {
             let postings = if let Some(state) = self.term_states[i].get(&reader_context.doc_base) {
                 term_iter.seek_exact_state(self.terms[i].bytes.as_ref(), state)?;
-                total_match_cost += self.term_positions_cost(&mut term_iter)?;
+                total_match_cost += term_positions_cost(&mut term_iter)?;
 
                 term_iter.postings_with_flags(PostingIteratorFlags::POSITIONS)?
             } else {
@@ -376,7 +379,7 @@ impl<C: Codec> Weight<C> for PhraseWeight<C> {
             if let Some(state) = self.term_states[i].get(&reader.doc_base()) {
                 if let Some(ref mut term_iter) = term_iter {
                     term_iter.seek_exact_state(self.terms[i].bytes.as_ref(), state)?;
-                    total_match_cost += self.term_positions_cost(term_iter)?;
+                    total_match_cost += term_positions_cost(term_iter)?;
 
                     let postings =
                         term_iter.postings_with_flags(PostingIteratorFlags::POSITIONS)?;
@@ -459,7 +462,7 @@ impl<C: Codec> fmt::Display for PhraseWeight<C> {
     }
 }
 
-struct PostingsAndFreq<T: PostingIterator> {
+pub struct PostingsAndFreq<T: PostingIterator> {
     pub postings: T,
     pub pos: i32,
     pub terms: Vec<Term>,
@@ -468,7 +471,7 @@ struct PostingsAndFreq<T: PostingIterator> {
 }
 
 impl<T: PostingIterator> PostingsAndFreq<T> {
-    fn new(postings: T, pos: i32, term: &Term) -> Self {
+    pub fn new(postings: T, pos: i32, term: &Term) -> Self {
         PostingsAndFreq {
             postings,
             pos,
@@ -476,6 +479,19 @@ impl<T: PostingIterator> PostingsAndFreq<T> {
             nterms: 1,
         }
     }
+
+    /// The postings of any of the `terms` at the phrase position `pos`.
+    pub fn with_terms(postings: T, pos: i32, terms: &[Term]) -> Self {
+        let mut terms = terms.to_vec();
+        terms.sort();
+        let nterms = terms.len() as i32;
+        PostingsAndFreq {
+            postings,
+            pos,
+            terms,
+            nterms,
+        }
+    }
 }
 
 impl<T: PostingIterator> Ord for PostingsAndFreq<T> {
@@ -554,7 +570,7 @@ pub struct ExactPhraseScorer<T: PostingIterator> {
 }
 
 impl<T: PostingIterator + 'static> ExactPhraseScorer<T> {
-    fn new(
+    pub fn new(
         postings: Vec<PostingsAndFreq<T>>,
         doc_scorer: Box<dyn SimScorer>,
         needs_scores: bool,
@@ -657,6 +673,11 @@ impl<T: PostingIterator + 'static> ExactPhraseScorer<T> {
         Ok(self.freq)
     }
 
+    /// Returns the phrase frequency of the current doc.
+    pub fn freq(&self) -> i32 {
+        self.freq
+    }
+
     pub fn do_next(&mut self, doc_id: DocId) -> Result<DocId> {
         let mut doc = doc_id;
         loop {
@@ -904,7 +925,7 @@ pub struct SloppyPhraseScorer<T: PostingIterator> {
 }
 
 impl<T: PostingIterator + 'static> SloppyPhraseScorer<T> {
-    fn new(
+    pub fn new(
         postings: Vec<PostingsAndFreq<T>>,
         slop: i32,
         doc_scorer: Box<dyn SimScorer>,
@@ -946,6 +967,11 @@ impl<T: PostingIterator + 'static> SloppyPhraseScorer<T> {
         }
     }
 
+    /// Returns the sloppy phrase frequency of the current doc.
+    pub fn sloppy_freq(&self) -> f32 {
+        self.sloppy_freq
+    }
+
     /// Score a candidate doc for all slop-valid position-combinations (matches)
     /// encountered while traversing/hopping the PhrasePositions.
     /// <br> The score contribution of a match depends on the distance:
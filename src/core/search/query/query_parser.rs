@@ -0,0 +1,1313 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::Analyzer;
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{
+    BooleanQuery, BoostQuery, DoublePoint, FloatPoint, FuzzyQuery, IntPoint, LongPoint,
+    MatchAllDocsQuery, MatchNoDocsQuery, MultiPhraseQuery, PhraseQuery, PointValueType,
+    PrefixQuery, Query, RegexpQuery, TermQuery, TermRangeQuery, WildcardQuery,
+};
+use core::util::automaton::MAXIMUM_SUPPORTED_DISTANCE;
+
+use error::{Error, ErrorKind::IllegalArgument, Result};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The operator used between clauses that have no explicit `AND`/`OR`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operator {
+    /// `capital of Hungary` means `capital OR of OR Hungary`.
+    Or,
+    /// `capital of Hungary` means `capital AND of AND Hungary`.
+    And,
+}
+
+/// A parser of the Lucene classic query syntax.
+///
+/// The syntax is:
+///
+/// * `term`, `field:term`, `field:(a b)`, `*:*` matches all documents.
+/// * `a AND b`, `a && b`, `a OR b`, `a || b`, `a | b`, `NOT a`, `!a`.
+/// * `+required`, `-excluded`.
+/// * `"a phrase"`, `"a phrase"~2` with a slop.
+/// * `term^2`, `(a b)^0.5`, boosts.
+/// * `term~`, `term~1`, fuzzy terms with a maximum number of edits.
+/// * `prefix*`, `te?t*`, prefix and wildcard terms.
+/// * `/reg[ex]p/`, regular expressions, see `RegexpQuery`.
+/// * `[a TO b]`, `{a TO b}`, `[a TO *]`, inclusive, exclusive and open ranges, on term fields and
+///   on the point fields registered with `set_point_field`.
+/// * `\` escapes the next char, see `QueryParser::escape`.
+///
+/// The text of terms and phrases goes through the `Analyzer`, a term giving
+/// several tokens is a disjunction, or a conjunction with `Operator::And`,
+/// of the tokens, and tokens at the same position are synonyms, which make a
+/// `MultiPhraseQuery` of a phrase. The terms of fuzzy, prefix, wildcard,
+/// regexp and range queries are not analyzed.
+///
+/// Parse errors report the char position in the query where they happened.
+pub struct QueryParser {
+    fields: Vec<(String, f32)>,
+    analyzer: Arc<dyn Analyzer>,
+    default_operator: Operator,
+    phrase_slop: i32,
+    fuzzy_prefix_length: usize,
+    fuzzy_max_expansions: usize,
+    allow_leading_wildcard: bool,
+    minimum_should_match: Option<i32>,
+    point_fields: HashMap<String, PointValueType>,
+}
+
+impl QueryParser {
+    /// Creates a parser searching the terms without a field in `fields`,
+    /// each one with its boost.
+    pub fn new(fields: Vec<(String, f32)>, analyzer: Arc<dyn Analyzer>) -> QueryParser {
+        QueryParser {
+            fields,
+            analyzer,
+            default_operator: Operator::Or,
+            phrase_slop: 0,
+            fuzzy_prefix_length: FuzzyQuery::DEFAULT_PREFIX_LENGTH,
+            fuzzy_max_expansions: FuzzyQuery::DEFAULT_MAX_EXPANSIONS,
+            allow_leading_wildcard: false,
+            minimum_should_match: None,
+            point_fields: HashMap::new(),
+        }
+    }
+
+    pub fn set_default_operator(&mut self, operator: Operator) {
+        self.default_operator = operator;
+    }
+
+    /// The slop of the phrases without an explicit one, 0 by default.
+    pub fn set_phrase_slop(&mut self, slop: i32) {
+        self.phrase_slop = slop;
+    }
+
+    pub fn set_fuzzy_prefix_length(&mut self, prefix_length: usize) {
+        self.fuzzy_prefix_length = prefix_length;
+    }
+
+    pub fn set_fuzzy_max_expansions(&mut self, max_expansions: usize) {
+        self.fuzzy_max_expansions = max_expansions;
+    }
+
+    /// Whether wildcard terms may start with `*` or `?`, false by default
+    /// since such queries have to go through all the terms of the field.
+    pub fn set_allow_leading_wildcard(&mut self, allow: bool) {
+        self.allow_leading_wildcard = allow;
+    }
+
+    /// The minimum number of optional clauses of the top level query that
+    /// must match, it is capped by the number of optional clauses.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: i32) {
+        self.minimum_should_match = Some(minimum_should_match);
+    }
+
+    /// Declares `field` as a point field of the given type, its terms and
+    /// ranges become point queries.
+    pub fn set_point_field(&mut self, field: &str, value_type: PointValueType) {
+        self.point_fields.insert(field.to_string(), value_type);
+    }
+
+    /// Escapes the chars that have a meaning in the query syntax.
+    pub fn escape(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            if "\\+-!():^[]\"{}~*?|&/".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Parses `query`, returns `None` if it has no clause left after the
+    /// analysis, e.g. when it is empty or only made of stop words.
+    pub fn parse<C: Codec>(&self, query: &str) -> Result<Option<Box<dyn Query<C>>>> {
+        let clauses = Parser::new(query)?.parse()?;
+        self.build_clauses(query, &clauses, self.minimum_should_match)
+    }
+
+    fn build_clauses<C: Codec>(
+        &self,
+        query: &str,
+        clauses: &[ClauseNode],
+        minimum_should_match: Option<i32>,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut built: Vec<(Occur, Box<dyn Query<C>>)> = Vec::with_capacity(clauses.len());
+        for clause in clauses {
+            let q = self.build_node(query, &clause.node)?;
+            self.add_clause(&mut built, clause.conj, clause.modifier, q);
+        }
+
+        let mut musts = vec![];
+        let mut shoulds = vec![];
+        let mut must_nots = vec![];
+        for (occur, q) in built {
+            match occur {
+                Occur::Must => musts.push(q),
+                Occur::Should => shoulds.push(q),
+                Occur::MustNot => must_nots.push(q),
+            }
+        }
+        if musts.is_empty() && shoulds.is_empty() && must_nots.is_empty() {
+            return Ok(None);
+        }
+        let minimum_should_match = match minimum_should_match {
+            Some(n) if !shoulds.is_empty() => n.min(shoulds.len() as i32),
+            _ if musts.is_empty() => 1,
+            _ => 0,
+        };
+        Ok(Some(BooleanQuery::with_options(
+            musts,
+            shoulds,
+            vec![],
+            must_nots,
+            minimum_should_match,
+        )?))
+    }
+
+    // The semantic of the conjunctions and modifiers of the Lucene classic
+    // query parser: `AND` makes the previous clause required, and `OR` makes
+    // it optional with `Operator::And`, unless it is prohibited.
+    fn add_clause<C: Codec>(
+        &self,
+        clauses: &mut Vec<(Occur, Box<dyn Query<C>>)>,
+        conj: Conjunction,
+        modifier: Modifier,
+        query: Option<Box<dyn Query<C>>>,
+    ) {
+        if let Some(last) = clauses.last_mut() {
+            if last.0 != Occur::MustNot {
+                if conj == Conjunction::And {
+                    last.0 = Occur::Must;
+                } else if conj == Conjunction::Or && self.default_operator == Operator::And {
+                    last.0 = Occur::Should;
+                }
+            }
+        }
+        let query = match query {
+            Some(q) => q,
+            None => return,
+        };
+        let occur = match (modifier, self.default_operator) {
+            (Modifier::Not, _) => Occur::MustNot,
+            (Modifier::Req, _) => Occur::Must,
+            (Modifier::None, Operator::Or) if conj == Conjunction::And => Occur::Must,
+            (Modifier::None, Operator::Or) => Occur::Should,
+            (Modifier::None, Operator::And) if conj == Conjunction::Or => Occur::Should,
+            (Modifier::None, Operator::And) => Occur::Must,
+        };
+        clauses.push((occur, query));
+    }
+
+    fn build_node<C: Codec>(&self, query: &str, node: &Node) -> Result<Option<Box<dyn Query<C>>>> {
+        match *node {
+            Node::Group { ref clauses, boost } => {
+                let q = self.build_clauses(query, clauses, None)?;
+                Ok(q.map(|q| BoostQuery::build(q, boost.unwrap_or(1.0))))
+            }
+            Node::Leaf {
+                ref field,
+                ref kind,
+                boost,
+                pos,
+            } => {
+                let boost = boost.unwrap_or(1.0);
+                if let Some(ref field) = *field {
+                    let field_boost = self
+                        .fields
+                        .iter()
+                        .find(|f| &f.0 == field)
+                        .map_or(1.0, |f| f.1);
+                    return self.build_leaf(query, field, kind, boost * field_boost, pos);
+                }
+                let mut queries = Vec::with_capacity(self.fields.len());
+                for &(ref field, field_boost) in &self.fields {
+                    if let Some(q) =
+                        self.build_leaf(query, field, kind, boost * field_boost, pos)?
+                    {
+                        queries.push(q);
+                    }
+                }
+                match queries.len() {
+                    0 => Ok(None),
+                    1 => Ok(queries.pop()),
+                    _ => Ok(Some(BooleanQuery::build(vec![], queries, vec![])?)),
+                }
+            }
+        }
+    }
+
+    fn build_leaf<C: Codec>(
+        &self,
+        query: &str,
+        field: &str,
+        kind: &LeafKind,
+        boost: f32,
+        pos: usize,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        let point_type = self.point_fields.get(field).cloned();
+        let q: Box<dyn Query<C>> = match *kind {
+            LeafKind::Term { ref text, fuzzy } => {
+                if field == "*" && text == "*" {
+                    BoostQuery::build(Box::new(MatchAllDocsQuery), boost)
+                } else if let Some(value_type) = point_type {
+                    if fuzzy.is_some() || has_wildcard(text) {
+                        return Err(parse_error(
+                            query,
+                            &format!("point field '{}' only supports exact values", field),
+                            pos,
+                        ));
+                    }
+                    let value = unescape(text);
+                    let q = self.point_range(
+                        query,
+                        field,
+                        value_type,
+                        Some(&value),
+                        Some(&value),
+                        true,
+                        true,
+                        pos,
+                    )?;
+                    BoostQuery::build(q, boost)
+                } else if let Some(similarity) = fuzzy {
+                    let text = unescape(text);
+                    let max_edits = fuzzy_max_edits(similarity, text.chars().count());
+                    let mut q = FuzzyQuery::with_options(
+                        Term::new(field.to_string(), text.into_bytes()),
+                        max_edits,
+                        self.fuzzy_prefix_length,
+                        self.fuzzy_max_expansions,
+                        FuzzyQuery::DEFAULT_TRANSPOSITIONS,
+                    )?;
+                    q.set_boost(boost);
+                    Box::new(q)
+                } else if has_wildcard(text) {
+                    if !self.allow_leading_wildcard
+                        && (text.starts_with('*') || text.starts_with('?'))
+                    {
+                        return Err(parse_error(
+                            query,
+                            "'*' or '?' not allowed as first character in a wildcard term",
+                            pos,
+                        ));
+                    }
+                    // only a trailing `*`
+                    let prefix = text.trim_end_matches('*');
+                    if text.len() == prefix.len() + 1 && !has_wildcard(prefix) {
+                        let mut q = PrefixQuery::new(Term::new(
+                            field.to_string(),
+                            unescape(prefix).into_bytes(),
+                        ))?;
+                        q.set_boost(boost);
+                        Box::new(q)
+                    } else {
+                        let mut q = WildcardQuery::new(Term::new(
+                            field.to_string(),
+                            text.as_bytes().to_vec(),
+                        ))?;
+                        q.set_boost(boost);
+                        Box::new(q)
+                    }
+                } else {
+                    match self.analyzed_query(field, &unescape(text), None, boost)? {
+                        Some(q) => q,
+                        None => return Ok(None),
+                    }
+                }
+            }
+            LeafKind::Phrase { ref text, slop } => {
+                let slop = slop.map_or(self.phrase_slop, |s| s as i32);
+                match self.analyzed_query(field, text, Some(slop), boost)? {
+                    Some(q) => q,
+                    None => return Ok(None),
+                }
+            }
+            LeafKind::Regexp(ref text) => {
+                let mut q =
+                    RegexpQuery::new(Term::new(field.to_string(), text.as_bytes().to_vec()))?;
+                q.set_boost(boost);
+                Box::new(q)
+            }
+            LeafKind::Range {
+                ref lower,
+                ref upper,
+                include_lower,
+                include_upper,
+            } => {
+                let (lower, upper) = (
+                    lower.as_ref().map(String::as_str),
+                    upper.as_ref().map(String::as_str),
+                );
+                if let Some(value_type) = point_type {
+                    let q = self.point_range(
+                        query,
+                        field,
+                        value_type,
+                        lower,
+                        upper,
+                        include_lower,
+                        include_upper,
+                        pos,
+                    )?;
+                    BoostQuery::build(q, boost)
+                } else {
+                    let mut q = TermRangeQuery::new_string_range(
+                        field.to_string(),
+                        lower,
+                        upper,
+                        include_lower,
+                        include_upper,
+                    );
+                    q.set_boost(boost);
+                    Box::new(q)
+                }
+            }
+        };
+        Ok(Some(q))
+    }
+
+    // Builds the query of an analyzed text, a phrase query if `slop` is set
+    // and the text gives several positions.
+    fn analyzed_query<C: Codec>(
+        &self,
+        field: &str,
+        text: &str,
+        slop: Option<i32>,
+        boost: f32,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        // the terms at each position, by increasing position
+        let mut positions: Vec<(i32, Vec<Term>)> = vec![];
+        let mut stream = self.analyzer.token_stream_from_str(field, text)?;
+        stream.reset()?;
+        let mut position = -1i32;
+        while stream.increment_token()? {
+            let increment = stream.position_attribute_mut().get_position() as i32;
+            let bytes = stream.term_bytes_attribute().get_bytes_ref();
+            let term = Term::new(field.to_string(), bytes.bytes().to_vec());
+            if increment > 0 || positions.is_empty() {
+                position += increment.max(1);
+                positions.push((position, vec![term]));
+            } else {
+                positions.last_mut().unwrap().1.push(term);
+            }
+        }
+        stream.end()?;
+
+        match positions.len() {
+            0 => Ok(None),
+            1 => {
+                let mut terms = positions.pop().unwrap().1;
+                if terms.len() == 1 {
+                    Ok(Some(Box::new(TermQuery::new(terms.remove(0), boost, None))))
+                } else {
+                    Ok(Some(BoostQuery::build(Self::synonyms_query(terms)?, boost)))
+                }
+            }
+            _ => {
+                let q: Box<dyn Query<C>> = if let Some(slop) = slop {
+                    if positions.iter().any(|(_, terms)| terms.len() > 1) {
+                        // stacked tokens, e.g. synonyms, are alternatives at their position
+                        let (positions, term_arrays): (Vec<i32>, Vec<Vec<Term>>) =
+                            positions.into_iter().unzip();
+                        Box::new(MultiPhraseQuery::new(term_arrays, positions, slop, None)?)
+                    } else {
+                        let (positions, terms): (Vec<i32>, Vec<Term>) = positions
+                            .into_iter()
+                            .map(|(position, mut terms)| (position, terms.remove(0)))
+                            .unzip();
+                        Box::new(PhraseQuery::new(terms, positions, slop, None, None)?)
+                    }
+                } else {
+                    let mut queries = Vec::with_capacity(positions.len());
+                    for (_, terms) in positions {
+                        queries.push(Self::synonyms_query(terms)?);
+                    }
+                    match self.default_operator {
+                        Operator::Or => BooleanQuery::build(vec![], queries, vec![])?,
+                        Operator::And => BooleanQuery::build(queries, vec![], vec![])?,
+                    }
+                };
+                Ok(Some(BoostQuery::build(q, boost)))
+            }
+        }
+    }
+
+    fn synonyms_query<C: Codec>(terms: Vec<Term>) -> Result<Box<dyn Query<C>>> {
+        let queries: Vec<Box<dyn Query<C>>> = terms
+            .into_iter()
+            .map(|term| Box::new(TermQuery::new(term, 1.0, None)) as Box<dyn Query<C>>)
+            .collect();
+        BooleanQuery::build(vec![], queries, vec![])
+    }
+
+    fn empty_range<C: Codec>(field: &str) -> Box<dyn Query<C>> {
+        Box::new(MatchNoDocsQuery::new(format!(
+            "empty range on field {}",
+            field
+        )))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn point_range<C: Codec>(
+        &self,
+        query: &str,
+        field: &str,
+        value_type: PointValueType,
+        lower: Option<&str>,
+        upper: Option<&str>,
+        include_lower: bool,
+        include_upper: bool,
+        pos: usize,
+    ) -> Result<Box<dyn Query<C>>> {
+        let field = field.to_string();
+        match value_type {
+            PointValueType::Integer => {
+                // an exclusive bound at the limit of the type excludes every value
+                let lower = match lower {
+                    Some(v) => {
+                        let v: i32 = parse_value(query, v, pos)?;
+                        if include_lower {
+                            Some(v)
+                        } else {
+                            v.checked_add(1)
+                        }
+                    }
+                    None => Some(i32::min_value()),
+                };
+                let upper = match upper {
+                    Some(v) => {
+                        let v: i32 = parse_value(query, v, pos)?;
+                        if include_upper {
+                            Some(v)
+                        } else {
+                            v.checked_sub(1)
+                        }
+                    }
+                    None => Some(i32::max_value()),
+                };
+                match (lower, upper) {
+                    (Some(lower), Some(upper)) => IntPoint::new_range_query(field, lower, upper),
+                    _ => Ok(Self::empty_range(&field)),
+                }
+            }
+            PointValueType::Long => {
+                // an exclusive bound at the limit of the type excludes every value
+                let lower = match lower {
+                    Some(v) => {
+                        let v: i64 = parse_value(query, v, pos)?;
+                        if include_lower {
+                            Some(v)
+                        } else {
+                            v.checked_add(1)
+                        }
+                    }
+                    None => Some(i64::min_value()),
+                };
+                let upper = match upper {
+                    Some(v) => {
+                        let v: i64 = parse_value(query, v, pos)?;
+                        if include_upper {
+                            Some(v)
+                        } else {
+                            v.checked_sub(1)
+                        }
+                    }
+                    None => Some(i64::max_value()),
+                };
+                match (lower, upper) {
+                    (Some(lower), Some(upper)) => LongPoint::new_range_query(field, lower, upper),
+                    _ => Ok(Self::empty_range(&field)),
+                }
+            }
+            PointValueType::Float => {
+                let lower = match lower {
+                    Some(v) => {
+                        let v: f32 = parse_value(query, v, pos)?;
+                        if include_lower {
+                            v
+                        } else {
+                            FloatPoint::next_up(v)
+                        }
+                    }
+                    None => ::std::f32::NEG_INFINITY,
+                };
+                let upper = match upper {
+                    Some(v) => {
+                        let v: f32 = parse_value(query, v, pos)?;
+                        if include_upper {
+                            v
+                        } else {
+                            FloatPoint::next_down(v)
+                        }
+                    }
+                    None => ::std::f32::INFINITY,
+                };
+                FloatPoint::new_range_query(field, lower, upper)
+            }
+            PointValueType::Double => {
+                let lower = match lower {
+                    Some(v) => {
+                        let v: f64 = parse_value(query, v, pos)?;
+                        if include_lower {
+                            v
+                        } else {
+                            DoublePoint::next_up(v)
+                        }
+                    }
+                    None => ::std::f64::NEG_INFINITY,
+                };
+                let upper = match upper {
+                    Some(v) => {
+                        let v: f64 = parse_value(query, v, pos)?;
+                        if include_upper {
+                            v
+                        } else {
+                            DoublePoint::next_down(v)
+                        }
+                    }
+                    None => ::std::f64::INFINITY,
+                };
+                DoublePoint::new_range_query(field, lower, upper)
+            }
+        }
+    }
+}
+
+fn parse_error(query: &str, msg: &str, pos: usize) -> Error {
+    IllegalArgument(format!(
+        "Cannot parse '{}': {} at position {}",
+        query, msg, pos
+    ))
+    .into()
+}
+
+fn parse_value<T: FromStr>(query: &str, text: &str, pos: usize) -> Result<T> {
+    text.parse()
+        .map_err(|_| parse_error(query, &format!("invalid point value '{}'", text), pos))
+}
+
+// the number of edits of `term~similarity`, a similarity below 1 is the
+// legacy minimum similarity relative to the term length
+fn fuzzy_max_edits(similarity: Option<f32>, term_length: usize) -> usize {
+    let similarity = similarity.unwrap_or(FuzzyQuery::DEFAULT_MAX_EDITS as f32);
+    let max_edits = if similarity >= 1.0 {
+        similarity as usize
+    } else {
+        ((1.0 - similarity) * term_length as f32) as usize
+    };
+    max_edits.min(MAXIMUM_SUPPORTED_DISTANCE)
+}
+
+// whether `text` has an unescaped `*` or `?`
+fn has_wildcard(text: &str) -> bool {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                unescaped.push(escaped);
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Occur {
+    Must,
+    Should,
+    MustNot,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Conjunction {
+    None,
+    And,
+    Or,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Modifier {
+    None,
+    Req,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LeafKind {
+    // the text keeps its escapes, they matter to wildcard terms
+    Term {
+        text: String,
+        fuzzy: Option<Option<f32>>,
+    },
+    Phrase {
+        text: String,
+        slop: Option<f32>,
+    },
+    Regexp(String),
+    Range {
+        lower: Option<String>,
+        upper: Option<String>,
+        include_lower: bool,
+        include_upper: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Group {
+        clauses: Vec<ClauseNode>,
+        boost: Option<f32>,
+    },
+    Leaf {
+        // `None` for the default fields
+        field: Option<String>,
+        kind: LeafKind,
+        boost: Option<f32>,
+        pos: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ClauseNode {
+    conj: Conjunction,
+    modifier: Modifier,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+    LParen,
+    RParen,
+    Colon,
+    Boost(f32),
+    // `~` and its optional number
+    Fuzzy(Option<f32>),
+    // with its escapes
+    Term(String),
+    Phrase(String),
+    Regexp(String),
+    Range {
+        lower: Option<String>,
+        upper: Option<String>,
+        include_lower: bool,
+        include_upper: bool,
+    },
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Colon => write!(f, ":"),
+            Token::Boost(boost) => write!(f, "^{}", boost),
+            Token::Fuzzy(Some(n)) => write!(f, "~{}", n),
+            Token::Fuzzy(None) => write!(f, "~"),
+            Token::Term(ref text) => write!(f, "{}", text),
+            Token::Phrase(ref text) => write!(f, "\"{}\"", text),
+            Token::Regexp(ref text) => write!(f, "/{}/", text),
+            Token::Range { .. } => write!(f, "range"),
+        }
+    }
+}
+
+// the chars that end a term, besides whitespace
+const TERM_END_CHARS: &str = "():^[]\"{}~!/";
+
+struct Lexer<'a> {
+    query: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error(&self, msg: &str, pos: usize) -> Error {
+        parse_error(self.query, msg, pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_is(&self, c: char) -> bool {
+        self.peek() == Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<(Token, usize)>> {
+        let mut tokens = vec![];
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Ok(tokens),
+            };
+            self.pos += 1;
+            let token = match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ':' => Token::Colon,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '!' => Token::Not,
+                '|' => {
+                    if self.peek_is('|') {
+                        self.pos += 1;
+                    }
+                    Token::Or
+                }
+                '&' if self.peek_is('&') => {
+                    self.pos += 1;
+                    Token::And
+                }
+                '^' => match self.number()? {
+                    Some(boost) => Token::Boost(boost),
+                    None => return Err(self.error("expected a boost after '^'", start)),
+                },
+                '~' => Token::Fuzzy(self.number()?),
+                '"' => Token::Phrase(self.quoted(start)?),
+                '/' => Token::Regexp(self.regexp(start)?),
+                '[' | '{' => self.range(c == '[', start)?,
+                ']' | '}' => return Err(self.error(&format!("unexpected '{}'", c), start)),
+                _ => {
+                    self.pos -= 1;
+                    let text = self.term()?;
+                    match text.as_str() {
+                        "AND" => Token::And,
+                        "OR" => Token::Or,
+                        "NOT" => Token::Not,
+                        _ => Token::Term(text),
+                    }
+                }
+            };
+            tokens.push((token, start));
+        }
+    }
+
+    // the number after '^' or '~', None if there is none
+    fn number(&mut self) -> Result<Option<f32>> {
+        let start = self.pos;
+        while self
+            .peek()
+            .map_or(false, |c| c.is_ascii_digit() || c == '.')
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.parse() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => Err(self.error(&format!("invalid number '{}'", text), start)),
+        }
+    }
+
+    // a term with its escapes, up to whitespace or a special char
+    fn term(&mut self) -> Result<String> {
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || TERM_END_CHARS.contains(c) {
+                break;
+            }
+            self.pos += 1;
+            text.push(c);
+            if c == '\\' {
+                match self.peek() {
+                    Some(escaped) => {
+                        text.push(escaped);
+                        self.pos += 1;
+                    }
+                    None => {
+                        return Err(
+                            self.error("term can not end with escape character", self.pos - 1)
+                        );
+                    }
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    // the unescaped text up to the closing quote
+    fn quoted(&mut self, start: usize) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') if self.pos + 1 < self.chars.len() => {
+                    text.push(self.chars[self.pos + 1]);
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(self.error("unterminated phrase", start)),
+            }
+        }
+    }
+
+    // the regexp up to the closing slash, only `\/` is unescaped
+    fn regexp(&mut self, start: usize) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('/') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') if self.chars.get(self.pos + 1) == Some(&'/') => {
+                    text.push('/');
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(self.error("unterminated regular expression", start)),
+            }
+        }
+    }
+
+    fn range(&mut self, include_lower: bool, start: usize) -> Result<Token> {
+        self.skip_whitespace();
+        let lower = self.range_bound(start)?;
+        self.skip_whitespace();
+        if self.chars[self.pos..].starts_with(&['T', 'O']) {
+            self.pos += 2;
+        } else {
+            return Err(self.error("expected 'TO' in range", self.pos));
+        }
+        self.skip_whitespace();
+        let upper = self.range_bound(start)?;
+        self.skip_whitespace();
+        let include_upper = match self.peek() {
+            Some(']') => true,
+            Some('}') => false,
+            _ => return Err(self.error("expected ']' or '}' to end the range", self.pos)),
+        };
+        self.pos += 1;
+        Ok(Token::Range {
+            lower,
+            upper,
+            include_lower,
+            include_upper,
+        })
+    }
+
+    // a quoted or unquoted bound, `None` for an open bound `*`
+    fn range_bound(&mut self, start: usize) -> Result<Option<String>> {
+        if self.peek_is('"') {
+            self.pos += 1;
+            return self.quoted(self.pos - 1).map(Some);
+        }
+        let bound_start = self.pos;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ']' || c == '}' {
+                break;
+            }
+            self.pos += 1;
+            if c == '\\' && self.pos < self.chars.len() {
+                text.push(self.chars[self.pos]);
+                self.pos += 1;
+            } else {
+                text.push(c);
+            }
+        }
+        if self.pos == bound_start {
+            return Err(self.error("expected a range bound", start));
+        }
+        if self.chars[bound_start..self.pos] == ['*'] {
+            Ok(None)
+        } else {
+            Ok(Some(text))
+        }
+    }
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<(Token, usize)>,
+    upto: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(query: &'a str) -> Result<Parser<'a>> {
+        let lexer = Lexer {
+            query,
+            chars: query.chars().collect(),
+            pos: 0,
+        };
+        Ok(Parser {
+            query,
+            tokens: lexer.tokenize()?,
+            upto: 0,
+        })
+    }
+
+    fn parse(&mut self) -> Result<Vec<ClauseNode>> {
+        let clauses = self.parse_query(None)?;
+        if let Some((token, pos)) = self.tokens.get(self.upto) {
+            return Err(parse_error(
+                self.query,
+                &format!("unexpected '{}'", token),
+                *pos,
+            ));
+        }
+        Ok(clauses)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.upto).map(|t| &t.0)
+    }
+
+    fn next_token(&mut self) -> Result<(Token, usize)> {
+        match self.tokens.get(self.upto) {
+            Some(token) => {
+                self.upto += 1;
+                Ok(token.clone())
+            }
+            None => Err(parse_error(
+                self.query,
+                "unexpected end of query",
+                self.query.chars().count(),
+            )),
+        }
+    }
+
+    // `Modifier Clause (Conjunction Modifier Clause)*` up to a closing
+    // parenthesis or the end of the query
+    fn parse_query(&mut self, field: Option<&str>) -> Result<Vec<ClauseNode>> {
+        let mut clauses = vec![];
+        while self.peek().is_some() && self.peek() != Some(&Token::RParen) {
+            let conj = if clauses.is_empty() {
+                Conjunction::None
+            } else {
+                match self.peek() {
+                    Some(Token::And) => Conjunction::And,
+                    Some(Token::Or) => Conjunction::Or,
+                    _ => Conjunction::None,
+                }
+            };
+            if conj != Conjunction::None {
+                self.upto += 1;
+            }
+            let modifier = match self.peek() {
+                Some(Token::Plus) => Modifier::Req,
+                Some(Token::Minus) | Some(Token::Not) => Modifier::Not,
+                _ => Modifier::None,
+            };
+            if modifier != Modifier::None {
+                self.upto += 1;
+            }
+            let node = self.parse_clause(field)?;
+            clauses.push(ClauseNode {
+                conj,
+                modifier,
+                node,
+            });
+        }
+        Ok(clauses)
+    }
+
+    fn parse_clause(&mut self, field: Option<&str>) -> Result<Node> {
+        let (token, pos) = self.next_token()?;
+        let field_name = match token {
+            Token::Term(ref text) if self.peek() == Some(&Token::Colon) => Some(unescape(text)),
+            _ => None,
+        };
+        let (token, pos, field) = match field_name {
+            Some(name) => {
+                self.upto += 1;
+                let (token, pos) = self.next_token()?;
+                (token, pos, Some(name))
+            }
+            None => (token, pos, field.map(str::to_string)),
+        };
+
+        let kind = match token {
+            Token::LParen => {
+                let clauses = self.parse_query(field.as_ref().map(String::as_str))?;
+                if clauses.is_empty() {
+                    return Err(parse_error(self.query, "expected a clause", pos));
+                }
+                match self.next_token() {
+                    Ok((Token::RParen, _)) => {}
+                    _ => return Err(parse_error(self.query, "missing ')'", pos)),
+                }
+                let boost = self.parse_boost();
+                return Ok(Node::Group { clauses, boost });
+            }
+            Token::Term(text) => LeafKind::Term { text, fuzzy: None },
+            Token::Phrase(text) => LeafKind::Phrase { text, slop: None },
+            Token::Regexp(text) => LeafKind::Regexp(text),
+            Token::Range {
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            } => LeafKind::Range {
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            },
+            token => {
+                return Err(parse_error(
+                    self.query,
+                    &format!("unexpected '{}'", token),
+                    pos,
+                ));
+            }
+        };
+
+        // the fuzziness or slop and the boost, in any order
+        let mut kind = kind;
+        let mut boost = None;
+        loop {
+            match self.peek().cloned() {
+                Some(Token::Fuzzy(n)) => match kind {
+                    LeafKind::Term { ref mut fuzzy, .. } if fuzzy.is_none() => *fuzzy = Some(n),
+                    LeafKind::Phrase { ref mut slop, .. } if slop.is_none() => {
+                        *slop = Some(n.unwrap_or(0.0))
+                    }
+                    _ => break,
+                },
+                Some(Token::Boost(b)) if boost.is_none() => boost = Some(b),
+                _ => break,
+            }
+            self.upto += 1;
+        }
+        Ok(Node::Leaf {
+            field,
+            kind,
+            boost,
+            pos,
+        })
+    }
+
+    fn parse_boost(&mut self) -> Option<f32> {
+        if let Some(&Token::Boost(boost)) = self.peek() {
+            self.upto += 1;
+            Some(boost)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::{StandardAnalyzer, WhitespaceAnalyzer};
+    use core::codec::tests::TestCodec;
+
+    fn parse(parser: &QueryParser, query: &str) -> String {
+        let q: Box<dyn Query<TestCodec>> = parser.parse(query).unwrap().unwrap();
+        q.to_string()
+    }
+
+    fn whitespace_parser() -> QueryParser {
+        QueryParser::new(
+            vec![("title".to_string(), 1.0)],
+            Arc::new(WhitespaceAnalyzer),
+        )
+    }
+
+    #[test]
+    fn test_boolean_syntax() {
+        let parser = whitespace_parser();
+        assert_eq!(
+            parse(&parser, "a AND b"),
+            "BooleanQuery(must: [TermQuery(field: title, term: a, boost: 1), TermQuery(field: \
+             title, term: b, boost: 1)], should: [], filters: [], match: 0)"
+        );
+        assert_eq!(
+            parse(&parser, "a -b !c"),
+            "BooleanQuery(must: [], should: [TermQuery(field: title, term: a, boost: 1)], \
+             filters: [], must_not: [TermQuery(field: title, term: b, boost: 1), TermQuery(field: \
+             title, term: c, boost: 1)], match: 1)"
+        );
+        assert_eq!(
+            parse(&parser, "body:(x || y)^2 AND NOT title:z"),
+            "BooleanQuery(must: [BoostQuery(query: BooleanQuery(must: [], should: \
+             [TermQuery(field: body, term: x, boost: 1), TermQuery(field: body, term: y, boost: \
+             1)], filters: [], match: 1), boost: 2)], should: [], filters: [], must_not: \
+             [TermQuery(field: title, term: z, boost: 1)], match: 0)"
+        );
+
+        let mut parser = whitespace_parser();
+        parser.set_default_operator(Operator::And);
+        assert_eq!(
+            parse(&parser, "a b OR c"),
+            "BooleanQuery(must: [TermQuery(field: title, term: a, boost: 1)], should: \
+             [TermQuery(field: title, term: b, boost: 1), TermQuery(field: title, term: c, boost: \
+             1)], filters: [], match: 0)"
+        );
+    }
+
+    #[test]
+    fn test_term_syntax() {
+        let mut parser = whitespace_parser();
+        parser.set_point_field("price", PointValueType::Integer);
+        let phrase = parse(&parser, "\"a b\"~2^3");
+        assert!(phrase.starts_with("BoostQuery(query: PhraseQuery(field: title"));
+        assert!(phrase.ends_with("positions: [0, 1], slop: 2), boost: 3)"));
+        assert_eq!(
+            parse(&parser, "roam~1"),
+            "FuzzyQuery(field: title, term: roam, max_edits: 1, prefix_length: 0, max_expansions: \
+             50, transpositions: true, boost: 1)"
+        );
+        assert!(parse(&parser, "te\\*s*").starts_with("PrefixQuery(field: title, prefix: \"te*s\""));
+        assert!(parse(&parser, "te?t").starts_with("WildcardQuery(field: title, term: \"te?t\""));
+        assert!(parse(&parser, "/jo[ae]l/")
+            .starts_with("RegexpQuery(field: title, regexp: \"jo[ae]l\""));
+        assert_eq!(
+            parse(&parser, "title:{a TO *]"),
+            "TermRangeQuery(field: title, range: {\"a\" TO *], boost: 1)"
+        );
+        assert!(parse(&parser, "price:[10 TO 20}").starts_with("PointRangeQuery(field: price"));
+        assert_eq!(
+            parse(&parser, "price:{2147483647 TO *]"),
+            "MatchNoDocsQuery(reason: empty range on field price)"
+        );
+        assert_eq!(
+            parse(&parser, "price:[* TO -2147483648}"),
+            "MatchNoDocsQuery(reason: empty range on field price)"
+        );
+        parser.set_point_field("time", PointValueType::Long);
+        assert_eq!(
+            parse(&parser, "time:{9223372036854775807 TO *]"),
+            "MatchNoDocsQuery(reason: empty range on field time)"
+        );
+        assert!(parse(&parser, "time:{0 TO *]").starts_with("PointRangeQuery(field: time"));
+        assert_eq!(parse(&parser, "*:*"), "MatchAllDocsQuery()");
+        assert_eq!(
+            parse(&parser, "a\\:b"),
+            "TermQuery(field: title, term: a:b, boost: 1)"
+        );
+    }
+
+    #[test]
+    fn test_analysis() {
+        let parser = QueryParser::new(
+            vec![("title".to_string(), 2.0), ("body".to_string(), 1.0)],
+            Arc::new(StandardAnalyzer::default()),
+        );
+        assert_eq!(
+            parse(&parser, "The WI-FI"),
+            "BooleanQuery(must: [], should: [BoostQuery(query: BooleanQuery(must: [], should: \
+             [TermQuery(field: title, term: wi, boost: 1), TermQuery(field: title, term: fi, \
+             boost: 1)], filters: [], match: 1), boost: 2), BooleanQuery(must: [], should: \
+             [TermQuery(field: body, term: wi, boost: 1), TermQuery(field: body, term: fi, boost: \
+             1)], filters: [], match: 1)], filters: [], match: 1)"
+        );
+        // the removed stop word leaves a hole in the phrase
+        let phrase = parse(&parser, "body:\"House of Cards\"");
+        assert!(phrase.starts_with("PhraseQuery(field: body"));
+        assert!(phrase.ends_with("positions: [0, 2], slop: 0)"));
+        assert!(parser.parse::<TestCodec>("the").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parser = whitespace_parser();
+        let error = |query: &str| {
+            parser
+                .parse::<TestCodec>(query)
+                .err()
+                .map(|e| e.to_string())
+                .unwrap()
+        };
+        assert_eq!(
+            error("a AND (b"),
+            "Illegal argument: Cannot parse 'a AND (b': missing ')' at position 6"
+        );
+        assert_eq!(
+            error("a \"b c"),
+            "Illegal argument: Cannot parse 'a \"b c': unterminated phrase at position 2"
+        );
+        assert_eq!(
+            error("a ) b"),
+            "Illegal argument: Cannot parse 'a ) b': unexpected ')' at position 2"
+        );
+        assert_eq!(
+            error("*ab"),
+            "Illegal argument: Cannot parse '*ab': '*' or '?' not allowed as first character in a \
+             wildcard term at position 0"
+        );
+        assert_eq!(
+            error("[a TO b"),
+            "Illegal argument: Cannot parse '[a TO b': expected ']' or '}' to end the range at \
+             position 7"
+        );
+        assert_eq!(
+            error("term~1.2.3"),
+            "Illegal argument: Cannot parse 'term~1.2.3': invalid number '1.2.3' at position 5"
+        );
+        assert_eq!(
+            error("a^2..5 b"),
+            "Illegal argument: Cannot parse 'a^2..5 b': invalid number '2..5' at position 2"
+        );
+        assert_eq!(
+            error("a^ b"),
+            "Illegal argument: Cannot parse 'a^ b': expected a boost after '^' at position 1"
+        );
+    }
+}
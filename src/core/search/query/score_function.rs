@@ -0,0 +1,583 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::sort_field::{SortFieldType, SortedNumericSelector, SortedNumericSelectorType};
+use core::util::{BitsMut, DocId};
+
+use error::{ErrorKind, Result};
+
+use std::fmt;
+
+/// A function computing a value for each doc, combined with the score of
+/// the query wrapped by a `FunctionScoreQuery`.
+#[derive(Debug, Clone)]
+pub enum ScoreFunction {
+    FieldValue(FieldValueFactorFunction),
+    Decay(DecayFunction),
+    RandomScore(RandomScoreFunction),
+    /// A constant value.
+    Weight(f32),
+    /// The value of the function multiplied with a weight.
+    Weighted(Box<ScoreFunction>, f32),
+}
+
+impl ScoreFunction {
+    /// Multiplies the values of this function with `weight`.
+    pub fn weighted(self, weight: f32) -> ScoreFunction {
+        ScoreFunction::Weighted(Box::new(self), weight)
+    }
+
+    /// Creates the per segment function of a leaf.
+    pub fn leaf_function<C: Codec>(
+        &self,
+        ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Box<dyn LeafScoreFunction>> {
+        Ok(match self {
+            ScoreFunction::FieldValue(f) => Box::new(LeafFieldValueFactor {
+                function: f.clone(),
+                values: f.source.values(ctx)?,
+            }),
+            ScoreFunction::Decay(f) => Box::new(LeafDecay {
+                function: f.clone(),
+                values: f.source.values(ctx)?,
+            }),
+            ScoreFunction::RandomScore(f) => Box::new(LeafRandomScore {
+                seed: f.seed,
+                doc_base: ctx.doc_base,
+            }),
+            ScoreFunction::Weight(w) => Box::new(LeafWeight { weight: *w }),
+            ScoreFunction::Weighted(f, w) => Box::new(LeafWeighted {
+                function: f.leaf_function(ctx)?,
+                weight: *w,
+            }),
+        })
+    }
+}
+
+impl fmt::Display for ScoreFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScoreFunction::FieldValue(func) => write!(f, "{}", func),
+            ScoreFunction::Decay(func) => write!(f, "{}", func),
+            ScoreFunction::RandomScore(func) => write!(f, "{}", func),
+            ScoreFunction::Weight(w) => write!(f, "weight({})", w),
+            ScoreFunction::Weighted(func, w) => write!(f, "{} * weight({})", func, w),
+        }
+    }
+}
+
+impl From<FieldValueFactorFunction> for ScoreFunction {
+    fn from(f: FieldValueFactorFunction) -> Self {
+        ScoreFunction::FieldValue(f)
+    }
+}
+
+impl From<DecayFunction> for ScoreFunction {
+    fn from(f: DecayFunction) -> Self {
+        ScoreFunction::Decay(f)
+    }
+}
+
+impl From<RandomScoreFunction> for ScoreFunction {
+    fn from(f: RandomScoreFunction) -> Self {
+        ScoreFunction::RandomScore(f)
+    }
+}
+
+/// The per segment instance of a `ScoreFunction`.
+pub trait LeafScoreFunction {
+    fn score(&mut self, doc: DocId) -> Result<f64>;
+
+    fn explain(&mut self, doc: DocId) -> Result<Explanation>;
+}
+
+/// The numeric doc values of a field read by a `ScoreFunction`.
+///
+/// Multi valued fields are read from the sorted numeric doc values, and
+/// reduced to a single value by the `selector`. Date fields are read as
+/// `Long` values, e.g. milliseconds since the epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericFieldSource {
+    field: String,
+    numeric_type: SortFieldType,
+    selector: Option<SortedNumericSelectorType>,
+}
+
+impl NumericFieldSource {
+    pub fn new(field: &str, numeric_type: SortFieldType) -> Result<Self> {
+        Self::build(field, numeric_type, None)
+    }
+
+    pub fn multi_valued(
+        field: &str,
+        numeric_type: SortFieldType,
+        selector: SortedNumericSelectorType,
+    ) -> Result<Self> {
+        Self::build(field, numeric_type, Some(selector))
+    }
+
+    fn build(
+        field: &str,
+        numeric_type: SortFieldType,
+        selector: Option<SortedNumericSelectorType>,
+    ) -> Result<Self> {
+        match numeric_type {
+            SortFieldType::Int
+            | SortFieldType::Long
+            | SortFieldType::Float
+            | SortFieldType::Double => {}
+            _ => bail!(ErrorKind::IllegalArgument(format!(
+                "field '{}' must be of a numeric type, got {:?}",
+                field, numeric_type
+            ))),
+        }
+        Ok(NumericFieldSource {
+            field: field.to_string(),
+            numeric_type,
+            selector,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the values of the field in a leaf.
+    pub fn values<C: Codec>(&self, ctx: &LeafReaderContext<'_, C>) -> Result<NumericFieldValues> {
+        let values = match self.selector {
+            Some(selector) => SortedNumericSelector::wrap(
+                ctx.reader.get_sorted_numeric_doc_values(&self.field)?,
+                selector,
+                self.numeric_type,
+            )?,
+            None => ctx.reader.get_numeric_doc_values(&self.field)?,
+        };
+        let docs_with_field = ctx.reader.get_docs_with_field(&self.field)?;
+        Ok(NumericFieldValues {
+            values,
+            docs_with_field,
+            numeric_type: self.numeric_type,
+        })
+    }
+}
+
+/// The per segment values of a `NumericFieldSource`.
+pub struct NumericFieldValues {
+    values: Box<dyn NumericDocValues>,
+    docs_with_field: Box<dyn BitsMut>,
+    numeric_type: SortFieldType,
+}
+
+impl NumericFieldValues {
+    /// Returns the value of `doc`, or `None` if it has no value.
+    pub fn get(&mut self, doc: DocId) -> Result<Option<f64>> {
+        if !self.docs_with_field.get(doc as usize)? {
+            return Ok(None);
+        }
+        let raw = self.values.get_mut(doc)?;
+        let value = match self.numeric_type {
+            SortFieldType::Float => f64::from(f32::from_bits(raw as u32)),
+            SortFieldType::Double => f64::from_bits(raw as u64),
+            _ => raw as f64,
+        };
+        Ok(Some(value))
+    }
+}
+
+/// A modifier applied to the value of a `FieldValueFactorFunction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValueModifier {
+    None,
+    /// `log10(1 + x)`
+    Log1p,
+    /// `ln(1 + x)`
+    Ln1p,
+    Sqrt,
+    Square,
+    Reciprocal,
+}
+
+impl FieldValueModifier {
+    pub fn apply(self, value: f64) -> f64 {
+        match self {
+            FieldValueModifier::None => value,
+            FieldValueModifier::Log1p => (1.0 + value).log10(),
+            FieldValueModifier::Ln1p => value.ln_1p(),
+            FieldValueModifier::Sqrt => value.sqrt(),
+            FieldValueModifier::Square => value * value,
+            FieldValueModifier::Reciprocal => 1.0 / value,
+        }
+    }
+}
+
+impl fmt::Display for FieldValueModifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FieldValueModifier::None => "none",
+            FieldValueModifier::Log1p => "log1p",
+            FieldValueModifier::Ln1p => "ln1p",
+            FieldValueModifier::Sqrt => "sqrt",
+            FieldValueModifier::Square => "square",
+            FieldValueModifier::Reciprocal => "reciprocal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Computes `modifier(factor * value)` from the value of a numeric field.
+///
+/// Docs without a value use the `missing` value instead, factor and
+/// modifier are applied to it like to a value of the field. Without a
+/// `missing` value these docs score 1.0, where Elasticsearch fails the
+/// request.
+#[derive(Debug, Clone)]
+pub struct FieldValueFactorFunction {
+    source: NumericFieldSource,
+    factor: f64,
+    modifier: FieldValueModifier,
+    missing: Option<f64>,
+}
+
+impl FieldValueFactorFunction {
+    pub fn new(source: NumericFieldSource, factor: f64, modifier: FieldValueModifier) -> Self {
+        FieldValueFactorFunction {
+            source,
+            factor,
+            modifier,
+            missing: None,
+        }
+    }
+
+    pub fn with_missing(mut self, missing: f64) -> Self {
+        self.missing = Some(missing);
+        self
+    }
+
+    fn compute(&self, value: f64) -> Result<f64> {
+        let result = self.modifier.apply(self.factor * value);
+        if !result.is_finite() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "result of field modification [{}({} * {})] must be a finite number",
+                self.modifier, value, self.factor
+            )));
+        }
+        Ok(result)
+    }
+}
+
+impl fmt::Display for FieldValueFactorFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "field_value_factor({}(doc['{}'] * {})",
+            self.modifier, self.source.field, self.factor
+        )?;
+        if let Some(missing) = self.missing {
+            write!(f, ", missing: {}", missing)?;
+        }
+        write!(f, ")")
+    }
+}
+
+struct LeafFieldValueFactor {
+    function: FieldValueFactorFunction,
+    values: NumericFieldValues,
+}
+
+impl LeafScoreFunction for LeafFieldValueFactor {
+    fn score(&mut self, doc: DocId) -> Result<f64> {
+        match self.values.get(doc)?.or(self.function.missing) {
+            Some(value) => self.function.compute(value),
+            None => Ok(1.0),
+        }
+    }
+
+    fn explain(&mut self, doc: DocId) -> Result<Explanation> {
+        let description = match (self.values.get(doc)?, self.function.missing) {
+            (Some(value), _) => format!(
+                "field value function: {}(doc['{}'].value={} * factor={})",
+                self.function.modifier, self.function.source.field, value, self.function.factor
+            ),
+            (None, Some(missing)) => format!(
+                "field value function: {}(missing value {} for doc['{}'] * factor={})",
+                self.function.modifier, missing, self.function.source.field, self.function.factor
+            ),
+            (None, None) => format!(
+                "field value function: no value for doc['{}']",
+                self.function.source.field
+            ),
+        };
+        let score = self.score(doc)?;
+        Ok(Explanation::new(true, score as f32, description, vec![]))
+    }
+}
+
+/// The shape of the curve of a `DecayFunction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecayType {
+    Gauss,
+    Exp,
+    Linear,
+}
+
+impl fmt::Display for DecayType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecayType::Gauss => write!(f, "gauss"),
+            DecayType::Exp => write!(f, "exp"),
+            DecayType::Linear => write!(f, "linear"),
+        }
+    }
+}
+
+/// Scores docs by the distance of the value of a numeric or date field to
+/// `origin`.
+///
+/// Docs within `offset` of the origin score 1.0, and the score decreases
+/// with the distance beyond it, down to `decay` at `offset + scale`. Docs
+/// without a value score 1.0.
+#[derive(Debug, Clone)]
+pub struct DecayFunction {
+    source: NumericFieldSource,
+    decay_type: DecayType,
+    origin: f64,
+    scale: f64,
+    offset: f64,
+    decay: f64,
+}
+
+impl DecayFunction {
+    pub fn new(
+        source: NumericFieldSource,
+        decay_type: DecayType,
+        origin: f64,
+        scale: f64,
+        offset: f64,
+        decay: f64,
+    ) -> Result<Self> {
+        if scale.is_nan() || scale <= 0.0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "decay scale must be positive, got {}",
+                scale
+            )));
+        }
+        if offset.is_nan() || offset < 0.0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "decay offset must not be negative, got {}",
+                offset
+            )));
+        }
+        if decay.is_nan() || decay <= 0.0 || decay >= 1.0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "decay must be in (0, 1), got {}",
+                decay
+            )));
+        }
+        Ok(DecayFunction {
+            source,
+            decay_type,
+            origin,
+            scale,
+            offset,
+            decay,
+        })
+    }
+
+    /// Returns the decayed score of a doc with the given field value.
+    pub fn compute(&self, value: f64) -> f64 {
+        let distance = ((value - self.origin).abs() - self.offset).max(0.0);
+        match self.decay_type {
+            DecayType::Gauss => {
+                let sigma_square = -self.scale * self.scale / (2.0 * self.decay.ln());
+                (-distance * distance / (2.0 * sigma_square)).exp()
+            }
+            DecayType::Exp => (self.decay.ln() / self.scale * distance).exp(),
+            DecayType::Linear => {
+                let s = self.scale / (1.0 - self.decay);
+                ((s - distance) / s).max(0.0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DecayFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}(doc['{}'], origin: {}, scale: {}, offset: {}, decay: {})",
+            self.decay_type, self.source.field, self.origin, self.scale, self.offset, self.decay
+        )
+    }
+}
+
+struct LeafDecay {
+    function: DecayFunction,
+    values: NumericFieldValues,
+}
+
+impl LeafScoreFunction for LeafDecay {
+    fn score(&mut self, doc: DocId) -> Result<f64> {
+        Ok(match self.values.get(doc)? {
+            Some(value) => self.function.compute(value),
+            None => 1.0,
+        })
+    }
+
+    fn explain(&mut self, doc: DocId) -> Result<Explanation> {
+        let description = match self.values.get(doc)? {
+            Some(value) => format!(
+                "{} with doc['{}'].value={}",
+                self.function, self.function.source.field, value
+            ),
+            None => format!("{} with missing value", self.function),
+        };
+        let score = self.score(doc)?;
+        Ok(Explanation::new(true, score as f32, description, vec![]))
+    }
+}
+
+/// Scores docs randomly in `[0, 1)`.
+///
+/// The score only depends on the seed and the doc id, so it is stable
+/// across requests with the same seed as long as the index does not change.
+#[derive(Debug, Clone)]
+pub struct RandomScoreFunction {
+    seed: u64,
+}
+
+impl RandomScoreFunction {
+    pub fn new(seed: u64) -> Self {
+        RandomScoreFunction { seed }
+    }
+}
+
+impl fmt::Display for RandomScoreFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "random_score(seed: {})", self.seed)
+    }
+}
+
+struct LeafRandomScore {
+    seed: u64,
+    doc_base: DocId,
+}
+
+impl LeafScoreFunction for LeafRandomScore {
+    fn score(&mut self, doc: DocId) -> Result<f64> {
+        // the finalizer of splitmix64 over the seeded global doc id
+        let mut z = self.seed.wrapping_add(
+            (i64::from(self.doc_base + doc) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+        );
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // the top 24 bits, so the score is exact as a f32
+        Ok((z >> 40) as f64 / (1u64 << 24) as f64)
+    }
+
+    fn explain(&mut self, doc: DocId) -> Result<Explanation> {
+        let score = self.score(doc)?;
+        Ok(Explanation::new(
+            true,
+            score as f32,
+            format!("random score function (seed: {})", self.seed),
+            vec![],
+        ))
+    }
+}
+
+struct LeafWeight {
+    weight: f32,
+}
+
+impl LeafScoreFunction for LeafWeight {
+    fn score(&mut self, _doc: DocId) -> Result<f64> {
+        Ok(f64::from(self.weight))
+    }
+
+    fn explain(&mut self, _doc: DocId) -> Result<Explanation> {
+        Ok(Explanation::new(
+            true,
+            self.weight,
+            "weight".to_string(),
+            vec![],
+        ))
+    }
+}
+
+struct LeafWeighted {
+    function: Box<dyn LeafScoreFunction>,
+    weight: f32,
+}
+
+impl LeafScoreFunction for LeafWeighted {
+    fn score(&mut self, doc: DocId) -> Result<f64> {
+        Ok(self.function.score(doc)? * f64::from(self.weight))
+    }
+
+    fn explain(&mut self, doc: DocId) -> Result<Explanation> {
+        let function_expl = self.function.explain(doc)?;
+        let weight_expl = Explanation::new(true, self.weight, "weight".to_string(), vec![]);
+        Ok(Explanation::new(
+            true,
+            self.score(doc)? as f32,
+            "product of:".to_string(),
+            vec![function_expl, weight_expl],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay_functions() {
+        let source = NumericFieldSource::new("date", SortFieldType::Long).unwrap();
+        for decay_type in &[DecayType::Gauss, DecayType::Exp, DecayType::Linear] {
+            let f =
+                DecayFunction::new(source.clone(), *decay_type, 1000.0, 100.0, 10.0, 0.5).unwrap();
+            assert!((f.compute(1000.0) - 1.0).abs() < 1e-9);
+            assert!((f.compute(995.0) - 1.0).abs() < 1e-9);
+            // the score is `decay` at `offset + scale` from the origin
+            assert!((f.compute(1110.0) - 0.5).abs() < 1e-9);
+            assert!((f.compute(890.0) - 0.5).abs() < 1e-9);
+            assert!(f.compute(1200.0) < 0.5);
+        }
+
+        assert!(DecayFunction::new(source.clone(), DecayType::Exp, 0.0, 0.0, 0.0, 0.5).is_err());
+        assert!(DecayFunction::new(source, DecayType::Exp, 0.0, 1.0, 0.0, 1.0).is_err());
+        assert!(NumericFieldSource::new("title", SortFieldType::String).is_err());
+    }
+
+    #[test]
+    fn test_field_value_factor() {
+        let source = NumericFieldSource::new("popularity", SortFieldType::Int).unwrap();
+        let f = FieldValueFactorFunction::new(source, 1.0, FieldValueModifier::Log1p);
+        assert!((f.compute(9.0).unwrap() - 1.0).abs() < 1e-9);
+        assert!(f.compute(-1.0).is_err());
+        assert_eq!(
+            f.to_string(),
+            "field_value_factor(log1p(doc['popularity'] * 1))"
+        );
+        let f = f.with_missing(0.5);
+        assert_eq!(
+            f.to_string(),
+            "field_value_factor(log1p(doc['popularity'] * 1), missing: 0.5)"
+        );
+    }
+}
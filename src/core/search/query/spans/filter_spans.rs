@@ -0,0 +1,245 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::PostingIterator;
+use core::search::query::spans::{SpanCollector, Spans, SpansEnum, NO_MORE_POSITIONS};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+
+/// Status returned from `SpansFilter::accept` that indicates whether a
+/// candidate match should be accepted, rejected, or rejected and move on to
+/// the next document.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AcceptStatus {
+    /// Indicates the match should be accepted
+    Yes,
+    /// Indicates the match should be rejected
+    No,
+    /// Indicates the match should be rejected, and the enumeration may
+    /// continue with the next document.
+    NoMoreInCurrentDoc,
+}
+
+/// Decides which matches of the `Spans` wrapped by a `FilterSpans` are kept.
+pub trait SpansFilter<P: PostingIterator> {
+    /// Returns the status of the current position of `candidate`, which is
+    /// positioned on a match.
+    fn accept(&mut self, candidate: &mut SpansEnum<P>) -> Result<AcceptStatus>;
+}
+
+/// A `Spans` wrapping another spans instance, whose matches are filtered by
+/// a `SpansFilter`.
+///
+/// The matches are only checked through the two phase iteration, the
+/// approximation is the one of the wrapped spans.
+pub struct FilterSpans<P: PostingIterator, F: SpansFilter<P>> {
+    spans: Box<SpansEnum<P>>,
+    filter: F,
+    at_first_in_current_doc: bool,
+    start_pos: i32,
+}
+
+impl<P: PostingIterator, F: SpansFilter<P>> FilterSpans<P, F> {
+    pub fn new(spans: SpansEnum<P>, filter: F) -> Self {
+        FilterSpans {
+            spans: Box::new(spans),
+            filter,
+            at_first_in_current_doc: false,
+            start_pos: -1,
+        }
+    }
+
+    /// Returns true if the current document has an accepted match, which
+    /// the next call to `next_start_position` returns.
+    fn two_phase_current_doc_matches(&mut self) -> Result<bool> {
+        self.at_first_in_current_doc = false;
+        self.start_pos = self.spans.next_start_position()?;
+        debug_assert_ne!(self.start_pos, NO_MORE_POSITIONS);
+        loop {
+            match self.filter.accept(&mut self.spans)? {
+                AcceptStatus::Yes => {
+                    self.at_first_in_current_doc = true;
+                    return Ok(true);
+                }
+                AcceptStatus::No => {
+                    self.start_pos = self.spans.next_start_position()?;
+                    if self.start_pos == NO_MORE_POSITIONS {
+                        self.start_pos = -1;
+                        return Ok(false);
+                    }
+                }
+                AcceptStatus::NoMoreInCurrentDoc => {
+                    self.start_pos = -1;
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    // moves from `doc` to the first doc with an accepted match
+    fn next_accepted_doc(&mut self, mut doc: DocId) -> Result<DocId> {
+        while doc != NO_MORE_DOCS {
+            if self.two_phase_current_doc_matches()? {
+                break;
+            }
+            doc = self.spans.next()?;
+        }
+        Ok(doc)
+    }
+}
+
+impl<P: PostingIterator, F: SpansFilter<P>> Spans for FilterSpans<P, F> {
+    fn next_start_position(&mut self) -> Result<i32> {
+        if self.at_first_in_current_doc {
+            self.at_first_in_current_doc = false;
+            return Ok(self.start_pos);
+        }
+
+        loop {
+            self.start_pos = self.spans.next_start_position()?;
+            if self.start_pos == NO_MORE_POSITIONS {
+                return Ok(NO_MORE_POSITIONS);
+            }
+            match self.filter.accept(&mut self.spans)? {
+                AcceptStatus::Yes => return Ok(self.start_pos),
+                AcceptStatus::No => {}
+                AcceptStatus::NoMoreInCurrentDoc => {
+                    self.start_pos = NO_MORE_POSITIONS;
+                    return Ok(NO_MORE_POSITIONS);
+                }
+            }
+        }
+    }
+
+    fn start_position(&self) -> i32 {
+        if self.at_first_in_current_doc {
+            -1
+        } else {
+            self.start_pos
+        }
+    }
+
+    fn end_position(&self) -> i32 {
+        if self.at_first_in_current_doc {
+            -1
+        } else if self.start_pos != NO_MORE_POSITIONS {
+            self.spans.end_position()
+        } else {
+            NO_MORE_POSITIONS
+        }
+    }
+
+    fn width(&self) -> i32 {
+        self.spans.width()
+    }
+
+    fn collect(&mut self, collector: &mut impl SpanCollector) -> Result<()> {
+        self.spans.collect(collector)
+    }
+
+    fn positions_cost(&self) -> f32 {
+        // support_two_phase always return `true`
+        unreachable!()
+    }
+}
+
+impl<P: PostingIterator, F: SpansFilter<P>> DocIterator for FilterSpans<P, F> {
+    fn doc_id(&self) -> DocId {
+        self.spans.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let doc = self.spans.next()?;
+        self.next_accepted_doc(doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let doc = self.spans.advance(target)?;
+        self.next_accepted_doc(doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.spans.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        if self.spans.support_two_phase() && !self.spans.matches()? {
+            return Ok(false);
+        }
+        self.two_phase_current_doc_matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        if self.spans.support_two_phase() {
+            self.spans.match_cost()
+        } else {
+            self.spans.positions_cost()
+        }
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.spans.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.spans.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::search::query::spans::tests::*;
+    use core::search::query::spans::{PostingsFlag, SpanQuery, SpanWeight};
+    use core::search::searcher::SearchPlanBuilder;
+
+    // accepts the odd start positions, and no match from the position 5
+    struct OddStartFilter;
+
+    impl<P: PostingIterator> SpansFilter<P> for OddStartFilter {
+        fn accept(&mut self, candidate: &mut SpansEnum<P>) -> Result<AcceptStatus> {
+            let start = candidate.start_position();
+            if start >= 5 {
+                Ok(AcceptStatus::NoMoreInCurrentDoc)
+            } else if start % 2 == 1 {
+                Ok(AcceptStatus::Yes)
+            } else {
+                Ok(AcceptStatus::No)
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_spans() {
+        let index = span_test_index(&["a a a a a a a", "b a", "b b b b b b a", "a b"]);
+        let searcher = index.searcher();
+        let weight =
+            SpanQuery::<CodecEnum>::span_weight(&span_term("a"), &searcher, false).unwrap();
+        let mut positions = vec![];
+        for leaf in searcher.leaves() {
+            if let Some(spans) = weight.get_spans(&leaf, &PostingsFlag::Positions).unwrap() {
+                let mut spans = FilterSpans::new(spans, OddStartFilter);
+                collect_positions(&mut spans, leaf.doc_base, &mut positions);
+            }
+        }
+        // the docs without an accepted match are skipped
+        assert_eq!(positions, vec![(0, 1, 2), (0, 3, 4), (1, 1, 2)]);
+    }
+}
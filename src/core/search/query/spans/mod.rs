@@ -16,18 +16,97 @@ mod span;
 
 pub use self::span::*;
 
+mod filter_spans;
+
+pub use self::filter_spans::*;
+
 mod span_boost;
 
 pub use self::span_boost::*;
 
+mod span_contain;
+
+pub use self::span_contain::*;
+
+mod span_multi_term;
+
+pub use self::span_multi_term::*;
+
 mod span_near;
 
 pub use self::span_near::*;
 
+mod span_not;
+
+pub use self::span_not::*;
+
 mod span_or;
 
 pub use self::span_or::*;
 
+mod span_position;
+
+pub use self::span_position::*;
+
 mod span_term;
 
 pub use self::span_term::*;
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Fieldable, Term};
+    use core::index::tests::*;
+    use core::search::searcher::SearchPlanBuilder;
+    use core::search::{DocIterator, NO_MORE_DOCS};
+    use core::util::DocId;
+
+    /// An index of a "body" text field per text, two docs per segment.
+    pub fn span_test_index(texts: &[&str]) -> TestIndex {
+        let docs: Vec<Vec<Box<dyn Fieldable>>> =
+            texts.iter().map(|t| vec![text_field("body", t)]).collect();
+        TestIndex::new(docs, 2)
+    }
+
+    pub fn span_term(text: &str) -> SpanQueryEnum {
+        SpanQueryEnum::Term(SpanTermQuery::new(
+            Term::new("body".into(), text.as_bytes().to_vec()),
+            None,
+        ))
+    }
+
+    /// Appends the `(doc, start, end)` of every match of `spans`.
+    pub fn collect_positions<S: Spans>(
+        spans: &mut S,
+        doc_base: DocId,
+        positions: &mut Vec<(DocId, i32, i32)>,
+    ) {
+        loop {
+            let doc = spans.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            loop {
+                let start = spans.next_start_position().unwrap();
+                if start == NO_MORE_POSITIONS {
+                    break;
+                }
+                positions.push((doc_base + doc, start, spans.end_position()));
+            }
+        }
+    }
+
+    /// Returns the `(doc, start, end)` of every match of `query`.
+    pub fn span_positions(index: &TestIndex, query: &SpanQueryEnum) -> Vec<(DocId, i32, i32)> {
+        let searcher = index.searcher();
+        let weight = SpanQuery::<CodecEnum>::span_weight(query, &searcher, false).unwrap();
+        let mut positions = vec![];
+        for leaf in searcher.leaves() {
+            if let Some(mut spans) = weight.get_spans(&leaf, &PostingsFlag::Positions).unwrap() {
+                collect_positions(&mut spans, leaf.doc_base, &mut positions);
+            }
+        }
+        positions
+    }
+}
@@ -16,6 +16,11 @@ use core::codec::{PostingIterator, PostingIteratorFlags};
 use core::doc::Term;
 use core::index::reader::{LeafReaderContext, SearchLeafReader};
 use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    ContainSpans, FilterSpans, PositionRangeFilter, SpanContainWeight, SpanContainingQuery,
+    SpanFirstQuery, SpanMultiTermQueryWrapper, SpanMultiTermWeight, SpanNotFilter, SpanNotQuery,
+    SpanNotWeight, SpanPositionRangeQuery, SpanPositionRangeWeight, SpanWithinQuery,
+};
 use core::search::query::spans::{
     GapSpans, NearSpansOrdered, NearSpansUnordered, SpanGapQuery, SpanGapWeight, SpanNearQuery,
     SpanNearWeight,
@@ -71,6 +76,12 @@ pub enum SpanQueryEnum {
     Or(SpanOrQuery),
     Near(SpanNearQuery),
     Boost(SpanBoostQuery),
+    Not(SpanNotQuery),
+    PositionRange(SpanPositionRangeQuery),
+    First(SpanFirstQuery),
+    Containing(SpanContainingQuery),
+    Within(SpanWithinQuery),
+    MultiTerm(SpanMultiTermQueryWrapper),
 }
 
 impl<C: Codec> SpanQuery<C> for SpanQueryEnum {
@@ -87,6 +98,22 @@ impl<C: Codec> SpanQuery<C> for SpanQueryEnum {
             SpanQueryEnum::Or(q) => SpanWeightEnum::Or(q.span_weight(searcher, needs_scores)?),
             SpanQueryEnum::Near(q) => SpanWeightEnum::Near(q.span_weight(searcher, needs_scores)?),
             SpanQueryEnum::Boost(q) => q.span_weight(searcher, needs_scores)?,
+            SpanQueryEnum::Not(q) => SpanWeightEnum::Not(q.span_weight(searcher, needs_scores)?),
+            SpanQueryEnum::PositionRange(q) => {
+                SpanWeightEnum::PositionRange(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanQueryEnum::First(q) => {
+                SpanWeightEnum::PositionRange(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanQueryEnum::Containing(q) => {
+                SpanWeightEnum::Contain(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanQueryEnum::Within(q) => {
+                SpanWeightEnum::Contain(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanQueryEnum::MultiTerm(q) => {
+                SpanWeightEnum::MultiTerm(q.span_weight(searcher, needs_scores)?)
+            }
         };
         Ok(weight)
     }
@@ -98,6 +125,12 @@ impl<C: Codec> SpanQuery<C> for SpanQueryEnum {
             SpanQueryEnum::Or(q) => SpanQuery::<C>::field(q),
             SpanQueryEnum::Near(q) => SpanQuery::<C>::field(q),
             SpanQueryEnum::Boost(q) => SpanQuery::<C>::field(q),
+            SpanQueryEnum::Not(q) => SpanQuery::<C>::field(q),
+            SpanQueryEnum::PositionRange(q) => SpanQuery::<C>::field(q),
+            SpanQueryEnum::First(q) => SpanQuery::<C>::field(q),
+            SpanQueryEnum::Containing(q) => SpanQuery::<C>::field(q),
+            SpanQueryEnum::Within(q) => SpanQuery::<C>::field(q),
+            SpanQueryEnum::MultiTerm(q) => SpanQuery::<C>::field(q),
         }
     }
 
@@ -108,6 +141,12 @@ impl<C: Codec> SpanQuery<C> for SpanQueryEnum {
             SpanQueryEnum::Or(q) => SpanQuery::<C>::ctx(q),
             SpanQueryEnum::Near(q) => SpanQuery::<C>::ctx(q),
             SpanQueryEnum::Boost(q) => SpanQuery::<C>::ctx(q),
+            SpanQueryEnum::Not(q) => SpanQuery::<C>::ctx(q),
+            SpanQueryEnum::PositionRange(q) => SpanQuery::<C>::ctx(q),
+            SpanQueryEnum::First(q) => SpanQuery::<C>::ctx(q),
+            SpanQueryEnum::Containing(q) => SpanQuery::<C>::ctx(q),
+            SpanQueryEnum::Within(q) => SpanQuery::<C>::ctx(q),
+            SpanQueryEnum::MultiTerm(q) => SpanQuery::<C>::ctx(q),
         }
     }
 }
@@ -124,6 +163,12 @@ impl<C: Codec> Query<C> for SpanQueryEnum {
             SpanQueryEnum::Or(q) => q.create_weight(searcher, needs_scores),
             SpanQueryEnum::Near(q) => q.create_weight(searcher, needs_scores),
             SpanQueryEnum::Boost(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::Not(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::PositionRange(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::First(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::Containing(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::Within(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::MultiTerm(q) => q.create_weight(searcher, needs_scores),
         }
     }
 
@@ -134,6 +179,12 @@ impl<C: Codec> Query<C> for SpanQueryEnum {
             SpanQueryEnum::Or(q) => Query::<C>::extract_terms(q),
             SpanQueryEnum::Near(q) => Query::<C>::extract_terms(q),
             SpanQueryEnum::Boost(q) => Query::<C>::extract_terms(q),
+            SpanQueryEnum::Not(q) => Query::<C>::extract_terms(q),
+            SpanQueryEnum::PositionRange(q) => Query::<C>::extract_terms(q),
+            SpanQueryEnum::First(q) => Query::<C>::extract_terms(q),
+            SpanQueryEnum::Containing(q) => Query::<C>::extract_terms(q),
+            SpanQueryEnum::Within(q) => Query::<C>::extract_terms(q),
+            SpanQueryEnum::MultiTerm(q) => Query::<C>::extract_terms(q),
         }
     }
 
@@ -144,6 +195,12 @@ impl<C: Codec> Query<C> for SpanQueryEnum {
             SpanQueryEnum::Or(q) => Query::<C>::as_any(q),
             SpanQueryEnum::Near(q) => Query::<C>::as_any(q),
             SpanQueryEnum::Boost(q) => Query::<C>::as_any(q),
+            SpanQueryEnum::Not(q) => Query::<C>::as_any(q),
+            SpanQueryEnum::PositionRange(q) => Query::<C>::as_any(q),
+            SpanQueryEnum::First(q) => Query::<C>::as_any(q),
+            SpanQueryEnum::Containing(q) => Query::<C>::as_any(q),
+            SpanQueryEnum::Within(q) => Query::<C>::as_any(q),
+            SpanQueryEnum::MultiTerm(q) => Query::<C>::as_any(q),
         }
     }
 }
@@ -156,6 +213,12 @@ impl fmt::Display for SpanQueryEnum {
             SpanQueryEnum::Or(q) => write!(f, "SpanQueryEnum({})", q),
             SpanQueryEnum::Near(q) => write!(f, "SpanQueryEnum({})", q),
             SpanQueryEnum::Boost(q) => write!(f, "SpanQueryEnum({})", q),
+            SpanQueryEnum::Not(q) => write!(f, "SpanQueryEnum({})", q),
+            SpanQueryEnum::PositionRange(q) => write!(f, "SpanQueryEnum({})", q),
+            SpanQueryEnum::First(q) => write!(f, "SpanQueryEnum({})", q),
+            SpanQueryEnum::Containing(q) => write!(f, "SpanQueryEnum({})", q),
+            SpanQueryEnum::Within(q) => write!(f, "SpanQueryEnum({})", q),
+            SpanQueryEnum::MultiTerm(q) => write!(f, "SpanQueryEnum({})", q),
         }
     }
 }
@@ -229,6 +292,9 @@ pub enum SpansEnum<P: PostingIterator> {
     NearUnordered(Box<NearSpansUnordered<P>>),
     Or(SpanOrSpans<P>),
     Term(TermSpans<P>),
+    Not(FilterSpans<P, SpanNotFilter<P>>),
+    PositionRange(FilterSpans<P, PositionRangeFilter>),
+    Contain(ContainSpans<P>),
 }
 
 impl<P: PostingIterator> Spans for SpansEnum<P> {
@@ -239,6 +305,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.next_start_position(),
             SpansEnum::Or(s) => s.next_start_position(),
             SpansEnum::Term(s) => s.next_start_position(),
+            SpansEnum::Not(s) => s.next_start_position(),
+            SpansEnum::PositionRange(s) => s.next_start_position(),
+            SpansEnum::Contain(s) => s.next_start_position(),
         }
     }
 
@@ -249,6 +318,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.start_position(),
             SpansEnum::Or(s) => s.start_position(),
             SpansEnum::Term(s) => s.start_position(),
+            SpansEnum::Not(s) => s.start_position(),
+            SpansEnum::PositionRange(s) => s.start_position(),
+            SpansEnum::Contain(s) => s.start_position(),
         }
     }
 
@@ -259,6 +331,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.end_position(),
             SpansEnum::Or(s) => s.end_position(),
             SpansEnum::Term(s) => s.end_position(),
+            SpansEnum::Not(s) => s.end_position(),
+            SpansEnum::PositionRange(s) => s.end_position(),
+            SpansEnum::Contain(s) => s.end_position(),
         }
     }
 
@@ -269,6 +344,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.width(),
             SpansEnum::Or(s) => s.width(),
             SpansEnum::Term(s) => s.width(),
+            SpansEnum::Not(s) => s.width(),
+            SpansEnum::PositionRange(s) => s.width(),
+            SpansEnum::Contain(s) => s.width(),
         }
     }
 
@@ -279,6 +357,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.collect(collector),
             SpansEnum::Or(s) => s.collect(collector),
             SpansEnum::Term(s) => s.collect(collector),
+            SpansEnum::Not(s) => s.collect(collector),
+            SpansEnum::PositionRange(s) => s.collect(collector),
+            SpansEnum::Contain(s) => s.collect(collector),
         }
     }
 
@@ -289,6 +370,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.positions_cost(),
             SpansEnum::Or(s) => s.positions_cost(),
             SpansEnum::Term(s) => s.positions_cost(),
+            SpansEnum::Not(s) => s.positions_cost(),
+            SpansEnum::PositionRange(s) => s.positions_cost(),
+            SpansEnum::Contain(s) => s.positions_cost(),
         }
     }
 
@@ -299,6 +383,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.do_start_current_doc(),
             SpansEnum::Or(s) => s.do_start_current_doc(),
             SpansEnum::Term(s) => s.do_start_current_doc(),
+            SpansEnum::Not(s) => s.do_start_current_doc(),
+            SpansEnum::PositionRange(s) => s.do_start_current_doc(),
+            SpansEnum::Contain(s) => s.do_start_current_doc(),
         }
     }
 
@@ -309,6 +396,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.do_current_spans(),
             SpansEnum::Or(s) => s.do_current_spans(),
             SpansEnum::Term(s) => s.do_current_spans(),
+            SpansEnum::Not(s) => s.do_current_spans(),
+            SpansEnum::PositionRange(s) => s.do_current_spans(),
+            SpansEnum::Contain(s) => s.do_current_spans(),
         }
     }
 
@@ -319,6 +409,9 @@ impl<P: PostingIterator> Spans for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.advance_position(position),
             SpansEnum::Or(s) => s.advance_position(position),
             SpansEnum::Term(s) => s.advance_position(position),
+            SpansEnum::Not(s) => s.advance_position(position),
+            SpansEnum::PositionRange(s) => s.advance_position(position),
+            SpansEnum::Contain(s) => s.advance_position(position),
         }
     }
 }
@@ -331,6 +424,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.doc_id(),
             SpansEnum::Or(s) => s.doc_id(),
             SpansEnum::Term(s) => s.doc_id(),
+            SpansEnum::Not(s) => s.doc_id(),
+            SpansEnum::PositionRange(s) => s.doc_id(),
+            SpansEnum::Contain(s) => s.doc_id(),
         }
     }
 
@@ -341,6 +437,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.next(),
             SpansEnum::Or(s) => s.next(),
             SpansEnum::Term(s) => s.next(),
+            SpansEnum::Not(s) => s.next(),
+            SpansEnum::PositionRange(s) => s.next(),
+            SpansEnum::Contain(s) => s.next(),
         }
     }
 
@@ -351,6 +450,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.advance(target),
             SpansEnum::Or(s) => s.advance(target),
             SpansEnum::Term(s) => s.advance(target),
+            SpansEnum::Not(s) => s.advance(target),
+            SpansEnum::PositionRange(s) => s.advance(target),
+            SpansEnum::Contain(s) => s.advance(target),
         }
     }
 
@@ -361,6 +463,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.slow_advance(target),
             SpansEnum::Or(s) => s.slow_advance(target),
             SpansEnum::Term(s) => s.slow_advance(target),
+            SpansEnum::Not(s) => s.slow_advance(target),
+            SpansEnum::PositionRange(s) => s.slow_advance(target),
+            SpansEnum::Contain(s) => s.slow_advance(target),
         }
     }
 
@@ -371,6 +476,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.cost(),
             SpansEnum::Or(s) => s.cost(),
             SpansEnum::Term(s) => s.cost(),
+            SpansEnum::Not(s) => s.cost(),
+            SpansEnum::PositionRange(s) => s.cost(),
+            SpansEnum::Contain(s) => s.cost(),
         }
     }
 
@@ -381,6 +489,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.matches(),
             SpansEnum::Or(s) => s.matches(),
             SpansEnum::Term(s) => s.matches(),
+            SpansEnum::Not(s) => s.matches(),
+            SpansEnum::PositionRange(s) => s.matches(),
+            SpansEnum::Contain(s) => s.matches(),
         }
     }
 
@@ -391,6 +502,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.match_cost(),
             SpansEnum::Or(s) => s.match_cost(),
             SpansEnum::Term(s) => s.match_cost(),
+            SpansEnum::Not(s) => s.match_cost(),
+            SpansEnum::PositionRange(s) => s.match_cost(),
+            SpansEnum::Contain(s) => s.match_cost(),
         }
     }
 
@@ -401,6 +515,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.support_two_phase(),
             SpansEnum::Or(s) => s.support_two_phase(),
             SpansEnum::Term(s) => s.support_two_phase(),
+            SpansEnum::Not(s) => s.support_two_phase(),
+            SpansEnum::PositionRange(s) => s.support_two_phase(),
+            SpansEnum::Contain(s) => s.support_two_phase(),
         }
     }
 
@@ -411,6 +528,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.approximate_next(),
             SpansEnum::Or(s) => s.approximate_next(),
             SpansEnum::Term(s) => s.approximate_next(),
+            SpansEnum::Not(s) => s.approximate_next(),
+            SpansEnum::PositionRange(s) => s.approximate_next(),
+            SpansEnum::Contain(s) => s.approximate_next(),
         }
     }
 
@@ -421,6 +541,9 @@ impl<P: PostingIterator> DocIterator for SpansEnum<P> {
             SpansEnum::NearUnordered(s) => s.approximate_advance(target),
             SpansEnum::Or(s) => s.approximate_advance(target),
             SpansEnum::Term(s) => s.approximate_advance(target),
+            SpansEnum::Not(s) => s.approximate_advance(target),
+            SpansEnum::PositionRange(s) => s.approximate_advance(target),
+            SpansEnum::Contain(s) => s.approximate_advance(target),
         }
     }
 }
@@ -688,6 +811,10 @@ pub enum SpanWeightEnum<C: Codec> {
     Boost(SpanBoostWeight<C>),
     Near(SpanNearWeight<C>),
     Or(SpanOrWeight<C>),
+    Not(SpanNotWeight<C>),
+    PositionRange(SpanPositionRangeWeight<C>),
+    Contain(SpanContainWeight<C>),
+    MultiTerm(SpanMultiTermWeight<C>),
 }
 
 impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
@@ -698,6 +825,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.sim_weight(),
             SpanWeightEnum::Near(w) => w.sim_weight(),
             SpanWeightEnum::Boost(w) => w.sim_weight(),
+            SpanWeightEnum::Not(w) => w.sim_weight(),
+            SpanWeightEnum::PositionRange(w) => w.sim_weight(),
+            SpanWeightEnum::Contain(w) => w.sim_weight(),
+            SpanWeightEnum::MultiTerm(w) => w.sim_weight(),
         }
     }
 
@@ -708,6 +839,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.sim_weight_mut(),
             SpanWeightEnum::Near(w) => w.sim_weight_mut(),
             SpanWeightEnum::Boost(w) => w.sim_weight_mut(),
+            SpanWeightEnum::Not(w) => w.sim_weight_mut(),
+            SpanWeightEnum::PositionRange(w) => w.sim_weight_mut(),
+            SpanWeightEnum::Contain(w) => w.sim_weight_mut(),
+            SpanWeightEnum::MultiTerm(w) => w.sim_weight_mut(),
         }
     }
 
@@ -722,6 +857,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.get_spans(reader, required_postings),
             SpanWeightEnum::Near(w) => w.get_spans(reader, required_postings),
             SpanWeightEnum::Boost(w) => w.get_spans(reader, required_postings),
+            SpanWeightEnum::Not(w) => w.get_spans(reader, required_postings),
+            SpanWeightEnum::PositionRange(w) => w.get_spans(reader, required_postings),
+            SpanWeightEnum::Contain(w) => w.get_spans(reader, required_postings),
+            SpanWeightEnum::MultiTerm(w) => w.get_spans(reader, required_postings),
         }
     }
 
@@ -735,6 +874,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.extract_term_contexts(contexts),
             SpanWeightEnum::Near(w) => w.extract_term_contexts(contexts),
             SpanWeightEnum::Boost(w) => w.extract_term_contexts(contexts),
+            SpanWeightEnum::Not(w) => w.extract_term_contexts(contexts),
+            SpanWeightEnum::PositionRange(w) => w.extract_term_contexts(contexts),
+            SpanWeightEnum::Contain(w) => w.extract_term_contexts(contexts),
+            SpanWeightEnum::MultiTerm(w) => w.extract_term_contexts(contexts),
         }
     }
 
@@ -745,6 +888,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.do_create_scorer(ctx),
             SpanWeightEnum::Near(w) => w.do_create_scorer(ctx),
             SpanWeightEnum::Boost(w) => w.do_create_scorer(ctx),
+            SpanWeightEnum::Not(w) => w.do_create_scorer(ctx),
+            SpanWeightEnum::PositionRange(w) => w.do_create_scorer(ctx),
+            SpanWeightEnum::Contain(w) => w.do_create_scorer(ctx),
+            SpanWeightEnum::MultiTerm(w) => w.do_create_scorer(ctx),
         }
     }
 
@@ -755,6 +902,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.do_value_for_normalization(),
             SpanWeightEnum::Near(w) => w.do_value_for_normalization(),
             SpanWeightEnum::Boost(w) => w.do_value_for_normalization(),
+            SpanWeightEnum::Not(w) => w.do_value_for_normalization(),
+            SpanWeightEnum::PositionRange(w) => w.do_value_for_normalization(),
+            SpanWeightEnum::Contain(w) => w.do_value_for_normalization(),
+            SpanWeightEnum::MultiTerm(w) => w.do_value_for_normalization(),
         }
     }
 
@@ -765,6 +916,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.do_normalize(query_norm, boost),
             SpanWeightEnum::Near(w) => w.do_normalize(query_norm, boost),
             SpanWeightEnum::Boost(w) => w.do_normalize(query_norm, boost),
+            SpanWeightEnum::Not(w) => w.do_normalize(query_norm, boost),
+            SpanWeightEnum::PositionRange(w) => w.do_normalize(query_norm, boost),
+            SpanWeightEnum::Contain(w) => w.do_normalize(query_norm, boost),
+            SpanWeightEnum::MultiTerm(w) => w.do_normalize(query_norm, boost),
         }
     }
 
@@ -775,6 +930,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.sim_scorer(reader),
             SpanWeightEnum::Near(w) => w.sim_scorer(reader),
             SpanWeightEnum::Boost(w) => w.sim_scorer(reader),
+            SpanWeightEnum::Not(w) => w.sim_scorer(reader),
+            SpanWeightEnum::PositionRange(w) => w.sim_scorer(reader),
+            SpanWeightEnum::Contain(w) => w.sim_scorer(reader),
+            SpanWeightEnum::MultiTerm(w) => w.sim_scorer(reader),
         }
     }
 
@@ -785,6 +944,10 @@ impl<C: Codec> SpanWeight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.explain_span(reader, doc),
             SpanWeightEnum::Near(w) => w.explain_span(reader, doc),
             SpanWeightEnum::Boost(w) => w.explain_span(reader, doc),
+            SpanWeightEnum::Not(w) => w.explain_span(reader, doc),
+            SpanWeightEnum::PositionRange(w) => w.explain_span(reader, doc),
+            SpanWeightEnum::Contain(w) => w.explain_span(reader, doc),
+            SpanWeightEnum::MultiTerm(w) => w.explain_span(reader, doc),
         }
     }
 }
@@ -800,6 +963,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.create_scorer(leaf_reader),
             SpanWeightEnum::Near(w) => w.create_scorer(leaf_reader),
             SpanWeightEnum::Boost(w) => w.create_scorer(leaf_reader),
+            SpanWeightEnum::Not(w) => w.create_scorer(leaf_reader),
+            SpanWeightEnum::PositionRange(w) => w.create_scorer(leaf_reader),
+            SpanWeightEnum::Contain(w) => w.create_scorer(leaf_reader),
+            SpanWeightEnum::MultiTerm(w) => w.create_scorer(leaf_reader),
         }
     }
 
@@ -810,6 +977,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.hash_code(),
             SpanWeightEnum::Near(w) => w.hash_code(),
             SpanWeightEnum::Boost(w) => w.hash_code(),
+            SpanWeightEnum::Not(w) => w.hash_code(),
+            SpanWeightEnum::PositionRange(w) => w.hash_code(),
+            SpanWeightEnum::Contain(w) => w.hash_code(),
+            SpanWeightEnum::MultiTerm(w) => w.hash_code(),
         }
     }
 
@@ -824,6 +995,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.actual_query_type(),
             SpanWeightEnum::Near(w) => w.actual_query_type(),
             SpanWeightEnum::Boost(w) => w.actual_query_type(),
+            SpanWeightEnum::Not(w) => w.actual_query_type(),
+            SpanWeightEnum::PositionRange(w) => w.actual_query_type(),
+            SpanWeightEnum::Contain(w) => w.actual_query_type(),
+            SpanWeightEnum::MultiTerm(w) => w.actual_query_type(),
         }
     }
 
@@ -834,6 +1009,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.normalize(norm, boost),
             SpanWeightEnum::Near(w) => w.normalize(norm, boost),
             SpanWeightEnum::Boost(w) => w.normalize(norm, boost),
+            SpanWeightEnum::Not(w) => w.normalize(norm, boost),
+            SpanWeightEnum::PositionRange(w) => w.normalize(norm, boost),
+            SpanWeightEnum::Contain(w) => w.normalize(norm, boost),
+            SpanWeightEnum::MultiTerm(w) => w.normalize(norm, boost),
         }
     }
 
@@ -844,6 +1023,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.value_for_normalization(),
             SpanWeightEnum::Near(w) => w.value_for_normalization(),
             SpanWeightEnum::Boost(w) => w.value_for_normalization(),
+            SpanWeightEnum::Not(w) => w.value_for_normalization(),
+            SpanWeightEnum::PositionRange(w) => w.value_for_normalization(),
+            SpanWeightEnum::Contain(w) => w.value_for_normalization(),
+            SpanWeightEnum::MultiTerm(w) => w.value_for_normalization(),
         }
     }
 
@@ -854,6 +1037,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.needs_scores(),
             SpanWeightEnum::Near(w) => w.needs_scores(),
             SpanWeightEnum::Boost(w) => w.needs_scores(),
+            SpanWeightEnum::Not(w) => w.needs_scores(),
+            SpanWeightEnum::PositionRange(w) => w.needs_scores(),
+            SpanWeightEnum::Contain(w) => w.needs_scores(),
+            SpanWeightEnum::MultiTerm(w) => w.needs_scores(),
         }
     }
 
@@ -864,6 +1051,10 @@ impl<C: Codec> Weight<C> for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => w.explain(reader, doc),
             SpanWeightEnum::Near(w) => w.explain(reader, doc),
             SpanWeightEnum::Boost(w) => w.explain(reader, doc),
+            SpanWeightEnum::Not(w) => w.explain(reader, doc),
+            SpanWeightEnum::PositionRange(w) => w.explain(reader, doc),
+            SpanWeightEnum::Contain(w) => w.explain(reader, doc),
+            SpanWeightEnum::MultiTerm(w) => w.explain(reader, doc),
         }
     }
 }
@@ -876,6 +1067,10 @@ impl<C: Codec> fmt::Display for SpanWeightEnum<C> {
             SpanWeightEnum::Or(w) => write!(f, "SpanWeightEnum({})", w),
             SpanWeightEnum::Near(w) => write!(f, "SpanWeightEnum({})", w),
             SpanWeightEnum::Boost(w) => write!(f, "SpanWeightEnum({})", w),
+            SpanWeightEnum::Not(w) => write!(f, "SpanWeightEnum({})", w),
+            SpanWeightEnum::PositionRange(w) => write!(f, "SpanWeightEnum({})", w),
+            SpanWeightEnum::Contain(w) => write!(f, "SpanWeightEnum({})", w),
+            SpanWeightEnum::MultiTerm(w) => write!(f, "SpanWeightEnum({})", w),
         }
     }
 }
@@ -887,6 +1082,10 @@ impl<C: Codec> From<SpanBoostWeightEnum<C>> for SpanWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => SpanWeightEnum::Gap(w),
             SpanBoostWeightEnum::Or(w) => SpanWeightEnum::Or(w),
             SpanBoostWeightEnum::Near(w) => SpanWeightEnum::Near(w),
+            SpanBoostWeightEnum::Not(w) => SpanWeightEnum::Not(w),
+            SpanBoostWeightEnum::PositionRange(w) => SpanWeightEnum::PositionRange(w),
+            SpanBoostWeightEnum::Contain(w) => SpanWeightEnum::Contain(w),
+            SpanBoostWeightEnum::MultiTerm(w) => SpanWeightEnum::MultiTerm(w),
         }
     }
 }
@@ -20,6 +20,11 @@ use core::search::query::spans::{
     SpanOrQuery, SpanOrWeight, SpanQuery, SpanQueryEnum, SpanTermQuery, SpanTermWeight, SpanWeight,
     SpanWeightEnum, SpansEnum,
 };
+use core::search::query::spans::{
+    SpanContainWeight, SpanContainingQuery, SpanFirstQuery, SpanMultiTermQueryWrapper,
+    SpanMultiTermWeight, SpanNotQuery, SpanNotWeight, SpanPositionRangeQuery,
+    SpanPositionRangeWeight, SpanWithinQuery,
+};
 use core::search::searcher::SearchPlanBuilder;
 use core::search::TermContext;
 use core::search::{
@@ -126,6 +131,12 @@ pub enum SpanBoostQueryEnum {
     Gap(SpanGapQuery),
     Or(SpanOrQuery),
     Near(SpanNearQuery),
+    Not(SpanNotQuery),
+    PositionRange(SpanPositionRangeQuery),
+    First(SpanFirstQuery),
+    Containing(SpanContainingQuery),
+    Within(SpanWithinQuery),
+    MultiTerm(SpanMultiTermQueryWrapper),
 }
 
 impl SpanBoostQueryEnum {
@@ -135,6 +146,12 @@ impl SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => SpanQueryEnum::Gap(q),
             SpanBoostQueryEnum::Or(q) => SpanQueryEnum::Or(q),
             SpanBoostQueryEnum::Near(q) => SpanQueryEnum::Near(q),
+            SpanBoostQueryEnum::Not(q) => SpanQueryEnum::Not(q),
+            SpanBoostQueryEnum::PositionRange(q) => SpanQueryEnum::PositionRange(q),
+            SpanBoostQueryEnum::First(q) => SpanQueryEnum::First(q),
+            SpanBoostQueryEnum::Containing(q) => SpanQueryEnum::Containing(q),
+            SpanBoostQueryEnum::Within(q) => SpanQueryEnum::Within(q),
+            SpanBoostQueryEnum::MultiTerm(q) => SpanQueryEnum::MultiTerm(q),
         }
     }
 }
@@ -160,6 +177,24 @@ impl<C: Codec> SpanQuery<C> for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Near(q) => {
                 SpanBoostWeightEnum::Near(q.span_weight(searcher, needs_scores)?)
             }
+            SpanBoostQueryEnum::Not(q) => {
+                SpanBoostWeightEnum::Not(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanBoostQueryEnum::PositionRange(q) => {
+                SpanBoostWeightEnum::PositionRange(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanBoostQueryEnum::First(q) => {
+                SpanBoostWeightEnum::PositionRange(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanBoostQueryEnum::Containing(q) => {
+                SpanBoostWeightEnum::Contain(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanBoostQueryEnum::Within(q) => {
+                SpanBoostWeightEnum::Contain(q.span_weight(searcher, needs_scores)?)
+            }
+            SpanBoostQueryEnum::MultiTerm(q) => {
+                SpanBoostWeightEnum::MultiTerm(q.span_weight(searcher, needs_scores)?)
+            }
         };
         Ok(weight)
     }
@@ -170,6 +205,12 @@ impl<C: Codec> SpanQuery<C> for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => SpanQuery::<C>::field(q),
             SpanBoostQueryEnum::Or(q) => SpanQuery::<C>::field(q),
             SpanBoostQueryEnum::Near(q) => SpanQuery::<C>::field(q),
+            SpanBoostQueryEnum::Not(q) => SpanQuery::<C>::field(q),
+            SpanBoostQueryEnum::PositionRange(q) => SpanQuery::<C>::field(q),
+            SpanBoostQueryEnum::First(q) => SpanQuery::<C>::field(q),
+            SpanBoostQueryEnum::Containing(q) => SpanQuery::<C>::field(q),
+            SpanBoostQueryEnum::Within(q) => SpanQuery::<C>::field(q),
+            SpanBoostQueryEnum::MultiTerm(q) => SpanQuery::<C>::field(q),
         }
     }
 
@@ -179,6 +220,12 @@ impl<C: Codec> SpanQuery<C> for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => SpanQuery::<C>::ctx(q),
             SpanBoostQueryEnum::Or(q) => SpanQuery::<C>::ctx(q),
             SpanBoostQueryEnum::Near(q) => SpanQuery::<C>::ctx(q),
+            SpanBoostQueryEnum::Not(q) => SpanQuery::<C>::ctx(q),
+            SpanBoostQueryEnum::PositionRange(q) => SpanQuery::<C>::ctx(q),
+            SpanBoostQueryEnum::First(q) => SpanQuery::<C>::ctx(q),
+            SpanBoostQueryEnum::Containing(q) => SpanQuery::<C>::ctx(q),
+            SpanBoostQueryEnum::Within(q) => SpanQuery::<C>::ctx(q),
+            SpanBoostQueryEnum::MultiTerm(q) => SpanQuery::<C>::ctx(q),
         }
     }
 }
@@ -194,6 +241,12 @@ impl<C: Codec> Query<C> for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => q.create_weight(searcher, needs_scores),
             SpanBoostQueryEnum::Or(q) => q.create_weight(searcher, needs_scores),
             SpanBoostQueryEnum::Near(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::Not(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::PositionRange(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::First(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::Containing(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::Within(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::MultiTerm(q) => q.create_weight(searcher, needs_scores),
         }
     }
 
@@ -203,6 +256,12 @@ impl<C: Codec> Query<C> for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => Query::<C>::extract_terms(q),
             SpanBoostQueryEnum::Or(q) => Query::<C>::extract_terms(q),
             SpanBoostQueryEnum::Near(q) => Query::<C>::extract_terms(q),
+            SpanBoostQueryEnum::Not(q) => Query::<C>::extract_terms(q),
+            SpanBoostQueryEnum::PositionRange(q) => Query::<C>::extract_terms(q),
+            SpanBoostQueryEnum::First(q) => Query::<C>::extract_terms(q),
+            SpanBoostQueryEnum::Containing(q) => Query::<C>::extract_terms(q),
+            SpanBoostQueryEnum::Within(q) => Query::<C>::extract_terms(q),
+            SpanBoostQueryEnum::MultiTerm(q) => Query::<C>::extract_terms(q),
         }
     }
 
@@ -212,6 +271,12 @@ impl<C: Codec> Query<C> for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => Query::<C>::as_any(q),
             SpanBoostQueryEnum::Or(q) => Query::<C>::as_any(q),
             SpanBoostQueryEnum::Near(q) => Query::<C>::as_any(q),
+            SpanBoostQueryEnum::Not(q) => Query::<C>::as_any(q),
+            SpanBoostQueryEnum::PositionRange(q) => Query::<C>::as_any(q),
+            SpanBoostQueryEnum::First(q) => Query::<C>::as_any(q),
+            SpanBoostQueryEnum::Containing(q) => Query::<C>::as_any(q),
+            SpanBoostQueryEnum::Within(q) => Query::<C>::as_any(q),
+            SpanBoostQueryEnum::MultiTerm(q) => Query::<C>::as_any(q),
         }
     }
 }
@@ -223,6 +288,12 @@ impl fmt::Display for SpanBoostQueryEnum {
             SpanBoostQueryEnum::Gap(q) => write!(f, "SpanBoostQueryEnum({})", q),
             SpanBoostQueryEnum::Or(q) => write!(f, "SpanBoostQueryEnum({})", q),
             SpanBoostQueryEnum::Near(q) => write!(f, "SpanBoostQueryEnum({})", q),
+            SpanBoostQueryEnum::Not(q) => write!(f, "SpanBoostQueryEnum({})", q),
+            SpanBoostQueryEnum::PositionRange(q) => write!(f, "SpanBoostQueryEnum({})", q),
+            SpanBoostQueryEnum::First(q) => write!(f, "SpanBoostQueryEnum({})", q),
+            SpanBoostQueryEnum::Containing(q) => write!(f, "SpanBoostQueryEnum({})", q),
+            SpanBoostQueryEnum::Within(q) => write!(f, "SpanBoostQueryEnum({})", q),
+            SpanBoostQueryEnum::MultiTerm(q) => write!(f, "SpanBoostQueryEnum({})", q),
         }
     }
 }
@@ -327,6 +398,10 @@ pub enum SpanBoostWeightEnum<C: Codec> {
     Gap(SpanGapWeight<C>),
     Or(SpanOrWeight<C>),
     Near(SpanNearWeight<C>),
+    Not(SpanNotWeight<C>),
+    PositionRange(SpanPositionRangeWeight<C>),
+    Contain(SpanContainWeight<C>),
+    MultiTerm(SpanMultiTermWeight<C>),
 }
 
 impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
@@ -336,6 +411,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.sim_weight(),
             SpanBoostWeightEnum::Or(w) => w.sim_weight(),
             SpanBoostWeightEnum::Near(w) => w.sim_weight(),
+            SpanBoostWeightEnum::Not(w) => w.sim_weight(),
+            SpanBoostWeightEnum::PositionRange(w) => w.sim_weight(),
+            SpanBoostWeightEnum::Contain(w) => w.sim_weight(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.sim_weight(),
         }
     }
 
@@ -345,6 +424,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.sim_weight_mut(),
             SpanBoostWeightEnum::Or(w) => w.sim_weight_mut(),
             SpanBoostWeightEnum::Near(w) => w.sim_weight_mut(),
+            SpanBoostWeightEnum::Not(w) => w.sim_weight_mut(),
+            SpanBoostWeightEnum::PositionRange(w) => w.sim_weight_mut(),
+            SpanBoostWeightEnum::Contain(w) => w.sim_weight_mut(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.sim_weight_mut(),
         }
     }
 
@@ -358,6 +441,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.get_spans(reader, required_postings),
             SpanBoostWeightEnum::Or(w) => w.get_spans(reader, required_postings),
             SpanBoostWeightEnum::Near(w) => w.get_spans(reader, required_postings),
+            SpanBoostWeightEnum::Not(w) => w.get_spans(reader, required_postings),
+            SpanBoostWeightEnum::PositionRange(w) => w.get_spans(reader, required_postings),
+            SpanBoostWeightEnum::Contain(w) => w.get_spans(reader, required_postings),
+            SpanBoostWeightEnum::MultiTerm(w) => w.get_spans(reader, required_postings),
         }
     }
 
@@ -370,6 +457,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.extract_term_contexts(contexts),
             SpanBoostWeightEnum::Or(w) => w.extract_term_contexts(contexts),
             SpanBoostWeightEnum::Near(w) => w.extract_term_contexts(contexts),
+            SpanBoostWeightEnum::Not(w) => w.extract_term_contexts(contexts),
+            SpanBoostWeightEnum::PositionRange(w) => w.extract_term_contexts(contexts),
+            SpanBoostWeightEnum::Contain(w) => w.extract_term_contexts(contexts),
+            SpanBoostWeightEnum::MultiTerm(w) => w.extract_term_contexts(contexts),
         }
     }
 
@@ -379,6 +470,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.do_create_scorer(ctx),
             SpanBoostWeightEnum::Or(w) => w.do_create_scorer(ctx),
             SpanBoostWeightEnum::Near(w) => w.do_create_scorer(ctx),
+            SpanBoostWeightEnum::Not(w) => w.do_create_scorer(ctx),
+            SpanBoostWeightEnum::PositionRange(w) => w.do_create_scorer(ctx),
+            SpanBoostWeightEnum::Contain(w) => w.do_create_scorer(ctx),
+            SpanBoostWeightEnum::MultiTerm(w) => w.do_create_scorer(ctx),
         }
     }
 
@@ -388,6 +483,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.do_value_for_normalization(),
             SpanBoostWeightEnum::Or(w) => w.do_value_for_normalization(),
             SpanBoostWeightEnum::Near(w) => w.do_value_for_normalization(),
+            SpanBoostWeightEnum::Not(w) => w.do_value_for_normalization(),
+            SpanBoostWeightEnum::PositionRange(w) => w.do_value_for_normalization(),
+            SpanBoostWeightEnum::Contain(w) => w.do_value_for_normalization(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.do_value_for_normalization(),
         }
     }
 
@@ -397,6 +496,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.do_normalize(query_norm, boost),
             SpanBoostWeightEnum::Or(w) => w.do_normalize(query_norm, boost),
             SpanBoostWeightEnum::Near(w) => w.do_normalize(query_norm, boost),
+            SpanBoostWeightEnum::Not(w) => w.do_normalize(query_norm, boost),
+            SpanBoostWeightEnum::PositionRange(w) => w.do_normalize(query_norm, boost),
+            SpanBoostWeightEnum::Contain(w) => w.do_normalize(query_norm, boost),
+            SpanBoostWeightEnum::MultiTerm(w) => w.do_normalize(query_norm, boost),
         }
     }
 
@@ -406,6 +509,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.sim_scorer(reader),
             SpanBoostWeightEnum::Or(w) => w.sim_scorer(reader),
             SpanBoostWeightEnum::Near(w) => w.sim_scorer(reader),
+            SpanBoostWeightEnum::Not(w) => w.sim_scorer(reader),
+            SpanBoostWeightEnum::PositionRange(w) => w.sim_scorer(reader),
+            SpanBoostWeightEnum::Contain(w) => w.sim_scorer(reader),
+            SpanBoostWeightEnum::MultiTerm(w) => w.sim_scorer(reader),
         }
     }
 
@@ -415,6 +522,10 @@ impl<C: Codec> SpanWeight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.explain_span(reader, doc),
             SpanBoostWeightEnum::Or(w) => w.explain_span(reader, doc),
             SpanBoostWeightEnum::Near(w) => w.explain_span(reader, doc),
+            SpanBoostWeightEnum::Not(w) => w.explain_span(reader, doc),
+            SpanBoostWeightEnum::PositionRange(w) => w.explain_span(reader, doc),
+            SpanBoostWeightEnum::Contain(w) => w.explain_span(reader, doc),
+            SpanBoostWeightEnum::MultiTerm(w) => w.explain_span(reader, doc),
         }
     }
 }
@@ -429,6 +540,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.create_scorer(leaf_reader),
             SpanBoostWeightEnum::Or(w) => w.create_scorer(leaf_reader),
             SpanBoostWeightEnum::Near(w) => w.create_scorer(leaf_reader),
+            SpanBoostWeightEnum::Not(w) => w.create_scorer(leaf_reader),
+            SpanBoostWeightEnum::PositionRange(w) => w.create_scorer(leaf_reader),
+            SpanBoostWeightEnum::Contain(w) => w.create_scorer(leaf_reader),
+            SpanBoostWeightEnum::MultiTerm(w) => w.create_scorer(leaf_reader),
         }
     }
 
@@ -438,6 +553,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.hash_code(),
             SpanBoostWeightEnum::Or(w) => w.hash_code(),
             SpanBoostWeightEnum::Near(w) => w.hash_code(),
+            SpanBoostWeightEnum::Not(w) => w.hash_code(),
+            SpanBoostWeightEnum::PositionRange(w) => w.hash_code(),
+            SpanBoostWeightEnum::Contain(w) => w.hash_code(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.hash_code(),
         }
     }
 
@@ -447,6 +566,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.query_type(),
             SpanBoostWeightEnum::Or(w) => w.query_type(),
             SpanBoostWeightEnum::Near(w) => w.query_type(),
+            SpanBoostWeightEnum::Not(w) => w.query_type(),
+            SpanBoostWeightEnum::PositionRange(w) => w.query_type(),
+            SpanBoostWeightEnum::Contain(w) => w.query_type(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.query_type(),
         }
     }
 
@@ -456,6 +579,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.actual_query_type(),
             SpanBoostWeightEnum::Or(w) => w.actual_query_type(),
             SpanBoostWeightEnum::Near(w) => w.actual_query_type(),
+            SpanBoostWeightEnum::Not(w) => w.actual_query_type(),
+            SpanBoostWeightEnum::PositionRange(w) => w.actual_query_type(),
+            SpanBoostWeightEnum::Contain(w) => w.actual_query_type(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.actual_query_type(),
         }
     }
 
@@ -465,6 +592,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.normalize(norm, boost),
             SpanBoostWeightEnum::Or(w) => w.normalize(norm, boost),
             SpanBoostWeightEnum::Near(w) => w.normalize(norm, boost),
+            SpanBoostWeightEnum::Not(w) => w.normalize(norm, boost),
+            SpanBoostWeightEnum::PositionRange(w) => w.normalize(norm, boost),
+            SpanBoostWeightEnum::Contain(w) => w.normalize(norm, boost),
+            SpanBoostWeightEnum::MultiTerm(w) => w.normalize(norm, boost),
         }
     }
 
@@ -474,6 +605,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.value_for_normalization(),
             SpanBoostWeightEnum::Or(w) => w.value_for_normalization(),
             SpanBoostWeightEnum::Near(w) => w.value_for_normalization(),
+            SpanBoostWeightEnum::Not(w) => w.value_for_normalization(),
+            SpanBoostWeightEnum::PositionRange(w) => w.value_for_normalization(),
+            SpanBoostWeightEnum::Contain(w) => w.value_for_normalization(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.value_for_normalization(),
         }
     }
 
@@ -483,6 +618,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.needs_scores(),
             SpanBoostWeightEnum::Or(w) => w.needs_scores(),
             SpanBoostWeightEnum::Near(w) => w.needs_scores(),
+            SpanBoostWeightEnum::Not(w) => w.needs_scores(),
+            SpanBoostWeightEnum::PositionRange(w) => w.needs_scores(),
+            SpanBoostWeightEnum::Contain(w) => w.needs_scores(),
+            SpanBoostWeightEnum::MultiTerm(w) => w.needs_scores(),
         }
     }
 
@@ -492,6 +631,10 @@ impl<C: Codec> Weight<C> for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => w.explain(reader, doc),
             SpanBoostWeightEnum::Or(w) => w.explain(reader, doc),
             SpanBoostWeightEnum::Near(w) => w.explain(reader, doc),
+            SpanBoostWeightEnum::Not(w) => w.explain(reader, doc),
+            SpanBoostWeightEnum::PositionRange(w) => w.explain(reader, doc),
+            SpanBoostWeightEnum::Contain(w) => w.explain(reader, doc),
+            SpanBoostWeightEnum::MultiTerm(w) => w.explain(reader, doc),
         }
     }
 }
@@ -503,6 +646,10 @@ impl<C: Codec> fmt::Display for SpanBoostWeightEnum<C> {
             SpanBoostWeightEnum::Gap(w) => write!(f, "SpanBoostWeightEnum({})", w),
             SpanBoostWeightEnum::Or(w) => write!(f, "SpanBoostWeightEnum({})", w),
             SpanBoostWeightEnum::Near(w) => write!(f, "SpanBoostWeightEnum({})", w),
+            SpanBoostWeightEnum::Not(w) => write!(f, "SpanBoostWeightEnum({})", w),
+            SpanBoostWeightEnum::PositionRange(w) => write!(f, "SpanBoostWeightEnum({})", w),
+            SpanBoostWeightEnum::Contain(w) => write!(f, "SpanBoostWeightEnum({})", w),
+            SpanBoostWeightEnum::MultiTerm(w) => write!(f, "SpanBoostWeightEnum({})", w),
         }
     }
 }
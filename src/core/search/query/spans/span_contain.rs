@@ -0,0 +1,479 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{Codec, CodecEnum, CodecPostingIterator, CodecTermState, PostingIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    build_sim_weight, ConjunctionSpanBase, ConjunctionSpans, PostingsFlag, SpanCollector,
+    SpanQuery, SpanQueryEnum, SpanWeight, SpanWeightEnum, Spans, SpansEnum, NO_MORE_POSITIONS,
+};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::SimWeight;
+use core::search::{DocIterator, TermContext, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::{ErrorKind, Result};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const SPAN_CONTAINING_QUERY: &str = "span_containing";
+const SPAN_WITHIN_QUERY: &str = "span_within";
+
+fn check_same_field(big: &SpanQueryEnum, little: &SpanQueryEnum) -> Result<()> {
+    if SpanQuery::<CodecEnum>::field(big) != SpanQuery::<CodecEnum>::field(little) {
+        bail!(ErrorKind::IllegalArgument(
+            "big and little not same field".into()
+        ));
+    }
+    Ok(())
+}
+
+fn span_contain_weight<C: Codec>(
+    big: &SpanQueryEnum,
+    little: &SpanQueryEnum,
+    searcher: &dyn SearchPlanBuilder<C>,
+    needs_scores: bool,
+    within: bool,
+) -> Result<SpanContainWeight<C>> {
+    let big_weight = big.span_weight(searcher, needs_scores)?;
+    let little_weight = little.span_weight(searcher, needs_scores)?;
+    let mut term_contexts = HashMap::new();
+    if needs_scores {
+        big_weight.extract_term_contexts(&mut term_contexts);
+        little_weight.extract_term_contexts(&mut term_contexts);
+    }
+    let sim_weight = build_sim_weight(SpanQuery::<C>::field(big), searcher, term_contexts, None)?;
+    Ok(SpanContainWeight {
+        sim_weight,
+        big_weight: Box::new(big_weight),
+        little_weight: Box::new(little_weight),
+        within,
+    })
+}
+
+/// Keeps the matches from `big` that contain at least one match of
+/// `little`.
+pub struct SpanContainingQuery {
+    big: Box<SpanQueryEnum>,
+    little: Box<SpanQueryEnum>,
+}
+
+impl SpanContainingQuery {
+    pub fn new(big: SpanQueryEnum, little: SpanQueryEnum) -> Result<Self> {
+        check_same_field(&big, &little)?;
+        Ok(SpanContainingQuery {
+            big: Box::new(big),
+            little: Box::new(little),
+        })
+    }
+}
+
+impl<C: Codec> SpanQuery<C> for SpanContainingQuery {
+    type Weight = SpanContainWeight<C>;
+
+    fn span_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Self::Weight> {
+        span_contain_weight(&self.big, &self.little, searcher, needs_scores, false)
+    }
+
+    fn field(&self) -> &str {
+        SpanQuery::<C>::field(self.big.as_ref())
+    }
+}
+
+impl<C: Codec> Query<C> for SpanContainingQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(span_contain_weight(
+            &self.big,
+            &self.little,
+            searcher,
+            needs_scores,
+            false,
+        )?))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        let mut terms = Query::<C>::extract_terms(self.big.as_ref());
+        terms.extend(Query::<C>::extract_terms(self.little.as_ref()));
+        terms
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for SpanContainingQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanContainingQuery(big: {}, little: {})",
+            self.big, self.little
+        )
+    }
+}
+
+/// Keeps the matches from `little` that are contained in at least one match
+/// of `big`.
+pub struct SpanWithinQuery {
+    big: Box<SpanQueryEnum>,
+    little: Box<SpanQueryEnum>,
+}
+
+impl SpanWithinQuery {
+    pub fn new(big: SpanQueryEnum, little: SpanQueryEnum) -> Result<Self> {
+        check_same_field(&big, &little)?;
+        Ok(SpanWithinQuery {
+            big: Box::new(big),
+            little: Box::new(little),
+        })
+    }
+}
+
+impl<C: Codec> SpanQuery<C> for SpanWithinQuery {
+    type Weight = SpanContainWeight<C>;
+
+    fn span_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Self::Weight> {
+        span_contain_weight(&self.big, &self.little, searcher, needs_scores, true)
+    }
+
+    fn field(&self) -> &str {
+        SpanQuery::<C>::field(self.little.as_ref())
+    }
+}
+
+impl<C: Codec> Query<C> for SpanWithinQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(span_contain_weight(
+            &self.big,
+            &self.little,
+            searcher,
+            needs_scores,
+            true,
+        )?))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        let mut terms = Query::<C>::extract_terms(self.big.as_ref());
+        terms.extend(Query::<C>::extract_terms(self.little.as_ref()));
+        terms
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for SpanWithinQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanWithinQuery(big: {}, little: {})",
+            self.big, self.little
+        )
+    }
+}
+
+/// The weight of `SpanContainingQuery` and `SpanWithinQuery`, which match
+/// the spans of `big` or of `little` respectively.
+pub struct SpanContainWeight<C: Codec> {
+    sim_weight: Option<Box<dyn SimWeight<C>>>,
+    big_weight: Box<SpanWeightEnum<C>>,
+    little_weight: Box<SpanWeightEnum<C>>,
+    within: bool,
+}
+
+impl<C: Codec> SpanWeight<C> for SpanContainWeight<C> {
+    fn sim_weight(&self) -> Option<&dyn SimWeight<C>> {
+        self.sim_weight.as_ref().map(|x| &**x)
+    }
+
+    fn sim_weight_mut(&mut self) -> Option<&mut dyn SimWeight<C>> {
+        if let Some(ref mut sim_weight) = self.sim_weight {
+            Some(sim_weight.as_mut())
+        } else {
+            None
+        }
+    }
+
+    fn get_spans(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        required_postings: &PostingsFlag,
+    ) -> Result<Option<SpansEnum<CodecPostingIterator<C>>>> {
+        let big_spans = match self.big_weight.get_spans(reader, required_postings)? {
+            Some(spans) => spans,
+            None => return Ok(None),
+        };
+        let little_spans = match self.little_weight.get_spans(reader, required_postings)? {
+            Some(spans) => spans,
+            None => return Ok(None),
+        };
+        Ok(Some(SpansEnum::Contain(ContainSpans::new(
+            big_spans,
+            little_spans,
+            self.within,
+        )?)))
+    }
+
+    fn extract_term_contexts(
+        &self,
+        contexts: &mut HashMap<Term, Arc<TermContext<CodecTermState<C>>>>,
+    ) {
+        self.big_weight.extract_term_contexts(contexts);
+        self.little_weight.extract_term_contexts(contexts);
+    }
+}
+
+impl<C: Codec> Weight<C> for SpanContainWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        self.do_create_scorer(ctx)
+    }
+
+    fn query_type(&self) -> &'static str {
+        if self.within {
+            SPAN_WITHIN_QUERY
+        } else {
+            SPAN_CONTAINING_QUERY
+        }
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.do_normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.do_value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.explain_span(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for SpanContainWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanContainWeight(big: {}, little: {}, within: {})",
+            self.big_weight, self.little_weight, self.within
+        )
+    }
+}
+
+const BIG: usize = 0;
+const LITTLE: usize = 1;
+
+/// The spans of the docs that match both `big` and `little`: the big spans
+/// that contain a little span, or the little spans that are within a big
+/// span when `within` is true.
+pub struct ContainSpans<P: PostingIterator> {
+    conjunction_span: ConjunctionSpanBase<P>,
+    // the big and the little spans
+    sub_spans: Vec<SpansEnum<P>>,
+    within: bool,
+}
+
+impl<P: PostingIterator> ContainSpans<P> {
+    pub fn new(big: SpansEnum<P>, little: SpansEnum<P>, within: bool) -> Result<Self> {
+        let mut sub_spans = vec![big, little];
+        let conjunction_span = ConjunctionSpanBase::new(&mut sub_spans)?;
+        Ok(ContainSpans {
+            conjunction_span,
+            sub_spans,
+            within,
+        })
+    }
+
+    fn source_spans(&self) -> &SpansEnum<P> {
+        if self.within {
+            &self.sub_spans[LITTLE]
+        } else {
+            &self.sub_spans[BIG]
+        }
+    }
+
+    // Moves to the next big span containing a little span, returns false
+    // when one of them is exhausted in the current doc.
+    fn next_containing(&mut self) -> Result<bool> {
+        while self.sub_spans[BIG].next_start_position()? != NO_MORE_POSITIONS {
+            while self.sub_spans[LITTLE].start_position() < self.sub_spans[BIG].start_position() {
+                if self.sub_spans[LITTLE].next_start_position()? == NO_MORE_POSITIONS {
+                    self.conjunction_span.one_exhausted_in_current_doc = true;
+                    return Ok(false);
+                }
+            }
+            if self.sub_spans[BIG].end_position() >= self.sub_spans[LITTLE].end_position() {
+                return Ok(true);
+            }
+        }
+        self.conjunction_span.one_exhausted_in_current_doc = true;
+        Ok(false)
+    }
+
+    // Moves to the next little span within a big span, returns false when
+    // one of them is exhausted in the current doc.
+    fn next_within(&mut self) -> Result<bool> {
+        while self.sub_spans[LITTLE].next_start_position()? != NO_MORE_POSITIONS {
+            while self.sub_spans[BIG].end_position() < self.sub_spans[LITTLE].end_position() {
+                if self.sub_spans[BIG].next_start_position()? == NO_MORE_POSITIONS {
+                    self.conjunction_span.one_exhausted_in_current_doc = true;
+                    return Ok(false);
+                }
+            }
+            if self.sub_spans[BIG].start_position() <= self.sub_spans[LITTLE].start_position() {
+                return Ok(true);
+            }
+        }
+        self.conjunction_span.one_exhausted_in_current_doc = true;
+        Ok(false)
+    }
+
+    fn next_match(&mut self) -> Result<bool> {
+        if self.within {
+            self.next_within()
+        } else {
+            self.next_containing()
+        }
+    }
+}
+
+impl<P: PostingIterator> ConjunctionSpans<P> for ContainSpans<P> {
+    fn conjunction_span_base(&self) -> &ConjunctionSpanBase<P> {
+        &self.conjunction_span
+    }
+
+    fn conjunction_span_base_mut(&mut self) -> &mut ConjunctionSpanBase<P> {
+        &mut self.conjunction_span
+    }
+
+    fn two_phase_current_doc_matches(&mut self) -> Result<bool> {
+        self.conjunction_span.one_exhausted_in_current_doc = false;
+        if self.next_match()? {
+            self.conjunction_span.first_in_current_doc = true;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl<P: PostingIterator> Spans for ContainSpans<P> {
+    fn next_start_position(&mut self) -> Result<i32> {
+        if self.conjunction_span.first_in_current_doc {
+            self.conjunction_span.first_in_current_doc = false;
+            return Ok(self.source_spans().start_position());
+        }
+        if self.next_match()? {
+            Ok(self.source_spans().start_position())
+        } else {
+            Ok(NO_MORE_POSITIONS)
+        }
+    }
+
+    fn start_position(&self) -> i32 {
+        if self.conjunction_span.first_in_current_doc {
+            -1
+        } else if self.conjunction_span.one_exhausted_in_current_doc {
+            NO_MORE_POSITIONS
+        } else {
+            self.source_spans().start_position()
+        }
+    }
+
+    fn end_position(&self) -> i32 {
+        if self.conjunction_span.first_in_current_doc {
+            -1
+        } else if self.conjunction_span.one_exhausted_in_current_doc {
+            NO_MORE_POSITIONS
+        } else {
+            self.source_spans().end_position()
+        }
+    }
+
+    fn width(&self) -> i32 {
+        self.source_spans().width()
+    }
+
+    fn collect(&mut self, collector: &mut impl SpanCollector) -> Result<()> {
+        for spans in &mut self.sub_spans {
+            spans.collect(collector)?;
+        }
+        Ok(())
+    }
+
+    fn positions_cost(&self) -> f32 {
+        // support_two_phase always return `true`
+        unreachable!()
+    }
+}
+
+conjunction_span_doc_iter!(ContainSpans<P: PostingIterator>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::spans::tests::*;
+    use core::search::query::spans::SpanNearQuery;
+
+    fn a_near_c() -> SpanQueryEnum {
+        SpanQueryEnum::Near(
+            SpanNearQuery::new(vec![span_term("a"), span_term("c")], 2, true).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_span_containing_and_within() {
+        // the "a .. c" spans are [0, 3) in the first doc, [1, 4) in the
+        // second one and [0, 4) in the last one
+        let index = span_test_index(&["a b c x b", "b a x c", "a b b c"]);
+
+        // the big spans containing a "b"
+        let query = SpanContainingQuery::new(a_near_c(), span_term("b")).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::Containing(query)),
+            vec![(0, 0, 3), (2, 0, 4)]
+        );
+
+        // the "b" spans within a big span
+        let query = SpanWithinQuery::new(a_near_c(), span_term("b")).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::Within(query)),
+            vec![(0, 1, 2), (2, 1, 2), (2, 2, 3)]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{Codec, CodecPostingIterator, CodecTermState, TermIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    build_sim_weight, PostingsFlag, SpanOrSpans, SpanQuery, SpanTermQuery, SpanTermWeight,
+    SpanWeight, SpansEnum,
+};
+use core::search::query::{MultiTermQuery, MultiTermQueryEnum, Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::SimWeight;
+use core::search::TermContext;
+use core::util::{DisiPriorityQueue, DocId};
+
+use error::Result;
+
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+const SPAN_MULTI_TERM_QUERY: &str = "span_multi_term";
+
+/// The default max number of terms a `SpanMultiTermQueryWrapper` expands to.
+pub const DEFAULT_SPAN_MULTI_TERM_MAX_EXPANSIONS: usize = 1024;
+
+/// Wraps any `MultiTermQuery` as a `SpanQuery`, so it can be nested within
+/// other span queries, e.g. a `SpanNearQuery` of a `WildcardQuery` and a
+/// `SpanTermQuery`.
+///
+/// The pattern is expanded to the matching terms of the index when the weight
+/// is created, which then matches as a span OR of these terms. If more terms
+/// than `max_expansions` match, only the ones with the highest doc freq are
+/// kept.
+pub struct SpanMultiTermQueryWrapper {
+    query: MultiTermQueryEnum,
+    max_expansions: usize,
+}
+
+impl SpanMultiTermQueryWrapper {
+    pub fn new<Q: Into<MultiTermQueryEnum>>(query: Q) -> Self {
+        Self::with_max_expansions(query, DEFAULT_SPAN_MULTI_TERM_MAX_EXPANSIONS)
+    }
+
+    pub fn with_max_expansions<Q: Into<MultiTermQueryEnum>>(
+        query: Q,
+        max_expansions: usize,
+    ) -> Self {
+        SpanMultiTermQueryWrapper {
+            query: query.into(),
+            max_expansions,
+        }
+    }
+
+    /// Returns the wrapped query.
+    pub fn wrapped_query(&self) -> &MultiTermQueryEnum {
+        &self.query
+    }
+
+    // Collects the terms of all the leaves matched by the wrapped query.
+    fn expand_terms<C: Codec>(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Vec<Term>> {
+        let field = self.query.field();
+        let mut doc_freqs: HashMap<Vec<u8>, i32> = HashMap::new();
+        for leaf in searcher.leaves() {
+            if let Some(terms) = leaf.reader.terms(field)? {
+                if let Some(mut iter) = self.query.term_iterator(&terms)? {
+                    while let Some(term) = iter.next()? {
+                        let doc_freq = iter.doc_freq()?;
+                        *doc_freqs.entry(term).or_insert(0) += doc_freq;
+                    }
+                }
+            }
+        }
+
+        let mut terms: Vec<(Vec<u8>, i32)> = doc_freqs.into_iter().collect();
+        if terms.len() > self.max_expansions {
+            // keep the most frequent terms
+            terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            terms.truncate(self.max_expansions);
+        }
+        terms.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(terms
+            .into_iter()
+            .map(|(bytes, _)| Term::new(field.to_string(), bytes))
+            .collect())
+    }
+
+    fn span_multi_term_weight<C: Codec>(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<SpanMultiTermWeight<C>> {
+        let terms = self.expand_terms(searcher)?;
+        let mut sub_weights = Vec::with_capacity(terms.len());
+        for term in terms {
+            sub_weights.push(SpanTermQuery::new(term, None).span_weight(searcher, needs_scores)?);
+        }
+        let mut term_contexts = HashMap::new();
+        if needs_scores {
+            for w in &sub_weights {
+                w.extract_term_contexts(&mut term_contexts);
+            }
+        }
+        let field = self.query.field().to_string();
+        let sim_weight = build_sim_weight(&field, searcher, term_contexts, None)?;
+        Ok(SpanMultiTermWeight {
+            field,
+            sim_weight,
+            sub_weights,
+        })
+    }
+}
+
+impl<C: Codec> SpanQuery<C> for SpanMultiTermQueryWrapper {
+    type Weight = SpanMultiTermWeight<C>;
+
+    fn span_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Self::Weight> {
+        self.span_multi_term_weight(searcher, needs_scores)
+    }
+
+    fn field(&self) -> &str {
+        self.query.field()
+    }
+}
+
+impl<C: Codec> Query<C> for SpanMultiTermQueryWrapper {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(
+            self.span_multi_term_weight(searcher, needs_scores)?,
+        ))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for SpanMultiTermQueryWrapper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanMultiTermQueryWrapper(query: {}, max_expansions: {})",
+            self.query, self.max_expansions
+        )
+    }
+}
+
+/// The weight of a `SpanMultiTermQueryWrapper`, a span OR of the expanded
+/// terms.
+pub struct SpanMultiTermWeight<C: Codec> {
+    field: String,
+    sim_weight: Option<Box<dyn SimWeight<C>>>,
+    sub_weights: Vec<SpanTermWeight<C>>,
+}
+
+impl<C: Codec> SpanWeight<C> for SpanMultiTermWeight<C> {
+    fn sim_weight(&self) -> Option<&dyn SimWeight<C>> {
+        self.sim_weight.as_ref().map(|x| &**x)
+    }
+
+    fn sim_weight_mut(&mut self) -> Option<&mut dyn SimWeight<C>> {
+        if let Some(ref mut sim_weight) = self.sim_weight {
+            Some(sim_weight.as_mut())
+        } else {
+            None
+        }
+    }
+
+    fn get_spans(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        required_postings: &PostingsFlag,
+    ) -> Result<Option<SpansEnum<CodecPostingIterator<C>>>> {
+        let mut sub_spans = Vec::with_capacity(self.sub_weights.len());
+        for w in &self.sub_weights {
+            if let Some(spans) = w.get_spans(reader, required_postings)? {
+                sub_spans.push(spans);
+            }
+        }
+        if sub_spans.len() <= 1 {
+            return Ok(sub_spans.pop());
+        }
+
+        let capacity = sub_spans.len();
+        Ok(Some(SpansEnum::Or(SpanOrSpans::new(
+            DisiPriorityQueue::new(sub_spans),
+            BinaryHeap::with_capacity(capacity),
+        ))))
+    }
+
+    fn extract_term_contexts(
+        &self,
+        contexts: &mut HashMap<Term, Arc<TermContext<CodecTermState<C>>>>,
+    ) {
+        for w in &self.sub_weights {
+            w.extract_term_contexts(contexts)
+        }
+    }
+}
+
+impl<C: Codec> Weight<C> for SpanMultiTermWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        self.do_create_scorer(ctx)
+    }
+
+    fn query_type(&self) -> &'static str {
+        SPAN_MULTI_TERM_QUERY
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.do_normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.do_value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.explain_span(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for SpanMultiTermWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanMultiTermWeight(field: {}, terms: {})",
+            self.field,
+            self.sub_weights.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::spans::tests::*;
+    use core::search::query::spans::SpanNearQuery;
+    use core::search::query::PrefixQuery;
+
+    fn prefix(text: &str) -> PrefixQuery {
+        PrefixQuery::new(Term::new("body".into(), text.as_bytes().to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_span_multi_term_near() {
+        let index = span_test_index(&["quick fox", "quiet fox", "quick brown fox", "fox quick"]);
+        let multi_term = SpanQueryEnum::MultiTerm(SpanMultiTermQueryWrapper::new(prefix("qu")));
+        assert_eq!(
+            span_positions(&index, &multi_term),
+            vec![(0, 0, 1), (1, 0, 1), (2, 0, 1), (3, 1, 2)]
+        );
+
+        let near = SpanNearQuery::new(vec![multi_term, span_term("fox")], 0, true).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::Near(near)),
+            vec![(0, 0, 2), (1, 0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_span_multi_term_max_expansions() {
+        let index = span_test_index(&["quick fox", "quiet fox", "quick brown fox"]);
+        // "quick" is in more docs than "quiet"
+        let query = SpanMultiTermQueryWrapper::with_max_expansions(prefix("qu"), 1);
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::MultiTerm(query)),
+            vec![(0, 0, 1), (2, 0, 1)]
+        );
+
+        let query = SpanMultiTermQueryWrapper::new(prefix("z"));
+        assert!(span_positions(&index, &SpanQueryEnum::MultiTerm(query)).is_empty());
+    }
+}
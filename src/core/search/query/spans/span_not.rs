@@ -0,0 +1,350 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{Codec, CodecEnum, CodecPostingIterator, CodecTermState, PostingIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    build_sim_weight, AcceptStatus, FilterSpans, PostingsFlag, SpanQuery, SpanQueryEnum,
+    SpanWeight, SpanWeightEnum, Spans, SpansEnum, SpansFilter, NO_MORE_POSITIONS,
+};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::SimWeight;
+use core::search::{DocIterator, TermContext};
+use core::util::DocId;
+
+use error::{ErrorKind, Result};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const SPAN_NOT_QUERY: &str = "span_not";
+
+/// Removes matches which overlap with another `SpanQuery` or which are
+/// within `pre` tokens before or `post` tokens after another `SpanQuery`.
+pub struct SpanNotQuery {
+    include: Box<SpanQueryEnum>,
+    exclude: Box<SpanQueryEnum>,
+    pre: i32,
+    post: i32,
+}
+
+impl SpanNotQuery {
+    /// Matches the spans of `include` which have no overlap with the spans
+    /// of `exclude`.
+    pub fn new(include: SpanQueryEnum, exclude: SpanQueryEnum) -> Result<Self> {
+        Self::with_distances(include, exclude, 0, 0)
+    }
+
+    /// Matches the spans of `include` which have no overlap with the spans
+    /// of `exclude` within `pre` tokens before or `post` tokens after them,
+    /// negative distances are taken as 0.
+    pub fn with_distances(
+        include: SpanQueryEnum,
+        exclude: SpanQueryEnum,
+        pre: i32,
+        post: i32,
+    ) -> Result<Self> {
+        if SpanQuery::<CodecEnum>::field(&include) != SpanQuery::<CodecEnum>::field(&exclude) {
+            bail!(ErrorKind::IllegalArgument(
+                "Clauses must have same field.".into()
+            ));
+        }
+        Ok(SpanNotQuery {
+            include: Box::new(include),
+            exclude: Box::new(exclude),
+            pre: pre.max(0),
+            post: post.max(0),
+        })
+    }
+
+    fn span_not_weight<C: Codec>(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<SpanNotWeight<C>> {
+        let include_weight = self.include.span_weight(searcher, needs_scores)?;
+        // the excluded spans are never scored
+        let exclude_weight = self.exclude.span_weight(searcher, false)?;
+        let mut term_contexts = HashMap::new();
+        if needs_scores {
+            include_weight.extract_term_contexts(&mut term_contexts);
+        }
+        SpanNotWeight::new(
+            self,
+            include_weight,
+            exclude_weight,
+            searcher,
+            term_contexts,
+        )
+    }
+}
+
+impl<C: Codec> SpanQuery<C> for SpanNotQuery {
+    type Weight = SpanNotWeight<C>;
+
+    fn span_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Self::Weight> {
+        self.span_not_weight(searcher, needs_scores)
+    }
+
+    fn field(&self) -> &str {
+        SpanQuery::<C>::field(self.include.as_ref())
+    }
+}
+
+impl<C: Codec> Query<C> for SpanNotQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(self.span_not_weight(searcher, needs_scores)?))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        Query::<C>::extract_terms(self.include.as_ref())
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for SpanNotQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanNotQuery(include: {}, exclude: {}, pre: {}, post: {})",
+            self.include, self.exclude, self.pre, self.post
+        )
+    }
+}
+
+pub struct SpanNotWeight<C: Codec> {
+    sim_weight: Option<Box<dyn SimWeight<C>>>,
+    include_weight: Box<SpanWeightEnum<C>>,
+    exclude_weight: Box<SpanWeightEnum<C>>,
+    pre: i32,
+    post: i32,
+}
+
+impl<C: Codec> SpanNotWeight<C> {
+    pub fn new<IS: SearchPlanBuilder<C> + ?Sized>(
+        query: &SpanNotQuery,
+        include_weight: SpanWeightEnum<C>,
+        exclude_weight: SpanWeightEnum<C>,
+        searcher: &IS,
+        terms: HashMap<Term, Arc<TermContext<CodecTermState<C>>>>,
+    ) -> Result<Self> {
+        let sim_weight = build_sim_weight(SpanQuery::<C>::field(query), searcher, terms, None)?;
+        Ok(SpanNotWeight {
+            sim_weight,
+            include_weight: Box::new(include_weight),
+            exclude_weight: Box::new(exclude_weight),
+            pre: query.pre,
+            post: query.post,
+        })
+    }
+}
+
+impl<C: Codec> SpanWeight<C> for SpanNotWeight<C> {
+    fn sim_weight(&self) -> Option<&dyn SimWeight<C>> {
+        self.sim_weight.as_ref().map(|x| &**x)
+    }
+
+    fn sim_weight_mut(&mut self) -> Option<&mut dyn SimWeight<C>> {
+        if let Some(ref mut sim_weight) = self.sim_weight {
+            Some(sim_weight.as_mut())
+        } else {
+            None
+        }
+    }
+
+    fn get_spans(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        required_postings: &PostingsFlag,
+    ) -> Result<Option<SpansEnum<CodecPostingIterator<C>>>> {
+        let include_spans = match self.include_weight.get_spans(reader, required_postings)? {
+            Some(spans) => spans,
+            None => return Ok(None),
+        };
+        match self.exclude_weight.get_spans(reader, required_postings)? {
+            Some(exclude_spans) => Ok(Some(SpansEnum::Not(FilterSpans::new(
+                include_spans,
+                SpanNotFilter::new(exclude_spans, self.pre, self.post),
+            )))),
+            None => Ok(Some(include_spans)),
+        }
+    }
+
+    fn extract_term_contexts(
+        &self,
+        contexts: &mut HashMap<Term, Arc<TermContext<CodecTermState<C>>>>,
+    ) {
+        self.include_weight.extract_term_contexts(contexts)
+    }
+}
+
+impl<C: Codec> Weight<C> for SpanNotWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        self.do_create_scorer(ctx)
+    }
+
+    fn query_type(&self) -> &'static str {
+        SPAN_NOT_QUERY
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.do_normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.do_value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.explain_span(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for SpanNotWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanNotWeight(include: {}, exclude: {}, pre: {}, post: {})",
+            self.include_weight, self.exclude_weight, self.pre, self.post
+        )
+    }
+}
+
+/// Rejects the candidate spans that are within `pre` tokens before or
+/// `post` tokens after a span of `exclude`.
+pub struct SpanNotFilter<P: PostingIterator> {
+    exclude: Box<SpansEnum<P>>,
+    pre: i32,
+    post: i32,
+    // last doc on which `matches` of the exclude spans was called, and its
+    // result, so it is called only once per doc
+    last_approx_doc: DocId,
+    last_approx_result: bool,
+}
+
+impl<P: PostingIterator> SpanNotFilter<P> {
+    pub fn new(exclude: SpansEnum<P>, pre: i32, post: i32) -> Self {
+        SpanNotFilter {
+            exclude: Box::new(exclude),
+            pre,
+            post,
+            last_approx_doc: -1,
+            last_approx_result: false,
+        }
+    }
+}
+
+impl<P: PostingIterator> SpansFilter<P> for SpanNotFilter<P> {
+    fn accept(&mut self, candidate: &mut SpansEnum<P>) -> Result<AcceptStatus> {
+        let doc = candidate.doc_id();
+        if doc > self.exclude.doc_id() {
+            // catch up the exclude spans to the current doc
+            if self.exclude.support_two_phase() {
+                if self.exclude.approximate_advance(doc)? == doc {
+                    self.last_approx_doc = doc;
+                    self.last_approx_result = self.exclude.matches()?;
+                }
+            } else {
+                self.exclude.advance(doc)?;
+            }
+        } else if self.exclude.support_two_phase()
+            && doc == self.exclude.doc_id()
+            && doc != self.last_approx_doc
+        {
+            // the exclude spans are already on the candidate doc, but not verified
+            self.last_approx_doc = doc;
+            self.last_approx_result = self.exclude.matches()?;
+        }
+
+        if doc != self.exclude.doc_id() || (doc == self.last_approx_doc && !self.last_approx_result)
+        {
+            return Ok(AcceptStatus::Yes);
+        }
+
+        if self.exclude.start_position() == -1 {
+            self.exclude.next_start_position()?;
+        }
+        while self.exclude.end_position() <= candidate.start_position() - self.pre {
+            // the exclude span ends before a possible overlap
+            if self.exclude.next_start_position()? == NO_MORE_POSITIONS {
+                return Ok(AcceptStatus::Yes);
+            }
+        }
+
+        if candidate.end_position() + self.post <= self.exclude.start_position() {
+            Ok(AcceptStatus::Yes)
+        } else {
+            Ok(AcceptStatus::No)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::spans::tests::*;
+    use core::search::query::spans::SpanNearQuery;
+
+    #[test]
+    fn test_span_not_distances() {
+        let index = span_test_index(&["a x b c a c c b a", "c a c"]);
+        let positions = |pre: i32, post: i32| -> Vec<(DocId, i32, i32)> {
+            let query =
+                SpanNotQuery::with_distances(span_term("a"), span_term("b"), pre, post).unwrap();
+            span_positions(&index, &SpanQueryEnum::Not(query))
+        };
+
+        // no "a" overlaps a "b", the doc without "b" is kept as is
+        let all = vec![(0, 0, 1), (0, 4, 5), (0, 8, 9), (1, 1, 2)];
+        assert_eq!(positions(0, 0), all);
+        assert_eq!(positions(-1, -1), all);
+        // the "b" at 2 is 1 token after the first "a"
+        assert_eq!(positions(0, 1), all);
+        assert_eq!(positions(0, 2), vec![(0, 4, 5), (0, 8, 9), (1, 1, 2)]);
+        // the "b" at 7 is right before the last "a"
+        assert_eq!(positions(1, 0), vec![(0, 0, 1), (0, 4, 5), (1, 1, 2)]);
+        assert_eq!(positions(1, 2), vec![(0, 4, 5), (1, 1, 2)]);
+        assert_eq!(positions(2, 3), vec![(1, 1, 2)]);
+    }
+
+    #[test]
+    fn test_span_not_overlap() {
+        let index = span_test_index(&["a x b", "a b x"]);
+        let near = SpanNearQuery::new(vec![span_term("a"), span_term("b")], 1, true).unwrap();
+        let query = SpanNotQuery::new(SpanQueryEnum::Near(near), span_term("x")).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::Not(query)),
+            vec![(1, 0, 2)]
+        );
+    }
+}
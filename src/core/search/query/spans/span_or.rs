@@ -253,7 +253,7 @@ pub struct SpanOrSpans<P: PostingIterator> {
 unsafe impl<P: PostingIterator> Send for SpanOrSpans<P> {}
 
 impl<P: PostingIterator> SpanOrSpans<P> {
-    fn new(
+    pub fn new(
         by_doc_queue: DisiPriorityQueue<SpansEnum<P>>,
         by_position_queue: BinaryHeap<SpansElement<P>>,
     ) -> Self {
@@ -264,7 +264,13 @@ impl<P: PostingIterator> SpanOrSpans<P> {
             let mut cost = 0;
             for spans in &by_doc_queue {
                 let cost_weight = max(spans.cost(), 1) as f32;
-                sum_position_cost += spans.positions_cost() * cost_weight;
+                // the positions cost of two phase spans is part of their match cost
+                let position_cost = if spans.support_two_phase() {
+                    spans.match_cost()
+                } else {
+                    spans.positions_cost()
+                };
+                sum_position_cost += position_cost * cost_weight;
                 sum_approx_cost += spans.match_cost() * cost_weight;
                 sum_weight += cost_weight;
                 cost += spans.cost();
@@ -0,0 +1,363 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::{Codec, CodecPostingIterator, CodecTermState, PostingIterator};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::spans::{
+    build_sim_weight, AcceptStatus, FilterSpans, PostingsFlag, SpanQuery, SpanQueryEnum,
+    SpanWeight, SpanWeightEnum, Spans, SpansEnum, SpansFilter,
+};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::similarity::SimWeight;
+use core::search::TermContext;
+use core::util::DocId;
+
+use error::{ErrorKind, Result};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+const SPAN_POSITION_RANGE_QUERY: &str = "span_position_range";
+const SPAN_FIRST_QUERY: &str = "span_first";
+
+/// Checks to see if the spans of `match_query` fall between `start`
+/// (inclusive) and `end` (exclusive) positions.
+pub struct SpanPositionRangeQuery {
+    match_query: Box<SpanQueryEnum>,
+    start: i32,
+    end: i32,
+}
+
+impl SpanPositionRangeQuery {
+    pub fn new(match_query: SpanQueryEnum, start: i32, end: i32) -> Result<Self> {
+        if start < 0 || end < start {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "invalid position range [{}, {})",
+                start, end
+            )));
+        }
+        Ok(SpanPositionRangeQuery {
+            match_query: Box::new(match_query),
+            start,
+            end,
+        })
+    }
+
+    pub fn start(&self) -> i32 {
+        self.start
+    }
+
+    pub fn end(&self) -> i32 {
+        self.end
+    }
+
+    fn span_position_range_weight<C: Codec>(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+        query_type: &'static str,
+    ) -> Result<SpanPositionRangeWeight<C>> {
+        let match_weight = self.match_query.span_weight(searcher, needs_scores)?;
+        let mut term_contexts = HashMap::new();
+        if needs_scores {
+            match_weight.extract_term_contexts(&mut term_contexts);
+        }
+        let sim_weight = build_sim_weight(
+            SpanQuery::<C>::field(self.match_query.as_ref()),
+            searcher,
+            term_contexts,
+            None,
+        )?;
+        Ok(SpanPositionRangeWeight {
+            sim_weight,
+            match_weight: Box::new(match_weight),
+            start: self.start,
+            end: self.end,
+            query_type,
+        })
+    }
+}
+
+impl<C: Codec> SpanQuery<C> for SpanPositionRangeQuery {
+    type Weight = SpanPositionRangeWeight<C>;
+
+    fn span_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Self::Weight> {
+        self.span_position_range_weight(searcher, needs_scores, SPAN_POSITION_RANGE_QUERY)
+    }
+
+    fn field(&self) -> &str {
+        SpanQuery::<C>::field(self.match_query.as_ref())
+    }
+}
+
+impl<C: Codec> Query<C> for SpanPositionRangeQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(self.span_position_range_weight(
+            searcher,
+            needs_scores,
+            SPAN_POSITION_RANGE_QUERY,
+        )?))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        Query::<C>::extract_terms(self.match_query.as_ref())
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for SpanPositionRangeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanPositionRangeQuery(match: {}, start: {}, end: {})",
+            self.match_query, self.start, self.end
+        )
+    }
+}
+
+/// Matches spans near the beginning of a field, that is the spans of
+/// `match_query` which end at or before `end`.
+pub struct SpanFirstQuery {
+    query: SpanPositionRangeQuery,
+}
+
+impl SpanFirstQuery {
+    pub fn new(match_query: SpanQueryEnum, end: i32) -> Result<Self> {
+        let query = SpanPositionRangeQuery::new(match_query, 0, end)?;
+        Ok(SpanFirstQuery { query })
+    }
+
+    pub fn end(&self) -> i32 {
+        self.query.end
+    }
+}
+
+impl<C: Codec> SpanQuery<C> for SpanFirstQuery {
+    type Weight = SpanPositionRangeWeight<C>;
+
+    fn span_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Self::Weight> {
+        self.query
+            .span_position_range_weight(searcher, needs_scores, SPAN_FIRST_QUERY)
+    }
+
+    fn field(&self) -> &str {
+        SpanQuery::<C>::field(&self.query)
+    }
+}
+
+impl<C: Codec> Query<C> for SpanFirstQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(self.query.span_position_range_weight(
+            searcher,
+            needs_scores,
+            SPAN_FIRST_QUERY,
+        )?))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        Query::<C>::extract_terms(&self.query)
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for SpanFirstQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanFirstQuery(match: {}, end: {})",
+            self.query.match_query, self.query.end
+        )
+    }
+}
+
+pub struct SpanPositionRangeWeight<C: Codec> {
+    sim_weight: Option<Box<dyn SimWeight<C>>>,
+    match_weight: Box<SpanWeightEnum<C>>,
+    start: i32,
+    end: i32,
+    query_type: &'static str,
+}
+
+impl<C: Codec> SpanWeight<C> for SpanPositionRangeWeight<C> {
+    fn sim_weight(&self) -> Option<&dyn SimWeight<C>> {
+        self.sim_weight.as_ref().map(|x| &**x)
+    }
+
+    fn sim_weight_mut(&mut self) -> Option<&mut dyn SimWeight<C>> {
+        if let Some(ref mut sim_weight) = self.sim_weight {
+            Some(sim_weight.as_mut())
+        } else {
+            None
+        }
+    }
+
+    fn get_spans(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        required_postings: &PostingsFlag,
+    ) -> Result<Option<SpansEnum<CodecPostingIterator<C>>>> {
+        Ok(self
+            .match_weight
+            .get_spans(reader, required_postings)?
+            .map(|spans| {
+                SpansEnum::PositionRange(FilterSpans::new(
+                    spans,
+                    PositionRangeFilter::new(self.start, self.end),
+                ))
+            }))
+    }
+
+    fn extract_term_contexts(
+        &self,
+        contexts: &mut HashMap<Term, Arc<TermContext<CodecTermState<C>>>>,
+    ) {
+        self.match_weight.extract_term_contexts(contexts)
+    }
+}
+
+impl<C: Codec> Weight<C> for SpanPositionRangeWeight<C> {
+    fn create_scorer(&self, ctx: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        self.do_create_scorer(ctx)
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.query_type
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.do_normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.do_value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.explain_span(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for SpanPositionRangeWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SpanPositionRangeWeight(match: {}, start: {}, end: {})",
+            self.match_weight, self.start, self.end
+        )
+    }
+}
+
+/// Accepts the spans which start at or after `start` and end at or
+/// before `end`.
+pub struct PositionRangeFilter {
+    start: i32,
+    end: i32,
+}
+
+impl PositionRangeFilter {
+    pub fn new(start: i32, end: i32) -> Self {
+        PositionRangeFilter { start, end }
+    }
+}
+
+impl<P: PostingIterator> SpansFilter<P> for PositionRangeFilter {
+    fn accept(&mut self, candidate: &mut SpansEnum<P>) -> Result<AcceptStatus> {
+        if candidate.start_position() >= self.start && candidate.end_position() <= self.end {
+            Ok(AcceptStatus::Yes)
+        } else if candidate.start_position() >= self.end {
+            // the spans are ordered by start position
+            Ok(AcceptStatus::NoMoreInCurrentDoc)
+        } else {
+            Ok(AcceptStatus::No)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::query::spans::tests::*;
+    use core::search::query::spans::SpanNearQuery;
+
+    #[test]
+    fn test_span_first_end() {
+        let index = span_test_index(&["a b a c", "b b b a"]);
+        let first = |end: i32| -> Vec<(DocId, i32, i32)> {
+            let query = SpanFirstQuery::new(span_term("a"), end).unwrap();
+            span_positions(&index, &SpanQueryEnum::First(query))
+        };
+        // the end is exclusive, a span must end at or before it
+        assert!(first(0).is_empty());
+        assert_eq!(first(1), vec![(0, 0, 1)]);
+        assert_eq!(first(2), vec![(0, 0, 1)]);
+        assert_eq!(first(3), vec![(0, 0, 1), (0, 2, 3)]);
+        assert_eq!(first(4), vec![(0, 0, 1), (0, 2, 3), (1, 3, 4)]);
+
+        // the whole span must be before the end, not only its start
+        let near = SpanNearQuery::new(vec![span_term("b"), span_term("a")], 0, true).unwrap();
+        let query = SpanFirstQuery::new(SpanQueryEnum::Near(near), 3).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::First(query)),
+            vec![(0, 1, 3)]
+        );
+    }
+
+    #[test]
+    fn test_span_position_range() {
+        let index = span_test_index(&["a b a c", "b b b a"]);
+        let query = SpanPositionRangeQuery::new(span_term("a"), 1, 4).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::PositionRange(query)),
+            vec![(0, 2, 3), (1, 3, 4)]
+        );
+        let query = SpanPositionRangeQuery::new(span_term("a"), 1, 3).unwrap();
+        assert_eq!(
+            span_positions(&index, &SpanQueryEnum::PositionRange(query)),
+            vec![(0, 2, 3)]
+        );
+
+        assert!(SpanPositionRangeQuery::new(span_term("a"), 2, 1).is_err());
+        assert!(SpanPositionRangeQuery::new(span_term("a"), -1, 1).is_err());
+    }
+}
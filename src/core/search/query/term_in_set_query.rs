@@ -0,0 +1,248 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator};
+use core::codec::{Codec, TermIterator, Terms};
+use core::search::query::{MultiTermIterEnum, MultiTermQuery, MultiTermQueryConstantScoreWeight};
+use core::search::query::{Query, TermQuery, TermText, Weight};
+use core::search::searcher::SearchPlanBuilder;
+
+use error::Result;
+
+pub const TERM_IN_SET: &str = "term_in_set";
+
+/// Specialization for a disjunction over many terms that behaves like a
+/// `ConstantScoreQuery` over a `BooleanQuery` containing only
+/// `Occur::Should` `TermQuery` clauses.
+///
+/// The terms are sorted and de-duplicated on construction, every segment
+/// seeks them in order through its `TermIterator` and the postings are
+/// merged with a disjunction for up to `BOOLEAN_REWRITE_TERM_COUNT_THRESHOLD`
+/// matching terms, beyond that they are unioned into a `DocIdSetBuilder`.
+///
+/// Since the terms are kept in order, two queries built from the same terms
+/// always have the same string representation, and so the same key and hash
+/// in the `LRUQueryCache`, no matter the order they were given in. The
+/// representation is exact: utf-8 terms are quoted and escaped, the other
+/// terms are written in hex.
+#[derive(Clone)]
+pub struct TermInSetQuery {
+    field: String,
+    terms: Arc<Vec<Vec<u8>>>,
+    boost: f32,
+}
+
+impl TermInSetQuery {
+    pub fn new(field: String, mut terms: Vec<Vec<u8>>) -> TermInSetQuery {
+        terms.sort();
+        terms.dedup();
+        TermInSetQuery {
+            field,
+            terms: Arc::new(terms),
+            boost: 1.0f32,
+        }
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+
+    /// Returns the sorted and de-duplicated terms of this query
+    pub fn terms(&self) -> &[Vec<u8>] {
+        &self.terms
+    }
+}
+
+impl MultiTermQuery for TermInSetQuery {
+    fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        if self.terms.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(MultiTermIterEnum::TermInSet(
+            TermInSetTermIterator::new(terms.iterator()?, Arc::clone(&self.terms)),
+        )))
+    }
+}
+
+impl<C: Codec> Query<C> for TermInSetQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            TERM_IN_SET,
+            self.boost,
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for TermInSetQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TermInSetQuery(field: {}, terms: [", &self.field)?;
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", TermText(term))?;
+        }
+        write!(f, "], boost: {})", self.boost)
+    }
+}
+
+/// A `FilteredTermIterator` that only returns the terms of a sorted set.
+///
+/// The wrapped iterator seeks to each term of the set in order, the set
+/// terms that sort before the term it landed on are skipped.
+pub struct TermInSetTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    terms: Arc<Vec<Vec<u8>>>,
+    // index of the last term of the set we seeked to
+    upto: usize,
+}
+
+impl<T: TermIterator> TermInSetTermIterator<T> {
+    /// `terms` must be sorted and must not contain duplicates.
+    pub fn new(terms_iter: T, terms: Arc<Vec<Vec<u8>>>) -> Self {
+        debug_assert!(terms.windows(2).all(|w| w[0] < w[1]));
+        TermInSetTermIterator {
+            base: FilteredTermIterBase::new(terms_iter, true),
+            terms,
+            upto: 0,
+        }
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for TermInSetTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        // the iterator may have landed past the seek term on another term
+        // of the set
+        if self.terms[self.upto..]
+            .binary_search_by(|t| t.as_slice().cmp(term))
+            .is_ok()
+        {
+            Ok(AcceptStatus::YesAndSeek)
+        } else {
+            Ok(AcceptStatus::NoAndSeek)
+        }
+    }
+
+    fn next_seek_term(&mut self) -> Option<Vec<u8>> {
+        if let Some(ref current) = self.base.actual_term {
+            // skip all the terms up to the one the iterator is positioned on
+            while self.upto < self.terms.len() && self.terms[self.upto] <= *current {
+                self.upto += 1;
+            }
+        }
+        self.terms.get(self.upto).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::codec::EmptyTermIterator;
+
+    fn bytes(terms: &[&str]) -> Vec<Vec<u8>> {
+        terms.iter().map(|t| t.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_sorted_terms() {
+        let query = TermInSetQuery::new("id".into(), bytes(&["3", "1", "2", "1"]));
+        assert_eq!(query.terms(), bytes(&["1", "2", "3"]).as_slice());
+
+        let other = TermInSetQuery::new("id".into(), bytes(&["2", "3", "1"]));
+        assert_eq!(query.to_string(), other.to_string());
+        assert_eq!(
+            query.to_string(),
+            "TermInSetQuery(field: id, terms: [\"1\", \"2\", \"3\"], boost: 1)"
+        );
+
+        // the terms can't be confused whatever their bytes
+        let query = TermInSetQuery::new("id".into(), bytes(&["a, b"]));
+        let other = TermInSetQuery::new("id".into(), bytes(&["a", "b"]));
+        assert_ne!(query.to_string(), other.to_string());
+        let query = TermInSetQuery::new("id".into(), vec![vec![0xff], vec![0xfe]]);
+        assert_eq!(
+            query.to_string(),
+            "TermInSetQuery(field: id, terms: [0xfe, 0xff], boost: 1)"
+        );
+        let query = TermInSetQuery::new("id".into(), bytes(&["0xfe", "a\"b"]));
+        assert_eq!(
+            query.to_string(),
+            "TermInSetQuery(field: id, terms: [\"0xfe\", \"a\\\"b\"], boost: 1)"
+        );
+    }
+
+    #[test]
+    fn test_seek_terms() {
+        let terms = Arc::new(bytes(&["b", "d", "e", "g"]));
+        let mut iter = TermInSetTermIterator::new(EmptyTermIterator {}, terms);
+
+        assert_eq!(iter.next_seek_term(), Some(b"b".to_vec()));
+        // the iterator landed on the seek term
+        iter.base.actual_term = Some(b"b".to_vec());
+        match iter.accept(b"b").unwrap() {
+            AcceptStatus::YesAndSeek => {}
+            _ => panic!("expected YesAndSeek"),
+        }
+        assert_eq!(iter.next_seek_term(), Some(b"d".to_vec()));
+
+        // "d" is missing, the iterator landed on "e"
+        iter.base.actual_term = Some(b"e".to_vec());
+        match iter.accept(b"e").unwrap() {
+            AcceptStatus::YesAndSeek => {}
+            _ => panic!("expected YesAndSeek"),
+        }
+        assert_eq!(iter.next_seek_term(), Some(b"g".to_vec()));
+
+        iter.base.actual_term = Some(b"f".to_vec());
+        match iter.accept(b"f").unwrap() {
+            AcceptStatus::NoAndSeek => {}
+            _ => panic!("expected NoAndSeek"),
+        }
+        assert_eq!(iter.next_seek_term(), Some(b"g".to_vec()));
+
+        iter.base.actual_term = Some(b"g".to_vec());
+        assert_eq!(iter.next_seek_term(), None);
+    }
+}
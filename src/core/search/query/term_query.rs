@@ -23,7 +23,7 @@ use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
 use core::search::query::{Query, Weight};
 use core::search::scorer::{Scorer, TermScorer};
-use core::search::searcher::SearchPlanBuilder;
+use core::search::searcher::{SearchPlanBuilder, TermContext};
 use core::search::similarity::{SimWeight, Similarity};
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use core::search::DocIterator;
@@ -53,19 +53,21 @@ impl TermQuery {
     pub fn term(&self) -> &Term {
         &self.term
     }
-}
 
-impl<C: Codec> Query<C> for TermQuery {
-    fn create_weight(
+    /// Creates the `Weight` of this query with the given term states and
+    /// statistics instead of the ones looked up by the searcher, this is used
+    /// by queries which rewrite to several terms and adjust their statistics,
+    /// like `FuzzyQuery`.
+    pub fn create_weight_with_context<C: Codec>(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
         needs_scores: bool,
+        term_context: &TermContext<CodecTermState<C>>,
     ) -> Result<Box<dyn Weight<C>>> {
-        let term_context = searcher.term_state(&self.term)?;
         let max_doc = i64::from(searcher.max_doc());
         let (term_stats, collection_stats) = if needs_scores {
             (
-                vec![searcher.term_statistics(&self.term, term_context.as_ref())],
+                vec![searcher.term_statistics(&self.term, term_context)],
                 searcher.collections_statistics(&self.term.field)?,
             )
         } else {
@@ -90,6 +92,17 @@ impl<C: Codec> Query<C> for TermQuery {
             needs_scores,
         )))
     }
+}
+
+impl<C: Codec> Query<C> for TermQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let term_context = searcher.term_state(&self.term)?;
+        self.create_weight_with_context(searcher, needs_scores, term_context.as_ref())
+    }
 
     fn extract_terms(&self) -> Vec<TermQuery> {
         vec![self.clone()]
@@ -0,0 +1,333 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator};
+use core::codec::{Codec, TermIterator, Terms};
+use core::search::query::{MultiTermIterEnum, MultiTermQuery, MultiTermQueryConstantScoreWeight};
+use core::search::query::{Query, TermQuery, TermText, Weight};
+use core::search::searcher::SearchPlanBuilder;
+
+use error::Result;
+
+pub const TERM_RANGE: &str = "term_range";
+
+/// A `Query` that matches documents within a range of terms.
+///
+/// This query matches the documents looking for terms that fall into the
+/// supplied range according to the byte order of the terms, it is not
+/// intended for numerical ranges; use `PointRangeQuery` instead.
+///
+/// Either bound may be `None`, the range is then open ended on that side.
+/// Like the other `MultiTermQuery`s the matching documents get a constant
+/// score equal to the boost, see `MultiTermQueryConstantScoreWeight`.
+#[derive(Clone)]
+pub struct TermRangeQuery {
+    field: String,
+    lower_term: Option<Vec<u8>>,
+    upper_term: Option<Vec<u8>>,
+    include_lower: bool,
+    include_upper: bool,
+    boost: f32,
+}
+
+impl TermRangeQuery {
+    /// Constructs a query selecting all terms greater/equal than `lower_term`
+    /// but less/equal than `upper_term`.
+    ///
+    /// If an endpoint is `None`, it is said to be "open". Either or both
+    /// endpoints may be open. Open endpoints may not be exclusive (you can't
+    /// select all but the first or last term without explicitly specifying
+    /// the term to exclude.)
+    pub fn new(
+        field: String,
+        lower_term: Option<Vec<u8>>,
+        upper_term: Option<Vec<u8>>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeQuery {
+        TermRangeQuery {
+            field,
+            lower_term,
+            upper_term,
+            include_lower,
+            include_upper,
+            boost: 1.0f32,
+        }
+    }
+
+    /// Factory that creates a new `TermRangeQuery` using strings for term
+    /// text.
+    pub fn new_string_range(
+        field: String,
+        lower_term: Option<&str>,
+        upper_term: Option<&str>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeQuery {
+        TermRangeQuery::new(
+            field,
+            lower_term.map(|t| t.as_bytes().to_vec()),
+            upper_term.map(|t| t.as_bytes().to_vec()),
+            include_lower,
+            include_upper,
+        )
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+
+    /// Returns the lower value of this range query
+    pub fn lower_term(&self) -> Option<&[u8]> {
+        self.lower_term.as_ref().map(Vec::as_slice)
+    }
+
+    /// Returns the upper value of this range query
+    pub fn upper_term(&self) -> Option<&[u8]> {
+        self.upper_term.as_ref().map(Vec::as_slice)
+    }
+
+    /// Returns `true` if the lower endpoint is inclusive
+    pub fn includes_lower(&self) -> bool {
+        self.include_lower
+    }
+
+    /// Returns `true` if the upper endpoint is inclusive
+    pub fn includes_upper(&self) -> bool {
+        self.include_upper
+    }
+
+    // the terms are displayed exactly, an open bound is an unquoted `*` that
+    // can't be mistaken for the term "*"
+    fn bound_text(term: &Option<Vec<u8>>) -> String {
+        match term {
+            Some(t) => TermText(t).to_string(),
+            None => "*".to_string(),
+        }
+    }
+}
+
+impl MultiTermQuery for TermRangeQuery {
+    fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn term_iterator<T: Terms>(&self, terms: &T) -> Result<Option<MultiTermIterEnum<T::Iterator>>> {
+        if let (Some(lower), Some(upper)) = (&self.lower_term, &self.upper_term) {
+            if lower > upper {
+                return Ok(None);
+            }
+        }
+        Ok(Some(MultiTermIterEnum::TermRange(
+            TermRangeTermIterator::new(
+                terms.iterator()?,
+                self.lower_term.clone(),
+                self.upper_term.clone(),
+                self.include_lower,
+                self.include_upper,
+            ),
+        )))
+    }
+}
+
+impl<C: Codec> Query<C> for TermRangeQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(MultiTermQueryConstantScoreWeight::new(
+            self.clone(),
+            TERM_RANGE,
+            self.boost,
+            needs_scores,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self
+    }
+}
+
+impl fmt::Display for TermRangeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TermRangeQuery(field: {}, range: {}{} TO {}{}, boost: {})",
+            &self.field,
+            if self.include_lower { '[' } else { '{' },
+            Self::bound_text(&self.lower_term),
+            Self::bound_text(&self.upper_term),
+            if self.include_upper { ']' } else { '}' },
+            self.boost
+        )
+    }
+}
+
+/// Subclass of `FilteredTermIterator` for enumerating all terms that match
+/// the specified range parameters.
+///
+/// Term enumerations are always ordered by byte order, each term in the
+/// enumeration is greater than all that precede it.
+pub struct TermRangeTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    lower_term: Vec<u8>,
+    upper_term: Option<Vec<u8>>,
+    include_lower: bool,
+    include_upper: bool,
+}
+
+impl<T: TermIterator> TermRangeTermIterator<T> {
+    /// Enumerates all terms greater/equal than `lower_term` but less/equal
+    /// than `upper_term`, `None` bounds are open ended and always inclusive.
+    pub fn new(
+        terms: T,
+        lower_term: Option<Vec<u8>>,
+        upper_term: Option<Vec<u8>>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> Self {
+        // open ended range queries should always be inclusive.
+        let include_lower = include_lower || lower_term.is_none();
+        let include_upper = include_upper || upper_term.is_none();
+        let lower_term = lower_term.unwrap_or_default();
+
+        let mut base = FilteredTermIterBase::new(terms, true);
+        base.initial_seek_term = Some(lower_term.clone());
+        TermRangeTermIterator {
+            base,
+            lower_term,
+            upper_term,
+            include_lower,
+            include_upper,
+        }
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for TermRangeTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        if !self.include_lower && term == self.lower_term.as_slice() {
+            return Ok(AcceptStatus::No);
+        }
+        if let Some(ref upper) = self.upper_term {
+            // if beyond the upper term, or is exclusive and this is equal to
+            // the upper term, break out
+            let upper = upper.as_slice();
+            if term > upper || (!self.include_upper && term == upper) {
+                return Ok(AcceptStatus::End);
+            }
+        }
+        Ok(AcceptStatus::Yes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::codec::EmptyTermIterator;
+
+    fn accept(iter: &TermRangeTermIterator<EmptyTermIterator>, term: &str) -> &'static str {
+        match iter.accept(term.as_bytes()).unwrap() {
+            AcceptStatus::Yes => "yes",
+            AcceptStatus::No => "no",
+            AcceptStatus::End => "end",
+            _ => "seek",
+        }
+    }
+
+    fn range(
+        lower: Option<&str>,
+        upper: Option<&str>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeTermIterator<EmptyTermIterator> {
+        TermRangeTermIterator::new(
+            EmptyTermIterator {},
+            lower.map(|t| t.as_bytes().to_vec()),
+            upper.map(|t| t.as_bytes().to_vec()),
+            include_lower,
+            include_upper,
+        )
+    }
+
+    #[test]
+    fn test_inclusive_exclusive() {
+        let iter = range(Some("20190101"), Some("20191231"), true, true);
+        assert_eq!(iter.base.initial_seek_term, Some(b"20190101".to_vec()));
+        assert_eq!(accept(&iter, "20190101"), "yes");
+        assert_eq!(accept(&iter, "20190601"), "yes");
+        assert_eq!(accept(&iter, "20191231"), "yes");
+        assert_eq!(accept(&iter, "20200101"), "end");
+
+        let iter = range(Some("20190101"), Some("20191231"), false, false);
+        assert_eq!(accept(&iter, "20190101"), "no");
+        assert_eq!(accept(&iter, "201901010"), "yes");
+        assert_eq!(accept(&iter, "20191231"), "end");
+    }
+
+    #[test]
+    fn test_open_ended() {
+        let iter = range(None, Some("b"), false, false);
+        assert_eq!(iter.base.initial_seek_term, Some(vec![]));
+        assert_eq!(accept(&iter, ""), "yes");
+        assert_eq!(accept(&iter, "a"), "yes");
+        assert_eq!(accept(&iter, "b"), "end");
+
+        let iter = range(Some("b"), None, true, false);
+        assert_eq!(accept(&iter, "b"), "yes");
+        assert_eq!(accept(&iter, "\u{4e2d}"), "yes");
+    }
+
+    #[test]
+    fn test_display() {
+        let query = TermRangeQuery::new_string_range("date".into(), Some("a"), None, false, true);
+        assert_eq!(
+            query.to_string(),
+            "TermRangeQuery(field: date, range: {\"a\" TO *], boost: 1)"
+        );
+        let query =
+            TermRangeQuery::new_string_range("date".into(), Some("a"), Some("*"), false, true);
+        assert_eq!(
+            query.to_string(),
+            "TermRangeQuery(field: date, range: {\"a\" TO \"*\"], boost: 1)"
+        );
+        let query = TermRangeQuery::new(
+            "date".into(),
+            Some(vec![0xfe]),
+            Some(vec![0xff, 0]),
+            true,
+            false,
+        );
+        assert_eq!(
+            query.to_string(),
+            "TermRangeQuery(field: date, range: [0xfe TO 0xff00}, boost: 1)"
+        );
+    }
+}
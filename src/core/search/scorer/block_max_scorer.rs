@@ -0,0 +1,652 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::scorer::{DisjunctionSumScorer, Scorer};
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+use std::f32;
+use std::mem;
+
+/// Disjunctions with at least this many clauses are pruned by a
+/// `MaxScoreScorer` rather than a `WANDScorer`, whose pivot selection sorts
+/// all the clauses on every candidate doc.
+pub const MAX_SCORE_MIN_CLAUSES: usize = 8;
+
+fn sum_max_score<T: Scorer>(scorers: &mut [T], up_to: DocId) -> Result<f32> {
+    let mut max_score = 0.0f32;
+    for s in scorers {
+        if s.doc_id() <= up_to {
+            max_score += s.max_score(up_to)?;
+        }
+    }
+    Ok(max_score)
+}
+
+fn min_advance_shallow<T: Scorer>(scorers: &mut [T], target: DocId) -> Result<DocId> {
+    let mut up_to = NO_MORE_DOCS;
+    for s in scorers {
+        if s.doc_id() <= target {
+            up_to = up_to.min(s.advance_shallow(target)?);
+        } else if s.doc_id() != NO_MORE_DOCS {
+            up_to = up_to.min(s.doc_id() - 1);
+        }
+    }
+    Ok(up_to)
+}
+
+/// A disjunction that skips the documents which can't be competitive, with
+/// the block-max WAND algorithm from Shuai Ding and Torsten Suel. 2011.
+/// Faster top-k document retrieval using block-max indexes. SIGIR '11.
+///
+/// The sub scorers are kept sorted by doc id and the pivot is the first of
+/// them at which the sum of the max scores reaches the min competitive score:
+/// no document before the pivot doc can be competitive. The block max scores
+/// of the sub scorers up to the pivot then allow to skip whole blocks.
+///
+/// Until `set_min_competitive_score` is called, this is a plain disjunction
+/// whose score is the sum of the scores of the matching sub scorers, but it
+/// reorders the sub scorers on every doc: `TopScoresDisjunctionScorer` only
+/// switches to it once pruning starts.
+pub struct WANDScorer<T: Scorer> {
+    sub_scorers: Vec<T>,
+    // max score of each sub scorer, computed once pruning starts
+    max_scores: Vec<f32>,
+    // indices of the sub scorers ordered by doc id
+    ordered: Vec<usize>,
+    min_competitive_score: f32,
+    curr_doc: DocId,
+    score: f32,
+    cost: usize,
+}
+
+impl<T: Scorer> WANDScorer<T> {
+    pub fn new(children: Vec<T>) -> WANDScorer<T> {
+        assert!(children.len() > 1);
+
+        let cost = children.iter().map(|s| s.cost()).sum();
+        WANDScorer {
+            max_scores: vec![f32::INFINITY; children.len()],
+            ordered: (0..children.len()).collect(),
+            sub_scorers: children,
+            min_competitive_score: 0.0,
+            curr_doc: -1,
+            score: 0.0,
+            cost,
+        }
+    }
+
+    fn doc(&self, ord: usize) -> DocId {
+        self.sub_scorers[self.ordered[ord]].doc_id()
+    }
+
+    // Moves to the first doc on or after `target` that may be competitive.
+    fn do_next(&mut self, target: DocId) -> Result<DocId> {
+        let mut target = target;
+        loop {
+            for s in &mut self.sub_scorers {
+                if s.doc_id() < target {
+                    s.advance(target)?;
+                }
+            }
+            {
+                let sub_scorers = &self.sub_scorers;
+                self.ordered.sort_by_key(|&i| sub_scorers[i].doc_id());
+            }
+
+            let mut pivot = None;
+            let mut max_score_sum = 0.0f32;
+            for (ord, &i) in self.ordered.iter().enumerate() {
+                if self.sub_scorers[i].doc_id() == NO_MORE_DOCS {
+                    break;
+                }
+                max_score_sum += self.max_scores[i];
+                if max_score_sum >= self.min_competitive_score {
+                    pivot = Some(ord);
+                    break;
+                }
+            }
+            let pivot = match pivot {
+                Some(pivot) => pivot,
+                None => {
+                    self.curr_doc = NO_MORE_DOCS;
+                    return Ok(NO_MORE_DOCS);
+                }
+            };
+            let pivot_doc = self.doc(pivot);
+            // the sub scorers after the pivot that are on the pivot doc match it too
+            let mut last = pivot;
+            while last + 1 < self.ordered.len() && self.doc(last + 1) == pivot_doc {
+                last += 1;
+            }
+
+            if self.min_competitive_score > 0.0 {
+                let mut up_to = if last + 1 < self.ordered.len() {
+                    self.doc(last + 1) - 1
+                } else {
+                    NO_MORE_DOCS
+                };
+                for &i in &self.ordered[..=last] {
+                    up_to = up_to.min(self.sub_scorers[i].advance_shallow(pivot_doc)?);
+                }
+                let mut block_max_score = 0.0f32;
+                for &i in &self.ordered[..=last] {
+                    block_max_score += self.sub_scorers[i].max_score(up_to)?;
+                }
+                if block_max_score < self.min_competitive_score {
+                    // none of the docs up to `up_to` is competitive
+                    if up_to == NO_MORE_DOCS {
+                        self.curr_doc = NO_MORE_DOCS;
+                        return Ok(NO_MORE_DOCS);
+                    }
+                    target = up_to + 1;
+                    continue;
+                }
+            }
+
+            if self.doc(0) != pivot_doc {
+                // move the sub scorers before the pivot to the pivot doc
+                target = pivot_doc;
+                continue;
+            }
+
+            let mut score = 0.0f32;
+            for &i in &self.ordered[..=last] {
+                score += self.sub_scorers[i].score()?;
+            }
+            if self.min_competitive_score <= 0.0 || score >= self.min_competitive_score {
+                self.curr_doc = pivot_doc;
+                self.score = score;
+                return Ok(pivot_doc);
+            }
+            target = pivot_doc + 1;
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for WANDScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.score)
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        min_advance_shallow(&mut self.sub_scorers, target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        sum_max_score(&mut self.sub_scorers, up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        if self.min_competitive_score <= 0.0 {
+            for (i, s) in self.sub_scorers.iter_mut().enumerate() {
+                self.max_scores[i] = s.max_score(NO_MORE_DOCS)?;
+            }
+        }
+        self.min_competitive_score = min_score;
+        Ok(())
+    }
+}
+
+impl<T: Scorer> DocIterator for WANDScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.curr_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        if self.curr_doc == NO_MORE_DOCS {
+            return Ok(NO_MORE_DOCS);
+        }
+        let target = self.curr_doc + 1;
+        self.do_next(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.do_next(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+/// A disjunction that skips the documents which can't be competitive, with
+/// the MaxScore algorithm from Howard Turtle and James Flood. 1995. Query
+/// evaluation: strategies and optimizations. Information Processing &
+/// Management, 31(6), 831-850.
+///
+/// The docs are scored by windows, the block max scores of the sub scorers
+/// are computed for each window and the sub scorers whose max scores sum up
+/// to less than the min competitive score are non essential: they can't make
+/// a document competitive on their own, so only the docs of the essential
+/// sub scorers are candidates. Unlike `WANDScorer`, this doesn't reorder all
+/// the sub scorers on every doc, which pays off for long lists of clauses.
+///
+/// Until `set_min_competitive_score` is called, this is a plain disjunction
+/// whose score is the sum of the scores of the matching sub scorers.
+pub struct MaxScoreScorer<T: Scorer> {
+    sub_scorers: Vec<T>,
+    // max score of each sub scorer in the current window
+    max_scores: Vec<f32>,
+    // indices of the sub scorers ordered by max score
+    ordered: Vec<usize>,
+    // sums of the max scores of the first sub scorers of `ordered`
+    max_score_sums: Vec<f32>,
+    // the first sub scorers of `ordered` are non essential
+    num_non_essential: usize,
+    // last doc of the current window, -1 if it must be recomputed
+    window_up_to: DocId,
+    min_competitive_score: f32,
+    curr_doc: DocId,
+    score: f32,
+    cost: usize,
+}
+
+impl<T: Scorer> MaxScoreScorer<T> {
+    pub fn new(children: Vec<T>) -> MaxScoreScorer<T> {
+        assert!(children.len() > 1);
+
+        let cost = children.iter().map(|s| s.cost()).sum();
+        MaxScoreScorer {
+            max_scores: vec![f32::INFINITY; children.len()],
+            ordered: (0..children.len()).collect(),
+            max_score_sums: vec![f32::INFINITY; children.len()],
+            sub_scorers: children,
+            num_non_essential: 0,
+            window_up_to: -1,
+            min_competitive_score: 0.0,
+            curr_doc: -1,
+            score: 0.0,
+            cost,
+        }
+    }
+
+    // Computes the max scores of the window that starts at `target` and splits
+    // the sub scorers into non essential and essential ones.
+    fn update_window(&mut self, target: DocId) -> Result<()> {
+        if self.min_competitive_score <= 0.0 {
+            // no pruning, every sub scorer is essential
+            self.window_up_to = NO_MORE_DOCS;
+            self.num_non_essential = 0;
+            return Ok(());
+        }
+
+        let mut up_to = NO_MORE_DOCS;
+        for s in &mut self.sub_scorers {
+            if s.doc_id() != NO_MORE_DOCS {
+                up_to = up_to.min(s.advance_shallow(target)?);
+            }
+        }
+        for (i, s) in self.sub_scorers.iter_mut().enumerate() {
+            self.max_scores[i] = if s.doc_id() == NO_MORE_DOCS {
+                0.0
+            } else {
+                s.max_score(up_to)?
+            };
+        }
+        {
+            let max_scores = &self.max_scores;
+            self.ordered.sort_by(|&a, &b| {
+                max_scores[a]
+                    .partial_cmp(&max_scores[b])
+                    .unwrap_or(::std::cmp::Ordering::Equal)
+            });
+        }
+        let mut sum = 0.0f32;
+        self.num_non_essential = 0;
+        for (ord, &i) in self.ordered.iter().enumerate() {
+            sum += self.max_scores[i];
+            self.max_score_sums[ord] = sum;
+            if sum < self.min_competitive_score {
+                self.num_non_essential = ord + 1;
+            }
+        }
+        self.window_up_to = up_to;
+        Ok(())
+    }
+
+    // Moves to the first doc on or after `target` that may be competitive.
+    fn do_next(&mut self, target: DocId) -> Result<DocId> {
+        let mut target = target;
+        loop {
+            if target > self.window_up_to {
+                self.update_window(target)?;
+            }
+            if self.num_non_essential == self.ordered.len() {
+                // no doc of the window is competitive
+                if self.window_up_to == NO_MORE_DOCS {
+                    self.curr_doc = NO_MORE_DOCS;
+                    return Ok(NO_MORE_DOCS);
+                }
+                target = self.window_up_to + 1;
+                continue;
+            }
+
+            let mut doc = NO_MORE_DOCS;
+            for &i in &self.ordered[self.num_non_essential..] {
+                let s = &mut self.sub_scorers[i];
+                if s.doc_id() < target {
+                    s.advance(target)?;
+                }
+                doc = doc.min(s.doc_id());
+            }
+            if doc == NO_MORE_DOCS {
+                self.curr_doc = NO_MORE_DOCS;
+                return Ok(NO_MORE_DOCS);
+            }
+            if doc > self.window_up_to {
+                target = doc;
+                continue;
+            }
+
+            let mut score = 0.0f32;
+            for &i in &self.ordered[self.num_non_essential..] {
+                let s = &mut self.sub_scorers[i];
+                if s.doc_id() == doc {
+                    score += s.score()?;
+                }
+            }
+            // the non essential sub scorers, by decreasing max score, as long
+            // as they can make the doc competitive
+            for ord in (0..self.num_non_essential).rev() {
+                if score + self.max_score_sums[ord] < self.min_competitive_score {
+                    break;
+                }
+                let s = &mut self.sub_scorers[self.ordered[ord]];
+                if s.doc_id() < doc {
+                    s.advance(doc)?;
+                }
+                if s.doc_id() == doc {
+                    score += s.score()?;
+                }
+            }
+            if self.min_competitive_score <= 0.0 || score >= self.min_competitive_score {
+                self.curr_doc = doc;
+                self.score = score;
+                return Ok(doc);
+            }
+            target = doc + 1;
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for MaxScoreScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.score)
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        min_advance_shallow(&mut self.sub_scorers, target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        sum_max_score(&mut self.sub_scorers, up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        self.min_competitive_score = min_score;
+        // the split of the sub scorers depends on the min competitive score
+        self.window_up_to = -1;
+        Ok(())
+    }
+}
+
+impl<T: Scorer> DocIterator for MaxScoreScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.curr_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        if self.curr_doc == NO_MORE_DOCS {
+            return Ok(NO_MORE_DOCS);
+        }
+        let target = self.curr_doc + 1;
+        self.do_next(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.do_next(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+enum DisjunctionState<T: Scorer> {
+    Exhaustive(DisjunctionSumScorer<T>),
+    Wand(WANDScorer<T>),
+    MaxScore(MaxScoreScorer<T>),
+    // only while the sub scorers move to a pruning scorer
+    Switching,
+}
+
+/// The scorer of a pure disjunction of scoring clauses.
+///
+/// It is a `DisjunctionSumScorer` until a collector sets a positive min
+/// competitive score, so the searches whose collectors need every hit don't
+/// pay for the pruning. The sub scorers then move to a `WANDScorer`, or to a
+/// `MaxScoreScorer` for `MAX_SCORE_MIN_CLAUSES` clauses or more, which
+/// resumes on the current doc.
+pub struct TopScoresDisjunctionScorer<T: Scorer> {
+    state: DisjunctionState<T>,
+}
+
+impl<T: Scorer> TopScoresDisjunctionScorer<T> {
+    pub fn new(children: Vec<T>) -> TopScoresDisjunctionScorer<T> {
+        TopScoresDisjunctionScorer {
+            state: DisjunctionState::Exhaustive(DisjunctionSumScorer::new(children, true)),
+        }
+    }
+
+    fn start_pruning(&mut self) -> Result<()> {
+        let (doc, score) = match self.state {
+            DisjunctionState::Exhaustive(ref mut scorer) => {
+                let doc = scorer.doc_id();
+                if doc == -1 || doc == NO_MORE_DOCS {
+                    (doc, 0.0)
+                } else {
+                    (doc, scorer.score()?)
+                }
+            }
+            _ => return Ok(()),
+        };
+        let sub_scorers = match mem::replace(&mut self.state, DisjunctionState::Switching) {
+            DisjunctionState::Exhaustive(scorer) => scorer.into_sub_scorers(),
+            _ => unreachable!(),
+        };
+        self.state = if sub_scorers.len() >= MAX_SCORE_MIN_CLAUSES {
+            let mut scorer = MaxScoreScorer::new(sub_scorers);
+            scorer.curr_doc = doc;
+            scorer.score = score;
+            DisjunctionState::MaxScore(scorer)
+        } else {
+            let mut scorer = WANDScorer::new(sub_scorers);
+            scorer.curr_doc = doc;
+            scorer.score = score;
+            DisjunctionState::Wand(scorer)
+        };
+        Ok(())
+    }
+
+    fn scorer(&self) -> &dyn Scorer {
+        match self.state {
+            DisjunctionState::Exhaustive(ref scorer) => scorer,
+            DisjunctionState::Wand(ref scorer) => scorer,
+            DisjunctionState::MaxScore(ref scorer) => scorer,
+            DisjunctionState::Switching => unreachable!(),
+        }
+    }
+
+    fn scorer_mut(&mut self) -> &mut dyn Scorer {
+        match self.state {
+            DisjunctionState::Exhaustive(ref mut scorer) => scorer,
+            DisjunctionState::Wand(ref mut scorer) => scorer,
+            DisjunctionState::MaxScore(ref mut scorer) => scorer,
+            DisjunctionState::Switching => unreachable!(),
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for TopScoresDisjunctionScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        self.scorer_mut().score()
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer_mut().advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.scorer_mut().max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        if min_score > 0.0 {
+            self.start_pruning()?;
+        }
+        self.scorer_mut().set_min_competitive_score(min_score)
+    }
+}
+
+impl<T: Scorer> DocIterator for TopScoresDisjunctionScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.scorer().doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.scorer_mut().next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer_mut().advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer().cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    struct FixedScorer {
+        iterator: MockDocIterator,
+        score: f32,
+    }
+
+    impl Scorer for FixedScorer {
+        fn score(&mut self) -> Result<f32> {
+            Ok(self.score)
+        }
+
+        fn max_score(&mut self, _up_to: DocId) -> Result<f32> {
+            Ok(self.score)
+        }
+    }
+
+    impl DocIterator for FixedScorer {
+        fn doc_id(&self) -> DocId {
+            self.iterator.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.iterator.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.iterator.advance(target)
+        }
+
+        fn cost(&self) -> usize {
+            self.iterator.cost()
+        }
+    }
+
+    fn sub_scorers() -> Vec<FixedScorer> {
+        vec![
+            FixedScorer {
+                iterator: MockDocIterator::new(vec![1, 2, 3, 5, 8]),
+                score: 1.0,
+            },
+            FixedScorer {
+                iterator: MockDocIterator::new(vec![2, 4, 5, 9]),
+                score: 2.0,
+            },
+            FixedScorer {
+                iterator: MockDocIterator::new(vec![3, 5, 6, 9]),
+                score: 3.0,
+            },
+        ]
+    }
+
+    fn check_pruning<S: Scorer>(scorer: &mut S) {
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert!((scorer.score().unwrap() - 1.0).abs() < f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert!((scorer.score().unwrap() - 3.0).abs() < f32::EPSILON);
+
+        // only the docs scoring at least 3.5 are left
+        scorer.set_min_competitive_score(3.5).unwrap();
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert!((scorer.score().unwrap() - 4.0).abs() < f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 5);
+        assert!((scorer.score().unwrap() - 6.0).abs() < f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 9);
+        assert!((scorer.score().unwrap() - 5.0).abs() < f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_wand_scorer() {
+        check_pruning(&mut WANDScorer::new(sub_scorers()));
+    }
+
+    #[test]
+    fn test_max_score_scorer() {
+        check_pruning(&mut MaxScoreScorer::new(sub_scorers()));
+    }
+
+    #[test]
+    fn test_top_scores_disjunction_scorer() {
+        let mut scorer = TopScoresDisjunctionScorer::new(sub_scorers());
+        // a min competitive score of 0 doesn't start the pruning
+        scorer.set_min_competitive_score(0.0).unwrap();
+        match scorer.state {
+            DisjunctionState::Exhaustive(_) => {}
+            _ => panic!("the pruning must start on a positive min competitive score"),
+        }
+        check_pruning(&mut scorer);
+        match scorer.state {
+            DisjunctionState::Wand(_) => {}
+            _ => panic!("a small disjunction must be pruned by WAND"),
+        }
+
+        let mut sub_scorers = sub_scorers();
+        for _ in sub_scorers.len()..MAX_SCORE_MIN_CLAUSES {
+            sub_scorers.push(FixedScorer {
+                iterator: MockDocIterator::new(vec![]),
+                score: 1.0,
+            });
+        }
+        let mut scorer = TopScoresDisjunctionScorer::new(sub_scorers);
+        check_pruning(&mut scorer);
+        match scorer.state {
+            DisjunctionState::MaxScore(_) => {}
+            _ => panic!("a large disjunction must be pruned by MaxScore"),
+        }
+    }
+}
@@ -93,6 +93,24 @@ impl<T: Scorer> Scorer for ConjunctionScorer<T> {
 
         Ok(score)
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        let mut up_to = self.lead1.advance_shallow(target)?;
+        up_to = up_to.min(self.lead2.advance_shallow(target)?);
+        for scorer in &mut self.others {
+            up_to = up_to.min(scorer.advance_shallow(target)?);
+        }
+        Ok(up_to)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        let mut max_score = self.lead1.max_score(up_to)?;
+        max_score += self.lead2.max_score(up_to)?;
+        for scorer in &mut self.others {
+            max_score += scorer.max_score(up_to)?;
+        }
+        Ok(max_score)
+    }
 }
 
 impl<T: Scorer> DocIterator for ConjunctionScorer<T> {
@@ -41,6 +41,10 @@ impl<T: Scorer> DisjunctionSumScorer<T> {
             cost,
         }
     }
+
+    pub(crate) fn into_sub_scorers(self) -> Vec<T> {
+        self.sub_scorers
+    }
 }
 
 impl<T: Scorer> Scorer for DisjunctionSumScorer<T> {
@@ -57,6 +61,29 @@ impl<T: Scorer> Scorer for DisjunctionSumScorer<T> {
 
         Ok(score)
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        let mut up_to = NO_MORE_DOCS;
+        for s in &mut self.sub_scorers {
+            if s.doc_id() <= target {
+                up_to = up_to.min(s.advance_shallow(target)?);
+            } else if s.doc_id() != NO_MORE_DOCS {
+                // the bound of this scorer changes at its next doc
+                up_to = up_to.min(s.doc_id() - 1);
+            }
+        }
+        Ok(up_to)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        let mut max_score = 0.0f32;
+        for s in &mut self.sub_scorers {
+            if s.doc_id() <= up_to {
+                max_score += s.max_score(up_to)?;
+            }
+        }
+        Ok(max_score)
+    }
 }
 
 impl<T: Scorer> DocIterator for DisjunctionSumScorer<T> {
@@ -222,3 +249,106 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
         Ok(self.doc_id())
     }
 }
+
+/// A disjunction that only matches the documents matched by at least
+/// `minimum_should_match` of its sub scorers, the score is the sum of the
+/// scores of the matching sub scorers.
+pub struct MinShouldMatchSumScorer<T: Scorer> {
+    sub_scorers: Vec<T>,
+    minimum_should_match: usize,
+    curr_doc: DocId,
+    cost: usize,
+}
+
+impl<T: Scorer> MinShouldMatchSumScorer<T> {
+    pub fn new(children: Vec<T>, minimum_should_match: usize) -> MinShouldMatchSumScorer<T> {
+        assert!(minimum_should_match > 0 && minimum_should_match <= children.len());
+
+        let cost = children.iter().map(|w| w.cost()).sum();
+        MinShouldMatchSumScorer {
+            sub_scorers: children,
+            minimum_should_match,
+            curr_doc: -1,
+            cost,
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for MinShouldMatchSumScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        let mut score: f32 = 0.0f32;
+
+        let doc_id = self.doc_id();
+        for s in self.sub_scorers.iter_mut() {
+            if s.doc_id() == doc_id {
+                score += s.score()?;
+            }
+        }
+
+        Ok(score)
+    }
+}
+
+impl<T: Scorer> DocIterator for MinShouldMatchSumScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.curr_doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        if self.curr_doc == NO_MORE_DOCS {
+            return Ok(NO_MORE_DOCS);
+        }
+        let target = self.curr_doc + 1;
+        self.advance(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let mut target = target;
+        loop {
+            let mut min_doc = NO_MORE_DOCS;
+            for s in self.sub_scorers.iter_mut() {
+                if s.doc_id() < target {
+                    s.advance(target)?;
+                }
+
+                min_doc = min_doc.min(s.doc_id());
+            }
+
+            let matched = self
+                .sub_scorers
+                .iter()
+                .filter(|s| s.doc_id() == min_doc)
+                .count();
+            if min_doc == NO_MORE_DOCS || matched >= self.minimum_should_match {
+                self.curr_doc = min_doc;
+                return Ok(min_doc);
+            }
+            target = min_doc + 1;
+        }
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::tests::*;
+
+    #[test]
+    fn test_min_should_match() {
+        let subs = vec![
+            create_mock_scorer(vec![1, 2, 4, 6]),
+            create_mock_scorer(vec![2, 3, 6]),
+            create_mock_scorer(vec![3, 4, 6]),
+        ];
+        let mut scorer = MinShouldMatchSumScorer::new(subs, 2);
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert_eq!(scorer.advance(5).unwrap(), 6);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
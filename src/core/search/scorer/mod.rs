@@ -15,6 +15,10 @@ mod bulk_scorer;
 
 pub use self::bulk_scorer::*;
 
+mod block_max_scorer;
+
+pub use self::block_max_scorer::*;
+
 mod conjunction_scorer;
 
 pub use self::conjunction_scorer::*;
@@ -31,15 +35,24 @@ mod req_opt_scorer;
 
 pub use self::req_opt_scorer::*;
 
+mod req_excl_scorer;
+
+pub use self::req_excl_scorer::*;
+
 mod rescorer;
 
 pub use self::rescorer::*;
 
+mod rescore_pipeline;
+
+pub use self::rescore_pipeline::*;
+
 mod term_scorer;
 
 pub use self::term_scorer::*;
 
 use std::collections::HashMap;
+use std::f32;
 use std::i32;
 
 use core::util::{DocId, IndexedContext, VariantValue};
@@ -84,6 +97,27 @@ pub trait Scorer: DocIterator {
     fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
         unimplemented!()
     }
+
+    /// Advances the score upper bounds to the block of docs that contains
+    /// `target`, without moving the iterator, and returns the last doc of
+    /// that block, i.e. the largest `up_to` for which `max_score(up_to)`
+    /// may be tighter than the bound of the whole segment.
+    fn advance_shallow(&mut self, _target: DocId) -> Result<DocId> {
+        Ok(NO_MORE_DOCS)
+    }
+
+    /// Returns an upper bound of the scores of the docs from the current doc,
+    /// or the target of the last `advance_shallow()`, up to `up_to` inclusive.
+    fn max_score(&mut self, _up_to: DocId) -> Result<f32> {
+        Ok(f32::INFINITY)
+    }
+
+    /// Informs the scorer that the docs scoring less than `min_score` can't
+    /// be collected anymore, so that it may skip them. Top docs collectors
+    /// call this once their queue is full.
+    fn set_min_competitive_score(&mut self, _min_score: f32) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl Scorer for Box<dyn Scorer> {
@@ -98,6 +132,18 @@ impl Scorer for Box<dyn Scorer> {
     fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
         (**self).score_feature()
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        (**self).advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        (**self).max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        (**self).set_min_competitive_score(min_score)
+    }
 }
 
 impl DocIterator for Box<dyn Scorer> {
@@ -142,6 +188,87 @@ impl DocIterator for Box<dyn Scorer> {
     }
 }
 
+/// Wraps a scorer for the collectors that must see every hit: it ignores
+/// `set_min_competitive_score`, so that the wrapped scorer never skips the
+/// docs that are not competitive for a top hits collector.
+pub struct NonPruningScorer<'a, S: Scorer + ?Sized + 'a> {
+    scorer: &'a mut S,
+}
+
+impl<'a, S: Scorer + ?Sized + 'a> NonPruningScorer<'a, S> {
+    pub fn new(scorer: &'a mut S) -> NonPruningScorer<'a, S> {
+        NonPruningScorer { scorer }
+    }
+}
+
+impl<'a, S: Scorer + ?Sized + 'a> Scorer for NonPruningScorer<'a, S> {
+    fn score(&mut self) -> Result<f32> {
+        self.scorer.score()
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        self.scorer.score_context()
+    }
+
+    fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
+        self.scorer.score_feature()
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.scorer.max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, _min_score: f32) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, S: Scorer + ?Sized + 'a> DocIterator for NonPruningScorer<'a, S> {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.scorer.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance(target)
+    }
+
+    fn slow_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.slow_advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.scorer.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.approximate_advance(target)
+    }
+}
+
 /// helper function for doc iterator support two phase
 pub fn two_phase_next(scorer: &mut dyn Scorer) -> Result<DocId> {
     let mut doc = scorer.doc_id();
@@ -260,6 +387,10 @@ impl<T: DocIterator> Scorer for ConstantScoreScorer<T> {
     fn score(&mut self) -> Result<f32> {
         Ok(self.score)
     }
+
+    fn max_score(&mut self, _up_to: DocId) -> Result<f32> {
+        Ok(self.score)
+    }
 }
 
 impl<T: DocIterator> DocIterator for ConstantScoreScorer<T> {
@@ -0,0 +1,118 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::scorer::Scorer;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+use error::Result;
+
+/// A Scorer for queries with a required sub scorer and an excluding
+/// (prohibited) sub scorer, it only scores the documents of the required
+/// scorer that are not matched by the excluding one.
+pub struct ReqExclScorer {
+    req_scorer: Box<dyn Scorer>,
+    excl_scorer: Box<dyn Scorer>,
+}
+
+impl ReqExclScorer {
+    pub fn new(req_scorer: Box<dyn Scorer>, excl_scorer: Box<dyn Scorer>) -> ReqExclScorer {
+        ReqExclScorer {
+            req_scorer,
+            excl_scorer,
+        }
+    }
+
+    fn excluded(&mut self, doc: DocId) -> Result<bool> {
+        let mut excl_doc = self.excl_scorer.doc_id();
+        if excl_doc < doc {
+            excl_doc = self.excl_scorer.advance(doc)?;
+        }
+        Ok(excl_doc == doc && self.excl_scorer.matches()?)
+    }
+
+    fn next_match(&mut self, doc: DocId) -> Result<DocId> {
+        let mut doc = doc;
+        while doc != NO_MORE_DOCS && !self.matches()? {
+            doc = self.req_scorer.approximate_next()?;
+        }
+        Ok(doc)
+    }
+}
+
+impl Scorer for ReqExclScorer {
+    fn score(&mut self) -> Result<f32> {
+        self.req_scorer.score()
+    }
+}
+
+impl DocIterator for ReqExclScorer {
+    fn doc_id(&self) -> DocId {
+        self.req_scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let doc = self.req_scorer.approximate_next()?;
+        self.next_match(doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let doc = self.req_scorer.approximate_advance(target)?;
+        self.next_match(doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.req_scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let doc = self.req_scorer.doc_id();
+        Ok(self.req_scorer.matches()? && !self.excluded(doc)?)
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.req_scorer.match_cost() + self.excl_scorer.match_cost()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.req_scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.req_scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::scorer::*;
+    use core::search::tests::*;
+
+    #[test]
+    fn test_exclusion() {
+        let req: Box<dyn Scorer> = Box::new(create_mock_scorer(vec![1, 2, 3, 4, 5, 7]));
+        let excl: Box<dyn Scorer> = Box::new(DisjunctionSumScorer::new(
+            vec![
+                create_mock_scorer(vec![2, 3]),
+                create_mock_scorer(vec![5, 6]),
+            ],
+            false,
+        ));
+        let mut scorer = ReqExclScorer::new(req, excl);
+
+        assert_eq!(scorer.next().unwrap(), 1);
+        assert_eq!(scorer.next().unwrap(), 4);
+        assert_eq!(scorer.advance(5).unwrap(), 7);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,377 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::codec::Codec;
+use core::index::reader::IndexReader;
+use core::search::ltr::LtrRescorer;
+use core::search::scorer::{merge_rescored_hits, QueryRescorer, RescoreRequest, Rescorer};
+use core::search::searcher::IndexSearcher;
+use core::search::sort_field::{
+    ComparatorValue, FieldComparator, FieldComparatorEnum, ScoreDocHit, SortField, SortFieldType,
+    TopDocs,
+};
+
+use error::{ErrorKind, Result};
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+enum StageRescorer<C: Codec> {
+    Query(RescoreRequest<C>),
+    Ltr(RescoreRequest<C>),
+    FieldTiebreak(Vec<SortField>),
+}
+
+/// A stage of a `RescorePipeline`, which reorders the top `window_size`
+/// hits of the previous stage.
+pub struct RescoreStage<C: Codec> {
+    name: String,
+    window_size: usize,
+    rescorer: StageRescorer<C>,
+}
+
+impl<C: Codec> RescoreStage<C> {
+    /// Rescores the hits with `QueryRescorer`, the window is the one of
+    /// the request.
+    pub fn query(name: &str, request: RescoreRequest<C>) -> Self {
+        Self::new(name, request.window_size, StageRescorer::Query(request))
+    }
+
+    /// Rescores the hits with `LtrRescorer`, the query of the request is
+    /// usually a `LtrQuery`.
+    pub fn ltr(name: &str, request: RescoreRequest<C>) -> Self {
+        Self::new(name, request.window_size, StageRescorer::Ltr(request))
+    }
+
+    /// Sorts the hits by descending score, breaking ties with `sort_fields`
+    /// and then by doc id.
+    pub fn field_tiebreak(name: &str, window_size: usize, sort_fields: Vec<SortField>) -> Self {
+        Self::new(name, window_size, StageRescorer::FieldTiebreak(sort_fields))
+    }
+
+    fn new(name: &str, window_size: usize, rescorer: StageRescorer<C>) -> Self {
+        RescoreStage {
+            name: name.to_string(),
+            window_size,
+            rescorer,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    fn set_window_size(&mut self, window_size: usize) {
+        self.window_size = window_size;
+        match &mut self.rescorer {
+            StageRescorer::Query(req) | StageRescorer::Ltr(req) => req.window_size = window_size,
+            StageRescorer::FieldTiebreak(_) => {}
+        }
+    }
+
+    fn rescore<IS: IndexSearcher<C>>(&self, searcher: &IS, top_docs: &mut TopDocs) -> Result<()> {
+        match &self.rescorer {
+            StageRescorer::Query(req) => QueryRescorer.rescore(searcher, req, top_docs),
+            StageRescorer::Ltr(req) => LtrRescorer.rescore(searcher, req, top_docs),
+            StageRescorer::FieldTiebreak(sort_fields) => {
+                field_tiebreak_rescore(searcher, sort_fields, self.window_size, top_docs)
+            }
+        }
+    }
+}
+
+/// The statistics of the execution of a `RescoreStage`.
+#[derive(Debug, Clone)]
+pub struct RescoreStageStats {
+    pub name: String,
+    /// The window size of the stage, bounded by the one of the previous stage.
+    pub window_size: usize,
+    /// The number of hits in the window.
+    pub rescored_hits: usize,
+    /// The number of hits of the window whose position changed.
+    pub reordered_hits: usize,
+    pub took: Duration,
+}
+
+impl fmt::Display for RescoreStageStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}(window: {}, rescored: {}, reordered: {}, took: {:?})",
+            self.name, self.window_size, self.rescored_hits, self.reordered_hits, self.took
+        )
+    }
+}
+
+/// An ordered list of rescore stages, e.g. a phrase proximity rescore of the
+/// top 1000 hits of a cheap query followed by a model on the top 100.
+///
+/// Every stage only reorders the top hits of the previous stage, its window
+/// is bounded by the window of the previous stage.
+pub struct RescorePipeline<C: Codec> {
+    stages: Vec<RescoreStage<C>>,
+}
+
+impl<C: Codec> Default for RescorePipeline<C> {
+    fn default() -> Self {
+        RescorePipeline { stages: vec![] }
+    }
+}
+
+impl<C: Codec> RescorePipeline<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a stage, its window is reduced to the window of the previous
+    /// stage if it is larger.
+    pub fn add_stage(&mut self, mut stage: RescoreStage<C>) -> Result<()> {
+        if stage.window_size == 0 {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "window size of rescore stage '{}' must be positive",
+                stage.name
+            )));
+        }
+        if let Some(last) = self.stages.last() {
+            if stage.window_size > last.window_size {
+                let window_size = last.window_size;
+                stage.set_window_size(window_size);
+            }
+        }
+        self.stages.push(stage);
+        Ok(())
+    }
+
+    pub fn stages(&self) -> &[RescoreStage<C>] {
+        &self.stages
+    }
+
+    /// Rescores `top_docs` with every stage in order, and returns the
+    /// statistics of the stages.
+    pub fn rescore<IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        top_docs: &mut TopDocs,
+    ) -> Result<Vec<RescoreStageStats>> {
+        let mut stats = Vec::with_capacity(self.stages.len());
+        for stage in &self.stages {
+            let rescored_hits = stage.window_size.min(top_docs.score_docs().len());
+            let before: Vec<_> = top_docs.score_docs()[..rescored_hits]
+                .iter()
+                .map(ScoreDocHit::doc_id)
+                .collect();
+
+            let start = Instant::now();
+            stage.rescore(searcher, top_docs)?;
+            let took = start.elapsed();
+
+            let reordered_hits = before
+                .iter()
+                .zip(top_docs.score_docs().iter())
+                .filter(|(doc, hit)| **doc != hit.doc_id())
+                .count();
+            debug!(
+                "rescore stage '{}' rescored {} hits, reordered {}, took {:?}",
+                stage.name, rescored_hits, reordered_hits, took
+            );
+            stats.push(RescoreStageStats {
+                name: stage.name.clone(),
+                window_size: stage.window_size,
+                rescored_hits,
+                reordered_hits,
+                took,
+            });
+        }
+        Ok(stats)
+    }
+}
+
+fn field_tiebreak_rescore<C: Codec, IS: IndexSearcher<C>>(
+    searcher: &IS,
+    sort_fields: &[SortField],
+    window_size: usize,
+    top_docs: &mut TopDocs,
+) -> Result<()> {
+    let mut hits = top_docs.score_docs().to_vec();
+    hits.truncate(window_size);
+    if hits.is_empty() {
+        return Ok(());
+    }
+    hits.sort_by(ScoreDocHit::order_by_doc);
+
+    let mut comparators: Vec<FieldComparatorEnum> = sort_fields
+        .iter()
+        .map(|f| f.get_comparator(hits.len(), f.missing_value()))
+        .collect();
+    let readers = searcher.reader().leaves();
+    let mut reader_idx = None;
+    for (slot, hit) in hits.iter().enumerate() {
+        let doc_id = hit.doc_id();
+        let idx = readers
+            .iter()
+            .rposition(|r| r.doc_base <= doc_id)
+            .unwrap_or(0);
+        let reader = &readers[idx];
+        if reader_idx != Some(idx) {
+            for c in &mut comparators {
+                c.get_information_from_reader(reader)?;
+            }
+            reader_idx = Some(idx);
+        }
+        for c in &mut comparators {
+            let value = if c.get_type() == SortFieldType::Score {
+                ComparatorValue::Score(hit.score())
+            } else {
+                ComparatorValue::DocScore(doc_id - reader.doc_base, hit.score())
+            };
+            c.copy(slot, value)?;
+        }
+    }
+
+    let mut slots: Vec<usize> = (0..hits.len()).collect();
+    slots.sort_by(|&a, &b| {
+        let mut cmp = hits[b]
+            .score()
+            .partial_cmp(&hits[a].score())
+            .unwrap_or(Ordering::Equal);
+        for (c, field) in comparators.iter().zip(sort_fields.iter()) {
+            if cmp != Ordering::Equal {
+                break;
+            }
+            cmp = c.compare(a, b);
+            if field.is_reverse() {
+                cmp = cmp.reverse();
+            }
+        }
+        cmp.then_with(|| hits[a].doc_id().cmp(&hits[b].doc_id()))
+    });
+    let resorted = slots.into_iter().map(|slot| hits[slot].clone()).collect();
+    merge_rescored_hits(top_docs, resorted, 1.0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Fieldable, Term};
+    use core::index::tests::*;
+    use core::search::query::{ConstantScoreQuery, Query, TermQuery};
+    use core::search::scorer::RescoreMode;
+    use core::search::sort_field::{ScoreDoc, TopScoreDocs};
+    use core::util::DocId;
+
+    // a rescore that adds `boost` to the score of the docs with `text`
+    fn boost_request(text: &str, boost: f32, window_size: usize) -> RescoreRequest<CodecEnum> {
+        let term_query: Box<dyn Query<CodecEnum>> = Box::new(TermQuery::new(
+            Term::new("body".into(), text.as_bytes().to_vec()),
+            1.0,
+            None,
+        ));
+        RescoreRequest::new(
+            Box::new(ConstantScoreQuery::with_boost(term_query, boost)),
+            1.0,
+            1.0,
+            RescoreMode::Total,
+            window_size,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_rescore_pipeline() {
+        // "b" is in the docs 3, 5 and 8, "c" in the docs 0 and 4
+        let docs: Vec<Vec<Box<dyn Fieldable>>> = (0..10)
+            .map(|i| {
+                let mut text = "a".to_string();
+                if i == 3 || i == 5 || i == 8 {
+                    text.push_str(" b");
+                }
+                if i == 0 || i == 4 {
+                    text.push_str(" c");
+                }
+                vec![text_field("body", &text)]
+            })
+            .collect();
+        let index = TestIndex::new(docs, usize::max_value());
+        let searcher = index.searcher();
+
+        let mut pipeline = RescorePipeline::new();
+        pipeline
+            .add_stage(RescoreStage::query("b", boost_request("b", 20.0, 6)))
+            .unwrap();
+        pipeline
+            .add_stage(RescoreStage::query("c", boost_request("c", 30.0, 3)))
+            .unwrap();
+        // the window of a stage is bounded by the one of the previous stage
+        pipeline
+            .add_stage(RescoreStage::query("z", boost_request("z", 40.0, 10)))
+            .unwrap();
+        let windows: Vec<usize> = pipeline.stages().iter().map(|s| s.window_size()).collect();
+        assert_eq!(windows, vec![6, 3, 3]);
+        assert!(pipeline
+            .add_stage(RescoreStage::query("empty", boost_request("a", 1.0, 0)))
+            .is_err());
+
+        // the doc `i` has the score `10 - i`
+        let hits = (0..10)
+            .map(|i| ScoreDocHit::Score(ScoreDoc::new(i, (10 - i) as f32)))
+            .collect();
+        let mut top_docs = TopDocs::Score(TopScoreDocs::new(10, hits));
+        let stats = pipeline.rescore(&searcher, &mut top_docs).unwrap();
+
+        // the doc 8 has a "b" but is out of the first window, the doc 4 has
+        // a "c" but is out of the second one
+        let hits: Vec<(DocId, f32)> = top_docs
+            .score_docs()
+            .iter()
+            .map(|h| (h.doc_id(), h.score()))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                (0, 40.0),
+                (3, 27.0),
+                (5, 25.0),
+                (1, 9.0),
+                (2, 8.0),
+                (4, 6.0),
+                (6, 4.0),
+                (7, 3.0),
+                (8, 2.0),
+                (9, 1.0),
+            ]
+        );
+
+        let stage_stats: Vec<(&str, usize, usize, usize)> = stats
+            .iter()
+            .map(|s| {
+                (
+                    s.name.as_str(),
+                    s.window_size,
+                    s.rescored_hits,
+                    s.reordered_hits,
+                )
+            })
+            .collect();
+        assert_eq!(
+            stage_stats,
+            vec![("b", 6, 6, 6), ("c", 3, 3, 3), ("z", 3, 3, 0)]
+        );
+    }
+}
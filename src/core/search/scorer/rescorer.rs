@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use error::Result;
+use error::{ErrorKind, Result};
 
 use std::collections::HashMap;
 use std::fmt;
@@ -29,9 +29,14 @@ use core::search::sort_field::TopDocs;
 use core::util::DocId;
 use core::util::{IndexedContext, VariantValue};
 
+/// Scores a batch of docs from the values collected in their contexts.
 pub trait BatchScorer {
+    /// Returns one score per context, in the same order. The default fails,
+    /// the scorers created by `Weight::create_batch_scorer` override it.
     fn scores(&self, _score_context: Vec<&IndexedContext>) -> Result<Vec<f32>> {
-        unimplemented!()
+        bail!(ErrorKind::IllegalState(
+            "batch scoring is not supported by this scorer".into()
+        ))
     }
 }
 
@@ -91,6 +96,10 @@ impl<C: Codec> RescoreRequest<C> {
             rescore_movedout,
         }
     }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -310,26 +319,7 @@ impl QueryRescorer {
         hits.sort_by(ScoreDocHit::order_by_doc);
 
         let readers = searcher.reader().leaves();
-        let mut score_field_index = -1;
-        match *top_docs {
-            TopDocs::Field(ref f) => {
-                for (index, field) in f.fields.iter().enumerate() {
-                    if field.field_type() == SortFieldType::Score {
-                        score_field_index = index as i32;
-                        break;
-                    }
-                }
-            }
-            TopDocs::Collapse(ref c) => {
-                for (index, field) in c.fields.iter().enumerate() {
-                    if field.field_type() == SortFieldType::Score {
-                        score_field_index = index as i32;
-                        break;
-                    }
-                }
-            }
-            _ => {}
-        }
+        let score_field_index = score_sort_field_index(top_docs);
 
         let weight = req.query.create_weight(searcher, true)?;
 
@@ -378,42 +368,9 @@ impl QueryRescorer {
         resorted: Vec<ScoreDocHit>,
         ctx: &RescoreRequest<C>,
     ) {
-        let rescore_len = resorted.len();
-        let mut resorted = resorted;
-        // used for collapsing top docs
-        let mut doc_idx_map = HashMap::new();
-        {
-            let hits = docs.score_docs_mut();
-
-            for (i, hit) in hits.iter().enumerate().take(rescore_len) {
-                doc_idx_map.insert(hit.doc_id(), i);
-            }
-
-            for i in 0..rescore_len {
-                hits[rescore_len - 1 - i] = resorted.pop().unwrap();
-            }
-            if hits.len() > rescore_len {
-                for hit in hits.iter_mut().skip(rescore_len) {
-                    // TODO: shouldn't this be up to the ScoreMode?  I.e., we should just invoke
-                    // ScoreMode.combine, passing 0.0f for the secondary score?
-                    let score = hit.score();
-                    hit.set_score(score * ctx.query_weight);
-                }
-            }
-        }
-
-        // adjust collapse_values for collapse top docs after rescore
-        if let TopDocs::Collapse(ref mut c) = docs {
-            // TODO maybe we can prevent clone collapse values
-            let mut collapse_value = Vec::with_capacity(c.collapse_values.len());
-            for i in 0..rescore_len {
-                let idx = &doc_idx_map[&c.score_docs[i].doc_id()];
-                collapse_value.push(c.collapse_values[*idx].clone());
-            }
-            let length = c.collapse_values.len();
-            collapse_value.extend(c.collapse_values[rescore_len..length].to_owned());
-            c.collapse_values = collapse_value;
-        }
+        // TODO: shouldn't this be up to the ScoreMode?  I.e., we should just invoke
+        // ScoreMode.combine, passing 0.0f for the secondary score?
+        merge_rescored_hits(docs, resorted, ctx.query_weight)
     }
 
     fn explain_inner<C: Codec, IS: IndexSearcher<C>>(
@@ -538,6 +495,60 @@ impl QueryRescorer {
     }
 }
 
+/// Replaces the first hits of `docs` with the rescored and resorted hits of
+/// the rescore window, and multiplies the scores of the hits after the window
+/// with `rest_weight`.
+pub fn merge_rescored_hits(docs: &mut TopDocs, resorted: Vec<ScoreDocHit>, rest_weight: f32) {
+    let rescore_len = resorted.len();
+    let mut resorted = resorted;
+    // used for collapsing top docs
+    let mut doc_idx_map = HashMap::new();
+    {
+        let hits = docs.score_docs_mut();
+
+        for (i, hit) in hits.iter().enumerate().take(rescore_len) {
+            doc_idx_map.insert(hit.doc_id(), i);
+        }
+
+        for i in 0..rescore_len {
+            hits[rescore_len - 1 - i] = resorted.pop().unwrap();
+        }
+        if hits.len() > rescore_len {
+            for hit in hits.iter_mut().skip(rescore_len) {
+                let score = hit.score();
+                hit.set_score(score * rest_weight);
+            }
+        }
+    }
+
+    // adjust collapse_values for collapse top docs after rescore
+    if let TopDocs::Collapse(ref mut c) = docs {
+        // TODO maybe we can prevent clone collapse values
+        let mut collapse_value = Vec::with_capacity(c.collapse_values.len());
+        for i in 0..rescore_len {
+            let idx = &doc_idx_map[&c.score_docs[i].doc_id()];
+            collapse_value.push(c.collapse_values[*idx].clone());
+        }
+        let length = c.collapse_values.len();
+        collapse_value.extend(c.collapse_values[rescore_len..length].to_owned());
+        c.collapse_values = collapse_value;
+    }
+}
+
+/// Returns the index of the score in the sort values of field sorted `TopDocs`,
+/// or -1 if the hits are not sorted by score.
+pub fn score_sort_field_index(top_docs: &TopDocs) -> i32 {
+    let fields = match *top_docs {
+        TopDocs::Field(ref f) => &f.fields,
+        TopDocs::Collapse(ref c) => &c.fields,
+        _ => return -1,
+    };
+    fields
+        .iter()
+        .position(|field| field.field_type() == SortFieldType::Score)
+        .map_or(-1, |index| index as i32)
+}
+
 impl Rescorer for QueryRescorer {
     fn rescore<C: Codec, IS: IndexSearcher<C>>(
         &self,
@@ -21,13 +21,21 @@ use error::Result;
 pub struct TermScorer<T: PostingIterator> {
     sim_scorer: Box<dyn SimScorer>,
     postings_iterator: T,
+    max_score: f32,
+    // last doc of the block reached by `advance_shallow`, and its max score
+    block_up_to: DocId,
+    block_max_score: f32,
 }
 
 impl<T: PostingIterator> TermScorer<T> {
     pub fn new(sim_scorer: Box<dyn SimScorer>, postings_iterator: T) -> Self {
+        let max_score = sim_scorer.max_score(None);
         TermScorer {
             sim_scorer,
             postings_iterator,
+            max_score,
+            block_up_to: -1,
+            block_max_score: max_score,
         }
     }
 
@@ -46,6 +54,26 @@ impl<T: PostingIterator> Scorer for TermScorer<T> {
         let freq = self.freq();
         Ok(self.sim_scorer.score(doc_id, freq as f32)?)
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        let up_to = self.postings_iterator.advance_shallow(target)?;
+        if up_to != self.block_up_to {
+            self.block_up_to = up_to;
+            self.block_max_score = match self.postings_iterator.impact() {
+                Some(impact) => self.sim_scorer.max_score(Some(&impact)).min(self.max_score),
+                None => self.max_score,
+            };
+        }
+        Ok(up_to)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        if up_to <= self.block_up_to {
+            Ok(self.block_max_score)
+        } else {
+            Ok(self.max_score)
+        }
+    }
 }
 
 impl<T: PostingIterator> DocIterator for TermScorer<T> {
@@ -26,13 +26,18 @@ use core::index::reader::{IndexReader, LeafReaderContext, LeafReaderContextPtr,
 use core::search::cache::{
     LRUQueryCache, QueryCache, QueryCachingPolicy, UsageTrackingQueryCachingPolicy,
 };
-use core::search::collector::{self, Collector, ParallelLeafCollector, SearchCollector};
+use core::search::collector::{
+    self, Collector, ParallelLeafCollector, SearchCollector, TopDocsCollector,
+};
 use core::search::explanation::Explanation;
 use core::search::query::{ConstantScoreQuery, MatchAllDocsQuery, Query, TermQuery, Weight};
-use core::search::scorer::{BulkScorer, Scorer};
+use core::search::scorer::{
+    BulkScorer, NonPruningScorer, RescorePipeline, RescoreStageStats, Scorer,
+};
 use core::search::similarity::{
     BM25Similarity, SimScorer, SimWeight, Similarity, SimilarityProducer,
 };
+use core::search::sort_field::TopDocs;
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use core::search::NO_MORE_DOCS;
 use core::util::external::{DefaultContext, ThreadPool, ThreadPoolBuilder};
@@ -90,6 +95,13 @@ impl<S: TermState> TermContext<S> {
         Ok(())
     }
 
+    /// Registers the term state of the leaf starting at `doc_base` and
+    /// accumulates the term statistics of that leaf.
+    pub fn register(&mut self, doc_base: DocId, state: S, doc_freq: i32, total_term_freq: i64) {
+        self.accumulate_statistics(doc_freq, total_term_freq);
+        self.states.push((doc_base, state));
+    }
+
     fn accumulate_statistics(&mut self, doc_freq: i32, total_term_freq: i64) {
         self.doc_freq += doc_freq;
         if self.total_term_freq >= 0 && total_term_freq >= 0 {
@@ -203,6 +215,9 @@ pub trait SearchPlanBuilder<C: Codec> {
     /// max doc of the reader in searcher, same as IndexSearcher::reader()::max_doc()
     fn max_doc(&self) -> i32;
 
+    /// leaves of the reader in searcher, same as IndexSearcher::reader()::leaves()
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>>;
+
     /// Creates a `Weight` for the given query, potentially adding caching
     /// if possible and configured.
     fn create_weight(&self, query: &dyn Query<C>, needs_scores: bool)
@@ -246,6 +261,37 @@ pub trait IndexSearcher<C: Codec>: SearchPlanBuilder<C> {
     fn count(&self, query: &dyn Query<C>) -> Result<i32>;
 
     fn explain(&self, query: &dyn Query<C>, doc: DocId) -> Result<Explanation>;
+
+    /// Rescores `top_docs`, the hits of a previous search, with the stages of
+    /// `pipeline` in order, and returns the statistics of the stages.
+    fn rescore(
+        &self,
+        pipeline: &RescorePipeline<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<Vec<RescoreStageStats>>
+    where
+        Self: Sized,
+    {
+        pipeline.rescore(self, top_docs)
+    }
+
+    /// Collects the top `num_hits` hits of `query` and rescores them with the
+    /// stages of `pipeline`.
+    fn search_rescored(
+        &self,
+        query: &dyn Query<C>,
+        num_hits: usize,
+        pipeline: &RescorePipeline<C>,
+    ) -> Result<(TopDocs, Vec<RescoreStageStats>)>
+    where
+        Self: Sized,
+    {
+        let mut collector = TopDocsCollector::new(num_hits);
+        self.search(query, &mut collector)?;
+        let mut top_docs = collector.top_docs();
+        let stats = self.rescore(pipeline, &mut top_docs)?;
+        Ok((top_docs, stats))
+    }
 }
 
 ///  Implements search over a single IndexReader.
@@ -386,8 +432,21 @@ where
         live_docs: &B,
         next_limit: usize,
     ) -> Result<()> {
-        let mut bulk_scorer = BulkScorer::new(scorer);
-        match bulk_scorer.score(collector, Some(live_docs), 0, NO_MORE_DOCS, next_limit) {
+        let res = if collector.wants_min_competitive_score() {
+            BulkScorer::new(scorer).score(collector, Some(live_docs), 0, NO_MORE_DOCS, next_limit)
+        } else {
+            // the collector must see every hit, the scorer must not skip the
+            // non competitive ones
+            let mut scorer = NonPruningScorer::new(scorer);
+            BulkScorer::new(&mut scorer).score(
+                collector,
+                Some(live_docs),
+                0,
+                NO_MORE_DOCS,
+                next_limit,
+            )
+        };
+        match res {
             Err(Error(ErrorKind::Collector(collector::ErrorKind::CollectionTerminated), _)) => {
                 // Collection was terminated prematurely
                 Ok(())
@@ -659,6 +718,10 @@ where
         self.reader.max_doc()
     }
 
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>> {
+        self.reader.leaves()
+    }
+
     /// Creates a {@link Weight} for the given query, potentially adding caching
     /// if possible and configured.
     fn create_weight(
@@ -684,13 +747,17 @@ where
         query: &dyn Query<C>,
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>> {
-        let weight = self.create_weight(query, needs_scores)?;
-        //        let v = weight.value_for_normalization();
-        //        let mut norm: f32 = self.similarity("", needs_scores).query_norm(v, None);
-        //        if norm.is_finite() || norm.is_nan() {
-        //            norm = 1.0f32;
-        //        }
-        //        weight.normalize(norm, 1.0f32);
+        let mut weight = self.create_weight(query, needs_scores)?;
+        let v = weight.value_for_normalization();
+        let mut norm: f32 = self.similarity("", needs_scores).query_norm(v, None);
+        if !norm.is_finite() {
+            norm = 1.0f32;
+        }
+        // the boosts are applied when the weights are created, only the
+        // similarities that normalize queries need another pass
+        if (norm - 1.0f32).abs() > ::std::f32::EPSILON {
+            weight.normalize(norm, 1.0f32);
+        }
         Ok(weight)
     }
 
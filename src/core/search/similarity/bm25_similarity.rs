@@ -22,7 +22,7 @@ use std::sync::Arc;
 
 use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::FieldInvertState;
-use core::codec::Codec;
+use core::codec::{Codec, Impact};
 use core::index::reader::SearchLeafReader;
 use core::search::explanation::Explanation;
 use core::search::similarity::{SimScorer, SimWeight, Similarity};
@@ -175,6 +175,15 @@ impl<C: Codec> Similarity<C> for BM25Similarity {
             boost,
         ))
     }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        BM25Similarity::compute_norm(state)
+    }
+
+    fn monotonic_norms(&self) -> bool {
+        // the shorter or the more boosted the field, the larger the norm
+        true
+    }
 }
 
 impl fmt::Display for BM25Similarity {
@@ -220,6 +229,22 @@ impl SimScorer for BM25SimScorer {
     fn compute_slop_factor(&self, distance: i32) -> f32 {
         BM25Similarity::sloppy_freq(distance)
     }
+
+    fn max_score(&self, impact: Option<&Impact>) -> f32 {
+        // the score tends to `weight * (k1 + 1)` as the freq grows
+        match impact {
+            Some(impact) => {
+                let norm = if self.norms.is_some() {
+                    self.cache[(impact.norm & 0xFF) as usize]
+                } else {
+                    self.k1
+                };
+                let freq = impact.freq as f32;
+                self.weight * (self.k1 + 1.0) * freq / (freq + norm)
+            }
+            None => self.weight * (self.k1 + 1.0),
+        }
+    }
 }
 
 struct BM25SimWeight {
@@ -0,0 +1,110 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+use std::fmt;
+
+use core::codec::{Codec, Impact};
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{SimScorer, SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+/// Simple similarity that gives terms a score that is equal to their query
+/// boost.
+///
+/// This similarity is typically used with disabled norms since neither
+/// document statistics nor index statistics are used for scoring. That
+/// said, if norms are enabled, they will be computed the same way as
+/// `BM25Similarity` so that switching between the two is possible.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BooleanSimilarity;
+
+impl<C: Codec> Similarity<C> for BooleanSimilarity {
+    fn compute_weight(
+        &self,
+        _collection_stats: &CollectionStatistics,
+        _term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        Box::new(BooleanSimWeight { boost })
+    }
+
+    fn monotonic_norms(&self) -> bool {
+        // the norms of `BM25Similarity` are written, but never scored
+        true
+    }
+}
+
+impl fmt::Display for BooleanSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BooleanSimilarity")
+    }
+}
+
+struct BooleanSimWeight {
+    boost: f32,
+}
+
+impl<C: Codec> SimWeight<C> for BooleanSimWeight {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.boost * self.boost
+    }
+
+    fn normalize(&mut self, _query_norm: f32, boost: f32) {
+        self.boost = boost;
+    }
+
+    fn sim_scorer(&self, _reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        Ok(Box::new(BooleanSimScorer { boost: self.boost }))
+    }
+
+    fn explain(
+        &self,
+        _reader: &SearchLeafReader<C>,
+        _doc: DocId,
+        _freq: Explanation,
+    ) -> Result<Explanation> {
+        Ok(Explanation::new(
+            true,
+            self.boost,
+            "score(BooleanSimilarity), computed from:".to_string(),
+            vec![Explanation::new(
+                true,
+                self.boost,
+                "boost, query boost".to_string(),
+                vec![],
+            )],
+        ))
+    }
+}
+
+struct BooleanSimScorer {
+    boost: f32,
+}
+
+impl SimScorer for BooleanSimScorer {
+    fn score(&mut self, _doc: DocId, _freq: f32) -> Result<f32> {
+        Ok(self.boost)
+    }
+
+    fn compute_slop_factor(&self, _distance: i32) -> f32 {
+        1.0
+    }
+
+    fn max_score(&self, _impact: Option<&Impact>) -> f32 {
+        self.boost
+    }
+}
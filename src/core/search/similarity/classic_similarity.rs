@@ -0,0 +1,339 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+use std::fmt;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::field_infos::FieldInvertState;
+use core::codec::{Codec, Impact};
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{SimScorer, SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::SmallFloat;
+use core::util::{DocId, KeyedContext};
+
+lazy_static! {
+    static ref NORM_TABLE: [f32; 256] = {
+        let mut norm_table: [f32; 256] = [0f32; 256];
+        for (i, norm) in norm_table.iter_mut().enumerate() {
+            *norm = SmallFloat::byte315_to_float(i as u8);
+        }
+        norm_table
+    };
+}
+
+/// The classic Lucene scoring, a vector space model of TF-IDF.
+///
+/// The score of a term in a document is `tf * idf^2 * boost * queryNorm *
+/// lengthNorm`, where:
+///
+/// * `tf` is the square root of the term frequency,
+/// * `idf` is `1 + log((docCount + 1) / (docFreq + 1))`,
+/// * `queryNorm` is `1 / sqrt(sumOfSquaredWeights)`, the weights being the
+///   `idf * boost` of the terms of the query, which makes the scores of
+///   different queries comparable, see `Similarity::query_norm`,
+/// * `lengthNorm` is the index time norm `1 / sqrt(numTerms)`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ClassicSimilarity;
+
+impl ClassicSimilarity {
+    pub fn tf(freq: f32) -> f32 {
+        freq.sqrt()
+    }
+
+    pub fn idf(doc_freq: i64, doc_count: i64) -> f32 {
+        ((doc_count as f64 + 1.0) / (doc_freq as f64 + 1.0)).ln() as f32 + 1.0
+    }
+
+    fn sloppy_freq(distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+
+    pub fn compute_norm(state: &FieldInvertState) -> i64 {
+        let num_terms = state.length - state.num_overlap;
+        ClassicSimilarity::encode_norm_value(state.boost, num_terms) as i64
+    }
+
+    pub fn encode_norm_value(boost: f32, field_length: i32) -> u8 {
+        SmallFloat::float_to_byte315(boost / (field_length as f32).sqrt())
+    }
+
+    #[inline]
+    fn decode_norm_value(b: usize) -> f32 {
+        NORM_TABLE[b]
+    }
+
+    fn idf_explain(
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Explanation {
+        let doc_count = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+        let mut details: Vec<Explanation> = term_stats
+            .iter()
+            .map(|stat| {
+                Explanation::new(
+                    true,
+                    ClassicSimilarity::idf(stat.doc_freq, doc_count),
+                    "idf, computed as log((docCount+1)/(docFreq+1)) + 1 from:".to_string(),
+                    vec![
+                        Explanation::new(true, stat.doc_freq as f32, "docFreq".to_string(), vec![]),
+                        Explanation::new(true, doc_count as f32, "docCount".to_string(), vec![]),
+                    ],
+                )
+            })
+            .collect();
+        if details.len() == 1 {
+            details.remove(0)
+        } else {
+            let idf = details.iter().map(Explanation::value).sum();
+            Explanation::new(true, idf, "idf(), sum of:".to_string(), details)
+        }
+    }
+}
+
+impl<C: Codec> Similarity<C> for ClassicSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let idf = ClassicSimilarity::idf_explain(collection_stats, term_stats);
+        let mut weight = ClassicSimWeight {
+            field: collection_stats.field.clone(),
+            idf,
+            boost: 1.0,
+            query_norm: 1.0,
+            query_weight: 0.0,
+            value: 0.0,
+        };
+        weight.do_normalize(1.0, boost);
+        Box::new(weight)
+    }
+
+    fn query_norm(&self, sum_of_squared_weights: f32, _context: Option<&KeyedContext>) -> f32 {
+        1.0 / sum_of_squared_weights.sqrt()
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        ClassicSimilarity::compute_norm(state)
+    }
+
+    fn monotonic_norms(&self) -> bool {
+        // the shorter or the more boosted the field, the larger the norm
+        true
+    }
+}
+
+impl fmt::Display for ClassicSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClassicSimilarity")
+    }
+}
+
+struct ClassicSimScorer {
+    value: f32,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl SimScorer for ClassicSimScorer {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let raw = ClassicSimilarity::tf(freq) * self.value;
+        if let Some(ref norms) = self.norms {
+            let norm = ClassicSimilarity::decode_norm_value((norms.get(doc)? & 0xFF) as usize);
+            Ok(raw * norm)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        ClassicSimilarity::sloppy_freq(distance)
+    }
+
+    fn max_score(&self, impact: Option<&Impact>) -> f32 {
+        // the tf grows with the freq without bound
+        match impact {
+            Some(impact) => {
+                let raw = ClassicSimilarity::tf(impact.freq as f32) * self.value;
+                if self.norms.is_some() {
+                    raw * ClassicSimilarity::decode_norm_value((impact.norm & 0xFF) as usize)
+                } else {
+                    raw
+                }
+            }
+            None => ::std::f32::INFINITY,
+        }
+    }
+}
+
+struct ClassicSimWeight {
+    field: String,
+    idf: Explanation,
+    boost: f32,
+    query_norm: f32,
+    // idf * boost * query_norm
+    query_weight: f32,
+    // query_weight * idf
+    value: f32,
+}
+
+impl ClassicSimWeight {
+    fn do_normalize(&mut self, query_norm: f32, boost: f32) {
+        self.boost = boost;
+        self.query_norm = query_norm;
+        self.query_weight = query_norm * boost * self.idf.value();
+        self.value = self.query_weight * self.idf.value();
+    }
+
+    fn explain_query(&self) -> Explanation {
+        let mut subs = vec![];
+        if (self.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                self.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(self.idf.clone());
+        subs.push(Explanation::new(
+            true,
+            self.query_norm,
+            "queryNorm".to_string(),
+            vec![],
+        ));
+        Explanation::new(
+            true,
+            self.query_weight,
+            "queryWeight, product of:".to_string(),
+            subs,
+        )
+    }
+
+    fn explain_field(
+        &self,
+        doc: DocId,
+        freq: Explanation,
+        norms: Option<Box<dyn NumericDocValues>>,
+    ) -> Result<Explanation> {
+        let freq_value = freq.value();
+        let tf = Explanation::new(
+            true,
+            ClassicSimilarity::tf(freq_value),
+            format!("tf(freq={}), with freq of:", freq_value),
+            vec![freq],
+        );
+        let norm = match norms {
+            Some(norms) => ClassicSimilarity::decode_norm_value((norms.get(doc)? & 0xFF) as usize),
+            None => 1.0,
+        };
+        let field_norm = Explanation::new(true, norm, format!("fieldNorm(doc={})", doc), vec![]);
+        Ok(Explanation::new(
+            true,
+            tf.value() * self.idf.value() * norm,
+            format!("fieldWeight in {}, product of:", doc),
+            vec![tf, self.idf.clone(), field_norm],
+        ))
+    }
+}
+
+impl<C: Codec> SimWeight<C> for ClassicSimWeight {
+    fn get_value_for_normalization(&self) -> f32 {
+        // the query weight without the query norm
+        let weight = self.idf.value() * self.boost;
+        weight * weight
+    }
+
+    fn normalize(&mut self, query_norm: f32, boost: f32) {
+        self.do_normalize(query_norm, boost)
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        Ok(Box::new(ClassicSimScorer {
+            value: self.value,
+            norms: reader.norm_values(&self.field)?,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let query = self.explain_query();
+        let freq_string = freq.to_string(0);
+        let field = self.explain_field(doc, freq, reader.norm_values(&self.field)?)?;
+        if (query.value() - 1.0).abs() <= ::std::f32::EPSILON {
+            return Ok(field);
+        }
+        Ok(Explanation::new(
+            true,
+            query.value() * field.value(),
+            format!("score(doc={},freq={}), product of:", doc, freq_string),
+            vec![query, field],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::tests::MockLeafReader;
+
+    #[test]
+    fn test_classic_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 32, 31, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 3, -1)];
+        let sim = ClassicSimilarity;
+        let mut sim_weight: Box<dyn SimWeight<TestCodec>> =
+            sim.compute_weight(&collection_stats, &term_stats, None, 2.0);
+
+        let idf = 1.0 + 8f32.ln();
+        assert!((sim_weight.get_value_for_normalization() - 4.0 * idf * idf).abs() < 1e-4);
+        let query_norm = Similarity::<TestCodec>::query_norm(
+            &sim,
+            sim_weight.get_value_for_normalization(),
+            None,
+        );
+        assert!((query_norm - 1.0 / (2.0 * idf)).abs() < 1e-6);
+        sim_weight.normalize(query_norm, 2.0);
+
+        let leaf_reader = MockLeafReader::new(1);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+        let norm = ClassicSimilarity::decode_norm_value(ClassicSimilarity::encode_norm_value(
+            1.0, 120,
+        ) as usize);
+        let score = sim_scorer.score(1, 4.0).unwrap();
+        // the query weight is normalized to 1
+        assert!((score - 2.0 * idf * norm).abs() < 1e-5);
+        // shorter documents score higher
+        assert!(score > sim_scorer.score(2, 4.0).unwrap());
+
+        let freq = Explanation::new(true, 4.0, "termFreq=4.0".to_string(), vec![]);
+        let explanation = sim_weight.explain(&leaf_reader, 1, freq).unwrap();
+        assert!((explanation.value() - score).abs() < 1e-5);
+        // a query weight of 1 is left out
+        assert_eq!(explanation.description(), "fieldWeight in 1, product of:");
+        assert_eq!(explanation.details().len(), 3);
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Divergence from randomness (DFR) framework. Introduced in Gianni Amati and
+//! Cornelis Joost Van Rijsbergen. 2002. Probabilistic models of information
+//! retrieval based on measuring the divergence from randomness. ACM Trans.
+//! Inf. Syst. 20, 4 (October 2002), 357-389.
+
+use std::f64::consts::{E, PI};
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::search::explanation::Explanation;
+use core::search::similarity::similarity_base::log2;
+use core::search::similarity::{
+    compute_base_weight, BasicStats, Normalization, SimWeight, Similarity, SimilarityBase,
+};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+/// The basic model of information content of a DFR similarity, which
+/// measures how much the term frequency diverges from a random
+/// distribution.
+pub trait BasicModel: fmt::Display + Send + Sync {
+    /// Returns the informative content, given the normalized term frequency.
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32;
+
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            format!("basic model {}, computed from:", self),
+            vec![
+                Explanation::new(true, tfn, "tfn".to_string(), vec![]),
+                Explanation::new(
+                    true,
+                    stats.number_of_documents as f32,
+                    "numberOfDocuments".to_string(),
+                    vec![],
+                ),
+                Explanation::new(
+                    true,
+                    stats.total_term_freq as f32,
+                    "totalTermFreq".to_string(),
+                    vec![],
+                ),
+            ],
+        )
+    }
+}
+
+/// Limiting form of the Bose-Einstein model.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelBE;
+
+impl BasicModelBE {
+    fn f(n: f64, m: f64) -> f64 {
+        (m + 0.5) * log2(n / m) + (n - m) * log2(n)
+    }
+}
+
+impl BasicModel for BasicModelBE {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let big_f = stats.total_term_freq as f64 + 1.0 + tfn;
+        // approximation only holds true when F << N, so we use N += F
+        let n = big_f + stats.number_of_documents as f64;
+        (-log2((n - 1.0) * E) + Self::f(n + big_f - 1.0, n + big_f - tfn - 2.0)
+            - Self::f(big_f, big_f - tfn)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelBE {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Be")
+    }
+}
+
+/// Implements the approximation of the binomial model with the divergence
+/// for DFR.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelD;
+
+impl BasicModel for BasicModelD {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        // we have to ensure phi is always < 1 for tiny TTF values, otherwise
+        // nphi can go negative, so we use F += tfn
+        let big_f = stats.total_term_freq as f64 + 1.0 + tfn;
+        let phi = tfn / big_f;
+        let nphi = 1.0 - phi;
+        let p = 1.0 / (stats.number_of_documents as f64 + 1.0);
+        let d = phi * log2(phi / p) + nphi * log2(nphi / (1.0 - p));
+        (d * big_f + 0.5 * log2(1.0 + 2.0 * PI * tfn * nphi)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelD {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "D")
+    }
+}
+
+/// Geometric as limiting form of the Bose-Einstein model.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelG;
+
+impl BasicModel for BasicModelG {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        // just like in BE, approximation only holds true when F << N, so we
+        // use lambda = F / (N + F)
+        let big_f = stats.total_term_freq as f64 + 1.0;
+        let n = stats.number_of_documents as f64;
+        let lambda = big_f / (n + big_f);
+        (log2(lambda + 1.0) + tfn * log2((1.0 + lambda) / lambda)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelG {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "G")
+    }
+}
+
+/// An approximation of the I(n<sub>e</sub>) model, based on the total term
+/// frequency.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelIF;
+
+impl BasicModel for BasicModelIF {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let n = stats.number_of_documents as f64;
+        let big_f = stats.total_term_freq as f64;
+        tfn * log2(1.0 + (n + 1.0) / (big_f + 0.5)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelIF {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "I(F)")
+    }
+}
+
+/// The basic tf-idf model of randomness, based on the document frequency.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelIn;
+
+impl BasicModel for BasicModelIn {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let n = stats.number_of_documents as f64;
+        let doc_freq = stats.doc_freq as f64;
+        tfn * log2((n + 1.0) / (doc_freq + 0.5)) as f32
+    }
+
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            format!("basic model {}, computed from:", self),
+            vec![
+                Explanation::new(true, tfn, "tfn".to_string(), vec![]),
+                Explanation::new(
+                    true,
+                    stats.number_of_documents as f32,
+                    "numberOfDocuments".to_string(),
+                    vec![],
+                ),
+                Explanation::new(true, stats.doc_freq as f32, "docFreq".to_string(), vec![]),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for BasicModelIn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "I(n)")
+    }
+}
+
+/// Tf-idf model of randomness, based on a mixture of Poisson and inverse
+/// document frequency.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelIne;
+
+impl BasicModel for BasicModelIne {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let n = stats.number_of_documents as f64;
+        let big_f = stats.total_term_freq as f64;
+        let ne = n * (1.0 - ((n - 1.0) / n).powf(big_f));
+        tfn * log2((n + 1.0) / (ne + 0.5)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelIne {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "I(ne)")
+    }
+}
+
+/// Implements the Poisson approximation for the binomial model for DFR.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BasicModelP;
+
+impl BasicModel for BasicModelP {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let lambda =
+            (stats.total_term_freq as f64 + 1.0) / (stats.number_of_documents as f64 + 1.0);
+        (tfn * log2(tfn / lambda)
+            + (lambda + 1.0 / (12.0 * tfn) - tfn) * log2(E)
+            + 0.5 * log2(2.0 * PI * tfn)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelP {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "P")
+    }
+}
+
+/// The first normalization of the informative content of a DFR similarity,
+/// which accounts for the information gain of the term in the document.
+pub trait AfterEffect: fmt::Display + Send + Sync {
+    /// Returns the aftereffect score, given the normalized term frequency.
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32;
+
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation;
+}
+
+/// Model of the information gain based on the ratio of two Bernoulli
+/// processes.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AfterEffectB;
+
+impl AfterEffect for AfterEffectB {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let big_f = stats.total_term_freq as f32 + 1.0;
+        let n = stats.doc_freq as f32 + 1.0;
+        (big_f + 1.0) / (n * (tfn + 1.0))
+    }
+
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            "after effect B, computed from:".to_string(),
+            vec![
+                Explanation::new(true, tfn, "tfn".to_string(), vec![]),
+                Explanation::new(
+                    true,
+                    stats.total_term_freq as f32,
+                    "totalTermFreq".to_string(),
+                    vec![],
+                ),
+                Explanation::new(true, stats.doc_freq as f32, "docFreq".to_string(), vec![]),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for AfterEffectB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")
+    }
+}
+
+/// Model of the information gain based on Laplace's law of succession.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AfterEffectL;
+
+impl AfterEffect for AfterEffectL {
+    fn score(&self, _stats: &BasicStats, tfn: f32) -> f32 {
+        1.0 / (tfn + 1.0)
+    }
+
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            "after effect L, computed from:".to_string(),
+            vec![Explanation::new(true, tfn, "tfn".to_string(), vec![])],
+        )
+    }
+}
+
+impl fmt::Display for AfterEffectL {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "L")
+    }
+}
+
+/// Disables the first normalization.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoAfterEffect;
+
+impl AfterEffect for NoAfterEffect {
+    fn score(&self, _stats: &BasicStats, _tfn: f32) -> f32 {
+        1.0
+    }
+
+    fn explain(&self, _stats: &BasicStats, _tfn: f32) -> Explanation {
+        Explanation::new(true, 1.0, "no aftereffect".to_string(), vec![])
+    }
+}
+
+impl fmt::Display for NoAfterEffect {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+/// Implements the divergence from randomness (DFR) framework.
+///
+/// The score of a term is the product of three components: the basic model
+/// of information content, the first normalization of that content (the
+/// aftereffect), and the normalization of the term frequency by the document
+/// length, e.g. `DFRSimilarity::new(BasicModelIn, AfterEffectL,
+/// NormalizationH2::default())` is the I(n)L2 model.
+#[derive(Clone)]
+pub struct DFRSimilarity {
+    basic_model: Arc<dyn BasicModel>,
+    after_effect: Arc<dyn AfterEffect>,
+    normalization: Arc<dyn Normalization>,
+}
+
+impl DFRSimilarity {
+    pub fn new<B, A, N>(basic_model: B, after_effect: A, normalization: N) -> DFRSimilarity
+    where
+        B: BasicModel + 'static,
+        A: AfterEffect + 'static,
+        N: Normalization + 'static,
+    {
+        DFRSimilarity {
+            basic_model: Arc::new(basic_model),
+            after_effect: Arc::new(after_effect),
+            normalization: Arc::new(normalization),
+        }
+    }
+}
+
+impl SimilarityBase for DFRSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let tfn = self.normalization.tfn(stats, freq, doc_len);
+        stats.boost * self.basic_model.score(stats, tfn) * self.after_effect.score(stats, tfn)
+    }
+
+    fn explain_score(
+        &self,
+        subs: &mut Vec<Explanation>,
+        stats: &BasicStats,
+        _doc: DocId,
+        freq: f32,
+        doc_len: f32,
+    ) {
+        if (stats.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        let normalization = self.normalization.explain(stats, freq, doc_len);
+        let tfn = normalization.value();
+        subs.push(normalization);
+        subs.push(self.basic_model.explain(stats, tfn));
+        subs.push(self.after_effect.explain(stats, tfn));
+    }
+}
+
+impl<C: Codec> Similarity<C> for DFRSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        compute_base_weight(self, collection_stats, term_stats, boost)
+    }
+}
+
+impl fmt::Display for DFRSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DFR {}{}{}",
+            self.basic_model, self.after_effect, self.normalization
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::tests::MockLeafReader;
+    use core::search::similarity::{NoNormalization, NormalizationH2};
+
+    #[test]
+    fn test_dfr_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 32, 32, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 4, 10)];
+        let stats = BasicStats::new(&collection_stats, &term_stats[0], 1.0);
+        assert!((stats.avg_field_length - 3.75).abs() < ::std::f32::EPSILON);
+
+        let sim = DFRSimilarity::new(BasicModelIn, AfterEffectL, NormalizationH2::default());
+        assert_eq!(sim.to_string(), "DFR I(n)L2");
+        let expected = 3.0 * (33f64 / 4.5).log2() as f32 / 4.0;
+        let sim = DFRSimilarity::new(BasicModelIn, AfterEffectL, NoNormalization);
+        assert!((SimilarityBase::score(&sim, &stats, 3.0, 10.0) - expected).abs() < 1e-6);
+
+        let models: Vec<Box<dyn BasicModel>> = vec![
+            Box::new(BasicModelBE),
+            Box::new(BasicModelD),
+            Box::new(BasicModelG),
+            Box::new(BasicModelIF),
+            Box::new(BasicModelIn),
+            Box::new(BasicModelIne),
+            Box::new(BasicModelP),
+        ];
+        for model in &models {
+            // the informative content grows with the frequency
+            let low = model.score(&stats, 1.0);
+            let high = model.score(&stats, 5.0);
+            assert!(low.is_finite() && high > low, "{}", model);
+        }
+
+        let sim = DFRSimilarity::new(BasicModelG, AfterEffectB, NormalizationH2::default());
+        let sim_weight: Box<dyn SimWeight<TestCodec>> =
+            sim.compute_weight(&collection_stats, &term_stats, None, 1.0);
+        let leaf_reader = MockLeafReader::new(1);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+        // the shorter document scores higher
+        let score = sim_scorer.score(1, 2.0).unwrap();
+        assert!(score > sim_scorer.score(2, 2.0).unwrap());
+
+        let freq = Explanation::new(true, 2.0, "termFreq=2.0".to_string(), vec![]);
+        let explanation = sim_weight.explain(&leaf_reader, 1, freq).unwrap();
+        assert!((explanation.value() - score).abs() < ::std::f32::EPSILON);
+        assert_eq!(explanation.details().len(), 3);
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Information based models. Introduced in Stéphane Clinchant and Eric
+//! Gaussier. 2010. Information-based models for ad hoc IR. In Proceeding of
+//! the 33rd international ACM SIGIR conference on Research and development in
+//! information retrieval (SIGIR '10). ACM, New York, NY, USA, 234-241.
+
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::search::explanation::Explanation;
+use core::search::similarity::{
+    compute_base_weight, BasicStats, Normalization, SimWeight, Similarity, SimilarityBase,
+};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+/// The probabilistic distribution of an information based model.
+pub trait Distribution: fmt::Display + Send + Sync {
+    /// Computes the score, given the normalized term frequency and `lambda`.
+    fn score(&self, stats: &BasicStats, tfn: f32, lambda: f32) -> f32;
+
+    fn explain(&self, stats: &BasicStats, tfn: f32, lambda: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn, lambda),
+            format!("distribution {}", self),
+            vec![],
+        )
+    }
+}
+
+/// Log-logistic distribution: `-log(lambda / (tfn + lambda))`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DistributionLL;
+
+impl Distribution for DistributionLL {
+    fn score(&self, _stats: &BasicStats, tfn: f32, lambda: f32) -> f32 {
+        -(f64::from(lambda) / f64::from(tfn + lambda)).ln() as f32
+    }
+}
+
+impl fmt::Display for DistributionLL {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LL")
+    }
+}
+
+/// The smoothed power-law (SPL) distribution:
+/// `-log((lambda ^ (tfn / (tfn + 1)) - lambda) / (1 - lambda))`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DistributionSPL;
+
+impl Distribution for DistributionSPL {
+    fn score(&self, _stats: &BasicStats, tfn: f32, lambda: f32) -> f32 {
+        // the distribution is undefined for lambda = 1
+        let lambda = if (lambda - 1.0).abs() < ::std::f32::EPSILON {
+            0.99
+        } else {
+            f64::from(lambda)
+        };
+        let tfn = f64::from(tfn);
+        -((lambda.powf(tfn / (tfn + 1.0)) - lambda) / (1.0 - lambda)).ln() as f32
+    }
+}
+
+impl fmt::Display for DistributionSPL {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SPL")
+    }
+}
+
+/// The `lambda` parameter of the distribution of an information based model.
+pub trait Lambda: fmt::Display + Send + Sync {
+    fn lambda(&self, stats: &BasicStats) -> f32;
+
+    fn explain(&self, stats: &BasicStats) -> Explanation;
+}
+
+/// Computes `lambda` as `(docFreq + 1) / (numberOfDocuments + 1)`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LambdaDF;
+
+impl Lambda for LambdaDF {
+    fn lambda(&self, stats: &BasicStats) -> f32 {
+        (stats.doc_freq as f32 + 1.0) / (stats.number_of_documents as f32 + 1.0)
+    }
+
+    fn explain(&self, stats: &BasicStats) -> Explanation {
+        Explanation::new(
+            true,
+            self.lambda(stats),
+            "lambda, computed as (n + 1) / (N + 1) from:".to_string(),
+            vec![
+                Explanation::new(
+                    true,
+                    stats.doc_freq as f32,
+                    "n, docFreq".to_string(),
+                    vec![],
+                ),
+                Explanation::new(
+                    true,
+                    stats.number_of_documents as f32,
+                    "N, numberOfDocuments".to_string(),
+                    vec![],
+                ),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for LambdaDF {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "D")
+    }
+}
+
+/// Computes `lambda` as `(totalTermFreq + 1) / (numberOfDocuments + 1)`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LambdaTTF;
+
+impl Lambda for LambdaTTF {
+    fn lambda(&self, stats: &BasicStats) -> f32 {
+        (stats.total_term_freq as f32 + 1.0) / (stats.number_of_documents as f32 + 1.0)
+    }
+
+    fn explain(&self, stats: &BasicStats) -> Explanation {
+        Explanation::new(
+            true,
+            self.lambda(stats),
+            "lambda, computed as (F + 1) / (N + 1) from:".to_string(),
+            vec![
+                Explanation::new(
+                    true,
+                    stats.total_term_freq as f32,
+                    "F, totalTermFreq".to_string(),
+                    vec![],
+                ),
+                Explanation::new(
+                    true,
+                    stats.number_of_documents as f32,
+                    "N, numberOfDocuments".to_string(),
+                    vec![],
+                ),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for LambdaTTF {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "L")
+    }
+}
+
+/// Provides a framework for the family of information-based models.
+///
+/// The score of a term is the information content `-log(Prob(X >= tfn))` of
+/// its normalized frequency `tfn` under a heavy tailed `Distribution`, whose
+/// parameter is computed by a `Lambda`, e.g. `IBSimilarity::new(DistributionLL,
+/// LambdaDF, NormalizationH2::default())`.
+#[derive(Clone)]
+pub struct IBSimilarity {
+    distribution: Arc<dyn Distribution>,
+    lambda: Arc<dyn Lambda>,
+    normalization: Arc<dyn Normalization>,
+}
+
+impl IBSimilarity {
+    pub fn new<D, L, N>(distribution: D, lambda: L, normalization: N) -> IBSimilarity
+    where
+        D: Distribution + 'static,
+        L: Lambda + 'static,
+        N: Normalization + 'static,
+    {
+        IBSimilarity {
+            distribution: Arc::new(distribution),
+            lambda: Arc::new(lambda),
+            normalization: Arc::new(normalization),
+        }
+    }
+}
+
+impl SimilarityBase for IBSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        stats.boost
+            * self.distribution.score(
+                stats,
+                self.normalization.tfn(stats, freq, doc_len),
+                self.lambda.lambda(stats),
+            )
+    }
+
+    fn explain_score(
+        &self,
+        subs: &mut Vec<Explanation>,
+        stats: &BasicStats,
+        _doc: DocId,
+        freq: f32,
+        doc_len: f32,
+    ) {
+        if (stats.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        let normalization = self.normalization.explain(stats, freq, doc_len);
+        let lambda = self.lambda.explain(stats);
+        let distribution = self
+            .distribution
+            .explain(stats, normalization.value(), lambda.value());
+        subs.push(normalization);
+        subs.push(lambda);
+        subs.push(distribution);
+    }
+}
+
+impl<C: Codec> Similarity<C> for IBSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        compute_base_weight(self, collection_stats, term_stats, boost)
+    }
+}
+
+impl fmt::Display for IBSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IB {}-{}{}",
+            self.distribution, self.lambda, self.normalization
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::similarity::{NoNormalization, NormalizationH2};
+
+    #[test]
+    fn test_ib_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 32, 31, 120, -1);
+        let term_stats = TermStatistics::new(Vec::new(), 4, 10);
+        let stats = BasicStats::new(&collection_stats, &term_stats, 2.0);
+
+        let sim = IBSimilarity::new(DistributionLL, LambdaDF, NormalizationH2::default());
+        assert_eq!(sim.to_string(), "IB LL-D2");
+
+        // lambda = 5 / 32, tfn = 3
+        let sim = IBSimilarity::new(DistributionLL, LambdaDF, NoNormalization);
+        let expected = 2.0 * -((5f64 / 32.0) / (3.0 + 5.0 / 32.0)).ln() as f32;
+        assert!((SimilarityBase::score(&sim, &stats, 3.0, 4.0) - expected).abs() < 1e-6);
+        let explanation = SimilarityBase::explain(
+            &sim,
+            &stats,
+            1,
+            Explanation::new(true, 3.0, "freq".to_string(), vec![]),
+            4.0,
+        );
+        assert!((explanation.value() - expected).abs() < 1e-6);
+        assert_eq!(explanation.details().len(), 4);
+
+        let sim = IBSimilarity::new(DistributionSPL, LambdaTTF, NormalizationH2::default());
+        let score = SimilarityBase::score(&sim, &stats, 3.0, 4.0);
+        assert!(score > SimilarityBase::score(&sim, &stats, 1.0, 4.0));
+        assert!(score > SimilarityBase::score(&sim, &stats, 3.0, 10.0));
+    }
+}
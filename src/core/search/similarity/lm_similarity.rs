@@ -0,0 +1,246 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Language model based similarities. Introduced in Chengxiang Zhai and John
+//! Lafferty. 2001. A study of smoothing methods for language models applied
+//! to Ad Hoc information retrieval. In Proceedings of the 24th annual
+//! international ACM SIGIR conference on Research and development in
+//! information retrieval (SIGIR '01). ACM, New York, NY, USA, 334-342.
+//!
+//! The probability of a term in the collection is estimated as
+//! `(totalTermFreq + 1) / (numberOfFieldTokens + 1)`, see
+//! `BasicStats::collection_probability`.
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::search::explanation::Explanation;
+use core::search::similarity::{
+    compute_base_weight, BasicStats, SimWeight, Similarity, SimilarityBase,
+};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+pub const DEFAULT_LM_DIRICHLET_MU: f32 = 2000.0;
+
+/// Bayesian smoothing using Dirichlet priors.
+///
+/// The formula may return negative scores for the terms of a document that
+/// occur less often than expected from the collection, they are clamped to
+/// 0.
+#[derive(Debug, Copy, Clone)]
+pub struct LMDirichletSimilarity {
+    mu: f32,
+}
+
+impl Default for LMDirichletSimilarity {
+    fn default() -> Self {
+        LMDirichletSimilarity::new(DEFAULT_LM_DIRICHLET_MU)
+    }
+}
+
+impl LMDirichletSimilarity {
+    pub fn new(mu: f32) -> LMDirichletSimilarity {
+        LMDirichletSimilarity { mu }
+    }
+}
+
+impl SimilarityBase for LMDirichletSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let mu = f64::from(self.mu);
+        let score = stats.boost
+            * ((1.0 + f64::from(freq) / (mu * f64::from(stats.collection_probability))).ln()
+                + (mu / (f64::from(doc_len) + mu)).ln()) as f32;
+        score.max(0.0)
+    }
+
+    fn explain_score(
+        &self,
+        subs: &mut Vec<Explanation>,
+        stats: &BasicStats,
+        _doc: DocId,
+        freq: f32,
+        doc_len: f32,
+    ) {
+        if (stats.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(Explanation::new(true, self.mu, "mu".to_string(), vec![]));
+        let weight = (1.0 + freq / (self.mu * stats.collection_probability)).ln();
+        subs.push(Explanation::new(
+            true,
+            weight,
+            "term weight, computed as log(1 + freq / (mu * collectionProbability)) from:"
+                .to_string(),
+            vec![
+                Explanation::new(true, freq, "freq".to_string(), vec![]),
+                Explanation::new(
+                    true,
+                    stats.collection_probability,
+                    "collectionProbability".to_string(),
+                    vec![],
+                ),
+            ],
+        ));
+        subs.push(Explanation::new(
+            true,
+            (self.mu / (doc_len + self.mu)).ln(),
+            "document norm, computed as log(mu / (docLen + mu)) from:".to_string(),
+            vec![Explanation::new(
+                true,
+                doc_len,
+                "docLen".to_string(),
+                vec![],
+            )],
+        ));
+    }
+}
+
+impl<C: Codec> Similarity<C> for LMDirichletSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        compute_base_weight(self, collection_stats, term_stats, boost)
+    }
+}
+
+impl fmt::Display for LMDirichletSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dirichlet({})", self.mu)
+    }
+}
+
+/// Language model based on the Jelinek-Mercer smoothing method, which
+/// mixes the probability of the term in the document with its probability
+/// in the collection: `lambda` is the weight of the latter, about 0.1 works
+/// well for title queries and 0.7 for long queries.
+#[derive(Debug, Copy, Clone)]
+pub struct LMJelinekMercerSimilarity {
+    lambda: f32,
+}
+
+impl LMJelinekMercerSimilarity {
+    pub fn new(lambda: f32) -> LMJelinekMercerSimilarity {
+        debug_assert!(lambda > 0.0 && lambda <= 1.0);
+        LMJelinekMercerSimilarity { lambda }
+    }
+}
+
+impl SimilarityBase for LMJelinekMercerSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let lambda = f64::from(self.lambda);
+        stats.boost
+            * (1.0
+                + ((1.0 - lambda) * f64::from(freq) / f64::from(doc_len))
+                    / (lambda * f64::from(stats.collection_probability)))
+            .ln() as f32
+    }
+
+    fn explain_score(
+        &self,
+        subs: &mut Vec<Explanation>,
+        stats: &BasicStats,
+        _doc: DocId,
+        freq: f32,
+        doc_len: f32,
+    ) {
+        if (stats.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(Explanation::new(
+            true,
+            self.lambda,
+            "lambda".to_string(),
+            vec![],
+        ));
+        subs.push(Explanation::new(true, freq, "freq".to_string(), vec![]));
+        subs.push(Explanation::new(
+            true,
+            doc_len,
+            "docLen".to_string(),
+            vec![],
+        ));
+        subs.push(Explanation::new(
+            true,
+            stats.collection_probability,
+            "collectionProbability".to_string(),
+            vec![],
+        ));
+    }
+}
+
+impl<C: Codec> Similarity<C> for LMJelinekMercerSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        compute_base_weight(self, collection_stats, term_stats, boost)
+    }
+}
+
+impl fmt::Display for LMJelinekMercerSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Jelinek-Mercer({})", self.lambda)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lm_similarities() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 100, 100, 999, -1);
+        let term_stats = TermStatistics::new(Vec::new(), 10, 49);
+        let stats = BasicStats::new(&collection_stats, &term_stats, 1.0);
+        assert!((stats.collection_probability - 0.05).abs() < ::std::f32::EPSILON);
+
+        let sim = LMDirichletSimilarity::new(100.0);
+        let expected = ((1.0 + 2.0 / 5.0f64).ln() + (100.0 / 110.0f64).ln()) as f32;
+        assert!((sim.score(&stats, 2.0, 10.0) - expected).abs() < 1e-6);
+        // a term less frequent than expected doesn't lower the score
+        assert_eq!(sim.score(&stats, 1.0, 10_000.0), 0.0);
+
+        let sim = LMJelinekMercerSimilarity::new(0.5);
+        let expected = (1.0 + (0.5 * 2.0 / 10.0) / (0.5 * 0.05f64)).ln() as f32;
+        assert!((sim.score(&stats, 2.0, 10.0) - expected).abs() < 1e-6);
+        let explanation = sim.explain(
+            &stats,
+            1,
+            Explanation::new(true, 2.0, "freq".to_string(), vec![]),
+            10.0,
+        );
+        assert!((explanation.value() - expected).abs() < 1e-6);
+        assert_eq!(
+            explanation.description(),
+            "score(Jelinek-Mercer(0.5), doc=1, freq=2), computed from:"
+        );
+    }
+}
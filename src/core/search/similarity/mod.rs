@@ -15,14 +15,48 @@ mod bm25_similarity;
 
 pub use self::bm25_similarity::*;
 
+mod boolean_similarity;
+
+pub use self::boolean_similarity::*;
+
+mod classic_similarity;
+
+pub use self::classic_similarity::*;
+
+mod similarity_base;
+
+pub use self::similarity_base::*;
+
+mod normalization;
+
+pub use self::normalization::*;
+
+mod dfr_similarity;
+
+pub use self::dfr_similarity::*;
+
+mod ib_similarity;
+
+pub use self::ib_similarity::*;
+
+mod lm_similarity;
+
+pub use self::lm_similarity::*;
+
+mod per_field_similarity_wrapper;
+
+pub use self::per_field_similarity_wrapper::*;
+
 use core::util::{DocId, KeyedContext};
 
-use core::codec::Codec;
+use core::codec::field_infos::FieldInvertState;
+use core::codec::{Codec, Impact};
 use core::index::reader::SearchLeafReader;
 use core::search::explanation::Explanation;
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use error::Result;
 use std::fmt::Display;
+use std::sync::Arc;
 
 /// Similarity defines the components of Lucene scoring.
 ///
@@ -31,7 +65,7 @@ use std::fmt::Display;
 /// This is a low-level API, you should only extend this API if you want to implement
 /// an information retrieval *model*.  If you are instead looking for a convenient way
 /// to alter Lucene's scoring, consider extending a higher-level implementation
-/// such as `TFIDFSimilarity`, which implements the vector space model with this API, or
+/// such as `ClassicSimilarity`, which implements the vector space model with this API, or
 /// just tweaking the default implementation: `BM25Similarity`.
 ///
 /// Similarity determines how Lucene weights terms, and Lucene interacts with
@@ -87,7 +121,7 @@ use std::fmt::Display;
 /// consult the Similarity's DocScorer for an explanation of how it computed its score. The query
 /// passes in a the document id and an explanation of how the frequency was computed.
 
-pub trait Similarity<C: Codec>: Display {
+pub trait Similarity<C: Codec>: Display + Send + Sync {
     /// Compute any collection-level weight (e.g. IDF, average document length, etc)
     /// needed for scoring a query.
     fn compute_weight(
@@ -106,13 +140,61 @@ pub trait Similarity<C: Codec>: Display {
     /// queries comparable.
     /// <p>
     /// By default this is disabled (returns 1), but some
-    /// implementations such as `TFIDFSimilarity` override this.
+    /// implementations such as `ClassicSimilarity` override this.
     ///
     /// @param valueForNormalization the sum of the term normalization values
     /// @return a normalization factor for query weights
     fn query_norm(&self, _value_for_normalization: f32, _context: Option<&KeyedContext>) -> f32 {
         1.0f32
     }
+
+    /// Computes the normalization value for a field, given the accumulated
+    /// state of term processing for this field (see `FieldInvertState`).
+    ///
+    /// Called at index time by the indexing chain of `IndexWriter` with the
+    /// similarity of `IndexWriterConfig`. The default encodes the boost and
+    /// the field length with `SmallFloat` into a single byte, the way
+    /// `BM25Similarity`, `ClassicSimilarity` and the `SimilarityBase` models
+    /// decode it.
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        BM25Similarity::compute_norm(state)
+    }
+
+    /// Returns true if `compute_norm` never encodes a field that scores
+    /// higher, all else being equal, into a smaller norm, the norms being
+    /// compared as unsigned values.
+    ///
+    /// The postings only record the most competitive norm of their blocks,
+    /// see `Impact`, for the similarities that declare it. Otherwise every doc
+    /// is assumed to have the most competitive norm, so the block max scores
+    /// of `WANDScorer` and `MaxScoreScorer` only rely on the freqs.
+    fn monotonic_norms(&self) -> bool {
+        false
+    }
+}
+
+impl<C: Codec, S: Similarity<C> + ?Sized> Similarity<C> for Arc<S> {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        (**self).compute_weight(collection_stats, term_stats, context, boost)
+    }
+
+    fn query_norm(&self, value_for_normalization: f32, context: Option<&KeyedContext>) -> f32 {
+        (**self).query_norm(value_for_normalization, context)
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        (**self).compute_norm(state)
+    }
+
+    fn monotonic_norms(&self) -> bool {
+        (**self).monotonic_norms()
+    }
 }
 
 /// Per-field similarity provider.
@@ -140,8 +222,32 @@ pub trait SimScorer: Send {
     /// Computes the amount of a sloppy phrase match, based on an edit distance.
     fn compute_slop_factor(&self, distance: i32) -> f32;
 
-    // Calculate a scoring factor based on the data in the payload.
-    // fn compute_payload_factor(&self, doc: DocId, start: i32, end: i32, payload: &Payload);
+    /// Returns an upper bound of the score of the docs whose freq and norm are
+    /// bounded by `impact`, or of any doc if it is `None`.
+    ///
+    /// The default bound is infinite, so `WANDScorer` and `MaxScoreScorer`
+    /// can't skip any doc of the term. `BM25Similarity`, `ClassicSimilarity`
+    /// and `BooleanSimilarity` implement it, the `SimilarityBase` models don't
+    /// as their scores don't grow monotonically with the freq and norm.
+    fn max_score(&self, _impact: Option<&Impact>) -> f32 {
+        ::std::f32::INFINITY
+    }
+
+    /// Calculate a scoring factor based on the data in the payload of a
+    /// position in `[start, end)` of `doc`, used by `PayloadScoreQuery` with
+    /// `PayloadDecoder::Similarity`.
+    ///
+    /// The default ignores the payload and returns 1.0, a similarity that
+    /// scores payloads must know how they are encoded.
+    fn compute_payload_factor(
+        &self,
+        _doc: DocId,
+        _start: i32,
+        _end: i32,
+        _payload: &[u8],
+    ) -> f32 {
+        1.0
+    }
 }
 
 /// Stores the weight for a query across the indexed collection.
@@ -0,0 +1,223 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::search::explanation::Explanation;
+use core::search::similarity::similarity_base::log2;
+use core::search::similarity::BasicStats;
+
+/// The term frequency normalizations of `DFRSimilarity` and `IBSimilarity`,
+/// which make the frequencies of documents of different lengths comparable.
+pub trait Normalization: fmt::Display + Send + Sync {
+    /// Returns the normalized term frequency.
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32;
+
+    fn explain(&self, stats: &BasicStats, tf: f32, len: f32) -> Explanation;
+}
+
+fn explain_tfn(
+    name: &str,
+    value: f32,
+    parameter: Option<(&str, f32)>,
+    stats: &BasicStats,
+    tf: f32,
+    len: f32,
+) -> Explanation {
+    let mut subs = vec![];
+    if let Some((name, value)) = parameter {
+        subs.push(Explanation::new(true, value, name.to_string(), vec![]));
+    }
+    subs.push(Explanation::new(true, tf, "tf".to_string(), vec![]));
+    subs.push(Explanation::new(
+        true,
+        stats.avg_field_length,
+        "avgFieldLength".to_string(),
+        vec![],
+    ));
+    subs.push(Explanation::new(true, len, "len".to_string(), vec![]));
+    Explanation::new(true, value, format!("{}, computed from:", name), subs)
+}
+
+/// Normalization model that assumes a uniform distribution of the term
+/// frequency: `tf * c * avgFieldLength / len`.
+#[derive(Debug, Copy, Clone)]
+pub struct NormalizationH1 {
+    c: f32,
+}
+
+impl NormalizationH1 {
+    pub fn new(c: f32) -> NormalizationH1 {
+        NormalizationH1 { c }
+    }
+}
+
+impl Default for NormalizationH1 {
+    fn default() -> Self {
+        NormalizationH1::new(1.0)
+    }
+}
+
+impl Normalization for NormalizationH1 {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        tf * self.c * (stats.avg_field_length / len)
+    }
+
+    fn explain(&self, stats: &BasicStats, tf: f32, len: f32) -> Explanation {
+        let tfn = self.tfn(stats, tf, len);
+        explain_tfn("NormalizationH1", tfn, Some(("c", self.c)), stats, tf, len)
+    }
+}
+
+impl fmt::Display for NormalizationH1 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "1")
+    }
+}
+
+/// Normalization model in which the term frequency is inversely related to
+/// the length: `tf * log2(1 + c * avgFieldLength / len)`.
+#[derive(Debug, Copy, Clone)]
+pub struct NormalizationH2 {
+    c: f32,
+}
+
+impl NormalizationH2 {
+    pub fn new(c: f32) -> NormalizationH2 {
+        NormalizationH2 { c }
+    }
+}
+
+impl Default for NormalizationH2 {
+    fn default() -> Self {
+        NormalizationH2::new(1.0)
+    }
+}
+
+impl Normalization for NormalizationH2 {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        tf * log2(1.0 + f64::from(self.c * stats.avg_field_length / len)) as f32
+    }
+
+    fn explain(&self, stats: &BasicStats, tf: f32, len: f32) -> Explanation {
+        let tfn = self.tfn(stats, tf, len);
+        explain_tfn("NormalizationH2", tfn, Some(("c", self.c)), stats, tf, len)
+    }
+}
+
+impl fmt::Display for NormalizationH2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "2")
+    }
+}
+
+/// Dirichlet Priors normalization:
+/// `(tf + mu * (totalTermFreq + 1) / (numberOfFieldTokens + 1)) / (len + mu) * mu`.
+#[derive(Debug, Copy, Clone)]
+pub struct NormalizationH3 {
+    mu: f32,
+}
+
+impl NormalizationH3 {
+    pub fn new(mu: f32) -> NormalizationH3 {
+        NormalizationH3 { mu }
+    }
+}
+
+impl Default for NormalizationH3 {
+    fn default() -> Self {
+        NormalizationH3::new(800.0)
+    }
+}
+
+impl Normalization for NormalizationH3 {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        let probability =
+            (stats.total_term_freq as f32 + 1.0) / (stats.number_of_field_tokens as f32 + 1.0);
+        (tf + self.mu * probability) / (len + self.mu) * self.mu
+    }
+
+    fn explain(&self, stats: &BasicStats, tf: f32, len: f32) -> Explanation {
+        let tfn = self.tfn(stats, tf, len);
+        explain_tfn(
+            "NormalizationH3",
+            tfn,
+            Some(("mu", self.mu)),
+            stats,
+            tf,
+            len,
+        )
+    }
+}
+
+impl fmt::Display for NormalizationH3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "3({})", self.mu)
+    }
+}
+
+/// Pareto-Zipf normalization: `tf * (avgFieldLength / len) ^ z`.
+#[derive(Debug, Copy, Clone)]
+pub struct NormalizationZ {
+    z: f32,
+}
+
+impl NormalizationZ {
+    /// `z` should be in `(0, 0.5)`.
+    pub fn new(z: f32) -> NormalizationZ {
+        NormalizationZ { z }
+    }
+}
+
+impl Default for NormalizationZ {
+    fn default() -> Self {
+        NormalizationZ::new(0.3)
+    }
+}
+
+impl Normalization for NormalizationZ {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        tf * (stats.avg_field_length / len).powf(self.z)
+    }
+
+    fn explain(&self, stats: &BasicStats, tf: f32, len: f32) -> Explanation {
+        let tfn = self.tfn(stats, tf, len);
+        explain_tfn("NormalizationZ", tfn, Some(("z", self.z)), stats, tf, len)
+    }
+}
+
+impl fmt::Display for NormalizationZ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Z({})", self.z)
+    }
+}
+
+/// Keeps the raw term frequency.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoNormalization;
+
+impl Normalization for NoNormalization {
+    fn tfn(&self, _stats: &BasicStats, tf: f32, _len: f32) -> f32 {
+        tf
+    }
+
+    fn explain(&self, _stats: &BasicStats, tf: f32, _len: f32) -> Explanation {
+        Explanation::new(true, tf, "no normalization".to_string(), vec![])
+    }
+}
+
+impl fmt::Display for NoNormalization {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::field_infos::FieldInvertState;
+use core::codec::Codec;
+use core::search::similarity::{SimWeight, Similarity, SimilarityProducer};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::KeyedContext;
+
+/// Provides the ability to use a different `Similarity` for different fields.
+///
+/// Fields without a similarity of their own use the default one. The wrapper
+/// dispatches both the query time weights, on `CollectionStatistics::field`,
+/// and the index time norms, on `FieldInvertState::name`, so the same
+/// instance should be used by `IndexWriterConfig` and by the searcher, which
+/// accepts it as a `SimilarityProducer`.
+pub struct PerFieldSimilarityWrapper<C: Codec> {
+    default_similarity: Arc<dyn Similarity<C>>,
+    field_similarities: HashMap<String, Arc<dyn Similarity<C>>>,
+}
+
+impl<C: Codec> PerFieldSimilarityWrapper<C> {
+    pub fn new<S: Similarity<C> + 'static>(default_similarity: S) -> Self {
+        PerFieldSimilarityWrapper {
+            default_similarity: Arc::new(default_similarity),
+            field_similarities: HashMap::new(),
+        }
+    }
+
+    /// Uses `similarity` for the field `field`.
+    pub fn add_field<S: Similarity<C> + 'static>(&mut self, field: &str, similarity: S) {
+        self.field_similarities
+            .insert(field.to_string(), Arc::new(similarity));
+    }
+
+    /// Returns the `Similarity` used by the field `field`.
+    pub fn get(&self, field: &str) -> &Arc<dyn Similarity<C>> {
+        self.field_similarities
+            .get(field)
+            .unwrap_or(&self.default_similarity)
+    }
+}
+
+impl<C: Codec> Clone for PerFieldSimilarityWrapper<C> {
+    fn clone(&self) -> Self {
+        PerFieldSimilarityWrapper {
+            default_similarity: Arc::clone(&self.default_similarity),
+            field_similarities: self.field_similarities.clone(),
+        }
+    }
+}
+
+impl<C: Codec> Similarity<C> for PerFieldSimilarityWrapper<C> {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        self.get(&collection_stats.field).compute_weight(
+            collection_stats,
+            term_stats,
+            context,
+            boost,
+        )
+    }
+
+    fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+        self.get(&state.name).compute_norm(state)
+    }
+
+    fn monotonic_norms(&self) -> bool {
+        self.default_similarity.monotonic_norms()
+            && self
+                .field_similarities
+                .values()
+                .all(|s| s.monotonic_norms())
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for PerFieldSimilarityWrapper<C> {
+    fn create(&self, field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(Arc::clone(self.get(field)))
+    }
+}
+
+impl<C: Codec> fmt::Display for PerFieldSimilarityWrapper<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PerFieldSimilarityWrapper(default: {}",
+            self.default_similarity
+        )?;
+        let mut fields: Vec<&String> = self.field_similarities.keys().collect();
+        fields.sort();
+        for field in fields {
+            write!(f, ", {}: {}", field, self.field_similarities[field])?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::search::similarity::{BM25Similarity, BooleanSimilarity};
+
+    struct LengthSimilarity;
+
+    impl<C: Codec> Similarity<C> for LengthSimilarity {
+        fn compute_weight(
+            &self,
+            collection_stats: &CollectionStatistics,
+            term_stats: &[TermStatistics],
+            context: Option<&KeyedContext>,
+            boost: f32,
+        ) -> Box<dyn SimWeight<C>> {
+            BM25Similarity::default().compute_weight(collection_stats, term_stats, context, boost)
+        }
+
+        fn compute_norm(&self, state: &FieldInvertState) -> i64 {
+            i64::from(state.length)
+        }
+    }
+
+    impl fmt::Display for LengthSimilarity {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "LengthSimilarity")
+        }
+    }
+
+    #[test]
+    fn test_per_field_similarity() {
+        let mut sim = PerFieldSimilarityWrapper::<TestCodec>::new(BM25Similarity::default());
+        sim.add_field("title", BooleanSimilarity);
+        assert!(sim.monotonic_norms());
+        sim.add_field("body", LengthSimilarity);
+        // the lengths are longer for the fields that score lower
+        assert!(!sim.monotonic_norms());
+
+        assert_eq!(
+            SimilarityProducer::create(&sim, "title").to_string(),
+            "BooleanSimilarity"
+        );
+        assert_eq!(
+            SimilarityProducer::create(&sim, "author").to_string(),
+            "BM25Similarity(k1: 1.2, b: 0.75)"
+        );
+        assert_eq!(
+            sim.to_string(),
+            "PerFieldSimilarityWrapper(default: BM25Similarity(k1: 1.2, b: 0.75), body: \
+             LengthSimilarity, title: BooleanSimilarity)"
+        );
+
+        let mut state = FieldInvertState::new("body".to_string(), 0, 100, 0, 0, 1.0);
+        assert_eq!(sim.compute_norm(&state), 100);
+        state.name = "author".to_string();
+        assert_eq!(
+            sim.compute_norm(&state),
+            i64::from(BM25Similarity::encode_norm_value(1.0, 100))
+        );
+    }
+}
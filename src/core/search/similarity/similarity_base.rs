@@ -0,0 +1,254 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{SimScorer, SimWeight};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, SmallFloat};
+
+lazy_static! {
+    static ref LENGTH_TABLE: [f32; 256] = {
+        let mut length_table: [f32; 256] = [0f32; 256];
+        for (i, length) in length_table.iter_mut().enumerate().skip(1) {
+            let f = SmallFloat::byte315_to_float(i as u8);
+            *length = 1f32 / (f * f);
+        }
+        length_table[0] = 1f32 / length_table[255];
+        length_table
+    };
+}
+
+/// Decodes the document length from the norm written at index time.
+pub fn decode_length(norm: u8) -> f32 {
+    LENGTH_TABLE[norm as usize]
+}
+
+pub(crate) fn log2(x: f64) -> f64 {
+    x.ln() / ::std::f64::consts::LN_2
+}
+
+/// Stores all statistics commonly used by the ranking methods of a
+/// `SimilarityBase`.
+#[derive(Debug, Clone)]
+pub struct BasicStats {
+    pub field: String,
+    /// The number of documents, or of the documents with the field when the
+    /// index keeps track of it.
+    pub number_of_documents: i64,
+    /// The total number of tokens in the field.
+    pub number_of_field_tokens: i64,
+    pub avg_field_length: f32,
+    pub doc_freq: i64,
+    pub total_term_freq: i64,
+    /// The probability that the term is generated by the collection, used by
+    /// the language models.
+    pub collection_probability: f32,
+    pub boost: f32,
+}
+
+impl BasicStats {
+    pub fn new(
+        collection_stats: &CollectionStatistics,
+        term_stats: &TermStatistics,
+        boost: f32,
+    ) -> BasicStats {
+        let number_of_documents = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+        let doc_freq = term_stats.doc_freq;
+        let total_term_freq = if term_stats.total_term_freq == -1 {
+            doc_freq
+        } else {
+            term_stats.total_term_freq
+        };
+        // frequencies are omitted, every document is considered one token long
+        let (number_of_field_tokens, avg_field_length) =
+            if collection_stats.sum_total_term_freq <= 0 {
+                (doc_freq, 1f32)
+            } else {
+                (
+                    collection_stats.sum_total_term_freq,
+                    (collection_stats.sum_total_term_freq as f64
+                        / number_of_documents.max(1) as f64) as f32,
+                )
+            };
+        let collection_probability =
+            (total_term_freq as f32 + 1f32) / (number_of_field_tokens as f32 + 1f32);
+        BasicStats {
+            field: collection_stats.field.clone(),
+            number_of_documents,
+            number_of_field_tokens,
+            avg_field_length,
+            doc_freq,
+            total_term_freq,
+            collection_probability,
+            boost,
+        }
+    }
+}
+
+/// A simpler API for the ranking methods based on the `BasicStats` of a
+/// term, such as `DFRSimilarity`, `IBSimilarity` and the language models.
+///
+/// Implementations only have to score a term frequency given the length of
+/// the document, `compute_base_weight` takes care of the statistics, the
+/// norms and the explanations. The scores of the terms of a multi-term
+/// weight are summed up.
+pub trait SimilarityBase: fmt::Display + Send + Sync {
+    /// Scores a document with the term frequency `freq` and the field
+    /// length `doc_len`.
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32;
+
+    /// Adds the explanations of the factors of the score to `subs`.
+    fn explain_score(
+        &self,
+        _subs: &mut Vec<Explanation>,
+        _stats: &BasicStats,
+        _doc: DocId,
+        _freq: f32,
+        _doc_len: f32,
+    ) {
+    }
+
+    fn explain(
+        &self,
+        stats: &BasicStats,
+        doc: DocId,
+        freq: Explanation,
+        doc_len: f32,
+    ) -> Explanation {
+        let freq_value = freq.value();
+        let mut subs = vec![];
+        self.explain_score(&mut subs, stats, doc, freq_value, doc_len);
+        Explanation::new(
+            true,
+            self.score(stats, freq_value, doc_len),
+            format!(
+                "score({}, doc={}, freq={}), computed from:",
+                self, doc, freq_value
+            ),
+            subs,
+        )
+    }
+}
+
+/// Builds the `SimWeight` of a `SimilarityBase`, to be used by its
+/// `Similarity::compute_weight`.
+pub fn compute_base_weight<C: Codec, S: SimilarityBase + Clone + 'static>(
+    similarity: &S,
+    collection_stats: &CollectionStatistics,
+    term_stats: &[TermStatistics],
+    boost: f32,
+) -> Box<dyn SimWeight<C>> {
+    let stats = term_stats
+        .iter()
+        .map(|term_stats| BasicStats::new(collection_stats, term_stats, boost))
+        .collect();
+    Box::new(BaseSimWeight {
+        similarity: Arc::new(similarity.clone()),
+        field: collection_stats.field.clone(),
+        stats: Arc::new(stats),
+    })
+}
+
+struct BaseSimWeight<S: SimilarityBase> {
+    similarity: Arc<S>,
+    field: String,
+    stats: Arc<Vec<BasicStats>>,
+}
+
+impl<S: SimilarityBase> BaseSimWeight<S> {
+    fn doc_len(norms: &Option<Box<dyn NumericDocValues>>, doc: DocId) -> Result<f32> {
+        Ok(match norms {
+            Some(ref norms) => decode_length((norms.get(doc)? & 0xFF) as u8),
+            None => 1f32,
+        })
+    }
+}
+
+impl<C: Codec, S: SimilarityBase + 'static> SimWeight<C> for BaseSimWeight<S> {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.stats
+            .iter()
+            .map(|stats| stats.boost * stats.boost)
+            .sum()
+    }
+
+    fn normalize(&mut self, _query_norm: f32, boost: f32) {
+        for stats in Arc::make_mut(&mut self.stats) {
+            stats.boost = boost;
+        }
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        Ok(Box::new(BaseSimScorer {
+            similarity: Arc::clone(&self.similarity),
+            stats: Arc::clone(&self.stats),
+            norms: reader.norm_values(&self.field)?,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let norms = reader.norm_values(&self.field)?;
+        let doc_len = Self::doc_len(&norms, doc)?;
+        if self.stats.len() == 1 {
+            return Ok(self.similarity.explain(&self.stats[0], doc, freq, doc_len));
+        }
+        let subs: Vec<Explanation> = self
+            .stats
+            .iter()
+            .map(|stats| self.similarity.explain(stats, doc, freq.clone(), doc_len))
+            .collect();
+        Ok(Explanation::new(
+            true,
+            subs.iter().map(Explanation::value).sum(),
+            format!("score(doc={},freq={}), sum of:", doc, freq.value()),
+            subs,
+        ))
+    }
+}
+
+struct BaseSimScorer<S: SimilarityBase> {
+    similarity: Arc<S>,
+    stats: Arc<Vec<BasicStats>>,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl<S: SimilarityBase> SimScorer for BaseSimScorer<S> {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let doc_len = BaseSimWeight::<S>::doc_len(&self.norms, doc)?;
+        Ok(self
+            .stats
+            .iter()
+            .map(|stats| self.similarity.score(stats, freq, doc_len))
+            .sum())
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+}
@@ -201,12 +201,23 @@ impl PartialOrd for ScoreDocHit {
     }
 }
 
+/// Whether the total number of hits is exact or a lower bound, once the
+/// collector lets the scorers skip the docs that can't be competitive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TotalHitsRelation {
+    EqualTo,
+    GreaterThanOrEqualTo,
+}
+
 /// Represents hits returned by `IndexSearcher::search`
 #[derive(Clone)]
 pub struct TopScoreDocs {
     /// The total number of hits for the query.
     pub total_hits: usize,
 
+    /// Whether `total_hits` is exact or a lower bound.
+    pub total_hits_relation: TotalHitsRelation,
+
     /// The top hits for the query.
     pub score_docs: Vec<ScoreDocHit>,
 
@@ -218,11 +229,17 @@ impl TopScoreDocs {
     pub fn new(total_hits: usize, score_docs: Vec<ScoreDocHit>) -> TopScoreDocs {
         TopScoreDocs {
             total_hits,
+            total_hits_relation: TotalHitsRelation::EqualTo,
             score_docs,
             max_score: f32::NAN,
         }
     }
 
+    pub fn with_total_hits_relation(mut self, relation: TotalHitsRelation) -> TopScoreDocs {
+        self.total_hits_relation = relation;
+        self
+    }
+
     pub fn score_docs(&self) -> &[ScoreDocHit] {
         &self.score_docs
     }
@@ -231,11 +248,35 @@ impl TopScoreDocs {
 #[derive(Clone)]
 pub struct TopFieldDocs {
     pub total_hits: usize,
+    /// Whether `total_hits` is exact or a lower bound.
+    pub total_hits_relation: TotalHitsRelation,
     pub score_docs: Vec<ScoreDocHit>,
     pub max_score: f32,
     pub fields: Vec<SortField>,
 }
 
+impl TopFieldDocs {
+    pub fn new(
+        total_hits: usize,
+        score_docs: Vec<ScoreDocHit>,
+        fields: Vec<SortField>,
+        max_score: f32,
+    ) -> TopFieldDocs {
+        TopFieldDocs {
+            total_hits,
+            total_hits_relation: TotalHitsRelation::EqualTo,
+            score_docs,
+            max_score,
+            fields,
+        }
+    }
+
+    pub fn with_total_hits_relation(mut self, relation: TotalHitsRelation) -> TopFieldDocs {
+        self.total_hits_relation = relation;
+        self
+    }
+}
+
 pub struct CollapseTopFieldDocs {
     /// The total number of hits for the query.
     pub total_hits: usize,
@@ -300,6 +341,14 @@ impl TopDocs {
         }
     }
 
+    pub fn total_hits_relation(&self) -> TotalHitsRelation {
+        match *self {
+            TopDocs::Score(ref s) => s.total_hits_relation,
+            TopDocs::Field(ref f) => f.total_hits_relation,
+            TopDocs::Collapse(_) => TotalHitsRelation::EqualTo,
+        }
+    }
+
     pub fn total_groups(&self) -> usize {
         match *self {
             TopDocs::Score(ref s) => s.total_hits,
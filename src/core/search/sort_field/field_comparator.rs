@@ -13,8 +13,10 @@
 
 use core::codec::doc_values::NumericDocValues;
 use core::index::reader::{LeafReaderContext, SearchLeafReader};
+use core::search::expression::ExpressionComparator;
 use core::search::sort_field::{SortFieldType, SortedWrapperDocValuesSource};
 use core::util::{BitsMut, DocId, VariantValue};
+use error::ErrorKind::IllegalArgument;
 use error::Result;
 
 use core::codec::Codec;
@@ -25,12 +27,14 @@ use std::fmt;
 pub enum ComparatorValue {
     Doc(DocId),
     Score(f32), // this is only used in RelevanceComparator
+    // a doc with its score, for comparators whose values depend on the score
+    DocScore(DocId, f32),
 }
 
 impl ComparatorValue {
     fn is_doc(self) -> bool {
         match self {
-            ComparatorValue::Doc(_) => true,
+            ComparatorValue::Doc(_) | ComparatorValue::DocScore(..) => true,
             _ => false,
         }
     }
@@ -44,10 +48,9 @@ impl ComparatorValue {
 
     fn doc(self) -> DocId {
         debug_assert!(self.is_doc());
-        if let ComparatorValue::Doc(d) = self {
-            d
-        } else {
-            unreachable!()
+        match self {
+            ComparatorValue::Doc(d) | ComparatorValue::DocScore(d, _) => d,
+            _ => unreachable!(),
         }
     }
 
@@ -62,7 +65,7 @@ impl ComparatorValue {
 
     pub fn as_variant(self) -> VariantValue {
         match self {
-            ComparatorValue::Doc(d) => VariantValue::Int(d),
+            ComparatorValue::Doc(d) | ComparatorValue::DocScore(d, _) => VariantValue::Int(d),
             ComparatorValue::Score(s) => VariantValue::Float(s),
         }
     }
@@ -75,6 +78,9 @@ impl PartialEq for ComparatorValue {
         match (self, other) {
             (ComparatorValue::Doc(d1), ComparatorValue::Doc(d2)) => *d1 == *d2,
             (ComparatorValue::Score(s1), ComparatorValue::Score(s2)) => s1.eq(s2),
+            (ComparatorValue::DocScore(d1, s1), ComparatorValue::DocScore(d2, s2)) => {
+                *d1 == *d2 && s1.eq(s2)
+            }
             (_, _) => false,
         }
     }
@@ -87,6 +93,7 @@ impl Ord for ComparatorValue {
             (ComparatorValue::Score(s1), ComparatorValue::Score(s2)) => {
                 (*s1).partial_cmp(s2).unwrap()
             }
+            (ComparatorValue::DocScore(d1, _), ComparatorValue::DocScore(d2, _)) => d1.cmp(d2),
             (_, _) => panic!("Non-comparable"),
         }
     }
@@ -130,6 +137,14 @@ pub trait FieldComparator: fmt::Display {
 
     fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering>;
 
+    /// Records the sort value of the hit after which the hits are collected,
+    /// e.g. the last hit of the previous page.
+    fn set_top_value(&mut self, value: &VariantValue) -> Result<()>;
+
+    /// Compares the top value with the doc, like `compare_bottom` does with
+    /// the bottom, `Ordering::Greater` means the top value sorts after the doc.
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering>;
+
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()>;
 
     fn get_information_from_reader<C: Codec>(
@@ -145,6 +160,7 @@ pub enum FieldComparatorEnum {
     Doc(DocComparator),
     NumericDV(NumericDocValuesComparator<DefaultDocValuesSource>),
     SortedNumericDV(NumericDocValuesComparator<SortedWrapperDocValuesSource>),
+    Expression(ExpressionComparator),
 }
 
 impl FieldComparator for FieldComparatorEnum {
@@ -154,6 +170,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::NumericDV(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::Expression(c) => c.compare(slot1, slot2),
         }
     }
 
@@ -163,6 +180,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.value(slot),
             FieldComparatorEnum::NumericDV(c) => c.value(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.value(slot),
+            FieldComparatorEnum::Expression(c) => c.value(slot),
         }
     }
 
@@ -172,6 +190,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.set_bottom(slot),
             FieldComparatorEnum::NumericDV(c) => c.set_bottom(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.set_bottom(slot),
+            FieldComparatorEnum::Expression(c) => c.set_bottom(slot),
         }
     }
 
@@ -181,6 +200,27 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare_bottom(value),
             FieldComparatorEnum::NumericDV(c) => c.compare_bottom(value),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare_bottom(value),
+            FieldComparatorEnum::Expression(c) => c.compare_bottom(value),
+        }
+    }
+
+    fn set_top_value(&mut self, value: &VariantValue) -> Result<()> {
+        match self {
+            FieldComparatorEnum::Score(c) => c.set_top_value(value),
+            FieldComparatorEnum::Doc(c) => c.set_top_value(value),
+            FieldComparatorEnum::NumericDV(c) => c.set_top_value(value),
+            FieldComparatorEnum::SortedNumericDV(c) => c.set_top_value(value),
+            FieldComparatorEnum::Expression(c) => c.set_top_value(value),
+        }
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        match self {
+            FieldComparatorEnum::Score(c) => c.compare_top(value),
+            FieldComparatorEnum::Doc(c) => c.compare_top(value),
+            FieldComparatorEnum::NumericDV(c) => c.compare_top(value),
+            FieldComparatorEnum::SortedNumericDV(c) => c.compare_top(value),
+            FieldComparatorEnum::Expression(c) => c.compare_top(value),
         }
     }
 
@@ -190,6 +230,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.copy(slot, value),
             FieldComparatorEnum::NumericDV(c) => c.copy(slot, value),
             FieldComparatorEnum::SortedNumericDV(c) => c.copy(slot, value),
+            FieldComparatorEnum::Expression(c) => c.copy(slot, value),
         }
     }
 
@@ -202,6 +243,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::NumericDV(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::Expression(c) => c.get_information_from_reader(reader),
         }
     }
 
@@ -211,6 +253,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_type(),
             FieldComparatorEnum::NumericDV(c) => c.get_type(),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_type(),
+            FieldComparatorEnum::Expression(c) => c.get_type(),
         }
     }
 }
@@ -222,6 +265,7 @@ impl fmt::Display for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::NumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::SortedNumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::Expression(c) => write!(f, "FieldComparatorEnum({})", c),
         }
     }
 }
@@ -234,6 +278,7 @@ impl fmt::Display for FieldComparatorEnum {
 pub struct RelevanceComparator {
     scores: Vec<f32>,
     bottom: f32,
+    top_value: f32,
 }
 
 impl RelevanceComparator {
@@ -243,6 +288,7 @@ impl RelevanceComparator {
         RelevanceComparator {
             scores,
             bottom: 0f32,
+            top_value: 0f32,
         }
     }
 }
@@ -270,6 +316,25 @@ impl FieldComparator for RelevanceComparator {
             .unwrap_or(Ordering::Equal))
     }
 
+    fn set_top_value(&mut self, value: &VariantValue) -> Result<()> {
+        match value.get_float() {
+            Some(score) => self.top_value = score,
+            None => bail!(IllegalArgument(format!(
+                "invalid top value {:?} for relevance",
+                value
+            ))),
+        }
+        Ok(())
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_score());
+        Ok(value
+            .score()
+            .partial_cmp(&self.top_value)
+            .unwrap_or(Ordering::Equal))
+    }
+
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
         debug_assert!(value.is_score());
         self.scores[slot] = value.score();
@@ -298,6 +363,7 @@ impl fmt::Display for RelevanceComparator {
 pub struct DocComparator {
     doc_ids: Vec<i32>,
     bottom: i32,
+    top_value: i32,
     doc_base: i32,
 }
 
@@ -311,6 +377,7 @@ impl DocComparator {
         DocComparator {
             doc_ids,
             bottom: 0,
+            top_value: 0,
             doc_base: 0,
         }
     }
@@ -331,7 +398,23 @@ impl FieldComparator for DocComparator {
 
     fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
         debug_assert!(value.is_doc());
-        Ok(self.bottom.cmp(&value.doc()))
+        Ok(self.bottom.cmp(&(value.doc() + self.doc_base)))
+    }
+
+    fn set_top_value(&mut self, value: &VariantValue) -> Result<()> {
+        match value.get_int() {
+            Some(doc) => self.top_value = doc,
+            None => bail!(IllegalArgument(format!(
+                "invalid top value {:?} for doc id",
+                value
+            ))),
+        }
+        Ok(())
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        Ok(self.top_value.cmp(&(value.doc() + self.doc_base)))
     }
 
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
@@ -412,6 +495,17 @@ impl<T: DocValuesSource> NumericDocValuesComparator<T> {
         };
         Ok(value)
     }
+
+    // the value of the doc, or the missing value if the doc has no value
+    fn doc_value_or_missing(&mut self, doc_id: DocId) -> Result<VariantValue> {
+        let value = self.get_doc_value(doc_id)?;
+        if let Some(ref mut bits) = self.docs_with_fields {
+            if value.is_zero() && !bits.get(doc_id as usize)? {
+                return Ok(self.missing_value.as_ref().unwrap().clone());
+            }
+        }
+        Ok(value)
+    }
 }
 
 impl<T: DocValuesSource> FieldComparator for NumericDocValuesComparator<T> {
@@ -429,26 +523,37 @@ impl<T: DocValuesSource> FieldComparator for NumericDocValuesComparator<T> {
 
     fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
         debug_assert!(value.is_doc());
-        let doc_id = value.doc();
-        let value = self.get_doc_value(doc_id)?;
-        if let Some(ref mut bits) = self.docs_with_fields {
-            if value.is_zero() && bits.get(doc_id as usize)? {
-                return Ok(self.bottom.cmp(self.missing_value.as_ref().unwrap()));
-            }
-        }
+        let value = self.doc_value_or_missing(value.doc())?;
         Ok(self.bottom.cmp(&value))
     }
 
+    fn set_top_value(&mut self, value: &VariantValue) -> Result<()> {
+        let valid = match self.field_type {
+            SortFieldType::Int => value.get_int().is_some(),
+            SortFieldType::Long => value.get_long().is_some(),
+            SortFieldType::Float => value.get_float().is_some(),
+            SortFieldType::Double => value.get_double().is_some(),
+            _ => false,
+        };
+        if !valid {
+            bail!(IllegalArgument(format!(
+                "invalid top value {:?} for field '{}' of type {:?}",
+                value, self.field, self.field_type
+            )));
+        }
+        self.top_value = value.clone();
+        Ok(())
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        let value = self.doc_value_or_missing(value.doc())?;
+        Ok(self.top_value.cmp(&value))
+    }
+
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
         debug_assert!(value.is_doc());
-        let doc_id = value.doc();
-        let mut value = self.get_doc_value(doc_id)?;
-        if let Some(ref mut bits) = self.docs_with_fields {
-            if value.is_zero() && bits.get(doc_id as usize)? {
-                value = self.missing_value.as_ref().unwrap().clone();
-            }
-        }
-        self.values[slot] = value;
+        self.values[slot] = self.doc_value_or_missing(value.doc())?;
         Ok(())
     }
 
@@ -27,6 +27,10 @@ mod search_group;
 
 pub use self::search_group::*;
 
+mod search_after;
+
+pub use self::search_after::*;
+
 /// Encapsulates sort criteria for returned hits.
 ///
 /// The fields used to determine sort order must be carefully chosen.
@@ -0,0 +1,159 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde;
+use serde::ser::{Error as SerError, SerializeStruct};
+
+use core::search::sort_field::{FieldDoc, ScoreDoc, ScoreDocHit, TopDocs};
+use core::util::{DocId, VariantValue};
+
+use error::Result;
+
+/// The position of the last hit of a page, for the clients to request the
+/// next page with `TopDocsCollector::with_search_after` or
+/// `TopFieldCollector::with_search_after`.
+///
+/// The cursor round-trips through `encode` and `decode`, the types of the
+/// sort values are kept so that they can be compared with the values of
+/// the sort fields again.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SearchAfterCursor {
+    /// The top level doc id of the hit.
+    pub doc: DocId,
+    /// The score of the hit, it must not be `NaN`.
+    pub score: f32,
+    /// The sort values of the hit, empty for a score ordered search.
+    pub fields: Vec<VariantValue>,
+}
+
+impl SearchAfterCursor {
+    pub fn new(doc: DocId, score: f32, fields: Vec<VariantValue>) -> SearchAfterCursor {
+        SearchAfterCursor { doc, score, fields }
+    }
+
+    pub fn from_hit(hit: &ScoreDocHit) -> SearchAfterCursor {
+        match hit {
+            ScoreDocHit::Score(s) => Self::new(s.doc, s.score, vec![]),
+            ScoreDocHit::Field(f) => Self::new(f.doc, f.score, f.fields.clone()),
+        }
+    }
+
+    /// Returns the cursor of the last hit of `top_docs`, `None` if there are
+    /// no hits.
+    pub fn last(top_docs: &TopDocs) -> Option<SearchAfterCursor> {
+        top_docs.score_docs().last().map(Self::from_hit)
+    }
+
+    pub fn to_score_doc(&self) -> ScoreDoc {
+        ScoreDoc::new(self.doc, self.score)
+    }
+
+    pub fn to_field_doc(&self) -> FieldDoc {
+        FieldDoc::new(self.doc, self.score, self.fields.clone())
+    }
+
+    /// Encodes the cursor as a JSON string.
+    pub fn encode(&self) -> Result<String> {
+        Ok(::serde_json::to_string(self)?)
+    }
+
+    /// Decodes a cursor encoded by `encode`.
+    pub fn decode(cursor: &str) -> Result<SearchAfterCursor> {
+        Ok(::serde_json::from_str(cursor)?)
+    }
+}
+
+impl serde::Serialize for SearchAfterCursor {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let fields: Vec<TaggedValue> = self.fields.iter().map(TaggedValue).collect();
+        let mut state = serializer.serialize_struct("SearchAfterCursor", 3)?;
+        state.serialize_field("doc", &self.doc)?;
+        state.serialize_field("score", &self.score)?;
+        state.serialize_field("fields", &fields)?;
+        state.end()
+    }
+}
+
+// `VariantValue` serializes to the bare value, this keeps the variant the
+// way its `Deserialize` expects it, e.g. `{"Long": 5}`.
+struct TaggedValue<'a>(&'a VariantValue);
+
+impl<'a> serde::Serialize for TaggedValue<'a> {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.0 {
+            VariantValue::Bool(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 0, "Bool", v)
+            }
+            VariantValue::Char(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 1, "Char", v)
+            }
+            VariantValue::Short(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 2, "Short", v)
+            }
+            VariantValue::Int(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 3, "Int", v)
+            }
+            VariantValue::Long(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 4, "Long", v)
+            }
+            VariantValue::Float(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 5, "Float", v)
+            }
+            VariantValue::Double(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 6, "Double", v)
+            }
+            VariantValue::VString(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 7, "VString", v)
+            }
+            VariantValue::Binary(v) => {
+                serializer.serialize_newtype_variant("VariantValue", 8, "Binary", v)
+            }
+            VariantValue::Map(_) => Err(S::Error::custom("map is not a sort value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let cursor = SearchAfterCursor::new(
+            42,
+            1.5,
+            vec![
+                VariantValue::Int(7),
+                VariantValue::Long(7),
+                VariantValue::Float(0.25),
+                VariantValue::Double(-3.5),
+            ],
+        );
+        let encoded = cursor.encode().unwrap();
+        let decoded = SearchAfterCursor::decode(&encoded).unwrap();
+        assert_eq!(decoded, cursor);
+        assert_eq!(decoded.fields[1].get_long(), Some(7));
+
+        let field_doc = decoded.to_field_doc();
+        assert_eq!(field_doc.doc, 42);
+        assert_eq!(field_doc.fields.len(), 4);
+
+        assert!(SearchAfterCursor::decode("{\"doc\": 1}").is_err());
+    }
+}
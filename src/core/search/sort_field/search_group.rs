@@ -14,7 +14,7 @@
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 
-use core::search::sort_field::{ComparatorValue, SortFieldType};
+use core::search::sort_field::{ComparatorValue, ScoreDocHit, SortField, SortFieldType};
 use core::util::DocId;
 use core::util::VariantValue;
 
@@ -79,6 +79,10 @@ pub struct CollectedSearchGroup {
     pub max_wilson_info: WilsonInfo,
 
     pub top_doc: DocId,
+
+    /// The values of all the sort fields for the top doc of the group,
+    /// `sort_info_list` only holds the relevance and doc id ones.
+    pub sort_values: Vec<VariantValue>,
 }
 
 impl CollectedSearchGroup {
@@ -95,8 +99,14 @@ impl CollectedSearchGroup {
             max_wilson_info,
             comparator_slot,
             top_doc,
+            sort_values: vec![],
         }
     }
+
+    pub fn with_sort_values(mut self, sort_values: Vec<VariantValue>) -> Self {
+        self.sort_values = sort_values;
+        self
+    }
 }
 
 impl Ord for CollectedSearchGroup {
@@ -139,6 +149,33 @@ impl PartialEq for CollectedSearchGroup {
     }
 }
 
+/// The top docs of a group, collected by the second pass of a grouping
+/// search.
+#[derive(Debug, Clone)]
+pub struct GroupDocs {
+    pub group_value: VariantValue,
+    /// The sort values of the group in the first pass.
+    pub group_sort_values: Vec<VariantValue>,
+    /// The number of hits of the group.
+    pub total_hits: usize,
+    /// The top hits of the group, sorted by the within group sort.
+    pub score_docs: Vec<ScoreDocHit>,
+    pub max_score: f32,
+}
+
+/// The result of a grouping search, the groups are in the order of the
+/// group sort.
+#[derive(Debug, Clone)]
+pub struct TopGroups {
+    /// The number of hits of the query.
+    pub total_hits: usize,
+    /// The number of hits of the collected groups.
+    pub total_grouped_hits: usize,
+    pub groups: Vec<GroupDocs>,
+    pub group_sort: Vec<SortField>,
+    pub within_group_sort: Vec<SortField>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -14,6 +14,7 @@
 use core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
 use core::codec::Codec;
 use core::index::reader::SearchLeafReader;
+use core::search::expression::ExpressionSortField;
 use core::search::sort_field::{
     DefaultDocValuesSource, DocComparator, DocValuesSource, FieldComparatorEnum,
     NumericDocValuesComparator, RelevanceComparator,
@@ -62,6 +63,7 @@ pub enum SortFieldMissingValue {
 pub enum SortField {
     Simple(SimpleSortField),
     SortedNumeric(SortedNumericSortField),
+    Expression(ExpressionSortField),
     // SortedSet(SortedSetSortField),
 }
 
@@ -74,6 +76,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => &s.field,
             SortField::SortedNumeric(s) => &s.raw_field.field,
+            SortField::Expression(s) => &s.raw_field().field,
         }
     }
 
@@ -81,6 +84,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.field_type,
             SortField::SortedNumeric(s) => s.raw_field.field_type,
+            SortField::Expression(s) => s.raw_field().field_type,
         }
     }
 
@@ -88,6 +92,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.is_reverse,
             SortField::SortedNumeric(s) => s.raw_field.is_reverse,
+            SortField::Expression(s) => s.raw_field().is_reverse,
         }
     }
 
@@ -95,6 +100,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.missing_value.as_ref(),
             SortField::SortedNumeric(s) => s.raw_field.missing_value.as_ref(),
+            SortField::Expression(s) => s.raw_field().missing_value.as_ref(),
         }
     }
 
@@ -102,6 +108,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.needs_scores(),
             SortField::SortedNumeric(s) => s.raw_field.needs_scores(),
+            SortField::Expression(s) => s.needs_scores(),
         }
     }
 
@@ -113,6 +120,9 @@ impl SortField {
             SortField::SortedNumeric(s) => {
                 s.raw_field.missing_value = value;
             }
+            SortField::Expression(s) => {
+                s.raw_field_mut().missing_value = value;
+            }
         }
     }
 
@@ -124,6 +134,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.get_comparator(num_hits, missing_value),
             SortField::SortedNumeric(s) => s.get_comparator(num_hits, missing_value),
+            SortField::Expression(s) => s.get_comparator(num_hits),
         }
     }
 }
@@ -0,0 +1,325 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+/// Maximum Unicode code point, the largest label of a character automaton.
+pub const MAX_CODE_POINT: u32 = 0x10_ffff;
+
+/// Maximum label of a binary (byte based) automaton.
+pub const MAX_BYTE_LABEL: u32 = 0xff;
+
+/// Default maximum number of states that `Operations::determinize` should create.
+pub const DEFAULT_MAX_DETERMINIZED_STATES: usize = 10_000;
+
+/// Holds one transition from an `Automaton`: all labels in `[min, max]`
+/// (both inclusive) lead to the `dest` state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Transition {
+    pub min: u32,
+    pub max: u32,
+    pub dest: usize,
+}
+
+impl Transition {
+    pub fn new(min: u32, max: u32, dest: usize) -> Transition {
+        debug_assert!(min <= max);
+        Transition { min, max, dest }
+    }
+
+    #[inline]
+    pub fn contains(&self, label: u32) -> bool {
+        self.min <= label && label <= self.max
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct State {
+    accept: bool,
+    transitions: Vec<Transition>,
+    epsilons: Vec<usize>,
+}
+
+/// Represents an automaton and all its states and transitions.
+///
+/// States are integers and must be created using `create_state`. Mark a
+/// state as an accept state using `set_accept`. Add transitions using
+/// `add_transition` and epsilon (empty label) transitions using `add_epsilon`.
+/// State 0 is always the initial state.
+///
+/// Labels are either unicode code points (`[0, MAX_CODE_POINT]`) or, for
+/// binary automata that run against index terms directly, bytes
+/// (`[0, MAX_BYTE_LABEL]`). Use `UTF32ToUTF8` to convert the former into the
+/// later.
+///
+/// An automaton is deterministic when it has no epsilon transitions and no
+/// state has two transitions with overlapping labels. Most algorithms working
+/// on index terms require a deterministic automaton, use
+/// `Operations::determinize` to get one.
+#[derive(Clone)]
+pub struct Automaton {
+    states: Vec<State>,
+    deterministic: bool,
+}
+
+impl Automaton {
+    pub fn new() -> Automaton {
+        Automaton {
+            states: vec![],
+            deterministic: true,
+        }
+    }
+
+    /// Create a new state, the first created state is the initial state.
+    pub fn create_state(&mut self) -> usize {
+        self.states.push(State::default());
+        self.states.len() - 1
+    }
+
+    /// Set or clear this state as an accept state.
+    pub fn set_accept(&mut self, state: usize, accept: bool) {
+        debug_assert!(state < self.states.len());
+        self.states[state].accept = accept;
+    }
+
+    /// Returns true if this state is an accept state.
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.states[state].accept
+    }
+
+    /// Returns true if any state is an accept state.
+    pub fn has_accept_state(&self) -> bool {
+        self.states.iter().any(|s| s.accept)
+    }
+
+    /// Add a new transition with `min <= label <= max`.
+    pub fn add_transition(&mut self, source: usize, dest: usize, min: u32, max: u32) {
+        debug_assert!(source < self.states.len());
+        debug_assert!(dest < self.states.len());
+        debug_assert!(min <= max);
+
+        if self.deterministic
+            && self.states[source]
+                .transitions
+                .iter()
+                .any(|t| t.min <= max && min <= t.max)
+        {
+            self.deterministic = false;
+        }
+        self.states[source]
+            .transitions
+            .push(Transition::new(min, max, dest));
+    }
+
+    /// Add an epsilon transition, the automaton accepts from `source` every
+    /// thing it accepts from `dest` without consuming any label.
+    pub fn add_epsilon(&mut self, source: usize, dest: usize) {
+        debug_assert!(source < self.states.len());
+        debug_assert!(dest < self.states.len());
+        if source != dest {
+            self.states[source].epsilons.push(dest);
+            self.deterministic = false;
+        }
+    }
+
+    /// Copies over all states and transitions from other, the states of
+    /// `other` are shifted by the returned offset.
+    pub fn copy(&mut self, other: &Automaton) -> usize {
+        let offset = self.states.len();
+        for state in &other.states {
+            let mut transitions = Vec::with_capacity(state.transitions.len());
+            for t in &state.transitions {
+                transitions.push(Transition::new(t.min, t.max, t.dest + offset));
+            }
+            let epsilons = state.epsilons.iter().map(|e| *e + offset).collect();
+            self.states.push(State {
+                accept: state.accept,
+                transitions,
+                epsilons,
+            });
+        }
+        // determinism is a per state property, so the result stays
+        // deterministic iff both parts are.
+        self.deterministic &= other.deterministic;
+        offset
+    }
+
+    /// Sorts the transitions of every state by label and collapses adjacent
+    /// ranges sharing the same destination.
+    pub fn finish(&mut self) {
+        for state in &mut self.states {
+            if state.transitions.len() > 1 {
+                state.transitions.sort_by(|a, b| {
+                    a.min
+                        .cmp(&b.min)
+                        .then(a.max.cmp(&b.max))
+                        .then(a.dest.cmp(&b.dest))
+                });
+                let mut merged: Vec<Transition> = Vec::with_capacity(state.transitions.len());
+                for t in &state.transitions {
+                    if let Some(last) = merged.last_mut() {
+                        if last.dest == t.dest && t.min <= last.max.saturating_add(1) {
+                            if t.max > last.max {
+                                last.max = t.max;
+                            }
+                            continue;
+                        }
+                    }
+                    merged.push(*t);
+                }
+                state.transitions = merged;
+            }
+            state.epsilons.sort();
+            state.epsilons.dedup();
+        }
+    }
+
+    /// Returns the number of states.
+    #[inline]
+    pub fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the number of transitions leaving the `state`.
+    #[inline]
+    pub fn num_transitions(&self, state: usize) -> usize {
+        self.states[state].transitions.len()
+    }
+
+    /// Returns the transitions leaving the `state`, they are sorted by label
+    /// once the automaton is `finish`ed.
+    #[inline]
+    pub fn transitions(&self, state: usize) -> &[Transition] {
+        &self.states[state].transitions
+    }
+
+    /// Returns the epsilon transitions leaving the `state`.
+    #[inline]
+    pub fn epsilons(&self, state: usize) -> &[usize] {
+        &self.states[state].epsilons
+    }
+
+    pub fn has_epsilons(&self) -> bool {
+        self.states.iter().any(|s| !s.epsilons.is_empty())
+    }
+
+    /// Returns true if this automaton is known to be deterministic.
+    #[inline]
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    pub(crate) fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Performs lookup in transitions, assuming determinism.
+    ///
+    /// Returns the destination state, or `None` if there is no matching
+    /// transition.
+    pub fn step(&self, state: usize, label: u32) -> Option<usize> {
+        debug_assert!(self.deterministic);
+        let transitions = &self.states[state].transitions;
+        // transitions are sorted and do not overlap for deterministic automaton
+        let mut low = 0usize;
+        let mut high = transitions.len();
+        while low < high {
+            let mid = (low + high) >> 1;
+            let t = &transitions[mid];
+            if t.max < label {
+                low = mid + 1;
+            } else if t.min > label {
+                high = mid;
+            } else {
+                return Some(t.dest);
+            }
+        }
+        None
+    }
+
+    /// Returns the sorted array of all interval start points, an interval
+    /// spans from one point to the next one minus one and every label in an
+    /// interval behaves the same way in every state.
+    pub fn start_points(&self) -> Vec<u32> {
+        let mut points = vec![0u32];
+        for state in &self.states {
+            for t in &state.transitions {
+                points.push(t.min);
+                points.push(t.max + 1);
+            }
+        }
+        points.sort();
+        points.dedup();
+        points
+    }
+
+    /// Returns the set of states reachable from `states` by following only
+    /// epsilon transitions, sorted and without duplicates.
+    pub fn epsilon_closure(&self, states: &[usize]) -> Vec<usize> {
+        let mut closure: Vec<usize> = Vec::with_capacity(states.len());
+        let mut seen = vec![false; self.states.len()];
+        let mut stack: Vec<usize> = states.to_vec();
+        while let Some(s) = stack.pop() {
+            if seen[s] {
+                continue;
+            }
+            seen[s] = true;
+            closure.push(s);
+            for e in &self.states[s].epsilons {
+                if !seen[*e] {
+                    stack.push(*e);
+                }
+            }
+        }
+        closure.sort();
+        closure
+    }
+}
+
+impl Default for Automaton {
+    fn default() -> Self {
+        Automaton::new()
+    }
+}
+
+impl fmt::Debug for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Automaton(states: {}, deterministic: {})",
+            self.states.len(),
+            self.deterministic
+        )?;
+        for (i, state) in self.states.iter().enumerate() {
+            write!(
+                f,
+                "  state {}{}:",
+                i,
+                if state.accept { " [accept]" } else { "" }
+            )?;
+            for t in &state.transitions {
+                if t.min == t.max {
+                    write!(f, " {:x} -> {}", t.min, t.dest)?;
+                } else {
+                    write!(f, " {:x}-{:x} -> {}", t.min, t.max, t.dest)?;
+                }
+            }
+            for e in &state.epsilons {
+                write!(f, " eps -> {}", e)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
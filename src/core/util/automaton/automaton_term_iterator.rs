@@ -0,0 +1,447 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::max;
+use std::sync::Arc;
+
+use core::codec::{AcceptStatus, FilteredTermIterBase, FilteredTermIterator};
+use core::codec::{SeekStatus, TermIterator};
+use core::util::automaton::{ByteRunAutomaton, CompiledAutomaton};
+
+use error::Result;
+
+/// A `FilteredTermIterator` that enumerates terms based upon what is accepted
+/// by a DFA.
+///
+/// The algorithm is such:
+/// 1. As long as matches are successful, keep reading sequentially.
+/// 2. When a match fails, skip to the next string in lexicographic order that
+///    does not enter a reject state.
+///
+/// The algorithm does not attempt to actually skip to the next string that is
+/// completely accepted. This is not possible when the language accepted by the
+/// DFA is not finite, or when the DFA is so large that this would be
+/// inefficient.
+pub struct AutomatonTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    run_automaton: Arc<ByteRunAutomaton>,
+    finite: bool,
+    // used for path tracking, where each slot is a numbered state.
+    visited: Vec<u64>,
+    cur_gen: u64,
+    // the reference used for seeking forwards through the term dictionary
+    seek_bytes: Vec<u8>,
+    // true if we are enumerating an infinite portion of the DFA.
+    // in this case it is faster to drive the query based on the terms dictionary.
+    // when this is true, linear_upper_bound indicate the end of range
+    // of terms where we should simply do sequential reads instead.
+    linear: bool,
+    linear_upper_bound: Vec<u8>,
+    saved_states: Vec<usize>,
+}
+
+impl<T: TermIterator> AutomatonTermIterator<T> {
+    /// Construct an iterator based upon an automaton, `compiled` must be of
+    /// type `AutomatonType::Normal`.
+    pub fn new(terms: T, compiled: &CompiledAutomaton) -> Self {
+        let run_automaton = Arc::clone(compiled.run_automaton.as_ref().unwrap());
+        let visited = vec![0u64; run_automaton.size()];
+        AutomatonTermIterator {
+            base: FilteredTermIterBase::new(terms, true),
+            run_automaton,
+            finite: compiled.finite,
+            visited,
+            cur_gen: 0,
+            seek_bytes: Vec::new(),
+            linear: false,
+            linear_upper_bound: Vec::new(),
+            saved_states: Vec::new(),
+        }
+    }
+
+    /// Sets the enum to operate in linear fashion, as we have found
+    /// a looping transition at position: we set an upper bound and
+    /// act like a TermRangeQuery for this portion of the term space.
+    fn set_linear(&mut self, position: usize) {
+        debug_assert!(!self.linear);
+        let mut state = 0;
+        for i in 0..position {
+            state = self
+                .run_automaton
+                .step(state, u32::from(self.seek_bytes[i]))
+                .unwrap();
+        }
+        let label = u32::from(self.seek_bytes[position]);
+        let mut max_interval = 0xffu32;
+        for t in self.run_automaton.automaton().transitions(state) {
+            if t.contains(label) {
+                max_interval = t.max;
+                break;
+            }
+        }
+        // 0xff terms don't get the optimization... not worth the trouble.
+        if max_interval != 0xff {
+            max_interval += 1;
+        }
+        self.linear_upper_bound.clear();
+        self.linear_upper_bound
+            .extend_from_slice(&self.seek_bytes[..position]);
+        self.linear_upper_bound.push(max_interval as u8);
+        self.linear = true;
+    }
+
+    /// Increments the byte buffer to the next string in binary order after
+    /// `seek_bytes` that can be accepted by the automaton. Returns false if
+    /// no more strings can be accepted.
+    fn next_string(&mut self) -> bool {
+        let mut pos = 0usize;
+        self.saved_states.clear();
+        self.saved_states.resize(self.seek_bytes.len() + 1, 0);
+
+        loop {
+            self.cur_gen += 1;
+            self.linear = false;
+            // walk the automaton until a character is rejected.
+            let mut state = self.saved_states[pos];
+            while pos < self.seek_bytes.len() {
+                self.visited[state] = self.cur_gen;
+                let next_state = match self
+                    .run_automaton
+                    .step(state, u32::from(self.seek_bytes[pos]))
+                {
+                    Some(s) => s,
+                    None => break,
+                };
+                self.saved_states[pos + 1] = next_state;
+                // we found a loop, record it for faster enumeration
+                if !self.finite && !self.linear && self.visited[next_state] == self.cur_gen {
+                    self.set_linear(pos);
+                }
+                state = next_state;
+                pos += 1;
+            }
+
+            // take the useful portion, and the last non-reject state, and attempt to
+            // append characters that will match.
+            if self.next_string_from(state, pos) {
+                return true;
+            }
+            // no more solutions exist from this useful portion, backtrack
+            match self.backtrack(pos) {
+                None => return false,
+                Some(p) => {
+                    pos = p;
+                    if let Some(new_state) = self
+                        .run_automaton
+                        .step(self.saved_states[pos], u32::from(self.seek_bytes[pos]))
+                    {
+                        if self.run_automaton.is_accept(new_state) {
+                            // String is good to go as-is
+                            return true;
+                        }
+                    }
+                    // else advance further, restart from scratch for infinite
+                    // DFAs since the loop detection is important
+                    if !self.finite {
+                        pos = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the next string in lexicographic order that will not put
+    /// the machine into a reject state.
+    ///
+    /// This method traverses the DFA from the given position in the string,
+    /// starting at the given state. If this cannot satisfy the machine, returns
+    /// false. This method will walk the minimal path, in lexicographic order, as
+    /// long as possible.
+    fn next_string_from(&mut self, state: usize, position: usize) -> bool {
+        // the next lexicographic character must be greater than the existing
+        // character, if it exists.
+        let mut c = 0u32;
+        if position < self.seek_bytes.len() {
+            c = u32::from(self.seek_bytes[position]);
+            // if the next byte is 0xff and is not part of the useful portion,
+            // then by definition it puts us in a reject state, and therefore this
+            // path is dead. there cannot be any higher transitions. backtrack.
+            if c == 0xff {
+                return false;
+            }
+            c += 1;
+        }
+
+        self.seek_bytes.truncate(position);
+        self.visited[state] = self.cur_gen;
+
+        let run_automaton = Arc::clone(&self.run_automaton);
+        let automaton = run_automaton.automaton();
+        // find the minimal path (lexicographic order) that is >= c
+        for t in automaton.transitions(state) {
+            if t.max >= c {
+                let next_char = max(c, t.min);
+                // append either the next sequential char, or the minimum transition
+                self.seek_bytes.push(next_char as u8);
+                let mut state = t.dest;
+                // as long as is possible, continue down the minimal path in
+                // lexicographic order. if a loop or accept state is encountered, stop.
+                while self.visited[state] != self.cur_gen && !run_automaton.is_accept(state) {
+                    self.visited[state] = self.cur_gen;
+                    // we work with a DFA with no transitions to dead states, so
+                    // if it is not an accept state there MUST be a transition.
+                    let min_transition = automaton.transitions(state)[0];
+                    state = min_transition.dest;
+                    // append the minimum transition
+                    self.seek_bytes.push(min_transition.min as u8);
+
+                    // we found a loop, record it for faster enumeration
+                    if !self.finite && !self.linear && self.visited[state] == self.cur_gen {
+                        let position = self.seek_bytes.len() - 1;
+                        self.set_linear(position);
+                    }
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Attempts to backtrack thru the string after encountering a dead end
+    /// at some given position. Returns `None` if no more possible strings
+    /// can match.
+    fn backtrack(&mut self, mut position: usize) -> Option<usize> {
+        while position > 0 {
+            position -= 1;
+            let next_char = self.seek_bytes[position];
+            // if a character is 0xff it's a dead-end too,
+            // because there is no higher character in binary sort order.
+            if next_char != 0xff {
+                self.seek_bytes[position] = next_char + 1;
+                self.seek_bytes.truncate(position + 1);
+                return Some(position);
+            }
+        }
+        None
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for AutomatonTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    /// Returns true if the term matches the automaton. Also stashes away the term
+    /// to assist with smart enumeration.
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        let status = if self.run_automaton.run(term) {
+            if self.linear {
+                AcceptStatus::Yes
+            } else {
+                AcceptStatus::YesAndSeek
+            }
+        } else if self.linear && term < self.linear_upper_bound.as_slice() {
+            AcceptStatus::No
+        } else {
+            AcceptStatus::NoAndSeek
+        };
+        Ok(status)
+    }
+
+    fn next_seek_term(&mut self) -> Option<Vec<u8>> {
+        let term = match self.base.actual_term {
+            Some(ref term) => Some(term.clone()),
+            None => self.base.initial_seek_term.take(),
+        };
+        match term {
+            Some(term) => {
+                self.seek_bytes = term;
+            }
+            None => {
+                debug_assert!(self.seek_bytes.is_empty());
+                // return the empty term, as it's valid
+                if self.run_automaton.is_accept(0) {
+                    return Some(Vec::new());
+                }
+            }
+        }
+
+        // seek to the next possible string;
+        if self.next_string() {
+            Some(self.seek_bytes.clone())
+        } else {
+            // no more possible strings can match
+            None
+        }
+    }
+}
+
+/// Subclass of `FilteredTermIterator` for enumerating a single term.
+///
+/// For example, this can be used by `MultiTermQuery`s that need only visit
+/// one term, but want to preserve `MultiTermQuery` semantics.
+pub struct SingleTermIterator<T: TermIterator> {
+    base: FilteredTermIterBase<T>,
+    single_term: Vec<u8>,
+}
+
+impl<T: TermIterator> SingleTermIterator<T> {
+    pub fn new(terms: T, term: Vec<u8>) -> Self {
+        let mut base = FilteredTermIterBase::new(terms, true);
+        base.initial_seek_term = Some(term.clone());
+        SingleTermIterator {
+            base,
+            single_term: term,
+        }
+    }
+}
+
+impl<T: TermIterator> FilteredTermIterator for SingleTermIterator<T> {
+    type Iter = T;
+
+    fn base(&self) -> &FilteredTermIterBase<T> {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut FilteredTermIterBase<T> {
+        &mut self.base
+    }
+
+    fn accept(&self, term: &[u8]) -> Result<AcceptStatus> {
+        if term == self.single_term.as_slice() {
+            Ok(AcceptStatus::Yes)
+        } else {
+            Ok(AcceptStatus::End)
+        }
+    }
+}
+
+/// The `TermIterator` returned by `CompiledAutomaton::term_iterator`.
+pub enum AutomatonTermIterEnum<T: TermIterator> {
+    All(T),
+    Single(SingleTermIterator<T>),
+    Normal(AutomatonTermIterator<T>),
+    /// Iterator returned by a codec's own `Terms::intersect`, which only
+    /// visits the accepted terms.
+    Intersect(T),
+}
+
+impl<T: TermIterator> TermIterator for AutomatonTermIterEnum<T> {
+    type Postings = T::Postings;
+    type TermState = T::TermState;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.next(),
+            AutomatonTermIterEnum::Single(i) => i.next(),
+            AutomatonTermIterEnum::Normal(i) => i.next(),
+            AutomatonTermIterEnum::Intersect(i) => i.next(),
+        }
+    }
+
+    fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.seek_exact(text),
+            AutomatonTermIterEnum::Single(i) => i.seek_exact(text),
+            AutomatonTermIterEnum::Normal(i) => i.seek_exact(text),
+            AutomatonTermIterEnum::Intersect(i) => i.seek_exact(text),
+        }
+    }
+
+    fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.seek_ceil(text),
+            AutomatonTermIterEnum::Single(i) => i.seek_ceil(text),
+            AutomatonTermIterEnum::Normal(i) => i.seek_ceil(text),
+            AutomatonTermIterEnum::Intersect(i) => i.seek_ceil(text),
+        }
+    }
+
+    fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.seek_exact_ord(ord),
+            AutomatonTermIterEnum::Single(i) => i.seek_exact_ord(ord),
+            AutomatonTermIterEnum::Normal(i) => i.seek_exact_ord(ord),
+            AutomatonTermIterEnum::Intersect(i) => i.seek_exact_ord(ord),
+        }
+    }
+
+    fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.seek_exact_state(text, state),
+            AutomatonTermIterEnum::Single(i) => i.seek_exact_state(text, state),
+            AutomatonTermIterEnum::Normal(i) => i.seek_exact_state(text, state),
+            AutomatonTermIterEnum::Intersect(i) => i.seek_exact_state(text, state),
+        }
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.term(),
+            AutomatonTermIterEnum::Single(i) => i.term(),
+            AutomatonTermIterEnum::Normal(i) => i.term(),
+            AutomatonTermIterEnum::Intersect(i) => i.term(),
+        }
+    }
+
+    fn ord(&self) -> Result<i64> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.ord(),
+            AutomatonTermIterEnum::Single(i) => i.ord(),
+            AutomatonTermIterEnum::Normal(i) => i.ord(),
+            AutomatonTermIterEnum::Intersect(i) => i.ord(),
+        }
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.doc_freq(),
+            AutomatonTermIterEnum::Single(i) => i.doc_freq(),
+            AutomatonTermIterEnum::Normal(i) => i.doc_freq(),
+            AutomatonTermIterEnum::Intersect(i) => i.doc_freq(),
+        }
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.total_term_freq(),
+            AutomatonTermIterEnum::Single(i) => i.total_term_freq(),
+            AutomatonTermIterEnum::Normal(i) => i.total_term_freq(),
+            AutomatonTermIterEnum::Intersect(i) => i.total_term_freq(),
+        }
+    }
+
+    fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.postings_with_flags(flags),
+            AutomatonTermIterEnum::Single(i) => i.postings_with_flags(flags),
+            AutomatonTermIterEnum::Normal(i) => i.postings_with_flags(flags),
+            AutomatonTermIterEnum::Intersect(i) => i.postings_with_flags(flags),
+        }
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        match self {
+            AutomatonTermIterEnum::All(i) => i.term_state(),
+            AutomatonTermIterEnum::Single(i) => i.term_state(),
+            AutomatonTermIterEnum::Normal(i) => i.term_state(),
+            AutomatonTermIterEnum::Intersect(i) => i.term_state(),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::char;
+use std::sync::Arc;
+
+use core::codec::Terms;
+use core::util::automaton::{
+    Automaton, ByteRunAutomaton, Operations, UTF32ToUTF8, DEFAULT_MAX_DETERMINIZED_STATES,
+    MAX_BYTE_LABEL, MAX_CODE_POINT,
+};
+use core::util::automaton::{AutomatonTermIterEnum, SingleTermIterator};
+
+use error::Result;
+
+/// Automata are compiled into different internal forms for the most
+/// efficient execution depending upon the language they accept.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AutomatonType {
+    /// Automaton that accepts no strings.
+    Empty,
+    /// Automaton that accepts all possible strings.
+    All,
+    /// Automaton that accepts only a single fixed string.
+    Single,
+    /// Catch-all for any other automata.
+    Normal,
+}
+
+/// Immutable class holding compiled details for a given `Automaton`. The
+/// automaton is deterministic, must not have dead states but is not
+/// necessarily minimal.
+#[derive(Clone)]
+pub struct CompiledAutomaton {
+    pub automaton_type: AutomatonType,
+    /// For `AutomatonType::Single` this is the singleton term.
+    pub term: Option<Vec<u8>>,
+    /// Matcher for quickly determining if a byte sequence is accepted, only
+    /// valid for `AutomatonType::Normal`.
+    pub run_automaton: Option<Arc<ByteRunAutomaton>>,
+    /// Indicates if the automaton accepts a finite set of strings, only valid
+    /// for `AutomatonType::Normal`.
+    pub finite: bool,
+    /// An accept state of the binary automaton that leads to itself for any
+    /// byte, so every term with the prefix leading to it is accepted.
+    pub sink_state: Option<usize>,
+}
+
+impl CompiledAutomaton {
+    /// Create this, passing simplify=true and finite=None, so that we try to
+    /// simplify the automaton and determine if it is finite.
+    pub fn new(automaton: &Automaton) -> Result<CompiledAutomaton> {
+        CompiledAutomaton::with_options(
+            automaton,
+            None,
+            true,
+            DEFAULT_MAX_DETERMINIZED_STATES,
+            false,
+        )
+    }
+
+    /// Create this. If `finite` is `None`, we use `Operations::is_finite` to
+    /// determine whether it is finite. If `simplify` is true, we run possibly
+    /// expensive operations to determine if the automaton is one of the
+    /// special cases. `is_binary` is true if the automaton labels are bytes
+    /// rather than unicode code points.
+    pub fn with_options(
+        automaton: &Automaton,
+        finite: Option<bool>,
+        simplify: bool,
+        max_determinized_states: usize,
+        is_binary: bool,
+    ) -> Result<CompiledAutomaton> {
+        let minimal;
+        let automaton = if simplify {
+            if Operations::is_empty(automaton) {
+                return Ok(CompiledAutomaton::of_type(AutomatonType::Empty, None));
+            }
+
+            minimal = Operations::minimize(automaton, max_determinized_states)?;
+            let max_label = if is_binary {
+                MAX_BYTE_LABEL
+            } else {
+                MAX_CODE_POINT
+            };
+            if Operations::is_total(&minimal, 0, max_label) {
+                return Ok(CompiledAutomaton::of_type(AutomatonType::All, None));
+            }
+
+            if let Some(labels) = Operations::get_singleton(&minimal) {
+                let term = if is_binary {
+                    Some(labels.iter().map(|l| *l as u8).collect())
+                } else {
+                    labels
+                        .iter()
+                        .map(|l| char::from_u32(*l))
+                        .collect::<Option<String>>()
+                        .map(String::into_bytes)
+                };
+                // a surrogate code point can't be part of any utf8 term
+                return Ok(match term {
+                    Some(term) => CompiledAutomaton::of_type(AutomatonType::Single, Some(term)),
+                    None => CompiledAutomaton::of_type(AutomatonType::Empty, None),
+                });
+            }
+            &minimal
+        } else {
+            automaton
+        };
+
+        let automaton = Operations::remove_dead_states(automaton);
+        let finite = finite.unwrap_or_else(|| Operations::is_finite(&automaton));
+        let binary = if is_binary {
+            automaton
+        } else {
+            Operations::remove_dead_states(&UTF32ToUTF8::convert(&automaton))
+        };
+        let run_automaton = ByteRunAutomaton::new(&binary, true, max_determinized_states)?;
+        let sink_state = find_sink_state(run_automaton.automaton());
+
+        Ok(CompiledAutomaton {
+            automaton_type: AutomatonType::Normal,
+            term: None,
+            run_automaton: Some(Arc::new(run_automaton)),
+            finite,
+            sink_state,
+        })
+    }
+
+    fn of_type(automaton_type: AutomatonType, term: Option<Vec<u8>>) -> CompiledAutomaton {
+        CompiledAutomaton {
+            automaton_type,
+            term,
+            run_automaton: None,
+            finite: true,
+            sink_state: None,
+        }
+    }
+
+    /// Return a `TermIterator` intersecting the provided `Terms` with the
+    /// terms accepted by this automaton, `None` if no term can be accepted.
+    pub fn term_iterator<T: Terms>(
+        &self,
+        terms: &T,
+    ) -> Result<Option<AutomatonTermIterEnum<T::Iterator>>> {
+        let iter = match self.automaton_type {
+            AutomatonType::Empty => None,
+            AutomatonType::All => Some(AutomatonTermIterEnum::All(terms.iterator()?)),
+            AutomatonType::Single => Some(AutomatonTermIterEnum::Single(SingleTermIterator::new(
+                terms.iterator()?,
+                self.term.clone().unwrap(),
+            ))),
+            AutomatonType::Normal => Some(terms.intersect(self, None)?),
+        };
+        Ok(iter)
+    }
+}
+
+fn find_sink_state(automaton: &Automaton) -> Option<usize> {
+    (0..automaton.num_states()).find(|s| {
+        automaton.is_accept(*s)
+            && automaton
+                .transitions(*s)
+                .iter()
+                .any(|t| t.dest == *s && t.min == 0 && t.max == MAX_BYTE_LABEL)
+    })
+}
@@ -0,0 +1,199 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::{Automaton, Operations, MAX_CODE_POINT};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Maximum edit distance supported by `LevenshteinAutomata`.
+pub const MAXIMUM_SUPPORTED_DISTANCE: usize = 2;
+
+/// Class to construct DFAs that match a word within some edit distance.
+///
+/// Edits are insertions, deletions, substitutions of one code point and,
+/// if `with_transpositions` is true, transpositions of two adjacent code
+/// points (the Damerau-Levenshtein distance).
+///
+/// The automaton is first built as an NFA with one state per pair of
+/// (position in the word, number of edits used) and then determinized; the
+/// resulting DFA has a number of states linear in the word length for a fixed
+/// edit distance.
+pub struct LevenshteinAutomata {
+    word: Vec<u32>,
+    with_transpositions: bool,
+}
+
+impl LevenshteinAutomata {
+    /// Create a new `LevenshteinAutomata` for some input string.
+    pub fn new(input: &str, with_transpositions: bool) -> LevenshteinAutomata {
+        LevenshteinAutomata {
+            word: input.chars().map(|c| c as u32).collect(),
+            with_transpositions,
+        }
+    }
+
+    /// Compute a DFA that accepts all strings within an edit distance of `n`.
+    pub fn to_automaton(&self, n: usize) -> Result<Automaton> {
+        self.to_automaton_with_prefix(n, "")
+    }
+
+    /// Compute a DFA that accepts all strings starting with `prefix` whose
+    /// remaining part is within an edit distance of `n` of the input string.
+    pub fn to_automaton_with_prefix(&self, n: usize, prefix: &str) -> Result<Automaton> {
+        if n > MAXIMUM_SUPPORTED_DISTANCE {
+            bail!(IllegalArgument(format!(
+                "max edits must be <= {}, got {}",
+                MAXIMUM_SUPPORTED_DISTANCE, n
+            )));
+        }
+
+        let mut a = Automaton::new();
+        // the prefix must match exactly
+        let mut last = a.create_state();
+        for c in prefix.chars() {
+            let state = a.create_state();
+            a.add_transition(last, state, c as u32, c as u32);
+            last = state;
+        }
+
+        // state for `i` code points of the word consumed with `e` edits
+        let len = self.word.len();
+        let offset = last;
+        let state = |i: usize, e: usize| offset + e * (len + 1) + i;
+        for _ in 1..(len + 1) * (n + 1) {
+            a.create_state();
+        }
+
+        for e in 0..=n {
+            for i in 0..=len {
+                let source = state(i, e);
+                if i == len {
+                    a.set_accept(source, true);
+                } else {
+                    let c = self.word[i];
+                    a.add_transition(source, state(i + 1, e), c, c);
+                }
+                if e == n {
+                    continue;
+                }
+                // insertion
+                a.add_transition(source, state(i, e + 1), 0, MAX_CODE_POINT);
+                if i < len {
+                    // substitution
+                    a.add_transition(source, state(i + 1, e + 1), 0, MAX_CODE_POINT);
+                    // deletion
+                    a.add_epsilon(source, state(i + 1, e + 1));
+                }
+                if self.with_transpositions && i + 1 < len && self.word[i] != self.word[i + 1] {
+                    let swapped = a.create_state();
+                    a.add_transition(source, swapped, self.word[i + 1], self.word[i + 1]);
+                    a.add_transition(swapped, state(i + 2, e + 1), self.word[i], self.word[i]);
+                }
+            }
+        }
+        a.finish();
+
+        Operations::determinize(&a, usize::max_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &[char], b: &[char], with_transpositions: bool) -> usize {
+        let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for (i, row) in d.iter_mut().enumerate() {
+            row[0] = i;
+        }
+        for (j, v) in d[0].iter_mut().enumerate() {
+            *v = j;
+        }
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+                let mut v = (d[i - 1][j] + 1)
+                    .min(d[i][j - 1] + 1)
+                    .min(d[i - 1][j - 1] + cost);
+                if with_transpositions
+                    && i > 1
+                    && j > 1
+                    && a[i - 1] == b[j - 2]
+                    && a[i - 2] == b[j - 1]
+                {
+                    v = v.min(d[i - 2][j - 2] + 1);
+                }
+                d[i][j] = v;
+            }
+        }
+        d[a.len()][b.len()]
+    }
+
+    // all the strings over `alphabet` up to `max_len` chars
+    fn strings(alphabet: &[char], max_len: usize) -> Vec<Vec<char>> {
+        let mut result = vec![vec![]];
+        let mut start = 0;
+        for _ in 0..max_len {
+            let end = result.len();
+            for i in start..end {
+                for c in alphabet {
+                    let mut s = result[i].clone();
+                    s.push(*c);
+                    result.push(s);
+                }
+            }
+            start = end;
+        }
+        result
+    }
+
+    #[test]
+    fn test_against_edit_distance() {
+        let alphabet = ['a', 'b', 'c'];
+        let candidates = strings(&alphabet, 5);
+        for word in &["", "a", "ab", "abc", "abca", "aab"] {
+            let chars: Vec<char> = word.chars().collect();
+            for &with_transpositions in &[false, true] {
+                let builder = LevenshteinAutomata::new(word, with_transpositions);
+                for n in 0..=MAXIMUM_SUPPORTED_DISTANCE {
+                    let a = builder.to_automaton(n).unwrap();
+                    assert!(a.is_deterministic());
+                    for s in &candidates {
+                        let text: String = s.iter().collect();
+                        assert_eq!(
+                            Operations::run_str(&a, &text),
+                            distance(&chars, s, with_transpositions) <= n,
+                            "word: {}, text: {}, n: {}",
+                            word,
+                            text,
+                            n
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix() {
+        let builder = LevenshteinAutomata::new("bc", true);
+        let a = builder.to_automaton_with_prefix(1, "a").unwrap();
+        assert!(Operations::run_str(&a, "abc"));
+        assert!(Operations::run_str(&a, "acb"));
+        assert!(Operations::run_str(&a, "ab"));
+        assert!(Operations::run_str(&a, "abcd"));
+        assert!(!Operations::run_str(&a, "bc"));
+        assert!(!Operations::run_str(&a, "xbc"));
+        assert!(builder.to_automaton(3).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod automaton;
+
+pub use self::automaton::*;
+
+mod automata;
+
+pub use self::automata::*;
+
+mod operations;
+
+pub use self::operations::*;
+
+mod utf32_to_utf8;
+
+pub use self::utf32_to_utf8::*;
+
+mod run_automaton;
+
+pub use self::run_automaton::*;
+
+mod levenshtein_automata;
+
+pub use self::levenshtein_automata::*;
+
+mod regexp;
+
+pub use self::regexp::*;
+
+mod compiled_automaton;
+
+pub use self::compiled_automaton::*;
+
+mod automaton_term_iterator;
+
+pub use self::automaton_term_iterator::*;
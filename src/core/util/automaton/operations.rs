@@ -0,0 +1,776 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::{max, min};
+use std::collections::{HashMap, VecDeque};
+
+use core::util::automaton::{Automata, Automaton, MAX_CODE_POINT};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Automata operations.
+///
+/// Operations building a new language out of other ones (`concatenate`, `union`,
+/// `optional` and `repeat*`) produce non-deterministic automata with epsilon
+/// transitions, call `determinize` or `minimize` on the result before running
+/// it against terms.
+pub struct Operations;
+
+impl Operations {
+    /// Returns an automaton that accepts the concatenation of the languages of
+    /// the given automata.
+    pub fn concatenate(automata: &[&Automaton]) -> Automaton {
+        if automata.is_empty() {
+            return Automata::make_empty_string();
+        }
+        if automata.iter().any(|a| a.num_states() == 0) {
+            return Automata::make_empty();
+        }
+
+        let mut result = Automaton::new();
+        let start = result.create_state();
+        let mut offsets = Vec::with_capacity(automata.len());
+        for a in automata {
+            offsets.push(result.copy(a));
+        }
+        result.add_epsilon(start, offsets[0]);
+        for i in 0..automata.len() - 1 {
+            let a = automata[i];
+            for s in 0..a.num_states() {
+                if a.is_accept(s) {
+                    result.set_accept(offsets[i] + s, false);
+                    result.add_epsilon(offsets[i] + s, offsets[i + 1]);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns an automaton that accepts the union of the languages of the
+    /// given automata.
+    pub fn union(automata: &[&Automaton]) -> Automaton {
+        let mut result = Automaton::new();
+        let start = result.create_state();
+        for a in automata {
+            if a.num_states() > 0 {
+                let offset = result.copy(a);
+                result.add_epsilon(start, offset);
+            }
+        }
+        result
+    }
+
+    /// Returns an automaton that accepts the union of the empty string and the
+    /// language of the given automaton.
+    pub fn optional(a: &Automaton) -> Automaton {
+        let mut result = Automaton::new();
+        let start = result.create_state();
+        result.set_accept(start, true);
+        if a.num_states() > 0 {
+            let offset = result.copy(a);
+            result.add_epsilon(start, offset);
+        }
+        result
+    }
+
+    /// Returns an automaton that accepts the Kleene star (zero or more
+    /// concatenated repetitions) of the language of the given automaton.
+    pub fn repeat(a: &Automaton) -> Automaton {
+        let mut result = Automaton::new();
+        let start = result.create_state();
+        result.set_accept(start, true);
+        if a.num_states() > 0 {
+            let offset = result.copy(a);
+            result.add_epsilon(start, offset);
+            for s in 0..a.num_states() {
+                if a.is_accept(s) {
+                    result.add_epsilon(offset + s, start);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns an automaton that accepts `min` or more concatenated
+    /// repetitions of the language of the given automaton.
+    pub fn repeat_min(a: &Automaton, min: usize) -> Automaton {
+        if min == 0 {
+            return Operations::repeat(a);
+        }
+        let star = Operations::repeat(a);
+        let mut parts: Vec<&Automaton> = Vec::with_capacity(min + 1);
+        for _ in 0..min {
+            parts.push(a);
+        }
+        parts.push(&star);
+        Operations::concatenate(&parts)
+    }
+
+    /// Returns an automaton that accepts between `min` and `max` (including
+    /// both) concatenated repetitions of the language of the given automaton.
+    pub fn repeat_range(a: &Automaton, min: usize, max: usize) -> Automaton {
+        if min > max {
+            return Automata::make_empty();
+        }
+        let opt = Operations::optional(a);
+        let mut parts: Vec<&Automaton> = Vec::with_capacity(max);
+        for _ in 0..min {
+            parts.push(a);
+        }
+        for _ in min..max {
+            parts.push(&opt);
+        }
+        Operations::concatenate(&parts)
+    }
+
+    /// Returns a (deterministic) automaton that accepts the complement of the
+    /// language of the given automaton.
+    ///
+    /// Complexity: linear in number of states if already deterministic and
+    /// exponential otherwise.
+    pub fn complement(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+        let mut a = Operations::totalize(
+            &Operations::determinize(a, max_determinized_states)?,
+            MAX_CODE_POINT,
+        );
+        for s in 0..a.num_states() {
+            let accept = a.is_accept(s);
+            a.set_accept(s, !accept);
+        }
+        Ok(Operations::remove_dead_states(&a))
+    }
+
+    /// Returns a (deterministic) automaton that accepts the intersection of
+    /// the language of `a1` and the complement of the language of `a2`.
+    pub fn minus(
+        a1: &Automaton,
+        a2: &Automaton,
+        max_determinized_states: usize,
+    ) -> Result<Automaton> {
+        if Operations::is_empty(a1) {
+            return Ok(Automata::make_empty());
+        }
+        if Operations::is_empty(a2) {
+            return Operations::determinize(a1, max_determinized_states);
+        }
+        let complement = Operations::complement(a2, max_determinized_states)?;
+        Operations::intersection(a1, &complement, max_determinized_states)
+    }
+
+    /// Returns a (deterministic) automaton that accepts the intersection of
+    /// the languages of the given automata.
+    pub fn intersection(
+        a1: &Automaton,
+        a2: &Automaton,
+        max_determinized_states: usize,
+    ) -> Result<Automaton> {
+        let a1 = Operations::determinize(a1, max_determinized_states)?;
+        let a2 = Operations::determinize(a2, max_determinized_states)?;
+
+        let mut result = Automaton::new();
+        let mut pairs: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut worklist: VecDeque<(usize, usize, usize)> = VecDeque::new();
+
+        let start = result.create_state();
+        result.set_accept(start, a1.is_accept(0) && a2.is_accept(0));
+        pairs.insert((0, 0), start);
+        worklist.push_back((0, 0, start));
+
+        while let Some((s1, s2, source)) = worklist.pop_front() {
+            for t1 in a1.transitions(s1) {
+                for t2 in a2.transitions(s2) {
+                    let lo = max(t1.min, t2.min);
+                    let hi = min(t1.max, t2.max);
+                    if lo > hi {
+                        continue;
+                    }
+                    let key = (t1.dest, t2.dest);
+                    let dest = if let Some(d) = pairs.get(&key) {
+                        *d
+                    } else {
+                        let d = result.create_state();
+                        result.set_accept(d, a1.is_accept(t1.dest) && a2.is_accept(t2.dest));
+                        pairs.insert(key, d);
+                        worklist.push_back((t1.dest, t2.dest, d));
+                        d
+                    };
+                    result.add_transition(source, dest, lo, hi);
+                }
+            }
+        }
+        result.finish();
+        Ok(Operations::remove_dead_states(&result))
+    }
+
+    /// Determinizes the given automaton using the subset construction.
+    ///
+    /// Worst case complexity: exponential in number of states, an error is
+    /// returned if more than `max_determinized_states` would be created.
+    pub fn determinize(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+        if a.num_states() == 0 {
+            return Ok(Automata::make_empty());
+        }
+        if a.is_deterministic() && !a.has_epsilons() {
+            let mut result = a.clone();
+            result.finish();
+            return Ok(result);
+        }
+
+        let mut result = Automaton::new();
+        let mut sets: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut worklist: VecDeque<(Vec<usize>, usize)> = VecDeque::new();
+
+        let initial = a.epsilon_closure(&[0]);
+        let start = result.create_state();
+        result.set_accept(start, initial.iter().any(|s| a.is_accept(*s)));
+        sets.insert(initial.clone(), start);
+        worklist.push_back((initial, start));
+
+        let mut points: Vec<u32> = Vec::new();
+        let mut dests: Vec<usize> = Vec::new();
+        while let Some((set, source)) = worklist.pop_front() {
+            points.clear();
+            for s in &set {
+                for t in a.transitions(*s) {
+                    points.push(t.min);
+                    points.push(t.max + 1);
+                }
+            }
+            points.sort();
+            points.dedup();
+
+            // every interval between two adjacent points is either fully
+            // covered by a transition or not covered at all
+            for i in 1..points.len() {
+                let lo = points[i - 1];
+                let hi = points[i] - 1;
+                dests.clear();
+                for s in &set {
+                    for t in a.transitions(*s) {
+                        if t.min <= lo && hi <= t.max {
+                            dests.push(t.dest);
+                        }
+                    }
+                }
+                if dests.is_empty() {
+                    continue;
+                }
+
+                let closure = a.epsilon_closure(&dests);
+                let dest = if let Some(d) = sets.get(&closure) {
+                    *d
+                } else {
+                    if result.num_states() >= max_determinized_states {
+                        bail!(IllegalArgument(format!(
+                            "Determinizing automaton would result in more than {} states.",
+                            max_determinized_states
+                        )));
+                    }
+                    let d = result.create_state();
+                    result.set_accept(d, closure.iter().any(|s| a.is_accept(*s)));
+                    sets.insert(closure.clone(), d);
+                    worklist.push_back((closure, d));
+                    d
+                };
+                result.add_transition(source, dest, lo, hi);
+            }
+        }
+
+        result.finish();
+        result.set_deterministic(true);
+        Ok(result)
+    }
+
+    /// Minimizes (and determinizes if not already deterministic) the given
+    /// automaton by partition refinement over the label intervals.
+    ///
+    /// The returned automaton has no dead states.
+    pub fn minimize(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+        let a =
+            Operations::remove_dead_states(&Operations::determinize(a, max_determinized_states)?);
+        let n = a.num_states();
+        if n <= 1 {
+            return Ok(a);
+        }
+
+        // totalize with an explicit dead state at index `n`
+        let points = a.start_points();
+        let k = points.len();
+        let dead = n;
+        let mut table = vec![dead; (n + 1) * k];
+        for s in 0..n {
+            for (j, p) in points.iter().enumerate() {
+                if let Some(d) = a.step(s, *p) {
+                    table[s * k + j] = d;
+                }
+            }
+        }
+
+        let mut block: Vec<usize> = (0..=n)
+            .map(|s| if s < n && a.is_accept(s) { 1 } else { 0 })
+            .collect();
+        let mut num_blocks = 0usize;
+        loop {
+            let mut signatures: HashMap<Vec<usize>, usize> = HashMap::new();
+            let mut new_block = vec![0usize; n + 1];
+            for s in 0..=n {
+                let mut signature = Vec::with_capacity(k + 1);
+                signature.push(block[s]);
+                for j in 0..k {
+                    signature.push(block[table[s * k + j]]);
+                }
+                let next_id = signatures.len();
+                new_block[s] = *signatures.entry(signature).or_insert(next_id);
+            }
+            let count = signatures.len();
+            block = new_block;
+            if count == num_blocks {
+                break;
+            }
+            num_blocks = count;
+        }
+
+        let unset = usize::max_value();
+        let mut representatives = vec![unset; num_blocks];
+        for s in 0..=n {
+            if representatives[block[s]] == unset {
+                representatives[block[s]] = s;
+            }
+        }
+
+        let mut result = Automaton::new();
+        let mut block_states = vec![unset; num_blocks];
+        block_states[block[0]] = result.create_state();
+        for state in block_states.iter_mut() {
+            if *state == unset {
+                *state = result.create_state();
+            }
+        }
+        for b in 0..num_blocks {
+            let s = representatives[b];
+            let source = block_states[b];
+            if s < n && a.is_accept(s) {
+                result.set_accept(source, true);
+            }
+            // the interval starting at the last point is never covered
+            for j in 0..k - 1 {
+                let dest = block_states[block[table[s * k + j]]];
+                result.add_transition(source, dest, points[j], points[j + 1] - 1);
+            }
+        }
+        result.finish();
+        result.set_deterministic(true);
+        Ok(Operations::remove_dead_states(&result))
+    }
+
+    /// Returns a new automaton without the states that are unreachable from
+    /// the initial state or that can not reach an accept state. The initial
+    /// state is always kept as state 0.
+    pub fn remove_dead_states(a: &Automaton) -> Automaton {
+        let n = a.num_states();
+        if n == 0 {
+            return Automata::make_empty();
+        }
+
+        let mut reachable = vec![false; n];
+        let mut stack = vec![0usize];
+        reachable[0] = true;
+        while let Some(s) = stack.pop() {
+            for t in a.transitions(s) {
+                if !reachable[t.dest] {
+                    reachable[t.dest] = true;
+                    stack.push(t.dest);
+                }
+            }
+            for e in a.epsilons(s) {
+                if !reachable[*e] {
+                    reachable[*e] = true;
+                    stack.push(*e);
+                }
+            }
+        }
+
+        let mut reverse: Vec<Vec<usize>> = vec![vec![]; n];
+        for s in 0..n {
+            for t in a.transitions(s) {
+                reverse[t.dest].push(s);
+            }
+            for e in a.epsilons(s) {
+                reverse[*e].push(s);
+            }
+        }
+        let mut live = vec![false; n];
+        for s in 0..n {
+            if reachable[s] && a.is_accept(s) {
+                live[s] = true;
+                stack.push(s);
+            }
+        }
+        while let Some(s) = stack.pop() {
+            for p in &reverse[s] {
+                if reachable[*p] && !live[*p] {
+                    live[*p] = true;
+                    stack.push(*p);
+                }
+            }
+        }
+
+        let mut result = Automaton::new();
+        let mut map: Vec<Option<usize>> = vec![None; n];
+        map[0] = Some(result.create_state());
+        for s in 1..n {
+            if live[s] {
+                map[s] = Some(result.create_state());
+            }
+        }
+        for s in 0..n {
+            if let Some(source) = map[s] {
+                result.set_accept(source, a.is_accept(s));
+                for t in a.transitions(s) {
+                    if live[t.dest] {
+                        result.add_transition(source, map[t.dest].unwrap(), t.min, t.max);
+                    }
+                }
+                for e in a.epsilons(s) {
+                    if live[*e] {
+                        result.add_epsilon(source, map[*e].unwrap());
+                    }
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns a copy of the (deterministic) automaton with a transition for
+    /// every label in `[0, max_label]` from every state, the missing ones
+    /// lead to a new non-accepting sink state.
+    pub fn totalize(a: &Automaton, max_label: u32) -> Automaton {
+        let mut result = a.clone();
+        if result.num_states() == 0 {
+            result.create_state();
+        }
+        let dead = result.create_state();
+        result.add_transition(dead, dead, 0, max_label);
+        for s in 0..a.num_states() {
+            let mut next_min = 0u32;
+            for t in a.transitions(s) {
+                if t.min > next_min {
+                    result.add_transition(s, dead, next_min, t.min - 1);
+                }
+                if t.max + 1 > next_min {
+                    next_min = t.max + 1;
+                }
+            }
+            if next_min <= max_label {
+                result.add_transition(s, dead, next_min, max_label);
+            }
+        }
+        result.finish();
+        result
+    }
+
+    /// Returns true if the given automaton accepts no strings.
+    pub fn is_empty(a: &Automaton) -> bool {
+        if a.num_states() == 0 {
+            return true;
+        }
+        let mut seen = vec![false; a.num_states()];
+        let mut stack = vec![0usize];
+        seen[0] = true;
+        while let Some(s) = stack.pop() {
+            if a.is_accept(s) {
+                return false;
+            }
+            for t in a.transitions(s) {
+                if !seen[t.dest] {
+                    seen[t.dest] = true;
+                    stack.push(t.dest);
+                }
+            }
+            for e in a.epsilons(s) {
+                if !seen[*e] {
+                    seen[*e] = true;
+                    stack.push(*e);
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns true if the given (minimal) automaton accepts all strings over
+    /// the labels `[min_label, max_label]`.
+    pub fn is_total(a: &Automaton, min_label: u32, max_label: u32) -> bool {
+        if a.num_states() == 1 && a.is_accept(0) && a.num_transitions(0) == 1 {
+            let t = &a.transitions(0)[0];
+            t.dest == 0 && t.min == min_label && t.max == max_label
+        } else {
+            false
+        }
+    }
+
+    /// Returns true if the language of this automaton is finite. The
+    /// automaton must not have dead states.
+    pub fn is_finite(a: &Automaton) -> bool {
+        let n = a.num_states();
+        if n == 0 {
+            return true;
+        }
+        // 0: unvisited, 1: on stack, 2: done
+        let mut color = vec![0u8; n];
+        let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+        color[0] = 1;
+        while let Some(&mut (s, ref mut idx)) = stack.last_mut() {
+            let num_transitions = a.num_transitions(s);
+            let num_edges = num_transitions + a.epsilons(s).len();
+            if *idx < num_edges {
+                let dest = if *idx < num_transitions {
+                    a.transitions(s)[*idx].dest
+                } else {
+                    a.epsilons(s)[*idx - num_transitions]
+                };
+                *idx += 1;
+                match color[dest] {
+                    0 => {
+                        color[dest] = 1;
+                        stack.push((dest, 0));
+                    }
+                    1 => return false,
+                    _ => {}
+                }
+            } else {
+                color[s] = 2;
+                stack.pop();
+            }
+        }
+        true
+    }
+
+    /// If this (deterministic, without dead states) automaton accepts a single
+    /// input, return it, otherwise return `None`.
+    pub fn get_singleton(a: &Automaton) -> Option<Vec<u32>> {
+        if !a.is_deterministic() || a.num_states() == 0 {
+            return None;
+        }
+        let mut labels = Vec::new();
+        let mut visited = vec![false; a.num_states()];
+        let mut s = 0usize;
+        loop {
+            if visited[s] {
+                return None;
+            }
+            visited[s] = true;
+            if a.is_accept(s) {
+                return if a.num_transitions(s) == 0 {
+                    Some(labels)
+                } else {
+                    None
+                };
+            }
+            let transitions = a.transitions(s);
+            if transitions.len() != 1 || transitions[0].min != transitions[0].max {
+                return None;
+            }
+            labels.push(transitions[0].min);
+            s = transitions[0].dest;
+        }
+    }
+
+    /// Returns the longest sequence of labels that all accepted inputs of
+    /// this (deterministic) automaton start with.
+    pub fn get_common_prefix(a: &Automaton) -> Vec<u32> {
+        let mut prefix = Vec::new();
+        if a.num_states() == 0 {
+            return prefix;
+        }
+        let mut visited = vec![false; a.num_states()];
+        let mut s = 0usize;
+        while !visited[s] && !a.is_accept(s) {
+            visited[s] = true;
+            let transitions = a.transitions(s);
+            if transitions.len() == 1 && transitions[0].min == transitions[0].max {
+                prefix.push(transitions[0].min);
+                s = transitions[0].dest;
+            } else {
+                break;
+            }
+        }
+        prefix
+    }
+
+    /// Returns true if the given sequence of labels is accepted by the
+    /// automaton, which need not be deterministic.
+    pub fn run(a: &Automaton, labels: &[u32]) -> bool {
+        if a.num_states() == 0 {
+            return false;
+        }
+        let mut current = a.epsilon_closure(&[0]);
+        let mut next = Vec::new();
+        for label in labels {
+            next.clear();
+            for s in &current {
+                for t in a.transitions(*s) {
+                    if t.contains(*label) {
+                        next.push(t.dest);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return false;
+            }
+            current = a.epsilon_closure(&next);
+        }
+        current.iter().any(|s| a.is_accept(*s))
+    }
+
+    /// Returns true if the given string is accepted by the (character) automaton.
+    pub fn run_str(a: &Automaton, s: &str) -> bool {
+        let labels: Vec<u32> = s.chars().map(|c| c as u32).collect();
+        Operations::run(a, &labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+
+    // the strings over `alphabet` of at most `max_len` chars accepted by `a`
+    fn language(a: &Automaton, alphabet: &str, max_len: usize) -> Vec<String> {
+        let mut accepted = vec![];
+        let mut strings = vec![String::new()];
+        for len in 0..=max_len {
+            for s in &strings {
+                if Operations::run_str(a, s) {
+                    accepted.push(s.clone());
+                }
+            }
+            if len < max_len {
+                strings = strings
+                    .iter()
+                    .flat_map(|s| alphabet.chars().map(move |c| format!("{}{}", s, c)))
+                    .collect();
+            }
+        }
+        accepted
+    }
+
+    fn any_ab() -> Automaton {
+        Operations::union(&[
+            &Automata::make_char('a' as u32),
+            &Automata::make_char('b' as u32),
+        ])
+    }
+
+    // (a|b)*abb, non deterministic
+    fn ends_with_abb() -> Automaton {
+        Operations::concatenate(&[
+            &Operations::repeat(&any_ab()),
+            &Automata::make_string("abb"),
+        ])
+    }
+
+    // (a|b)*a(a|b){n}, the minimal deterministic automaton has 2^(n+1) states
+    fn nth_from_last_is_a(n: usize) -> Automaton {
+        Operations::concatenate(&[
+            &Operations::repeat(&any_ab()),
+            &Automata::make_char('a' as u32),
+            &Operations::repeat_range(&any_ab(), n, n),
+        ])
+    }
+
+    #[test]
+    fn test_concatenate_and_union() {
+        let ab_b = Operations::concatenate(&[
+            &Automata::make_string("ab"),
+            &Operations::repeat(&Automata::make_char('b' as u32)),
+        ]);
+        assert_eq!(language(&ab_b, "abc", 4), vec!["ab", "abb", "abbb"]);
+
+        let union = Operations::union(&[
+            &Automata::make_string("a"),
+            &ab_b,
+            &Automata::make_empty_string(),
+            &Automata::make_empty(),
+        ]);
+        assert_eq!(
+            language(&union, "abc", 4),
+            vec!["", "a", "ab", "abb", "abbb"]
+        );
+
+        let empty_string = Operations::concatenate(&[]);
+        assert_eq!(language(&empty_string, "ab", 2), vec![""]);
+        let empty = Operations::concatenate(&[&ab_b, &Automata::make_empty()]);
+        assert!(language(&empty, "ab", 4).is_empty());
+        assert!(language(&Operations::union(&[]), "ab", 2).is_empty());
+    }
+
+    #[test]
+    fn test_determinize_and_minimize() {
+        let automata = vec![
+            any_ab(),
+            ends_with_abb(),
+            Operations::union(&[&Automata::make_string("ab"), &Automata::make_string("ab")]),
+            Operations::repeat(&Operations::union(&[
+                &Automata::make_string("ab"),
+                &Automata::make_string("abc"),
+            ])),
+            nth_from_last_is_a(2),
+        ];
+        for a in &automata {
+            assert!(!a.is_deterministic() || a.has_epsilons());
+            let expected = language(a, "abc", 6);
+
+            let det = Operations::determinize(a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+            assert!(det.is_deterministic());
+            assert!(!det.has_epsilons());
+            assert_eq!(language(&det, "abc", 6), expected);
+
+            let min = Operations::minimize(a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+            assert!(min.is_deterministic());
+            assert!(min.num_states() <= det.num_states());
+            assert_eq!(language(&min, "abc", 6), expected);
+            // minimizing again changes nothing
+            let min_again = Operations::minimize(&min, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+            assert_eq!(min_again.num_states(), min.num_states());
+        }
+
+        // the classic minimal automata: a|b, (a|b)*abb, ab, (a|b)*a(a|b){2}
+        let sizes: Vec<usize> = automata
+            .iter()
+            .map(|a| {
+                Operations::minimize(a, DEFAULT_MAX_DETERMINIZED_STATES)
+                    .unwrap()
+                    .num_states()
+            })
+            .collect();
+        assert_eq!(sizes, vec![2, 4, 3, 3, 8]);
+    }
+
+    #[test]
+    fn test_state_explosion() {
+        let a = nth_from_last_is_a(8);
+
+        let error = Operations::determinize(&a, 100).err().unwrap().to_string();
+        assert!(error.contains("Determinizing automaton would result in more than 100 states"));
+        assert!(Operations::minimize(&a, 100).is_err());
+        assert!(Operations::complement(&a, 100).is_err());
+
+        let min = Operations::minimize(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert_eq!(min.num_states(), 512);
+        assert!(Operations::run_str(&min, "babbbbbbbb"));
+        assert!(!Operations::run_str(&min, "abbbbbbbbb"));
+        assert!(!Operations::run_str(&min, "abbbbbbb"));
+    }
+}
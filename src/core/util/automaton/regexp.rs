@@ -0,0 +1,521 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::util::automaton::{Automata, Automaton, Operations, MAX_CODE_POINT};
+
+use error::{Error, ErrorKind::IllegalArgument, Result};
+
+/// Syntax flags for `RegExp::with_flags`, enabling the optional syntax.
+pub struct RegExpFlags;
+
+impl RegExpFlags {
+    /// Syntax flag, enables intersection (`&`).
+    pub const INTERSECTION: u32 = 0x0001;
+    /// Syntax flag, enables complement (`~`).
+    pub const COMPLEMENT: u32 = 0x0002;
+    /// Syntax flag, enables empty language (`#`).
+    pub const EMPTY: u32 = 0x0004;
+    /// Syntax flag, enables anystring (`@`).
+    pub const ANYSTRING: u32 = 0x0010;
+    /// Syntax flag, enables all optional regexp syntax.
+    pub const ALL: u32 = 0xffff;
+    /// Syntax flag, enables no optional regexp syntax.
+    pub const NONE: u32 = 0x0000;
+}
+
+/// Regular Expression extension to `Automaton`, using the same syntax as
+/// Lucene's `RegExp`:
+///
+/// ```text
+/// regexp     ::= unionexp
+/// unionexp   ::= interexp | unionexp     (union)
+///             |  interexp
+/// interexp   ::= concatexp & interexp    (intersection)   [OPTIONAL]
+///             |  concatexp
+/// concatexp  ::= repeatexp concatexp     (concatenation)
+///             |  repeatexp
+/// repeatexp  ::= repeatexp ?             (zero or one occurrence)
+///             |  repeatexp *             (zero or more occurrences)
+///             |  repeatexp +             (one or more occurrences)
+///             |  repeatexp {n}           (n occurrences)
+///             |  repeatexp {n,}          (n or more occurrences)
+///             |  repeatexp {n,m}         (n to m occurrences, including both)
+///             |  complexp
+/// complexp   ::= ~ complexp              (complement)     [OPTIONAL]
+///             |  charclassexp
+/// charclassexp ::= [ charclasses ]       (character class)
+///             |  [^ charclasses ]        (negated character class)
+///             |  simpleexp
+/// charclasses ::= charclass charclasses
+///             |  charclass
+/// charclass  ::= charexp - charexp       (character range, including end-points)
+///             |  charexp
+/// simpleexp  ::= charexp
+///             |  .                       (any single character)
+///             |  #                       (the empty language)  [OPTIONAL]
+///             |  @                       (any string)          [OPTIONAL]
+///             |  " <Unicode string without double-quotes> "  (a string)
+///             |  ( )                     (the empty string)
+///             |  ( unionexp )            (precedence override)
+/// charexp    ::= <Unicode character>     (a single non-reserved character)
+///             |  \ <Unicode character>   (a single character)
+/// ```
+///
+/// The named automata (`<identifier>`) and numerical intervals (`<n-m>`) of
+/// the Lucene syntax are not supported, `<` and `>` are ordinary characters.
+pub struct RegExp {
+    original: String,
+    node: Node,
+}
+
+enum Node {
+    Union(Box<Node>, Box<Node>),
+    Concatenation(Box<Node>, Box<Node>),
+    Intersection(Box<Node>, Box<Node>),
+    Optional(Box<Node>),
+    Repeat(Box<Node>),
+    RepeatMin(Box<Node>, usize),
+    RepeatMinMax(Box<Node>, usize, usize),
+    Complement(Box<Node>),
+    Char(u32),
+    CharClass(Vec<(u32, u32)>, bool),
+    AnyChar,
+    Empty,
+    String(String),
+    AnyString,
+}
+
+impl RegExp {
+    /// Constructs new `RegExp` from a string, all optional syntax is enabled.
+    pub fn new(s: &str) -> Result<RegExp> {
+        RegExp::with_flags(s, RegExpFlags::ALL)
+    }
+
+    /// Constructs new `RegExp` from a string, `flags` enables the optional
+    /// regexp syntax.
+    pub fn with_flags(s: &str, flags: u32) -> Result<RegExp> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+            flags,
+        };
+        let node = if parser.chars.is_empty() {
+            Node::String(String::new())
+        } else {
+            let node = parser.parse_union()?;
+            if parser.more() {
+                return Err(parser.error("end-of-string expected"));
+            }
+            node
+        };
+        Ok(RegExp {
+            original: s.to_string(),
+            node,
+        })
+    }
+
+    /// The string this regexp was parsed from.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// Constructs a new (minimal, deterministic) `Automaton` from this `RegExp`.
+    pub fn to_automaton(&self, max_determinized_states: usize) -> Result<Automaton> {
+        let a = node_to_automaton(&self.node, max_determinized_states)?;
+        Operations::minimize(&a, max_determinized_states)
+    }
+}
+
+impl fmt::Display for RegExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.original)
+    }
+}
+
+fn node_to_automaton(node: &Node, max_determinized_states: usize) -> Result<Automaton> {
+    let a = match node {
+        Node::Union(..) => {
+            let mut children = Vec::new();
+            flatten_union(node, &mut children);
+            let automata = children
+                .into_iter()
+                .map(|n| node_to_automaton(n, max_determinized_states))
+                .collect::<Result<Vec<_>>>()?;
+            let refs: Vec<&Automaton> = automata.iter().collect();
+            Operations::union(&refs)
+        }
+        Node::Concatenation(..) => {
+            let mut children = Vec::new();
+            flatten_concatenation(node, &mut children);
+            let automata = children
+                .into_iter()
+                .map(|n| node_to_automaton(n, max_determinized_states))
+                .collect::<Result<Vec<_>>>()?;
+            let refs: Vec<&Automaton> = automata.iter().collect();
+            Operations::concatenate(&refs)
+        }
+        Node::Intersection(a, b) => Operations::intersection(
+            &node_to_automaton(a, max_determinized_states)?,
+            &node_to_automaton(b, max_determinized_states)?,
+            max_determinized_states,
+        )?,
+        Node::Optional(a) => Operations::optional(&node_to_automaton(a, max_determinized_states)?),
+        Node::Repeat(a) => Operations::repeat(&node_to_automaton(a, max_determinized_states)?),
+        Node::RepeatMin(a, min) => {
+            Operations::repeat_min(&node_to_automaton(a, max_determinized_states)?, *min)
+        }
+        Node::RepeatMinMax(a, min, max) => {
+            Operations::repeat_range(&node_to_automaton(a, max_determinized_states)?, *min, *max)
+        }
+        Node::Complement(a) => Operations::complement(
+            &node_to_automaton(a, max_determinized_states)?,
+            max_determinized_states,
+        )?,
+        Node::Char(c) => Automata::make_char(*c),
+        Node::CharClass(ranges, negated) => make_char_class(ranges, *negated),
+        Node::AnyChar => Automata::make_any_char(),
+        Node::Empty => Automata::make_empty(),
+        Node::String(s) => Automata::make_string(s),
+        Node::AnyString => Automata::make_any_string(),
+    };
+    Ok(a)
+}
+
+fn flatten_union<'a>(node: &'a Node, out: &mut Vec<&'a Node>) {
+    if let Node::Union(a, b) = node {
+        flatten_union(a, out);
+        flatten_union(b, out);
+    } else {
+        out.push(node);
+    }
+}
+
+fn flatten_concatenation<'a>(node: &'a Node, out: &mut Vec<&'a Node>) {
+    if let Node::Concatenation(a, b) = node {
+        flatten_concatenation(a, out);
+        flatten_concatenation(b, out);
+    } else {
+        out.push(node);
+    }
+}
+
+fn make_char_class(ranges: &[(u32, u32)], negated: bool) -> Automaton {
+    let mut ranges = ranges.to_vec();
+    ranges.sort();
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+    for (min, max) in ranges {
+        if let Some(last) = merged.last_mut() {
+            if min <= last.1.saturating_add(1) {
+                if max > last.1 {
+                    last.1 = max;
+                }
+                continue;
+            }
+        }
+        merged.push((min, max));
+    }
+    if negated {
+        let mut complement = Vec::with_capacity(merged.len() + 1);
+        let mut next = 0u32;
+        for (min, max) in merged {
+            if min > next {
+                complement.push((next, min - 1));
+            }
+            next = max + 1;
+        }
+        if next <= MAX_CODE_POINT {
+            complement.push((next, MAX_CODE_POINT));
+        }
+        merged = complement;
+    }
+
+    let mut a = Automaton::new();
+    let s1 = a.create_state();
+    let s2 = a.create_state();
+    a.set_accept(s2, true);
+    for (min, max) in merged {
+        a.add_transition(s1, s2, min, max);
+    }
+    a.finish();
+    a
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    flags: u32,
+}
+
+impl Parser {
+    fn error(&self, msg: &str) -> Error {
+        IllegalArgument(format!("{} at position {}", msg, self.pos)).into()
+    }
+
+    fn check(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    fn more(&self) -> bool {
+        self.pos < self.chars.len()
+    }
+
+    fn peek(&self, s: &str) -> bool {
+        self.more() && s.contains(self.chars[self.pos])
+    }
+
+    fn matches(&mut self, c: char) -> bool {
+        if self.more() && self.chars[self.pos] == c {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Result<char> {
+        if !self.more() {
+            return Err(self.error("unexpected end-of-string"));
+        }
+        self.pos += 1;
+        Ok(self.chars[self.pos - 1])
+    }
+
+    fn parse_union(&mut self) -> Result<Node> {
+        let e = self.parse_intersection()?;
+        if self.matches('|') {
+            Ok(Node::Union(Box::new(e), Box::new(self.parse_union()?)))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_intersection(&mut self) -> Result<Node> {
+        let e = self.parse_concatenation()?;
+        if self.check(RegExpFlags::INTERSECTION) && self.matches('&') {
+            Ok(Node::Intersection(
+                Box::new(e),
+                Box::new(self.parse_intersection()?),
+            ))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_concatenation(&mut self) -> Result<Node> {
+        let e = self.parse_repeat()?;
+        if self.more()
+            && !self.peek(")|")
+            && (!self.check(RegExpFlags::INTERSECTION) || !self.peek("&"))
+        {
+            Ok(Node::Concatenation(
+                Box::new(e),
+                Box::new(self.parse_concatenation()?),
+            ))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_repeat(&mut self) -> Result<Node> {
+        let mut e = self.parse_complement()?;
+        while self.peek("?*+{") {
+            if self.matches('?') {
+                e = Node::Optional(Box::new(e));
+            } else if self.matches('*') {
+                e = Node::Repeat(Box::new(e));
+            } else if self.matches('+') {
+                e = Node::RepeatMin(Box::new(e), 1);
+            } else if self.matches('{') {
+                let min = self.parse_integer()?;
+                let max = if self.matches(',') {
+                    if self.peek("0123456789") {
+                        Some(self.parse_integer()?)
+                    } else {
+                        None
+                    }
+                } else {
+                    Some(min)
+                };
+                if !self.matches('}') {
+                    return Err(self.error("expected '}'"));
+                }
+                e = match max {
+                    Some(max) => Node::RepeatMinMax(Box::new(e), min, max),
+                    None => Node::RepeatMin(Box::new(e), min),
+                };
+            }
+        }
+        Ok(e)
+    }
+
+    fn parse_integer(&mut self) -> Result<usize> {
+        let start = self.pos;
+        while self.peek("0123456789") {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("integer expected"));
+        }
+        let s: String = self.chars[start..self.pos].iter().collect();
+        s.parse::<usize>()
+            .map_err(|_| self.error("integer out of range"))
+    }
+
+    fn parse_complement(&mut self) -> Result<Node> {
+        if self.check(RegExpFlags::COMPLEMENT) && self.matches('~') {
+            Ok(Node::Complement(Box::new(self.parse_complement()?)))
+        } else {
+            self.parse_char_class_exp()
+        }
+    }
+
+    fn parse_char_class_exp(&mut self) -> Result<Node> {
+        if self.matches('[') {
+            let negated = self.matches('^');
+            let mut ranges = Vec::new();
+            loop {
+                let c = self.parse_char_exp()?;
+                if self.matches('-') {
+                    let d = self.parse_char_exp()?;
+                    if c > d {
+                        return Err(self.error("invalid character class"));
+                    }
+                    ranges.push((c, d));
+                } else {
+                    ranges.push((c, c));
+                }
+                if self.matches(']') {
+                    break;
+                }
+                if !self.more() {
+                    return Err(self.error("expected ']'"));
+                }
+            }
+            Ok(Node::CharClass(ranges, negated))
+        } else {
+            self.parse_simple()
+        }
+    }
+
+    fn parse_simple(&mut self) -> Result<Node> {
+        if self.matches('.') {
+            Ok(Node::AnyChar)
+        } else if self.check(RegExpFlags::EMPTY) && self.matches('#') {
+            Ok(Node::Empty)
+        } else if self.check(RegExpFlags::ANYSTRING) && self.matches('@') {
+            Ok(Node::AnyString)
+        } else if self.matches('"') {
+            let start = self.pos;
+            while self.more() && !self.peek("\"") {
+                self.pos += 1;
+            }
+            if !self.matches('"') {
+                return Err(self.error("expected '\"'"));
+            }
+            let s: String = self.chars[start..self.pos - 1].iter().collect();
+            Ok(Node::String(s))
+        } else if self.matches('(') {
+            if self.matches(')') {
+                return Ok(Node::String(String::new()));
+            }
+            let e = self.parse_union()?;
+            if !self.matches(')') {
+                return Err(self.error("expected ')'"));
+            }
+            Ok(e)
+        } else {
+            Ok(Node::Char(self.parse_char_exp()?))
+        }
+    }
+
+    fn parse_char_exp(&mut self) -> Result<u32> {
+        self.matches('\\');
+        Ok(self.next()? as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+
+    fn automaton(s: &str) -> Automaton {
+        RegExp::new(s)
+            .unwrap()
+            .to_automaton(DEFAULT_MAX_DETERMINIZED_STATES)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_basic_syntax() {
+        let a = automaton("ab(c|d)*e?");
+        assert!(a.is_deterministic());
+        assert!(Operations::run_str(&a, "ab"));
+        assert!(Operations::run_str(&a, "abcdce"));
+        assert!(!Operations::run_str(&a, "abee"));
+        assert!(!Operations::run_str(&a, "a"));
+
+        let a = automaton("[a-c][^a-c]{2,3}\\.");
+        assert!(Operations::run_str(&a, "bxy."));
+        assert!(Operations::run_str(&a, "cxyz."));
+        assert!(!Operations::run_str(&a, "cay."));
+        assert!(!Operations::run_str(&a, "bx."));
+        assert!(!Operations::run_str(&a, "bxyza."));
+
+        let a = automaton("\"a.b\"|x+");
+        assert!(Operations::run_str(&a, "a.b"));
+        assert!(!Operations::run_str(&a, "acb"));
+        assert!(Operations::run_str(&a, "xxx"));
+        assert!(!Operations::run_str(&a, ""));
+    }
+
+    #[test]
+    fn test_optional_syntax() {
+        let a = automaton("@&~(foo.*)");
+        assert!(Operations::run_str(&a, "bar"));
+        assert!(Operations::run_str(&a, "fo"));
+        assert!(!Operations::run_str(&a, "foobar"));
+
+        let a = automaton("#");
+        assert!(Operations::is_empty(&a));
+
+        let a = RegExp::with_flags("a@", RegExpFlags::NONE)
+            .unwrap()
+            .to_automaton(DEFAULT_MAX_DETERMINIZED_STATES)
+            .unwrap();
+        assert!(Operations::run_str(&a, "a@"));
+    }
+
+    #[test]
+    fn test_minimal() {
+        let a = automaton("(a|b)*abb|(a|b)*abb");
+        assert_eq!(a.num_states(), 4);
+        assert!(Operations::run_str(&a, "babb"));
+
+        let a = automaton(".*");
+        assert!(Operations::is_total(&a, 0, MAX_CODE_POINT));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(RegExp::new("(ab").is_err());
+        assert!(RegExp::new("[ab").is_err());
+        assert!(RegExp::new("a{2").is_err());
+        assert!(RegExp::new("ab)").is_err());
+        assert!(RegExp::new("[z-a]").is_err());
+        assert!(RegExp::new("a\\").is_err());
+    }
+}
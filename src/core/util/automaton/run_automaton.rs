@@ -0,0 +1,197 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::min;
+use std::ops::Deref;
+
+use core::util::automaton::{Automaton, Operations, UTF32ToUTF8, MAX_BYTE_LABEL, MAX_CODE_POINT};
+
+use error::Result;
+
+/// Finite-state automaton with fast run operation, the transitions are
+/// stored in a table indexed by state and label interval.
+///
+/// The state numbering is the same as the one of the determinized automaton
+/// returned by `automaton()`.
+pub struct RunAutomaton {
+    automaton: Automaton,
+    max_label: u32,
+    size: usize,
+    accept: Vec<bool>,
+    // `points.len()` entries per state, -1 for the dead state
+    transitions: Vec<i32>,
+    points: Vec<u32>,
+    // map from label to interval index for labels < 256
+    classmap: Vec<usize>,
+}
+
+impl RunAutomaton {
+    pub fn new(a: &Automaton, max_label: u32, max_determinized_states: usize) -> Result<Self> {
+        let automaton = Operations::determinize(a, max_determinized_states)?;
+        let points = automaton.start_points();
+        let size = automaton.num_states().max(1);
+        let mut accept = vec![false; size];
+        let mut transitions = vec![-1i32; size * points.len()];
+        for s in 0..automaton.num_states() {
+            accept[s] = automaton.is_accept(s);
+            for (j, p) in points.iter().enumerate() {
+                if let Some(dest) = automaton.step(s, *p) {
+                    transitions[s * points.len() + j] = dest as i32;
+                }
+            }
+        }
+
+        let classmap_len = min(256, max_label as usize + 1);
+        let mut classmap = vec![0usize; classmap_len];
+        let mut i = 0usize;
+        for (label, class) in classmap.iter_mut().enumerate() {
+            if i + 1 < points.len() && label as u32 == points[i + 1] {
+                i += 1;
+            }
+            *class = i;
+        }
+
+        Ok(RunAutomaton {
+            automaton,
+            max_label,
+            size,
+            accept,
+            transitions,
+            points,
+            classmap,
+        })
+    }
+
+    /// Returns the (deterministic) automaton this was built from.
+    pub fn automaton(&self) -> &Automaton {
+        &self.automaton
+    }
+
+    /// Returns number of states in automaton.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn max_label(&self) -> u32 {
+        self.max_label
+    }
+
+    /// Returns acceptance status for given state.
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accept[state]
+    }
+
+    /// Returns array of codepoint class interval start points.
+    pub fn char_intervals(&self) -> &[u32] {
+        &self.points
+    }
+
+    /// Returns the state obtained by reading the given label from the given
+    /// state, `None` for the dead state.
+    #[inline]
+    pub fn step(&self, state: usize, label: u32) -> Option<usize> {
+        let class = if (label as usize) < self.classmap.len() {
+            self.classmap[label as usize]
+        } else {
+            self.char_class(label)
+        };
+        let dest = self.transitions[state * self.points.len() + class];
+        if dest < 0 {
+            None
+        } else {
+            Some(dest as usize)
+        }
+    }
+
+    fn char_class(&self, label: u32) -> usize {
+        // the largest index with `points[index] <= label`
+        match self.points.binary_search(&label) {
+            Ok(idx) => idx,
+            Err(idx) => idx - 1,
+        }
+    }
+}
+
+/// Automaton representation for matching UTF-8 `&[u8]`.
+pub struct ByteRunAutomaton(RunAutomaton);
+
+impl ByteRunAutomaton {
+    /// Converts incoming automaton to byte-based (UTF32ToUTF8) first, unless
+    /// `is_binary` is true.
+    pub fn new(a: &Automaton, is_binary: bool, max_determinized_states: usize) -> Result<Self> {
+        let run_automaton = if is_binary {
+            RunAutomaton::new(a, MAX_BYTE_LABEL, max_determinized_states)?
+        } else {
+            RunAutomaton::new(
+                &UTF32ToUTF8::convert(a),
+                MAX_BYTE_LABEL,
+                max_determinized_states,
+            )?
+        };
+        Ok(ByteRunAutomaton(run_automaton))
+    }
+
+    /// Returns true if the given byte array is accepted by this automaton.
+    pub fn run(&self, bytes: &[u8]) -> bool {
+        let mut state = 0usize;
+        for b in bytes {
+            match self.0.step(state, u32::from(*b)) {
+                Some(s) => state = s,
+                None => return false,
+            }
+        }
+        self.0.is_accept(state)
+    }
+}
+
+impl Deref for ByteRunAutomaton {
+    type Target = RunAutomaton;
+
+    fn deref(&self) -> &RunAutomaton {
+        &self.0
+    }
+}
+
+/// Automaton representation for matching `char` sequences.
+pub struct CharacterRunAutomaton(RunAutomaton);
+
+impl CharacterRunAutomaton {
+    pub fn new(a: &Automaton, max_determinized_states: usize) -> Result<Self> {
+        Ok(CharacterRunAutomaton(RunAutomaton::new(
+            a,
+            MAX_CODE_POINT,
+            max_determinized_states,
+        )?))
+    }
+
+    /// Returns true if the given string is accepted by this automaton.
+    pub fn run(&self, s: &str) -> bool {
+        let mut state = 0usize;
+        for c in s.chars() {
+            match self.0.step(state, c as u32) {
+                Some(s) => state = s,
+                None => return false,
+            }
+        }
+        self.0.is_accept(state)
+    }
+}
+
+impl Deref for CharacterRunAutomaton {
+    type Target = RunAutomaton;
+
+    fn deref(&self) -> &RunAutomaton {
+        &self.0
+    }
+}
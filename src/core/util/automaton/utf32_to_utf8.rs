@@ -0,0 +1,196 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::char;
+
+use core::util::automaton::Automaton;
+
+// the largest code point encoded with 1, 2 and 3 bytes
+const UTF8_LENGTH_BOUNDS: [u32; 3] = [0x7f, 0x7ff, 0xffff];
+
+const MIN_SURROGATE: u32 = 0xd800;
+const MAX_SURROGATE: u32 = 0xdfff;
+
+/// Converts UTF-32 automata to the equivalent UTF-8 representation.
+///
+/// Every code point transition is split into a set of byte range sequences
+/// (following the encoding rules of UTF-8) so that the converted automaton
+/// can run directly against the bytes of the index terms. Surrogate code
+/// points are never matched since they can not be encoded.
+pub struct UTF32ToUTF8;
+
+impl UTF32ToUTF8 {
+    /// Converts an incoming utf32 automaton to an equivalent utf8 one. The
+    /// incoming automaton need not be deterministic, the returned automaton
+    /// usually is not, call `Operations::determinize` on it before use.
+    pub fn convert(utf32: &Automaton) -> Automaton {
+        let mut result = Automaton::new();
+        for s in 0..utf32.num_states() {
+            let state = result.create_state();
+            result.set_accept(state, utf32.is_accept(s));
+        }
+
+        let mut sequences: Vec<Vec<(u8, u8)>> = Vec::new();
+        for s in 0..utf32.num_states() {
+            for e in utf32.epsilons(s) {
+                result.add_epsilon(s, *e);
+            }
+            for t in utf32.transitions(s) {
+                sequences.clear();
+                utf8_sequences(t.min, t.max, &mut sequences);
+                for sequence in &sequences {
+                    let mut source = s;
+                    for (i, &(min, max)) in sequence.iter().enumerate() {
+                        let dest = if i + 1 == sequence.len() {
+                            t.dest
+                        } else {
+                            result.create_state()
+                        };
+                        result.add_transition(source, dest, u32::from(min), u32::from(max));
+                        source = dest;
+                    }
+                }
+            }
+        }
+        result.finish();
+        result
+    }
+}
+
+/// Splits the code point range `[min, max]` into sequences of byte ranges,
+/// every code point in the range is encoded by exactly one sequence and
+/// every byte sequence matched by a sequence decodes to a code point in
+/// the range.
+fn utf8_sequences(min: u32, max: u32, out: &mut Vec<Vec<(u8, u8)>>) {
+    let mut stack = vec![(min, max)];
+    'ranges: while let Some((start, end)) = stack.pop() {
+        if start > end {
+            continue;
+        }
+
+        // surrogates can't be encoded, drop them
+        if start <= MAX_SURROGATE && end >= MIN_SURROGATE {
+            if end > MAX_SURROGATE {
+                stack.push((MAX_SURROGATE + 1, end));
+            }
+            if start < MIN_SURROGATE {
+                stack.push((start, MIN_SURROGATE - 1));
+            }
+            continue;
+        }
+
+        // split into ranges with the same encoded length
+        for bound in &UTF8_LENGTH_BOUNDS {
+            if start <= *bound && *bound < end {
+                stack.push((*bound + 1, end));
+                stack.push((start, *bound));
+                continue 'ranges;
+            }
+        }
+
+        if end <= 0x7f {
+            out.push(vec![(start as u8, end as u8)]);
+            continue;
+        }
+
+        // split until every continuation byte covers either a full or a
+        // single value prefix
+        for i in 1..4 {
+            let mask = (1u32 << (6 * i)) - 1;
+            if start & !mask != end & !mask {
+                if start & mask != 0 {
+                    stack.push(((start | mask) + 1, end));
+                    stack.push((start, start | mask));
+                    continue 'ranges;
+                }
+                if end & mask != mask {
+                    stack.push((end & !mask, end));
+                    stack.push((start, (end & !mask) - 1));
+                    continue 'ranges;
+                }
+            }
+        }
+
+        let mut start_bytes = [0u8; 4];
+        let mut end_bytes = [0u8; 4];
+        let start_bytes = char::from_u32(start)
+            .unwrap()
+            .encode_utf8(&mut start_bytes)
+            .as_bytes();
+        let end_bytes = char::from_u32(end)
+            .unwrap()
+            .encode_utf8(&mut end_bytes)
+            .as_bytes();
+        debug_assert_eq!(start_bytes.len(), end_bytes.len());
+        out.push(
+            start_bytes
+                .iter()
+                .zip(end_bytes.iter())
+                .map(|(s, e)| (*s, *e))
+                .collect(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::util::automaton::{Automata, Operations, DEFAULT_MAX_DETERMINIZED_STATES};
+
+    fn run_bytes(a: &Automaton, bytes: &[u8]) -> bool {
+        let labels: Vec<u32> = bytes.iter().map(|b| u32::from(*b)).collect();
+        Operations::run(a, &labels)
+    }
+
+    #[test]
+    fn test_sequences_cover_range() {
+        let mut sequences = Vec::new();
+        utf8_sequences(0, 0x10_ffff, &mut sequences);
+        for c in (0u32..0x11_0000).step_by(97) {
+            if let Some(ch) = char::from_u32(c) {
+                let mut buf = [0u8; 4];
+                let bytes = ch.encode_utf8(&mut buf).as_bytes();
+                let matched = sequences
+                    .iter()
+                    .filter(|seq| {
+                        seq.len() == bytes.len()
+                            && seq
+                                .iter()
+                                .zip(bytes.iter())
+                                .all(|(r, b)| r.0 <= *b && *b <= r.1)
+                    })
+                    .count();
+                assert_eq!(matched, 1, "code point {:x}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let a = Operations::concatenate(&[
+            &Automata::make_string("ab"),
+            &Automata::make_char_range(0x80, 0x1_0000),
+        ]);
+        let utf8 =
+            Operations::determinize(&UTF32ToUTF8::convert(&a), DEFAULT_MAX_DETERMINIZED_STATES)
+                .unwrap();
+        assert!(utf8.is_deterministic());
+        assert!(run_bytes(&utf8, "ab\u{80}".as_bytes()));
+        assert!(run_bytes(&utf8, "ab\u{4e2d}".as_bytes()));
+        assert!(run_bytes(&utf8, "ab\u{10000}".as_bytes()));
+        assert!(!run_bytes(&utf8, "ab\u{10001}".as_bytes()));
+        assert!(!run_bytes(&utf8, b"abc"));
+        assert!(!run_bytes(&utf8, b"ab"));
+    }
+}